@@ -0,0 +1,158 @@
+//! Decoder throughput on representative message mixes
+//!
+//! Run with `cargo bench --bench decoder`.  Every benchmark decodes a fixed batch of frames, as
+//! the reader hands them to the decoder, and drains the decoded events, so the numbers cover
+//! field parsing, message construction and queueing.  Throughput is reported in frames.
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use ibtwsapi::core::common::ConnStatus;
+use ibtwsapi::core::decoder::Decoder;
+use ibtwsapi::core::event_queue::{event_queue, EventQueueConfig, EventReceiver};
+
+const SERVER_VERSION: i32 = 151;
+
+//==================================================================================================
+/// A frame payload written with `|` in place of the NUL field terminators
+fn frame(fields: &str) -> String {
+    fields.replace('|', "\0")
+}
+
+//----------------------------------------------------------------------------------------------
+/// Quotes and trades of a few symbols, as a busy market data subscription sends them
+fn tick_storm() -> Vec<String> {
+    let mut frames = Vec::new();
+    for i in 0..1000 {
+        let req_id = i % 8 + 1;
+        let price = 150.0 + (i % 50) as f64 / 100.0;
+        frames.push(match i % 4 {
+            0 => frame(&format!("1|6|{}|1|{:.2}|300|1|", req_id, price)),
+            1 => frame(&format!("1|6|{}|2|{:.2}|200|0|", req_id, price + 0.01)),
+            2 => frame(&format!("2|6|{}|5|{}|", req_id, 100 + i % 7)),
+            _ => frame(&format!(
+                "46|6|{}|48|{:.2};100;1704465000123;{};150.1342;true|",
+                req_id,
+                price,
+                48211 + i
+            )),
+        });
+    }
+    frames
+}
+
+//----------------------------------------------------------------------------------------------
+/// Level 1 and level 2 book updates
+fn depth_updates() -> Vec<String> {
+    (0..1000)
+        .map(|i| {
+            let position = i % 10;
+            let operation = if i % 10 == 9 { 2 } else { 1 };
+            let price = 150.0 + position as f64 / 100.0;
+            if i % 2 == 0 {
+                frame(&format!(
+                    "12|1|5|{}|{}|{}|{:.2}|{}|",
+                    position,
+                    operation,
+                    i % 2,
+                    price,
+                    100 * (position + 1)
+                ))
+            } else {
+                frame(&format!(
+                    "13|1|6|{}|ARCA|{}|1|{:.2}|{}|1|",
+                    position,
+                    operation,
+                    price,
+                    100 * (position + 1)
+                ))
+            }
+        })
+        .collect()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Replies to historical data requests of 500 one minute bars each
+fn historical_bars() -> Vec<String> {
+    (0..10)
+        .map(|req_id| {
+            let mut fields = format!("17|{}|20240105  09:30:00|20240105  17:50:00|500|", req_id);
+            for bar in 0..500 {
+                let open = 150.0 + (bar % 20) as f64 / 10.0;
+                fields.push_str(&format!(
+                    "{}|{:.2}|{:.2}|{:.2}|{:.2}|{}|{:.4}|{}|",
+                    1704465000 + 60 * bar,
+                    open,
+                    open + 0.3,
+                    open - 0.2,
+                    open + 0.1,
+                    1000 + bar,
+                    open + 0.05,
+                    12 + bar % 9
+                ));
+            }
+            frame(&fields)
+        })
+        .collect()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Open orders as sent after reqAllOpenOrders.  Fields after the order type are left empty,
+/// which the decoder reads as defaults, as TWS sends them for a plain limit order.
+fn open_orders() -> Vec<String> {
+    (0..200)
+        .map(|order_id| {
+            let mut fields = format!(
+                "5|{}|265598|AAPL|STK||0||100|SMART|USD|AAPL|NMS|BUY|{}|LMT|{:.2}||DAY||DU111|O|0||0|{}|",
+                order_id,
+                100 + order_id,
+                150.0 + order_id as f64 / 100.0,
+                1_000_000 + order_id
+            );
+            fields.push_str(&"|".repeat(250));
+            frame(&fields)
+        })
+        .collect()
+}
+
+//==================================================================================================
+fn decoder() -> (Decoder, EventReceiver) {
+    let (_, msg_queue) = channel::<String>();
+    let (sender, receiver) = event_queue(EventQueueConfig::default());
+    let decoder = Decoder::new(
+        msg_queue,
+        sender,
+        SERVER_VERSION,
+        Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+    );
+    (decoder, receiver)
+}
+
+//----------------------------------------------------------------------------------------------
+fn bench_decoder(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, frames) in [
+        ("tick_storm", tick_storm()),
+        ("depth_updates", depth_updates()),
+        ("historical_bars", historical_bars()),
+        ("open_orders", open_orders()),
+    ] {
+        let (mut decoder, events) = decoder();
+        group.throughput(Throughput::Elements(frames.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for frame in &frames {
+                    decoder.interpret(black_box(frame)).unwrap();
+                }
+                while let Ok(event) = events.try_recv() {
+                    black_box(event);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decoder);
+criterion_main!(benches);
@@ -0,0 +1,121 @@
+//! Records a live session with TWS or IB Gateway as a decoder test capture
+//!
+//! Connects, requests the current time, positions and streaming market data for the given
+//! stock symbols, and writes every frame TWS sends in the capture format of test_vectors to
+//! DIR/captures/NAME.txt.  Run ibtws_vectors afterwards to write the snapshot.
+//!
+//! Usage: `ibtws_capture [--host HOST] [--port PORT] [--client-id ID] [--seconds SECONDS]
+//! [--dir DIR] NAME [SYMBOL...]`.  The defaults are 127.0.0.1, 4002, 0, 10 and testdata.
+//!
+//! Captures hold account ids, positions and whatever else the session received.  Review and
+//! scrub them before committing.
+
+use ibtwsapi::core::client::{EClient, EClientConfig};
+use ibtwsapi::core::contract::Contract;
+use ibtwsapi::core::errors::IBKRApiLibError;
+use ibtwsapi::core::req_ids::RequestKind;
+use ibtwsapi::core::test_vectors::CaptureRecorder;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+//==================================================================================================
+struct Options {
+    host: String,
+    port: u32,
+    client_id: i32,
+    seconds: u64,
+    dir: PathBuf,
+    name: String,
+    symbols: Vec<String>,
+}
+
+//==================================================================================================
+fn parse_args(args: &[String]) -> Result<Options, IBKRApiLibError> {
+    fn value<T: FromStr>(flag: &str, value: Option<&String>) -> Result<T, IBKRApiLibError> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| IBKRApiLibError::Config(format!("{} needs a valid value", flag)))
+    }
+
+    let mut options = Options {
+        host: "127.0.0.1".to_string(),
+        port: 4002,
+        client_id: 0,
+        seconds: 10,
+        dir: PathBuf::from("testdata"),
+        name: String::new(),
+        symbols: Vec::new(),
+    };
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => options.host = value(arg, args.next())?,
+            "--port" => options.port = value(arg, args.next())?,
+            "--client-id" => options.client_id = value(arg, args.next())?,
+            "--seconds" => options.seconds = value(arg, args.next())?,
+            "--dir" => options.dir = value(arg, args.next())?,
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let mut positional = positional.into_iter();
+    options.name = positional
+        .next()
+        .ok_or_else(|| IBKRApiLibError::Config("the capture needs a name".to_string()))?;
+    options.symbols = positional.collect();
+    Ok(options)
+}
+
+//==================================================================================================
+pub fn main() -> Result<(), IBKRApiLibError> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = parse_args(&args)?;
+
+    let recorder = CaptureRecorder::new(&options.name);
+    let mut client =
+        EClient::with_config(EClientConfig::default().record_capture(recorder.clone()));
+    client.connect(&options.host, options.port, options.client_id)?;
+
+    client.req_current_time()?;
+    client.req_positions()?;
+    for symbol in &options.symbols {
+        let contract = Contract {
+            symbol: symbol.clone(),
+            sec_type: "STK".to_string(),
+            exchange: "SMART".to_string(),
+            currency: "USD".to_string(),
+            ..Default::default()
+        };
+        let req_id = client.next_req_id(RequestKind::MarketData, symbol);
+        client.req_mkt_data(req_id, &contract, "", false, false, vec![])?;
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(options.seconds);
+    while Instant::now() < deadline {
+        if client.get_event()?.is_none() {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    client.disconnect()?;
+
+    let target = options
+        .dir
+        .join("captures")
+        .join(format!("{}.txt", options.name));
+    fs::create_dir_all(options.dir.join("captures"))?;
+    fs::write(
+        &target,
+        format!(
+            "# Recorded by ibtws_capture from {}:{}\n{}",
+            options.host,
+            options.port,
+            recorder.capture()
+        ),
+    )?;
+    println!("{}: {} frames written", target.display(), recorder.len());
+    Ok(())
+}
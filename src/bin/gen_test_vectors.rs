@@ -0,0 +1,75 @@
+//! Generates the decoder test vectors
+//!
+//! Replays every capture in testdata/captures through the decoder and writes the snapshots to
+//! testdata/vectors.  Run it after adding a capture or after a deliberate change to decoding,
+//! and review the diff of the vectors before committing them.
+//!
+//! Usage: `ibtws_vectors [--check] [DIR]`.  DIR defaults to testdata.  With `--check` nothing is
+//! written, and the exit status is 1 if any vector is missing or stale.
+
+use ibtwsapi::core::errors::IBKRApiLibError;
+use ibtwsapi::core::test_vectors::{Capture, VERSION_MATRIX};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+//==================================================================================================
+pub fn main() -> Result<(), IBKRApiLibError> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let check = args.iter().any(|arg| arg == "--check");
+    let dir = args
+        .iter()
+        .find(|arg| *arg != "--check")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("testdata"));
+
+    let stale = generate(&dir, check)?;
+    if check && stale > 0 {
+        eprintln!(
+            "{} vectors are stale, run ibtws_vectors to regenerate them",
+            stale
+        );
+        process::exit(1);
+    }
+    Ok(())
+}
+
+//==================================================================================================
+/// Returns the number of vectors that differed from the snapshots
+fn generate(dir: &Path, check: bool) -> Result<usize, IBKRApiLibError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir.join("captures"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    let mut stale = 0;
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let capture = Capture::parse(&name, &fs::read_to_string(&path)?)?;
+        for version in VERSION_MATRIX.iter() {
+            if !capture.server_versions().contains(version) {
+                println!("{}: no section for server version {}", name, version);
+            }
+        }
+
+        let snapshot = capture.snapshot()?;
+        let target = dir.join("vectors").join(format!("{}.snap", name));
+        if fs::read_to_string(&target).ok().as_deref() == Some(snapshot.as_str()) {
+            continue;
+        }
+        stale += 1;
+        if check {
+            println!("{}: stale", target.display());
+        } else {
+            fs::create_dir_all(dir.join("vectors"))?;
+            fs::write(&target, snapshot)?;
+            println!("{}: written", target.display());
+        }
+    }
+    Ok(stale)
+}
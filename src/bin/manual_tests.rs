@@ -1,18 +1,44 @@
 //! Binary for manually testing crate
 
+use ibtwsapi::core::client::{EClient, EClientConfig};
+use ibtwsapi::core::crypto::{crypto_contract, CryptoOrder};
 use ibtwsapi::core::errors::*;
+use ibtwsapi::core::frame_dump::{decode_dump, DumpEncoding, FrameDirection};
+use ibtwsapi::core::messages::ServerRspMsg;
+use ibtwsapi::core::order::OrderStatusKind;
 use ibtwsapi::examples::example_wrapper::ExampleWrapper;
 use log::*;
+use rust_decimal::Decimal;
+use std::env;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::mpsc::RecvTimeoutError;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Example of using client and wrapper.
 /// Requires a running instance of TWS or IB Gateway connected to the port in main.
 /// Upon connecting, TWS will send the next valid order ID which will cause the wrapper callback method
 /// next_valid_id to be called, which will start sending tests requests to TWS (see the
 /// start_requests function in ExampleWrapper which is called by next_valid_id
+///
+/// Run with `decode [--outgoing] [--hex|--base64] [DUMP]` to print the messages in a hex or
+/// base64 dump of TWS frames instead.  The dump is read from stdin if it is not given, and its
+/// encoding is guessed unless `--hex` or `--base64` is given.
+///
+/// Run with `crypto [SYMBOL] [LIMIT]` to check crypto orders against a paper account: it looks up
+/// the PAXOS contract, places a GTC buy limit of 0.001 coins at LIMIT, 1000 by default, which
+/// should be far below the market, and cancels it.
 //==================================================================================================
 pub fn main() -> Result<(), IBKRApiLibError> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("decode") {
+        return decode(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("crypto") {
+        return crypto(&args[1..]);
+    }
+
     match log4rs::init_file("./log_config.yml", Default::default()) {
         Ok(_) => (),
         Err(e) => {
@@ -44,3 +70,95 @@ pub fn main() -> Result<(), IBKRApiLibError> {
 
     Ok(())
 }
+
+//==================================================================================================
+fn decode(args: &[String]) -> Result<(), IBKRApiLibError> {
+    let mut direction = FrameDirection::Incoming;
+    let mut encoding = None;
+    let mut words = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--outgoing" => direction = FrameDirection::Outgoing,
+            "--hex" => encoding = Some(DumpEncoding::Hex),
+            "--base64" => encoding = Some(DumpEncoding::Base64),
+            _ => words.push(arg.as_str()),
+        }
+    }
+    let mut dump = words.join(" ");
+    if dump.is_empty() {
+        std::io::stdin().read_to_string(&mut dump)?;
+    }
+
+    for frame in decode_dump(&dump, encoding, direction)? {
+        print!("{}", frame);
+    }
+    Ok(())
+}
+
+//==================================================================================================
+/// Waits up to 10 seconds for an event `found` picks, printing every event
+fn wait_for<T>(
+    client: &EClient,
+    mut found: impl FnMut(&ServerRspMsg) -> Option<T>,
+) -> Result<T, IBKRApiLibError> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        if let Some(msg) = client.wait_event(left)? {
+            println!("{:?}", msg);
+            if let Some(value) = found(&msg) {
+                return Ok(value);
+            }
+        }
+    }
+    Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout))
+}
+
+//==================================================================================================
+fn crypto(args: &[String]) -> Result<(), IBKRApiLibError> {
+    let symbol = args.first().map(String::as_str).unwrap_or("BTC");
+    let limit = match args.get(1) {
+        Some(limit) => Decimal::from_str(limit)
+            .map_err(|_| IBKRApiLibError::Config(format!("invalid limit {}", limit)))?,
+        None => Decimal::from(1000),
+    };
+    let contract = crypto_contract(symbol, "USD");
+
+    let mut client = EClient::with_config(EClientConfig::default().require_paper());
+    client.connect("127.0.0.1", 4002, 0)?;
+    let order_id = wait_for(&client, |msg| match msg {
+        ServerRspMsg::NextValidId { order_id } => Some(*order_id),
+        _ => None,
+    })?;
+
+    client.req_contract_details(1, &contract)?;
+    let min_tick = wait_for(&client, |msg| match msg {
+        ServerRspMsg::ContractData {
+            contract_details, ..
+        } => Some(contract_details.min_tick),
+        _ => None,
+    })?;
+    let min_tick = Decimal::from_str(&min_tick.to_string())
+        .map_err(|_| IBKRApiLibError::Config(format!("invalid min tick {}", min_tick)))?;
+
+    let order = CryptoOrder::buy(Decimal::new(1, 3))
+        .limit(limit)
+        .min_tick(min_tick)
+        .build()?;
+    client.place_order(order_id, &contract, &order)?;
+    wait_for(&client, |msg| match msg {
+        ServerRspMsg::OrderStatus {
+            status: OrderStatusKind::PreSubmitted | OrderStatusKind::Submitted,
+            ..
+        } => Some(()),
+        _ => None,
+    })?;
+    client.cancel_order(order_id)?;
+    wait_for(&client, |msg| match msg {
+        ServerRspMsg::OrderStatus {
+            status: OrderStatusKind::Cancelled,
+            ..
+        } => Some(()),
+        _ => None,
+    })?;
+    client.disconnect()
+}
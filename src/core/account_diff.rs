@@ -0,0 +1,166 @@
+//! Turns repeated account value updates into change-only events
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// Which subscription an account value came from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccountValueSource {
+    /// AccountSummary, from req_account_summary()
+    Summary,
+    /// AcctValue, from req_account_updates()
+    Updates,
+    /// AccountUpdateMulti, from req_account_updates_multi()
+    UpdatesMulti,
+}
+
+//==================================================================================================
+/// A value that changed since it was last seen
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountValueChange {
+    pub source: AccountValueSource,
+    pub account: String,
+    pub model_code: String,
+    pub tag: String,
+    pub currency: String,
+    /// None the first time the value is seen
+    pub previous: Option<String>,
+    pub value: String,
+    /// New minus previous, when both are numeric
+    pub delta: Option<f64>,
+}
+
+impl fmt::Display for AccountValueChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "account: {}, tag: {}, currency: {}, previous: {:?}, value: {}, delta: {:?}",
+            self.account, self.tag, self.currency, self.previous, self.value, self.delta
+        )
+    }
+}
+
+//==================================================================================================
+type ValueKey = (AccountValueSource, String, String, String, String);
+
+/// Remembers the last value of every account tag and reports only the ones that change.
+///
+/// Feed it AccountSummary, AcctValue and AccountUpdateMulti events; everything else is ignored.
+#[derive(Clone, Debug, Default)]
+pub struct AccountDiffer {
+    values: HashMap<ValueKey, String>,
+}
+
+impl AccountDiffer {
+    pub fn new() -> Self {
+        AccountDiffer::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the change carried by an event, or None if the event is not an account value or
+    /// repeats the last value
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> Option<AccountValueChange> {
+        let (source, account, model_code, tag, value, currency) = match msg {
+            ServerRspMsg::AccountSummary {
+                account,
+                tag,
+                value,
+                currency,
+                ..
+            } => (
+                AccountValueSource::Summary,
+                account,
+                "",
+                tag.clone(),
+                value.clone(),
+                currency.as_str(),
+            ),
+            ServerRspMsg::AcctValue {
+                key,
+                value,
+                account_name,
+            } => (
+                AccountValueSource::Updates,
+                account_name,
+                "",
+                key.to_string(),
+                value.to_string(),
+                value.currency(),
+            ),
+            ServerRspMsg::AccountUpdateMulti {
+                account,
+                model_code,
+                key,
+                value,
+                ..
+            } => (
+                AccountValueSource::UpdatesMulti,
+                account,
+                model_code.as_str(),
+                key.to_string(),
+                value.to_string(),
+                value.currency(),
+            ),
+            _ => return None,
+        };
+
+        let key = (
+            source,
+            account.clone(),
+            model_code.to_string(),
+            tag.clone(),
+            currency.to_string(),
+        );
+        let previous = self.values.insert(key, value.clone());
+        if previous.as_ref() == Some(&value) {
+            return None;
+        }
+
+        let delta = match (
+            previous.as_ref().map(|p| p.parse::<f64>()),
+            value.parse::<f64>(),
+        ) {
+            (Some(Ok(previous)), Ok(value)) => Some(value - previous),
+            _ => None,
+        };
+        Some(AccountValueChange {
+            source,
+            account: account.clone(),
+            model_code: model_code.to_string(),
+            tag,
+            currency: currency.to_string(),
+            previous,
+            value,
+            delta,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Last value seen for a tag
+    pub fn value(
+        &self,
+        source: AccountValueSource,
+        account: &str,
+        model_code: &str,
+        tag: &str,
+        currency: &str,
+    ) -> Option<&String> {
+        self.values.get(&(
+            source,
+            account.to_string(),
+            model_code.to_string(),
+            tag.to_string(),
+            currency.to_string(),
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Forgets all values, so the next update of every tag is reported again
+    pub fn reset(&mut self) {
+        self.values.clear();
+    }
+}
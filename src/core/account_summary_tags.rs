@@ -1,7 +1,25 @@
-//! Account summary tags
+//! Account summary tags, and the report collected from an account summary request
+use std::collections::BTreeMap;
 use std::fmt::{Display, Error, Formatter};
+#[cfg(feature = "client-sync")]
+use std::future::Future;
+#[cfg(feature = "client-sync")]
+use std::pin::Pin;
+#[cfg(feature = "client-sync")]
+use std::sync::mpsc::RecvTimeoutError;
+#[cfg(feature = "client-sync")]
+use std::task::{Context, Poll};
+#[cfg(feature = "client-sync")]
+use std::time::Duration;
 
-use crate::core::account_summary_tags::AccountSummaryTags::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::account_summary_tags::AccountSummaryTag::*;
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+#[cfg(feature = "client-sync")]
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
 
 //==================================================================================================
 /// AccountType — Identifies the IB account structure
@@ -35,9 +53,12 @@ use crate::core::account_summary_tags::AccountSummaryTags::*;
 /// Leverage — GrossPositionValue / NetLiquidation
 /// $LEDGER — Single flag to relay all cash balance tags*, only in base currency.
 /// $LEDGER:CURRENCY — Single flag to relay all cash balance tags*, only in the specified currency.
+///                    LedgerCurrency holds the currency.
 /// $LEDGER:ALL — Single flag to relay all cash balance tags* in all currencies.
+/// AllTags — Every tag above except the $LEDGER ones
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 
-pub enum AccountSummaryTags {
+pub enum AccountSummaryTag {
     AccountType,
     NetLiquidation,
     TotalCashValue,
@@ -47,8 +68,8 @@ pub enum AccountSummaryTags {
     EquityWithLoanValue,
     PreviousEquityWithLoanValue,
     GrossPositionValue,
-    ReqTEquity,
-    ReqTMargin,
+    RegTEquity,
+    RegTMargin,
     SMA,
     InitMarginReq,
     MaintMarginReq,
@@ -68,86 +89,255 @@ pub enum AccountSummaryTags {
     DayTradesRemaining,
     Leverage,
     Ledger,
-    LedgerCurrency,
+    LedgerCurrency(String),
     LedgerAll,
     AllTags,
 }
 
-impl AccountSummaryTags {
-    fn display(&self) -> &str {
+/// The former name of AccountSummaryTag
+pub type AccountSummaryTags = AccountSummaryTag;
+
+/// The tags AllTags stands for
+const ALL_TAGS: [AccountSummaryTag; 29] = [
+    AccountType,
+    NetLiquidation,
+    TotalCashValue,
+    SettledCash,
+    AccruedCash,
+    BuyingPower,
+    EquityWithLoanValue,
+    PreviousEquityWithLoanValue,
+    GrossPositionValue,
+    RegTEquity,
+    RegTMargin,
+    SMA,
+    InitMarginReq,
+    MaintMarginReq,
+    AvailableFunds,
+    ExcessLiquidity,
+    Cushion,
+    FullInitMarginReq,
+    FullMaintMarginReq,
+    FullAvailableFunds,
+    FullExcessLiquidity,
+    LookAheadNextChange,
+    LookAheadInitMarginReq,
+    LookAheadMaintMarginReq,
+    LookAheadAvailableFunds,
+    LookAheadExcessLiquidity,
+    HighestSeverity,
+    DayTradesRemaining,
+    Leverage,
+];
+
+impl AccountSummaryTag {
+    /// Builds the comma separated list expected by req_account_summary()
+    pub fn to_list(tags: &[AccountSummaryTag]) -> String {
+        tags.iter()
+            .map(|tag| tag.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl Display for AccountSummaryTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
-            AccountType => "AccountType",
-            NetLiquidation => "NetLiquidation",
-            TotalCashValue => "TotalCashValue",
-            SettledCash => "SettledCash",
-            AccruedCash => "AccruedCash",
-            BuyingPower => "BuyingPower",
-            EquityWithLoanValue => "EquityWithLoanValue",
-            PreviousEquityWithLoanValue => "PreviousEquityWithLoanValue",
-            GrossPositionValue => "GrossPositionValue",
-            ReqTEquity => "ReqTEquity",
-            ReqTMargin => "ReqTMargin",
-            SMA => "SMA",
-            InitMarginReq => "InitMarginReq",
-            MaintMarginReq => "MaintMarginReq",
-            AvailableFunds => "AvailableFunds",
-            ExcessLiquidity => "ExcessLiquidity",
-            Cushion => "Cushion",
-            FullInitMarginReq => "FullInitMarginReq",
-            FullMaintMarginReq => "FullMaintMarginReq",
-            FullAvailableFunds => "FullAvailableFunds",
-            FullExcessLiquidity => "FullExcessLiquidity",
-            LookAheadNextChange => "LookAheadNextChange",
-            LookAheadInitMarginReq => "LookAheadInitMarginReq",
-            LookAheadMaintMarginReq => "LookAheadMaintMarginReq",
-            LookAheadAvailableFunds => "LookAheadAvailableFunds",
-            LookAheadExcessLiquidity => "LookAheadExcessLiquidity",
-            HighestSeverity => "HighestSeverity",
-            DayTradesRemaining => "DayTradesRemaining",
-            Leverage => "Leverage",
-            Ledger => "$LEDGER",
-            LedgerCurrency => "$LEDGER:CURRENCY",
-            LedgerAll => "$LEDGER:ALL",
-            AllTags => {
-                "AccountType,
-            NetLiquidation,
-            TotalCashValue,
-            SettledCash,
-            AccruedCash,
-            BuyingPower,
-            EquityWithLoanValue,
-            PreviousEquityWithLoanValue,
-            GrossPositionValue,
-            ReqTEquity,
-            ReqTMargin,
-            SMA,
-            InitMarginReq,
-            MaintMarginReq,
-            AvailableFunds,
-            ExcessLiquidity,
-            Cushion,Cushion,
-            FullInitMarginReq,
-            FullMaintMarginReq,
-            FullAvailableFunds,
-            FullExcessLiquidity,
-            LookAheadNextChange,
-            LookAheadInitMarginReq,
-            LookAheadMaintMarginReq,
-            LookAheadAvailableFunds,
-            LookAheadExcessLiquidity,
-            HighestSeverity,
-            DayTradesRemaining,
-            Leverage,
-            $LEDGER,
-            $LEDGER:CURRENCY,
-            $LEDGER:ALL"
+            Ledger => write!(f, "$LEDGER"),
+            LedgerCurrency(currency) => write!(f, "$LEDGER:{}", currency),
+            LedgerAll => write!(f, "$LEDGER:ALL"),
+            AllTags => write!(f, "{}", AccountSummaryTag::to_list(&ALL_TAGS)),
+            // the other tags are sent as their variant name
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+//==================================================================================================
+/// One value of an account summary.  The $LEDGER tags report a value per currency.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AccountSummaryValue {
+    pub tag: String,
+    pub value: String,
+    pub currency: String,
+}
+
+impl AccountSummaryValue {
+    /// The value as a number, None for text values such as AccountType
+    pub fn amount(&self) -> Option<f64> {
+        self.value.parse().ok()
+    }
+}
+
+//==================================================================================================
+/// The values of an account summary request, per account
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountSummaryReport {
+    pub accounts: BTreeMap<String, Vec<AccountSummaryValue>>,
+}
+
+impl AccountSummaryReport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an AccountSummary event and returns whether it was one.  A value replaces the one
+    /// held for the same account, tag and currency.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::AccountSummary {
+                account,
+                tag,
+                value,
+                currency,
+                ..
+            } => {
+                self.insert(
+                    account,
+                    AccountSummaryValue {
+                        tag: tag.clone(),
+                        value: value.clone(),
+                        currency: currency.clone(),
+                    },
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a value of an account, replacing the one held for the same tag and currency
+    pub fn insert(&mut self, account: &str, value: AccountSummaryValue) {
+        let values = self.accounts.entry(account.to_string()).or_default();
+        match values
+            .iter_mut()
+            .find(|held| held.tag == value.tag && held.currency == value.currency)
+        {
+            Some(held) => held.value = value.value,
+            None => values.push(value),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds the values of another report, such as the report of another account
+    pub fn merge(&mut self, other: AccountSummaryReport) {
+        for (account, values) in other.accounts {
+            for value in values {
+                self.insert(&account, value);
             }
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// The value of a tag for an account, in the first currency reported for it
+    pub fn value(&self, account: &str, tag: &AccountSummaryTag) -> Option<&AccountSummaryValue> {
+        let tag = tag.to_string();
+        self.accounts
+            .get(account)?
+            .iter()
+            .find(|held| held.tag == tag)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The value of a tag for an account as a number
+    pub fn amount(&self, account: &str, tag: &AccountSummaryTag) -> Option<f64> {
+        self.value(account, tag)
+            .and_then(AccountSummaryValue::amount)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A value in a given currency, such as the CashBalance in EUR reported for $LEDGER:ALL
+    pub fn value_in(
+        &self,
+        account: &str,
+        tag: &str,
+        currency: &str,
+    ) -> Option<&AccountSummaryValue> {
+        self.accounts
+            .get(account)?
+            .iter()
+            .find(|held| held.tag == tag && held.currency == currency)
+    }
+}
+
+//==================================================================================================
+/// Future returned by EClient::account_summary().  It resolves to the report once
+/// AccountSummaryEnd arrives, or to the error TWS reported, and cancels the subscription once it
+/// is done or dropped.  Events of other requests stay available to get_event().
+#[cfg(feature = "client-sync")]
+pub struct AccountSummaryFuture<'a> {
+    client: &'a mut EClient,
+    req_id: i32,
+    report: AccountSummaryReport,
+    done: bool,
+}
+
+#[cfg(feature = "client-sync")]
+impl<'a> AccountSummaryFuture<'a> {
+    pub(crate) fn new(client: &'a mut EClient, req_id: i32) -> Self {
+        AccountSummaryFuture {
+            client,
+            req_id,
+            report: AccountSummaryReport::new(),
+            done: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
 }
 
-impl Display for AccountSummaryTags {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}", self.display())
+#[cfg(feature = "client-sync")]
+impl Future for AccountSummaryFuture<'_> {
+    type Output = Result<AccountSummaryReport, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let req_id = self.req_id;
+        let mut report = std::mem::take(&mut self.report);
+        let result = self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::AccountSummary { req_id: id, .. } if *id == req_id => {
+                report.update(msg);
+                Ok(Collected::Consumed)
+            }
+            ServerRspMsg::AccountSummaryEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        });
+        let output = match result {
+            // the subscription keeps sending updates until cancelled
+            Ok(()) => self.client.cancel_account_summary(req_id).map(|_| report),
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.report = report;
+                self.client.wake_on_event(cx.waker());
+                return Poll::Pending;
+            }
+            Err(err) => Err(err),
+        };
+        self.done = true;
+        self.client.correlations().complete(req_id);
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(feature = "client-sync")]
+impl Drop for AccountSummaryFuture<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            // nothing to cancel once the connection is gone
+            let _ = self.client.cancel_account_summary(self.req_id);
+        }
     }
 }
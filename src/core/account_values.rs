@@ -0,0 +1,272 @@
+//! Typed keys and values of the account values sent by req_account_updates() and
+//! req_account_updates_multi()
+//!
+//! TWS sends every account value as text.  AccountKey names the documented keys, and
+//! AccountValue holds the value as a Decimal amount in its currency when it is a number.  Keys
+//! that are not documented, such as the per segment keys ending in -C or -S, are kept as
+//! AccountKey::Other.
+use std::convert::Infallible;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+//==================================================================================================
+/// The documented keys of account values
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AccountKey {
+    AccountCode,
+    AccountOrGroup,
+    AccountReady,
+    AccountType,
+    AccruedCash,
+    AccruedDividend,
+    AvailableFunds,
+    Billable,
+    BuyingPower,
+    CashBalance,
+    CorporateBondValue,
+    Currency,
+    Cushion,
+    DayTradesRemaining,
+    DayTradesRemainingT1,
+    DayTradesRemainingT2,
+    DayTradesRemainingT3,
+    DayTradesRemainingT4,
+    EquityWithLoanValue,
+    ExcessLiquidity,
+    ExchangeRate,
+    FullAvailableFunds,
+    FullExcessLiquidity,
+    FullInitMarginReq,
+    FullMaintMarginReq,
+    FundValue,
+    FutureOptionValue,
+    FuturesPnl,
+    FxCashBalance,
+    GrossPositionValue,
+    IndianStockHaircut,
+    InitMarginReq,
+    IssuerOptionValue,
+    Leverage,
+    LookAheadAvailableFunds,
+    LookAheadExcessLiquidity,
+    LookAheadInitMarginReq,
+    LookAheadMaintMarginReq,
+    LookAheadNextChange,
+    MaintMarginReq,
+    MoneyMarketFundValue,
+    MutualFundValue,
+    NetDividend,
+    NetLiquidation,
+    NetLiquidationByCurrency,
+    OptionMarketValue,
+    PaSharesValue,
+    Pnl,
+    PreviousDayEquityWithLoanValue,
+    RealCurrency,
+    RealizedPnl,
+    RegTEquity,
+    RegTMargin,
+    Sma,
+    StockMarketValue,
+    TBillValue,
+    TBondValue,
+    TotalCashBalance,
+    TotalCashValue,
+    TradingType,
+    UnrealizedPnl,
+    WarrantValue,
+    WhatIfPmEnabled,
+    /// A key that is not documented, as TWS sent it
+    Other(String),
+}
+
+/// The documented keys and the names TWS sends them as
+const KEY_NAMES: [(AccountKey, &str); 63] = [
+    (AccountKey::AccountCode, "AccountCode"),
+    (AccountKey::AccountOrGroup, "AccountOrGroup"),
+    (AccountKey::AccountReady, "AccountReady"),
+    (AccountKey::AccountType, "AccountType"),
+    (AccountKey::AccruedCash, "AccruedCash"),
+    (AccountKey::AccruedDividend, "AccruedDividend"),
+    (AccountKey::AvailableFunds, "AvailableFunds"),
+    (AccountKey::Billable, "Billable"),
+    (AccountKey::BuyingPower, "BuyingPower"),
+    (AccountKey::CashBalance, "CashBalance"),
+    (AccountKey::CorporateBondValue, "CorporateBondValue"),
+    (AccountKey::Currency, "Currency"),
+    (AccountKey::Cushion, "Cushion"),
+    (AccountKey::DayTradesRemaining, "DayTradesRemaining"),
+    (AccountKey::DayTradesRemainingT1, "DayTradesRemainingT+1"),
+    (AccountKey::DayTradesRemainingT2, "DayTradesRemainingT+2"),
+    (AccountKey::DayTradesRemainingT3, "DayTradesRemainingT+3"),
+    (AccountKey::DayTradesRemainingT4, "DayTradesRemainingT+4"),
+    (AccountKey::EquityWithLoanValue, "EquityWithLoanValue"),
+    (AccountKey::ExcessLiquidity, "ExcessLiquidity"),
+    (AccountKey::ExchangeRate, "ExchangeRate"),
+    (AccountKey::FullAvailableFunds, "FullAvailableFunds"),
+    (AccountKey::FullExcessLiquidity, "FullExcessLiquidity"),
+    (AccountKey::FullInitMarginReq, "FullInitMarginReq"),
+    (AccountKey::FullMaintMarginReq, "FullMaintMarginReq"),
+    (AccountKey::FundValue, "FundValue"),
+    (AccountKey::FutureOptionValue, "FutureOptionValue"),
+    (AccountKey::FuturesPnl, "FuturesPNL"),
+    (AccountKey::FxCashBalance, "FxCashBalance"),
+    (AccountKey::GrossPositionValue, "GrossPositionValue"),
+    (AccountKey::IndianStockHaircut, "IndianStockHaircut"),
+    (AccountKey::InitMarginReq, "InitMarginReq"),
+    (AccountKey::IssuerOptionValue, "IssuerOptionValue"),
+    (AccountKey::Leverage, "Leverage-S"),
+    (
+        AccountKey::LookAheadAvailableFunds,
+        "LookAheadAvailableFunds",
+    ),
+    (
+        AccountKey::LookAheadExcessLiquidity,
+        "LookAheadExcessLiquidity",
+    ),
+    (AccountKey::LookAheadInitMarginReq, "LookAheadInitMarginReq"),
+    (
+        AccountKey::LookAheadMaintMarginReq,
+        "LookAheadMaintMarginReq",
+    ),
+    (AccountKey::LookAheadNextChange, "LookAheadNextChange"),
+    (AccountKey::MaintMarginReq, "MaintMarginReq"),
+    (AccountKey::MoneyMarketFundValue, "MoneyMarketFundValue"),
+    (AccountKey::MutualFundValue, "MutualFundValue"),
+    (AccountKey::NetDividend, "NetDividend"),
+    (AccountKey::NetLiquidation, "NetLiquidation"),
+    (
+        AccountKey::NetLiquidationByCurrency,
+        "NetLiquidationByCurrency",
+    ),
+    (AccountKey::OptionMarketValue, "OptionMarketValue"),
+    (AccountKey::PaSharesValue, "PASharesValue"),
+    (AccountKey::Pnl, "PNL"),
+    (
+        AccountKey::PreviousDayEquityWithLoanValue,
+        "PreviousDayEquityWithLoanValue",
+    ),
+    (AccountKey::RealCurrency, "RealCurrency"),
+    (AccountKey::RealizedPnl, "RealizedPnL"),
+    (AccountKey::RegTEquity, "RegTEquity"),
+    (AccountKey::RegTMargin, "RegTMargin"),
+    (AccountKey::Sma, "SMA"),
+    (AccountKey::StockMarketValue, "StockMarketValue"),
+    (AccountKey::TBillValue, "TBillValue"),
+    (AccountKey::TBondValue, "TBondValue"),
+    (AccountKey::TotalCashBalance, "TotalCashBalance"),
+    (AccountKey::TotalCashValue, "TotalCashValue"),
+    (AccountKey::TradingType, "TradingType-S"),
+    (AccountKey::UnrealizedPnl, "UnrealizedPnL"),
+    (AccountKey::WarrantValue, "WarrantValue"),
+    (AccountKey::WhatIfPmEnabled, "WhatIfPMEnabled"),
+];
+
+impl AccountKey {
+    /// The key as TWS sends it
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccountKey::Other(key) => key,
+            key => KEY_NAMES
+                .iter()
+                .find(|(known, _)| known == key)
+                .map(|(_, name)| *name)
+                .unwrap_or_default(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_other(&self) -> bool {
+        matches!(self, AccountKey::Other(_))
+    }
+}
+
+impl From<&str> for AccountKey {
+    fn from(key: &str) -> Self {
+        KEY_NAMES
+            .iter()
+            .find(|(_, name)| *name == key)
+            .map(|(known, _)| known.clone())
+            .unwrap_or_else(|| AccountKey::Other(key.to_string()))
+    }
+}
+
+impl FromStr for AccountKey {
+    type Err = Infallible;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        Ok(AccountKey::from(key))
+    }
+}
+
+impl Display for AccountKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+//==================================================================================================
+/// The value of an account key.  Numbers are parsed into a Decimal that keeps the digits TWS
+/// sent; the currency is empty for values that are not amounts, such as counts and ratios.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum AccountValue {
+    Amount {
+        amount: Decimal,
+        currency: String,
+    },
+    /// A value that is not a number, such as the account type or a flag
+    Text {
+        text: String,
+        currency: String,
+    },
+}
+
+impl AccountValue {
+    /// Parses a value as TWS sends it
+    ///
+    /// # Arguments
+    /// * value - The value text
+    /// * currency - The currency field that came with it
+    pub fn parse(value: &str, currency: &str) -> Self {
+        let value = value.trim();
+        match Decimal::from_str(value).or_else(|_| Decimal::from_scientific(value)) {
+            Ok(amount) => AccountValue::Amount {
+                amount,
+                currency: currency.to_string(),
+            },
+            Err(_) => AccountValue::Text {
+                text: value.to_string(),
+                currency: currency.to_string(),
+            },
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The amount, None for a value that is not a number
+    pub fn amount(&self) -> Option<Decimal> {
+        match self {
+            AccountValue::Amount { amount, .. } => Some(*amount),
+            AccountValue::Text { .. } => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn currency(&self) -> &str {
+        match self {
+            AccountValue::Amount { currency, .. } | AccountValue::Text { currency, .. } => currency,
+        }
+    }
+}
+
+impl Display for AccountValue {
+    /// The value as TWS sent it, without the currency
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            AccountValue::Amount { amount, .. } => write!(f, "{}", amount),
+            AccountValue::Text { text, .. } => write!(f, "{}", text),
+        }
+    }
+}
@@ -1,4 +1,5 @@
-//! Utility functions that illustrate setting fields related to algo parameters
+//! Utility functions that illustrate setting fields related to algo parameters.  order::algo has
+//! typed builders for the common IB algos.
 use crate::core::common::TagValue;
 use crate::core::order::Order;
 
@@ -0,0 +1,1023 @@
+//! Runs strategies against recorded or historical market data instead of TWS
+//!
+//! A BacktestClient is an EClient connected to a simulated TWS, so a strategy written against
+//! EClient runs unchanged: it makes requests through the usual methods and reads the answers with
+//! get_event().  Requests are answered as soon as they are sent.  step() advances a simulated
+//! clock to the next tick of the loaded data, streams it to the subscriptions and fills the
+//! pending orders that it reaches.
+//!
+//! Data is loaded per contract from bars, historical trade and quote ticks, and journals, which
+//! are captures recorded with a CaptureRecorder, such as by the ibtws_capture binary.  A bar is
+//! played at its date as four trades: open, low, high and close, or open, high, low and close
+//! for a falling bar.  Bar dates in exchange time are read as UTC.
+//!
+//! Simulated requests:
+//! - req_mkt_data() and cancel_mkt_data(): Last, Bid and Ask prices and sizes and the volume,
+//!   and snapshots
+//! - req_tick_by_tick_data() and cancel_tick_by_tick_data()
+//! - req_historical_data(): the loaded bars up to the simulated time, whatever the duration, bar
+//!   size and end date
+//! - place_order() and cancel_order(), matched by a sim::MatchingEngine with the slippage,
+//!   latency and commission models of the BacktestConfig
+//! - req_ids(), req_current_time(), req_positions() and cancel_positions()
+//!
+//! Other requests are answered with an Unsupported error.
+pub mod sim;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use num_traits::FromPrimitive;
+use tracing::debug;
+
+use crate::core::client::{EClient, EClientConfig, POISONED_MUTEX};
+use crate::core::common::{
+    AccountId, BarData, ConnStatus, Environment, HistoricalTickBidAsk, HistoricalTickLast,
+    TickAttrib, TickAttribBidAsk, TickAttribLast, TickByTickData, TickByTickType, TickType,
+    NO_VALID_ID, UNSET_DOUBLE,
+};
+use crate::core::connection::ConnectionEvent;
+use crate::core::contract::Contract;
+use crate::core::errors::{IBKRApiLibError, TwsError};
+use crate::core::event_queue::EventSender;
+use crate::core::frame_reader::FrameReader;
+use crate::core::messages::{read_fields, ServerReqMsgDiscriminants, ServerRspMsg};
+use crate::core::order::Order;
+use crate::core::server_versions::MAX_CLIENT_VER;
+use crate::core::streamer::Streamer;
+use crate::core::test_vectors::{replay_timed, Capture};
+
+use self::sim::{CommissionModel, LatencyModel, MatchingEngine, SlippageModel, Tick};
+
+/// Account of the simulated connection.  It starts with DU, so it counts as a paper account.
+pub const BACKTEST_ACCOUNT: &str = "DUBACKTEST";
+/// TWS error for a request about a contract without loaded data
+const NO_SECURITY_DEFINITION: i32 = 200;
+/// Format of the end time of historical data
+const HISTORICAL_TIME: &str = "%Y%m%d  %H:%M:%S";
+
+//==================================================================================================
+/// Settings of a BacktestClient
+#[derive(Clone, Debug)]
+pub struct BacktestConfig {
+    /// Settings of the EClient that strategies use
+    pub client: EClientConfig,
+    pub account: String,
+    pub commission: CommissionModel,
+    pub slippage: SlippageModel,
+    pub latency: LatencyModel,
+    /// Ticks before this time update the last prices but are not streamed and fill no orders
+    pub start: Option<DateTime<Utc>>,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig {
+            client: EClientConfig::default(),
+            account: BACKTEST_ACCOUNT.to_string(),
+            commission: CommissionModel::None,
+            slippage: SlippageModel::None,
+            latency: LatencyModel::None,
+            start: None,
+        }
+    }
+}
+
+impl BacktestConfig {
+    pub fn client(mut self, client: EClientConfig) -> Self {
+        self.client = client;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn account(mut self, account: &str) -> Self {
+        self.account = account.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn commission(mut self, commission: CommissionModel) -> Self {
+        self.commission = commission;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn slippage(mut self, slippage: SlippageModel) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn latency(mut self, latency: LatencyModel) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts streaming and filling at `start`.  Earlier data only sets the prices orders are
+    /// first checked against, and can be requested as historical bars.
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct TapeTick {
+    time: DateTime<Utc>,
+    instrument: usize,
+    tick: Tick,
+}
+
+//==================================================================================================
+/// Loaded data and market state of one contract
+#[derive(Clone, Debug, Default)]
+struct Instrument {
+    contract: Contract,
+    bars: Vec<(DateTime<Utc>, BarData)>,
+    last: Option<(f64, i32)>,
+    bid: Option<(f64, i32)>,
+    ask: Option<(f64, i32)>,
+    volume: i32,
+}
+
+//----------------------------------------------------------------------------------------------
+/// Whether a request for `requested` is about the loaded contract
+fn same_instrument(requested: &Contract, loaded: &Contract) -> bool {
+    if requested.con_id > 0 && loaded.con_id > 0 {
+        return requested.con_id == loaded.con_id;
+    }
+    let matches = |a: &str, b: &str| a.is_empty() || b.is_empty() || a.eq_ignore_ascii_case(b);
+    requested.symbol.eq_ignore_ascii_case(&loaded.symbol)
+        && matches(&requested.sec_type, &loaded.sec_type)
+        && matches(&requested.currency, &loaded.currency)
+}
+
+//==================================================================================================
+/// The simulated TWS.  Shared by the BacktestClient and the streamer of its EClient.
+struct Simulator {
+    events: EventSender,
+    config: BacktestConfig,
+    engine: MatchingEngine,
+    clock: DateTime<Utc>,
+    requests: FrameReader,
+    instruments: Vec<Instrument>,
+    tape: Vec<TapeTick>,
+    /// Index of the next tick to play
+    next_tick: usize,
+    /// Set when ticks were added since the tape was last sorted
+    unsorted: bool,
+    market_data: HashMap<i32, usize>,
+    tick_by_tick: HashMap<i32, (usize, TickByTickType)>,
+    next_order_id: i32,
+}
+
+impl Simulator {
+    fn emit(&self, msg: ServerRspMsg) {
+        // the receiver lives in the EClient that owns this simulator
+        let _ = self.events.send(msg);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn error(&self, req_id: i32, error_code: i32, error_str: String) {
+        self.emit(ServerRspMsg::ErrMsg {
+            req_id,
+            error_code,
+            error_str,
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn instrument(&mut self, contract: &Contract) -> usize {
+        match self.find(contract) {
+            Some(index) => index,
+            None => {
+                self.instruments.push(Instrument {
+                    contract: contract.clone(),
+                    ..Default::default()
+                });
+                self.instruments.len() - 1
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn find(&self, contract: &Contract) -> Option<usize> {
+        self.instruments
+            .iter()
+            .position(|instrument| same_instrument(contract, &instrument.contract))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The instrument a request is about, or None after answering it with an error
+    fn find_requested(&self, req_id: i32, contract: &Contract) -> Option<usize> {
+        let found = self.find(contract);
+        if found.is_none() {
+            self.error(
+                req_id,
+                NO_SECURITY_DEFINITION,
+                format!(
+                    "No security definition has been found for the request: no data is loaded \
+                     for {}",
+                    contract.symbol
+                ),
+            );
+        }
+        found
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn push(&mut self, time: DateTime<Utc>, instrument: usize, tick: Tick) {
+        self.tape.push(TapeTick {
+            time,
+            instrument,
+            tick,
+        });
+        self.unsorted = true;
+    }
+
+    //==============================================================================================
+    /// Handles the bytes the EClient wrote
+    fn receive(&mut self, bytes: &[u8]) {
+        self.requests.extend(bytes);
+        loop {
+            let fields = match self.requests.next_frame() {
+                Ok(Some(frame)) => read_fields(&String::from_utf8_lossy(frame)),
+                Ok(None) => return,
+                Err(err) => {
+                    // the client only writes valid frames
+                    debug!("unreadable request: {}", err);
+                    return;
+                }
+            };
+            self.handle(&fields);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn handle(&mut self, fields: &[String]) {
+        let field = |index: usize| fields.get(index).map(String::as_str).unwrap_or_default();
+        let int = |index: usize| field(index).parse::<i32>().unwrap_or_default();
+        let msg_id = match fields.first() {
+            Some(msg_id) => msg_id.parse::<i32>().unwrap_or_default(),
+            // the API version prefix of the handshake is not a frame
+            None => return,
+        };
+        use ServerReqMsgDiscriminants as Req;
+        match Req::from_i32(msg_id) {
+            Some(Req::ReqMktData) => {
+                let contract = contract_at(fields, 3);
+                // combo legs and the delta neutral contract come before the generic ticks
+                let mut index = 15;
+                if contract.sec_type == "BAG" {
+                    index += 1 + 4 * int(index).max(0) as usize;
+                }
+                index += if field(index) == "1" { 4 } else { 1 };
+                let snapshot = field(index + 1) == "1" || field(index + 2) == "1";
+                self.req_mkt_data(int(2), &contract, snapshot)
+            }
+            Some(Req::CancelMktData) => {
+                self.market_data.remove(&int(2));
+            }
+            Some(Req::ReqTickByTickData) => {
+                let tick_type = match field(14) {
+                    "Last" => TickByTickType::Last,
+                    "AllLast" => TickByTickType::AllLast,
+                    "BidAsk" => TickByTickType::BidAsk,
+                    _ => TickByTickType::MidPoint,
+                };
+                self.req_tick_by_tick(int(1), &contract_at(fields, 2), tick_type)
+            }
+            Some(Req::CancelTickByTickData) => {
+                self.tick_by_tick.remove(&int(1));
+            }
+            Some(Req::ReqHistoricalData) => {
+                self.req_historical_data(int(1), &contract_at(fields, 2))
+            }
+            Some(Req::CancelHistoricalData) => (),
+            Some(Req::PlaceOrder) => {
+                let (contract, order) = order_at(fields);
+                self.place_order(int(1), &contract, &order)
+            }
+            Some(Req::CancelOrder) => self.engine.cancel(int(2), self.clock),
+            Some(Req::ReqIds) => self.emit(ServerRspMsg::NextValidId {
+                order_id: self.next_order_id,
+            }),
+            Some(Req::ReqCurrentTime) => self.emit(ServerRspMsg::CurrentTime {
+                time: self.clock.timestamp(),
+            }),
+            Some(Req::ReqPositions) => self.engine.req_positions(),
+            Some(Req::CancelPositions) => self.engine.cancel_positions(),
+            other => {
+                let name = match other {
+                    Some(name) => format!("{:?}", name),
+                    None => format!("Request {}", msg_id),
+                };
+                self.error(
+                    NO_VALID_ID,
+                    TwsError::Unsupported.code(),
+                    format!("{} is not simulated by the backtest", name),
+                );
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn req_mkt_data(&mut self, req_id: i32, contract: &Contract, snapshot: bool) {
+        let index = match self.find_requested(req_id, contract) {
+            Some(index) => index,
+            None => return,
+        };
+        // the current prices, as TWS sends them when a subscription starts
+        let instrument = &self.instruments[index];
+        let current = [
+            instrument
+                .last
+                .map(|(price, size)| Tick::Trade { price, size }),
+            instrument
+                .bid
+                .map(|(price, size)| Tick::Bid { price, size }),
+            instrument
+                .ask
+                .map(|(price, size)| Tick::Ask { price, size }),
+        ];
+        for tick in current.iter().flatten() {
+            self.stream_mkt_data(req_id, index, tick);
+        }
+        if snapshot {
+            self.emit(ServerRspMsg::TickSnapshotEnd { req_id });
+        } else {
+            self.market_data.insert(req_id, index);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn req_tick_by_tick(&mut self, req_id: i32, contract: &Contract, tick_type: TickByTickType) {
+        if let Some(index) = self.find_requested(req_id, contract) {
+            self.tick_by_tick.insert(req_id, (index, tick_type));
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn req_historical_data(&mut self, req_id: i32, contract: &Contract) {
+        let index = match self.find_requested(req_id, contract) {
+            Some(index) => index,
+            None => return,
+        };
+        let bars: Vec<&(DateTime<Utc>, BarData)> = self.instruments[index]
+            .bars
+            .iter()
+            .filter(|(time, _)| *time <= self.clock)
+            .collect();
+        let start = bars
+            .first()
+            .map(|(time, _)| time.format(HISTORICAL_TIME).to_string())
+            .unwrap_or_default();
+        let msgs: Vec<ServerRspMsg> = bars
+            .into_iter()
+            .map(|(_, bar)| ServerRspMsg::HistoricalData {
+                req_id,
+                bar: bar.clone(),
+            })
+            .collect();
+        for msg in msgs {
+            self.emit(msg);
+        }
+        self.emit(ServerRspMsg::HistoricalDataEnd {
+            req_id,
+            start,
+            end: self.clock.format(HISTORICAL_TIME).to_string(),
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn place_order(&mut self, order_id: i32, contract: &Contract, order: &Order) {
+        if let Some(index) = self.find_requested(order_id, contract) {
+            self.next_order_id = self.next_order_id.max(order_id + 1);
+            self.engine
+                .place(order_id, index, contract, order, self.clock);
+        }
+    }
+
+    //==============================================================================================
+    fn sort_tape(&mut self) {
+        if self.unsorted {
+            // stable, so ticks of the same time keep the order they were loaded in
+            self.tape[self.next_tick..].sort_by_key(|tick| tick.time);
+            self.unsorted = false;
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn start(&self) -> DateTime<Utc> {
+        self.config.start.unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Plays the ticks of the next time on the tape.  Returns false when the tape is played.
+    fn step(&mut self) -> bool {
+        self.sort_tape();
+        let time = match self.tape.get(self.next_tick) {
+            Some(tick) => tick.time,
+            None => return false,
+        };
+        self.clock = self.clock.max(time);
+        let live = time >= self.start();
+        while let Some(tick) = self.tape.get(self.next_tick) {
+            if tick.time != time {
+                break;
+            }
+            let (instrument, tick) = (tick.instrument, tick.tick);
+            self.next_tick += 1;
+            self.play(instrument, tick, live);
+        }
+        true
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn play(&mut self, index: usize, tick: Tick, live: bool) {
+        let instrument = &mut self.instruments[index];
+        match tick {
+            Tick::Trade { price, size } => {
+                instrument.last = Some((price, size));
+                instrument.volume = instrument.volume.saturating_add(size);
+            }
+            Tick::Bid { price, size } => instrument.bid = Some((price, size)),
+            Tick::Ask { price, size } => instrument.ask = Some((price, size)),
+        }
+        if !live {
+            self.engine.update(index, &tick);
+            return;
+        }
+
+        let subscriptions: Vec<i32> = self
+            .market_data
+            .iter()
+            .filter(|(_, subscribed)| **subscribed == index)
+            .map(|(req_id, _)| *req_id)
+            .collect();
+        for req_id in subscriptions {
+            self.stream_mkt_data(req_id, index, &tick);
+        }
+        let subscriptions: Vec<(i32, TickByTickType)> = self
+            .tick_by_tick
+            .iter()
+            .filter(|(_, (subscribed, _))| *subscribed == index)
+            .map(|(req_id, (_, tick_type))| (*req_id, tick_type.clone()))
+            .collect();
+        for (req_id, tick_type) in subscriptions {
+            self.stream_tick_by_tick(req_id, index, &tick, tick_type);
+        }
+
+        self.engine.on_tick(index, &tick, self.clock);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn stream_mkt_data(&self, req_id: i32, index: usize, tick: &Tick) {
+        let (price_type, size_type, price, size) = match *tick {
+            Tick::Trade { price, size } => (TickType::Last, TickType::LastSize, price, size),
+            Tick::Bid { price, size } => (TickType::Bid, TickType::BidSize, price, size),
+            Tick::Ask { price, size } => (TickType::Ask, TickType::AskSize, price, size),
+        };
+        self.emit(ServerRspMsg::TickPrice {
+            req_id,
+            tick_type: price_type,
+            price,
+            tick_attr: TickAttrib::default(),
+        });
+        self.emit(ServerRspMsg::TickSize {
+            req_id,
+            tick_type: size_type,
+            size,
+        });
+        if let Tick::Trade { .. } = tick {
+            self.emit(ServerRspMsg::TickSize {
+                req_id,
+                tick_type: TickType::Volume,
+                size: self.instruments[index].volume,
+            });
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn stream_tick_by_tick(
+        &self,
+        req_id: i32,
+        index: usize,
+        tick: &Tick,
+        tick_type: TickByTickType,
+    ) {
+        let instrument = &self.instruments[index];
+        let data = match (&tick_type, *tick) {
+            (TickByTickType::Last | TickByTickType::AllLast, Tick::Trade { price, size }) => {
+                TickByTickData::Last {
+                    all_last: tick_type == TickByTickType::AllLast,
+                    price,
+                    size,
+                    tick_attrib_last: TickAttribLast::default(),
+                    exchange: instrument.contract.exchange.clone(),
+                    special_conditions: String::new(),
+                }
+            }
+            (TickByTickType::BidAsk, Tick::Bid { .. } | Tick::Ask { .. }) => {
+                let (bid_price, bid_size) = instrument.bid.unwrap_or_default();
+                let (ask_price, ask_size) = instrument.ask.unwrap_or_default();
+                TickByTickData::BidAsk {
+                    bid_price,
+                    ask_price,
+                    bid_size,
+                    ask_size,
+                    tick_attrib_bid_ask: TickAttribBidAsk::default(),
+                }
+            }
+            (TickByTickType::MidPoint, Tick::Bid { .. } | Tick::Ask { .. }) => {
+                match (instrument.bid, instrument.ask) {
+                    (Some((bid, _)), Some((ask, _))) => TickByTickData::MidPoint {
+                        mid_point: (bid + ask) / 2.0,
+                    },
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        self.emit(ServerRspMsg::TickByTick {
+            req_id,
+            time: self.clock.timestamp(),
+            tick: data,
+        });
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Reads the contract fields that requests send from `start` on: con_id, symbol, sec_type,
+/// last_trade_date_or_contract_month, strike, right, multiplier, exchange, primary_exchange,
+/// currency, local_symbol and trading_class
+fn contract_at(fields: &[String], start: usize) -> Contract {
+    let field = |offset: usize| fields.get(start + offset).cloned().unwrap_or_default();
+    Contract {
+        con_id: field(0).parse().unwrap_or_default(),
+        symbol: field(1),
+        sec_type: field(2),
+        last_trade_date_or_contract_month: field(3),
+        strike: field(4).parse().unwrap_or_default(),
+        right: field(5),
+        multiplier: field(6),
+        exchange: field(7),
+        primary_exchange: field(8),
+        currency: field(9),
+        local_symbol: field(10),
+        trading_class: field(11),
+        ..Default::default()
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Reads the contract and the order fields the matching engine uses from a PlaceOrder request,
+/// as EClient::place_order() writes it at MAX_CLIENT_VER.  lmt_price_offset comes after the
+/// order conditions and is not read.
+fn order_at(fields: &[String]) -> (Contract, Order) {
+    let field = |index: usize| fields.get(index).map(String::as_str).unwrap_or_default();
+    let price = |index: usize| field(index).parse::<f64>().unwrap_or(UNSET_DOUBLE);
+    let count = |index: usize| field(index).parse::<usize>().unwrap_or_default();
+    let contract = contract_at(fields, 2);
+    let mut order = Order {
+        action: field(16).to_string(),
+        total_quantity: field(17).parse().unwrap_or_default(),
+        order_type: field(18).to_string(),
+        lmt_price: price(19),
+        aux_price: price(20),
+        ..Default::default()
+    };
+
+    // the trailing stop fields come after combo legs and delta neutral fields of varying length
+    let mut index = 35;
+    if contract.sec_type == "BAG" {
+        // combo legs, combo leg prices and smart combo routing params
+        index += 1 + 8 * count(index);
+        index += 1 + count(index);
+        index += 1 + 2 * count(index);
+    }
+    // shares allocation up to the delta neutral order type
+    index += 30;
+    index += if field(index).is_empty() { 2 } else { 10 };
+    // continuous update and reference price type
+    index += 2;
+    order.trail_stop_price = price(index);
+    order.trailing_percent = price(index + 1);
+    (contract, order)
+}
+
+//==================================================================================================
+/// The socket of a BacktestClient's EClient.  Writes go to the simulator, reads find nothing.
+struct SimStreamer {
+    simulator: Arc<Mutex<Simulator>>,
+}
+
+impl Streamer for SimStreamer {
+    fn shutdown(&mut self, _how: Shutdown) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn connect(&mut self, _addr: &SocketAddr) {}
+
+    fn try_clone(&self) -> io::Result<Box<dyn Streamer>> {
+        Ok(Box::new(SimStreamer {
+            simulator: self.simulator.clone(),
+        }))
+    }
+}
+
+impl Read for SimStreamer {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for SimStreamer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.simulator.lock().expect(POISONED_MUTEX).receive(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//==================================================================================================
+/// An EClient that trades against loaded data on a simulated clock.  It derefs to the EClient,
+/// so strategies make requests and read events the way they do when connected to TWS.
+pub struct BacktestClient {
+    client: EClient,
+    simulator: Arc<Mutex<Simulator>>,
+}
+
+impl BacktestClient {
+    pub fn new() -> Self {
+        BacktestClient::with_config(BacktestConfig::default())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_config(config: BacktestConfig) -> Self {
+        let mut client = EClient::with_config(config.client.clone());
+        let accounts = AccountId::parse_list(&config.account);
+        *client.managed_accounts.lock().expect(POISONED_MUTEX) = accounts.clone();
+        let events = client.evt_chan.1.sender();
+        let engine = MatchingEngine::new(events.clone())
+            .with_account(&config.account)
+            .with_commission(config.commission)
+            .with_slippage(config.slippage)
+            .with_latency(config.latency);
+        let simulator = Arc::new(Mutex::new(Simulator {
+            events,
+            engine,
+            clock: config.start.unwrap_or(DateTime::<Utc>::UNIX_EPOCH),
+            config,
+            requests: FrameReader::new(),
+            instruments: Vec::new(),
+            tape: Vec::new(),
+            next_tick: 0,
+            unsorted: false,
+            market_data: HashMap::new(),
+            tick_by_tick: HashMap::new(),
+            next_order_id: 1,
+        }));
+
+        client.set_streamer(Some(Box::new(SimStreamer {
+            simulator: simulator.clone(),
+        }) as Box<dyn Streamer>));
+        client.server_version = MAX_CLIENT_VER;
+        *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
+        *client.environment.lock().expect(POISONED_MUTEX) = Environment::Paper;
+        for event in [
+            ConnectionEvent::Connect,
+            ConnectionEvent::HandshakeStarted,
+            ConnectionEvent::ApiStarted,
+        ] {
+            client.connection.apply(event);
+        }
+
+        let backtest = BacktestClient { client, simulator };
+        {
+            let simulator = backtest.simulator();
+            simulator.emit(ServerRspMsg::ManagedAccts { accounts });
+            simulator.emit(ServerRspMsg::NextValidId { order_id: 1 });
+        }
+        backtest
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn simulator(&self) -> MutexGuard<'_, Simulator> {
+        self.simulator.lock().expect(POISONED_MUTEX)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The simulated time
+    pub fn now(&self) -> DateTime<Utc> {
+        self.simulator().clock
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Ticks loaded but not played yet
+    pub fn remaining(&self) -> usize {
+        let simulator = self.simulator();
+        simulator.tape.len() - simulator.next_tick
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The time of the next tick step() plays, None once all ticks are played
+    pub fn next_time(&self) -> Option<DateTime<Utc>> {
+        let mut simulator = self.simulator();
+        simulator.sort_tape();
+        simulator
+            .tape
+            .get(simulator.next_tick)
+            .map(|tick| tick.time)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Advances the clock to the time of the next loaded tick and plays every tick of that time.
+    /// Returns false, without changing anything, once all ticks are played.
+    pub fn step(&mut self) -> bool {
+        self.simulator().step()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Plays every tick up to and including `time`, then sets the clock to it
+    pub fn run_until(&mut self, time: DateTime<Utc>) {
+        let mut simulator = self.simulator();
+        loop {
+            simulator.sort_tape();
+            match simulator.tape.get(simulator.next_tick) {
+                Some(tick) if tick.time <= time => {
+                    simulator.step();
+                }
+                _ => break,
+            }
+        }
+        simulator.clock = simulator.clock.max(time);
+        simulator.engine.advance(time);
+    }
+
+    //==============================================================================================
+    /// Loads bars, which are played as trades and returned by req_historical_data()
+    ///
+    /// # Arguments
+    /// * contract - The contract requests will be made for
+    /// * bars - Bars, each played at its date.  Bars without a date are skipped.
+    pub fn add_bars(
+        &mut self,
+        contract: &Contract,
+        bars: &[BarData],
+    ) -> Result<(), IBKRApiLibError> {
+        let mut simulator = self.simulator();
+        let index = simulator.instrument(contract);
+        for bar in bars {
+            let time = match bar.date {
+                Some(time) => time,
+                None => continue,
+            };
+            let size = (bar.volume / 4).clamp(0, i32::MAX as i64) as i32;
+            let path = if bar.close >= bar.open {
+                [bar.open, bar.low, bar.high, bar.close]
+            } else {
+                [bar.open, bar.high, bar.low, bar.close]
+            };
+            for price in path.iter() {
+                simulator.push(
+                    time,
+                    index,
+                    Tick::Trade {
+                        price: *price,
+                        size,
+                    },
+                );
+            }
+            simulator.instruments[index].bars.push((time, bar.clone()));
+        }
+        simulator.instruments[index]
+            .bars
+            .sort_by_key(|(time, _)| *time);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads historical trades, as from req_historical_ticks() with TRADES
+    pub fn add_trades(&mut self, contract: &Contract, ticks: &[HistoricalTickLast]) {
+        let mut simulator = self.simulator();
+        let index = simulator.instrument(contract);
+        for tick in ticks {
+            simulator.push(
+                epoch_seconds(tick.time as i64),
+                index,
+                Tick::Trade {
+                    price: tick.price,
+                    size: tick.size,
+                },
+            );
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads historical quotes, as from req_historical_ticks() with BID_ASK
+    pub fn add_quotes(&mut self, contract: &Contract, ticks: &[HistoricalTickBidAsk]) {
+        let mut simulator = self.simulator();
+        let index = simulator.instrument(contract);
+        for tick in ticks {
+            let time = epoch_seconds(tick.time as i64);
+            simulator.push(
+                time,
+                index,
+                Tick::Bid {
+                    price: tick.price_bid,
+                    size: tick.size_bid,
+                },
+            );
+            simulator.push(
+                time,
+                index,
+                Tick::Ask {
+                    price: tick.price_ask,
+                    size: tick.size_ask,
+                },
+            );
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads the market data of one subscription from a recorded capture.  Last, Bid and Ask
+    /// prices and sizes and tick by tick trades and quotes are played at the times they were
+    /// received.  Returns the number of ticks loaded.
+    ///
+    /// # Arguments
+    /// * contract - The contract requests will be made for
+    /// * capture - A capture with receive times, as recorded by a CaptureRecorder
+    /// * req_id - The id the data was requested with in the recorded session
+    pub fn add_journal(
+        &mut self,
+        contract: &Contract,
+        capture: &Capture,
+        req_id: i32,
+    ) -> Result<usize, IBKRApiLibError> {
+        let mut ticks: Vec<(DateTime<Utc>, Tick)> = Vec::new();
+        for section in &capture.sections {
+            for (time, msg) in replay_timed(section)? {
+                let time = time.ok_or_else(|| {
+                    IBKRApiLibError::Parse(format!(
+                        "{} has frames without a receive time",
+                        capture.name
+                    ))
+                })?;
+                journal_ticks(&msg, req_id, time, &mut ticks);
+            }
+        }
+
+        let mut simulator = self.simulator();
+        let index = simulator.instrument(contract);
+        for (time, tick) in &ticks {
+            simulator.push(*time, index, *tick);
+        }
+        Ok(ticks.len())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads a journal from a capture file.  See add_journal().
+    pub fn add_journal_file(
+        &mut self,
+        contract: &Contract,
+        path: &Path,
+        req_id: i32,
+    ) -> Result<usize, IBKRApiLibError> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let capture = Capture::parse(&name, &fs::read_to_string(path)?)?;
+        self.add_journal(contract, &capture, req_id)
+    }
+}
+
+impl Default for BacktestClient {
+    fn default() -> Self {
+        BacktestClient::new()
+    }
+}
+
+impl Deref for BacktestClient {
+    type Target = EClient;
+
+    fn deref(&self) -> &EClient {
+        &self.client
+    }
+}
+
+impl DerefMut for BacktestClient {
+    fn deref_mut(&mut self) -> &mut EClient {
+        &mut self.client
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn epoch_seconds(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Appends the ticks a recorded message of subscription `req_id` stands for.  TWS sends the size
+/// of a price in a TickSize right after it, which sets the size of the price tick.
+fn journal_ticks(
+    msg: &ServerRspMsg,
+    req_id: i32,
+    time: DateTime<Utc>,
+    ticks: &mut Vec<(DateTime<Utc>, Tick)>,
+) {
+    match msg {
+        ServerRspMsg::TickPrice {
+            req_id: id,
+            tick_type,
+            price,
+            ..
+        } if *id == req_id => {
+            let (price, size) = (*price, 0);
+            let tick = match tick_type {
+                TickType::Last | TickType::DelayedLast => Tick::Trade { price, size },
+                TickType::Bid | TickType::DelayedBid => Tick::Bid { price, size },
+                TickType::Ask | TickType::DelayedAsk => Tick::Ask { price, size },
+                _ => return,
+            };
+            ticks.push((time, tick));
+        }
+        ServerRspMsg::TickSize {
+            req_id: id,
+            tick_type,
+            size,
+        } if *id == req_id => {
+            let last = match ticks.last_mut() {
+                Some((last_time, tick)) if *last_time == time => tick,
+                _ => return,
+            };
+            match (tick_type, last) {
+                (
+                    TickType::LastSize | TickType::DelayedLastSize,
+                    Tick::Trade {
+                        size: last_size, ..
+                    },
+                )
+                | (
+                    TickType::BidSize | TickType::DelayedBidSize,
+                    Tick::Bid {
+                        size: last_size, ..
+                    },
+                )
+                | (
+                    TickType::AskSize | TickType::DelayedAskSize,
+                    Tick::Ask {
+                        size: last_size, ..
+                    },
+                ) => *last_size = *size,
+                _ => (),
+            }
+        }
+        ServerRspMsg::TickByTick {
+            req_id: id, tick, ..
+        } if *id == req_id => match tick {
+            TickByTickData::Last { price, size, .. } => ticks.push((
+                time,
+                Tick::Trade {
+                    price: *price,
+                    size: *size,
+                },
+            )),
+            TickByTickData::BidAsk {
+                bid_price,
+                ask_price,
+                bid_size,
+                ask_size,
+                ..
+            } => {
+                ticks.push((
+                    time,
+                    Tick::Bid {
+                        price: *bid_price,
+                        size: *bid_size,
+                    },
+                ));
+                ticks.push((
+                    time,
+                    Tick::Ask {
+                        price: *ask_price,
+                        size: *ask_size,
+                    },
+                ));
+            }
+            TickByTickData::MidPoint { .. } => (),
+        },
+        _ => (),
+    }
+}
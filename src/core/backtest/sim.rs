@@ -0,0 +1,757 @@
+//! Order matching for backtests
+//!
+//! A MatchingEngine holds the orders of a backtest and fills them against the prices it is given,
+//! reporting to the event channel of an EClient the way TWS does: OrderStatus when an order is
+//! working, is cancelled or fills, and ExecutionData, OrderStatus and CommissionReport for every
+//! fill.  Orders always fill in full, at the first price that reaches them.
+//!
+//! Buy orders are matched against the ask, sell orders against the bid, and both against trades.
+//! Stops trigger when that price reaches the stop price.  Trailing stops move the stop as the
+//! price moves away from it, by the trailing amount in aux_price or by trailing_percent.
+//!
+//! Supported order types are MKT, LMT, STP, STP LMT, TRAIL and TRAIL LIMIT.  The limit of a TRAIL
+//! LIMIT order is lmt_price_offset beyond the stop, or, without it, stays as far from the stop as
+//! lmt_price is from trail_stop_price.
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{CommissionReport, UNSET_DOUBLE};
+use crate::core::contract::Contract;
+use crate::core::event_queue::EventSender;
+use crate::core::execution::Execution;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderStatusKind, WhyHeld};
+
+/// TWS error for an order type the engine cannot fill
+const UNSUPPORTED_ORDER_TYPE: i32 = 387;
+/// TWS error for an order without a quantity
+const ZERO_QUANTITY: i32 = 434;
+/// TWS error for an order with missing or invalid prices
+const INVALID_ORDER: i32 = 321;
+/// TWS error for cancelling an order that is not working
+const ORDER_NOT_FOUND: i32 = 10147;
+/// Format of execution times, as TWS sends them
+const EXECUTION_TIME: &str = "%Y%m%d  %H:%M:%S";
+
+//==================================================================================================
+/// A price update of one instrument
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tick {
+    Trade { price: f64, size: i32 },
+    Bid { price: f64, size: i32 },
+    Ask { price: f64, size: i32 },
+}
+
+//==================================================================================================
+/// How much worse than the market price orders fill.  Limit orders never fill beyond their limit.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SlippageModel {
+    None,
+    /// A fixed amount per share
+    Fixed {
+        amount: f64,
+    },
+    /// A fraction of the price, such as 0.0005 for 5 basis points
+    Proportional {
+        fraction: f64,
+    },
+}
+
+impl SlippageModel {
+    /// The fill price of an order that executes at `price`
+    pub fn apply(&self, price: f64, buy: bool) -> f64 {
+        let slippage = match *self {
+            SlippageModel::None => 0.0,
+            SlippageModel::Fixed { amount } => amount,
+            SlippageModel::Proportional { fraction } => price.abs() * fraction,
+        };
+        if buy {
+            price + slippage
+        } else {
+            price - slippage
+        }
+    }
+}
+
+//==================================================================================================
+/// How long orders and cancels take to reach the simulated exchange
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatencyModel {
+    None,
+    Fixed { millis: i64 },
+}
+
+impl LatencyModel {
+    pub fn delay(&self) -> Duration {
+        match *self {
+            LatencyModel::None => Duration::zero(),
+            LatencyModel::Fixed { millis } => Duration::milliseconds(millis.max(0)),
+        }
+    }
+}
+
+//==================================================================================================
+/// The commission charged for a fill
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CommissionModel {
+    None,
+    /// `rate` per share or contract, and at least `minimum` per fill
+    PerShare {
+        rate: f64,
+        minimum: f64,
+    },
+    /// `amount` per fill
+    PerOrder {
+        amount: f64,
+    },
+    /// A fraction of the traded value, and at least `minimum` per fill
+    Proportional {
+        fraction: f64,
+        minimum: f64,
+    },
+}
+
+impl CommissionModel {
+    /// # Arguments
+    /// * quantity - Shares or contracts filled
+    /// * price - The fill price
+    /// * multiplier - The contract multiplier, 1 for stocks
+    pub fn commission(&self, quantity: f64, price: f64, multiplier: f64) -> f64 {
+        match *self {
+            CommissionModel::None => 0.0,
+            CommissionModel::PerShare { rate, minimum } => (quantity.abs() * rate).max(minimum),
+            CommissionModel::PerOrder { amount } => amount,
+            CommissionModel::Proportional { fraction, minimum } => {
+                (quantity.abs() * price.abs() * multiplier * fraction).max(minimum)
+            }
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Trail {
+    Amount(f64),
+    Percent(f64),
+}
+
+impl Trail {
+    fn distance(&self, price: f64) -> f64 {
+        match *self {
+            Trail::Amount(amount) => amount,
+            Trail::Percent(percent) => price.abs() * percent / 100.0,
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct WorkingOrder {
+    order_id: i32,
+    perm_id: i64,
+    instrument: usize,
+    contract: Contract,
+    buy: bool,
+    quantity: f64,
+    limit: Option<f64>,
+    /// The stop price of stop and trailing orders, None for trailing orders until they see a price
+    stop: Option<f64>,
+    trail: Option<Trail>,
+    /// TRAIL LIMIT orders: the limit is this far from the stop
+    limit_offset: Option<f64>,
+    /// Set once the stop was reached, from then on the order is a market or limit order
+    triggered: bool,
+}
+
+impl WorkingOrder {
+    /// The price the order executes at, if `price` reaches it.  `price` is the price a buy or a
+    /// sell would execute at, depending on the side of the order.
+    fn execution_price(&mut self, price: f64) -> Option<f64> {
+        if !self.triggered {
+            if let Some(trail) = self.trail {
+                let trailed = if self.buy {
+                    price + trail.distance(price)
+                } else {
+                    price - trail.distance(price)
+                };
+                self.stop = Some(match self.stop {
+                    Some(stop) if self.buy => stop.min(trailed),
+                    Some(stop) => stop.max(trailed),
+                    None => trailed,
+                });
+                if let Some(offset) = self.limit_offset {
+                    self.limit = self.stop.map(|stop| stop + offset);
+                }
+            }
+            if let Some(stop) = self.stop {
+                let stopped = if self.buy {
+                    price >= stop
+                } else {
+                    price <= stop
+                };
+                if !stopped {
+                    return None;
+                }
+                self.triggered = true;
+            }
+        }
+        match self.limit {
+            Some(limit) if self.buy && price > limit => None,
+            Some(limit) if !self.buy && price < limit => None,
+            _ => Some(price),
+        }
+    }
+}
+
+//==================================================================================================
+/// Orders and cancels on their way to the simulated exchange
+#[derive(Clone, Debug)]
+enum Request {
+    Place(Box<WorkingOrder>),
+    Cancel(i32),
+}
+
+//==================================================================================================
+#[derive(Clone, Copy, Debug, Default)]
+struct Quotes {
+    last: Option<f64>,
+    bid: Option<f64>,
+    ask: Option<f64>,
+}
+
+impl Quotes {
+    fn price(&self, buy: bool) -> Option<f64> {
+        if buy {
+            self.ask.or(self.last)
+        } else {
+            self.bid.or(self.last)
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+struct Position {
+    contract: Contract,
+    quantity: f64,
+    /// Average price, without the multiplier
+    avg_price: f64,
+}
+
+impl Position {
+    fn multiplier(&self) -> f64 {
+        self.contract.multiplier.parse().unwrap_or(1.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Books a fill.  Returns the realized P&L if the fill closed some of the position.
+    fn book(&mut self, quantity: f64, price: f64) -> Option<f64> {
+        let closing = self.quantity * quantity < 0.0;
+        let realized = if closing {
+            let closed = quantity.abs().min(self.quantity.abs());
+            Some((price - self.avg_price) * closed * self.quantity.signum() * self.multiplier())
+        } else {
+            None
+        };
+
+        let after = self.quantity + quantity;
+        if !closing {
+            self.avg_price =
+                (self.avg_price * self.quantity.abs() + price * quantity.abs()) / after.abs();
+        } else if after * self.quantity < 0.0 {
+            // the fill closed the position and opened one on the other side
+            self.avg_price = price;
+        } else if after == 0.0 {
+            self.avg_price = 0.0;
+        }
+        self.quantity = after;
+        realized
+    }
+}
+
+//==================================================================================================
+/// Matches the orders of one account against market prices and reports fills as TWS does
+///
+/// Instruments are identified by a number the caller chooses, such as an index into its own list
+/// of contracts.
+pub struct MatchingEngine {
+    events: EventSender,
+    account: String,
+    client_id: i32,
+    slippage: SlippageModel,
+    latency: LatencyModel,
+    commission: CommissionModel,
+    now: DateTime<Utc>,
+    quotes: BTreeMap<usize, Quotes>,
+    positions: BTreeMap<usize, Position>,
+    positions_subscribed: bool,
+    /// Working orders in the order they arrived
+    orders: Vec<WorkingOrder>,
+    /// Orders and cancels by the time they arrive, in that order
+    requests: Vec<(DateTime<Utc>, Request)>,
+    next_perm_id: i64,
+    executions: u64,
+}
+
+impl Debug for MatchingEngine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchingEngine")
+            .field("account", &self.account)
+            .field("slippage", &self.slippage)
+            .field("latency", &self.latency)
+            .field("commission", &self.commission)
+            .field("now", &self.now)
+            .field("orders", &self.orders.len())
+            .finish()
+    }
+}
+
+impl MatchingEngine {
+    /// An engine without slippage, latency or commission
+    ///
+    /// # Arguments
+    /// * events - Where the engine sends order statuses, executions and errors
+    pub fn new(events: EventSender) -> Self {
+        MatchingEngine {
+            events,
+            account: String::new(),
+            client_id: 0,
+            slippage: SlippageModel::None,
+            latency: LatencyModel::None,
+            commission: CommissionModel::None,
+            now: DateTime::<Utc>::UNIX_EPOCH,
+            quotes: BTreeMap::new(),
+            positions: BTreeMap::new(),
+            positions_subscribed: false,
+            orders: Vec::new(),
+            requests: Vec::new(),
+            next_perm_id: 1,
+            executions: 0,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The account that executions and positions are reported for
+    pub fn with_account(mut self, account: &str) -> Self {
+        self.account = account.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_client_id(mut self, client_id: i32) -> Self {
+        self.client_id = client_id;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_slippage(mut self, slippage: SlippageModel) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_latency(mut self, latency: LatencyModel) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_commission(mut self, commission: CommissionModel) -> Self {
+        self.commission = commission;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn emit(&self, msg: ServerRspMsg) {
+        // nobody listening is not an error for a simulation
+        let _ = self.events.send(msg);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn error(&self, order_id: i32, error_code: i32, error_str: String) {
+        self.emit(ServerRspMsg::ErrMsg {
+            req_id: order_id,
+            error_code,
+            error_str,
+        });
+    }
+
+    //==============================================================================================
+    /// Sends an order to the simulated exchange, where it arrives after the latency.  An order
+    /// with the id of a working order replaces it.  Invalid orders are answered with an error
+    /// right away.
+    ///
+    /// # Arguments
+    /// * order_id - The id of the order
+    /// * instrument - The instrument the contract is traded as
+    /// * contract - The contract, reported with executions and positions
+    /// * order - The order.  The engine uses action, total_quantity, order_type, lmt_price,
+    ///   aux_price, trail_stop_price, trailing_percent and lmt_price_offset.
+    /// * now - The simulated time
+    pub fn place(
+        &mut self,
+        order_id: i32,
+        instrument: usize,
+        contract: &Contract,
+        order: &Order,
+        now: DateTime<Utc>,
+    ) {
+        match self.working_order(order_id, instrument, contract, order) {
+            Ok(working) => {
+                self.requests.push((
+                    now + self.latency.delay(),
+                    Request::Place(Box::new(working)),
+                ));
+                self.advance(now);
+            }
+            Err((error_code, error_str)) => self.error(order_id, error_code, error_str),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn working_order(
+        &self,
+        order_id: i32,
+        instrument: usize,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<WorkingOrder, (i32, String)> {
+        let price = |value: f64| {
+            if value == UNSET_DOUBLE {
+                None
+            } else {
+                Some(value)
+            }
+        };
+        let buy = match order.action.as_str() {
+            "BUY" => true,
+            "SELL" | "SSHORT" => false,
+            action => return Err((INVALID_ORDER, format!("Invalid action {:?}", action))),
+        };
+        if order.total_quantity <= 0.0 {
+            return Err((ZERO_QUANTITY, "The order size cannot be zero.".to_string()));
+        }
+        let mut working = WorkingOrder {
+            order_id,
+            perm_id: 0,
+            instrument,
+            contract: contract.clone(),
+            buy,
+            quantity: order.total_quantity,
+            limit: None,
+            stop: None,
+            trail: None,
+            limit_offset: None,
+            triggered: false,
+        };
+        let missing = |name: &str| {
+            (
+                INVALID_ORDER,
+                format!("{} orders need {}", order.order_type, name),
+            )
+        };
+        match order.order_type.as_str() {
+            "MKT" => (),
+            "LMT" => {
+                working.limit = Some(price(order.lmt_price).ok_or_else(|| missing("lmt_price"))?)
+            }
+            "STP" => {
+                working.stop = Some(price(order.aux_price).ok_or_else(|| missing("aux_price"))?)
+            }
+            "STP LMT" => {
+                working.limit = Some(price(order.lmt_price).ok_or_else(|| missing("lmt_price"))?);
+                working.stop = Some(price(order.aux_price).ok_or_else(|| missing("aux_price"))?);
+            }
+            "TRAIL" | "TRAIL LIMIT" => {
+                working.trail = Some(
+                    match (price(order.trailing_percent), price(order.aux_price)) {
+                        (Some(percent), _) => Trail::Percent(percent),
+                        (None, Some(amount)) => Trail::Amount(amount),
+                        (None, None) => return Err(missing("aux_price or trailing_percent")),
+                    },
+                );
+                working.stop = price(order.trail_stop_price);
+                if order.order_type == "TRAIL LIMIT" {
+                    let offset = match (price(order.lmt_price_offset), price(order.lmt_price)) {
+                        (Some(offset), _) if buy => offset,
+                        (Some(offset), _) => -offset,
+                        (None, Some(limit)) => {
+                            let stop = working.stop.ok_or_else(|| {
+                                missing("lmt_price_offset, or lmt_price and trail_stop_price")
+                            })?;
+                            limit - stop
+                        }
+                        (None, None) => return Err(missing("lmt_price_offset or lmt_price")),
+                    };
+                    working.limit_offset = Some(offset);
+                    working.limit = working.stop.map(|stop| stop + offset);
+                }
+            }
+            other => {
+                return Err((
+                    UNSUPPORTED_ORDER_TYPE,
+                    format!("The order type {} is not simulated by the backtest", other),
+                ))
+            }
+        }
+        Ok(working)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels a working order once the cancel arrives, after the latency.  The order may fill
+    /// before that.
+    pub fn cancel(&mut self, order_id: i32, now: DateTime<Utc>) {
+        self.requests
+            .push((now + self.latency.delay(), Request::Cancel(order_id)));
+        self.advance(now);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether an order has arrived and neither filled nor been cancelled
+    pub fn is_working(&self, order_id: i32) -> bool {
+        self.orders.iter().any(|order| order.order_id == order_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The position in an instrument, negative when short
+    pub fn position(&self, instrument: usize) -> f64 {
+        self.positions
+            .get(&instrument)
+            .map(|position| position.quantity)
+            .unwrap_or_default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sends PositionData for every open position, then PositionEnd, and an update after every
+    /// fill until cancel_positions()
+    pub fn req_positions(&mut self) {
+        self.positions_subscribed = true;
+        for instrument in self.positions.keys() {
+            if self.position(*instrument) != 0.0 {
+                self.emit_position(*instrument);
+            }
+        }
+        self.emit(ServerRspMsg::PositionEnd);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn cancel_positions(&mut self) {
+        self.positions_subscribed = false;
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn emit_position(&self, instrument: usize) {
+        if let Some(position) = self.positions.get(&instrument) {
+            self.emit(ServerRspMsg::PositionData {
+                account: self.account.clone(),
+                contract: position.contract.clone(),
+                position: position.quantity,
+                avg_cost: position.avg_price * position.multiplier(),
+            });
+        }
+    }
+
+    //==============================================================================================
+    /// Moves the clock to `now`, so the orders and cancels sent up to the latency before it arrive.
+    /// Orders arriving are matched against the prices at that time.
+    pub fn advance(&mut self, now: DateTime<Utc>) {
+        self.now = self.now.max(now);
+        while let Some((arrival, _)) = self.requests.first() {
+            if *arrival > self.now {
+                break;
+            }
+            let (arrival, request) = self.requests.remove(0);
+            match request {
+                Request::Place(order) => self.arrive(*order, arrival),
+                Request::Cancel(order_id) => self.arrive_cancel(order_id),
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn arrive(&mut self, mut order: WorkingOrder, arrival: DateTime<Utc>) {
+        match self
+            .orders
+            .iter()
+            .position(|working| working.order_id == order.order_id)
+        {
+            Some(index) => {
+                order.perm_id = self.orders[index].perm_id;
+                self.orders[index] = order.clone();
+            }
+            None => {
+                order.perm_id = self.next_perm_id;
+                self.next_perm_id += 1;
+                self.orders.push(order.clone());
+            }
+        }
+        self.order_status(&order, OrderStatusKind::Submitted, 0.0, 0.0);
+
+        // marketable orders fill at the prices they arrive at
+        let quotes = self
+            .quotes
+            .get(&order.instrument)
+            .copied()
+            .unwrap_or_default();
+        let now = self.now;
+        self.now = arrival;
+        self.match_orders(order.instrument, quotes.price(true), quotes.price(false));
+        self.now = now;
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn arrive_cancel(&mut self, order_id: i32) {
+        match self
+            .orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+        {
+            Some(index) => {
+                let order = self.orders.remove(index);
+                self.order_status(&order, OrderStatusKind::Cancelled, 0.0, 0.0);
+            }
+            None => self.error(
+                order_id,
+                ORDER_NOT_FOUND,
+                format!(
+                    "OrderId {} that needs to be cancelled is not found.",
+                    order_id
+                ),
+            ),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a price without matching orders, as for data before the backtest starts
+    pub fn update(&mut self, instrument: usize, tick: &Tick) {
+        let quotes = self.quotes.entry(instrument).or_default();
+        match *tick {
+            Tick::Trade { price, .. } => quotes.last = Some(price),
+            Tick::Bid { price, .. } => quotes.bid = Some(price),
+            Tick::Ask { price, .. } => quotes.ask = Some(price),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a price at `now` and fills the orders it reaches
+    pub fn on_tick(&mut self, instrument: usize, tick: &Tick, now: DateTime<Utc>) {
+        self.advance(now);
+        self.update(instrument, tick);
+        match *tick {
+            Tick::Trade { price, .. } => self.match_orders(instrument, Some(price), Some(price)),
+            Tick::Bid { price, .. } => self.match_orders(instrument, None, Some(price)),
+            Tick::Ask { price, .. } => self.match_orders(instrument, Some(price), None),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Fills the working orders of an instrument that the prices reach
+    ///
+    /// # Arguments
+    /// * buy - Price buy orders would execute at, if it changed
+    /// * sell - Price sell orders would execute at, if it changed
+    fn match_orders(&mut self, instrument: usize, buy: Option<f64>, sell: Option<f64>) {
+        let mut filled = Vec::new();
+        for (index, order) in self.orders.iter_mut().enumerate() {
+            if order.instrument != instrument {
+                continue;
+            }
+            let price = if order.buy { buy } else { sell };
+            if let Some(price) = price.and_then(|price| order.execution_price(price)) {
+                let mut fill = self.slippage.apply(price, order.buy);
+                if let Some(limit) = order.limit {
+                    fill = if order.buy {
+                        fill.min(limit)
+                    } else {
+                        fill.max(limit)
+                    };
+                }
+                filled.push((index, fill));
+            }
+        }
+        for (index, price) in filled.into_iter().rev() {
+            let order = self.orders.remove(index);
+            self.fill(order, price);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn order_status(&self, order: &WorkingOrder, status: OrderStatusKind, filled: f64, price: f64) {
+        self.emit(ServerRspMsg::OrderStatus {
+            order_id: order.order_id,
+            status,
+            filled,
+            remaining: order.quantity - filled,
+            avg_fill_price: price,
+            perm_id: order.perm_id,
+            parent_id: 0,
+            last_fill_price: price,
+            client_id: self.client_id,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn fill(&mut self, order: WorkingOrder, price: f64) {
+        self.executions += 1;
+        let exec_id = format!("backtest.{:08}", self.executions);
+        let position = self
+            .positions
+            .entry(order.instrument)
+            .or_insert_with(|| Position {
+                contract: order.contract.clone(),
+                ..Default::default()
+            });
+        let commission = self
+            .commission
+            .commission(order.quantity, price, position.multiplier());
+        let quantity = if order.buy {
+            order.quantity
+        } else {
+            -order.quantity
+        };
+        let realized_pnl = position.book(quantity, price);
+
+        self.emit(ServerRspMsg::ExecutionData {
+            req_id: -1,
+            contract: order.contract.clone(),
+            execution: Execution {
+                exec_id: exec_id.clone(),
+                time: Some(self.now),
+                #[cfg(feature = "raw-times")]
+                time_raw: self.now.format(EXECUTION_TIME).to_string(),
+                acct_number: self.account.clone(),
+                exchange: order.contract.exchange.clone(),
+                side: if order.buy { "BOT" } else { "SLD" }.to_string(),
+                shares: order.quantity,
+                price,
+                perm_id: order.perm_id,
+                client_id: self.client_id,
+                order_id: order.order_id,
+                cum_qty: order.quantity,
+                avg_price: price,
+                ..Default::default()
+            },
+        });
+        self.order_status(&order, OrderStatusKind::Filled, order.quantity, price);
+        self.emit(ServerRspMsg::CommissionReport {
+            commission_report: CommissionReport {
+                exec_id,
+                commission,
+                currency: order.contract.currency.clone(),
+                realized_pnl: realized_pnl
+                    .map(|pnl| pnl - commission)
+                    .unwrap_or(UNSET_DOUBLE),
+                yield_: UNSET_DOUBLE,
+                yield_redemption_date: String::new(),
+            },
+        });
+        if self.positions_subscribed {
+            self.emit_position(order.instrument);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Reusable byte buffers for inbound and outbound frames
+//!
+//! The reader copies every frame it reads into a buffer taken from a BufferPool and the decoder
+//! gives the buffer back once the frame is decoded, so a steady stream of ticks is handled
+//! without allocating a new buffer per message.  EClient assembles outbound frames in buffers from
+//! the same pool.  Clones share the buffers.
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Buffers kept for reuse by BufferPool::default()
+pub const DEFAULT_POOL_BUFFERS: usize = 64;
+/// Buffers that grew beyond this many bytes, e.g. for fundamentals XML, are dropped when given
+/// back instead of being kept
+pub const DEFAULT_MAX_RETAINED: usize = 64 * 1024;
+
+//==================================================================================================
+/// How often a BufferPool could reuse a buffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers taken when none was free, which had to be allocated
+    pub allocated: u64,
+    /// Buffers taken from the free list
+    pub reused: u64,
+    /// Buffers given back but dropped, because the pool was full or they were too large
+    pub discarded: u64,
+}
+
+//==================================================================================================
+struct Pool {
+    free: Vec<Vec<u8>>,
+    max_buffers: usize,
+    max_retained: usize,
+    stats: PoolStats,
+}
+
+//==================================================================================================
+/// A free list of byte buffers shared by the threads of one connection
+#[derive(Clone)]
+pub struct BufferPool {
+    shared: Arc<Mutex<Pool>>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::with_limits(DEFAULT_POOL_BUFFERS, DEFAULT_MAX_RETAINED)
+    }
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pool = self.lock();
+        f.debug_struct("BufferPool")
+            .field("available", &pool.free.len())
+            .field("max_buffers", &pool.max_buffers)
+            .field("max_retained", &pool.max_retained)
+            .field("stats", &pool.stats)
+            .finish()
+    }
+}
+
+impl BufferPool {
+    pub fn new() -> Self {
+        BufferPool::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// # Arguments
+    /// * max_buffers - Buffers kept for reuse.  Further buffers given back are dropped.
+    /// * max_retained - Capacity in bytes above which a buffer given back is dropped
+    pub fn with_limits(max_buffers: usize, max_retained: usize) -> Self {
+        BufferPool {
+            shared: Arc::new(Mutex::new(Pool {
+                free: Vec::with_capacity(max_buffers),
+                max_buffers,
+                max_retained,
+                stats: PoolStats::default(),
+            })),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn lock(&self) -> MutexGuard<'_, Pool> {
+        // the free list is only pushed to and popped from, so a panicking thread cannot corrupt it
+        self.shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// An empty buffer, reused if one is free
+    pub fn take(&self) -> Vec<u8> {
+        let mut pool = self.lock();
+        match pool.free.pop() {
+            Some(buffer) => {
+                pool.stats.reused += 1;
+                buffer
+            }
+            None => {
+                pool.stats.allocated += 1;
+                Vec::new()
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Takes a buffer and fills it with `bytes` as text.  Invalid UTF-8 is replaced, which
+    /// allocates.
+    pub fn take_string(&self, bytes: &[u8]) -> String {
+        let mut buffer = self.take();
+        buffer.extend_from_slice(bytes);
+        String::from_utf8(buffer)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Gives a buffer back for reuse.  Its contents are cleared.
+    pub fn give(&self, mut buffer: Vec<u8>) {
+        let mut pool = self.lock();
+        if pool.free.len() >= pool.max_buffers || buffer.capacity() > pool.max_retained {
+            pool.stats.discarded += 1;
+            return;
+        }
+        buffer.clear();
+        pool.free.push(buffer);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Gives back the buffer of a string taken with take_string()
+    pub fn give_string(&self, text: String) {
+        self.give(text.into_bytes())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buffers free for reuse
+    pub fn available(&self) -> usize {
+        self.lock().free.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn stats(&self) -> PoolStats {
+        self.lock().stats
+    }
+}
@@ -0,0 +1,84 @@
+//! Closures registered per message kind, for scripts that only handle a few events and do not
+//! want to implement the handler traits.
+//!
+//! ```no_run
+//! use ibtwsapi::core::callbacks::Callbacks;
+//! use ibtwsapi::core::messages::{ServerRspMsg, ServerRspMsgKind};
+//!
+//! let mut callbacks = Callbacks::new();
+//! callbacks.on(ServerRspMsgKind::TickPrice, |msg| {
+//!     if let ServerRspMsg::TickPrice { req_id, price, .. } = msg {
+//!         println!("{} {}", req_id, price);
+//!     }
+//! });
+//! ```
+use std::collections::HashMap;
+
+use crate::core::messages::{ServerRspMsg, ServerRspMsgKind};
+
+type Callback = Box<dyn FnMut(&ServerRspMsg) + Send>;
+
+//==================================================================================================
+/// Closures keyed by message kind
+#[derive(Default)]
+pub struct Callbacks {
+    by_kind: HashMap<ServerRspMsgKind, Vec<Callback>>,
+    any: Vec<Callback>,
+}
+
+impl Callbacks {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Registers a closure called with every message of a kind.  Closures registered for the
+    /// same kind are called in the order they were registered.
+    ///
+    /// # Arguments
+    /// * kind - The message variant to call the closure for
+    /// * callback - Called with the message
+    pub fn on<F>(&mut self, kind: ServerRspMsgKind, callback: F) -> &mut Self
+    where
+        F: FnMut(&ServerRspMsg) + Send + 'static,
+    {
+        self.by_kind
+            .entry(kind)
+            .or_default()
+            .push(Box::new(callback));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Registers a closure called with every message, after the closures for its kind
+    pub fn on_any<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: FnMut(&ServerRspMsg) + Send + 'static,
+    {
+        self.any.push(Box::new(callback));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes the closures registered for a kind
+    pub fn clear(&mut self, kind: ServerRspMsgKind) {
+        self.by_kind.remove(&kind);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Calls the closures registered for a message and returns whether there were any
+    pub fn dispatch(&mut self, msg: &ServerRspMsg) -> bool {
+        let mut called = false;
+        if let Some(callbacks) = self.by_kind.get_mut(&ServerRspMsgKind::from(msg)) {
+            for callback in callbacks.iter_mut() {
+                callback(msg);
+            }
+            called = !callbacks.is_empty();
+        }
+        for callback in self.any.iter_mut() {
+            callback(msg);
+            called = true;
+        }
+        called
+    }
+}
@@ -1,46 +1,245 @@
 //! EClient and supporting structs.  Responsible for connecting to Trader Workstation or IB Gatway and sending requests
-use std::io::Write;
+use std::io::{self, Write};
 //use std::marker::Sync;
+use std::collections::BTreeMap;
+#[cfg(feature = "marketdata")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::net::Shutdown;
 use std::net::TcpStream;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::mpsc::{channel, RecvTimeoutError, TryRecvError};
 use std::sync::{Arc, Mutex};
+use std::task::Waker;
+use std::time::{Duration, Instant};
 use std::{fmt::Debug, thread};
 
-use from_ascii::FromAscii;
-use log::*;
-
-use num_derive::FromPrimitive;
+use chrono_tz::Tz;
+use num_traits::FromPrimitive;
+use tracing::{debug, error, info, instrument, warn};
 
 use super::streamer::{Streamer, TcpStreamer};
+use crate::core::account_summary_tags::{
+    AccountSummaryFuture, AccountSummaryReport, AccountSummaryTag, AccountSummaryValue,
+};
+use crate::core::buffer_pool::{BufferPool, PoolStats};
+use crate::core::callbacks::Callbacks;
+pub use crate::core::common::ConnStatus;
 use crate::core::common::*;
+#[cfg(feature = "orders")]
+use crate::core::completed_orders::CompletedOrders;
+#[cfg(feature = "marketdata")]
+use crate::core::connection::ConnectivityNotice;
+use crate::core::connection::{
+    ConnectionEvent, ConnectionReceiver, ConnectionState, ConnectionWatch,
+};
 //use crate::core::wrapper::Wrapper;
 use crate::core::contract::Contract;
+#[cfg(feature = "orders")]
+use crate::core::crypto::{is_crypto, validate_crypto_order};
 use crate::core::decoder::Decoder;
-use crate::core::errors::{IBKRApiLibError, TwsApiReportableError, TwsError};
-use crate::core::execution::ExecutionFilter;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError, TwsError};
+use crate::core::event_queue::{
+    event_queue, EventQueueConfig, EventReceiver, EventSender, QueueMetrics,
+};
+#[cfg(feature = "orders")]
+use crate::core::execution::{ExecutionFilter, Executions};
+use crate::core::fa::{FaGroup, FaProfile};
+#[cfg(feature = "orders")]
+use crate::core::fill_report::{FillFailure, FillReport, FillTracker};
+use crate::core::handlers::{dispatch, Handlers};
+use crate::core::handshake::{self, Feature, ServerHello, ServerVersion};
+#[cfg(feature = "marketdata")]
+use crate::core::historical::{histogram_period, Histogram};
+use crate::core::latency::LatencyObserver;
+#[cfg(feature = "orders")]
+use crate::core::live_orders::{CancelReport, LiveOrders, OrderFilter, PendingCancels};
+#[cfg(feature = "marketdata")]
+use crate::core::market_data::{Shortability, ShortabilityTicks, SmartComponentMap, Snapshot};
 use crate::core::messages::make_field;
-use crate::core::messages::{make_field_handle_empty, read_msg};
-use crate::core::messages::{make_message, read_fields, ServerReqMsgDiscriminants, ServerRspMsg};
-use crate::core::order::Order;
+#[cfg(any(feature = "orders", feature = "marketdata"))]
+use crate::core::messages::make_field_handle_empty;
+use crate::core::messages::{
+    read_fields, write_message, ServerReqMsgDiscriminants, ServerRspMsg, ServerRspMsgKind,
+};
+#[cfg(feature = "metrics")]
+use crate::core::metrics;
+use crate::core::models::ModelPosition;
+#[cfg(feature = "marketdata")]
+use crate::core::options::{Calculation, OptionCalculation};
+#[cfg(feature = "orders")]
+use crate::core::order::trailing::validate_trailing_order;
+#[cfg(feature = "orders")]
+use crate::core::order::{ExerciseAction, ExerciseOptions, Order};
+#[cfg(feature = "orders")]
 use crate::core::order_condition::Condition;
+use crate::core::pnl::PnlStream;
+use crate::core::proxy::Proxy;
 use crate::core::reader::Reader;
+use crate::core::req_ids::{
+    CorrelationRegistry, EnrichedEvent, ReqIdAllocator, RequestContext, RequestKind,
+};
+use crate::core::router::Router;
+#[cfg(feature = "marketdata")]
 use crate::core::scanner::ScannerSubscription;
-use crate::core::server_versions::*;
+use crate::core::shutdown::{ShutdownOptions, ShutdownReport};
+use crate::core::socket_options::SocketOptions;
+use crate::core::test_vectors::CaptureRecorder;
+use crate::core::time::zone_of;
+#[cfg(feature = "marketdata")]
+use crate::core::time::TwsTime;
+#[cfg(feature = "orders")]
+use crate::core::what_if::WhatIfOrder;
 
 pub(crate) static POISONED_MUTEX: &str = "Mutex was poisoned";
 
+/// Errors TWS sends for a subscription the account has no market data permissions for
+#[cfg(feature = "marketdata")]
+const NO_MARKET_DATA_PERMISSIONS: [i32; 2] = [354, 10167];
+
+//==================================================================================================
+/// What EClient::collect() should do with an event
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collected {
+    /// The event is not part of the response.  It is kept for get_event().
+    Ignored,
+    /// The event was used and more are expected
+    Consumed,
+    /// The event was used and the response is complete
+    Finished,
+}
+
 //==================================================================================================
-/// Connection status
-#[repr(i32)]
-#[derive(FromPrimitive, Copy, Clone, Debug)]
-pub enum ConnStatus {
-    DISCONNECTED,
-    CONNECTING,
-    CONNECTED,
-    REDIRECT,
+/// Options for EClient::with_config()
+#[derive(Clone, Debug)]
+pub struct EClientConfig {
+    pub event_queue: EventQueueConfig,
+    /// Refuse to place orders unless the connection is logged into a paper account
+    pub require_paper: bool,
+    /// First id handed out by EClient::next_req_id()
+    pub first_req_id: i32,
+    /// Resubscribe to delayed data when TWS refuses real time data for a subscription
+    pub delayed_fallback: bool,
+    /// Repeat req_mkt_data() subscriptions after TWS reports connectivity restored with data
+    /// lost.  Other streams are not restored, see resubscribe_after_data_loss().
+    pub resubscribe_after_data_loss: bool,
+    /// Proxy that connect() tunnels through
+    pub proxy: Option<Proxy>,
+    /// TCP options set by connect()
+    pub socket: SocketOptions,
+    /// Buffers for inbound and outbound frames
+    pub buffers: BufferPool,
+    /// Records the frames received from TWS
+    pub recorder: Option<CaptureRecorder>,
+}
+
+impl Default for EClientConfig {
+    fn default() -> Self {
+        EClientConfig {
+            event_queue: EventQueueConfig::default(),
+            require_paper: false,
+            first_req_id: 1,
+            delayed_fallback: false,
+            resubscribe_after_data_loss: false,
+            proxy: None,
+            socket: SocketOptions::default(),
+            buffers: BufferPool::default(),
+            recorder: None,
+        }
+    }
+}
+
+impl EClientConfig {
+    /// Makes place_order() return an error unless every managed account is a paper account.
+    /// Orders are also refused until the managed accounts list has arrived.
+    pub fn require_paper(mut self) -> Self {
+        self.require_paper = true;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn event_queue(mut self, event_queue: EventQueueConfig) -> Self {
+        self.event_queue = event_queue;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn first_req_id(mut self, first_req_id: i32) -> Self {
+        self.first_req_id = first_req_id;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// When TWS reports missing market data permissions (error 10167 or 354) for a subscription
+    /// made with req_mkt_data(), the client requests delayed data and resubscribes with the same
+    /// req_id instead of returning the error.  Events read through get_event() and collect()
+    /// trigger the fallback.
+    pub fn delayed_fallback(mut self) -> Self {
+        self.delayed_fallback = true;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// When TWS reports that connectivity was restored but market data was lost (error 1101),
+    /// the client repeats every streaming subscription made with req_mkt_data() under the same
+    /// req_id.  Events read through get_event() and collect() trigger the resubscription.
+    ///
+    /// Only these top-of-book subscriptions are restored.  Market depth, tick-by-tick data,
+    /// real time bars and historical data kept up to date stay silent until requested again:
+    /// declare them in a SubscriptionManifest and call
+    /// SubscriptionSupervisor::on_connectivity_restored() to have all of them repeated.
+    pub fn resubscribe_after_data_loss(mut self) -> Self {
+        self.resubscribe_after_data_loss = true;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Connects to TWS or IB Gateway through a SOCKS5 or HTTP CONNECT proxy
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets TCP_NODELAY, keepalive and buffer sizes on the socket when connecting
+    pub fn socket(mut self, socket: SocketOptions) -> Self {
+        self.socket = socket;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Uses the given pool for frame buffers, e.g. one with other limits, or one shared by several
+    /// clients
+    pub fn buffer_pool(mut self, buffers: BufferPool) -> Self {
+        self.buffers = buffers;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records every frame received from TWS after the handshake, with the server version it was
+    /// decoded for.  The capture can be saved as a decoder test vector.
+    pub fn record_capture(mut self, recorder: CaptureRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+}
+
+//==================================================================================================
+/// A market data subscription, kept so it can be repeated with delayed data
+#[cfg(feature = "marketdata")]
+#[derive(Clone, Debug)]
+struct MarketDataRequest {
+    contract: Contract,
+    generic_tick_list: String,
+    snapshot: bool,
+    regulatory_snapshot: bool,
+    mkt_data_options: Vec<TagValue>,
+    /// The mode last reported by TWS
+    mode: Option<MarketDataMode>,
+    /// Set once the subscription was repeated with delayed data
+    fell_back: bool,
 }
 
 //==================================================================================================
@@ -52,38 +251,179 @@ pub struct EClient {
     port: u32,
     extra_auth: bool,
     client_id: i32,
-    evt_chan: (Sender<ServerRspMsg>, Receiver<ServerRspMsg>),
+    pub(crate) evt_chan: (EventSender, EventReceiver),
+    backlog: Mutex<VecDeque<ServerRspMsg>>,
     pub(crate) server_version: i32,
     conn_time: String,
     pub conn_state: Arc<Mutex<ConnStatus>>,
+    pub(crate) connection: ConnectionWatch,
     opt_capab: String,
     disconnect_requested: Arc<AtomicBool>,
+    /// Set by shutdown().  Shared with clones so no handle can start new requests.
+    shutting_down: Arc<AtomicBool>,
+    send_lock: Arc<Mutex<()>>,
+    /// Frame buffers shared by the reader, the decoder and all handles
+    buffers: BufferPool,
+    recorder: Option<CaptureRecorder>,
+    /// The reader and decoder threads of the current connection
+    threads: Vec<thread::JoinHandle<()>>,
+    pub(crate) environment: Arc<Mutex<Environment>>,
+    pub(crate) managed_accounts: Arc<Mutex<Vec<AccountId>>>,
+    require_paper: bool,
+    proxy: Option<Proxy>,
+    socket: SocketOptions,
+    req_ids: ReqIdAllocator,
+    correlations: CorrelationRegistry,
+    latency_observer: Option<Arc<dyn LatencyObserver>>,
+    callbacks: Mutex<Callbacks>,
+    #[cfg(feature = "marketdata")]
+    delayed_fallback: bool,
+    #[cfg(feature = "marketdata")]
+    resubscribe_after_data_loss: bool,
+    /// The mode last requested with req_market_data_type()
+    #[cfg(feature = "marketdata")]
+    market_data_mode: Arc<Mutex<MarketDataMode>>,
+    #[cfg(feature = "marketdata")]
+    market_data: Mutex<HashMap<i32, MarketDataRequest>>,
+    /// Depth requests sent as SMART depth, so they are cancelled as SMART depth
+    #[cfg(feature = "marketdata")]
+    smart_depth: Arc<Mutex<HashSet<i32>>>,
 }
 
 impl EClient {
     pub fn new() -> Self {
+        EClient::with_event_queue(EventQueueConfig::default())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates a client whose event queue uses the given capacity and overflow policies
+    pub fn with_event_queue(config: EventQueueConfig) -> Self {
+        EClient::with_config(EClientConfig::default().event_queue(config))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_config(config: EClientConfig) -> Self {
         EClient {
             stream: None,
             host: "".to_string(),
             port: 0,
             extra_auth: false,
             client_id: 0,
-            evt_chan: channel(),
+            evt_chan: event_queue(config.event_queue),
+            backlog: Mutex::new(VecDeque::new()),
             server_version: 0,
             conn_time: "".to_string(),
             conn_state: Arc::new(Mutex::new(ConnStatus::DISCONNECTED)),
+            connection: ConnectionWatch::new(),
             opt_capab: "".to_string(),
             disconnect_requested: Arc::new(AtomicBool::new(false)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            send_lock: Arc::new(Mutex::new(())),
+            buffers: config.buffers,
+            recorder: config.recorder,
+            threads: Vec::new(),
+            environment: Arc::new(Mutex::new(Environment::Unknown)),
+            managed_accounts: Arc::new(Mutex::new(Vec::new())),
+            require_paper: config.require_paper,
+            proxy: config.proxy,
+            socket: config.socket,
+            req_ids: ReqIdAllocator::new(config.first_req_id),
+            correlations: CorrelationRegistry::new(),
+            latency_observer: None,
+            callbacks: Mutex::new(Callbacks::new()),
+            #[cfg(feature = "marketdata")]
+            delayed_fallback: config.delayed_fallback,
+            #[cfg(feature = "marketdata")]
+            resubscribe_after_data_loss: config.resubscribe_after_data_loss,
+            #[cfg(feature = "marketdata")]
+            market_data_mode: Arc::new(Mutex::new(MarketDataMode::default())),
+            #[cfg(feature = "marketdata")]
+            market_data: Mutex::new(HashMap::new()),
+            #[cfg(feature = "marketdata")]
+            smart_depth: Arc::new(Mutex::new(HashSet::new())),
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates a second handle on the same connection that can send requests from another thread.
+    /// Writes from all handles are serialized so messages never interleave on the socket.
+    /// Events are only delivered to the original handle's get_event().
+    pub fn try_clone(&self) -> Result<EClient, IBKRApiLibError> {
+        let stream = match self.stream.as_ref() {
+            Some(stream) => Some(stream.try_clone()?),
+            None => None,
+        };
+        let evt_chan = event_queue(EventQueueConfig::default());
+        evt_chan
+            .1
+            .set_latency_observer(self.latency_observer.clone());
+        Ok(EClient {
+            stream,
+            host: self.host.clone(),
+            port: self.port,
+            extra_auth: self.extra_auth,
+            client_id: self.client_id,
+            evt_chan,
+            backlog: Mutex::new(VecDeque::new()),
+            server_version: self.server_version,
+            conn_time: self.conn_time.clone(),
+            conn_state: self.conn_state.clone(),
+            connection: self.connection.clone(),
+            opt_capab: self.opt_capab.clone(),
+            disconnect_requested: self.disconnect_requested.clone(),
+            shutting_down: self.shutting_down.clone(),
+            send_lock: self.send_lock.clone(),
+            buffers: self.buffers.clone(),
+            recorder: self.recorder.clone(),
+            threads: Vec::new(),
+            environment: self.environment.clone(),
+            managed_accounts: self.managed_accounts.clone(),
+            require_paper: self.require_paper,
+            proxy: self.proxy.clone(),
+            socket: self.socket.clone(),
+            req_ids: self.req_ids.clone(),
+            correlations: self.correlations.clone(),
+            latency_observer: self.latency_observer.clone(),
+            callbacks: Mutex::new(Callbacks::new()),
+            #[cfg(feature = "marketdata")]
+            delayed_fallback: self.delayed_fallback,
+            #[cfg(feature = "marketdata")]
+            resubscribe_after_data_loss: self.resubscribe_after_data_loss,
+            #[cfg(feature = "marketdata")]
+            market_data_mode: self.market_data_mode.clone(),
+            #[cfg(feature = "marketdata")]
+            market_data: Mutex::new(HashMap::new()),
+            #[cfg(feature = "marketdata")]
+            smart_depth: self.smart_depth.clone(),
+        })
+    }
+
     fn send_request(&mut self, request: &str) -> Result<(), IBKRApiLibError> {
-        let bytes = make_message(request)?;
-        self.send_bytes(bytes.as_slice())?;
+        let msg_id = request.split('\0').next().and_then(|id| id.parse().ok());
+        if let Some(kind) = msg_id.and_then(ServerReqMsgDiscriminants::from_i32) {
+            ServerVersion(self.server_version).check(&kind)?;
+            if self.shutting_down.load(Ordering::Acquire) && !kind.is_cancel() {
+                return Err(IBKRApiLibError::ShuttingDown);
+            }
+            debug!(request = ?kind, "sending request");
+        }
+        let mut buffer = self.buffers.take();
+        write_message(request, &mut buffer)?;
+        let result = self.send_bytes(buffer.as_slice());
+        self.buffers.give(buffer);
+        result?;
         Ok(())
     }
 
     fn send_bytes(&mut self, bytes: &[u8]) -> Result<usize, IBKRApiLibError> {
+        // the lock only guards the socket, so a poisoned lock is still safe to use
+        let _guard = self
+            .send_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
         let return_val = self.stream.as_mut().unwrap().write(bytes)?;
+        #[cfg(feature = "metrics")]
+        metrics::bytes_sent(return_val);
         Ok(return_val)
     }
 
@@ -110,9 +450,27 @@ impl EClient {
         self.port = port;
         self.client_id = client_id;
         info!("Connecting");
+        self.connection.apply(ConnectionEvent::Connect);
+        let result = self.open_connection();
+        if result.is_err() {
+            *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::DISCONNECTED;
+            self.connection.apply(ConnectionEvent::ConnectFailed);
+        }
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Opens the socket, negotiates the server version and starts the API
+    fn open_connection(&mut self) -> Result<(), IBKRApiLibError> {
+        let port = self.port;
         self.disconnect_requested.store(false, Ordering::Release);
+        self.shutting_down.store(false, Ordering::Release);
         *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTING;
-        let tcp_stream = TcpStream::connect(format!("{}:{}", self.host, port))?;
+        let tcp_stream = match &self.proxy {
+            Some(proxy) => proxy.connect(&self.host, port)?,
+            None => TcpStream::connect(format!("{}:{}", self.host, port))?,
+        };
+        self.socket.apply(&tcp_stream)?;
         let streamer = TcpStreamer::new(tcp_stream);
         self.set_streamer(Option::from(Box::new(streamer.clone()) as Box<dyn Streamer>));
         let (tx, rx) = channel::<String>();
@@ -124,16 +482,8 @@ impl EClient {
 
         let mut fields: Vec<String> = Vec::new();
 
-        let v_100_prefix = "API\0";
-        let v_100_version = format!("v{}..{}", MIN_CLIENT_VER, MAX_CLIENT_VER);
-
-        let msg = make_message(v_100_version.as_str())?;
-
-        let mut bytearray: Vec<u8> = Vec::new();
-        bytearray.extend_from_slice(v_100_prefix.as_bytes());
-        bytearray.extend_from_slice(msg.as_slice());
-
-        self.send_bytes(bytearray.as_slice())?;
+        self.send_bytes(handshake::hello()?.as_slice())?;
+        self.connection.apply(ConnectionEvent::HandshakeStarted);
 
         let mut decoder = Decoder::new(
             rx,
@@ -141,57 +491,304 @@ impl EClient {
             self.server_version,
             self.conn_state.clone(),
         );
+        *self.environment.lock().expect(POISONED_MUTEX) = Environment::Unknown;
+        decoder.environment = self.environment.clone();
+        self.managed_accounts.lock().expect(POISONED_MUTEX).clear();
+        decoder.managed_accounts = self.managed_accounts.clone();
+        decoder.connection = self.connection.clone();
+        decoder.buffers = self.buffers.clone();
+        reader.buffers = self.buffers.clone();
+        decoder.recorder = self.recorder.clone();
+        if self.latency_observer.is_some() {
+            let (read_times, read_times_rx) = channel();
+            reader.read_times = Some(read_times);
+            decoder.read_times = Some(read_times_rx);
+        }
 
         //An Interactive Broker's developer's note: "sometimes I get news before the server version, thus the loop"
-        while fields.len() != 2 {
+        let mut msg = String::new();
+        let hello = loop {
+            if let Some(hello) = ServerHello::parse(&fields) {
+                break hello?;
+            }
             if fields.len() > 0 {
-                decoder.interpret(fields.as_slice())?;
+                decoder.interpret(&msg)?;
             }
 
-            let buf = reader.recv_packet()?;
-
-            if buf.len() > 0 {
-                let (_size, msg, _remaining_messages) = read_msg(buf.as_slice())?;
-
-                fields.clear();
-                fields.extend_from_slice(read_fields(msg.as_ref()).as_slice());
-            } else {
-                fields.clear();
-            }
-        }
+            let text = match reader.next_message()? {
+                Some(text) => text,
+                None => {
+                    return Err(IBKRApiLibError::Io(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "TWS closed the connection during the handshake",
+                    )))
+                }
+            };
+            fields.clear();
+            fields.extend_from_slice(read_fields(text.as_ref()).as_slice());
+            msg = text;
+        };
 
-        self.server_version = i32::from_ascii(fields.get(0).unwrap().as_bytes()).unwrap();
+        self.server_version = hello.server_version.value();
 
         info!("Server version: {}", self.server_version);
 
-        self.conn_time = fields.get(1).unwrap().to_string();
+        #[cfg(feature = "metrics")]
+        if !self.conn_time.is_empty() {
+            metrics::reconnected();
+        }
         decoder.server_version = self.server_version;
-
-        thread::spawn(move || {
+        decoder.time_zone = zone_of(&hello.connection_time);
+        if decoder.time_zone.is_none() {
+            warn!(
+                "Unknown time zone in connection time {:?}.  Times TWS sends without a zone name \
+                 will not be read.",
+                hello.connection_time
+            );
+        }
+        self.conn_time = hello.connection_time;
+
+        self.threads.clear();
+        self.threads.push(thread::spawn(move || {
             reader.run();
-        });
+        }));
 
-        thread::spawn(move || {
+        self.threads.push(thread::spawn(move || {
             if decoder.run().is_err() {
                 panic!("decoder.run() failed!!");
             }
-        });
+        }));
         *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
         info!("Connected");
         self.start_api()?;
+        self.connection.apply(ConnectionEvent::ApiStarted);
         Ok(())
     }
 
     pub fn get_event(&self) -> Result<Option<ServerRspMsg>, IBKRApiLibError> {
-        match self.evt_chan.1.try_recv() {
-            Ok(i) => Ok(Some(i)),
-            Err(TryRecvError::Empty) => Ok(None),
-            Err(TryRecvError::Disconnected) => {
-                Err(IBKRApiLibError::TryRecvError(TryRecvError::Disconnected))
+        if let Some(msg) = self.backlog.lock().expect(POISONED_MUTEX).pop_front() {
+            return Ok(Some(msg));
+        }
+        loop {
+            let msg = match self.evt_chan.1.try_recv() {
+                Ok(i) => i,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => {
+                    return Err(IBKRApiLibError::TryRecvError(TryRecvError::Disconnected))
+                }
+            };
+            #[cfg(feature = "marketdata")]
+            if self.observe_market_data(&msg)? {
+                continue;
             }
+            return Ok(Some(msg));
         }
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Like get_event(), but waits up to `timeout` for an event to arrive
+    pub fn wait_event(&self, timeout: Duration) -> Result<Option<ServerRspMsg>, IBKRApiLibError> {
+        if let Some(msg) = self.backlog.lock().expect(POISONED_MUTEX).pop_front() {
+            return Ok(Some(msg));
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let msg = match self.evt_chan.1.recv_timeout(remaining) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(err) => return Err(IBKRApiLibError::RecvTimeoutError(err)),
+            };
+            #[cfg(feature = "marketdata")]
+            if self.observe_market_data(&msg)? {
+                continue;
+            }
+            return Ok(Some(msg));
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Passes every pending event to the registered handlers and returns how many were
+    /// delivered.  Events without a registered handler are dropped.
+    ///
+    /// # Arguments
+    /// * handlers - A struct implementing the handler traits it needs, or a HandlerSet
+    pub fn dispatch_events(&self, handlers: &mut dyn Handlers) -> Result<usize, IBKRApiLibError> {
+        let mut delivered = 0;
+        while let Some(msg) = self.get_event()? {
+            if dispatch(&msg, handlers) {
+                delivered += 1;
+            }
+        }
+        Ok(delivered)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Registers a closure that run_callbacks() calls with every event of a kind
+    ///
+    /// # Arguments
+    /// * kind - The message variant to call the closure for
+    /// * callback - Called with the event
+    pub fn on<F>(&mut self, kind: ServerRspMsgKind, callback: F) -> &mut Self
+    where
+        F: FnMut(&ServerRspMsg) + Send + 'static,
+    {
+        self.callbacks
+            .lock()
+            .expect(POISONED_MUTEX)
+            .on(kind, callback);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Passes every pending event to the closures registered with on() and returns how many
+    /// events had a closure.  Events without one are dropped.
+    pub fn run_callbacks(&self) -> Result<usize, IBKRApiLibError> {
+        let mut called = 0;
+        while let Some(msg) = self.get_event()? {
+            if self.callbacks.lock().expect(POISONED_MUTEX).dispatch(&msg) {
+                called += 1;
+            }
+        }
+        Ok(called)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Passes every pending event to a router and returns how many were routed.  Events that
+    /// match no route and have no fallback stay available to get_event().
+    pub fn route_events(&self, router: &mut Router) -> Result<usize, IBKRApiLibError> {
+        let mut routed = 0;
+        let mut unrouted = Vec::new();
+        while let Some(msg) = self.get_event()? {
+            match router.route(msg) {
+                Some(msg) => unrouted.push(msg),
+                None => routed += 1,
+            }
+        }
+        self.backlog.lock().expect(POISONED_MUTEX).extend(unrouted);
+        Ok(routed)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Like get_event(), but attaches the request registered with next_req_id() and drops
+    /// responses to requests that have been cancelled
+    pub fn get_enriched_event(&self) -> Result<Option<EnrichedEvent>, IBKRApiLibError> {
+        while let Some(msg) = self.get_event()? {
+            match self.correlations.enrich(msg) {
+                #[cfg(feature = "marketdata")]
+                Some(mut event) => {
+                    event.market_data_mode =
+                        event.msg.req_id().and_then(|id| self.market_data_mode(id));
+                    return Ok(Some(event));
+                }
+                #[cfg(not(feature = "marketdata"))]
+                Some(event) => return Ok(Some(event)),
+                None => debug!("dropped a response to a cancelled request"),
+            }
+        }
+        Ok(None)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Allocates a request id and records what it is used for.  The cancel_* methods mark the
+    /// id as cancelled so get_enriched_event() drops late responses.
+    ///
+    /// # Arguments
+    /// * kind - The kind of request the id is for
+    /// * description - Free text returned with the responses, for example the contract
+    pub fn next_req_id(&self, kind: RequestKind, description: &str) -> i32 {
+        let req_id = self.req_ids.next();
+        self.correlations.register(req_id, kind, description);
+        req_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A handle on the request id counter, for allocating ids on other threads
+    pub fn req_id_allocator(&self) -> ReqIdAllocator {
+        self.req_ids.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn correlations(&self) -> &CorrelationRegistry {
+        &self.correlations
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits for the events that make up a response.  Every event is passed to `handle` until it
+    /// returns Collected::Finished.  Events it ignores stay available to get_event() in order.
+    ///
+    /// # Arguments
+    /// * timeout - How long to wait for the whole response
+    /// * handle - Called with each event.  An Err is returned to the caller immediately.
+    pub fn collect<F>(&mut self, timeout: Duration, mut handle: F) -> Result<(), IBKRApiLibError>
+    where
+        F: FnMut(&ServerRspMsg) -> Result<Collected, IBKRApiLibError>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut ignored = Vec::new();
+        let mut pending: VecDeque<ServerRspMsg> = self
+            .backlog
+            .lock()
+            .expect(POISONED_MUTEX)
+            .drain(..)
+            .collect();
+
+        let result = loop {
+            let msg = match pending.pop_front() {
+                Some(msg) => msg,
+                None => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    let msg = match self.evt_chan.1.recv_timeout(remaining) {
+                        Ok(msg) => msg,
+                        Err(e) => break Err(IBKRApiLibError::RecvTimeoutError(e)),
+                    };
+                    #[cfg(feature = "marketdata")]
+                    match self.observe_market_data(&msg) {
+                        Ok(true) => continue,
+                        Ok(false) => (),
+                        Err(e) => break Err(e),
+                    }
+                    msg
+                }
+            };
+            match handle(&msg) {
+                Ok(Collected::Ignored) => ignored.push(msg),
+                Ok(Collected::Consumed) => (),
+                Ok(Collected::Finished) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut backlog = self.backlog.lock().expect(POISONED_MUTEX);
+        backlog.extend(ignored);
+        backlog.extend(pending);
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Wakes the task of waker when the next event arrives, for futures that found nothing for
+    /// them in collect()
+    pub(crate) fn wake_on_event(&self, waker: &Waker) {
+        self.evt_chan.1.register_waker(waker);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Current depth, high water mark and drop counts of the event queue
+    pub fn event_queue_metrics(&self) -> QueueMetrics {
+        self.evt_chan.1.metrics()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Installs an observer that receives the read, decode and dispatch times of every event.
+    /// Times are only captured for connections made after the observer was installed.
+    ///
+    /// # Arguments
+    /// * observer - The observer, or None to stop observing
+    pub fn set_latency_observer(&mut self, observer: Option<Arc<dyn LatencyObserver>>) {
+        self.evt_chan.1.set_latency_observer(observer.clone());
+        self.latency_observer = observer;
+    }
+
     /// Checks connection status
     pub fn is_connected(&self) -> bool {
         let connected = match *self.conn_state.lock().unwrap().deref() {
@@ -205,12 +802,77 @@ impl EClient {
         connected
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// How often frame buffers were reused instead of allocated
+    pub fn buffer_stats(&self) -> PoolStats {
+        self.buffers.stats()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Where the connection is in its lifecycle
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection.state()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A receiver that is woken on every change of connection_state(), for supervision logic
+    /// such as resubscribing after connectivity is restored
+    pub fn watch_connection(&self) -> ConnectionReceiver {
+        self.connection.subscribe()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Paper or live, once the managed accounts list has arrived after connecting
+    pub fn environment(&self) -> Environment {
+        *self.environment.lock().expect(POISONED_MUTEX)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The accounts TWS listed in ManagedAccts after connecting, empty before it has
+    pub fn managed_accounts(&self) -> Vec<AccountId> {
+        self.managed_accounts.lock().expect(POISONED_MUTEX).clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Runs a request for every managed account, one after the other, and returns the results
+    /// with their account.  Stops at the first error.
+    ///
+    /// # Arguments
+    /// * request - Makes the request for one account and waits for its result
+    pub fn for_each_account<T, F>(
+        &mut self,
+        mut request: F,
+    ) -> Result<Vec<(AccountId, T)>, IBKRApiLibError>
+    where
+        F: FnMut(&mut EClient, &AccountId) -> Result<T, IBKRApiLibError>,
+    {
+        let accounts = self.managed_accounts();
+        if accounts.is_empty() {
+            return Err(IBKRApiLibError::Config(
+                "the managed accounts list has not arrived yet".to_string(),
+            ));
+        }
+        accounts
+            .into_iter()
+            .map(|account| {
+                let result = request(self, &account)?;
+                Ok((account, result))
+            })
+            .collect()
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Get the server version (important for checking feature flags for different versions)
     pub fn server_version(&self) -> i32 {
         self.server_version
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Whether the connected server understands a feature.  False before connecting.
+    pub fn supports(&self, feature: Feature) -> bool {
+        ServerVersion(self.server_version).supports(feature)
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Sets server logging level
     pub fn set_server_log_level(&mut self, log_evel: i32) -> Result<(), IBKRApiLibError> {
@@ -244,6 +906,14 @@ impl EClient {
         self.conn_time.clone()
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Gets the time zone of the TWS session, read from the connection time.  None before
+    /// connecting, or if TWS named a zone this crate does not know, in which case times sent
+    /// without a zone name, such as bar dates, decode as None.
+    pub fn tws_time_zone(&self) -> Option<Tz> {
+        zone_of(&self.conn_time)
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Request the current time according to TWS or IB Gateway
     pub fn req_current_time(&mut self) -> Result<(), IBKRApiLibError> {
@@ -269,27 +939,161 @@ impl EClient {
         self.disconnect_requested.store(true, Ordering::Release);
         self.stream.as_mut().unwrap().shutdown(Shutdown::Both)?;
         *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::DISCONNECTED;
+        self.connection.apply(ConnectionEvent::Disconnect);
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Closes the connection after cancelling open subscriptions, waiting up to `timeout` for the
+    /// reader and decoder threads to exit
+    pub fn shutdown(&mut self, timeout: Duration) -> Result<ShutdownReport, IBKRApiLibError> {
+        self.shutdown_with(ShutdownOptions::default().timeout(timeout))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Closes the connection cleanly.  From the start, requests other than cancels fail with
+    /// IBKRApiLibError::ShuttingDown on this handle and all its clones.  Events already received
+    /// can still be read with get_event().
+    pub fn shutdown_with(
+        &mut self,
+        options: ShutdownOptions,
+    ) -> Result<ShutdownReport, IBKRApiLibError> {
+        let mut report = ShutdownReport::default();
+        if !self.is_connected() {
+            info!("Already disconnected...");
+            return Ok(report);
+        }
+        info!("Shutdown requested.  Draining...");
+        self.shutting_down.store(true, Ordering::Release);
+        let deadline = Instant::now() + options.timeout;
+
+        if options.cancel_subscriptions {
+            for context in self.open_subscriptions() {
+                match self.cancel_subscription(&context) {
+                    Ok(()) => report.cancelled.push((context.req_id, context.kind)),
+                    Err(err) => {
+                        report
+                            .failed_cancels
+                            .push((context.req_id, context.kind, err.to_string()))
+                    }
+                }
+            }
+        }
+
+        // writes from other handles finish before the socket is flushed and closed
+        {
+            let _guard = self
+                .send_lock
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Err(err) = self.stream.as_mut().unwrap().flush() {
+                report.flush_error = Some(err.to_string());
+            }
+            self.disconnect_requested.store(true, Ordering::Release);
+            self.stream.as_mut().unwrap().shutdown(Shutdown::Both)?;
+        }
+        *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::DISCONNECTED;
+        self.connection.apply(ConnectionEvent::Disconnect);
+
+        while self.threads.iter().any(|handle| !handle.is_finished()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let (finished, running): (Vec<_>, Vec<_>) = self
+            .threads
+            .drain(..)
+            .partition(|handle| handle.is_finished());
+        for handle in finished {
+            if handle.join().is_err() {
+                warn!("a connection thread panicked");
+            }
+        }
+        // threads that missed the deadline are left to exit on their own
+        report.threads_running = running.len();
+        report.undelivered_events =
+            self.evt_chan.1.metrics().depth + self.backlog.lock().expect(POISONED_MUTEX).len();
+        info!(?report, "Shut down");
+        Ok(report)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Streaming requests that are still open, in the order they were made
+    fn open_subscriptions(&self) -> Vec<RequestContext> {
+        let active = self.correlations.active();
+        // req_mkt_data() takes the caller's ids, which are not in the registry
+        #[cfg(feature = "marketdata")]
+        let active = {
+            let mut active = active;
+            let mut market_data: Vec<i32> = self
+                .market_data
+                .lock()
+                .expect(POISONED_MUTEX)
+                .keys()
+                .filter(|req_id| !active.iter().any(|context| context.req_id == **req_id))
+                .copied()
+                .collect();
+            market_data.sort_unstable();
+            active.extend(market_data.into_iter().map(|req_id| RequestContext {
+                req_id,
+                kind: RequestKind::MarketData,
+                description: String::new(),
+                issued_at: Instant::now(),
+                cancelled_at: None,
+            }));
+            active
+        };
+        active
+            .into_iter()
+            .filter(|context| cancel_message(&context.kind).is_some())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn cancel_subscription(&mut self, context: &RequestContext) -> Result<(), IBKRApiLibError> {
+        let req_id = context.req_id;
+        match cancel_message(&context.kind) {
+            #[cfg(feature = "marketdata")]
+            Some(ServerReqMsgDiscriminants::CancelMktData) => self.cancel_mkt_data(req_id),
+            #[cfg(feature = "marketdata")]
+            Some(ServerReqMsgDiscriminants::CancelTickByTickData) => {
+                self.cancel_tick_by_tick_data(req_id)
+            }
+            #[cfg(feature = "marketdata")]
+            Some(ServerReqMsgDiscriminants::CancelHistoricalData) => {
+                self.cancel_historical_data(req_id)
+            }
+            #[cfg(feature = "marketdata")]
+            Some(ServerReqMsgDiscriminants::CancelRealTimeBars) => {
+                self.cancel_real_time_bars(req_id)
+            }
+            #[cfg(feature = "marketdata")]
+            Some(ServerReqMsgDiscriminants::CancelScannerSubscription) => {
+                self.cancel_scanner_subscription(req_id)
+            }
+            Some(ServerReqMsgDiscriminants::CancelAccountSummary) => {
+                self.cancel_account_summary(req_id)
+            }
+            Some(ServerReqMsgDiscriminants::CancelPositionsMulti) => {
+                self.cancel_positions_multi(req_id)
+            }
+            Some(ServerReqMsgDiscriminants::CancelAccountUpdatesMulti) => {
+                self.cancel_account_updates_multi(req_id)
+            }
+            Some(ServerReqMsgDiscriminants::CancelPnl) => self.cancel_pnl(req_id),
+            Some(ServerReqMsgDiscriminants::CancelPnlSingle) => self.cancel_pnl_single(req_id),
+            _ => Ok(()),
+        }
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Initiates the message exchange between the client application and the TWS/IB Gateway
     fn start_api(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        let version = 2;
-        let mut opt_capab = "".to_string();
-        if self.server_version >= MIN_SERVER_VER_OPTIONAL_CAPABILITIES as i32 {
-            opt_capab = make_field(&self.opt_capab)?;
-        }
-
-        let msg = format!(
-            "{}{}{}{}",
-            make_field(&mut (Some(ServerReqMsgDiscriminants::StartApi).unwrap() as i32))?,
-            make_field(&mut version.to_string())?,
-            make_field(&mut self.client_id.to_string())?,
-            opt_capab
-        );
+        let msg = handshake::start_api(
+            self.client_id,
+            ServerVersion(self.server_version),
+            &self.opt_capab,
+        )?;
 
         self.send_request(msg.as_str())?;
         Ok(())
@@ -318,6 +1122,8 @@ impl EClient {
     /// * regulatory_snapshot - With the US Value Snapshot Bundle for stocks,
     ///                         regulatory snapshots are available for 0.01 USD each.
     /// * mkt_data_options - For internal use only. Use default value XYZ.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_mkt_data(
         &mut self,
         req_id: i32,
@@ -329,7 +1135,7 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_DELTA_NEUTRAL {
+        if !self.supports(Feature::DeltaNeutral) {
             if let Some(_value) = &contract.delta_neutral_contract {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -345,7 +1151,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() < MIN_SERVER_VER_REQ_MKT_DATA_CONID && contract.con_id > 0 {
+        if !self.supports(Feature::ReqMktDataConid) && contract.con_id > 0 {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::NotConnected.code().to_string(),
@@ -354,7 +1160,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS && "" != contract.trading_class {
+        if !self.supports(Feature::TradingClass) && "" != contract.trading_class {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -380,7 +1186,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         // send contract fields
-        if self.server_version() >= MIN_SERVER_VER_REQ_MKT_DATA_CONID {
+        if self.supports(Feature::ReqMktDataConid) {
             msg.push_str(&make_field(&contract.con_id)?);
             msg.push_str(&make_field(&contract.symbol)?);
 
@@ -395,7 +1201,7 @@ impl EClient {
             msg.push_str(&make_field(&contract.local_symbol)?); //  srv v2 and above
         }
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
         // Send combo legs for BAG requests(srv v8 and above)
@@ -410,7 +1216,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() >= MIN_SERVER_VER_DELTA_NEUTRAL {
+        if self.supports(Feature::DeltaNeutral) {
             if contract.delta_neutral_contract.is_some() {
                 msg.push_str(&make_field(&true)?);
                 msg.push_str(&make_field(
@@ -430,12 +1236,12 @@ impl EClient {
             msg.push_str(&make_field(&snapshot)?); // srv v35 and above
         }
 
-        if self.server_version() >= MIN_SERVER_VER_REQ_SMART_COMPONENTS {
+        if self.supports(Feature::ReqSmartComponents) {
             msg.push_str(&make_field(&regulatory_snapshot)?);
         }
 
         // send mktDataOptions parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             // current doc says this part is for "internal use only" -> won't support it
             if mkt_data_options.len() > 0 {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -455,17 +1261,73 @@ impl EClient {
         }
 
         self.send_request(msg.as_str())?;
+        self.market_data.lock().expect(POISONED_MUTEX).insert(
+            req_id,
+            MarketDataRequest {
+                contract: contract.clone(),
+                generic_tick_list: generic_tick_list.to_string(),
+                snapshot,
+                regulatory_snapshot,
+                mkt_data_options,
+                mode: None,
+                fell_back: false,
+            },
+        );
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Same as req_mkt_data(), taking the generic ticks as a list of GenericTickType
+    ///
+    /// # Arguments
+    /// * req_id - The request id. Must be a unique value.
+    /// * contract - The contract for which market data is being requested
+    /// * generic_ticks - The generic ticks to add to the default ones
+    /// * snapshot - Return a single snapshot of market data and cancel the subscription
+    /// * regulatory_snapshot - Request a regulatory snapshot (US stocks, 0.01 USD each)
+    /// * mkt_data_options - For internal use only
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
+    pub fn req_mkt_data_with_ticks(
+        &mut self,
+        req_id: i32,
+        contract: &Contract,
+        generic_ticks: &[GenericTickType],
+        snapshot: bool,
+        regulatory_snapshot: bool,
+        mkt_data_options: Vec<TagValue>,
+    ) -> Result<(), IBKRApiLibError> {
+        self.req_mkt_data(
+            req_id,
+            contract,
+            &GenericTickType::to_list(generic_ticks),
+            snapshot,
+            regulatory_snapshot,
+            mkt_data_options,
+        )
+    }
+
     //----------------------------------------------------------------------------------------------
     /// After calling this function, market data for the specified id will stop flowing.
     ///
     /// # Arguments
     /// * req_id - The ID that was specified in the call to req_mkt_data()
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_mkt_data(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
+        self.send_cancel_mkt_data(req_id)?;
+        self.correlations.cancel(req_id);
+        self.market_data
+            .lock()
+            .expect(POISONED_MUTEX)
+            .remove(&req_id);
+        Ok(())
+    }
 
+    //----------------------------------------------------------------------------------------------
+    #[cfg(feature = "marketdata")]
+    fn send_cancel_mkt_data(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         let version = 2;
 
         let message_id: i32 = ServerReqMsgDiscriminants::CancelMktData as i32;
@@ -478,6 +1340,73 @@ impl EClient {
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests a market data snapshot and returns a future resolving to its quote once TWS has
+    /// sent all of its ticks
+    ///
+    /// # Arguments
+    /// * contract - The contract to get a quote for
+    #[cfg(feature = "marketdata")]
+    pub fn snapshot(&mut self, contract: &Contract) -> Result<Snapshot<'_>, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::MarketData, &contract.symbol);
+        if let Err(err) = self.req_mkt_data(req_id, contract, "", true, false, vec![]) {
+            self.correlations.complete(req_id);
+            return Err(err);
+        }
+        Ok(Snapshot::new(self, req_id))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the Shortable generic tick (236) of a contract and waits for its indicator and
+    /// number of shares.  Generic ticks cannot be part of a snapshot, so the market data line is
+    /// streamed and cancelled once both have arrived.  If only the indicator arrives before the
+    /// timeout, the shortability is returned without available_shares.
+    ///
+    /// # Arguments
+    /// * contract - The contract to check, usually a stock
+    /// * timeout - How long to wait for the ticks
+    #[cfg(feature = "marketdata")]
+    pub fn shortability(
+        &mut self,
+        contract: &Contract,
+        timeout: Duration,
+    ) -> Result<Shortability, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::MarketData, &contract.symbol);
+        self.req_mkt_data(
+            req_id,
+            contract,
+            &GenericTickType::Shortable.to_string(),
+            false,
+            false,
+            vec![],
+        )?;
+
+        let mut ticks = ShortabilityTicks::new(req_id);
+        let result = self.collect(timeout, |msg| match msg {
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ if ticks.update(msg) => Ok(if ticks.is_complete() {
+                Collected::Finished
+            } else {
+                Collected::Consumed
+            }),
+            _ => Ok(Collected::Ignored),
+        });
+        self.cancel_mkt_data(req_id)?;
+
+        match (result, ticks.shortability()) {
+            (Ok(()), Some(shortability))
+            | (Err(IBKRApiLibError::RecvTimeoutError(_)), Some(shortability)) => Ok(shortability),
+            (Err(err), _) => Err(err),
+            (Ok(()), None) => Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)),
+        }
+    }
+
     //----------------------------------------------------------------------------------------------
     /// The API can receive frozen market data from Trader
     /// Workstation. Frozen market data is the last data recorded in our system.
@@ -491,23 +1420,10 @@ impl EClient {
     /// * market_data_type
     /// * 1 for real-time streaming market data
     /// * 2 for frozen market data
+    #[cfg(feature = "marketdata")]
     pub fn req_market_data_type(&mut self, market_data_type: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_MARKET_DATA_TYPE {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support market data type requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let mut msg = "".to_string();
         let version = 1;
         let message_id = ServerReqMsgDiscriminants::ReqMarketDataType as i32;
@@ -517,6 +1433,132 @@ impl EClient {
         msg.push_str(&make_field(&market_data_type)?);
 
         self.send_request(msg.as_str())?;
+        if let Some(mode) = MarketDataMode::from_i32(market_data_type) {
+            *self.market_data_mode.lock().expect(POISONED_MUTEX) = mode;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Same as req_market_data_type(), taking a MarketDataMode
+    #[cfg(feature = "marketdata")]
+    pub fn req_market_data_mode(&mut self, mode: MarketDataMode) -> Result<(), IBKRApiLibError> {
+        self.req_market_data_type(mode as i32)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The mode TWS last reported for a subscription made with req_mkt_data(), or None before
+    /// it has reported one
+    #[cfg(feature = "marketdata")]
+    pub fn market_data_mode(&self, req_id: i32) -> Option<MarketDataMode> {
+        self.market_data
+            .lock()
+            .expect(POISONED_MUTEX)
+            .get(&req_id)
+            .and_then(|request| request.mode)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Follows the market data subscriptions through the events read from the queue, and
+    /// repeats a subscription with delayed data when it lacks permissions.  Returns true if the
+    /// event was handled and should not be passed on.
+    #[cfg(feature = "marketdata")]
+    fn observe_market_data(&self, msg: &ServerRspMsg) -> Result<bool, IBKRApiLibError> {
+        let mut market_data = self.market_data.lock().expect(POISONED_MUTEX);
+        let (req_id, error_code) = match msg {
+            ServerRspMsg::ErrMsg {
+                error_code,
+                error_str,
+                ..
+            } if self.resubscribe_after_data_loss
+                && ConnectivityNotice::from_error(*error_code, error_str)
+                    == Some(ConnectivityNotice::Restored {
+                        data_maintained: false,
+                    }) =>
+            {
+                let streams: Vec<(i32, MarketDataRequest)> = market_data
+                    .iter()
+                    .filter(|(_, request)| !request.snapshot && !request.regulatory_snapshot)
+                    .map(|(req_id, request)| (*req_id, request.clone()))
+                    .collect();
+                drop(market_data);
+                self.resubscribe_market_data(streams)?;
+                // handlers still hear about the restored connection
+                return Ok(false);
+            }
+            ServerRspMsg::MarketDataType {
+                req_id,
+                market_data_type,
+            } => {
+                if let Some(request) = market_data.get_mut(req_id) {
+                    request.mode = MarketDataMode::from_i32(*market_data_type);
+                }
+                return Ok(false);
+            }
+            ServerRspMsg::TickSnapshotEnd { req_id } => {
+                market_data.remove(req_id);
+                return Ok(false);
+            }
+            ServerRspMsg::ErrMsg {
+                req_id, error_code, ..
+            } if self.delayed_fallback && NO_MARKET_DATA_PERMISSIONS.contains(error_code) => {
+                (*req_id, *error_code)
+            }
+            _ => return Ok(false),
+        };
+        let request = match market_data.get_mut(&req_id) {
+            Some(request) if !request.fell_back => {
+                request.fell_back = true;
+                request.clone()
+            }
+            _ => return Ok(false),
+        };
+        drop(market_data);
+
+        let mode = self
+            .market_data_mode
+            .lock()
+            .expect(POISONED_MUTEX)
+            .delayed();
+        info!(req_id, error_code, %mode, "no market data permissions, falling back");
+        let mut sender = self.try_clone()?;
+        // 354 ends the subscription, 10167 leaves it running
+        if error_code != 354 {
+            sender.send_cancel_mkt_data(req_id)?;
+        }
+        sender.req_market_data_mode(mode)?;
+        sender.req_mkt_data(
+            req_id,
+            &request.contract,
+            &request.generic_tick_list,
+            request.snapshot,
+            request.regulatory_snapshot,
+            request.mkt_data_options,
+        )?;
+        Ok(true)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests market data again for subscriptions TWS dropped.  The market data type set with
+    /// req_market_data_type() belongs to the API session, which survived, so it is not repeated.
+    #[cfg(feature = "marketdata")]
+    fn resubscribe_market_data(
+        &self,
+        mut streams: Vec<(i32, MarketDataRequest)>,
+    ) -> Result<(), IBKRApiLibError> {
+        streams.sort_by_key(|(req_id, _)| *req_id);
+        info!(count = streams.len(), "market data lost, resubscribing");
+        let mut sender = self.try_clone()?;
+        for (req_id, request) in streams {
+            sender.req_mkt_data(
+                req_id,
+                &request.contract,
+                &request.generic_tick_list,
+                false,
+                false,
+                request.mkt_data_options,
+            )?;
+        }
         Ok(())
     }
 
@@ -527,26 +1569,13 @@ impl EClient {
     ///            market data returns, it will be identified by this tag. This is
     ///            also used when canceling the market data.
     /// * bbo_exchange - mapping identifier received from Wrapper::tick_req_params
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_smart_components(
-        &mut self,
-        req_id: i32,
-        bbo_exchange: &str,
-    ) -> Result<(), IBKRApiLibError> {
-        self.check_connected(req_id)?;
-
-        if self.server_version() < MIN_SERVER_VER_REQ_SMART_COMPONENTS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support smart components request."
-                ),
-            ));
-
-            return Err(err);
-        }
+        &mut self,
+        req_id: i32,
+        bbo_exchange: &str,
+    ) -> Result<(), IBKRApiLibError> {
+        self.check_connected(req_id)?;
 
         let mut msg = "".to_string();
 
@@ -560,6 +1589,43 @@ impl EClient {
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests the exchanges behind the letters of a BBO exchange and waits for them
+    ///
+    /// # Arguments
+    /// * bbo_exchange - The BBO exchange of a TickReqParams event
+    /// * timeout - How long to wait for the answer
+    #[cfg(feature = "marketdata")]
+    pub fn smart_components(
+        &mut self,
+        bbo_exchange: &str,
+        timeout: Duration,
+    ) -> Result<SmartComponentMap, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::SmartComponents, bbo_exchange);
+        self.req_smart_components(req_id, bbo_exchange)?;
+
+        let mut map = SmartComponentMap::default();
+        self.collect(timeout, |msg| match msg {
+            ServerRspMsg::SmartComponents {
+                req_id: id,
+                smart_components,
+            } if *id == req_id => {
+                map = SmartComponentMap::new(bbo_exchange, smart_components);
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        })?;
+        self.correlations.complete(req_id);
+        Ok(map)
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Requests details about a given market rule
     /// The market rule for an instrument on a particular exchange provides details about how the
@@ -572,20 +1638,6 @@ impl EClient {
     pub fn req_market_rule(&mut self, market_rule_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_MARKET_RULES {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support market rule requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let mut msg = "".to_string();
 
         let message_id = ServerReqMsgDiscriminants::ReqMarketRule as i32;
@@ -603,9 +1655,12 @@ impl EClient {
     /// # Arguments
     /// * req_id	- unique identifier of the request.
     /// * contract	- the contract for which tick-by-tick data is requested.
-    /// * tick_type	- TickByTickType data type: "Last", "AllLast", "BidAsk" or "MidPoint".
-    /// * number_of_ticks	- number of ticks.
+    /// * tick_type	- TickByTickType data type: "Last", "AllLast", "BidAsk" or "MidPoint".  The
+    ///   ticks arrive as TickByTick events with the matching TickByTickData payload.
+    /// * number_of_ticks	- number of historical ticks sent before the live ticks.
     /// * ignore_size	- ignore size flag.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_tick_by_tick_data(
         &mut self,
         req_id: i32,
@@ -616,28 +1671,28 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_TICK_BY_TICK {
+        if !self.supports(Feature::TickByTickIgnoreSize) && (number_of_ticks != 0 || ignore_size) {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
                 format!(
                     "{}{}",
                     TwsError::UpdateTws.message(),
-                    " It does not support tick-by-tick data requests."
+                    " It does not support ignore_size and number_of_ticks parameters in tick-by-tick data requests."
                 ),
             ));
 
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE {
+        if tick_type == TickByTickType::NA {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
-                TwsError::UpdateTws.code().to_string(),
+                TwsError::BadMessage.code().to_string(),
                 format!(
                     "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support ignore_size and number_of_ticks parameters in tick-by-tick data requests."
+                    TwsError::BadMessage.message(),
+                    " A tick-by-tick request needs a type: Last, AllLast, BidAsk or MidPoint."
                 ),
             ));
 
@@ -666,7 +1721,7 @@ impl EClient {
         msg.push_str(&make_field(&contract.trading_class)?);
         msg.push_str(&make_field(&(tick_type.to_string()))?);
 
-        if self.server_version() >= MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE {
+        if self.supports(Feature::TickByTickIgnoreSize) {
             msg.push_str(&make_field(&number_of_ticks)?);
             msg.push_str(&make_field(&ignore_size)?);
         }
@@ -680,23 +1735,11 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id	- The identifier of the original request.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_tick_by_tick_data(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_TICK_BY_TICK {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support tick-by-tick data requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let mut msg = "".to_string();
 
         let message_id = ServerReqMsgDiscriminants::CancelTickByTickData as i32;
@@ -705,6 +1748,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -722,6 +1766,8 @@ impl EClient {
     /// * option_price - The price of the option.
     /// * under_price - Price of the underlying.
     /// * impl_vol_options - Implied volatility options.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn calculate_implied_volatility(
         &mut self,
         req_id: i32,
@@ -732,21 +1778,7 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_CALC_IMPLIED_VOLAT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support calculate_implied_volatility req."
-                ),
-            ));
-
-            return Err(err);
-        }
-
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS && "" != contract.trading_class {
+        if !self.supports(Feature::TradingClass) && "" != contract.trading_class {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -784,14 +1816,14 @@ impl EClient {
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
 
         msg.push_str(&make_field(&option_price)?);
         msg.push_str(&make_field(&under_price)?);
 
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let mut impl_vol_opt_str = "".to_string();
             let tag_values_count = impl_vol_options.len();
             if tag_values_count > 0 {
@@ -817,6 +1849,8 @@ impl EClient {
     /// * contract - Describes the contract.
     /// * volatility - The volatility.
     /// * under_price - Price of the underlying.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn calculate_option_price(
         &mut self,
         req_id: i32,
@@ -827,21 +1861,7 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_CALC_IMPLIED_VOLAT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support calculateImpliedVolatility req."
-                ),
-            ));
-
-            return Err(err);
-        }
-
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             if "" != contract.trading_class {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -880,14 +1900,14 @@ impl EClient {
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
 
         msg.push_str(&make_field(&volatility)?);
         msg.push_str(&make_field(&under_price)?);
 
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let _opt_prc_opt_str = "".to_string();
             let tag_values_count = opt_prc_options.len();
             if tag_values_count > 0 {
@@ -910,23 +1930,11 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The original request id.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_calculate_option_price(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_CALC_IMPLIED_VOLAT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support calculateImpliedVolatility req."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let mut msg = "".to_string();
@@ -938,6 +1946,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -946,26 +1955,14 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The original request id.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_calculate_implied_volatility(
         &mut self,
         req_id: i32,
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_CALC_IMPLIED_VOLAT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support calculateImpliedVolatility req."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let mut msg = "".to_string();
@@ -977,9 +1974,58 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Asks TWS for the implied volatility of an option at a price.  The returned future resolves
+    /// to the greeks of the computation and cancels it afterwards.
+    ///
+    /// # Arguments
+    /// * contract - The option
+    /// * option_price - The price of the option
+    /// * under_price - The price of the underlying
+    #[cfg(feature = "marketdata")]
+    pub fn implied_volatility(
+        &mut self,
+        contract: &Contract,
+        option_price: f64,
+        under_price: f64,
+    ) -> Result<OptionCalculation<'_>, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::OptionCalculation, &contract.symbol);
+        self.calculate_implied_volatility(req_id, contract, option_price, under_price, vec![])?;
+        Ok(OptionCalculation::new(
+            self,
+            req_id,
+            Calculation::ImpliedVolatility,
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Asks TWS for the price and greeks of an option at a volatility.  The returned future
+    /// resolves to the greeks of the computation and cancels it afterwards.
+    ///
+    /// # Arguments
+    /// * contract - The option
+    /// * volatility - The volatility
+    /// * under_price - The price of the underlying
+    #[cfg(feature = "marketdata")]
+    pub fn option_price(
+        &mut self,
+        contract: &Contract,
+        volatility: f64,
+        under_price: f64,
+    ) -> Result<OptionCalculation<'_>, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::OptionCalculation, &contract.symbol);
+        self.calculate_option_price(req_id, contract, volatility, under_price, vec![])?;
+        Ok(OptionCalculation::new(
+            self,
+            req_id,
+            Calculation::OptionPrice,
+        ))
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Call this function to excercise options
     ///
@@ -999,6 +2045,8 @@ impl EClient {
     ///              Values are:
     ///      * 0 = no
     ///      * 1 = yes.
+    #[cfg(feature = "orders")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn exercise_options(
         &mut self,
         req_id: i32,
@@ -1007,28 +2055,80 @@ impl EClient {
         exercise_quantity: i32,
         account: &String,
         over_ride: i32,
+    ) -> Result<(), IBKRApiLibError> {
+        let action = match exercise_action {
+            1 => ExerciseAction::Exercise,
+            2 => ExerciseAction::Lapse,
+            other => {
+                return Err(IBKRApiLibError::Config(format!(
+                    "invalid exercise action {}, expected 1 to exercise or 2 to lapse",
+                    other
+                )))
+            }
+        };
+        let request = ExerciseOptions::new(action, exercise_quantity, account, over_ride != 0);
+        self.req_exercise_options(req_id, contract, &request)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Exercises options or lets them lapse, and returns the request id errors are reported
+    /// with.
+    ///
+    /// # Arguments
+    /// * contract - The option contract
+    /// * action - Exercise, or let the options lapse
+    /// * quantity - Number of contracts
+    /// * account - Account holding the options
+    /// * over_ride - Act against the natural action, for example exercise an option that is
+    ///   out of the money
+    #[cfg(feature = "orders")]
+    pub fn exercise_option(
+        &mut self,
+        contract: &Contract,
+        action: ExerciseAction,
+        quantity: i32,
+        account: &str,
+        over_ride: bool,
+    ) -> Result<i32, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::ExerciseOptions, &contract.local_symbol);
+        let request = ExerciseOptions::new(action, quantity, account, over_ride);
+        self.req_exercise_options(req_id, contract, &request)?;
+        Ok(req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sends an exercise request under a request id of the caller's choosing
+    ///
+    /// # Arguments
+    /// * req_id - The request id. Must be a unique value.
+    /// * contract - The option contract
+    /// * request - What to exercise and how
+    #[cfg(feature = "orders")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
+    pub fn req_exercise_options(
+        &mut self,
+        req_id: i32,
+        contract: &Contract,
+        request: &ExerciseOptions,
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
-            if !contract.trading_class.is_empty() {
-                let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                    req_id,
-                    TwsError::UpdateTws.code().to_string(),
-                    format!(
-                        "{}{}",
-                        TwsError::UpdateTws.message(),
-                        " It does not support con_id, multiplier, trading_class parameter in exercise_options."
-                    ),
-                ));
+        if !self.supports(Feature::TradingClass) && !contract.trading_class.is_empty() {
+            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                req_id,
+                TwsError::UpdateTws.code().to_string(),
+                format!(
+                    "{}{}",
+                    TwsError::UpdateTws.message(),
+                    " It does not support con_id, multiplier, trading_class parameter in exercise_options."
+                ),
+            ));
 
-                return Err(err);
-            }
+            return Err(err);
         }
 
         let version = 2;
 
-        // send req mkt data msg
         let mut msg = "".to_string();
 
         let message_id = ServerReqMsgDiscriminants::ExerciseOptions as i32;
@@ -1039,7 +2139,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         // send contract fields
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.con_id)?);
         }
         msg.push_str(&make_field(&contract.symbol)?);
@@ -1051,13 +2151,13 @@ impl EClient {
         msg.push_str(&make_field(&contract.exchange)?);
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
-        msg.push_str(&make_field(&exercise_action)?);
-        msg.push_str(&make_field(&exercise_quantity)?);
-        msg.push_str(&make_field(account)?);
-        msg.push_str(&make_field(&over_ride)?);
+        msg.push_str(&make_field(&(request.action as i32))?);
+        msg.push_str(&make_field(&request.quantity)?);
+        msg.push_str(&make_field(&request.account)?);
+        msg.push_str(&make_field(&(request.over_ride as i32))?);
 
         self.send_request(msg.as_str())?;
         Ok(())
@@ -1079,6 +2179,8 @@ impl EClient {
     /// * order - This structure contains the details of the order.
     ///
     /// Note: Each client MUST connect with a unique client_id.
+    #[cfg(feature = "orders")]
+    #[instrument(level = "debug", skip_all, fields(order_id = order_id))]
     pub fn place_order(
         &mut self,
         order_id: i32,
@@ -1087,7 +2189,20 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_DELTA_NEUTRAL {
+        if self.require_paper && self.environment() != Environment::Paper {
+            return Err(IBKRApiLibError::Config(format!(
+                "order {} refused: paper trading is required but the environment is {:?}",
+                order_id,
+                self.environment()
+            )));
+        }
+
+        if is_crypto(contract) {
+            validate_crypto_order(order)?;
+        }
+        validate_trailing_order(order)?;
+
+        if !self.supports(Feature::DeltaNeutral) {
             if contract.delta_neutral_contract.is_some() {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     NO_VALID_ID,
@@ -1103,9 +2218,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() < MIN_SERVER_VER_SCALE_ORDERS2
-            && order.scale_subs_level_size != UNSET_INTEGER
-        {
+        if !self.supports(Feature::ScaleOrders2) && order.scale_subs_level_size != UNSET_INTEGER {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1119,7 +2232,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_ALGO_ORDERS && !order.algo_strategy.is_empty() {
+        if !self.supports(Feature::AlgoOrders) && !order.algo_strategy.is_empty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1133,7 +2246,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_NOT_HELD && order.not_held {
+        if !self.supports(Feature::NotHeld) && order.not_held {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1147,7 +2260,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_SEC_ID_TYPE
+        if !self.supports(Feature::SecIdType)
             && (!contract.sec_id_type.is_empty() || !contract.sec_id.is_empty())
         {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -1163,7 +2276,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_PLACE_ORDER_CONID && contract.con_id > 0 {
+        if !self.supports(Feature::PlaceOrderConid) && contract.con_id > 0 {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1177,7 +2290,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_SSHORTX {
+        if !self.supports(Feature::Sshortx) {
             if order.exempt_code != -1 {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     order_id,
@@ -1207,7 +2320,7 @@ impl EClient {
                 return Err(err);
             }
         }
-        if self.server_version() < MIN_SERVER_VER_HEDGE_ORDERS && !order.hedge_type.is_empty() {
+        if !self.supports(Feature::HedgeOrders) && !order.hedge_type.is_empty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1221,9 +2334,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_OPT_OUT_SMART_ROUTING
-            && order.opt_out_smart_routing
-        {
+        if !self.supports(Feature::OptOutSmartRouting) && order.opt_out_smart_routing {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1237,7 +2348,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_DELTA_NEUTRAL_CONID
+        if !self.supports(Feature::DeltaNeutralConid)
             && (order.delta_neutral_con_id > 0
                 || !order.delta_neutral_settling_firm.is_empty()
                 || !order.delta_neutral_clearing_account.is_empty()
@@ -1256,7 +2367,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_DELTA_NEUTRAL_OPEN_CLOSE
+        if !self.supports(Feature::DeltaNeutralOpenClose)
             && (!order.delta_neutral_open_close.is_empty()
                 || order.delta_neutral_short_sale
                 || order.delta_neutral_short_sale_slot > 0
@@ -1275,7 +2386,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_SCALE_ORDERS3
+        if !self.supports(Feature::ScaleOrders3)
             && order.scale_price_increment > 0 as f64
             && order.scale_price_increment != UNSET_DOUBLE
             && (order.scale_price_adjust_value != UNSET_DOUBLE
@@ -1300,7 +2411,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_ORDER_COMBO_LEGS_PRICE
+        if !self.supports(Feature::OrderComboLegsPrice)
             && contract.sec_type == "BAG"
             && order.order_combo_legs.len() > 0
             && order
@@ -1321,9 +2432,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_TRAILING_PERCENT
-            && order.trailing_percent != UNSET_DOUBLE
-        {
+        if !self.supports(Feature::TrailingPercent) && order.trailing_percent != UNSET_DOUBLE {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1337,9 +2446,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS
-            && !contract.trading_class.is_empty()
-        {
+        if !self.supports(Feature::TradingClass) && !contract.trading_class.is_empty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1353,7 +2460,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_SCALE_TABLE
+        if !self.supports(Feature::ScaleTable)
             && (!order.scale_table.is_empty()
                 || !order.active_start_time.is_empty()
                 || !order.active_stop_time.is_empty())
@@ -1371,7 +2478,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_ALGO_ID && order.algo_id != "" {
+        if !self.supports(Feature::AlgoId) && order.algo_id != "" {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1385,7 +2492,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_ORDER_SOLICITED && order.solicited {
+        if !self.supports(Feature::OrderSolicited) && order.solicited {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1399,7 +2506,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_MODELS_SUPPORT && !order.model_code.is_empty() {
+        if !self.supports(Feature::ModelsSupport) && !order.model_code.is_empty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1413,7 +2520,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_EXT_OPERATOR && !order.ext_operator.is_empty() {
+        if !self.supports(Feature::ExtOperator) && !order.ext_operator.is_empty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1427,7 +2534,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_SOFT_DOLLAR_TIER
+        if !self.supports(Feature::SoftDollarTier)
             && (!order.soft_dollar_tier.name.is_empty() || !order.soft_dollar_tier.val.is_empty())
         {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -1443,7 +2550,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_CASH_QTY && order.cash_qty != 0.0 {
+        if !self.supports(Feature::CashQty) && order.has_cash_qty() {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1457,7 +2564,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_DECISION_MAKER
+        if !self.supports(Feature::DecisionMaker)
             && (!order.mifid2decision_maker.is_empty() || !order.mifid2decision_algo.is_empty())
         {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -1473,7 +2580,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_MIFID_EXECUTION
+        if !self.supports(Feature::MifidExecution)
             && (!order.mifid2execution_trader.is_empty() || !order.mifid2execution_algo.is_empty())
         {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -1489,9 +2596,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_AUTO_PRICE_FOR_HEDGE
-            && order.dont_use_auto_price_for_hedge
-        {
+        if !self.supports(Feature::AutoPriceForHedge) && order.dont_use_auto_price_for_hedge {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1505,7 +2610,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_ORDER_CONTAINER && order.is_oms_container {
+        if !self.supports(Feature::OrderContainer) && order.is_oms_container {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1519,7 +2624,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_PRICE_MGMT_ALGO && order.use_price_mgmt_algo {
+        if !self.supports(Feature::PriceMgmtAlgo) && order.use_price_mgmt_algo {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 order_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -1533,7 +2638,7 @@ impl EClient {
             return Err(err);
         }
 
-        let version: i32 = if self.server_version() < MIN_SERVER_VER_NOT_HELD {
+        let version: i32 = if !self.supports(Feature::NotHeld) {
             27
         } else {
             45
@@ -1546,14 +2651,14 @@ impl EClient {
 
         msg.push_str(&make_field(&message_id)?);
 
-        if self.server_version() < MIN_SERVER_VER_ORDER_CONTAINER {
+        if !self.supports(Feature::OrderContainer) {
             msg.push_str(&make_field(&version)?);
         }
 
         msg.push_str(&make_field(&order_id)?);
 
         // send contract fields
-        if self.server_version() >= MIN_SERVER_VER_PLACE_ORDER_CONID {
+        if self.supports(Feature::PlaceOrderConid) {
             msg.push_str(&make_field(&contract.con_id)?);
         }
         msg.push_str(&make_field(&contract.symbol)?);
@@ -1567,11 +2672,11 @@ impl EClient {
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?); // srv v2 && above
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_SEC_ID_TYPE {
+        if self.supports(Feature::SecIdType) {
             msg.push_str(&make_field(&contract.sec_id_type)?);
             msg.push_str(&make_field(&contract.sec_id)?);
         }
@@ -1579,7 +2684,7 @@ impl EClient {
         // send main order fields
         msg.push_str(&make_field(&order.action)?);
 
-        if self.server_version() >= MIN_SERVER_VER_FRACTIONAL_POSITIONS {
+        if self.supports(Feature::FractionalPositions) {
             msg.push_str(&make_field(&order.total_quantity)?);
         } else {
             msg.push_str(&make_field(&(order.total_quantity as i32))?);
@@ -1587,7 +2692,7 @@ impl EClient {
 
         msg.push_str(&make_field(&order.order_type)?);
 
-        if self.server_version() < MIN_SERVER_VER_ORDER_COMBO_LEGS_PRICE {
+        if !self.supports(Feature::OrderComboLegsPrice) {
             msg.push_str(&make_field(if order.lmt_price != UNSET_DOUBLE {
                 &order.lmt_price
             } else {
@@ -1597,7 +2702,7 @@ impl EClient {
             msg.push_str(&make_field_handle_empty(&order.lmt_price)?);
         }
 
-        if self.server_version() < MIN_SERVER_VER_TRAILING_PERCENT {
+        if !self.supports(Feature::TrailingPercent) {
             msg.push_str(&make_field(if order.aux_price != UNSET_DOUBLE {
                 &order.aux_price
             } else {
@@ -1636,7 +2741,7 @@ impl EClient {
                     msg.push_str(&make_field(&(combo_leg.open_close as i32))?);
                     msg.push_str(&make_field(&combo_leg.short_sale_slot)?); //srv v35 && above
                     msg.push_str(&make_field(&combo_leg.designated_location)?); // srv v35 && above
-                    if self.server_version() >= MIN_SERVER_VER_SSHORTX_OLD {
+                    if self.supports(Feature::SshortxOld) {
                         msg.push_str(&make_field(&combo_leg.exempt_code)?);
                     }
                 }
@@ -1644,9 +2749,7 @@ impl EClient {
         }
 
         // Send order combo legs for BAG requests
-        if self.server_version() >= MIN_SERVER_VER_ORDER_COMBO_LEGS_PRICE
-            && contract.sec_type == "BAG"
-        {
+        if self.supports(Feature::OrderComboLegsPrice) && contract.sec_type == "BAG" {
             let order_combo_legs_count = order.order_combo_legs.len();
 
             msg.push_str(&make_field(&order_combo_legs_count)?);
@@ -1657,9 +2760,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() >= MIN_SERVER_VER_SMART_COMBO_ROUTING_PARAMS
-            && contract.sec_type == "BAG"
-        {
+        if self.supports(Feature::SmartComboRoutingParams) && contract.sec_type == "BAG" {
             let smart_combo_routing_params_count = order.smart_combo_routing_params.len();
             msg.push_str(&make_field(&smart_combo_routing_params_count)?);
             if smart_combo_routing_params_count > 0 {
@@ -1695,7 +2796,7 @@ impl EClient {
         msg.push_str(&make_field(&order.fa_percentage)?); // srv v13 && above
         msg.push_str(&make_field(&order.fa_profile)?); // srv v13 && above
 
-        if self.server_version() >= MIN_SERVER_VER_MODELS_SUPPORT {
+        if self.supports(Feature::ModelsSupport) {
             msg.push_str(&make_field(&order.model_code)?);
         }
 
@@ -1703,7 +2804,7 @@ impl EClient {
         msg.push_str(&make_field(&order.short_sale_slot)?); // 0 for retail, 1 || 2 for institutions
         msg.push_str(&make_field(&order.designated_location)?); // populate only when shortSaleSlot = 2.
 
-        if self.server_version() >= MIN_SERVER_VER_SSHORTX_OLD {
+        if self.supports(Feature::SshortxOld) {
             msg.push_str(&make_field(&order.exempt_code)?);
         }
 
@@ -1739,18 +2840,14 @@ impl EClient {
         msg.push_str(&make_field(&order.delta_neutral_order_type)?); // srv v28 && above
         msg.push_str(&make_field_handle_empty(&order.delta_neutral_aux_price)?); // srv v28 && above
 
-        if self.server_version() >= MIN_SERVER_VER_DELTA_NEUTRAL_CONID
-            && !order.delta_neutral_order_type.is_empty()
-        {
+        if self.supports(Feature::DeltaNeutralConid) && !order.delta_neutral_order_type.is_empty() {
             msg.push_str(&make_field(&order.delta_neutral_con_id)?);
             msg.push_str(&make_field(&order.delta_neutral_settling_firm)?);
             msg.push_str(&make_field(&order.delta_neutral_clearing_account)?);
             msg.push_str(&make_field(&order.delta_neutral_clearing_intent)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_DELTA_NEUTRAL_OPEN_CLOSE
-            && order.delta_neutral_order_type != ""
-        {
+        if self.supports(Feature::DeltaNeutralOpenClose) && order.delta_neutral_order_type != "" {
             msg.push_str(&make_field(&order.delta_neutral_open_close)?);
             msg.push_str(&make_field(&order.delta_neutral_short_sale)?);
             msg.push_str(&make_field(&order.delta_neutral_short_sale_slot)?);
@@ -1761,12 +2858,12 @@ impl EClient {
         msg.push_str(&make_field_handle_empty(&order.reference_price_type)?);
         msg.push_str(&make_field_handle_empty(&order.trail_stop_price)?); // srv v30 && above
 
-        if self.server_version() >= MIN_SERVER_VER_TRAILING_PERCENT {
+        if self.supports(Feature::TrailingPercent) {
             msg.push_str(&make_field_handle_empty(&order.trailing_percent)?);
         }
 
         // SCALE orders
-        if self.server_version() >= MIN_SERVER_VER_SCALE_ORDERS2 {
+        if self.supports(Feature::ScaleOrders2) {
             msg.push_str(&make_field_handle_empty(&order.scale_init_level_size)?);
             msg.push_str(&make_field_handle_empty(&order.scale_subs_level_size)?);
         } else {
@@ -1778,7 +2875,7 @@ impl EClient {
 
         msg.push_str(&make_field_handle_empty(&order.scale_price_increment)?);
 
-        if self.server_version() >= MIN_SERVER_VER_SCALE_ORDERS3
+        if self.supports(Feature::ScaleOrders3)
             && order.scale_price_increment != UNSET_DOUBLE
             && order.scale_price_increment > 0.0
         {
@@ -1793,14 +2890,14 @@ impl EClient {
             msg.push_str(&make_field(&order.scale_random_percent)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_SCALE_TABLE {
+        if self.supports(Feature::ScaleTable) {
             msg.push_str(&make_field(&order.scale_table)?);
             msg.push_str(&make_field(&order.active_start_time)?);
             msg.push_str(&make_field(&order.active_stop_time)?);
         }
 
         // HEDGE orders
-        if self.server_version() >= MIN_SERVER_VER_HEDGE_ORDERS {
+        if self.supports(Feature::HedgeOrders) {
             msg.push_str(&make_field(&order.hedge_type)?);
 
             if !order.hedge_type.is_empty() {
@@ -1808,20 +2905,20 @@ impl EClient {
             }
         }
 
-        if self.server_version() >= MIN_SERVER_VER_OPT_OUT_SMART_ROUTING {
+        if self.supports(Feature::OptOutSmartRouting) {
             msg.push_str(&make_field(&order.opt_out_smart_routing)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_PTA_ORDERS {
+        if self.supports(Feature::PtaOrders) {
             msg.push_str(&make_field(&order.clearing_account)?);
             msg.push_str(&make_field(&order.clearing_intent)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_NOT_HELD {
+        if self.supports(Feature::NotHeld) {
             msg.push_str(&make_field(&order.not_held)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_DELTA_NEUTRAL {
+        if self.supports(Feature::DeltaNeutral) {
             if contract.delta_neutral_contract.is_some() {
                 msg.push_str(&make_field(&true)?);
                 msg.push_str(&make_field(
@@ -1838,7 +2935,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() >= MIN_SERVER_VER_ALGO_ORDERS {
+        if self.supports(Feature::AlgoOrders) {
             msg.push_str(&make_field(&order.algo_strategy)?);
             if !order.algo_strategy.is_empty() {
                 let algo_params_count = order.algo_params.len();
@@ -1852,14 +2949,14 @@ impl EClient {
             }
         }
 
-        if self.server_version() >= MIN_SERVER_VER_ALGO_ID {
+        if self.supports(Feature::AlgoId) {
             msg.push_str(&make_field(&order.algo_id)?);
         }
 
         msg.push_str(&make_field(&order.what_if)?); // srv v36 && above
 
         // send miscOptions parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let misc_options_str = order
                 .order_misc_options
                 .iter()
@@ -1868,16 +2965,16 @@ impl EClient {
             msg.push_str(&make_field(&misc_options_str)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_ORDER_SOLICITED {
+        if self.supports(Feature::OrderSolicited) {
             msg.push_str(&make_field(&order.solicited)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_RANDOMIZE_SIZE_AND_PRICE {
+        if self.supports(Feature::RandomizeSizeAndPrice) {
             msg.push_str(&make_field(&order.randomize_size)?);
             msg.push_str(&make_field(&order.randomize_price)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_PEGGED_TO_BENCHMARK {
+        if self.supports(Feature::PeggedToBenchmark) {
             if order.order_type == "PEG BENCH" {
                 msg.push_str(&make_field(&order.reference_contract_id)?);
                 msg.push_str(&make_field(&order.is_pegged_change_amount_decrease)?);
@@ -1909,42 +3006,42 @@ impl EClient {
             msg.push_str(&make_field(&order.adjustable_trailing_unit)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_EXT_OPERATOR {
+        if self.supports(Feature::ExtOperator) {
             msg.push_str(&make_field(&order.ext_operator)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_SOFT_DOLLAR_TIER {
+        if self.supports(Feature::SoftDollarTier) {
             msg.push_str(&make_field(&order.soft_dollar_tier.name)?);
             msg.push_str(&make_field(&order.soft_dollar_tier.val)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_CASH_QTY {
+        if self.supports(Feature::CashQty) {
             msg.push_str(&make_field(&order.cash_qty)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_DECISION_MAKER {
+        if self.supports(Feature::DecisionMaker) {
             msg.push_str(&make_field(&order.mifid2decision_maker)?);
             msg.push_str(&make_field(&order.mifid2decision_algo)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_MIFID_EXECUTION {
+        if self.supports(Feature::MifidExecution) {
             msg.push_str(&make_field(&order.mifid2execution_trader)?);
             msg.push_str(&make_field(&order.mifid2execution_algo)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_AUTO_PRICE_FOR_HEDGE {
+        if self.supports(Feature::AutoPriceForHedge) {
             msg.push_str(&make_field(&order.dont_use_auto_price_for_hedge)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_ORDER_CONTAINER {
+        if self.supports(Feature::OrderContainer) {
             msg.push_str(&make_field(&order.is_oms_container)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_D_PEG_ORDERS {
+        if self.supports(Feature::DPegOrders) {
             msg.push_str(&make_field(&order.discretionary_up_to_limit_price)?);
         }
 
-        if self.server_version() >= MIN_SERVER_VER_PRICE_MGMT_ALGO {
+        if self.supports(Feature::PriceMgmtAlgo) {
             msg.push_str(&make_field_handle_empty(&order.use_price_mgmt_algo)?);
         }
 
@@ -1956,6 +3053,8 @@ impl EClient {
     /// Call this function to cancel an order.
     /// # Arguments
     /// * order_id - The order ID that was specified previously when placing the order
+    #[cfg(feature = "orders")]
+    #[instrument(level = "debug", skip_all, fields(order_id = order_id))]
     pub fn cancel_order(&mut self, order_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -1973,6 +3072,118 @@ impl EClient {
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Buys at the market and waits until the order is filled
+    ///
+    /// # Arguments
+    /// * contract - The contract to buy
+    /// * quantity - The quantity to buy
+    /// * timeout - How long to wait for the order id, the fills and their commissions
+    #[cfg(feature = "orders")]
+    pub fn buy_market(
+        &mut self,
+        contract: &Contract,
+        quantity: f64,
+        timeout: Duration,
+    ) -> Result<FillReport, FillFailure> {
+        self.market_order_and_wait("BUY", contract, quantity, timeout)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sells at the market and waits until the order is filled.  See buy_market().
+    #[cfg(feature = "orders")]
+    pub fn sell_market(
+        &mut self,
+        contract: &Contract,
+        quantity: f64,
+        timeout: Duration,
+    ) -> Result<FillReport, FillFailure> {
+        self.market_order_and_wait("SELL", contract, quantity, timeout)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Asks TWS for an order id, places a market order with it and follows the order until it
+    /// is filled, cancelled or rejected.  If it is still working at the timeout it is cancelled.
+    #[cfg(feature = "orders")]
+    fn market_order_and_wait(
+        &mut self,
+        action: &str,
+        contract: &Contract,
+        quantity: f64,
+        timeout: Duration,
+    ) -> Result<FillReport, FillFailure> {
+        let deadline = Instant::now() + timeout;
+        let order_id = self.next_order_id(timeout)?;
+
+        let order = Order {
+            action: action.to_string(),
+            order_type: "MKT".to_string(),
+            total_quantity: quantity,
+            ..Default::default()
+        };
+        self.place_order(order_id, contract, &order)?;
+
+        let mut tracker = FillTracker::new(order_id, contract, &order);
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match self.collect(remaining, |msg| {
+            Ok(match tracker.observe(msg) {
+                true if tracker.is_finished() => Collected::Finished,
+                true => Collected::Consumed,
+                false => Collected::Ignored,
+            })
+        }) {
+            Ok(()) => (),
+            Err(IBKRApiLibError::RecvTimeoutError(_)) if !tracker.report().is_filled() => {
+                warn!("market order {} timed out, cancelling it", order_id);
+                self.cancel_order(order_id)?;
+            }
+            Err(IBKRApiLibError::RecvTimeoutError(_)) => (),
+            Err(e) => return Err(e.into()),
+        }
+        tracker.finish()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Asks TWS for the next valid order id and waits for it
+    #[cfg(feature = "orders")]
+    fn next_order_id(&mut self, timeout: Duration) -> Result<i32, IBKRApiLibError> {
+        self.req_ids(1)?;
+        let mut order_id = None;
+        self.collect(timeout, |msg| match msg {
+            ServerRspMsg::NextValidId { order_id: id } => {
+                order_id = Some(*id);
+                Ok(Collected::Finished)
+            }
+            _ => Ok(Collected::Ignored),
+        })?;
+        Ok(order_id.expect("collect() only finishes on NextValidId"))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Previews an order: TWS checks it and returns the margin impact and the commission it
+    /// would have, without transmitting it.  A copy of the order is sent with what_if set,
+    /// under a new order id and without its parent, so a bracket child can be previewed on its
+    /// own.  The returned future resolves to the OrderState of the preview.
+    ///
+    /// # Arguments
+    /// * contract - The contract of the order
+    /// * order - The order to preview
+    #[cfg(feature = "orders")]
+    pub fn what_if_order(
+        &mut self,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<WhatIfOrder<'_>, IBKRApiLibError> {
+        let preview = Order {
+            what_if: true,
+            transmit: true,
+            parent_id: 0,
+            ..order.clone()
+        };
+        self.req_ids(1)?;
+        Ok(WhatIfOrder::new(self, contract.clone(), preview))
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Call this function to request the open orders that were
     /// placed from this client. Each open order will be fed back through the
@@ -1982,6 +3193,7 @@ impl EClient {
     ///        open orders. These orders will be associated with the client and a new
     ///        order_id will be generated. This association will persist over multiple
     ///        API and TWS sessions
+    #[cfg(feature = "orders")]
     pub fn req_open_orders(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -2010,6 +3222,7 @@ impl EClient {
     /// * b_auto_bind - If set to TRUE, newly created TWS orders will be implicitly
     ///                 associated with the client.If set to FALSE, no association will be
     ///                 made.
+    #[cfg(feature = "orders")]
     pub fn req_auto_open_orders(&mut self, b_auto_bind: bool) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -2034,6 +3247,7 @@ impl EClient {
     /// open_order() and order_status() functions on the EWrapper.
     /// Note:  No association is made between the returned orders and the
     /// requesting client.
+    #[cfg(feature = "orders")]
     pub fn req_all_open_orders(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -2056,6 +3270,7 @@ impl EClient {
     /// cancels both API and TWS open orders.
     /// If the order was created in TWS, it also gets canceled. If the order
     /// was initiated in the API client, it also gets canceled.
+    #[cfg(feature = "orders")]
     pub fn req_global_cancel(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -2073,6 +3288,89 @@ impl EClient {
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Cancels every open order of every client and of TWS with a global cancel, and waits until
+    /// TWS reports each order that was working as cancelled or completed
+    ///
+    /// # Arguments
+    /// * timeout - How long to wait for the open orders and their cancellation
+    #[cfg(feature = "orders")]
+    pub fn global_cancel(&mut self, timeout: Duration) -> Result<CancelReport, IBKRApiLibError> {
+        let deadline = Instant::now() + timeout;
+        self.req_all_open_orders()?;
+        let orders = self.collect_open_orders(timeout)?;
+
+        self.req_global_cancel()?;
+        let pending = PendingCancels::new(orders.live());
+        self.await_cancels(pending, deadline)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels the working orders of this client that match a filter, and waits until TWS reports
+    /// each of them as cancelled or completed, or refuses the cancel.  Unlike global_cancel(),
+    /// orders of other clients and of TWS are left alone.
+    ///
+    /// # Arguments
+    /// * filter - The orders to cancel, such as OrderFilter::all().symbol("AAPL").side("BUY")
+    /// * timeout - How long to wait for the open orders and their cancellation
+    #[cfg(feature = "orders")]
+    pub fn cancel_all(
+        &mut self,
+        filter: &OrderFilter,
+        timeout: Duration,
+    ) -> Result<CancelReport, IBKRApiLibError> {
+        let deadline = Instant::now() + timeout;
+        self.req_open_orders()?;
+        let orders = self.collect_open_orders(timeout)?;
+
+        let targets: Vec<_> = orders
+            .live()
+            .filter(|tracked| filter.matches(&tracked.contract, &tracked.order))
+            .collect();
+        for tracked in targets.iter() {
+            self.cancel_order(tracked.order_id)?;
+        }
+        self.await_cancels(PendingCancels::new(targets), deadline)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Collects the OpenOrder and OrderStatus events of a request for open orders until
+    /// OpenOrderEnd
+    #[cfg(feature = "orders")]
+    fn collect_open_orders(&mut self, timeout: Duration) -> Result<LiveOrders, IBKRApiLibError> {
+        let mut orders = LiveOrders::new();
+        self.collect(timeout, |msg| match msg {
+            ServerRspMsg::OpenOrderEnd => Ok(Collected::Finished),
+            msg if orders.observe(msg) => Ok(Collected::Consumed),
+            _ => Ok(Collected::Ignored),
+        })?;
+        Ok(orders)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Follows cancelled orders until none is working or the deadline passes
+    #[cfg(feature = "orders")]
+    fn await_cancels(
+        &mut self,
+        mut pending: PendingCancels,
+        deadline: Instant,
+    ) -> Result<CancelReport, IBKRApiLibError> {
+        if !pending.is_done() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.collect(remaining, |msg| {
+                Ok(match pending.observe(msg) {
+                    true if pending.is_done() => Collected::Finished,
+                    true => Collected::Consumed,
+                    false => Collected::Ignored,
+                })
+            }) {
+                Ok(()) | Err(IBKRApiLibError::RecvTimeoutError(_)) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(pending.finish())
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Call this function to request from TWS the next valid ID that
     /// can be used when placing an order.  After calling this function, the
@@ -2155,7 +3453,8 @@ impl EClient {
     /// * group_name - Set to All to return account summary data for all
     ///                accounts, or set to a specific Advisor Account Group name that has
     ///                already been created in TWS Global Configuration.
-    /// * tags- A comma-separated list of account tags.  See the AccountSummaryTags enum for valid values
+    /// * tags- A comma-separated list of account tags.  See the AccountSummaryTag enum for valid values
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_account_summary(
         &mut self,
         req_id: i32,
@@ -2175,28 +3474,182 @@ impl EClient {
         msg.push_str(&make_field(&String::from(group_name))?);
         msg.push_str(&make_field(&String::from(tags))?);
 
-        self.send_request(msg.as_str())?;
-        Ok(())
+        self.send_request(msg.as_str())?;
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels the request for Account Window Summary tab data.
+    ///
+    /// # Arguments
+    /// * req_id - The ID of the data request being canceled.
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
+    pub fn cancel_account_summary(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
+        self.check_connected(req_id)?;
+        let version = 1;
+
+        let message_id: i32 = ServerReqMsgDiscriminants::CancelAccountSummary as i32;
+        let mut msg = "".to_string();
+        msg.push_str(&make_field(&message_id)?);
+        msg.push_str(&make_field(&version)?);
+        msg.push_str(&make_field(&req_id)?);
+
+        self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the summary of all accounts and returns a future resolving to it once TWS has
+    /// sent it.  The subscription is cancelled once the summary has been received.
+    ///
+    /// # Arguments
+    /// * tags - The values to report
+    pub fn account_summary(
+        &mut self,
+        tags: &[AccountSummaryTag],
+    ) -> Result<AccountSummaryFuture<'_>, IBKRApiLibError> {
+        let tags = AccountSummaryTag::to_list(tags);
+        let req_id = self.next_req_id(RequestKind::AccountSummary, &tags);
+        if let Err(err) = self.req_account_summary(req_id, "All", &tags) {
+            self.correlations.complete(req_id);
+            return Err(err);
+        }
+        Ok(AccountSummaryFuture::new(self, req_id))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the values of one account and waits for them.  Unlike account_summary(), which
+    /// covers the accounts of an advisor group, this works for any account id.  The $LEDGER tags
+    /// match no value; cash balances are reported per currency under CashBalance instead.
+    ///
+    /// # Arguments
+    /// * account - The account
+    /// * tags - The values to report
+    /// * timeout - How long to wait for TWS to send the values
+    pub fn account_summary_of(
+        &mut self,
+        account: &AccountId,
+        tags: &[AccountSummaryTag],
+        timeout: Duration,
+    ) -> Result<AccountSummaryReport, IBKRApiLibError> {
+        // AllTags is displayed as the list of the tags it stands for
+        let wanted = AccountSummaryTag::to_list(tags);
+        let wanted: Vec<&str> = wanted.split(',').collect();
+        let req_id = self.next_req_id(RequestKind::AccountUpdatesMulti, account.as_str());
+        self.req_account_updates_multi(req_id, account.as_str(), "", false)?;
+
+        let mut report = AccountSummaryReport::new();
+        let result = self.collect(timeout, |msg| match msg {
+            ServerRspMsg::AccountUpdateMulti {
+                req_id: id,
+                account,
+                key,
+                value,
+                ..
+            } if *id == req_id => {
+                if wanted.contains(&key.as_str()) {
+                    report.insert(
+                        account,
+                        AccountSummaryValue {
+                            tag: key.to_string(),
+                            value: value.to_string(),
+                            currency: value.currency().to_string(),
+                        },
+                    );
+                }
+                Ok(Collected::Consumed)
+            }
+            ServerRspMsg::AccountUpdateMultiEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        });
+        self.cancel_account_updates_multi(req_id)?;
+        result.map(|_| report)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// account_summary_of() for every managed account, merged into one report
+    pub fn account_summaries(
+        &mut self,
+        tags: &[AccountSummaryTag],
+        timeout: Duration,
+    ) -> Result<AccountSummaryReport, IBKRApiLibError> {
+        let reports = self.for_each_account(|client, account| {
+            client.account_summary_of(account, tags, timeout)
+        })?;
+        let mut report = AccountSummaryReport::new();
+        for (_, account_report) in reports {
+            report.merge(account_report);
+        }
+        Ok(report)
     }
 
     //----------------------------------------------------------------------------------------------
-    /// Cancels the request for Account Window Summary tab data.
+    /// Requests the positions of one account and waits for them.  The subscription is cancelled
+    /// once they have been received.
     ///
     /// # Arguments
-    /// * req_id - The ID of the data request being canceled.
-    pub fn cancel_account_summary(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
-        self.check_connected(req_id)?;
-        let version = 1;
-
-        let message_id: i32 = ServerReqMsgDiscriminants::CancelAccountSummary as i32;
-        let mut msg = "".to_string();
-        msg.push_str(&make_field(&message_id)?);
-        msg.push_str(&make_field(&version)?);
-        msg.push_str(&make_field(&req_id)?);
-
-        self.send_request(msg.as_str())?;
+    /// * account - The account
+    /// * timeout - How long to wait for TWS to send the positions
+    pub fn positions_of(
+        &mut self,
+        account: &AccountId,
+        timeout: Duration,
+    ) -> Result<Vec<ModelPosition>, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::PositionsMulti, account.as_str());
+        self.req_positions_multi(req_id, account.as_str(), "")?;
+
+        let mut positions = Vec::new();
+        let result = self.collect(timeout, |msg| match msg {
+            ServerRspMsg::PositionMulti {
+                req_id: id,
+                contract,
+                position,
+                avg_cost,
+                ..
+            } if *id == req_id => {
+                positions.push(ModelPosition {
+                    contract: contract.clone(),
+                    position: *position,
+                    avg_cost: *avg_cost,
+                });
+                Ok(Collected::Consumed)
+            }
+            ServerRspMsg::PositionMultiEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        });
+        self.cancel_positions_multi(req_id)?;
+        result.map(|_| positions)
+    }
 
-        Ok(())
+    //----------------------------------------------------------------------------------------------
+    /// positions_of() for every managed account
+    pub fn positions_by_account(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<BTreeMap<AccountId, Vec<ModelPosition>>, IBKRApiLibError> {
+        Ok(self
+            .for_each_account(|client, account| client.positions_of(account, timeout))?
+            .into_iter()
+            .collect())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -2204,20 +3657,6 @@ impl EClient {
     pub fn req_positions(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_POSITIONS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support positions request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::ReqPositions as i32;
@@ -2235,20 +3674,6 @@ impl EClient {
     pub fn cancel_positions(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_POSITIONS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support positions request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::CancelPositions as i32;
@@ -2269,6 +3694,7 @@ impl EClient {
     /// * account - If an account Id is provided, only the account's positions belonging to the
     ///             specified model will be delivered
     /// * modelCode	- The code of the model's positions we are interested in.
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_positions_multi(
         &mut self,
         req_id: i32,
@@ -2277,20 +3703,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_POSITIONS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support positions multi request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
         let req_id = req_id;
         let account = account;
@@ -2313,23 +3725,10 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The id of the original request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_positions_multi(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_POSITIONS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support positions multi request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
         let req_id = req_id;
         let message_id: i32 = ServerReqMsgDiscriminants::CancelPositionsMulti as i32;
@@ -2339,6 +3738,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -2351,6 +3751,7 @@ impl EClient {
     /// * model_code - values can also be requested for a model
     /// * ledger_and_nvl - returns light-weight request; only currency positions as opposed to
     ///                    account values and currency positions
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_account_updates_multi(
         &mut self,
         req_id: i32,
@@ -2360,20 +3761,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_MODELS_SUPPORT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support account updates multi request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
         let mut_req_id = req_id;
         let mut_account = account;
@@ -2399,23 +3786,10 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The id of the original request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_account_updates_multi(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_MODELS_SUPPORT {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support account updates multi request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
         let mut_req_id = req_id;
         let message_id: i32 = ServerReqMsgDiscriminants::CancelAccountUpdatesMulti as i32;
@@ -2424,7 +3798,9 @@ impl EClient {
         msg.push_str(&make_field(&version)?);
         msg.push_str(&make_field(&mut_req_id)?);
 
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
+        Ok(())
     }
 
     //#########################################################################
@@ -2436,6 +3812,7 @@ impl EClient {
     /// * req_id - identifier to tag the request
     /// * account - account values can be requested for a particular account
     /// * model_code - values can also be requested for a model
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_pnl(
         &mut self,
         req_id: i32,
@@ -2444,20 +3821,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_PNL {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support PnL request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqPnl as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -2473,29 +3836,18 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The id of the original request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_pnl(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_PNL {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support PnL request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::CancelPnl as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&req_id)?);
 
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -2506,6 +3858,7 @@ impl EClient {
     /// * account - account values can be requested for a particular account
     /// * model_code - values can also be requested for a model
     /// * con_id - contract id of the specific contact of interest
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_pnl_single(
         &mut self,
         req_id: i32,
@@ -2515,20 +3868,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_PNL {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support PnL request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqPnlSingle as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -2545,29 +3884,54 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The id of the original request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_pnl_single(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_PNL {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support PnL request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::CancelPnlSingle as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&req_id)?);
 
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the PnL of an account or model.  The returned stream yields its updates and
+    /// cancels the subscription when dropped.
+    ///
+    /// # Arguments
+    /// * account - The account
+    /// * model_code - The model, or "" for the whole account
+    pub fn subscribe_pnl(
+        &mut self,
+        account: &str,
+        model_code: &str,
+    ) -> Result<PnlStream<'_>, IBKRApiLibError> {
+        let mut stream = PnlStream::new(self);
+        stream.add_account(account, model_code)?;
+        Ok(stream)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the PnL of one position.  More positions can be added to the returned
+    /// stream with PnlStream::add_position(); PortfolioPnl sums their updates.
+    ///
+    /// # Arguments
+    /// * account - The account holding the position
+    /// * model_code - The model, or ""
+    /// * con_id - The position's contract
+    pub fn subscribe_pnl_single(
+        &mut self,
+        account: &str,
+        model_code: &str,
+        con_id: i32,
+    ) -> Result<PnlStream<'_>, IBKRApiLibError> {
+        let mut stream = PnlStream::new(self);
+        stream.add_position(account, model_code, con_id)?;
+        Ok(stream)
     }
 
     //#########################################################################
@@ -2588,6 +3952,8 @@ impl EClient {
     ///                 reports are returned.
     ///
     /// NOTE: Time format must be 'yyyymmdd-hh:mm:ss' Eg: '20030702-14:55'
+    #[cfg(feature = "orders")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_executions(
         &mut self,
         req_id: i32,
@@ -2600,7 +3966,7 @@ impl EClient {
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&version)?);
-        if self.server_version() >= MIN_SERVER_VER_EXECUTION_DATA_CHAIN {
+        if self.supports(Feature::ExecutionDataChain) {
             msg.push_str(&make_field(&req_id)?);
         }
         msg.push_str(&make_field(&exec_filter.client_id)?);
@@ -2614,6 +3980,25 @@ impl EClient {
         self.send_request(msg.as_str())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests the executions matching a filter and returns a future resolving to them, with
+    /// their contracts, once TWS has sent them all
+    ///
+    /// # Arguments
+    /// * filter - The executions to return, such as ExecutionFilter::default().symbol("IBM")
+    #[cfg(feature = "orders")]
+    pub fn executions(
+        &mut self,
+        filter: &ExecutionFilter,
+    ) -> Result<Executions<'_>, IBKRApiLibError> {
+        let req_id = self.next_req_id(RequestKind::Executions, &filter.symbol);
+        if let Err(err) = self.req_executions(req_id, filter) {
+            self.correlations.complete(req_id);
+            return Err(err);
+        }
+        Ok(Executions::new(self, req_id))
+    }
+
     //#########################################################################
     //################## Contract Details
     //#########################################################################
@@ -2626,6 +4011,7 @@ impl EClient {
     /// * req_id - The ID of the data request. Ensures that responses are
     ///            matched to requests if several requests are in process.
     /// * contract - The summary description of the contract being looked up.
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_contract_details(
         &mut self,
         req_id: i32,
@@ -2633,7 +4019,7 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(req_id)?;
 
-        if self.server_version() < MIN_SERVER_VER_SEC_ID_TYPE {
+        if !self.supports(Feature::SecIdType) {
             if contract.sec_id_type != "" || contract.sec_id != "" {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -2649,7 +4035,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             if contract.trading_class != "" {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -2665,7 +4051,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
+        if !self.supports(Feature::Linking) {
             if contract.primary_exchange != "" {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -2688,7 +4074,7 @@ impl EClient {
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&version)?);
 
-        if self.server_version() >= MIN_SERVER_VER_CONTRACT_DATA_CHAIN {
+        if self.supports(Feature::ContractDataChain) {
             msg.push_str(&make_field(&req_id)?);
         }
 
@@ -2702,10 +4088,10 @@ impl EClient {
         msg.push_str(&make_field(&contract.right)?);
         msg.push_str(&make_field(&contract.multiplier)?); // srv v15 and above
 
-        if self.server_version() >= MIN_SERVER_VER_PRIMARYEXCH {
+        if self.supports(Feature::Primaryexch) {
             msg.push_str(&make_field(&contract.exchange)?);
             msg.push_str(&make_field(&contract.primary_exchange)?);
-        } else if self.server_version() >= MIN_SERVER_VER_LINKING {
+        } else if self.supports(Feature::Linking) {
             if contract.primary_exchange != ""
                 && (contract.exchange == "BEST" || contract.exchange == "SMART")
             {
@@ -2721,12 +4107,12 @@ impl EClient {
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
             msg.push_str(&make_field(&contract.include_expired)?); // srv v31 and above
         }
 
-        if self.server_version() >= MIN_SERVER_VER_SEC_ID_TYPE {
+        if self.supports(Feature::SecIdType) {
             msg.push_str(&make_field(&contract.sec_id_type)?);
             msg.push_str(&make_field(&contract.sec_id)?);
         }
@@ -2738,23 +4124,10 @@ impl EClient {
     //################## Market Depth
     //#########################################################################
     /// Requests venues for which market data is returned to update_mkt_depth_l2 (those with market makers)
+    #[cfg(feature = "marketdata")]
     pub fn req_mkt_depth_exchanges(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_MKT_DEPTH_EXCHANGES {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support market depth exchanges request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqMktDepthExchanges as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -2762,6 +4135,39 @@ impl EClient {
         self.send_request(msg.as_str())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests the venues that provide market depth and waits for those serving a contract's
+    /// security type and exchange.  A SMART or empty exchange keeps every venue of the security
+    /// type.
+    ///
+    /// # Arguments
+    /// * contract - The contract to find depth venues for
+    /// * timeout - How long to wait for the list
+    #[cfg(feature = "marketdata")]
+    pub fn depth_exchanges_for(
+        &mut self,
+        contract: &Contract,
+        timeout: Duration,
+    ) -> Result<Vec<DepthMktDataDescription>, IBKRApiLibError> {
+        self.req_mkt_depth_exchanges()?;
+
+        let mut venues = Vec::new();
+        self.collect(timeout, |msg| match msg {
+            ServerRspMsg::MktDepthExchanges {
+                depth_mkt_data_descriptions,
+            } => {
+                venues = depth_mkt_data_descriptions
+                    .iter()
+                    .filter(|venue| venue.matches(&contract.sec_type, &contract.exchange))
+                    .cloned()
+                    .collect();
+                Ok(Collected::Finished)
+            }
+            _ => Ok(Collected::Ignored),
+        })?;
+        Ok(venues)
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Call this function to request market depth for a specific
     /// contract. The market depth will be returned by the update_mkt_depth() and
@@ -2779,10 +4185,11 @@ impl EClient {
     /// * contract - This structure contains a description of the contract
     ///              for which market depth data is being requested.
     /// * num_rows - Specifies the number of rows of market depth rows to display.
-    /// * is_smart_depth - specifies SMART depth request  NOTE: ALWAYS SET TO FALSE!!!!!
-    ///                    THERE SEEMS TO BE A BUG ON IB's SIDE AND THEY WILL STOP STREAMING
-    ///                    DATA IF THIS IS SET TO TRUE
+    /// * is_smart_depth - specifies SMART depth request.  Always set when the contract's
+    ///                    exchange is SMART, which TWS only serves as SMART depth.
     /// * mkt_depth_options - For internal use only. Use default value XYZ.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_mkt_depth(
         &mut self,
         req_id: i32,
@@ -2792,8 +4199,9 @@ impl EClient {
         mkt_depth_options: Vec<TagValue>,
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
+        let is_smart_depth = is_smart_depth || contract.exchange.eq_ignore_ascii_case("SMART");
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             if &contract.trading_class != "" || *&contract.con_id > 0 {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -2809,7 +4217,7 @@ impl EClient {
             }
         }
 
-        if self.server_version() < MIN_SERVER_VER_SMART_DEPTH && is_smart_depth {
+        if !self.supports(Feature::SmartDepth) && is_smart_depth {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -2823,9 +4231,7 @@ impl EClient {
             return Err(err);
         }
 
-        if self.server_version() < MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE
-            && contract.primary_exchange != ""
-        {
+        if !self.supports(Feature::MktDepthPrimExchange) && contract.primary_exchange != "" {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -2850,7 +4256,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         // send contract fields
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.con_id)?);
         }
         msg.push_str(&make_field(&contract.symbol)?);
@@ -2861,22 +4267,22 @@ impl EClient {
         msg.push_str(&make_field(&contract.multiplier)?); // srv v15 and above
         msg.push_str(&make_field(&contract.exchange)?);
 
-        if self.server_version() >= MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE {
+        if self.supports(Feature::MktDepthPrimExchange) {
             msg.push_str(&make_field(&contract.primary_exchange)?);
         }
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
 
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
         msg.push_str(&make_field(&num_rows)?); // srv v19 and above
 
-        if self.server_version() >= MIN_SERVER_VER_SMART_DEPTH {
+        if self.supports(Feature::SmartDepth) {
             msg.push_str(&make_field(&is_smart_depth)?);
         }
         // send mkt_depth_options parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             // current doc says this part if for "internal use only" -> won't support it
             if mkt_depth_options.len() > 0 {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
@@ -2894,7 +4300,14 @@ impl EClient {
             let mkt_data_options_str = "";
             msg.push_str(&make_field(&mkt_data_options_str)?);
         }
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        let mut smart_depth = self.smart_depth.lock().expect(POISONED_MUTEX);
+        if is_smart_depth {
+            smart_depth.insert(req_id);
+        } else {
+            smart_depth.remove(&req_id);
+        }
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -2903,16 +4316,24 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The ID that was specified in the call to req_mkt_depth().
-    //  * is_smart_depth - specifies SMART depth request
-    //
+    /// * is_smart_depth - specifies SMART depth request.  Requests sent as SMART depth by
+    ///                    req_mkt_depth() are always cancelled as SMART depth.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_mkt_depth(
         &mut self,
         req_id: i32,
         is_smart_depth: bool,
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
-
-        if self.server_version() < MIN_SERVER_VER_SMART_DEPTH && is_smart_depth {
+        let is_smart_depth = is_smart_depth
+            || self
+                .smart_depth
+                .lock()
+                .expect(POISONED_MUTEX)
+                .contains(&req_id);
+
+        if !self.supports(Feature::SmartDepth) && is_smart_depth {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -2934,11 +4355,17 @@ impl EClient {
         msg.push_str(&make_field(&version)?);
         msg.push_str(&make_field(&req_id)?);
 
-        if self.server_version() >= MIN_SERVER_VER_SMART_DEPTH {
+        if self.supports(Feature::SmartDepth) {
             msg.push_str(&make_field(&is_smart_depth)?);
         }
 
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        self.smart_depth
+            .lock()
+            .expect(POISONED_MUTEX)
+            .remove(&req_id);
+        self.correlations.cancel(req_id);
+        Ok(())
     }
 
     //#########################################################################
@@ -2951,6 +4378,7 @@ impl EClient {
     /// * all_msgs - If set to TRUE, returns all the existing bulletins for
     //               the current day and any new ones. If set to FALSE, will only
     //               return new bulletins.
+    #[cfg(feature = "news")]
     pub fn req_news_bulletins(&mut self, all_msgs: bool) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -2968,6 +4396,7 @@ impl EClient {
 
     //----------------------------------------------------------------------------------------------
     ///Call this function to stop receiving news bulletins.
+    #[cfg(feature = "news")]
     pub fn cancel_news_bulletins(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -3042,12 +4471,30 @@ impl EClient {
         msg.push_str(&make_field(&message_id)?);
 
         msg.push_str(&make_field(&version)?);
-        msg.push_str(&make_field(&fa_data)?);
+        msg.push_str(&make_field(&(fa_data as i32))?);
         msg.push_str(&make_field(&String::from(cxml))?);
 
         self.send_request(msg.as_str())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Replaces the FA allocation groups
+    ///
+    /// # Arguments
+    /// * groups - The complete list of groups.  Groups not in the list are removed.
+    pub fn replace_fa_groups(&mut self, groups: &[FaGroup]) -> Result<(), IBKRApiLibError> {
+        self.replace_fa(FaDataType::GROUPS, &FaGroup::list_to_xml(groups))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Replaces the FA allocation profiles
+    ///
+    /// # Arguments
+    /// * profiles - The complete list of profiles.  Profiles not in the list are removed.
+    pub fn replace_fa_profiles(&mut self, profiles: &[FaProfile]) -> Result<(), IBKRApiLibError> {
+        self.replace_fa(FaDataType::PROFILES, &FaProfile::list_to_xml(profiles))
+    }
+
     //#########################################################################
     //################## Historical Data
     //#########################################################################
@@ -3064,23 +4511,13 @@ impl EClient {
     /// * end_date_time - Defines a query end date and time at any point during the past 6 mos.
     ///                   Valid values include any date/time within the past six months in the format:
     ///                   yyyymmdd HH:mm:ss ttt where "ttt" is the optional time zone.
-    /// * duration_str - Set the query duration up to one week, using a time unit
-    ///                  of seconds, days or weeks. Valid values include any integer followed by a space
-    ///                  and then S (seconds)); D (days) or W (week). If no unit is specified, seconds is used.
-    /// * bar_size_setting - See the BarSize enum for valid values. Specifies the size of the bars that will be returned (within IB/TWS listimits).
-    ///                      Valid values include:
-    ///     * 1 sec
-    ///     * 5 secs
-    ///     * 15 secs
-    ///     * 30 secs
-    ///     * 1 min
-    ///     * 2 mins
-    ///     * 3 mins
-    ///     * 5 mins
-    ///     * 15 mins
-    ///     * 30 mins
-    ///     * 1 hour
-    ///     * 1 day
+    ///                   A chrono DateTime is written in that format, with its time zone.
+    /// * duration - How far back the bars go from end_date_time, such as
+    ///              HistoricalDuration::Days(2), sent as "2 D".
+    /// * bar_size - The size of the bars, such as BarSize::_5Mins, sent as "5 mins".  TWS only
+    ///              serves some bar sizes for each duration, see HistoricalDuration::bar_sizes().
+    ///              Other pairs are refused with IBKRApiLibError::InvalidBarSize before
+    ///              anything is sent.
     /// * what_to_show - See the WhatToShow enum for valid values.  Determines the nature of data beinging extracted. Valid values include:
     ///
     ///     * TRADES
@@ -3102,13 +4539,15 @@ impl EClient {
     ///     * 1 - dates applying to bars returned in the format: yyyymmdd{space}{space}hh:mm:dd
     ///     * 2 - dates are returned as a long integer specifying the number of seconds since 1/1/1970 GMT.
     /// *chart_options: - For internal use only. Use default value XYZ.
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_historical_data(
         &mut self,
         req_id: i32,
         contract: &Contract,
-        end_date_time: &str,
-        duration_str: &str,
-        bar_size_setting: &str,
+        end_date_time: &(impl TwsTime + ?Sized),
+        duration: HistoricalDuration,
+        bar_size: BarSize,
         what_to_show: &str,
         use_rth: i32,
         format_date: i32,
@@ -3116,8 +4555,9 @@ impl EClient {
         chart_options: Vec<TagValue>,
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
+        duration.check(bar_size)?;
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             if &contract.trading_class != "" || contract.con_id > 0 {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     req_id,
@@ -3140,14 +4580,14 @@ impl EClient {
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
 
-        if self.server_version() < MIN_SERVER_VER_SYNT_REALTIME_BARS {
+        if !self.supports(Feature::SyntRealtimeBars) {
             msg.push_str(&make_field(&version)?);
         }
 
         msg.push_str(&make_field(&req_id)?);
 
         // Send contract fields
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.con_id)?);
             msg.push_str(&make_field(&contract.symbol)?);
             msg.push_str(&make_field(&contract.sec_type)?);
@@ -3160,14 +4600,14 @@ impl EClient {
             msg.push_str(&make_field(&contract.currency)?);
             msg.push_str(&make_field(&contract.local_symbol)?);
         }
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
         msg.push_str(&make_field(&contract.include_expired)?); // srv v31 and above
 
-        msg.push_str(&make_field(&String::from(end_date_time))?); // srv v20 and above
-        msg.push_str(&make_field(&String::from(bar_size_setting))?); // srv v20 and above
-        msg.push_str(&make_field(&String::from(duration_str))?);
+        msg.push_str(&make_field(&end_date_time.to_tws_time())?); // srv v20 and above
+        msg.push_str(&make_field(&bar_size.to_string())?); // srv v20 and above
+        msg.push_str(&make_field(&duration.to_string())?);
         msg.push_str(&make_field(&use_rth)?);
         msg.push_str(&make_field(&String::from(what_to_show))?);
         msg.push_str(&make_field(&format_date)?); // srv v16 and above
@@ -3182,11 +4622,11 @@ impl EClient {
                 msg.push_str(&make_field(&combo_leg.exchange)?);
             }
         }
-        if self.server_version() >= MIN_SERVER_VER_SYNT_REALTIME_BARS {
+        if self.supports(Feature::SyntRealtimeBars) {
             msg.push_str(&make_field(&keep_up_to_date)?);
         }
         // Send chart_options parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let chart_options_str = chart_options
                 .iter()
                 .map(|x| format!("{}={};", x.tag, x.value))
@@ -3205,6 +4645,8 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - the id of the original request
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_historical_data(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -3218,7 +4660,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
-
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -3234,6 +4676,8 @@ impl EClient {
     ///
     /// Note that formatData parameter affects intraday bars only
     /// 1-day bars always return with date in YYYYMMDD format
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_head_time_stamp(
         &mut self,
         req_id: i32,
@@ -3244,20 +4688,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_HEAD_TIMESTAMP {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support head time stamp requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqHeadTimestamp as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3287,24 +4717,12 @@ impl EClient {
     //----------------------------------------------------------------------------------------------
     /// Cancel the request
     ///
-    /// # Arguments
-    /// * req_id - the id of the original request
-    pub fn cancel_head_time_stamp(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
-        self.check_connected(NO_VALID_ID)?;
-
-        if self.server_version() < MIN_SERVER_VER_CANCEL_HEADTIMESTAMP {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support head time stamp requests."
-                ),
-            ));
-
-            return Err(err);
-        }
+    /// # Arguments
+    /// * req_id - the id of the original request
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
+    pub fn cancel_head_time_stamp(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
+        self.check_connected(NO_VALID_ID)?;
 
         let message_id: i32 = ServerReqMsgDiscriminants::CancelHeadTimestamp as i32;
         let mut msg = "".to_string();
@@ -3313,6 +4731,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -3324,6 +4743,8 @@ impl EClient {
     /// * contract - Contract object for which histogram is being requested
     /// * use_rth - use regular trading hours only, 1 for yes or 0 for no
     /// * time_period - period of which data is being requested, e.g. "3 days"
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = ticker_id))]
     pub fn req_histogram_data(
         &mut self,
         ticker_id: i32,
@@ -3333,20 +4754,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_HISTOGRAM {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support histogram requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqHistogramData as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3377,32 +4784,47 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - the id of the original request
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = ticker_id))]
     pub fn cancel_histogram_data(&mut self, ticker_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_HISTOGRAM {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support histogram requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::CancelHistogramData as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&ticker_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(ticker_id);
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests the histogram of a contract's prices over a period and returns a future resolving
+    /// to it.  The items are sorted by price; see historical::cumulative_distribution() and
+    /// historical::price_at_quantile().
+    ///
+    /// # Arguments
+    /// * contract - The contract
+    /// * use_rth - Only count trades of regular trading hours
+    /// * period - How far back to look.  It is rounded up to whole days, or sent in weeks when
+    ///   it is a whole number of weeks.
+    #[cfg(feature = "marketdata")]
+    pub fn histogram(
+        &mut self,
+        contract: &Contract,
+        use_rth: bool,
+        period: Duration,
+    ) -> Result<Histogram<'_>, IBKRApiLibError> {
+        let time_period = histogram_period(period);
+        let req_id = self.next_req_id(RequestKind::HistogramData, &contract.symbol);
+        if let Err(err) = self.req_histogram_data(req_id, contract, use_rth, &time_period) {
+            self.correlations.complete(req_id);
+            return Err(err);
+        }
+        Ok(Histogram::new(self, req_id))
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Requests historical Time&Sales data for an instrument.
     ///
@@ -3411,17 +4833,20 @@ impl EClient {
     /// * contract - Contract object that is subject of query
     /// * start_date_time,i.e.	"20170701 12:01:00". Uses TWS timezone specified at login.
     /// * end_date_time,i.e.	"20170701 13:01:00". In TWS timezone. Exactly one of start time and end time has to be defined.
+    ///   Both times also accept a chrono DateTime, which is sent with its time zone.
     /// * number_of_ticks - Number of distinct data points. Max currently 1000 per request.
     /// * what_to_show - (Bid_Ask, Midpoint, Trades) Type of data requested.
     /// * use_rth - Data from regular trading hours (1), or all available hours (0)
     /// * ignore_size - A filter only used when the source price is Bid_Ask
     /// * misc_options - should be defined as null, reserved for internal use
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_historical_ticks(
         &mut self,
         req_id: i32,
         contract: &Contract,
-        start_date_time: &str,
-        end_date_time: &str,
+        start_date_time: &(impl TwsTime + ?Sized),
+        end_date_time: &(impl TwsTime + ?Sized),
         number_of_ticks: i32,
         what_to_show: &str,
         use_rth: i32,
@@ -3430,20 +4855,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_HISTORICAL_TICKS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support historical ticks requests."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqHistoricalTicks as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3462,8 +4873,8 @@ impl EClient {
         msg.push_str(&make_field(&contract.local_symbol)?);
         msg.push_str(&make_field(&contract.trading_class)?);
         msg.push_str(&make_field(&contract.include_expired)?);
-        msg.push_str(&make_field(&String::from(start_date_time))?);
-        msg.push_str(&make_field(&String::from(end_date_time))?);
+        msg.push_str(&make_field(&start_date_time.to_tws_time())?);
+        msg.push_str(&make_field(&end_date_time.to_tws_time())?);
         msg.push_str(&make_field(&number_of_ticks)?);
         msg.push_str(&make_field(&String::from(what_to_show))?);
         msg.push_str(&make_field(&use_rth)?);
@@ -3485,6 +4896,7 @@ impl EClient {
     //#########################################################################
     /// Requests an XML list of scanner parameters valid in TWS.
     /// Not all parameters are valid from API scanner.
+    #[cfg(feature = "marketdata")]
     pub fn req_scanner_parameters(&mut self) -> Result<(), IBKRApiLibError> {
         /*Requests an XML string that describes all possible scanner queries*/
 
@@ -3507,6 +4919,8 @@ impl EClient {
     /// * req_id - The ticker ID. Must be a unique value.
     /// * subscription - This structure contains possible parameters used to filter results.
     /// * scanner_subscription_options -  For internal use only. Use default value XYZ
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_scanner_subscription(
         &mut self,
         req_id: i32,
@@ -3517,7 +4931,7 @@ impl EClient {
         self.check_connected(req_id)?;
 
         error!("Server version: {}", self.server_version());
-        if self.server_version() < MIN_SERVER_VER_SCANNER_GENERIC_OPTS {
+        if !self.supports(Feature::ScannerGenericOpts) {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -3537,7 +4951,7 @@ impl EClient {
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
 
-        if self.server_version() < MIN_SERVER_VER_SCANNER_GENERIC_OPTS {
+        if !self.supports(Feature::ScannerGenericOpts) {
             msg.push_str(&make_field(&version)?);
         }
         msg.push_str(&make_field(&req_id)?);
@@ -3566,7 +4980,7 @@ impl EClient {
         msg.push_str(&make_field(&subscription.stock_type_filter)?); // srv v27 and above
 
         // Send scanner_subscription_filter_options parameter
-        if self.server_version() >= MIN_SERVER_VER_SCANNER_GENERIC_OPTS {
+        if self.supports(Feature::ScannerGenericOpts) {
             error!("!!!!!!!! making scanner options");
             let scanner_subscription_filter = scanner_subscription_filter_options
                 .iter()
@@ -3576,7 +4990,7 @@ impl EClient {
             msg.push_str(&make_field(&scanner_subscription_filter)?);
         }
         // Send scanner_subscription_options parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let scanner_subscription_options = scanner_subscription_options
                 .iter()
                 .map(|x| format!("{}={};", x.tag, x.value))
@@ -3594,6 +5008,8 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - the id of the original request
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_scanner_subscription(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         /*reqId:i32 - The ticker ID. Must be a unique value*/
 
@@ -3609,6 +5025,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -3640,6 +5057,8 @@ impl EClient {
     ///                        requested is returned, even if the time time span falls
     ///                        partially or completely outside.
     /// * real_time_bars_options: - For internal use only. Use pub fnault value XYZ
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_real_time_bars(
         &mut self,
         req_id: i32,
@@ -3651,7 +5070,7 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             if !contract.trading_class.is_empty() {
                 let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                     NO_VALID_ID,
@@ -3677,7 +5096,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         // Send contract fields
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.con_id)?);
         }
         msg.push_str(&make_field(&contract.symbol)?);
@@ -3690,7 +5109,7 @@ impl EClient {
         msg.push_str(&make_field(&contract.primary_exchange)?);
         msg.push_str(&make_field(&contract.currency)?);
         msg.push_str(&make_field(&contract.local_symbol)?);
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.trading_class)?);
         }
         msg.push_str(&make_field(&bar_size)?);
@@ -3698,7 +5117,7 @@ impl EClient {
         msg.push_str(&make_field(&use_rth)?);
 
         // Send real_time_bars_options parameter
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let real_time_bars_options_str = real_time_bars_options
                 .iter()
                 .map(|x| format!("{}={};", x.tag, x.value))
@@ -3716,6 +5135,8 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The Id that was specified in the call to req_real_time_bars().
+    #[cfg(feature = "marketdata")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_real_time_bars(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -3730,6 +5151,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -3758,6 +5180,7 @@ impl EClient {
     ///     * ReportsFinStatements (financial statements)
     ///     * RESC (analyst estimates)
     ///     * CalendarReport (company calendar)
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_fundamental_data(
         &mut self,
         req_id: i32,
@@ -3769,21 +5192,7 @@ impl EClient {
 
         let version = 2;
 
-        if self.server_version() < MIN_SERVER_VER_FUNDAMENTAL_DATA {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support fundamental data request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
-        if self.server_version() < MIN_SERVER_VER_TRADING_CLASS {
+        if !self.supports(Feature::TradingClass) {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 req_id,
                 TwsError::UpdateTws.code().to_string(),
@@ -3805,7 +5214,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         // Send contract fields
-        if self.server_version() >= MIN_SERVER_VER_TRADING_CLASS {
+        if self.supports(Feature::TradingClass) {
             msg.push_str(&make_field(&contract.con_id)?);
         }
         msg.push_str(&make_field(&contract.symbol)?);
@@ -3816,7 +5225,7 @@ impl EClient {
         msg.push_str(&make_field(&contract.local_symbol)?);
         msg.push_str(&make_field(&String::from(report_type))?);
 
-        if self.server_version() >= MIN_SERVER_VER_LINKING {
+        if self.supports(Feature::Linking) {
             let tags_value_count = fundamental_data_options.len();
             let fund_data_opt_str = fundamental_data_options
                 .iter()
@@ -3836,23 +5245,10 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The ID of the data request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn cancel_fundamental_data(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_FUNDAMENTAL_DATA {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support fundamental data request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::CancelFundamentalData as i32;
@@ -3863,6 +5259,7 @@ impl EClient {
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.correlations.cancel(req_id);
         Ok(())
     }
 
@@ -3871,23 +5268,10 @@ impl EClient {
     //#########################################################################
     /// Requests all open orders places by this specific API client (identified by the API client id).
     /// For client ID 0, this will bind previous manual TWS orders.
+    #[cfg(feature = "news")]
     pub fn req_news_providers(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_NEWS_PROVIDERS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support news providers request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqNewsProviders as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3905,6 +5289,8 @@ impl EClient {
     /// * provider_code - short code indicating news provider, e.g. FLY
     /// * article_id - id of the specific article
     /// * news_article_options - reserved for internal use. Should be defined as null.
+    #[cfg(feature = "news")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_news_article(
         &mut self,
         req_id: i32,
@@ -3914,20 +5300,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_NEWS_ARTICLE {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support news article request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqNewsArticle as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3937,7 +5309,7 @@ impl EClient {
         msg.push_str(&make_field(&String::from(article_id))?);
 
         // Send news_article_options parameter
-        if self.server_version() >= MIN_SERVER_VER_NEWS_QUERY_ORIGINS {
+        if self.supports(Feature::NewsQueryOrigins) {
             let news_article_options_str = news_article_options
                 .iter()
                 .map(|x| format!("{}={};", x.tag, x.value))
@@ -3961,6 +5333,8 @@ impl EClient {
     /// * end_date_time	- marks the (inclusive) end of the date range. The format is yyyy-MM-dd HH:mm:ss.0
     /// * total_results	- the maximum number of headlines to fetch (1 - 300)
     /// * historical_news_options	reserved for internal use. Should be defined as null.
+    #[cfg(feature = "news")]
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_historical_news(
         &mut self,
         req_id: i32,
@@ -3973,20 +5347,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_HISTORICAL_NEWS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support historical news request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqHistoricalNews as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -3999,7 +5359,7 @@ impl EClient {
         msg.push_str(&make_field(&total_results)?);
 
         // Send historical_news_options parameter
-        if self.server_version() >= MIN_SERVER_VER_NEWS_QUERY_ORIGINS {
+        if self.supports(Feature::NewsQueryOrigins) {
             let historical_news_options_str = historical_news_options
                 .iter()
                 .map(|x| format!("{}={};", x.tag, x.value))
@@ -4022,23 +5382,10 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The unique number that will be associated with the response
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn query_display_groups(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support query_display_groups request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::QueryDisplayGroups as i32;
@@ -4057,6 +5404,7 @@ impl EClient {
     /// # Arguments
     /// * req_id - The unique number that will be associated with the response
     /// * group_id - is the display group for integration
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn subscribe_to_group_events(
         &mut self,
         req_id: i32,
@@ -4064,20 +5412,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support subscribe_to_group_events request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::SubscribeToGroupEvents as i32;
@@ -4102,6 +5436,7 @@ impl EClient {
     /// 2. contract_id - any non-combination contract. Examples 8314 for IBM SMART; 8314 for IBM ARCA
     /// 3. combo - if any combo is selected Note: This request from the API does not get a TWS response unless an error occurs.
 
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn update_display_group(
         &mut self,
         req_id: i32,
@@ -4109,20 +5444,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support update_display_group request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::UpdateDisplayGroup as i32;
@@ -4142,23 +5463,10 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - The request Id specified in subscribe_to_group_events()
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn unsubscribe_from_group_events(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                req_id,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support unsubscribe_from_group_events request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::UnsubscribeFromGroupEvents as i32;
@@ -4181,20 +5489,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support verification request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         if !self.extra_auth {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 NO_VALID_ID,
@@ -4228,20 +5522,6 @@ impl EClient {
     pub fn verify_message(&mut self, api_data: &'static str) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support verification request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::VerifyMessage as i32;
@@ -4265,20 +5545,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support verification request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         if !self.extra_auth {
             let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
                 NO_VALID_ID,
@@ -4317,20 +5583,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_LINKING {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support verification request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let version = 1;
 
         let message_id: i32 = ServerReqMsgDiscriminants::VerifyAndAuthMessage as i32;
@@ -4354,6 +5606,7 @@ impl EClient {
     /// * fut_fop_exchange - The exchange on which the returned options are trading. Can be set to the empty string "" for all exchanges.
     /// * underlying_sec_type - The type of the underlying security, i.e. STK
     /// * underlying_con_id - the contract ID of the underlying security
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_sec_def_opt_params(
         &mut self,
         req_id: i32,
@@ -4364,20 +5617,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_SEC_DEF_OPT_PARAMS_REQ {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support security pub fninition option request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqSecDefOptParams as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -4399,6 +5638,7 @@ impl EClient {
     ///
     /// # Arguments
     /// * req_id - the identifier for this request
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_soft_dollar_tiers(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -4417,20 +5657,6 @@ impl EClient {
     pub fn req_family_codes(&mut self) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_FAMILY_CODES {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " It does not support family codes request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqFamilyCodes as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -4445,6 +5671,7 @@ impl EClient {
     /// # Arguments
     /// * req_id - the identifier for this request
     /// * pattern - either start of ticker symbol or (for larger strings) company name
+    #[instrument(level = "debug", skip_all, fields(req_id = req_id))]
     pub fn req_matching_symbols(
         &mut self,
         req_id: i32,
@@ -4452,20 +5679,6 @@ impl EClient {
     ) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
-        if self.server_version() < MIN_SERVER_VER_REQ_MATCHING_SYMBOLS {
-            let err = IBKRApiLibError::ApiError(TwsApiReportableError::new(
-                NO_VALID_ID,
-                TwsError::UpdateTws.code().to_string(),
-                format!(
-                    "{}{}",
-                    TwsError::UpdateTws.message(),
-                    " t does not support matching symbols request."
-                ),
-            ));
-
-            return Err(err);
-        }
-
         let message_id: i32 = ServerReqMsgDiscriminants::ReqMatchingSymbols as i32;
         let mut msg = "".to_string();
         msg.push_str(&make_field(&message_id)?);
@@ -4483,6 +5696,7 @@ impl EClient {
     /// # Arguments
     /// * api_only - If api_only parameter is true, then only completed orders placed from API are requested.
     ///              Each completed order will be fed back through the completed_order() function on the Wrapper
+    #[cfg(feature = "orders")]
     pub fn req_completed_orders(&mut self, api_only: bool) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
 
@@ -4496,6 +5710,21 @@ impl EClient {
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Requests completed orders and returns a future resolving to them once TWS has sent them
+    /// all
+    ///
+    /// # Arguments
+    /// * api_only - Only return orders placed from the API
+    #[cfg(feature = "orders")]
+    pub fn completed_orders(
+        &mut self,
+        api_only: bool,
+    ) -> Result<CompletedOrders<'_>, IBKRApiLibError> {
+        self.req_completed_orders(api_only)?;
+        Ok(CompletedOrders::new(self))
+    }
+
     //------------------------------------------------------------------------------------------------
     /// check if client is connected to TWS
     fn check_connected(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
@@ -4512,3 +5741,29 @@ impl EClient {
         }
     }
 }
+
+//==================================================================================================
+/// The message that cancels a kind of request, for kinds that stream until cancelled and
+/// are cancelled by req_id alone
+fn cancel_message(kind: &RequestKind) -> Option<ServerReqMsgDiscriminants> {
+    match kind {
+        #[cfg(feature = "marketdata")]
+        RequestKind::MarketData => Some(ServerReqMsgDiscriminants::CancelMktData),
+        #[cfg(feature = "marketdata")]
+        RequestKind::TickByTick => Some(ServerReqMsgDiscriminants::CancelTickByTickData),
+        #[cfg(feature = "marketdata")]
+        RequestKind::HistoricalData => Some(ServerReqMsgDiscriminants::CancelHistoricalData),
+        #[cfg(feature = "marketdata")]
+        RequestKind::RealTimeBars => Some(ServerReqMsgDiscriminants::CancelRealTimeBars),
+        #[cfg(feature = "marketdata")]
+        RequestKind::Scanner => Some(ServerReqMsgDiscriminants::CancelScannerSubscription),
+        RequestKind::AccountSummary => Some(ServerReqMsgDiscriminants::CancelAccountSummary),
+        RequestKind::PositionsMulti => Some(ServerReqMsgDiscriminants::CancelPositionsMulti),
+        RequestKind::AccountUpdatesMulti => {
+            Some(ServerReqMsgDiscriminants::CancelAccountUpdatesMulti)
+        }
+        RequestKind::Pnl => Some(ServerReqMsgDiscriminants::CancelPnl),
+        RequestKind::PnlSingle => Some(ServerReqMsgDiscriminants::CancelPnlSingle),
+        _ => None,
+    }
+}
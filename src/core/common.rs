@@ -1,12 +1,20 @@
 //! Common types
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::fmt::{self, Error, Formatter};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use strum_macros::EnumString;
 
+use chrono::{DateTime, Utc};
+
 use num_derive::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
 
+use crate::core::errors::IBKRApiLibError;
+use crate::core::time::display_time;
+
 pub const NO_VALID_ID: i32 = -1;
 pub const MAX_MSG_LEN: i64 = 0xFFFFFF; //16Mb - 1byte
 
@@ -16,6 +24,97 @@ pub const UNSET_LONG: i64 = std::i64::MAX;
 pub const UNSET_INTEGER_I32_AS_I64: i64 = std::i32::MAX as i64;
 pub const UNSET_INTEGER_I32_AS_U64: u64 = std::i32::MAX as u64;
 
+//==================================================================================================
+/// Connection status
+#[repr(i32)]
+#[derive(FromPrimitive, Copy, Clone, Debug)]
+pub enum ConnStatus {
+    DISCONNECTED,
+    CONNECTING,
+    CONNECTED,
+    REDIRECT,
+}
+
+//==================================================================================================
+/// Whether the connected TWS or IB Gateway is logged into a paper or a live account
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Environment {
+    /// The managed accounts list has not arrived yet
+    #[default]
+    Unknown,
+    Paper,
+    Live,
+}
+
+impl Environment {
+    /// Resolves the environment from the comma-separated list sent in ManagedAccts
+    pub fn from_accounts(accounts_list: &str) -> Self {
+        Environment::of(&AccountId::parse_list(accounts_list))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Resolves the environment from the managed accounts.  A single live account makes the
+    /// whole connection live.
+    pub fn of(accounts: &[AccountId]) -> Self {
+        if accounts.is_empty() {
+            Environment::Unknown
+        } else if accounts.iter().all(AccountId::is_paper) {
+            Environment::Paper
+        } else {
+            Environment::Live
+        }
+    }
+}
+
+//==================================================================================================
+/// An account id, such as U1234567 or DU1234567
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountId(String);
+
+impl AccountId {
+    pub fn new(account: &str) -> Self {
+        AccountId(account.trim().to_string())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses the comma-separated list sent in ManagedAccts, skipping empty entries
+    pub fn parse_list(accounts_list: &str) -> Vec<AccountId> {
+        accounts_list
+            .split(',')
+            .map(AccountId::new)
+            .filter(|account| !account.0.is_empty())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Paper accounts start with DU, or DF for a paper advisor master account
+    pub fn is_paper(&self) -> bool {
+        self.0.starts_with("DU") || self.0.starts_with("DF")
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_live(&self) -> bool {
+        !self.is_paper()
+    }
+}
+
+impl From<&str> for AccountId {
+    fn from(account: &str) -> Self {
+        AccountId::new(account)
+    }
+}
+
+impl fmt::Display for AccountId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 //==================================================================================================
 /// Tick types
 #[repr(i32)]
@@ -225,13 +324,18 @@ pub enum FaDataType {
 
 impl fmt::Display for FaDataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Type: {}", self)
+        write!(f, "Type: {:?}", self)
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum TickMsgType {
-    AllLast {
+//==================================================================================================
+/// The payload of a TickByTick event
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TickByTickData {
+    /// A trade, from a Last or AllLast request
+    Last {
+        /// Set for AllLast ticks, which include trades TWS does not report as Last
+        all_last: bool,
         price: f64,
         size: i32,
         tick_attrib_last: TickAttribLast,
@@ -250,10 +354,24 @@ pub enum TickMsgType {
     },
 }
 
+impl TickByTickData {
+    /// The request type that produces this payload
+    pub fn tick_type(&self) -> TickByTickType {
+        match self {
+            TickByTickData::Last {
+                all_last: false, ..
+            } => TickByTickType::Last,
+            TickByTickData::Last { all_last: true, .. } => TickByTickType::AllLast,
+            TickByTickData::BidAsk { .. } => TickByTickType::BidAsk,
+            TickByTickData::MidPoint { .. } => TickByTickType::MidPoint,
+        }
+    }
+}
+
 //==================================================================================================
 /// Tick by tick types
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
+#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug, PartialEq)]
 pub enum TickByTickType {
     NA = 0,
     Last = 1,
@@ -275,8 +393,59 @@ impl fmt::Display for TickByTickType {
 }
 
 //==================================================================================================
-/// date - the bar's date and time (either as a yyyymmss hh:mm:ssformatted
-///        string or as system time according to the request)
+/// Generic ticks that can be added to a market data request
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq, Eq, Hash)]
+pub enum GenericTickType {
+    OptionVolume = 100,
+    OptionOpenInterest = 101,
+    HistoricalVolatility = 104,
+    AverageOptionVolume = 105,
+    OptionImpliedVol = 106,
+    IndexFuturePremium = 162,
+    MiscellaneousStats = 165,
+    MarkPrice = 221,
+    AuctionValues = 225,
+    RtVolume = 233,
+    Shortable = 236,
+    Inventory = 256,
+    FundamentalRatios = 258,
+    News = 292,
+    TradeCount = 293,
+    TradeRate = 294,
+    VolumeRate = 295,
+    LastRthTrade = 318,
+    RtTradeVolume = 375,
+    RtHistoricalVolatility = 411,
+    IbDividends = 456,
+    BondFactorMultiplier = 460,
+    FuturesOpenInterest = 588,
+    ShortTermVolume = 595,
+    CreditmanSlowMarkPrice = 619,
+}
+
+impl GenericTickType {
+    /// Builds the comma separated list expected by req_mkt_data()
+    pub fn to_list(ticks: &[GenericTickType]) -> String {
+        ticks
+            .iter()
+            .map(|tick| tick.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl fmt::Display for GenericTickType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as i32)
+    }
+}
+
+//==================================================================================================
+/// date - the bar's date and time in UTC.  The date of a daily or longer bar is midnight UTC of
+///        the date TWS sent, so date.date_naive() gives that date.
+/// date_raw - the date as TWS sent it, as yyyymmdd, yyyymmdd hh:mm:ss or epoch seconds
+///        according to the request (with the raw-times feature)
 /// open  - the bar's open point
 /// high  - the bar's high point
 /// low   - the bar's low point
@@ -288,7 +457,10 @@ impl fmt::Display for TickByTickType {
 /// average - average price of the bar
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct BarData {
-    pub date: String,
+    /// Start of the bar in UTC, None if TWS sent a time that could not be read
+    pub date: Option<DateTime<Utc>>,
+    #[cfg(feature = "raw-times")]
+    pub date_raw: String,
     pub open: f64,
     pub high: f64,
     pub low: f64,
@@ -300,7 +472,7 @@ pub struct BarData {
 
 impl BarData {
     pub fn new(
-        date: String,
+        date: DateTime<Utc>,
         open: f64,
         high: f64,
         low: f64,
@@ -310,7 +482,9 @@ impl BarData {
         average: f64,
     ) -> Self {
         BarData {
-            date,
+            date: Some(date),
+            #[cfg(feature = "raw-times")]
+            date_raw: String::new(),
             open,
             high,
             low,
@@ -324,7 +498,7 @@ impl BarData {
 
 impl fmt::Display for BarData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "date: {}, open: {}, high: {}, low: {}, close: {}, volume: {}, average: {}, barcount: {}", self.date, self.open, self.high,
+        write!(f, "date: {}, open: {}, high: {}, low: {}, close: {}, volume: {}, average: {}, barcount: {}", display_time(&self.date), self.open, self.high,
                self.low, self.close, self.volume, self.average, self.bar_count)
     }
 }
@@ -414,6 +588,18 @@ impl fmt::Display for HistogramData {
 }
 
 //==================================================================================================
+/// Kind of market depth an exchange provides
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthServiceType {
+    /// Aggregated depth without market makers
+    Deep,
+    /// Depth by market maker
+    Deep2,
+    Unknown,
+}
+
+//==================================================================================================
+/// A venue that provides market depth, from MktDepthExchanges
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct DepthMktDataDescription {
     pub exchange: String,
@@ -439,6 +625,45 @@ impl DepthMktDataDescription {
             agg_group,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn service_type(&self) -> DepthServiceType {
+        match self.service_data_type.as_str() {
+            "Deep" => DepthServiceType::Deep,
+            "Deep2" => DepthServiceType::Deep2,
+            _ => DepthServiceType::Unknown,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the depth of this venue arrives in MarketDepthL2 with market makers
+    pub fn has_market_makers(&self) -> bool {
+        self.service_type() == DepthServiceType::Deep2
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The aggregation group of the venue, which SMART depth merges, when TWS sent one
+    pub fn aggregation_group(&self) -> Option<i32> {
+        if self.agg_group == UNSET_INTEGER {
+            None
+        } else {
+            Some(self.agg_group)
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the venue provides depth for a security type on an exchange.  An empty or SMART
+    /// exchange matches every venue.
+    ///
+    /// # Arguments
+    /// * sec_type - Security type, for example STK
+    /// * exchange - Exchange of the contract
+    pub fn matches(&self, sec_type: &str, exchange: &str) -> bool {
+        self.sec_type.eq_ignore_ascii_case(sec_type)
+            && (exchange.is_empty()
+                || exchange.eq_ignore_ascii_case("SMART")
+                || self.exchange.eq_ignore_ascii_case(exchange))
+    }
 }
 
 impl fmt::Display for DepthMktDataDescription {
@@ -516,7 +741,7 @@ impl fmt::Display for TickAttrib {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct TickAttribBidAsk {
     pub bid_past_low: bool,
     pub ask_past_high: bool,
@@ -542,7 +767,7 @@ impl fmt::Display for TickAttribBidAsk {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct TickAttribLast {
     pub past_limit: bool,
     pub unreported: bool,
@@ -732,6 +957,74 @@ impl fmt::Display for HistoricalTickLast {
     }
 }
 
+//==================================================================================================
+/// The payload of an RTVolume or RTTradeVolume tick string:
+/// "price;size;time;total volume;vwap;single trade flag".  Price and size are empty when the
+/// tick only updates the volume.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RtVolumeTick {
+    pub price: Option<f64>,
+    pub size: Option<i32>,
+    /// Time of the last trade in milliseconds since the epoch
+    pub time: i64,
+    /// Total volume of the day
+    pub volume: i64,
+    pub vwap: f64,
+    /// Set when the trade was filled by a single market maker
+    pub single_trade: bool,
+}
+
+impl RtVolumeTick {
+    /// Whether a TickString of this tick type carries an RtVolumeTick
+    pub fn is_rt_volume(tick_type: TickType) -> bool {
+        matches!(tick_type, TickType::RtVolume | TickType::RtTrdVolume)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Time of the last trade
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.time)
+    }
+}
+
+impl std::str::FromStr for RtVolumeTick {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || IBKRApiLibError::Parse(format!("invalid RTVolume tick {:?}", s));
+        let fields: Vec<&str> = s.split(';').map(str::trim).collect();
+        if fields.len() != 6 {
+            return Err(invalid());
+        }
+        let price = match fields[0] {
+            "" => None,
+            price => Some(price.parse().map_err(|_| invalid())?),
+        };
+        let size = match fields[1] {
+            "" => None,
+            size => Some(size.parse().map_err(|_| invalid())?),
+        };
+        Ok(RtVolumeTick {
+            price,
+            size,
+            time: fields[2].parse().map_err(|_| invalid())?,
+            volume: fields[3].parse().map_err(|_| invalid())?,
+            vwap: fields[4].parse().map_err(|_| invalid())?,
+            single_trade: fields[5].eq_ignore_ascii_case("true"),
+        })
+    }
+}
+
+impl fmt::Display for RtVolumeTick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "price: {:?}, size: {:?}, time: {}, volume: {}, vwap: {}, single_trade: {}",
+            self.price, self.size, self.time, self.volume, self.vwap, self.single_trade
+        )
+    }
+}
+
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct CommissionReport {
@@ -1144,8 +1437,10 @@ impl Display for WhatToShow {
 }
 
 //==================================================================================================
+/// Size of historical bars.  Displayed and parsed as the bar_size_setting TWS expects, such as
+/// "5 mins", and ordered by length.
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq, Eq, Hash)]
 pub enum BarSize {
     _1Secs,
     _5Secs,
@@ -1165,6 +1460,76 @@ pub enum BarSize {
     _1Day,
     _1Week,
     _1Month,
+    _2Hours,
+    _3Hours,
+    _8Hours,
+}
+
+impl BarSize {
+    /// Every bar size, from the smallest to the largest
+    pub const ALL: [BarSize; 21] = [
+        BarSize::_1Secs,
+        BarSize::_5Secs,
+        BarSize::_10Secs,
+        BarSize::_15Secs,
+        BarSize::_30Secs,
+        BarSize::_1Min,
+        BarSize::_2Mins,
+        BarSize::_3Mins,
+        BarSize::_5Mins,
+        BarSize::_10Mins,
+        BarSize::_15Mins,
+        BarSize::_20Mins,
+        BarSize::_30Mins,
+        BarSize::_1Hour,
+        BarSize::_2Hours,
+        BarSize::_3Hours,
+        BarSize::_4Hours,
+        BarSize::_8Hours,
+        BarSize::_1Day,
+        BarSize::_1Week,
+        BarSize::_1Month,
+    ];
+
+    //----------------------------------------------------------------------------------------------
+    /// Length of a bar in seconds, with a month counted as 30 days
+    pub fn seconds(self) -> u64 {
+        match self {
+            BarSize::_1Secs => 1,
+            BarSize::_5Secs => 5,
+            BarSize::_10Secs => 10,
+            BarSize::_15Secs => 15,
+            BarSize::_30Secs => 30,
+            BarSize::_1Min => 60,
+            BarSize::_2Mins => 2 * 60,
+            BarSize::_3Mins => 3 * 60,
+            BarSize::_5Mins => 5 * 60,
+            BarSize::_10Mins => 10 * 60,
+            BarSize::_15Mins => 15 * 60,
+            BarSize::_20Mins => 20 * 60,
+            BarSize::_30Mins => 30 * 60,
+            BarSize::_1Hour => 3600,
+            BarSize::_2Hours => 2 * 3600,
+            BarSize::_3Hours => 3 * 3600,
+            BarSize::_4Hours => 4 * 3600,
+            BarSize::_8Hours => 8 * 3600,
+            BarSize::_1Day => SECONDS_PER_DAY,
+            BarSize::_1Week => 7 * SECONDS_PER_DAY,
+            BarSize::_1Month => 30 * SECONDS_PER_DAY,
+        }
+    }
+}
+
+impl PartialOrd for BarSize {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BarSize {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.seconds().cmp(&other.seconds())
+    }
 }
 
 impl Display for BarSize {
@@ -1184,7 +1549,10 @@ impl Display for BarSize {
             BarSize::_20Mins => write!(f, "20 mins"),
             BarSize::_30Mins => write!(f, "30 mins"),
             BarSize::_1Hour => write!(f, "1 hour"),
+            BarSize::_2Hours => write!(f, "2 hours"),
+            BarSize::_3Hours => write!(f, "3 hours"),
             BarSize::_4Hours => write!(f, "4 hours"),
+            BarSize::_8Hours => write!(f, "8 hours"),
             BarSize::_1Day => write!(f, "1 day"),
             BarSize::_1Week => write!(f, "1 week"),
             BarSize::_1Month => write!(f, "1 month"),
@@ -1192,6 +1560,157 @@ impl Display for BarSize {
     }
 }
 
+impl FromStr for BarSize {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.split_whitespace().collect::<Vec<&str>>().join(" ");
+        BarSize::ALL
+            .iter()
+            .find(|size| size.to_string() == text)
+            .copied()
+            .ok_or_else(|| IBKRApiLibError::Parse(format!("unknown bar size {:?}", s)))
+    }
+}
+
+//==================================================================================================
+/// How far back historical bars go from the end time, the duration_str of req_historical_data().
+/// Written and read as TWS expects it, such as "2 D".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum HistoricalDuration {
+    /// Up to 86400 seconds
+    Seconds(u32),
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+/// The longest duration of each row of IB's table of valid bar sizes, and the smallest and
+/// largest bar size it allows.  A duration uses the first row that covers it.
+const BAR_SIZE_STEPS: [(u64, BarSize, BarSize); 11] = [
+    (60, BarSize::_1Secs, BarSize::_1Min),
+    (120, BarSize::_1Secs, BarSize::_2Mins),
+    (1800, BarSize::_1Secs, BarSize::_30Mins),
+    (3600, BarSize::_5Secs, BarSize::_1Hour),
+    (14400, BarSize::_10Secs, BarSize::_3Hours),
+    (28800, BarSize::_30Secs, BarSize::_8Hours),
+    (SECONDS_PER_DAY, BarSize::_1Min, BarSize::_1Day),
+    (2 * SECONDS_PER_DAY, BarSize::_2Mins, BarSize::_1Day),
+    (7 * SECONDS_PER_DAY, BarSize::_3Mins, BarSize::_1Week),
+    (30 * SECONDS_PER_DAY, BarSize::_30Mins, BarSize::_1Month),
+    (365 * SECONDS_PER_DAY, BarSize::_1Day, BarSize::_1Month),
+];
+
+pub(crate) const SECONDS_PER_DAY: u64 = 86400;
+
+impl HistoricalDuration {
+    /// The shortest duration that covers span: seconds up to a day, then days up to a year, then
+    /// years
+    pub fn covering(span: chrono::Duration) -> Self {
+        let seconds = (span.num_milliseconds().max(1) as u64).div_ceil(1000);
+        let days = seconds.div_ceil(SECONDS_PER_DAY);
+        if seconds <= SECONDS_PER_DAY {
+            HistoricalDuration::Seconds(seconds as u32)
+        } else if days <= 365 {
+            HistoricalDuration::Days(days as u32)
+        } else {
+            HistoricalDuration::Years(days.div_ceil(365).min(u32::MAX as u64) as u32)
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Length in seconds, with a month counted as 30 days and a year as 365
+    pub fn seconds(&self) -> u64 {
+        match *self {
+            HistoricalDuration::Seconds(count) => count as u64,
+            HistoricalDuration::Days(count) => count as u64 * SECONDS_PER_DAY,
+            HistoricalDuration::Weeks(count) => count as u64 * 7 * SECONDS_PER_DAY,
+            HistoricalDuration::Months(count) => count as u64 * 30 * SECONDS_PER_DAY,
+            HistoricalDuration::Years(count) => count as u64 * 365 * SECONDS_PER_DAY,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The bar sizes TWS serves for this duration, None for a duration it refuses
+    pub fn bar_sizes(&self) -> Option<RangeInclusive<BarSize>> {
+        let seconds = self.seconds();
+        if seconds == 0 {
+            return None;
+        }
+        if let HistoricalDuration::Seconds(count) = *self {
+            if count as u64 > SECONDS_PER_DAY {
+                return None;
+            }
+        }
+        let (_, smallest, largest) = BAR_SIZE_STEPS
+            .iter()
+            .find(|(longest, _, _)| seconds <= *longest)
+            .unwrap_or(&BAR_SIZE_STEPS[BAR_SIZE_STEPS.len() - 1]);
+        Some(*smallest..=*largest)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks that TWS serves bars of bar_size for this duration
+    pub fn check(&self, bar_size: BarSize) -> Result<(), IBKRApiLibError> {
+        match self.bar_sizes() {
+            Some(sizes) if sizes.contains(&bar_size) => Ok(()),
+            _ => Err(IBKRApiLibError::InvalidBarSize {
+                duration: *self,
+                bar_size,
+            }),
+        }
+    }
+}
+
+impl Display for HistoricalDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match *self {
+            HistoricalDuration::Seconds(count) => write!(f, "{} S", count),
+            HistoricalDuration::Days(count) => write!(f, "{} D", count),
+            HistoricalDuration::Weeks(count) => write!(f, "{} W", count),
+            HistoricalDuration::Months(count) => write!(f, "{} M", count),
+            HistoricalDuration::Years(count) => write!(f, "{} Y", count),
+        }
+    }
+}
+
+impl FromStr for HistoricalDuration {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || IBKRApiLibError::Parse(format!("invalid historical duration {:?}", s));
+        let mut parts = s.split_whitespace();
+        let count = parts.next().ok_or_else(invalid)?;
+        let count: u32 = count.parse().map_err(|_| invalid())?;
+        let duration = match (parts.next(), parts.next()) {
+            // TWS reads a number without a unit as seconds
+            (None, None) | (Some("S"), None) => HistoricalDuration::Seconds(count),
+            (Some("D"), None) => HistoricalDuration::Days(count),
+            (Some("W"), None) => HistoricalDuration::Weeks(count),
+            (Some("M"), None) => HistoricalDuration::Months(count),
+            (Some("Y"), None) => HistoricalDuration::Years(count),
+            _ => return Err(invalid()),
+        };
+        Ok(duration)
+    }
+}
+
+impl TryFrom<String> for HistoricalDuration {
+    type Error = IBKRApiLibError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<HistoricalDuration> for String {
+    fn from(duration: HistoricalDuration) -> Self {
+        duration.to_string()
+    }
+}
+
 //==================================================================================================
 #[repr(i32)]
 #[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
@@ -1384,6 +1903,62 @@ impl Display for MarketDataTypeEnum {
     }
 }
 
+//==================================================================================================
+/// The market data type requested with EClient::req_market_data_mode(), and reported for each
+/// subscription in MarketDataType events
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MarketDataMode {
+    #[default]
+    RealTime = 1,
+    /// The last values recorded when the market closed
+    Frozen = 2,
+    /// Real time data delayed by 15-20 minutes, available without a market data subscription
+    Delayed = 3,
+    DelayedFrozen = 4,
+}
+
+impl MarketDataMode {
+    /// The mode of a market_data_type value, None for values TWS does not define
+    pub fn from_i32(market_data_type: i32) -> Option<Self> {
+        match market_data_type {
+            1 => Some(MarketDataMode::RealTime),
+            2 => Some(MarketDataMode::Frozen),
+            3 => Some(MarketDataMode::Delayed),
+            4 => Some(MarketDataMode::DelayedFrozen),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_delayed(&self) -> bool {
+        matches!(
+            self,
+            MarketDataMode::Delayed | MarketDataMode::DelayedFrozen
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The delayed counterpart of the mode, keeping whether frozen data is wanted
+    pub fn delayed(&self) -> Self {
+        match self {
+            MarketDataMode::RealTime | MarketDataMode::Delayed => MarketDataMode::Delayed,
+            MarketDataMode::Frozen | MarketDataMode::DelayedFrozen => MarketDataMode::DelayedFrozen,
+        }
+    }
+}
+
+impl Display for MarketDataMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            MarketDataMode::RealTime => write!(f, "REALTIME"),
+            MarketDataMode::Frozen => write!(f, "FROZEN"),
+            MarketDataMode::Delayed => write!(f, "DELAYED"),
+            MarketDataMode::DelayedFrozen => write!(f, "DELAYED_FROZEN"),
+        }
+    }
+}
+
 //==================================================================================================
 #[repr(i32)]
 #[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
@@ -0,0 +1,130 @@
+//! Requests the orders that finished during the session as a future
+//!
+//! ReqCompletedOrders answers with one CompletedOrder event per filled, cancelled or inactive
+//! order and ends with CompletedOrdersEnd.  EClient::completed_orders() returns a future that
+//! collects them into CompletedOrder values.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::{Collected, EClient};
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderState, OrderStatusKind};
+
+//==================================================================================================
+/// An order that can no longer fill, with its final status
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CompletedOrder {
+    pub contract: Contract,
+    pub order: Order,
+    pub order_state: OrderState,
+    pub status: OrderStatusKind,
+    /// Quantity filled before the order completed.  None if TWS did not report it.
+    pub filled_quantity: Option<f64>,
+    pub total_quantity: f64,
+    /// When the order completed, as sent by TWS, such as "20231020 15:59:58 America/New_York"
+    pub completed_time: String,
+    /// TWS's description of the completion, such as "Filled Size: 100"
+    pub completed_status: String,
+}
+
+impl CompletedOrder {
+    pub fn new(contract: Contract, order: Order, order_state: OrderState) -> Self {
+        let filled_quantity = match order.filled_quantity {
+            quantity if quantity == UNSET_DOUBLE || quantity.is_nan() => None,
+            quantity => Some(quantity),
+        };
+        CompletedOrder {
+            status: order_state.order_status(),
+            filled_quantity,
+            total_quantity: order.total_quantity,
+            completed_time: order_state.completed_time.clone(),
+            completed_status: order_state.completed_status.clone(),
+            contract,
+            order,
+            order_state,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reads a CompletedOrder event
+    pub fn from_msg(msg: &ServerRspMsg) -> Option<Self> {
+        match msg {
+            ServerRspMsg::CompletedOrder {
+                contract,
+                order,
+                order_state,
+            } => Some(CompletedOrder::new(
+                contract.clone(),
+                order.clone(),
+                order_state.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Quantity left unfilled, if the filled quantity is known
+    pub fn remaining(&self) -> Option<f64> {
+        self.filled_quantity
+            .map(|filled| (self.total_quantity - filled).max(0.0))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether part of the order filled before it was cancelled or became inactive
+    pub fn is_partially_filled(&self) -> bool {
+        self.status != OrderStatusKind::Filled
+            && self.filled_quantity.is_some_and(|filled| filled > 0.0)
+    }
+}
+
+//==================================================================================================
+/// Future returned by EClient::completed_orders().  It resolves when CompletedOrdersEnd arrives
+/// and leaves other events for get_event().
+pub struct CompletedOrders<'a> {
+    client: &'a mut EClient,
+    orders: Vec<CompletedOrder>,
+}
+
+impl<'a> CompletedOrders<'a> {
+    pub(crate) fn new(client: &'a mut EClient) -> Self {
+        CompletedOrders {
+            client,
+            orders: Vec::new(),
+        }
+    }
+}
+
+impl Future for CompletedOrders<'_> {
+    type Output = Result<Vec<CompletedOrder>, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut received = Vec::new();
+        let result = self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::CompletedOrdersEnd => Ok(Collected::Finished),
+            msg => match CompletedOrder::from_msg(msg) {
+                Some(order) => {
+                    received.push(order);
+                    Ok(Collected::Consumed)
+                }
+                None => Ok(Collected::Ignored),
+            },
+        });
+        self.orders.append(&mut received);
+        match result {
+            Ok(()) => Poll::Ready(Ok(std::mem::take(&mut self.orders))),
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.client.wake_on_event(cx.waker());
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! The lifecycle of a connection to TWS or IB Gateway as an explicit state machine
+//!
+//! EClient and its decoder feed ConnectionEvents into a ConnectionWatch.  Applications read the
+//! current ConnectionState or wait for changes on a ConnectionReceiver from
+//! EClient::watch_connection(), instead of inferring the state from error codes 1100-1102.
+use std::fmt::{Display, Error, Formatter};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::core::errors::IBKRApiLibError;
+
+/// Connectivity between TWS and IB has been lost
+pub const CONNECTIVITY_LOST: i32 = 1100;
+/// Connectivity restored, but market data was lost and has to be requested again
+pub const CONNECTIVITY_RESTORED_DATA_LOST: i32 = 1101;
+/// Connectivity restored and market data kept
+pub const CONNECTIVITY_RESTORED: i32 = 1102;
+
+//==================================================================================================
+/// Where a connection is in its lifecycle
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum ConnectionState {
+    /// Never connected, or the last attempt failed
+    #[default]
+    Disconnected,
+    /// Opening the socket
+    Connecting,
+    /// The socket is open and the server version is being negotiated
+    Handshaking,
+    /// StartApi was sent.  Requests can be made.
+    ApiStarted,
+    /// TWS lost its connection to IB (error 1100).  Requests are accepted but not answered.
+    Degraded,
+    /// The socket was closed, by disconnect() or by TWS
+    Closed,
+}
+
+impl ConnectionState {
+    /// The state an event leads to.  None if the event is not expected in this state.
+    pub fn next(self, event: &ConnectionEvent) -> Option<ConnectionState> {
+        use ConnectionState as State;
+        match (self, event) {
+            (State::Disconnected | State::Closed, ConnectionEvent::Connect) => {
+                Some(State::Connecting)
+            }
+            (State::Connecting, ConnectionEvent::HandshakeStarted) => Some(State::Handshaking),
+            (State::Connecting | State::Handshaking, ConnectionEvent::ConnectFailed) => {
+                Some(State::Disconnected)
+            }
+            (State::Handshaking, ConnectionEvent::ApiStarted) => Some(State::ApiStarted),
+            (State::ApiStarted, ConnectionEvent::ConnectivityLost) => Some(State::Degraded),
+            (State::Degraded, ConnectionEvent::ConnectivityRestored { .. }) => {
+                Some(State::ApiStarted)
+            }
+            (
+                State::Connecting | State::Handshaking | State::ApiStarted | State::Degraded,
+                ConnectionEvent::SocketClosed | ConnectionEvent::Disconnect,
+            ) => Some(State::Closed),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether requests can be sent.  They are in Degraded too, but TWS answers them late.
+    pub fn is_usable(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::ApiStarted | ConnectionState::Degraded
+        )
+    }
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+//==================================================================================================
+/// Something that moves a connection from one state to another
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// EClient::connect() was called
+    Connect,
+    /// The socket is open and the API version range was sent
+    HandshakeStarted,
+    /// Opening the socket or the handshake failed
+    ConnectFailed,
+    /// StartApi was sent
+    ApiStarted,
+    /// Error 1100
+    ConnectivityLost,
+    /// Error 1101 or 1102.  `data_lost` is true for 1101, after which market data has to be
+    /// requested again.
+    ConnectivityRestored { data_lost: bool },
+    /// The reader found the socket closed
+    SocketClosed,
+    /// EClient::disconnect() or shutdown() was called
+    Disconnect,
+}
+
+impl ConnectionEvent {
+    /// The event an error code from TWS stands for, if any
+    pub fn from_error_code(error_code: i32) -> Option<Self> {
+        match ConnectivityNotice::from_error(error_code, "")? {
+            ConnectivityNotice::Lost => Some(ConnectionEvent::ConnectivityLost),
+            ConnectivityNotice::Restored { data_maintained } => {
+                Some(ConnectionEvent::ConnectivityRestored {
+                    data_lost: !data_maintained,
+                })
+            }
+            ConnectivityNotice::Farm { .. } => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// The kind of data farm a status notice is about
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FarmKind {
+    MarketData,
+    /// Historical market data (HMDS)
+    Historical,
+    /// Security definitions, used for contract details
+    SecurityDefinition,
+}
+
+//==================================================================================================
+/// The state of a data farm connection reported by TWS
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FarmStatus {
+    Ok,
+    Broken,
+    /// Not connected, but connected again on demand
+    Inactive,
+    Connecting,
+}
+
+//==================================================================================================
+/// What an ErrMsg about connectivity means
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ConnectivityNotice {
+    /// Error 1100
+    Lost,
+    /// Error 1101 (data_maintained false) or 1102 (data_maintained true).  Market data has to be
+    /// requested again when it was not maintained.
+    Restored { data_maintained: bool },
+    /// Errors 2103-2108, 2119, 2157 and 2158.  `name` is the farm, such as "usfarm.nj", or empty
+    /// if TWS did not say.
+    Farm {
+        kind: FarmKind,
+        name: String,
+        status: FarmStatus,
+    },
+}
+
+impl ConnectivityNotice {
+    /// Reads an ErrMsg's code and text.  None for errors that are not about connectivity.
+    pub fn from_error(error_code: i32, error_str: &str) -> Option<Self> {
+        let farm = |kind, status| {
+            // "Market data farm connection is OK:usfarm.nj"
+            let name = match error_str.split_once(':') {
+                Some((_, name)) => name.trim().to_string(),
+                None => String::new(),
+            };
+            Some(ConnectivityNotice::Farm { kind, name, status })
+        };
+        match error_code {
+            CONNECTIVITY_LOST => Some(ConnectivityNotice::Lost),
+            CONNECTIVITY_RESTORED_DATA_LOST => Some(ConnectivityNotice::Restored {
+                data_maintained: false,
+            }),
+            CONNECTIVITY_RESTORED => Some(ConnectivityNotice::Restored {
+                data_maintained: true,
+            }),
+            2103 => farm(FarmKind::MarketData, FarmStatus::Broken),
+            2104 => farm(FarmKind::MarketData, FarmStatus::Ok),
+            2105 => farm(FarmKind::Historical, FarmStatus::Broken),
+            2106 => farm(FarmKind::Historical, FarmStatus::Ok),
+            2107 => farm(FarmKind::Historical, FarmStatus::Inactive),
+            2108 => farm(FarmKind::MarketData, FarmStatus::Inactive),
+            2119 => farm(FarmKind::MarketData, FarmStatus::Connecting),
+            2157 => farm(FarmKind::SecurityDefinition, FarmStatus::Broken),
+            2158 => farm(FarmKind::SecurityDefinition, FarmStatus::Ok),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+struct Watched {
+    state: ConnectionState,
+    /// The event that led to `state`
+    event: Option<ConnectionEvent>,
+    /// Incremented on every change, so receivers can tell whether they have seen it
+    version: u64,
+}
+
+type Shared = Arc<(Mutex<Watched>, Condvar)>;
+
+fn lock(shared: &Shared) -> MutexGuard<'_, Watched> {
+    // the state is replaced as a whole, so a panicking thread cannot leave it half updated
+    shared
+        .0
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+//==================================================================================================
+/// Holds the state of one connection and notifies receivers of changes.  Clones share the state.
+#[derive(Clone)]
+pub struct ConnectionWatch {
+    shared: Shared,
+}
+
+impl Default for ConnectionWatch {
+    fn default() -> Self {
+        ConnectionWatch {
+            shared: Arc::new((
+                Mutex::new(Watched {
+                    state: ConnectionState::Disconnected,
+                    event: None,
+                    version: 0,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+}
+
+impl ConnectionWatch {
+    pub fn new() -> Self {
+        ConnectionWatch::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn state(&self) -> ConnectionState {
+        lock(&self.shared).state
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A receiver that has seen the current state
+    pub fn subscribe(&self) -> ConnectionReceiver {
+        ConnectionReceiver {
+            shared: self.shared.clone(),
+            seen: lock(&self.shared).version,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Moves to the state the event leads to and wakes the receivers.  Events that are not
+    /// expected in the current state are ignored.  Returns the new state if it changed.
+    pub(crate) fn apply(&self, event: ConnectionEvent) -> Option<ConnectionState> {
+        let mut watched = lock(&self.shared);
+        let next = match watched.state.next(&event) {
+            Some(next) => next,
+            None => {
+                debug!(state = %watched.state, ?event, "ignoring connection event");
+                return None;
+            }
+        };
+        info!(from = %watched.state, to = %next, ?event, "connection state changed");
+        watched.state = next;
+        watched.event = Some(event);
+        watched.version += 1;
+        self.shared.1.notify_all();
+        Some(next)
+    }
+}
+
+//==================================================================================================
+/// Receives the changes of a ConnectionWatch.  Like a watch channel, it only keeps the latest
+/// state: changes that happen between two reads are seen as one.
+#[derive(Clone)]
+pub struct ConnectionReceiver {
+    shared: Shared,
+    seen: u64,
+}
+
+impl ConnectionReceiver {
+    /// The current state, without marking it as seen
+    pub fn borrow(&self) -> ConnectionState {
+        lock(&self.shared).state
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The event that led to the current state
+    pub fn last_event(&self) -> Option<ConnectionEvent> {
+        lock(&self.shared).event
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the state changed since it was last seen
+    pub fn has_changed(&self) -> bool {
+        lock(&self.shared).version != self.seen
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits until the state changes, then marks it as seen and returns it.  Returns
+    /// RecvTimeoutError::Timeout if it does not change within `timeout`.
+    pub fn changed(&mut self, timeout: Duration) -> Result<ConnectionState, IBKRApiLibError> {
+        let deadline = Instant::now() + timeout;
+        let mut watched = lock(&self.shared);
+        while watched.version == self.seen {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout));
+            }
+            watched = self
+                .shared
+                .1
+                .wait_timeout(watched, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        self.seen = watched.version;
+        Ok(watched.state)
+    }
+}
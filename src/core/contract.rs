@@ -6,6 +6,10 @@ use serde::{Deserialize, Serialize};
 // 0.2.6 (the trait)
 
 use crate::core::common::TagValue;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::trading_hours::TradingHours;
+
+pub mod combo;
 
 //==================================================================================================
 #[repr(i32)]
@@ -402,6 +406,18 @@ impl ContractDetails {
             notes,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// The sessions of trading_hours, in the contract's time zone
+    pub fn trading_schedule(&self) -> Result<TradingHours, IBKRApiLibError> {
+        TradingHours::parse(&self.trading_hours, &self.time_zone_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The sessions of liquid_hours, the regular trading hours
+    pub fn liquid_schedule(&self) -> Result<TradingHours, IBKRApiLibError> {
+        TradingHours::parse(&self.liquid_hours, &self.time_zone_id)
+    }
 }
 
 impl Display for ContractDetails {
@@ -0,0 +1,250 @@
+//! Builders for combo (BAG) contracts: vertical spreads, calendars, butterflies and stock
+//! against option combos
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{Action, TagValue, UNSET_DOUBLE};
+use crate::core::contract::{ComboLeg, Contract, PositionType};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::{Order, OrderComboLeg};
+
+const DEFAULT_EXCHANGE: &str = "SMART";
+
+//==================================================================================================
+/// One leg of a combo
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpreadLeg {
+    pub con_id: i32,
+    pub symbol: String,
+    pub currency: String,
+    /// Exchange of the leg.  Empty uses the exchange of the combo.
+    pub exchange: String,
+    pub action: Action,
+    pub ratio: u32,
+    pub open_close: PositionType,
+    /// Limit price of this leg, sent as an order combo leg
+    pub price: Option<f64>,
+}
+
+impl SpreadLeg {
+    /// # Arguments
+    /// * contract - Contract of the leg.  It must have its con_id.
+    /// * action - Side of the leg when the combo is bought
+    /// * ratio - Quantity of the leg per combo
+    pub fn new(contract: &Contract, action: Action, ratio: u32) -> Self {
+        SpreadLeg {
+            con_id: contract.con_id,
+            symbol: contract.symbol.clone(),
+            currency: contract.currency.clone(),
+            exchange: contract.exchange.clone(),
+            action,
+            ratio,
+            open_close: PositionType::SamePos,
+            price: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn buy(contract: &Contract, ratio: u32) -> Self {
+        SpreadLeg::new(contract, Action::BUY, ratio)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn sell(contract: &Contract, ratio: u32) -> Self {
+        SpreadLeg::new(contract, Action::SELL, ratio)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn price(mut self, price: f64) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn exchange(mut self, exchange: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn open_close(mut self, open_close: PositionType) -> Self {
+        self.open_close = open_close;
+        self
+    }
+}
+
+//==================================================================================================
+/// Builds a BAG contract from legs, and the matching order combo legs and smart combo routing
+/// parameters.
+///
+/// The presets describe buying the spread; sell the combo to take the other side.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SpreadBuilder {
+    legs: Vec<SpreadLeg>,
+    exchange: String,
+    routing_params: Vec<TagValue>,
+}
+
+impl SpreadBuilder {
+    pub fn new() -> Self {
+        SpreadBuilder {
+            exchange: DEFAULT_EXCHANGE.to_string(),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buys `long` and sells `short`, for example a bull call spread
+    pub fn vertical(long: &Contract, short: &Contract) -> Self {
+        SpreadBuilder::new()
+            .leg(SpreadLeg::buy(long, 1))
+            .leg(SpreadLeg::sell(short, 1))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sells the near expiry and buys the far expiry of the same strike
+    pub fn calendar(near: &Contract, far: &Contract) -> Self {
+        SpreadBuilder::new()
+            .leg(SpreadLeg::sell(near, 1))
+            .leg(SpreadLeg::buy(far, 1))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buys one each of the outer strikes and sells two of the middle strike
+    pub fn butterfly(lower: &Contract, middle: &Contract, upper: &Contract) -> Self {
+        SpreadBuilder::new()
+            .leg(SpreadLeg::buy(lower, 1))
+            .leg(SpreadLeg::sell(middle, 2))
+            .leg(SpreadLeg::buy(upper, 1))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buys stock and sells one option against it, for example a buy-write
+    ///
+    /// # Arguments
+    /// * stock - The underlying stock
+    /// * option - The option sold
+    /// * shares - Shares per option, usually the option multiplier
+    pub fn stock_vs_option(stock: &Contract, option: &Contract, shares: u32) -> Self {
+        SpreadBuilder::new()
+            .leg(SpreadLeg::buy(stock, shares))
+            .leg(SpreadLeg::sell(option, 1))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn leg(mut self, leg: SpreadLeg) -> Self {
+        self.legs.push(leg);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Exchange of the combo, SMART by default
+    pub fn exchange(mut self, exchange: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Lets SMART routing execute the legs separately.  The combo is then not guaranteed to fill
+    /// completely or at the combo price.
+    pub fn non_guaranteed(self, non_guaranteed: bool) -> Self {
+        self.routing_param("NonGuaranteed", if non_guaranteed { "1" } else { "0" })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a smart combo routing parameter, replacing one with the same tag
+    pub fn routing_param(mut self, tag: &str, value: &str) -> Self {
+        self.routing_params.retain(|param| param.tag != tag);
+        self.routing_params
+            .push(TagValue::new(tag.to_string(), value.to_string()));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn legs(&self) -> &[SpreadLeg] {
+        &self.legs
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        if self.legs.len() < 2 {
+            return Err(IBKRApiLibError::Config(
+                "a combo needs at least two legs".to_string(),
+            ));
+        }
+        if let Some(leg) = self.legs.iter().find(|leg| leg.con_id == 0) {
+            return Err(IBKRApiLibError::Config(format!(
+                "leg {} has no con_id, look up its contract details first",
+                leg.symbol
+            )));
+        }
+        if let Some(leg) = self.legs.iter().find(|leg| leg.ratio == 0) {
+            return Err(IBKRApiLibError::Config(format!(
+                "leg {} has a ratio of 0",
+                leg.con_id
+            )));
+        }
+        let currency = &self.legs[0].currency;
+        if self.legs.iter().any(|leg| &leg.currency != currency) {
+            return Err(IBKRApiLibError::Config(
+                "all legs of a combo must trade in the same currency".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The BAG contract.  Its symbol is the underlying symbol, or the symbols of all legs
+    /// joined by commas when they differ.
+    pub fn contract(&self) -> Result<Contract, IBKRApiLibError> {
+        self.validate()?;
+        let mut symbols: Vec<&str> = Vec::new();
+        for leg in &self.legs {
+            if !symbols.contains(&leg.symbol.as_str()) {
+                symbols.push(&leg.symbol);
+            }
+        }
+
+        let combo_legs = self
+            .legs
+            .iter()
+            .map(|leg| ComboLeg {
+                con_id: leg.con_id,
+                ratio: leg.ratio as f64,
+                action: leg.action.to_string(),
+                exchange: if leg.exchange.is_empty() {
+                    self.exchange.clone()
+                } else {
+                    leg.exchange.clone()
+                },
+                open_close: leg.open_close,
+                short_sale_slot: 0,
+                designated_location: "".to_string(),
+                exempt_code: -1,
+            })
+            .collect();
+        Ok(Contract {
+            symbol: symbols.join(","),
+            sec_type: "BAG".to_string(),
+            currency: self.legs[0].currency.clone(),
+            exchange: self.exchange.clone(),
+            combo_legs,
+            ..Default::default()
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the per-leg prices and smart combo routing parameters of an order for the combo.
+    /// Legs without a price are sent unpriced; when no leg has a price, no order combo legs are
+    /// sent and the order's limit price applies to the whole combo.
+    pub fn apply(&self, order: &mut Order) {
+        order.order_combo_legs = if self.legs.iter().any(|leg| leg.price.is_some()) {
+            self.legs
+                .iter()
+                .map(|leg| OrderComboLeg::new(leg.price.unwrap_or(UNSET_DOUBLE)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        order.smart_combo_routing_params = self.routing_params.clone();
+    }
+}
@@ -0,0 +1,242 @@
+//! Caches contract details so repeated lookups of the same contract do not each cost a
+//! ReqContractData round trip.  With the `storage` feature the cache can be kept in a JSON file
+//! and reloaded after a restart.
+use std::collections::HashMap;
+#[cfg(feature = "storage")]
+use std::fs;
+#[cfg(feature = "storage")]
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "client-sync", feature = "storage"))]
+use tracing::warn;
+
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+use crate::core::contract::{Contract, ContractDetails};
+#[cfg(any(feature = "client-sync", feature = "storage"))]
+use crate::core::errors::IBKRApiLibError;
+#[cfg(feature = "client-sync")]
+use crate::core::errors::{is_informational, TwsApiReportableError};
+#[cfg(feature = "client-sync")]
+use crate::core::messages::ServerRspMsg;
+#[cfg(feature = "client-sync")]
+use crate::core::req_ids::RequestKind;
+
+//==================================================================================================
+/// The fields of a contract that identify a lookup.  Compared case-insensitively.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ContractKey {
+    pub symbol: String,
+    pub sec_type: String,
+    pub exchange: String,
+    pub currency: String,
+    pub last_trade_date: String,
+}
+
+impl ContractKey {
+    pub fn from_contract(contract: &Contract) -> Self {
+        ContractKey {
+            symbol: contract.symbol.to_uppercase(),
+            sec_type: contract.sec_type.to_uppercase(),
+            exchange: contract.exchange.to_uppercase(),
+            currency: contract.currency.to_uppercase(),
+            last_trade_date: contract.last_trade_date_or_contract_month.clone(),
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CacheEntry {
+    key: ContractKey,
+    /// Seconds since the epoch
+    fetched_at: u64,
+    details: Vec<ContractDetails>,
+}
+
+//==================================================================================================
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+//==================================================================================================
+fn is_fresh(entry: &CacheEntry, ttl: Option<Duration>) -> bool {
+    ttl.is_none_or(|ttl| now_secs().saturating_sub(entry.fetched_at) < ttl.as_secs())
+}
+
+//==================================================================================================
+/// Contract details keyed by ContractKey.  Entries older than the time to live are treated as
+/// missing; without a time to live they never expire.
+#[derive(Debug, Default)]
+pub struct ContractCache {
+    entries: HashMap<ContractKey, CacheEntry>,
+    ttl: Option<Duration>,
+    #[cfg(feature = "storage")]
+    path: Option<PathBuf>,
+}
+
+impl ContractCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets how long entries stay valid
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Number of entries, including expired ones not pruned yet
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The cached details of a contract, None if they are missing or expired
+    pub fn get(&self, contract: &Contract) -> Option<&[ContractDetails]> {
+        self.entries
+            .get(&ContractKey::from_contract(contract))
+            .filter(|entry| is_fresh(entry, self.ttl))
+            .map(|entry| entry.details.as_slice())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn insert(&mut self, contract: &Contract, details: Vec<ContractDetails>) {
+        let key = ContractKey::from_contract(contract);
+        self.entries.insert(
+            key.clone(),
+            CacheEntry {
+                key,
+                fetched_at: now_secs(),
+                details,
+            },
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes the entry of a contract, so the next fetch asks TWS again
+    pub fn invalidate(&mut self, contract: &Contract) {
+        self.entries.remove(&ContractKey::from_contract(contract));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Drops expired entries
+    pub fn prune(&mut self) {
+        let ttl = self.ttl;
+        self.entries.retain(|_, entry| is_fresh(entry, ttl));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the details of a contract from the cache, or requests them and waits for them.
+    /// Fetched details are saved to the store if the cache has one.
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * contract - The contract to look up
+    /// * timeout - How long to wait for TWS to send all the details
+    #[cfg(feature = "client-sync")]
+    pub fn fetch(
+        &mut self,
+        client: &mut EClient,
+        contract: &Contract,
+        timeout: Duration,
+    ) -> Result<Vec<ContractDetails>, IBKRApiLibError> {
+        if let Some(details) = self.get(contract) {
+            return Ok(details.to_vec());
+        }
+
+        let req_id = client.next_req_id(RequestKind::ContractDetails, &contract.symbol);
+        client.req_contract_details(req_id, contract)?;
+        let mut details = Vec::new();
+        client.collect(timeout, |msg| match msg {
+            ServerRspMsg::ContractData {
+                req_id: id,
+                contract_details,
+            }
+            | ServerRspMsg::BondContractData {
+                req_id: id,
+                contract_details,
+            } if *id == req_id => {
+                details.push(contract_details.clone());
+                Ok(Collected::Consumed)
+            }
+            ServerRspMsg::ContractDataEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        })?;
+
+        self.insert(contract, details.clone());
+        #[cfg(feature = "storage")]
+        if let Err(err) = self.save() {
+            warn!(%err, "contract cache was not saved");
+        }
+        Ok(details)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Opens a cache kept in a JSON file.  The file is created on the first save if it does not
+    /// exist; expired entries are dropped when it is read.
+    ///
+    /// # Arguments
+    /// * path - The file holding the cache
+    /// * ttl - How long entries stay valid, None to keep them until invalidated
+    #[cfg(feature = "storage")]
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Option<Duration>) -> Result<Self, IBKRApiLibError> {
+        let mut cache = ContractCache {
+            ttl,
+            path: Some(path.as_ref().to_path_buf()),
+            ..Default::default()
+        };
+        if path.as_ref().exists() {
+            let text = fs::read_to_string(path.as_ref())?;
+            let entries: Vec<CacheEntry> = serde_json::from_str(&text).map_err(|err| {
+                IBKRApiLibError::Parse(format!("invalid contract cache file: {}", err))
+            })?;
+            cache.entries = entries
+                .into_iter()
+                .map(|entry| (entry.key.clone(), entry))
+                .collect();
+            cache.prune();
+        }
+        Ok(cache)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Writes the cache to its file.  Does nothing for a cache that was not opened from a file.
+    #[cfg(feature = "storage")]
+    pub fn save(&self) -> Result<(), IBKRApiLibError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let entries: Vec<&CacheEntry> = self.entries.values().collect();
+        let text = serde_json::to_string(&entries).map_err(|err| {
+            IBKRApiLibError::Config(format!("contract cache not serializable: {}", err))
+        })?;
+        // written next to the file and renamed, so a crash never leaves half a cache
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
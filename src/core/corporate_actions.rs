@@ -0,0 +1,207 @@
+//! Detects splits and dividends by comparing ADJUSTED_LAST bars with TRADES bars
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::BarData;
+
+/// Relative change in the adjustment factor below which it is treated as rounding noise
+pub const DEFAULT_TOLERANCE: f64 = 0.0005;
+
+/// Adjustment factors at or above this are treated as dividends, below it as splits
+const DIVIDEND_FACTOR_FLOOR: f64 = 0.85;
+
+/// How effective dates are displayed
+const DATE_FORMAT: &str = "%Y%m%d";
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CorporateActionKind {
+    /// `numerator` shares for every `denominator` held.  A reverse split has numerator <
+    /// denominator.
+    Split { numerator: u32, denominator: u32 },
+    /// Cash dividend per share, estimated from the previous close
+    Dividend { amount: f64 },
+    /// An adjustment that fits neither pattern, for example a spin-off
+    Other,
+}
+
+//==================================================================================================
+/// An adjustment that takes effect on `effective_date`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CorporateAction {
+    /// Date of the first bar after the adjustment, as given in BarData::date
+    pub effective_date: DateTime<Utc>,
+    pub kind: CorporateActionKind,
+    /// Multiplier applied to prices before the effective date
+    pub factor: f64,
+    /// Unadjusted close of the last bar before the effective date
+    pub previous_close: f64,
+}
+
+impl Display for CorporateAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match &self.kind {
+            CorporateActionKind::Split {
+                numerator,
+                denominator,
+            } => write!(
+                f,
+                "{}: split {}:{}",
+                self.effective_date.format(DATE_FORMAT),
+                numerator,
+                denominator
+            ),
+            CorporateActionKind::Dividend { amount } => {
+                write!(
+                    f,
+                    "{}: dividend {:.4}",
+                    self.effective_date.format(DATE_FORMAT),
+                    amount
+                )
+            }
+            CorporateActionKind::Other => {
+                write!(
+                    f,
+                    "{}: adjustment {:.6}",
+                    self.effective_date.format(DATE_FORMAT),
+                    self.factor
+                )
+            }
+        }
+    }
+}
+
+//==================================================================================================
+/// Corporate actions in date order
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CorporateActionTimeline {
+    pub actions: Vec<CorporateAction>,
+}
+
+impl CorporateActionTimeline {
+    /// Compares a TRADES series with an ADJUSTED_LAST series of the same contract and bar size.
+    /// Bars are paired by date; dates present in only one series, and bars without a date, are
+    /// skipped.
+    ///
+    /// # Arguments
+    /// * trades - Unadjusted bars, oldest first
+    /// * adjusted - Adjusted bars
+    /// * tolerance - Relative change in the adjustment factor to ignore, see DEFAULT_TOLERANCE
+    pub fn reconcile(trades: &[BarData], adjusted: &[BarData], tolerance: f64) -> Self {
+        let adjusted_closes: HashMap<DateTime<Utc>, f64> = adjusted
+            .iter()
+            .filter_map(|bar| Some((bar.date?, bar.close)))
+            .collect();
+
+        // (date, unadjusted close, factor) for every bar found in both series
+        let factors: Vec<(DateTime<Utc>, f64, f64)> = trades
+            .iter()
+            .filter(|bar| bar.close > 0.0)
+            .filter_map(|bar| {
+                let date = bar.date?;
+                adjusted_closes
+                    .get(&date)
+                    .map(|adjusted| (date, bar.close, adjusted / bar.close))
+            })
+            .collect();
+
+        let mut actions = Vec::new();
+        for pair in factors.windows(2) {
+            let (_, previous_close, before) = pair[0];
+            let (date, _, after) = pair[1];
+            if after <= 0.0 {
+                continue;
+            }
+            let factor = before / after;
+            if (factor - 1.0).abs() <= tolerance {
+                continue;
+            }
+            actions.push(CorporateAction {
+                effective_date: date,
+                kind: classify(factor, previous_close),
+                factor,
+                previous_close,
+            });
+        }
+        CorporateActionTimeline { actions }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Product of the factors of all actions effective after `date`
+    pub fn cumulative_factor(&self, date: DateTime<Utc>) -> f64 {
+        self.actions
+            .iter()
+            .filter(|action| action.effective_date > date)
+            .map(|action| action.factor)
+            .product()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adjusts unadjusted bars in place, as ADJUSTED_LAST would.  Volumes are scaled by splits
+    /// only.  Bars without a date are left as they are.
+    pub fn adjust(&self, bars: &mut [BarData]) {
+        for bar in bars.iter_mut() {
+            let date = match bar.date {
+                Some(date) => date,
+                None => continue,
+            };
+            let factor = self.cumulative_factor(date);
+            bar.open *= factor;
+            bar.high *= factor;
+            bar.low *= factor;
+            bar.close *= factor;
+            bar.average *= factor;
+
+            let split_factor: f64 = self
+                .actions
+                .iter()
+                .filter(|action| action.effective_date > date)
+                .filter(|action| matches!(action.kind, CorporateActionKind::Split { .. }))
+                .map(|action| action.factor)
+                .product();
+            bar.volume = (bar.volume as f64 / split_factor).round() as i64;
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn splits(&self) -> impl Iterator<Item = &CorporateAction> {
+        self.actions
+            .iter()
+            .filter(|action| matches!(action.kind, CorporateActionKind::Split { .. }))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn dividends(&self) -> impl Iterator<Item = &CorporateAction> {
+        self.actions
+            .iter()
+            .filter(|action| matches!(action.kind, CorporateActionKind::Dividend { .. }))
+    }
+}
+
+//==================================================================================================
+fn classify(factor: f64, previous_close: f64) -> CorporateActionKind {
+    if (DIVIDEND_FACTOR_FLOOR..1.0).contains(&factor) {
+        return CorporateActionKind::Dividend {
+            amount: (1.0 - factor) * previous_close,
+        };
+    }
+
+    // shares after the split per share before it
+    let ratio = 1.0 / factor;
+    for denominator in 1..=10u32 {
+        let numerator = (ratio * denominator as f64).round();
+        if numerator >= 1.0
+            && numerator as u32 != denominator
+            && ((numerator / denominator as f64) - ratio).abs() / ratio < 0.01
+        {
+            return CorporateActionKind::Split {
+                numerator: numerator as u32,
+                denominator,
+            };
+        }
+    }
+    CorporateActionKind::Other
+}
@@ -0,0 +1,315 @@
+//! Cryptocurrency contracts and orders
+//!
+//! IB trades crypto as SecType CRYPTO on PAXOS, in fractional quantities.  It accepts fewer
+//! order types than for stocks: market orders must be immediate or cancel, and a market buy is
+//! given as an amount of cash to spend rather than a quantity of coins.  CryptoOrder builds
+//! orders from exact decimal amounts and checks them, and EClient::place_order() refuses crypto
+//! orders that break these rules before they reach TWS.
+//!
+//! ```no_run
+//! use ibtwsapi::core::crypto::{CryptoAsset, CryptoOrder};
+//! use rust_decimal_macros::dec;
+//!
+//! let contract = CryptoAsset::Btc.contract();
+//! let spend_100_usd = CryptoOrder::buy_cash(dec!(100)).build().unwrap();
+//! let sell_at_limit = CryptoOrder::sell(dec!(0.0015))
+//!     .limit(dec!(65000.37))
+//!     .min_tick(dec!(0.25))
+//!     .build()
+//!     .unwrap();
+//! ```
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::Order;
+
+pub const CRYPTO_SEC_TYPE: &str = "CRYPTO";
+pub const PAXOS: &str = "PAXOS";
+
+/// Order types IB accepts for crypto
+pub const CRYPTO_ORDER_TYPES: [&str; 3] = ["MKT", "LMT", "STP LMT"];
+/// Decimals of the smallest coin quantity IB accepts
+pub const QUANTITY_DECIMALS: u32 = 8;
+/// Decimals of a cash quantity
+pub const CASH_DECIMALS: u32 = 2;
+
+//==================================================================================================
+/// The coins IB trades on PAXOS
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CryptoAsset {
+    Btc,
+    Eth,
+    Ltc,
+    Bch,
+}
+
+impl CryptoAsset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CryptoAsset::Btc => "BTC",
+            CryptoAsset::Eth => "ETH",
+            CryptoAsset::Ltc => "LTC",
+            CryptoAsset::Bch => "BCH",
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The contract of the coin against USD on PAXOS
+    pub fn contract(self) -> Contract {
+        crypto_contract(self.as_str(), "USD")
+    }
+}
+
+//==================================================================================================
+/// A crypto contract on PAXOS
+///
+/// # Arguments
+/// * symbol - The coin, such as BTC
+/// * currency - The currency it is traded against, such as USD
+pub fn crypto_contract(symbol: &str, currency: &str) -> Contract {
+    Contract {
+        symbol: symbol.to_uppercase(),
+        sec_type: CRYPTO_SEC_TYPE.to_string(),
+        exchange: PAXOS.to_string(),
+        currency: currency.to_uppercase(),
+        ..Default::default()
+    }
+}
+
+//==================================================================================================
+pub fn is_crypto(contract: &Contract) -> bool {
+    contract.sec_type.eq_ignore_ascii_case(CRYPTO_SEC_TYPE)
+}
+
+//==================================================================================================
+/// How long a crypto order works
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CryptoTif {
+    /// Immediate or cancel, the only choice for market orders
+    Ioc,
+    /// Good till cancelled
+    Gtc,
+}
+
+impl CryptoTif {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CryptoTif::Ioc => "IOC",
+            CryptoTif::Gtc => "GTC",
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Amount {
+    Quantity(Decimal),
+    Cash(Decimal),
+}
+
+//==================================================================================================
+/// Builds a crypto order from decimal amounts.  The order is a market order unless limit() or
+/// stop_limit() is called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CryptoOrder {
+    buy: bool,
+    amount: Amount,
+    lmt_price: Option<Decimal>,
+    stop_price: Option<Decimal>,
+    tif: Option<CryptoTif>,
+    min_tick: Option<Decimal>,
+}
+
+impl CryptoOrder {
+    fn new(buy: bool, amount: Amount) -> Self {
+        CryptoOrder {
+            buy,
+            amount,
+            lmt_price: None,
+            stop_price: None,
+            tif: None,
+            min_tick: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buys a quantity of coins.  A market buy must use buy_cash() instead.
+    pub fn buy(quantity: Decimal) -> Self {
+        CryptoOrder::new(true, Amount::Quantity(quantity))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Buys coins for an amount of the contract's currency, sent as the order's cash_qty
+    pub fn buy_cash(cash: Decimal) -> Self {
+        CryptoOrder::new(true, Amount::Cash(cash))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn sell(quantity: Decimal) -> Self {
+        CryptoOrder::new(false, Amount::Quantity(quantity))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn limit(mut self, price: Decimal) -> Self {
+        self.lmt_price = Some(price);
+        self.stop_price = None;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A limit order that starts working when the price reaches `stop`
+    pub fn stop_limit(mut self, stop: Decimal, limit: Decimal) -> Self {
+        self.stop_price = Some(stop);
+        self.lmt_price = Some(limit);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Defaults to IOC for market orders and GTC for the others
+    pub fn tif(mut self, tif: CryptoTif) -> Self {
+        self.tif = Some(tif);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The price increment of the contract, from ContractDetails::min_tick.  Prices are rounded
+    /// to it, down for buys and up for sells.
+    pub fn min_tick(mut self, tick: Decimal) -> Self {
+        self.min_tick = Some(tick);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn round_price(&self, price: Decimal) -> Decimal {
+        match self.min_tick {
+            Some(tick) if tick > Decimal::ZERO => {
+                let steps = price / tick;
+                let steps = if self.buy {
+                    steps.floor()
+                } else {
+                    steps.ceil()
+                };
+                (steps * tick).normalize()
+            }
+            _ => price,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The order, with quantities rounded down to 8 decimals and cash to 2
+    pub fn build(&self) -> Result<Order, IBKRApiLibError> {
+        let order_type = match (self.stop_price, self.lmt_price) {
+            (Some(_), _) => "STP LMT",
+            (None, Some(_)) => "LMT",
+            (None, None) => "MKT",
+        };
+        let tif = self.tif.unwrap_or(if order_type == "MKT" {
+            CryptoTif::Ioc
+        } else {
+            CryptoTif::Gtc
+        });
+        let (total_quantity, cash_qty) = match self.amount {
+            Amount::Quantity(quantity) => (
+                to_f64(
+                    quantity.round_dp_with_strategy(QUANTITY_DECIMALS, RoundingStrategy::ToZero),
+                )?,
+                UNSET_DOUBLE,
+            ),
+            Amount::Cash(cash) => (
+                0.0,
+                to_f64(cash.round_dp_with_strategy(CASH_DECIMALS, RoundingStrategy::ToZero))?,
+            ),
+        };
+        let price = |price: Option<Decimal>| match price {
+            Some(price) => to_f64(self.round_price(price)),
+            None => Ok(UNSET_DOUBLE),
+        };
+
+        let order = Order {
+            action: if self.buy { "BUY" } else { "SELL" }.to_string(),
+            order_type: order_type.to_string(),
+            total_quantity,
+            cash_qty,
+            lmt_price: price(self.lmt_price)?,
+            aux_price: price(self.stop_price)?,
+            tif: tif.as_str().to_string(),
+            ..Default::default()
+        };
+        validate_crypto_order(&order)?;
+        Ok(order)
+    }
+}
+
+//==================================================================================================
+fn to_f64(value: Decimal) -> Result<f64, IBKRApiLibError> {
+    value
+        .to_f64()
+        .ok_or_else(|| IBKRApiLibError::Config(format!("{} is not a valid amount", value)))
+}
+
+//==================================================================================================
+fn is_set(value: f64) -> bool {
+    value != UNSET_DOUBLE && value != 0.0
+}
+
+//==================================================================================================
+/// Checks an order for a crypto contract against the rules IB applies to crypto
+///
+/// * The order type is MKT, LMT or STP LMT
+/// * A market order is IOC, and the others IOC or GTC
+/// * A market buy gives a cash quantity and no quantity
+/// * Every other order gives a quantity and no cash quantity
+/// * Limit and stop prices are positive
+pub fn validate_crypto_order(order: &Order) -> Result<(), IBKRApiLibError> {
+    let refuse = |reason: String| Err(IBKRApiLibError::Config(format!("crypto order {}", reason)));
+
+    let order_type = order.order_type.to_uppercase();
+    if !CRYPTO_ORDER_TYPES.contains(&order_type.as_str()) {
+        return refuse(format!("type {:?} is not supported", order.order_type));
+    }
+    let buy = match order.action.to_uppercase().as_str() {
+        "BUY" => true,
+        "SELL" => false,
+        action => return refuse(format!("action {:?} is not supported", action)),
+    };
+    let market = order_type == "MKT";
+
+    let tif = order.tif.to_uppercase();
+    let tif_valid = if market {
+        tif == "IOC"
+    } else {
+        tif == "IOC" || tif == "GTC"
+    };
+    if !tif_valid {
+        return refuse(format!(
+            "time in force {:?} is not supported for {}",
+            order.tif, order_type
+        ));
+    }
+
+    let has_quantity = order.total_quantity > 0.0;
+    let has_cash = order.has_cash_qty();
+    if market && buy {
+        if !has_cash || order.cash_qty < 0.0 || has_quantity {
+            return refuse("market buy needs a positive cash quantity and no quantity".to_string());
+        }
+    } else if has_cash || !has_quantity {
+        return refuse(format!(
+            "{} {} needs a positive quantity and no cash quantity",
+            order.action, order_type
+        ));
+    }
+
+    let positive = |price: f64| is_set(price) && price > 0.0;
+    if !(market || positive(order.lmt_price)) {
+        return refuse("needs a positive limit price".to_string());
+    }
+    if order_type == "STP LMT" && !positive(order.aux_price) {
+        return refuse("needs a positive stop price".to_string());
+    }
+    Ok(())
+}
@@ -1,31 +1,40 @@
 //! Receives messages from Reader, decodes messages, and feeds them to Cmd  Queue
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use std::ops::Deref;
-use std::slice::Iter;
 use std::str::FromStr;
 use std::string::ToString;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use float_cmp::*;
-use log::*;
 use num_traits::float::FloatCore;
 use num_traits::FromPrimitive;
 use rust_decimal::Decimal;
+use tracing::field::Empty;
+use tracing::{debug, debug_span, error, info, warn};
 
-use crate::core::client::ConnStatus;
+use crate::core::account_values::{AccountKey, AccountValue};
+use crate::core::buffer_pool::BufferPool;
 use crate::core::common::{
-    BarData, CommissionReport, DepthMktDataDescription, FamilyCode, HistogramData, HistoricalTick,
-    HistoricalTickBidAsk, HistoricalTickLast, NewsProvider, PriceIncrement, RealTimeBar,
-    SmartComponent, TagValue, TickAttrib, TickAttribBidAsk, TickAttribLast, TickMsgType, TickType,
-    MAX_MSG_LEN, NO_VALID_ID, UNSET_DOUBLE, UNSET_INTEGER,
+    AccountId, BarData, CommissionReport, ConnStatus, DepthMktDataDescription, Environment,
+    FamilyCode, HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast,
+    NewsProvider, PriceIncrement, RealTimeBar, SmartComponent, TagValue, TickAttrib,
+    TickAttribBidAsk, TickAttribLast, TickByTickData, TickType, MAX_MSG_LEN, NO_VALID_ID,
+    UNSET_DOUBLE, UNSET_INTEGER,
 };
+use crate::core::connection::{ConnectionEvent, ConnectionWatch};
 use crate::core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract};
 use crate::core::errors::{IBKRApiLibError, TwsError};
+use crate::core::event_queue::EventSender;
 use crate::core::execution::Execution;
-use crate::core::messages::{read_fields, ServerRspMsg, ServerRspMsgDiscriminants};
-use crate::core::order::{Order, OrderState, SoftDollarTier};
+use crate::core::messages::{FieldIterator, ServerRspMsg, ServerRspMsgDiscriminants};
+#[cfg(feature = "metrics")]
+use crate::core::metrics;
+use crate::core::order::{Order, OrderState, OrderStatusKind, SoftDollarTier, WhyHeld};
 use crate::core::order_decoder::OrderDecoder;
 use crate::core::scanner::ScanData;
 use crate::core::server_versions::{
@@ -38,9 +47,12 @@ use crate::core::server_versions::{
     MIN_SERVER_VER_SYNT_REALTIME_BARS, MIN_SERVER_VER_UNDERLYING_INFO,
     MIN_SERVER_VER_UNREALIZED_PNL,
 };
+#[cfg(feature = "codec")]
+use crate::core::test_vectors::CaptureRecorder;
+use crate::core::time::parse_session_time;
 
 //==================================================================================================
-pub fn decode_i32(iter: &mut Iter<String>) -> Result<i32, IBKRApiLibError> {
+pub fn decode_i32(iter: &mut FieldIterator) -> Result<i32, IBKRApiLibError> {
     let next = iter.next();
 
     let val: i32 = next.unwrap().parse().unwrap_or(0);
@@ -48,15 +60,19 @@ pub fn decode_i32(iter: &mut Iter<String>) -> Result<i32, IBKRApiLibError> {
 }
 
 //==================================================================================================
-pub fn decode_tick_type(iter: &mut Iter<String>) -> Result<TickType, IBKRApiLibError> {
-    let next = iter.next();
-
-    let val: TickType = next.unwrap().parse().unwrap_or(TickType::NotSet);
+/// TWS sends tick types as numbers.  Names are accepted too.
+pub fn decode_tick_type(iter: &mut FieldIterator) -> Result<TickType, IBKRApiLibError> {
+    let next = iter.next().unwrap();
+
+    let val: TickType = match next.parse::<i32>() {
+        Ok(number) => FromPrimitive::from_i32(number).unwrap_or(TickType::NotSet),
+        Err(_) => next.parse().unwrap_or(TickType::NotSet),
+    };
     Ok(val)
 }
 
 //==================================================================================================
-pub fn decode_i32_show_unset(iter: &mut Iter<String>) -> Result<i32, IBKRApiLibError> {
+pub fn decode_i32_show_unset(iter: &mut FieldIterator) -> Result<i32, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
     let retval: i32 = next.unwrap().parse().unwrap_or(0);
@@ -64,7 +80,7 @@ pub fn decode_i32_show_unset(iter: &mut Iter<String>) -> Result<i32, IBKRApiLibE
 }
 
 //==================================================================================================
-pub fn decode_i64(iter: &mut Iter<String>) -> Result<i64, IBKRApiLibError> {
+pub fn decode_i64(iter: &mut FieldIterator) -> Result<i64, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
     let val: i64 = next.unwrap().parse().unwrap_or(0);
@@ -72,7 +88,7 @@ pub fn decode_i64(iter: &mut Iter<String>) -> Result<i64, IBKRApiLibError> {
 }
 
 //==================================================================================================
-pub fn decode_f64(iter: &mut Iter<String>) -> Result<f64, IBKRApiLibError> {
+pub fn decode_f64(iter: &mut FieldIterator) -> Result<f64, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
     let val = next.unwrap().parse().unwrap_or(0.0);
@@ -80,7 +96,7 @@ pub fn decode_f64(iter: &mut Iter<String>) -> Result<f64, IBKRApiLibError> {
 }
 
 //==================================================================================================
-pub fn decode_f64_show_unset(iter: &mut Iter<String>) -> Result<f64, IBKRApiLibError> {
+pub fn decode_f64_show_unset(iter: &mut FieldIterator) -> Result<f64, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
     let retval: f64 = next.unwrap().parse().unwrap_or(0.0);
@@ -88,33 +104,68 @@ pub fn decode_f64_show_unset(iter: &mut Iter<String>) -> Result<f64, IBKRApiLibE
 }
 
 //==================================================================================================
-pub fn decode_string(iter: &mut Iter<String>) -> Result<String, IBKRApiLibError> {
+pub fn decode_string(iter: &mut FieldIterator) -> Result<String, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
-    let val = next.unwrap().parse().unwrap_or("".to_string());
-    Ok(val)
+    Ok(next.unwrap().to_string())
+}
+
+//==================================================================================================
+/// Decodes a time in any format TWS sends, and the text it was read from.  Times sent without a
+/// zone name are read in zone, the zone of the TWS session if it is known.  Text that is not a
+/// time, or a zone-less time when the zone is unknown, is logged and decodes as None.
+pub fn decode_time(
+    iter: &mut FieldIterator,
+    zone: Option<Tz>,
+) -> Result<(Option<DateTime<Utc>>, String), IBKRApiLibError> {
+    let text = decode_string(iter)?;
+    let time = parse_session_time(&text, zone);
+    if time.is_none() {
+        warn!("Cannot read {:?} as a time in zone {:?}", text, zone);
+    }
+    Ok((time, text))
 }
 
 //==================================================================================================
-pub fn decode_bool(iter: &mut Iter<String>) -> Result<bool, IBKRApiLibError> {
+pub fn decode_bool(iter: &mut FieldIterator) -> Result<bool, IBKRApiLibError> {
     let next = iter.next();
     //info!("{:?}", next);
-    let retval: i32 = next.unwrap_or(&"0".to_string()).parse().unwrap_or(0);
+    let retval: i32 = next.unwrap_or("0").parse().unwrap_or(0);
     Ok(retval != 0)
 }
 
 //==================================================================================================
 pub struct Decoder {
     msg_queue: Receiver<String>,
-    send_queue: Sender<ServerRspMsg>,
+    send_queue: EventSender,
     pub server_version: i32,
+    /// Zone of the times TWS sends without a zone name, that of the TWS session.  Set from the
+    /// connection time of the handshake, None if its zone is unknown.
+    pub time_zone: Option<Tz>,
     conn_state: Arc<Mutex<ConnStatus>>,
+    /// Set from the managed accounts list, shared with the EClient
+    pub(crate) environment: Arc<Mutex<Environment>>,
+    /// Set from the managed accounts list, shared with the EClient
+    pub(crate) managed_accounts: Arc<Mutex<Vec<AccountId>>>,
+    /// Lifecycle state, shared with the EClient
+    pub(crate) connection: ConnectionWatch,
+    /// Decoded frames are given back to this pool, shared with the reader
+    pub(crate) buffers: BufferPool,
+    /// Orders of the executions whose commission report has not arrived yet
+    exec_orders: HashMap<String, i32>,
+    /// Read times of the frames in msg_queue, when latency is measured
+    pub(crate) read_times: Option<Receiver<Instant>>,
+    /// Read time of the frame being decoded
+    read: Option<Instant>,
+    /// Receives every frame run() decodes, when the session is recorded
+    #[cfg(feature = "codec")]
+    pub(crate) recorder: Option<CaptureRecorder>,
 }
 
 impl Decoder {
     pub fn new(
         msg_queue: Receiver<String>,
-        send_queue: Sender<ServerRspMsg>,
+        send_queue: EventSender,
         server_version: i32,
         conn_state: Arc<Mutex<ConnStatus>>,
     ) -> Self {
@@ -122,207 +173,224 @@ impl Decoder {
             send_queue: send_queue,
             msg_queue: msg_queue,
             server_version,
+            time_zone: Some(Tz::UTC),
             conn_state,
+            environment: Arc::new(Mutex::new(Environment::Unknown)),
+            managed_accounts: Arc::new(Mutex::new(Vec::new())),
+            connection: ConnectionWatch::new(),
+            buffers: BufferPool::new(),
+            exec_orders: HashMap::new(),
+            read_times: None,
+            read: None,
+            #[cfg(feature = "codec")]
+            recorder: None,
         }
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn interpret(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        if fields.is_empty() {
-            return Ok(());
+    /// Queues a decoded message.  It is announced in a span carrying its request or order id,
+    /// so a request or an order can be followed from the request to the last response.
+    fn emit(&mut self, msg: ServerRspMsg) {
+        let order_id = match &msg {
+            ServerRspMsg::ExecutionData { execution, .. } => {
+                self.exec_orders
+                    .insert(execution.exec_id.clone(), execution.order_id);
+                Some(execution.order_id)
+            }
+            // commission reports only carry the execution id
+            ServerRspMsg::CommissionReport { commission_report } => {
+                self.exec_orders.remove(&commission_report.exec_id)
+            }
+            other => other.order_id(),
+        };
+        let span = debug_span!("tws_event", req_id = Empty, order_id = Empty);
+        if let Some(req_id) = msg.req_id() {
+            span.record("req_id", req_id);
+        }
+        if let Some(order_id) = order_id {
+            span.record("order_id", order_id);
         }
+        span.in_scope(|| debug!(msg = %msg, "received"));
+        if let ServerRspMsg::ErrMsg { error_code, .. } = &msg {
+            if let Some(event) = ConnectionEvent::from_error_code(*error_code) {
+                self.connection.apply(event);
+            }
+        }
+        match self.read {
+            Some(read) => self
+                .send_queue
+                .send_stamped(msg, read, Instant::now())
+                .unwrap(),
+            None => self.send_queue.send(msg).unwrap(),
+        }
+    }
 
-        let msg_id = i32::from_str(fields.get(0).unwrap().as_str())?;
+    //----------------------------------------------------------------------------------------------
+    pub fn interpret(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let msg_id = match FieldIterator::new(msg).next() {
+            Some(msg_id) => i32::from_str(msg_id)?,
+            None => return Ok(()),
+        };
+        #[cfg(feature = "metrics")]
+        metrics::message_decoded(msg_id);
 
         match FromPrimitive::from_i32(msg_id) {
-            Some(ServerRspMsgDiscriminants::TickPrice) => self.process_tick_price(fields)?,
-            Some(ServerRspMsgDiscriminants::AccountSummary) => {
-                self.process_account_summary(fields)?
-            }
+            Some(ServerRspMsgDiscriminants::TickPrice) => self.process_tick_price(msg)?,
+            Some(ServerRspMsgDiscriminants::AccountSummary) => self.process_account_summary(msg)?,
             Some(ServerRspMsgDiscriminants::AccountSummaryEnd) => {
-                self.process_account_summary_end(fields)?
+                self.process_account_summary_end(msg)?
             }
             Some(ServerRspMsgDiscriminants::AccountUpdateMulti) => {
-                self.process_account_update_multi(fields)?
+                self.process_account_update_multi(msg)?
             }
             Some(ServerRspMsgDiscriminants::AccountUpdateMultiEnd) => {
-                self.process_account_update_multi_end(fields)?
+                self.process_account_update_multi_end(msg)?
             }
             Some(ServerRspMsgDiscriminants::AcctDownloadEnd) => {
-                self.process_account_download_end(fields)?
+                self.process_account_download_end(msg)?
             }
             Some(ServerRspMsgDiscriminants::AcctUpdateTime) => {
-                self.process_account_update_time(fields)?
+                self.process_account_update_time(msg)?
             }
-            Some(ServerRspMsgDiscriminants::AcctValue) => self.process_account_value(fields)?,
+            Some(ServerRspMsgDiscriminants::AcctValue) => self.process_account_value(msg)?,
             Some(ServerRspMsgDiscriminants::BondContractData) => {
-                self.process_bond_contract_data(fields)?
+                self.process_bond_contract_data(msg)?
             }
             Some(ServerRspMsgDiscriminants::CommissionReport) => {
-                self.process_commission_report(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::CompletedOrder) => {
-                self.process_completed_order(fields)?
+                self.process_commission_report(msg)?
             }
+            Some(ServerRspMsgDiscriminants::CompletedOrder) => self.process_completed_order(msg)?,
             Some(ServerRspMsgDiscriminants::CompletedOrdersEnd) => {
                 self.process_end_msg_noarg(ServerRspMsg::CompletedOrdersEnd)?
             }
-            Some(ServerRspMsgDiscriminants::ContractData) => {
-                self.process_contract_details(fields)?
-            }
+            Some(ServerRspMsgDiscriminants::ContractData) => self.process_contract_details(msg)?,
             Some(ServerRspMsgDiscriminants::ContractDataEnd) => {
-                self.process_contract_details_end(fields)?
+                self.process_contract_details_end(msg)?
             }
-            Some(ServerRspMsgDiscriminants::CurrentTime) => self.process_current_time(fields)?,
+            Some(ServerRspMsgDiscriminants::CurrentTime) => self.process_current_time(msg)?,
             Some(ServerRspMsgDiscriminants::DeltaNeutralValidation) => {
-                self.process_delta_neutral_validation(fields)?
+                self.process_delta_neutral_validation(msg)?
             }
             Some(ServerRspMsgDiscriminants::DisplayGroupList) => {
-                self.process_display_group_list(fields)?
+                self.process_display_group_list(msg)?
             }
             Some(ServerRspMsgDiscriminants::DisplayGroupUpdated) => {
-                self.process_display_group_updated(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::ErrMsg) => self.process_error_message(fields)?,
-            Some(ServerRspMsgDiscriminants::ExecutionData) => {
-                self.process_execution_data(fields)?
+                self.process_display_group_updated(msg)?
             }
+            Some(ServerRspMsgDiscriminants::ErrMsg) => self.process_error_message(msg)?,
+            Some(ServerRspMsgDiscriminants::ExecutionData) => self.process_execution_data(msg)?,
             Some(ServerRspMsgDiscriminants::ExecutionDataEnd) => {
-                self.process_execution_data_end(fields)?
+                self.process_execution_data_end(msg)?
             }
-            Some(ServerRspMsgDiscriminants::FamilyCodes) => self.process_family_codes(fields)?,
+            Some(ServerRspMsgDiscriminants::FamilyCodes) => self.process_family_codes(msg)?,
             Some(ServerRspMsgDiscriminants::FundamentalData) => {
-                self.process_fundamental_data(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::HeadTimestamp) => {
-                self.process_head_timestamp(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::HistogramData) => {
-                self.process_histogram_data(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::HistoricalData) => {
-                self.process_historical_data(fields)?
+                self.process_fundamental_data(msg)?
             }
+            Some(ServerRspMsgDiscriminants::HeadTimestamp) => self.process_head_timestamp(msg)?,
+            Some(ServerRspMsgDiscriminants::HistogramData) => self.process_histogram_data(msg)?,
+            Some(ServerRspMsgDiscriminants::HistoricalData) => self.process_historical_data(msg)?,
             Some(ServerRspMsgDiscriminants::HistoricalDataUpdate) => {
-                self.process_historical_data_update(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::HistoricalNews) => {
-                self.process_historical_news(fields)?
+                self.process_historical_data_update(msg)?
             }
+            Some(ServerRspMsgDiscriminants::HistoricalNews) => self.process_historical_news(msg)?,
             Some(ServerRspMsgDiscriminants::HistoricalNewsEnd) => {
-                self.process_historical_news_end(fields)?
+                self.process_historical_news_end(msg)?
             }
             Some(ServerRspMsgDiscriminants::HistoricalTicks) => {
-                self.process_historical_ticks(fields)?
+                self.process_historical_ticks(msg)?
             }
             Some(ServerRspMsgDiscriminants::HistoricalTicksBidAsk) => {
-                self.process_historical_ticks_bid_ask(fields)?
+                self.process_historical_ticks_bid_ask(msg)?
             }
 
             Some(ServerRspMsgDiscriminants::HistoricalTicksLast) => {
-                self.process_historical_ticks_last(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::ManagedAccts) => {
-                self.process_managed_accounts(fields)?
+                self.process_historical_ticks_last(msg)?
             }
+            Some(ServerRspMsgDiscriminants::ManagedAccts) => self.process_managed_accounts(msg)?,
             Some(ServerRspMsgDiscriminants::MarketDataType) => {
-                self.process_market_data_type(fields)?
+                self.process_market_data_type(msg)?
             }
-            Some(ServerRspMsgDiscriminants::MarketDepth) => self.process_market_depth(fields)?,
-            Some(ServerRspMsgDiscriminants::MarketDepthL2) => {
-                self.process_market_depth_l2(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::MarketRule) => self.process_market_rule(fields)?,
+            Some(ServerRspMsgDiscriminants::MarketDepth) => self.process_market_depth(msg)?,
+            Some(ServerRspMsgDiscriminants::MarketDepthL2) => self.process_market_depth_l2(msg)?,
+            Some(ServerRspMsgDiscriminants::MarketRule) => self.process_market_rule(msg)?,
             Some(ServerRspMsgDiscriminants::MktDepthExchanges) => {
-                self.process_market_depth_exchanges(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::NewsArticle) => self.process_news_article(fields)?,
-            Some(ServerRspMsgDiscriminants::NewsBulletins) => {
-                self.process_news_bulletins(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::NewsProviders) => {
-                self.process_news_providers(fields)?
+                self.process_market_depth_exchanges(msg)?
             }
-            Some(ServerRspMsgDiscriminants::NextValidId) => self.process_next_valid_id(fields)?,
-            Some(ServerRspMsgDiscriminants::OpenOrder) => self.process_open_order(fields)?,
+            Some(ServerRspMsgDiscriminants::NewsArticle) => self.process_news_article(msg)?,
+            Some(ServerRspMsgDiscriminants::NewsBulletins) => self.process_news_bulletins(msg)?,
+            Some(ServerRspMsgDiscriminants::NewsProviders) => self.process_news_providers(msg)?,
+            Some(ServerRspMsgDiscriminants::NextValidId) => self.process_next_valid_id(msg)?,
+            Some(ServerRspMsgDiscriminants::OpenOrder) => self.process_open_order(msg)?,
             Some(ServerRspMsgDiscriminants::OpenOrderEnd) => {
                 self.process_end_msg_noarg(ServerRspMsg::OpenOrderEnd)?
             }
-            Some(ServerRspMsgDiscriminants::OrderStatus) => self.process_order_status(fields)?,
-            Some(ServerRspMsgDiscriminants::OrderBound) => self.process_order_bound(fields)?,
-            Some(ServerRspMsgDiscriminants::Pnl) => self.process_pnl(fields)?,
-            Some(ServerRspMsgDiscriminants::PnlSingle) => self.process_pnl_single(fields)?,
-            Some(ServerRspMsgDiscriminants::PortfolioValue) => {
-                self.process_portfolio_value(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::PositionData) => self.process_position_data(fields)?,
+            Some(ServerRspMsgDiscriminants::OrderStatus) => self.process_order_status(msg)?,
+            Some(ServerRspMsgDiscriminants::OrderBound) => self.process_order_bound(msg)?,
+            Some(ServerRspMsgDiscriminants::Pnl) => self.process_pnl(msg)?,
+            Some(ServerRspMsgDiscriminants::PnlSingle) => self.process_pnl_single(msg)?,
+            Some(ServerRspMsgDiscriminants::PortfolioValue) => self.process_portfolio_value(msg)?,
+            Some(ServerRspMsgDiscriminants::PositionData) => self.process_position_data(msg)?,
             Some(ServerRspMsgDiscriminants::PositionEnd) => {
                 self.process_end_msg_noarg(ServerRspMsg::PositionEnd)?
             }
-            Some(ServerRspMsgDiscriminants::RealTimeBars) => self.process_real_time_bars(fields)?,
-            Some(ServerRspMsgDiscriminants::ReceiveFa) => self.process_receive_fa(fields)?,
+            Some(ServerRspMsgDiscriminants::RealTimeBars) => self.process_real_time_bars(msg)?,
+            Some(ServerRspMsgDiscriminants::ReceiveFa) => self.process_receive_fa(msg)?,
             Some(ServerRspMsgDiscriminants::RerouteMktDataReq) => {
-                self.process_reroute_mkt_data_req(fields)?
+                self.process_reroute_mkt_data_req(msg)?
             }
 
-            Some(ServerRspMsgDiscriminants::PositionMulti) => {
-                self.process_position_multi(fields)?
-            }
+            Some(ServerRspMsgDiscriminants::PositionMulti) => self.process_position_multi(msg)?,
             Some(ServerRspMsgDiscriminants::PositionMultiEnd) => {
-                self.process_position_multi_end(fields)?
+                self.process_position_multi_end(msg)?
             }
-            Some(ServerRspMsgDiscriminants::ScannerData) => self.process_scanner_data(fields)?,
+            Some(ServerRspMsgDiscriminants::ScannerData) => self.process_scanner_data(msg)?,
             Some(ServerRspMsgDiscriminants::ScannerParameters) => {
-                self.process_scanner_parameters(fields)?
+                self.process_scanner_parameters(msg)?
             }
             Some(ServerRspMsgDiscriminants::SecurityDefinitionOptionParameter) => {
-                self.process_security_definition_option_parameter(fields)?
+                self.process_security_definition_option_parameter(msg)?
             }
             Some(ServerRspMsgDiscriminants::SecurityDefinitionOptionParameterEnd) => {
-                self.process_security_definition_option_parameter_end(fields)?
+                self.process_security_definition_option_parameter_end(msg)?
             }
 
             Some(ServerRspMsgDiscriminants::SmartComponents) => {
-                self.process_smart_components(fields)?
+                self.process_smart_components(msg)?
             }
             Some(ServerRspMsgDiscriminants::SoftDollarTiers) => {
-                self.process_soft_dollar_tiers(fields)?
+                self.process_soft_dollar_tiers(msg)?
             }
-            Some(ServerRspMsgDiscriminants::SymbolSamples) => {
-                self.process_symbol_samples(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::TickByTick) => self.process_tick_by_tick(fields)?,
-            Some(ServerRspMsgDiscriminants::TickEfp) => self.process_tick_by_tick(fields)?,
-            Some(ServerRspMsgDiscriminants::TickGeneric) => self.process_tick_generic(fields)?,
-            Some(ServerRspMsgDiscriminants::TickNews) => self.process_tick_news(fields)?,
+            Some(ServerRspMsgDiscriminants::SymbolSamples) => self.process_symbol_samples(msg)?,
+            Some(ServerRspMsgDiscriminants::TickByTick) => self.process_tick_by_tick(msg)?,
+            Some(ServerRspMsgDiscriminants::TickEfp) => self.process_tick_by_tick(msg)?,
+            Some(ServerRspMsgDiscriminants::TickGeneric) => self.process_tick_generic(msg)?,
+            Some(ServerRspMsgDiscriminants::TickNews) => self.process_tick_news(msg)?,
             Some(ServerRspMsgDiscriminants::TickOptionComputation) => {
-                self.process_tick_option_computation(fields)?
-            }
-            Some(ServerRspMsgDiscriminants::TickReqParams) => {
-                self.process_tick_req_params(fields)?
+                self.process_tick_option_computation(msg)?
             }
-            Some(ServerRspMsgDiscriminants::TickSize) => self.process_tick_size(fields)?,
+            Some(ServerRspMsgDiscriminants::TickReqParams) => self.process_tick_req_params(msg)?,
+            Some(ServerRspMsgDiscriminants::TickSize) => self.process_tick_size(msg)?,
             Some(ServerRspMsgDiscriminants::TickSnapshotEnd) => {
-                self.process_tick_snapshot_end(fields)?
+                self.process_tick_snapshot_end(msg)?
             }
-            Some(ServerRspMsgDiscriminants::TickString) => self.process_tick_string(fields)?,
+            Some(ServerRspMsgDiscriminants::TickString) => self.process_tick_string(msg)?,
             Some(ServerRspMsgDiscriminants::VerifyAndAuthCompleted) => {
-                self.process_verify_and_auth_completed(fields)?
+                self.process_verify_and_auth_completed(msg)?
             }
 
             Some(ServerRspMsgDiscriminants::VerifyCompleted) => {
-                self.process_verify_completed(fields)?
+                self.process_verify_completed(msg)?
             }
 
             Some(ServerRspMsgDiscriminants::VerifyMessageApi) => {
-                self.process_verify_completed(fields)?
+                self.process_verify_completed(msg)?
             }
 
             Some(ServerRspMsgDiscriminants::VerifyAndAuthMessageApi) => {
-                self.process_verify_and_auth_message_api(fields)?
+                self.process_verify_and_auth_message_api(msg)?
             }
             Some(ServerRspMsgDiscriminants::RerouteMktDepthReq) => {
-                self.process_reroute_mkt_depth_req(fields)?
+                self.process_reroute_mkt_depth_req(msg)?
             }
 
             _ => panic!("Received unkown message id!!  Exiting..."),
@@ -331,8 +399,8 @@ impl Decoder {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_price(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_price(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -364,7 +432,7 @@ impl Decoder {
             tick_attr: tick_attrib.clone(),
         };
 
-        self.send_queue.send(tick_price.clone()).unwrap();
+        self.emit(tick_price.clone());
 
         if let ServerRspMsg::TickPrice { .. } = tick_price {
             // process ver 2 fields
@@ -384,7 +452,7 @@ impl Decoder {
                     tick_type: size_tick_type,
                     size: size,
                 };
-                self.send_queue.send(tick_size).unwrap();
+                self.emit(tick_size);
             }
         }
 
@@ -392,8 +460,8 @@ impl Decoder {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_string(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_string(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -406,14 +474,14 @@ impl Decoder {
             value: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(tick_string).unwrap();
+        self.emit(tick_string);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_summary(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_summary(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -428,14 +496,14 @@ impl Decoder {
             currency: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(account_summary).unwrap();
+        self.emit(account_summary);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_summary_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_summary_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -446,40 +514,41 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(account_summary_end).unwrap();
+        self.emit(account_summary_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_update_multi(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_update_multi(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
         //throw away version
         fields_itr.next();
 
+        let req_id = decode_i32(&mut fields_itr)?;
+        let account = decode_string(&mut fields_itr)?;
+        let model_code = decode_string(&mut fields_itr)?;
+        let key = decode_string(&mut fields_itr)?;
+        let value = decode_string(&mut fields_itr)?;
         let account_update_multi = ServerRspMsg::AccountUpdateMulti {
-            req_id: decode_i32(&mut fields_itr)?,
-            account: decode_string(&mut fields_itr)?,
-            model_code: decode_string(&mut fields_itr)?,
-            key: decode_string(&mut fields_itr)?,
-            value: decode_string(&mut fields_itr)?,
-            currency: decode_string(&mut fields_itr)?,
+            req_id,
+            account,
+            model_code,
+            key: AccountKey::from(key.as_str()),
+            value: AccountValue::parse(&value, &decode_string(&mut fields_itr)?),
         };
 
-        self.send_queue.send(account_update_multi).unwrap();
+        self.emit(account_update_multi);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_update_multi_end(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_update_multi_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -490,14 +559,14 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(account_update_multi_end).unwrap();
+        self.emit(account_update_multi_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_download_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_download_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -508,14 +577,14 @@ impl Decoder {
             account_name: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(account_download_end).unwrap();
+        self.emit(account_download_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_update_time(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_update_time(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -526,35 +595,37 @@ impl Decoder {
             time_stamp: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(update_account_time).unwrap();
+        self.emit(update_account_time);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_account_value(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_account_value(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
         //throw away version
         fields_itr.next();
 
+        let key = decode_string(&mut fields_itr)?;
+        let value = decode_string(&mut fields_itr)?;
+        let currency = decode_string(&mut fields_itr)?;
         let update_account_value = ServerRspMsg::AcctValue {
-            key: decode_string(&mut fields_itr)?,
-            val: decode_string(&mut fields_itr)?,
-            currency: decode_string(&mut fields_itr)?,
+            key: AccountKey::from(key.as_str()),
+            value: AccountValue::parse(&value, &currency),
             account_name: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(update_account_value).unwrap();
+        self.emit(update_account_value);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_bond_contract_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_bond_contract_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -577,9 +648,9 @@ impl Decoder {
         contract.ratings = decode_string(&mut fields_itr)?;
         contract.bond_type = decode_string(&mut fields_itr)?;
         contract.coupon_type = decode_string(&mut fields_itr)?;
-        contract.convertible = i32::from_str(fields_itr.next().unwrap().as_ref())? != 0;
-        contract.callable = i32::from_str(fields_itr.next().unwrap().as_ref())? != 0;
-        contract.putable = i32::from_str(fields_itr.next().unwrap().as_ref())? != 0;
+        contract.convertible = i32::from_str(fields_itr.next().unwrap())? != 0;
+        contract.callable = i32::from_str(fields_itr.next().unwrap())? != 0;
+        contract.putable = i32::from_str(fields_itr.next().unwrap())? != 0;
         contract.desc_append = decode_string(&mut fields_itr)?;
         contract.contract.exchange = decode_string(&mut fields_itr)?;
         contract.contract.currency = decode_string(&mut fields_itr)?;
@@ -629,14 +700,14 @@ impl Decoder {
             contract_details: contract.clone(),
         };
 
-        self.send_queue.send(bond_contract_details).unwrap();
+        self.emit(bond_contract_details);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_commission_report(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_commission_report(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
         //throw away version
@@ -654,14 +725,14 @@ impl Decoder {
             commission_report: commission_report.clone(),
         };
 
-        self.send_queue.send(commission_report).unwrap();
+        self.emit(commission_report);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_completed_order(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_completed_order(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -686,14 +757,14 @@ impl Decoder {
             order_state: order_state.clone(),
         };
 
-        self.send_queue.send(completed_order).unwrap();
+        self.emit(completed_order);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_contract_details(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_contract_details(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -781,14 +852,14 @@ impl Decoder {
             contract_details: contract.clone(),
         };
 
-        self.send_queue.send(contract_details).unwrap();
+        self.emit(contract_details);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_contract_details_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_contract_details_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -799,14 +870,14 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(contract_details_end).unwrap();
+        self.emit(contract_details_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_current_time(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_current_time(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -817,17 +888,14 @@ impl Decoder {
             time: decode_i64(&mut fields_itr)?,
         };
 
-        self.send_queue.send(current_time).unwrap();
+        self.emit(current_time);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_delta_neutral_validation(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_delta_neutral_validation(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -847,14 +915,14 @@ impl Decoder {
             delta_neutral_contract: delta_neutral_contract.clone(),
         };
 
-        self.send_queue.send(delta_neutral_validation).unwrap();
+        self.emit(delta_neutral_validation);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_display_group_list(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_display_group_list(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -866,14 +934,14 @@ impl Decoder {
             groups: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(display_group_list).unwrap();
+        self.emit(display_group_list);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_display_group_updated(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_display_group_updated(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -885,12 +953,12 @@ impl Decoder {
             contract_info: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(display_group_updated).unwrap();
+        self.emit(display_group_updated);
 
         Ok(())
     }
-    fn process_error_message(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_error_message(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -903,14 +971,14 @@ impl Decoder {
             error_str: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(error).unwrap();
+        self.emit(error);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_execution_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_execution_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -951,7 +1019,12 @@ impl Decoder {
         let mut execution = Execution::default();
         execution.order_id = order_id;
         execution.exec_id = decode_string(&mut fields_itr)?;
-        execution.time = decode_string(&mut fields_itr)?;
+        let (time, _raw) = decode_time(&mut fields_itr, self.time_zone)?;
+        execution.time = time;
+        #[cfg(feature = "raw-times")]
+        {
+            execution.time_raw = _raw;
+        }
         execution.acct_number = decode_string(&mut fields_itr)?;
         execution.exchange = decode_string(&mut fields_itr)?;
         execution.side = decode_string(&mut fields_itr)?;
@@ -963,7 +1036,7 @@ impl Decoder {
         }
 
         execution.price = decode_f64(&mut fields_itr)?;
-        execution.perm_id = decode_i32(&mut fields_itr)?; // ver 2 field
+        execution.perm_id = decode_i64(&mut fields_itr)?; // ver 2 field
         execution.client_id = decode_i32(&mut fields_itr)?; // ver 3 field
         execution.liquidation = decode_i32(&mut fields_itr)?; // ver 4 field
 
@@ -979,9 +1052,9 @@ impl Decoder {
         if version >= 9 {
             execution.ev_rule = decode_string(&mut fields_itr)?;
 
-            let tmp_ev_mult = (&mut fields_itr).peekable().peek().unwrap().as_str();
+            let tmp_ev_mult = fields_itr.next().unwrap_or("");
             if tmp_ev_mult != "" {
-                execution.ev_multiplier = decode_f64(&mut fields_itr)?;
+                execution.ev_multiplier = tmp_ev_mult.parse().unwrap_or(0.0);
             } else {
                 execution.ev_multiplier = 1.0;
             }
@@ -1000,14 +1073,14 @@ impl Decoder {
             execution: execution.clone(),
         };
 
-        self.send_queue.send(exec_details).unwrap();
+        self.emit(exec_details);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_execution_data_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_execution_data_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1018,14 +1091,14 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(exec_details_end).unwrap();
+        self.emit(exec_details_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_family_codes(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_family_codes(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1043,14 +1116,14 @@ impl Decoder {
             family_codes: family_codes.clone(),
         };
 
-        self.send_queue.send(family_codes_msg).unwrap();
+        self.emit(family_codes_msg);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_fundamental_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_fundamental_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1062,31 +1135,35 @@ impl Decoder {
             data: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(fundamental_data).unwrap();
+        self.emit(fundamental_data);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_head_timestamp(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_head_timestamp(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
 
-        let fundamental_data = ServerRspMsg::FundamentalData {
-            req_id: decode_i32(&mut fields_itr)?,
-            data: decode_string(&mut fields_itr)?,
+        let req_id = decode_i32(&mut fields_itr)?;
+        let (head_timestamp, _raw) = decode_time(&mut fields_itr, self.time_zone)?;
+        let head_timestamp = ServerRspMsg::HeadTimestamp {
+            req_id,
+            head_timestamp,
+            #[cfg(feature = "raw-times")]
+            head_timestamp_raw: _raw,
         };
 
-        self.send_queue.send(fundamental_data).unwrap();
+        self.emit(head_timestamp);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_histogram_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_histogram_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1107,14 +1184,14 @@ impl Decoder {
             items: histogram,
         };
 
-        self.send_queue.send(histogram_data).unwrap();
+        self.emit(histogram_data);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
 
@@ -1132,7 +1209,12 @@ impl Decoder {
 
         for _ in 0..bar_count {
             let mut bar = BarData::default();
-            bar.date = decode_string(&mut fields_itr)?;
+            let (date, _raw) = decode_time(&mut fields_itr, self.time_zone)?;
+            bar.date = date;
+            #[cfg(feature = "raw-times")]
+            {
+                bar.date_raw = _raw;
+            }
             bar.open = decode_f64(&mut fields_itr)?;
             bar.high = decode_f64(&mut fields_itr)?;
             bar.low = decode_f64(&mut fields_itr)?;
@@ -1155,7 +1237,7 @@ impl Decoder {
                 bar: bar.clone(),
             };
 
-            self.send_queue.send(historical_data_msg).unwrap();
+            self.emit(historical_data_msg);
         }
 
         let historical_data_end = ServerRspMsg::HistoricalDataEnd {
@@ -1165,14 +1247,14 @@ impl Decoder {
         };
 
         // send end of dataset marker
-        self.send_queue.send(historical_data_end).unwrap();
+        self.emit(historical_data_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_data_update(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_data_update(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1181,7 +1263,12 @@ impl Decoder {
 
         let mut bar = BarData::default();
         bar.bar_count = decode_i32(&mut fields_itr)?;
-        bar.date = decode_string(&mut fields_itr)?;
+        let (date, _raw) = decode_time(&mut fields_itr, self.time_zone)?;
+        bar.date = date;
+        #[cfg(feature = "raw-times")]
+        {
+            bar.date_raw = _raw;
+        }
         bar.open = decode_f64(&mut fields_itr)?;
         bar.close = decode_f64(&mut fields_itr)?;
         bar.high = decode_f64(&mut fields_itr)?;
@@ -1194,34 +1281,38 @@ impl Decoder {
             bar: bar.clone(),
         };
 
-        self.send_queue.send(historical_data_update).unwrap();
+        self.emit(historical_data_update);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_news(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_news(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
 
+        let req_id = decode_i32(&mut fields_itr)?;
+        let (time, _raw) = decode_time(&mut fields_itr, Some(Tz::UTC))?;
         let historical_news = ServerRspMsg::HistoricalNews {
-            req_id: decode_i32(&mut fields_itr)?,
-            time: decode_string(&mut fields_itr)?,
+            req_id,
+            time,
+            #[cfg(feature = "raw-times")]
+            time_raw: _raw,
             provider_code: decode_string(&mut fields_itr)?,
             article_id: decode_string(&mut fields_itr)?,
             headline: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(historical_news).unwrap();
+        self.emit(historical_news);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_news_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_news_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1232,14 +1323,14 @@ impl Decoder {
         };
 
         // send end of dataset marker
-        self.send_queue.send(historical_news_end).unwrap();
+        self.emit(historical_news_end);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_ticks(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_ticks(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1266,17 +1357,14 @@ impl Decoder {
             done: done,
         };
 
-        self.send_queue.send(historical_ticks).unwrap();
+        self.emit(historical_ticks);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_ticks_bid_ask(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_ticks_bid_ask(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1309,14 +1397,14 @@ impl Decoder {
             done: done,
         };
 
-        self.send_queue.send(historical_ticks_bid_ask).unwrap();
+        self.emit(historical_ticks_bid_ask);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_historical_ticks_last(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_historical_ticks_last(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1349,14 +1437,14 @@ impl Decoder {
             done: done,
         };
 
-        self.send_queue.send(historical_ticks_last_msg).unwrap();
+        self.emit(historical_ticks_last_msg);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_managed_accounts(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_managed_accounts(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1364,19 +1452,26 @@ impl Decoder {
         fields_itr.next();
 
         info!("calling managed_accounts");
-        let managed_accounts = ServerRspMsg::ManagedAccts {
-            accounts_list: decode_string(&mut fields_itr)?,
-        };
-
-        self.send_queue.send(managed_accounts).unwrap();
+        let accounts = AccountId::parse_list(&decode_string(&mut fields_itr)?);
+        *self
+            .environment
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Environment::of(&accounts);
+        *self
+            .managed_accounts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = accounts.clone();
+        let managed_accounts = ServerRspMsg::ManagedAccts { accounts };
+
+        self.emit(managed_accounts);
 
         info!("finished calling managed_accounts");
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_market_data_type(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_market_data_type(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
         //throw away version
@@ -1387,13 +1482,13 @@ impl Decoder {
             market_data_type: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(marketdatatype).unwrap();
+        self.emit(marketdatatype);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_market_depth(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_market_depth(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
         //throw away version
@@ -1408,14 +1503,14 @@ impl Decoder {
             size: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(update_mkt_depth).unwrap();
+        self.emit(update_mkt_depth);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_market_depth_l2(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_market_depth_l2(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1446,14 +1541,14 @@ impl Decoder {
             is_smart_depth: is_smart_depth,
         };
 
-        self.send_queue.send(update_mkt_depth_l2).unwrap();
+        self.emit(update_mkt_depth_l2);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_market_rule(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_market_rule(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1475,14 +1570,14 @@ impl Decoder {
             price_increments: price_increments,
         };
 
-        self.send_queue.send(market_rule).unwrap();
+        self.emit(market_rule);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_market_depth_exchanges(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_market_depth_exchanges(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1499,7 +1594,12 @@ impl Decoder {
                 desc.service_data_type = decode_string(&mut fields_itr)?;
                 desc.agg_group = decode_i32(&mut fields_itr)?;
             } else {
-                decode_i32(&mut fields_itr)?; // boolean notSuppIsL2
+                desc.agg_group = UNSET_INTEGER;
+                desc.service_data_type = if decode_bool(&mut fields_itr)? {
+                    "Deep2".to_string()
+                } else {
+                    "Deep".to_string()
+                };
             }
             depth_mkt_data_descriptions.push(desc);
         }
@@ -1508,14 +1608,14 @@ impl Decoder {
             depth_mkt_data_descriptions: depth_mkt_data_descriptions,
         };
 
-        self.send_queue.send(market_depth_xchng).unwrap();
+        self.emit(market_depth_xchng);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_news_article(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_news_article(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1526,13 +1626,13 @@ impl Decoder {
             article_text: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(news_article).unwrap();
+        self.emit(news_article);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_news_bulletins(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_news_bulletins(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1546,14 +1646,14 @@ impl Decoder {
             origin_exch: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(news_bulletin).unwrap();
+        self.emit(news_bulletin);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_news_providers(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_news_providers(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1571,14 +1671,14 @@ impl Decoder {
             news_providers: news_providers,
         };
 
-        self.send_queue.send(news_providers).unwrap();
+        self.emit(news_providers);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_next_valid_id(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_next_valid_id(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1589,14 +1689,14 @@ impl Decoder {
             order_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(next_valid_id).unwrap();
+        self.emit(next_valid_id);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_open_order(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_open_order(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //info!("Processing open order");
         //throw away message_id
         fields_itr.next();
@@ -1626,14 +1726,14 @@ impl Decoder {
             order_state: order_state,
         };
 
-        self.send_queue.send(open_order_msg).unwrap();
+        self.emit(open_order_msg);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_order_bound(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_order_bound(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1644,14 +1744,14 @@ impl Decoder {
             api_order_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(order_bound).unwrap();
+        self.emit(order_bound);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_order_status(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_order_status(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1662,7 +1762,7 @@ impl Decoder {
 
         let order_id = decode_i32(&mut fields_itr)?;
 
-        let status = decode_string(&mut fields_itr)?;
+        let status = OrderStatusKind::from(decode_string(&mut fields_itr)?.as_str());
 
         let filled;
         if self.server_version >= MIN_SERVER_VER_FRACTIONAL_POSITIONS {
@@ -1681,11 +1781,11 @@ impl Decoder {
 
         let avg_fill_price = decode_f64(&mut fields_itr)?;
 
-        let perm_id = decode_i32(&mut fields_itr)?; // ver 2 field
+        let perm_id = decode_i64(&mut fields_itr)?; // ver 2 field
         let parent_id = decode_i32(&mut fields_itr)?; // ver 3 field
         let last_fill_price = decode_f64(&mut fields_itr)?; // ver 4 field
         let client_id = decode_i32(&mut fields_itr)?; // ver 5 field
-        let why_held = decode_string(&mut fields_itr)?; // ver 6 field
+        let why_held = WhyHeld::from(decode_string(&mut fields_itr)?.as_str()); // ver 6 field
 
         let mut mkt_cap_price = 0.0;
         if self.server_version >= MIN_SERVER_VER_MARKET_CAP_PRICE {
@@ -1705,14 +1805,14 @@ impl Decoder {
             mkt_cap_price,
         };
 
-        self.send_queue.send(order_status).unwrap();
+        self.emit(order_status);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_pnl(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_pnl(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1737,14 +1837,14 @@ impl Decoder {
             realized_pnl,
         };
 
-        self.send_queue.send(pnl_msg).unwrap();
+        self.emit(pnl_msg);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_pnl_single(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_pnl_single(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1773,14 +1873,14 @@ impl Decoder {
             value,
         };
 
-        self.send_queue.send(pnl_single).unwrap();
+        self.emit(pnl_single);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_portfolio_value(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_portfolio_value(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1837,14 +1937,14 @@ impl Decoder {
             account_name,
         };
 
-        self.send_queue.send(update_portfolio).unwrap();
+        self.emit(update_portfolio);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_position_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_position_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1888,18 +1988,18 @@ impl Decoder {
             avg_cost,
         };
 
-        self.send_queue.send(position_data).unwrap();
+        self.emit(position_data);
 
         Ok(())
     }
 
     fn process_end_msg_noarg(&mut self, cmd: ServerRspMsg) -> Result<(), IBKRApiLibError> {
-        self.send_queue.send(cmd).unwrap();
+        self.emit(cmd);
         Ok(())
     }
 
-    fn process_position_multi(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_position_multi(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1937,14 +2037,14 @@ impl Decoder {
             avg_cost,
         };
 
-        self.send_queue.send(position_multi).unwrap();
+        self.emit(position_multi);
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_position_multi_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_position_multi_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1955,13 +2055,13 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(position_multi_end).unwrap();
+        self.emit(position_multi_end);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_real_time_bars(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_real_time_bars(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -1982,13 +2082,13 @@ impl Decoder {
 
         let real_time_bars = ServerRspMsg::RealTimeBars { req_id, bar: bar };
 
-        self.send_queue.send(real_time_bars).unwrap();
+        self.emit(real_time_bars);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_receive_fa(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_receive_fa(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2003,13 +2103,13 @@ impl Decoder {
             cxml: xml,
         };
 
-        self.send_queue.send(receive_fa).unwrap();
+        self.emit(receive_fa);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_reroute_mkt_data_req(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_reroute_mkt_data_req(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2020,13 +2120,13 @@ impl Decoder {
             exchange: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(reroute_mkt_data).unwrap();
+        self.emit(reroute_mkt_data);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_reroute_mkt_depth_req(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_reroute_mkt_depth_req(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2037,13 +2137,13 @@ impl Decoder {
             exchange: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(reroute_mkt_depth).unwrap();
+        self.emit(reroute_mkt_depth);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_scanner_data(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_scanner_data(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2085,18 +2185,18 @@ impl Decoder {
                 legs_str: data.legs,
             };
 
-            self.send_queue.send(scanner_data).unwrap();
+            self.emit(scanner_data);
         }
 
         let scanner_data_end = ServerRspMsg::ScannerDataEnd { req_id };
 
-        self.send_queue.send(scanner_data_end).unwrap();
+        self.emit(scanner_data_end);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_scanner_parameters(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_scanner_parameters(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2106,16 +2206,16 @@ impl Decoder {
         let xml = decode_string(&mut fields_itr)?;
         let scanner_params = ServerRspMsg::ScannerParameters { xml };
 
-        self.send_queue.send(scanner_params).unwrap();
+        self.emit(scanner_params);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn process_security_definition_option_parameter(
         &mut self,
-        fields: &[String],
+        msg: &str,
     ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2151,16 +2251,16 @@ impl Decoder {
             strikes,
         };
 
-        self.send_queue.send(security_def_opt_param).unwrap();
+        self.emit(security_def_opt_param);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn process_security_definition_option_parameter_end(
         &mut self,
-        fields: &[String],
+        msg: &str,
     ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2169,13 +2269,13 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(security_def_opt_param_end).unwrap();
+        self.emit(security_def_opt_param_end);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_smart_components(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_smart_components(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2196,13 +2296,13 @@ impl Decoder {
             smart_components,
         };
 
-        self.send_queue.send(smart_component_msg).unwrap();
+        self.emit(smart_component_msg);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_soft_dollar_tiers(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_soft_dollar_tiers(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2222,13 +2322,13 @@ impl Decoder {
 
         let soft_dollar_tiers = ServerRspMsg::SoftDollarTiers { req_id, tiers };
 
-        self.send_queue.send(soft_dollar_tiers).unwrap();
+        self.emit(soft_dollar_tiers);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_symbol_samples(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_symbol_samples(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2259,13 +2359,13 @@ impl Decoder {
             contract_descriptions,
         };
 
-        self.send_queue.send(symbol_samples).unwrap();
+        self.emit(symbol_samples);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_by_tick(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_by_tick(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2274,7 +2374,7 @@ impl Decoder {
         let tick_type = decode_i32(&mut fields_itr)?;
         let time = decode_i64(&mut fields_itr)?;
 
-        let tick_msg = match tick_type {
+        let tick = match tick_type {
             0 => return Ok(()), // None
             1..=2 =>
             // Last (1) or AllLast (2)
@@ -2288,7 +2388,8 @@ impl Decoder {
                 let exchange = decode_string(&mut fields_itr)?;
                 let special_conditions = decode_string(&mut fields_itr)?;
 
-                TickMsgType::AllLast {
+                TickByTickData::Last {
+                    all_last: tick_type == 2,
                     price,
                     size,
                     tick_attrib_last,
@@ -2308,7 +2409,7 @@ impl Decoder {
                 tick_attrib_bid_ask.bid_past_low = mask & 1 != 0;
                 tick_attrib_bid_ask.ask_past_high = mask & 2 != 0;
 
-                TickMsgType::BidAsk {
+                TickByTickData::BidAsk {
                     bid_price,
                     ask_price,
                     bid_size,
@@ -2321,24 +2422,19 @@ impl Decoder {
             {
                 let mid_point = decode_f64(&mut fields_itr)?;
 
-                TickMsgType::MidPoint { mid_point }
+                TickByTickData::MidPoint { mid_point }
             }
             _ => return Ok(()),
         };
-        let tick_by_tick_msg = ServerRspMsg::TickByTick {
-            req_id,
-            tick_type,
-            time,
-            tick_msg,
-        };
-        self.send_queue.send(tick_by_tick_msg).unwrap();
+        let tick_by_tick_msg = ServerRspMsg::TickByTick { req_id, time, tick };
+        self.emit(tick_by_tick_msg);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     #[allow(dead_code)]
-    fn process_tick_efp(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_efp(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2367,13 +2463,13 @@ impl Decoder {
             dividends_to_last_trade_date,
         };
 
-        self.send_queue.send(tick_efp).unwrap();
+        self.emit(tick_efp);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_generic(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_generic(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2390,36 +2486,33 @@ impl Decoder {
             value,
         };
 
-        self.send_queue.send(tick_generic).unwrap();
+        self.emit(tick_generic);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_news(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_news(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
 
         let tick_news = ServerRspMsg::TickNews {
             ticker_id: decode_i32(&mut fields_itr)?,
-            time_stamp: decode_i32(&mut fields_itr)?,
+            time_stamp: decode_i64(&mut fields_itr)?,
             provider_code: decode_string(&mut fields_itr)?,
             article_id: decode_string(&mut fields_itr)?,
             headline: decode_string(&mut fields_itr)?,
             extra_data: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(tick_news).unwrap();
+        self.emit(tick_news);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_option_computation(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_option_computation(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2498,13 +2591,13 @@ impl Decoder {
             und_price,
         };
 
-        self.send_queue.send(tick_option_computation).unwrap();
+        self.emit(tick_option_computation);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_req_params(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_req_params(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2516,13 +2609,13 @@ impl Decoder {
             snapshot_permissions: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(tick_req_params).unwrap();
+        self.emit(tick_req_params);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_size(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_size(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2535,13 +2628,13 @@ impl Decoder {
             size: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(tick_size).unwrap();
+        self.emit(tick_size);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_tick_snapshot_end(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_tick_snapshot_end(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2552,16 +2645,13 @@ impl Decoder {
             req_id: decode_i32(&mut fields_itr)?,
         };
 
-        self.send_queue.send(tick_snapshot_end).unwrap();
+        self.emit(tick_snapshot_end);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_verify_and_auth_completed(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_verify_and_auth_completed(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2576,16 +2666,13 @@ impl Decoder {
             error_text,
         };
 
-        self.send_queue.send(verify_and_auth).unwrap();
+        self.emit(verify_and_auth);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_verify_and_auth_message_api(
-        &mut self,
-        fields: &[String],
-    ) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_verify_and_auth_message_api(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
 
         //throw away message_id
         fields_itr.next();
@@ -2597,13 +2684,13 @@ impl Decoder {
             xyz_challenge: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(verify_and_auth_message).unwrap();
+        self.emit(verify_and_auth_message);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn process_verify_completed(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_verify_completed(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
         //throw away version
@@ -2617,14 +2704,14 @@ impl Decoder {
             error_text,
         };
 
-        self.send_queue.send(verify_completed).unwrap();
+        self.emit(verify_completed);
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     #[allow(dead_code)]
-    fn process_verify_message_api(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
-        let mut fields_itr = fields.iter();
+    fn process_verify_message_api(&mut self, msg: &str) -> Result<(), IBKRApiLibError> {
+        let mut fields_itr = FieldIterator::new(msg);
         //throw away message_id
         fields_itr.next();
         //throw away version
@@ -2634,7 +2721,7 @@ impl Decoder {
             api_data: decode_string(&mut fields_itr)?,
         };
 
-        self.send_queue.send(verify_message_api).unwrap();
+        self.emit(verify_message_api);
         Ok(())
     }
 
@@ -2690,16 +2777,29 @@ impl Decoder {
                             .to_string(),
                         };
 
-                        self.send_queue.send(error_msg).unwrap();
+                        self.emit(error_msg);
                         error!("Error receiving message.  Disconnected: Message too big");
-                        //self.send_queue.send(connection_closed).unwrap();
+                        //self.emit(connection_closed);
                         *self.conn_state.lock().expect(CONN_STATE_POISONED) =
                             ConnStatus::DISCONNECTED;
+                        self.connection.apply(ConnectionEvent::SocketClosed);
                         error!("Error receiving message.  Invalid size.  Disconnected.");
                         return Ok(());
                     } else {
-                        let fields = read_fields((&val).as_ref());
-                        self.interpret(fields.as_slice())?;
+                        #[cfg(feature = "metrics")]
+                        let started = Instant::now();
+                        self.read = match &self.read_times {
+                            Some(read_times) => read_times.try_recv().ok(),
+                            None => None,
+                        };
+                        #[cfg(feature = "codec")]
+                        if let Some(recorder) = &self.recorder {
+                            recorder.record(self.server_version, &val);
+                        }
+                        self.interpret(&val)?;
+                        self.buffers.give_string(val);
+                        #[cfg(feature = "metrics")]
+                        metrics::decoded_in(started.elapsed());
                     }
                 }
                 Result::Err(err) => {
@@ -2707,9 +2807,10 @@ impl Decoder {
                         != ConnStatus::DISCONNECTED as i32
                     {
                         info!("Error receiving message.  Disconnected: {:?}", err);
-                        //self.send_queue.send(connection_closed).unwrap();
+                        //self.emit(connection_closed);
                         *self.conn_state.lock().expect(CONN_STATE_POISONED) =
                             ConnStatus::DISCONNECTED;
+                        self.connection.apply(ConnectionEvent::SocketClosed);
 
                         return Ok(());
                     } else {
@@ -0,0 +1,241 @@
+//! Links an application to the window groups of TWS
+//!
+//! TWS windows sharing a display group show the same contract.  query_groups() lists the
+//! groups, and a DisplayGroup follows and changes the contract of one of them.  Contracts are
+//! exchanged as contract_info strings: "none", "conId@exchange" or "combo".
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "client-sync")]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+#[cfg(feature = "client-sync")]
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+#[cfg(feature = "client-sync")]
+use crate::core::errors::{is_informational, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+#[cfg(feature = "client-sync")]
+use crate::core::req_ids::RequestKind;
+
+//==================================================================================================
+/// The contract shown by a display group
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum GroupContract {
+    /// Nothing is selected
+    Empty,
+    /// A single contract on an exchange
+    Contract { con_id: i32, exchange: String },
+    /// A combo, which TWS does not describe further
+    Combo,
+}
+
+impl FromStr for GroupContract {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let info = s.trim();
+        if info.is_empty() || info.eq_ignore_ascii_case("none") {
+            return Ok(GroupContract::Empty);
+        }
+        if info.eq_ignore_ascii_case("combo") {
+            return Ok(GroupContract::Combo);
+        }
+        let (con_id, exchange) = info.split_once('@').unwrap_or((info, ""));
+        let con_id = con_id
+            .parse()
+            .map_err(|_| IBKRApiLibError::Parse(format!("invalid contract info {:?}", s)))?;
+        Ok(GroupContract::Contract {
+            con_id,
+            exchange: exchange.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for GroupContract {
+    /// Writes the contract_info sent to TWS
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupContract::Empty => write!(f, "none"),
+            GroupContract::Contract { con_id, exchange } if exchange.is_empty() => {
+                write!(f, "{}", con_id)
+            }
+            GroupContract::Contract { con_id, exchange } => write!(f, "{}@{}", con_id, exchange),
+            GroupContract::Combo => write!(f, "combo"),
+        }
+    }
+}
+
+//==================================================================================================
+/// Reads the groups of a DisplayGroupList event, such as "1|2|3"
+pub fn parse_groups(groups: &str) -> Result<Vec<i32>, IBKRApiLibError> {
+    groups
+        .split('|')
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            group
+                .parse()
+                .map_err(|_| IBKRApiLibError::Parse(format!("invalid display group {:?}", group)))
+        })
+        .collect()
+}
+
+//==================================================================================================
+/// Lists the display groups of TWS
+///
+/// # Arguments
+/// * client - A connected client
+/// * timeout - How long to wait for the list
+#[cfg(feature = "client-sync")]
+pub fn query_groups(client: &mut EClient, timeout: Duration) -> Result<Vec<i32>, IBKRApiLibError> {
+    let req_id = client.next_req_id(RequestKind::DisplayGroups, "query");
+    client.query_display_groups(req_id)?;
+
+    let mut groups = Vec::new();
+    client.collect(timeout, |msg| match msg {
+        ServerRspMsg::DisplayGroupList {
+            req_id: id,
+            groups: list,
+        } if *id == req_id => {
+            groups = parse_groups(list)?;
+            Ok(Collected::Finished)
+        }
+        _ => match request_error(req_id, msg) {
+            Some(err) => Err(err),
+            None => Ok(Collected::Ignored),
+        },
+    })?;
+    Ok(groups)
+}
+
+//==================================================================================================
+/// A subscription to one display group.  It does not hold the client, so the application can keep
+/// it next to its other state and pass it the events it receives.
+#[derive(Clone, Debug)]
+pub struct DisplayGroup {
+    pub group_id: i32,
+    pub req_id: i32,
+    /// The contract last reported by TWS or set through this handle
+    pub contract: Option<GroupContract>,
+}
+
+impl DisplayGroup {
+    /// Applies an event.  Returns the group's new contract if the event is an update of this
+    /// group.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> Option<&GroupContract> {
+        match msg {
+            ServerRspMsg::DisplayGroupUpdated {
+                req_id,
+                contract_info,
+            } if *req_id == self.req_id => {
+                self.contract = contract_info.parse().ok();
+                self.contract.as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the changes of a group.  TWS reports the current contract first.
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * group_id - One of the groups returned by query_groups()
+    #[cfg(feature = "client-sync")]
+    pub fn subscribe(client: &mut EClient, group_id: i32) -> Result<Self, IBKRApiLibError> {
+        let req_id = client.next_req_id(RequestKind::DisplayGroups, &group_id.to_string());
+        client.subscribe_to_group_events(req_id, group_id)?;
+        Ok(DisplayGroup {
+            group_id,
+            req_id,
+            contract: None,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits for the next change of the group
+    ///
+    /// # Arguments
+    /// * client - The client the group was subscribed with
+    /// * timeout - How long to wait.  None is returned if nothing changed.
+    #[cfg(feature = "client-sync")]
+    pub fn next_change(
+        &mut self,
+        client: &mut EClient,
+        timeout: Duration,
+    ) -> Result<Option<GroupContract>, IBKRApiLibError> {
+        let req_id = self.req_id;
+        let mut changed = None;
+        let result = client.collect(timeout, |msg| {
+            if let Some(contract) = self.update(msg) {
+                changed = Some(contract.clone());
+                return Ok(Collected::Finished);
+            }
+            match request_error(req_id, msg) {
+                Some(err) => Err(err),
+                None => Ok(Collected::Ignored),
+            }
+        });
+        match result {
+            Ok(()) => Ok(changed),
+            Err(IBKRApiLibError::RecvTimeoutError(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Shows a contract in the windows of the group
+    #[cfg(feature = "client-sync")]
+    pub fn set_contract(
+        &mut self,
+        client: &mut EClient,
+        contract: GroupContract,
+    ) -> Result<(), IBKRApiLibError> {
+        client.update_display_group(self.req_id, &contract.to_string())?;
+        self.contract = Some(contract);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Shows a resolved contract in the windows of the group, on its exchange
+    #[cfg(feature = "client-sync")]
+    pub fn show(
+        &mut self,
+        client: &mut EClient,
+        contract: &Contract,
+    ) -> Result<(), IBKRApiLibError> {
+        self.set_contract(
+            client,
+            GroupContract::Contract {
+                con_id: contract.con_id,
+                exchange: contract.exchange.clone(),
+            },
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels the subscription
+    #[cfg(feature = "client-sync")]
+    pub fn unsubscribe(self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        client.unsubscribe_from_group_events(self.req_id)
+    }
+}
+
+//==================================================================================================
+#[cfg(feature = "client-sync")]
+fn request_error(req_id: i32, msg: &ServerRspMsg) -> Option<IBKRApiLibError> {
+    match msg {
+        ServerRspMsg::ErrMsg {
+            req_id: id,
+            error_code,
+            error_str,
+        } if *id == req_id && !is_informational(*error_code) => Some(IBKRApiLibError::ApiError(
+            TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+        )),
+        _ => None,
+    }
+}
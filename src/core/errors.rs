@@ -3,6 +3,9 @@ use std::num::{ParseFloatError, ParseIntError};
 use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
 use std::{error, fmt, io};
 
+use crate::core::common::{BarSize, HistoricalDuration};
+use crate::core::handshake::Feature;
+
 const ALREADY_CONNECTED: (i32, &str) = (501, "Already connected.");
 const CONNECT_FAIL: (i32, &str) = (502, "Couldn't connect to TWS. Confirm that \"Enable ActiveX and Socket EClients\"
                                             is enabled and connection port is the same as \"Socket Port\" on the
@@ -75,6 +78,13 @@ impl fmt::Display for TwsError {
     }
 }
 
+//==================================================================================================
+/// Whether an error code sent by TWS is only a notice rather than a failed request.
+/// 2100-2199 are connection and data farm notices, 10167 means delayed data is shown instead.
+pub fn is_informational(error_code: i32) -> bool {
+    (2100..2200).contains(&error_code) || error_code == 10167
+}
+
 // #[derive(Debug)]
 pub enum IBKRApiLibError {
     Io(io::Error),
@@ -84,6 +94,36 @@ pub enum IBKRApiLibError {
     TryRecvError(TryRecvError),
     RecvTimeoutError(RecvTimeoutError),
     ApiError(TwsApiReportableError),
+    Config(String),
+    Xml(String),
+    /// Text that is not in the format expected, such as a frame dump, a manifest, a JSON
+    /// document or a time
+    Parse(String),
+    Export(String),
+    /// The connected server is too old for a request.  Returned before anything is sent.
+    UpdateTws {
+        required: i32,
+        current: i32,
+        feature: Feature,
+    },
+    /// EClient::shutdown() has begun.  Only cancels are still sent.
+    ShuttingDown,
+    /// TWS does not serve bars of this size for this duration.  Returned before anything is sent.
+    InvalidBarSize {
+        duration: HistoricalDuration,
+        bar_size: BarSize,
+    },
+}
+
+impl IBKRApiLibError {
+    /// The BadMessage error for a message from TWS that cannot be decoded
+    pub fn bad_message(req_id: i32, detail: &str) -> Self {
+        IBKRApiLibError::ApiError(TwsApiReportableError::new(
+            req_id,
+            TwsError::BadMessage.code().to_string(),
+            format!("{} {}", TwsError::BadMessage.message(), detail),
+        ))
+    }
 }
 
 impl fmt::Display for IBKRApiLibError {
@@ -98,6 +138,27 @@ impl fmt::Display for IBKRApiLibError {
             IBKRApiLibError::TryRecvError(ref err) => write!(f, "TryRecieve error: {}", err),
             IBKRApiLibError::RecvTimeoutError(ref err) => write!(f, "Reader Send error {}", err),
             IBKRApiLibError::ApiError(ref err) => write!(f, "TWS Error: {}", err),
+            IBKRApiLibError::Config(ref err) => write!(f, "Config error: {}", err),
+            IBKRApiLibError::Xml(ref err) => write!(f, "XML error: {}", err),
+            IBKRApiLibError::Parse(ref err) => write!(f, "Parse error: {}", err),
+            IBKRApiLibError::Export(ref err) => write!(f, "Export error: {}", err),
+            IBKRApiLibError::UpdateTws {
+                required,
+                current,
+                feature,
+            } => write!(
+                f,
+                "{} {:?} needs server version {}, connected to {}",
+                UPDATE_TWS.1, feature, required, current
+            ),
+            IBKRApiLibError::ShuttingDown => write!(f, "Client is shutting down"),
+            IBKRApiLibError::InvalidBarSize { duration, bar_size } => {
+                write!(
+                    f,
+                    "{} bars are not available for a duration of {}",
+                    bar_size, duration
+                )
+            }
         }
     }
 }
@@ -114,6 +175,27 @@ impl fmt::Debug for IBKRApiLibError {
             IBKRApiLibError::TryRecvError(ref err) => write!(f, "TryReceive error {}", err),
             IBKRApiLibError::RecvTimeoutError(ref err) => write!(f, "Reader Send error {}", err),
             IBKRApiLibError::ApiError(ref err) => write!(f, "TWS Error: {}", err),
+            IBKRApiLibError::Config(ref err) => write!(f, "Config error: {}", err),
+            IBKRApiLibError::Xml(ref err) => write!(f, "XML error: {}", err),
+            IBKRApiLibError::Parse(ref err) => write!(f, "Parse error: {}", err),
+            IBKRApiLibError::Export(ref err) => write!(f, "Export error: {}", err),
+            IBKRApiLibError::UpdateTws {
+                required,
+                current,
+                feature,
+            } => write!(
+                f,
+                "{} {:?} needs server version {}, connected to {}",
+                UPDATE_TWS.1, feature, required, current
+            ),
+            IBKRApiLibError::ShuttingDown => write!(f, "Client is shutting down"),
+            IBKRApiLibError::InvalidBarSize { duration, bar_size } => {
+                write!(
+                    f,
+                    "{} bars are not available for a duration of {}",
+                    bar_size, duration
+                )
+            }
         }
     }
 }
@@ -132,6 +214,13 @@ impl error::Error for IBKRApiLibError {
             IBKRApiLibError::TryRecvError(ref err) => Some(err),
             IBKRApiLibError::RecvTimeoutError(ref err) => Some(err),
             IBKRApiLibError::ApiError(ref err) => Some(err),
+            IBKRApiLibError::Config(_) => None,
+            IBKRApiLibError::Xml(_) => None,
+            IBKRApiLibError::Parse(_) => None,
+            IBKRApiLibError::Export(_) => None,
+            IBKRApiLibError::UpdateTws { .. } => None,
+            IBKRApiLibError::ShuttingDown => None,
+            IBKRApiLibError::InvalidBarSize { .. } => None,
         }
     }
 }
@@ -166,6 +255,13 @@ impl From<RecvTimeoutError> for IBKRApiLibError {
     }
 }
 
+#[cfg(feature = "xml")]
+impl From<roxmltree::Error> for IBKRApiLibError {
+    fn from(err: roxmltree::Error) -> IBKRApiLibError {
+        IBKRApiLibError::Xml(err.to_string())
+    }
+}
+
 impl From<TwsApiReportableError> for IBKRApiLibError {
     fn from(err: TwsApiReportableError) -> IBKRApiLibError {
         IBKRApiLibError::ApiError(err)
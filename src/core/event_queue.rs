@@ -0,0 +1,473 @@
+//! Queue that carries decoded messages from the decoder thread to EClient::get_event()
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::latency::{LatencyObserver, LatencyStamps};
+use crate::core::messages::ServerRspMsg;
+#[cfg(feature = "metrics")]
+use crate::core::metrics;
+
+//==================================================================================================
+/// Groups of messages that can be given their own overflow policy
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    MarketData,
+    MarketDepth,
+    Order,
+    Account,
+    Error,
+    Other,
+}
+
+impl MessageClass {
+    pub fn of(msg: &ServerRspMsg) -> Self {
+        match msg {
+            ServerRspMsg::TickPrice { .. }
+            | ServerRspMsg::TickSize { .. }
+            | ServerRspMsg::TickOptionComputation { .. }
+            | ServerRspMsg::TickGeneric { .. }
+            | ServerRspMsg::TickString { .. }
+            | ServerRspMsg::TickEfp { .. }
+            | ServerRspMsg::TickSnapshotEnd { .. }
+            | ServerRspMsg::TickReqParams { .. }
+            | ServerRspMsg::TickNews { .. }
+            | ServerRspMsg::TickByTick { .. }
+            | ServerRspMsg::MarketDataType { .. }
+            | ServerRspMsg::RealTimeBars { .. }
+            | ServerRspMsg::HistoricalDataUpdate { .. } => MessageClass::MarketData,
+            ServerRspMsg::MarketDepth { .. } | ServerRspMsg::MarketDepthL2 { .. } => {
+                MessageClass::MarketDepth
+            }
+            ServerRspMsg::OrderStatus { .. }
+            | ServerRspMsg::OpenOrder { .. }
+            | ServerRspMsg::OpenOrderEnd
+            | ServerRspMsg::NextValidId { .. }
+            | ServerRspMsg::ExecutionData { .. }
+            | ServerRspMsg::ExecutionDataEnd { .. }
+            | ServerRspMsg::CommissionReport { .. }
+            | ServerRspMsg::OrderBound { .. }
+            | ServerRspMsg::CompletedOrder { .. }
+            | ServerRspMsg::CompletedOrdersEnd => MessageClass::Order,
+            ServerRspMsg::AcctValue { .. }
+            | ServerRspMsg::PortfolioValue { .. }
+            | ServerRspMsg::AcctUpdateTime { .. }
+            | ServerRspMsg::AcctDownloadEnd { .. }
+            | ServerRspMsg::ManagedAccts { .. }
+            | ServerRspMsg::PositionData { .. }
+            | ServerRspMsg::PositionEnd
+            | ServerRspMsg::AccountSummary { .. }
+            | ServerRspMsg::AccountSummaryEnd { .. }
+            | ServerRspMsg::PositionMulti { .. }
+            | ServerRspMsg::PositionMultiEnd { .. }
+            | ServerRspMsg::AccountUpdateMulti { .. }
+            | ServerRspMsg::AccountUpdateMultiEnd { .. }
+            | ServerRspMsg::Pnl { .. }
+            | ServerRspMsg::PnlSingle { .. } => MessageClass::Account,
+            ServerRspMsg::ErrMsg { .. } => MessageClass::Error,
+            _ => MessageClass::Other,
+        }
+    }
+}
+
+//==================================================================================================
+/// What to do with a new message when the queue is full
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Block the decoder thread until the consumer makes room
+    Block,
+    /// Discard the oldest queued message of the same class.  If none is queued, the new message
+    /// is discarded instead.
+    DropOldest,
+    /// Discard the new message
+    DropNewest,
+}
+
+//==================================================================================================
+/// Capacity and overflow policies of the event queue.  The default is unbounded.
+#[derive(Clone, Debug)]
+pub struct EventQueueConfig {
+    pub capacity: Option<usize>,
+    pub default_policy: OverflowPolicy,
+    pub policies: HashMap<MessageClass, OverflowPolicy>,
+}
+
+impl Default for EventQueueConfig {
+    fn default() -> Self {
+        EventQueueConfig {
+            capacity: None,
+            default_policy: OverflowPolicy::Block,
+            policies: HashMap::new(),
+        }
+    }
+}
+
+impl EventQueueConfig {
+    /// A queue holding at most `capacity` messages, blocking the decoder when full
+    pub fn bounded(capacity: usize) -> Self {
+        EventQueueConfig {
+            capacity: Some(capacity.max(1)),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the overflow policy for one class of messages
+    pub fn with_policy(mut self, class: MessageClass, policy: OverflowPolicy) -> Self {
+        self.policies.insert(class, policy);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn policy_for(&self, class: MessageClass) -> OverflowPolicy {
+        *self.policies.get(&class).unwrap_or(&self.default_policy)
+    }
+}
+
+//==================================================================================================
+/// Snapshot of the queue's state
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct QueueMetrics {
+    pub depth: usize,
+    pub capacity: Option<usize>,
+    pub high_water_mark: usize,
+    pub blocked_sends: u64,
+    pub dropped: HashMap<MessageClass, u64>,
+}
+
+impl QueueMetrics {
+    pub fn total_dropped(&self) -> u64 {
+        self.dropped.values().sum()
+    }
+}
+
+//==================================================================================================
+/// A message with the times its frame was read and decoded, when latency is measured
+struct Queued {
+    msg: ServerRspMsg,
+    stamps: Option<(Instant, Instant)>,
+}
+
+struct QueueState {
+    messages: VecDeque<Queued>,
+    senders: usize,
+    receiver_alive: bool,
+    high_water_mark: usize,
+    dropped: HashMap<MessageClass, u64>,
+    /// Tasks to wake when the next message is queued or the last sender is dropped
+    wakers: Vec<Waker>,
+}
+
+struct Shared {
+    config: EventQueueConfig,
+    state: Mutex<QueueState>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    blocked_sends: AtomicU64,
+    latency: Mutex<Option<Arc<dyn LatencyObserver>>>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+//==================================================================================================
+/// Creates a connected sender and receiver pair
+pub fn event_queue(config: EventQueueConfig) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        config,
+        state: Mutex::new(QueueState {
+            messages: VecDeque::new(),
+            senders: 1,
+            receiver_alive: true,
+            high_water_mark: 0,
+            dropped: HashMap::new(),
+            wakers: Vec::new(),
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        blocked_sends: AtomicU64::new(0),
+        latency: Mutex::new(None),
+    });
+    (
+        EventSender {
+            shared: shared.clone(),
+        },
+        EventReceiver { shared },
+    )
+}
+
+//==================================================================================================
+/// Sending half of the event queue.  Mirrors std::sync::mpsc::Sender.
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl EventSender {
+    /// Queues a message, applying the overflow policy of its class if the queue is full.
+    /// Fails only if the receiver has been dropped.
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, msg: ServerRspMsg) -> Result<(), SendError<ServerRspMsg>> {
+        self.push(Queued { msg, stamps: None })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Queues a message like send(), keeping the times its frame was read and decoded for the
+    /// latency observer of the receiver
+    #[allow(clippy::result_large_err)]
+    pub fn send_stamped(
+        &self,
+        msg: ServerRspMsg,
+        read: Instant,
+        decoded: Instant,
+    ) -> Result<(), SendError<ServerRspMsg>> {
+        self.push(Queued {
+            msg,
+            stamps: Some((read, decoded)),
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    #[allow(clippy::result_large_err)]
+    fn push(&self, queued: Queued) -> Result<(), SendError<ServerRspMsg>> {
+        let mut state = self.shared.lock();
+        if !state.receiver_alive {
+            return Err(SendError(queued.msg));
+        }
+
+        if let Some(capacity) = self.shared.config.capacity {
+            if state.messages.len() >= capacity {
+                let class = MessageClass::of(&queued.msg);
+                match self.shared.config.policy_for(class) {
+                    OverflowPolicy::Block => {
+                        self.shared.blocked_sends.fetch_add(1, Ordering::Relaxed);
+                        while state.receiver_alive && state.messages.len() >= capacity {
+                            state = self
+                                .shared
+                                .not_full
+                                .wait(state)
+                                .unwrap_or_else(|poisoned| poisoned.into_inner());
+                        }
+                        if !state.receiver_alive {
+                            return Err(SendError(queued.msg));
+                        }
+                    }
+                    OverflowPolicy::DropOldest => {
+                        *state.dropped.entry(class).or_insert(0) += 1;
+                        let oldest = state
+                            .messages
+                            .iter()
+                            .position(|held| MessageClass::of(&held.msg) == class);
+                        match oldest {
+                            Some(index) => {
+                                state.messages.remove(index);
+                            }
+                            None => return Ok(()),
+                        }
+                    }
+                    OverflowPolicy::DropNewest => {
+                        *state.dropped.entry(class).or_insert(0) += 1;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        state.messages.push_back(queued);
+        state.high_water_mark = state.high_water_mark.max(state.messages.len());
+        #[cfg(feature = "metrics")]
+        metrics::event_queue_depth(state.messages.len());
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+        self.shared.not_empty.notify_one();
+        for waker in wakers {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        EventSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        let wakers = if state.senders == 0 {
+            std::mem::take(&mut state.wakers)
+        } else {
+            Vec::new()
+        };
+        drop(state);
+        self.shared.not_empty.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+//==================================================================================================
+/// Receiving half of the event queue.  Mirrors std::sync::mpsc::Receiver.
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    fn pop(&self, state: &mut QueueState) -> Option<Queued> {
+        let queued = state.messages.pop_front()?;
+        self.shared.not_full.notify_one();
+        #[cfg(feature = "metrics")]
+        metrics::event_queue_depth(state.messages.len());
+        Some(queued)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reports a stamped message to the latency observer, outside the queue lock
+    fn deliver(&self, queued: Queued) -> ServerRspMsg {
+        if let Some((read, decoded)) = queued.stamps {
+            let observer = self
+                .shared
+                .latency
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone();
+            if let Some(observer) = observer {
+                let stamps = LatencyStamps {
+                    read,
+                    decoded,
+                    dispatched: Instant::now(),
+                };
+                observer.observe(&queued.msg, &stamps);
+            }
+        }
+        queued.msg
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn try_recv(&self) -> Result<ServerRspMsg, TryRecvError> {
+        let mut state = self.shared.lock();
+        match self.pop(&mut state) {
+            Some(queued) => {
+                drop(state);
+                Ok(self.deliver(queued))
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn recv(&self) -> Result<ServerRspMsg, RecvError> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(queued) = self.pop(&mut state) {
+                drop(state);
+                return Ok(self.deliver(queued));
+            }
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<ServerRspMsg, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(queued) = self.pop(&mut state) {
+                drop(state);
+                return Ok(self.deliver(queued));
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Wakes the task of waker when the next message is queued, or when the last sender is
+    /// dropped.  It is woken at once if a message is already waiting or no sender is left, so a
+    /// future that found the queue empty can register and return Pending without missing one.
+    pub fn register_waker(&self, waker: &Waker) {
+        let mut state = self.shared.lock();
+        if !state.messages.is_empty() || state.senders == 0 {
+            drop(state);
+            waker.wake_by_ref();
+            return;
+        }
+        if !state.wakers.iter().any(|known| known.will_wake(waker)) {
+            state.wakers.push(waker.clone());
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates another sender feeding this receiver
+    pub fn sender(&self) -> EventSender {
+        self.shared.lock().senders += 1;
+        EventSender {
+            shared: self.shared.clone(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Installs or removes the observer called with the stamps of stamped messages as they are
+    /// received
+    pub fn set_latency_observer(&self, observer: Option<Arc<dyn LatencyObserver>>) {
+        *self
+            .shared
+            .latency
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = observer;
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn metrics(&self) -> QueueMetrics {
+        let state = self.shared.lock();
+        QueueMetrics {
+            depth: state.messages.len(),
+            capacity: self.shared.config.capacity,
+            high_water_mark: state.high_water_mark,
+            blocked_sends: self.shared.blocked_sends.load(Ordering::Relaxed),
+            dropped: state.dropped.clone(),
+        }
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+        self.shared.not_full.notify_all();
+    }
+}
@@ -1,19 +1,50 @@
 //! Types related to executions
 use std::fmt::{Display, Error, Formatter};
+#[cfg(feature = "orders")]
+use std::future::Future;
+#[cfg(feature = "orders")]
+use std::pin::Pin;
+#[cfg(feature = "orders")]
+use std::sync::mpsc::RecvTimeoutError;
+#[cfg(feature = "orders")]
+use std::task::{Context, Poll};
+#[cfg(feature = "orders")]
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "orders")]
+use crate::core::client::{Collected, EClient};
+use crate::core::common::Action;
+#[cfg(feature = "orders")]
+use crate::core::contract::Contract;
+#[cfg(feature = "orders")]
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+#[cfg(feature = "orders")]
+use crate::core::messages::ServerRspMsg;
+use crate::core::time::display_time;
+
+#[cfg(feature = "analytics")]
+pub use crate::core::fill_ledger::{
+    ClosedLot, Fill, FillLedger, FillSummary, LotMatching, OpenLot,
+};
+
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Execution {
     pub exec_id: String,
-    pub time: String,
+    /// Time of the execution in UTC, None if TWS sent a time that could not be read
+    pub time: Option<DateTime<Utc>>,
+    /// The time as TWS sent it, as yyyymmdd hh:mm:ss with or without a zone name
+    #[cfg(feature = "raw-times")]
+    pub time_raw: String,
     pub acct_number: String,
     pub exchange: String,
     pub side: String,
     pub shares: f64,
     pub price: f64,
-    pub perm_id: i32,
+    pub perm_id: i64,
     pub client_id: i32,
     pub order_id: i32,
     pub liquidation: i32,
@@ -29,13 +60,13 @@ pub struct Execution {
 impl Execution {
     pub fn new(
         exec_id: String,
-        time: String,
+        time: DateTime<Utc>,
         acct_number: String,
         exchange: String,
         side: String,
         shares: f64,
         price: f64,
-        perm_id: i32,
+        perm_id: i64,
         client_id: i32,
         order_id: i32,
         liquidation: i32,
@@ -49,7 +80,9 @@ impl Execution {
     ) -> Self {
         Execution {
             exec_id,
-            time,
+            time: Some(time),
+            #[cfg(feature = "raw-times")]
+            time_raw: String::new(),
             acct_number,
             exchange,
             side,
@@ -93,7 +126,7 @@ impl Display for Execution {
             model_code: : {},
             last_liquidity: : {} ",
             self.exec_id,
-            self.time,
+            display_time(&self.time),
             self.acct_number,
             self.exchange,
             self.side,
@@ -146,4 +179,122 @@ impl ExecutionFilter {
             side,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions of orders placed by an API client
+    pub fn client_id(mut self, client_id: i32) -> Self {
+        self.client_id = client_id;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions in an account
+    pub fn account(mut self, account: &str) -> Self {
+        self.acct_code = account.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions after a time
+    pub fn time_since(mut self, time: DateTime<Utc>) -> Self {
+        self.time = time.format("%Y%m%d-%H:%M:%S").to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions of contracts with a symbol
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = symbol.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions of contracts of a security type, such as "STK" or "OPT"
+    pub fn sec_type(mut self, sec_type: &str) -> Self {
+        self.sec_type = sec_type.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only executions on an exchange
+    pub fn exchange(mut self, exchange: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only buys or only sells
+    pub fn side(mut self, side: Action) -> Self {
+        self.side = side.to_string();
+        self
+    }
+}
+
+//==================================================================================================
+/// Future returned by EClient::executions().  It resolves when ExecutionDataEnd arrives and
+/// leaves other events, including executions of other requests, for get_event().
+#[cfg(feature = "orders")]
+pub struct Executions<'a> {
+    client: &'a mut EClient,
+    req_id: i32,
+    executions: Vec<(Contract, Execution)>,
+}
+
+#[cfg(feature = "orders")]
+impl<'a> Executions<'a> {
+    pub(crate) fn new(client: &'a mut EClient, req_id: i32) -> Self {
+        Executions {
+            client,
+            req_id,
+            executions: Vec::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+}
+
+#[cfg(feature = "orders")]
+impl Future for Executions<'_> {
+    type Output = Result<Vec<(Contract, Execution)>, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let req_id = self.req_id;
+        let mut received = Vec::new();
+        let result = self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::ExecutionData {
+                req_id: id,
+                contract,
+                execution,
+            } if *id == req_id => {
+                received.push((contract.clone(), execution.clone()));
+                Ok(Collected::Consumed)
+            }
+            ServerRspMsg::ExecutionDataEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        });
+        self.executions.append(&mut received);
+        let output = match result {
+            Ok(()) => Ok(std::mem::take(&mut self.executions)),
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.client.wake_on_event(cx.waker());
+                return Poll::Pending;
+            }
+            Err(err) => Err(err),
+        };
+        self.client.correlations().complete(req_id);
+        Poll::Ready(output)
+    }
 }
@@ -0,0 +1,502 @@
+//! CSV and Parquet export of historical bars, historical ticks and recorded depth and tick
+//! streams.
+//!
+//! Times are written as UTC timestamps rather than the strings and epoch seconds TWS sends.
+//! Prices are written as doubles and sizes as 64-bit integers, the types the decoder gives them.
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use parquet::arrow::ArrowWriter;
+
+use crate::core::common::{
+    BarData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, TickByTickData,
+};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+
+//==================================================================================================
+/// Values of one column
+#[derive(Clone, Debug)]
+enum Values {
+    /// Microseconds since the epoch, in UTC when `utc` is set and in exchange time otherwise
+    Time {
+        utc: bool,
+        micros: Vec<Option<i64>>,
+    },
+    Float(Vec<Option<f64>>),
+    Int(Vec<Option<i64>>),
+    Bool(Vec<bool>),
+    Text(Vec<String>),
+}
+
+//==================================================================================================
+/// One column of an export.  Built by the `Record` implementations of this module.
+#[derive(Clone, Debug)]
+pub struct Column {
+    name: &'static str,
+    nullable: bool,
+    values: Values,
+}
+
+impl Column {
+    fn new(name: &'static str, nullable: bool, values: Values) -> Self {
+        Column {
+            name,
+            nullable,
+            values,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn utc<T>(name: &'static str, rows: &[T], time: impl Fn(&T) -> Option<i64>) -> Self {
+        let micros = rows.iter().map(time).collect::<Vec<_>>();
+        let nullable = micros.iter().any(Option::is_none);
+        Column::new(name, nullable, Values::Time { utc: true, micros })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn float<T>(name: &'static str, rows: &[T], value: impl Fn(&T) -> f64) -> Self {
+        let values = rows.iter().map(|row| Some(value(row))).collect();
+        Column::new(name, false, Values::Float(values))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn optional_float<T>(
+        name: &'static str,
+        rows: &[T],
+        value: impl Fn(&T) -> Option<f64>,
+    ) -> Self {
+        let values = rows.iter().map(value).collect();
+        Column::new(name, true, Values::Float(values))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn int<T>(name: &'static str, rows: &[T], value: impl Fn(&T) -> i64) -> Self {
+        let values = rows.iter().map(|row| Some(value(row))).collect();
+        Column::new(name, false, Values::Int(values))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn optional_int<T>(name: &'static str, rows: &[T], value: impl Fn(&T) -> Option<i64>) -> Self {
+        let values = rows.iter().map(value).collect();
+        Column::new(name, true, Values::Int(values))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn bool<T>(name: &'static str, rows: &[T], value: impl Fn(&T) -> bool) -> Self {
+        Column::new(name, false, Values::Bool(rows.iter().map(value).collect()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn text<T>(name: &'static str, rows: &[T], value: impl Fn(&T) -> &str) -> Self {
+        let values = rows.iter().map(|row| value(row).to_string()).collect();
+        Column::new(name, false, Values::Text(values))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn field(&self) -> Field {
+        let data_type = match &self.values {
+            Values::Time { utc: true, .. } => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            }
+            Values::Time { utc: false, .. } => DataType::Timestamp(TimeUnit::Microsecond, None),
+            Values::Float(_) => DataType::Float64,
+            Values::Int(_) => DataType::Int64,
+            Values::Bool(_) => DataType::Boolean,
+            Values::Text(_) => DataType::Utf8,
+        };
+        Field::new(self.name, data_type, self.nullable)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn array(&self) -> ArrayRef {
+        match &self.values {
+            Values::Time { utc: true, micros } => {
+                Arc::new(TimestampMicrosecondArray::from(micros.clone()).with_timezone("UTC"))
+            }
+            Values::Time { utc: false, micros } => {
+                Arc::new(TimestampMicrosecondArray::from(micros.clone()))
+            }
+            Values::Float(values) => Arc::new(Float64Array::from(values.clone())),
+            Values::Int(values) => Arc::new(Int64Array::from(values.clone())),
+            Values::Bool(values) => Arc::new(BooleanArray::from(values.clone())),
+            Values::Text(values) => Arc::new(StringArray::from(values.clone())),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// CSV text of one value.  Missing values are left empty.
+    fn format(&self, row: usize) -> String {
+        match &self.values {
+            Values::Time { utc, micros } => match micros[row] {
+                Some(micros) if *utc => Utc
+                    .timestamp_micros(micros)
+                    .single()
+                    .map(|time| time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+                    .unwrap_or_default(),
+                Some(micros) => DateTime::from_timestamp_micros(micros)
+                    .map(|time| time.naive_utc().format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+                    .unwrap_or_default(),
+                None => String::new(),
+            },
+            Values::Float(values) => values[row].map(|v| v.to_string()).unwrap_or_default(),
+            Values::Int(values) => values[row].map(|v| v.to_string()).unwrap_or_default(),
+            Values::Bool(values) => values[row].to_string(),
+            Values::Text(values) => values[row].clone(),
+        }
+    }
+}
+
+//==================================================================================================
+/// Rows that can be exported.  Implemented for bars, historical ticks and the records of a
+/// `StreamRecorder`.
+pub trait Record: Sized {
+    /// Columns of the rows, in the order they are written
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError>;
+}
+
+//==================================================================================================
+/// Writes rows as CSV with a header line
+///
+/// # Arguments
+/// * rows - Bars, historical ticks or recorded stream records
+/// * writer - Destination, such as a file
+pub fn to_csv<R: Record, W: Write>(rows: &[R], writer: W) -> Result<(), IBKRApiLibError> {
+    let columns = R::columns(rows)?;
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(columns.iter().map(|column| column.name))
+        .map_err(export_error)?;
+    for row in 0..rows.len() {
+        csv.write_record(columns.iter().map(|column| column.format(row)))
+            .map_err(export_error)?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+//==================================================================================================
+/// Writes rows as a Parquet file with one row group
+///
+/// # Arguments
+/// * rows - Bars, historical ticks or recorded stream records
+/// * writer - Destination, such as a file
+pub fn to_parquet<R: Record, W: Write + Send>(
+    rows: &[R],
+    writer: W,
+) -> Result<(), IBKRApiLibError> {
+    let columns = R::columns(rows)?;
+    let schema = Arc::new(Schema::new(
+        columns.iter().map(Column::field).collect::<Vec<_>>(),
+    ));
+    let batch = RecordBatch::try_new(schema.clone(), columns.iter().map(Column::array).collect())
+        .map_err(export_error)?;
+    let mut parquet = ArrowWriter::try_new(writer, schema, None).map_err(export_error)?;
+    parquet.write(&batch).map_err(export_error)?;
+    parquet.close().map_err(export_error)?;
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------
+fn export_error(err: impl std::fmt::Display) -> IBKRApiLibError {
+    IBKRApiLibError::Export(err.to_string())
+}
+
+//----------------------------------------------------------------------------------------------
+fn seconds(time: i64) -> Option<i64> {
+    time.checked_mul(MICROS_PER_SECOND)
+}
+
+//==================================================================================================
+impl Record for BarData {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("time", rows, |bar| bar.date.map(|date| date.timestamp_micros())),
+            Column::float("open", rows, |bar| bar.open),
+            Column::float("high", rows, |bar| bar.high),
+            Column::float("low", rows, |bar| bar.low),
+            Column::float("close", rows, |bar| bar.close),
+            Column::int("volume", rows, |bar| bar.volume),
+            Column::int("bar_count", rows, |bar| bar.bar_count as i64),
+            Column::float("average", rows, |bar| bar.average),
+        ])
+    }
+}
+
+//==================================================================================================
+impl Record for HistoricalTick {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("time", rows, |tick| seconds(tick.time as i64)),
+            Column::float("price", rows, |tick| tick.price),
+            Column::int("size", rows, |tick| tick.size as i64),
+        ])
+    }
+}
+
+//==================================================================================================
+impl Record for HistoricalTickBidAsk {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("time", rows, |tick| seconds(tick.time as i64)),
+            Column::float("price_bid", rows, |tick| tick.price_bid),
+            Column::float("price_ask", rows, |tick| tick.price_ask),
+            Column::int("size_bid", rows, |tick| tick.size_bid as i64),
+            Column::int("size_ask", rows, |tick| tick.size_ask as i64),
+            Column::bool("bid_past_low", rows, |tick| {
+                tick.tick_attrib_bid_ask.bid_past_low
+            }),
+            Column::bool("ask_past_high", rows, |tick| {
+                tick.tick_attrib_bid_ask.ask_past_high
+            }),
+        ])
+    }
+}
+
+//==================================================================================================
+impl Record for HistoricalTickLast {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("time", rows, |tick| seconds(tick.time as i64)),
+            Column::float("price", rows, |tick| tick.price),
+            Column::int("size", rows, |tick| tick.size as i64),
+            Column::text("exchange", rows, |tick| &tick.exchange),
+            Column::text("special_conditions", rows, |tick| &tick.special_conditions),
+            Column::bool("past_limit", rows, |tick| tick.tick_attrib_last.past_limit),
+            Column::bool("unreported", rows, |tick| tick.tick_attrib_last.unreported),
+        ])
+    }
+}
+
+//==================================================================================================
+/// One market depth update, from MarketDepth or MarketDepthL2
+#[derive(Clone, Debug)]
+pub struct DepthRecord {
+    /// When the update was recorded
+    pub received: DateTime<Utc>,
+    pub req_id: i32,
+    pub position: i32,
+    /// Empty for MarketDepth updates
+    pub market_maker: String,
+    /// 0 insert, 1 update, 2 delete
+    pub operation: i32,
+    /// 0 ask, 1 bid
+    pub side: i32,
+    pub price: f64,
+    pub size: i64,
+    pub is_smart_depth: bool,
+}
+
+impl Record for DepthRecord {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("received", rows, |row| {
+                Some(row.received.timestamp_micros())
+            }),
+            Column::int("req_id", rows, |row| row.req_id as i64),
+            Column::int("position", rows, |row| row.position as i64),
+            Column::text("market_maker", rows, |row| &row.market_maker),
+            Column::int("operation", rows, |row| row.operation as i64),
+            Column::int("side", rows, |row| row.side as i64),
+            Column::float("price", rows, |row| row.price),
+            Column::int("size", rows, |row| row.size),
+            Column::bool("is_smart_depth", rows, |row| row.is_smart_depth),
+        ])
+    }
+}
+
+//==================================================================================================
+/// One tick, from TickPrice, TickSize, TickGeneric or TickByTick.  A tick by tick bid/ask is
+/// recorded as a Bid and an Ask record.
+#[derive(Clone, Debug)]
+pub struct TickRecord {
+    /// When the tick was recorded
+    pub received: DateTime<Utc>,
+    /// Exchange time, sent only with tick by tick data
+    pub time: Option<DateTime<Utc>>,
+    pub req_id: i32,
+    /// Tick type, such as bidPrice, or Last, AllLast, Bid, Ask or MidPoint for tick by tick data
+    pub field: String,
+    pub price: Option<f64>,
+    pub size: Option<i64>,
+}
+
+impl Record for TickRecord {
+    fn columns(rows: &[Self]) -> Result<Vec<Column>, IBKRApiLibError> {
+        Ok(vec![
+            Column::utc("received", rows, |row| {
+                Some(row.received.timestamp_micros())
+            }),
+            Column::utc("time", rows, |row| {
+                row.time.map(|time| time.timestamp_micros())
+            }),
+            Column::int("req_id", rows, |row| row.req_id as i64),
+            Column::text("field", rows, |row| &row.field),
+            Column::optional_float("price", rows, |row| row.price),
+            Column::optional_int("size", rows, |row| row.size),
+        ])
+    }
+}
+
+//==================================================================================================
+/// Records depth and tick updates from the event stream so they can be exported
+#[derive(Clone, Debug, Default)]
+pub struct StreamRecorder {
+    depth: Vec<DepthRecord>,
+    ticks: Vec<TickRecord>,
+}
+
+impl StreamRecorder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an event received now and returns whether it was a depth or tick update
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        self.observe_at(msg, Utc::now())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an event and returns whether it was a depth or tick update
+    ///
+    /// # Arguments
+    /// * msg - Event from the client
+    /// * received - When the event was received
+    pub fn observe_at(&mut self, msg: &ServerRspMsg, received: DateTime<Utc>) -> bool {
+        let tick = |req_id: i32, field: String, price: Option<f64>, size: Option<i64>| TickRecord {
+            received,
+            time: None,
+            req_id,
+            field,
+            price,
+            size,
+        };
+        match msg {
+            ServerRspMsg::MarketDepth {
+                req_id,
+                position,
+                operation,
+                side,
+                price,
+                size,
+            } => self.depth.push(DepthRecord {
+                received,
+                req_id: *req_id,
+                position: *position,
+                market_maker: String::new(),
+                operation: *operation,
+                side: *side,
+                price: *price,
+                size: *size as i64,
+                is_smart_depth: false,
+            }),
+            ServerRspMsg::MarketDepthL2 {
+                req_id,
+                position,
+                market_maker,
+                operation,
+                side,
+                price,
+                size,
+                is_smart_depth,
+            } => self.depth.push(DepthRecord {
+                received,
+                req_id: *req_id,
+                position: *position,
+                market_maker: market_maker.clone(),
+                operation: *operation,
+                side: *side,
+                price: *price,
+                size: *size as i64,
+                is_smart_depth: *is_smart_depth,
+            }),
+            ServerRspMsg::TickPrice {
+                req_id,
+                tick_type,
+                price,
+                ..
+            } => self
+                .ticks
+                .push(tick(*req_id, tick_type.to_string(), Some(*price), None)),
+            ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size,
+            } => self.ticks.push(tick(
+                *req_id,
+                tick_type.to_string(),
+                None,
+                Some(*size as i64),
+            )),
+            ServerRspMsg::TickGeneric {
+                ticker_id,
+                tick_type,
+                value,
+            } => self
+                .ticks
+                .push(tick(*ticker_id, tick_type.to_string(), Some(*value), None)),
+            ServerRspMsg::TickByTick {
+                req_id,
+                time,
+                tick: data,
+            } => {
+                let time = Utc.timestamp_opt(*time, 0).single();
+                let mut record = |field: &str, price: f64, size: Option<i64>| {
+                    self.ticks.push(TickRecord {
+                        time,
+                        ..tick(*req_id, field.to_string(), Some(price), size)
+                    })
+                };
+                match data {
+                    TickByTickData::Last {
+                        all_last,
+                        price,
+                        size,
+                        ..
+                    } => {
+                        let field = if *all_last { "AllLast" } else { "Last" };
+                        record(field, *price, Some(*size as i64));
+                    }
+                    TickByTickData::BidAsk {
+                        bid_price,
+                        ask_price,
+                        bid_size,
+                        ask_size,
+                        ..
+                    } => {
+                        record("Bid", *bid_price, Some(*bid_size as i64));
+                        record("Ask", *ask_price, Some(*ask_size as i64));
+                    }
+                    TickByTickData::MidPoint { mid_point } => record("MidPoint", *mid_point, None),
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn depth(&self) -> &[DepthRecord] {
+        &self.depth
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn ticks(&self) -> &[TickRecord] {
+        &self.ticks
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Drops everything recorded so far, after it has been exported
+    pub fn clear(&mut self) {
+        self.depth.clear();
+        self.ticks.clear();
+    }
+}
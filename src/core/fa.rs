@@ -0,0 +1,269 @@
+//! Financial advisor configuration: the allocation groups, allocation profiles and account
+//! aliases exchanged as XML by request_fa() / ReceiveFa and replace_fa()
+//!
+//! Orders are allocated to the accounts of a group with an allocation method, or to the
+//! accounts of a profile with fixed amounts; see Order::allocate_to_group() and
+//! Order::allocate_to_profile().
+use std::fmt::{Display, Error, Formatter, Write};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::errors::IBKRApiLibError;
+
+//==================================================================================================
+/// How an order placed for a group is split between the group's accounts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaAllocationMethod {
+    /// The same quantity for every account
+    EqualQuantity,
+    /// In proportion to each account's net liquidation value
+    NetLiq,
+    /// In proportion to each account's available equity
+    AvailableEquity,
+    /// Changes each account's position by the same percentage
+    PctChange,
+}
+
+impl FaAllocationMethod {
+    /// The method name sent to TWS
+    pub fn code(&self) -> &'static str {
+        match self {
+            FaAllocationMethod::EqualQuantity => "EqualQuantity",
+            FaAllocationMethod::NetLiq => "NetLiq",
+            FaAllocationMethod::AvailableEquity => "AvailableEquity",
+            FaAllocationMethod::PctChange => "PctChange",
+        }
+    }
+}
+
+impl Display for FaAllocationMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for FaAllocationMethod {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EqualQuantity" => Ok(FaAllocationMethod::EqualQuantity),
+            "NetLiq" => Ok(FaAllocationMethod::NetLiq),
+            "AvailableEquity" => Ok(FaAllocationMethod::AvailableEquity),
+            "PctChange" => Ok(FaAllocationMethod::PctChange),
+            _ => Err(IBKRApiLibError::Parse(format!(
+                "unknown FA allocation method {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// How the amounts of an allocation profile are read
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaProfileType {
+    /// Percentages of the order quantity
+    Percentages = 1,
+    /// Ratios between the accounts
+    FinancialRatios = 2,
+    /// Number of shares per account
+    Shares = 3,
+}
+
+impl FromStr for FaProfileType {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "1" => Ok(FaProfileType::Percentages),
+            "2" => Ok(FaProfileType::FinancialRatios),
+            "3" => Ok(FaProfileType::Shares),
+            _ => Err(IBKRApiLibError::Parse(format!(
+                "unknown FA profile type {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// An allocation group
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaGroup {
+    pub name: String,
+    pub accounts: Vec<String>,
+    pub default_method: FaAllocationMethod,
+}
+
+impl FaGroup {
+    /// Writes groups in the format replace_fa() expects for FaDataType::GROUPS
+    pub fn list_to_xml(groups: &[FaGroup]) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListOfGroups>");
+        for group in groups {
+            let _ = write!(
+                xml,
+                "<Group><name>{}</name><ListOfAccts varName=\"list\">",
+                escape(&group.name)
+            );
+            for account in &group.accounts {
+                let _ = write!(xml, "<String>{}</String>", escape(account));
+            }
+            let _ = write!(
+                xml,
+                "</ListOfAccts><defaultMethod>{}</defaultMethod></Group>",
+                group.default_method
+            );
+        }
+        xml.push_str("</ListOfGroups>");
+        xml
+    }
+}
+
+//==================================================================================================
+/// The amount of a profile allocated to one account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaAllocation {
+    pub account: String,
+    pub amount: f64,
+}
+
+//==================================================================================================
+/// An allocation profile
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaProfile {
+    pub name: String,
+    pub profile_type: FaProfileType,
+    pub allocations: Vec<FaAllocation>,
+}
+
+impl FaProfile {
+    /// Writes profiles in the format replace_fa() expects for FaDataType::PROFILES
+    pub fn list_to_xml(profiles: &[FaProfile]) -> String {
+        let mut xml =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListOfAllocationProfiles>");
+        for profile in profiles {
+            let _ = write!(
+                xml,
+                "<AllocationProfile><name>{}</name><type>{}</type>\
+                 <ListOfAllocations varName=\"listOfAllocations\">",
+                escape(&profile.name),
+                profile.profile_type as i32
+            );
+            for allocation in &profile.allocations {
+                let _ = write!(
+                    xml,
+                    "<Allocation><acct>{}</acct><amount>{}</amount></Allocation>",
+                    escape(&allocation.account),
+                    allocation.amount
+                );
+            }
+            xml.push_str("</ListOfAllocations></AllocationProfile>");
+        }
+        xml.push_str("</ListOfAllocationProfiles>");
+        xml
+    }
+}
+
+//==================================================================================================
+/// The alias shown for an account
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FaAlias {
+    pub account: String,
+    pub alias: String,
+}
+
+//==================================================================================================
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(feature = "xml")]
+impl FaGroup {
+    /// Parses the XML of a ReceiveFa event for FaDataType::GROUPS
+    pub fn parse_list(xml: &str) -> Result<Vec<Self>, IBKRApiLibError> {
+        let document = roxmltree::Document::parse(xml)?;
+        child_elements(document.root_element(), "Group")
+            .map(|group| {
+                Ok(FaGroup {
+                    name: child_text(group, "name"),
+                    accounts: child_elements(group, "ListOfAccts")
+                        .flat_map(|list| child_elements(list, "String"))
+                        .map(text)
+                        .collect(),
+                    default_method: child_text(group, "defaultMethod").parse()?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "xml")]
+impl FaProfile {
+    /// Parses the XML of a ReceiveFa event for FaDataType::PROFILES
+    pub fn parse_list(xml: &str) -> Result<Vec<Self>, IBKRApiLibError> {
+        let document = roxmltree::Document::parse(xml)?;
+        child_elements(document.root_element(), "AllocationProfile")
+            .map(|profile| {
+                let allocations = child_elements(profile, "ListOfAllocations")
+                    .flat_map(|list| child_elements(list, "Allocation"))
+                    .map(|allocation| {
+                        Ok(FaAllocation {
+                            account: child_text(allocation, "acct"),
+                            amount: child_text(allocation, "amount").parse()?,
+                        })
+                    })
+                    .collect::<Result<Vec<FaAllocation>, IBKRApiLibError>>()?;
+                Ok(FaProfile {
+                    name: child_text(profile, "name"),
+                    profile_type: child_text(profile, "type").parse()?,
+                    allocations,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "xml")]
+impl FaAlias {
+    /// Parses the XML of a ReceiveFa event for FaDataType::ALIASES
+    pub fn parse_list(xml: &str) -> Result<Vec<Self>, IBKRApiLibError> {
+        let document = roxmltree::Document::parse(xml)?;
+        Ok(child_elements(document.root_element(), "AccountAlias")
+            .map(|alias| FaAlias {
+                account: child_text(alias, "account"),
+                alias: child_text(alias, "alias"),
+            })
+            .collect())
+    }
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn child_elements<'a, 'input: 'a>(
+    node: roxmltree::Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn child_text(node: roxmltree::Node<'_, '_>, tag: &str) -> String {
+    child_elements(node, tag)
+        .next()
+        .map(text)
+        .unwrap_or_default()
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn text(node: roxmltree::Node<'_, '_>) -> String {
+    node.text().unwrap_or("").trim().to_string()
+}
@@ -0,0 +1,348 @@
+//! Pairs executions with their commission reports and matches them into closed lots
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::CommissionReport;
+use crate::core::contract::Contract;
+use crate::core::execution::Execution;
+use crate::core::messages::ServerRspMsg;
+
+/// Quantities closer to zero than this are treated as flat, so fractional fills do not leave
+/// rounding dust behind as a tiny open lot
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+//==================================================================================================
+/// Which open lot a closing fill is matched against first
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LotMatching {
+    /// Oldest lot first
+    Fifo,
+    /// Newest lot first
+    Lifo,
+}
+
+//==================================================================================================
+/// An execution, with its commission once the CommissionReport has arrived
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Fill {
+    pub exec_id: String,
+    pub order_id: i32,
+    pub perm_id: i64,
+    pub account: String,
+    pub con_id: i32,
+    pub symbol: String,
+    /// Time of the execution, None if TWS sent a time that could not be read
+    pub time: Option<DateTime<Utc>>,
+    /// Date of time in UTC, YYYYMMDD, empty if the time is unknown
+    pub day: String,
+    /// Positive for buys, negative for sells
+    pub quantity: f64,
+    pub price: f64,
+    pub multiplier: f64,
+    pub commission: Option<f64>,
+    pub commission_currency: String,
+    /// Realized PnL of the lots this fill closed, before commissions
+    pub realized_pnl: f64,
+}
+
+//==================================================================================================
+/// Part of a position that is still open
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OpenLot {
+    pub exec_id: String,
+    pub time: Option<DateTime<Utc>>,
+    /// Positive for long lots, negative for short lots
+    pub quantity: f64,
+    pub price: f64,
+}
+
+//==================================================================================================
+/// A quantity opened by one fill and closed by another
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ClosedLot {
+    pub account: String,
+    pub con_id: i32,
+    pub symbol: String,
+    pub open_exec_id: String,
+    pub close_exec_id: String,
+    pub open_time: Option<DateTime<Utc>>,
+    pub close_time: Option<DateTime<Utc>>,
+    /// Day the lot was closed, YYYYMMDD
+    pub day: String,
+    /// Positive if the lot was long, negative if it was short
+    pub quantity: f64,
+    pub open_price: f64,
+    pub close_price: f64,
+    pub realized_pnl: f64,
+}
+
+//==================================================================================================
+/// Totals over a group of fills
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FillSummary {
+    pub fills: usize,
+    pub bought: f64,
+    pub sold: f64,
+    pub realized_pnl: f64,
+    pub commissions: f64,
+}
+
+impl FillSummary {
+    pub fn net_realized_pnl(&self) -> f64 {
+        self.realized_pnl - self.commissions
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn add(&mut self, fill: &Fill) {
+        self.fills += 1;
+        if fill.quantity > 0.0 {
+            self.bought += fill.quantity;
+        } else {
+            self.sold -= fill.quantity;
+        }
+        self.realized_pnl += fill.realized_pnl;
+        self.commissions += fill.commission.unwrap_or(0.0);
+    }
+}
+
+impl fmt::Display for FillSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fills: {}, bought: {}, sold: {}, realized_pnl: {}, commissions: {}",
+            self.fills, self.bought, self.sold, self.realized_pnl, self.commissions
+        )
+    }
+}
+
+//==================================================================================================
+/// Reconciles ExecutionData and CommissionReport events.
+///
+/// Executions are matched into lots per account and contract as they arrive, so feed them in
+/// the order TWS sends them.  An execution seen twice, for example after a second
+/// req_executions(), is ignored.
+#[derive(Clone, Debug)]
+pub struct FillLedger {
+    matching: LotMatching,
+    fills: Vec<Fill>,
+    index: HashMap<String, usize>,
+    pending_commissions: HashMap<String, CommissionReport>,
+    open_lots: HashMap<(String, i32), VecDeque<OpenLot>>,
+    closed_lots: Vec<ClosedLot>,
+}
+
+impl FillLedger {
+    pub fn new(matching: LotMatching) -> Self {
+        FillLedger {
+            matching,
+            fills: Vec::new(),
+            index: HashMap::new(),
+            pending_commissions: HashMap::new(),
+            open_lots: HashMap::new(),
+            closed_lots: Vec::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records ExecutionData and CommissionReport events and ignores everything else.  Returns
+    /// the lots closed by the event.
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> Vec<ClosedLot> {
+        match msg {
+            ServerRspMsg::ExecutionData {
+                contract,
+                execution,
+                ..
+            } => self.add_execution(contract, execution),
+            ServerRspMsg::CommissionReport { commission_report } => {
+                self.add_commission(commission_report);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an execution and matches it against the open lots of its account and contract
+    pub fn add_execution(&mut self, contract: &Contract, execution: &Execution) -> Vec<ClosedLot> {
+        if self.index.contains_key(&execution.exec_id) {
+            return Vec::new();
+        }
+
+        let sign = if execution.side == "SLD" { -1.0 } else { 1.0 };
+        let mut fill = Fill {
+            exec_id: execution.exec_id.clone(),
+            order_id: execution.order_id,
+            perm_id: execution.perm_id,
+            account: execution.acct_number.clone(),
+            con_id: contract.con_id,
+            symbol: if contract.local_symbol.is_empty() {
+                contract.symbol.clone()
+            } else {
+                contract.local_symbol.clone()
+            },
+            time: execution.time,
+            day: execution
+                .time
+                .map(|time| time.format("%Y%m%d").to_string())
+                .unwrap_or_default(),
+            quantity: sign * execution.shares,
+            price: execution.price,
+            multiplier: contract.multiplier.parse().unwrap_or(1.0),
+            ..Default::default()
+        };
+        if let Some(report) = self.pending_commissions.remove(&fill.exec_id) {
+            fill.commission = Some(report.commission);
+            fill.commission_currency = report.currency;
+        }
+
+        let closed = self.match_lots(&fill);
+        fill.realized_pnl = closed.iter().map(|lot| lot.realized_pnl).sum();
+        self.closed_lots.extend(closed.iter().cloned());
+        self.index.insert(fill.exec_id.clone(), self.fills.len());
+        self.fills.push(fill);
+        closed
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Attaches a commission to its execution.  Reports that arrive before their execution are
+    /// held until it does.
+    pub fn add_commission(&mut self, report: &CommissionReport) {
+        match self.index.get(&report.exec_id) {
+            Some(&i) => {
+                self.fills[i].commission = Some(report.commission);
+                self.fills[i].commission_currency = report.currency.clone();
+            }
+            None => {
+                self.pending_commissions
+                    .insert(report.exec_id.clone(), report.clone());
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn match_lots(&mut self, fill: &Fill) -> Vec<ClosedLot> {
+        let lots = self
+            .open_lots
+            .entry((fill.account.clone(), fill.con_id))
+            .or_default();
+        let mut remaining = fill.quantity;
+        let mut closed = Vec::new();
+
+        while remaining.abs() > QUANTITY_EPSILON {
+            let lot = match self.matching {
+                LotMatching::Fifo => lots.front_mut(),
+                LotMatching::Lifo => lots.back_mut(),
+            };
+            let lot = match lot {
+                Some(lot) if lot.quantity.signum() != remaining.signum() => lot,
+                _ => break,
+            };
+
+            let quantity = if lot.quantity.abs() <= remaining.abs() + QUANTITY_EPSILON {
+                lot.quantity
+            } else {
+                -remaining
+            };
+            closed.push(ClosedLot {
+                account: fill.account.clone(),
+                con_id: fill.con_id,
+                symbol: fill.symbol.clone(),
+                open_exec_id: lot.exec_id.clone(),
+                close_exec_id: fill.exec_id.clone(),
+                open_time: lot.time,
+                close_time: fill.time,
+                day: fill.day.clone(),
+                quantity,
+                open_price: lot.price,
+                close_price: fill.price,
+                realized_pnl: (fill.price - lot.price) * quantity * fill.multiplier,
+            });
+            lot.quantity -= quantity;
+            remaining += quantity;
+
+            if lot.quantity.abs() <= QUANTITY_EPSILON {
+                match self.matching {
+                    LotMatching::Fifo => lots.pop_front(),
+                    LotMatching::Lifo => lots.pop_back(),
+                };
+            }
+        }
+
+        if remaining.abs() > QUANTITY_EPSILON {
+            lots.push_back(OpenLot {
+                exec_id: fill.exec_id.clone(),
+                time: fill.time,
+                quantity: remaining,
+                price: fill.price,
+            });
+        }
+        closed
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn fill(&self, exec_id: &str) -> Option<&Fill> {
+        self.index.get(exec_id).map(|&i| &self.fills[i])
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Fills whose CommissionReport has not arrived yet
+    pub fn missing_commissions(&self) -> Vec<&Fill> {
+        self.fills
+            .iter()
+            .filter(|fill| fill.commission.is_none())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn closed_lots(&self) -> &[ClosedLot] {
+        &self.closed_lots
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Lots still open for an account and contract, oldest first
+    pub fn open_lots(&self, account: &str, con_id: i32) -> Vec<&OpenLot> {
+        self.open_lots
+            .get(&(account.to_string(), con_id))
+            .map(|lots| lots.iter().collect())
+            .unwrap_or_default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Net open quantity for an account and contract
+    pub fn position(&self, account: &str, con_id: i32) -> f64 {
+        self.open_lots(account, con_id)
+            .iter()
+            .map(|lot| lot.quantity)
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn per_symbol(&self) -> BTreeMap<String, FillSummary> {
+        self.summarize(|fill| fill.symbol.clone())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn per_day(&self) -> BTreeMap<String, FillSummary> {
+        self.summarize(|fill| fill.day.clone())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn summarize<F>(&self, key: F) -> BTreeMap<String, FillSummary>
+    where
+        F: Fn(&Fill) -> String,
+    {
+        let mut summaries: BTreeMap<String, FillSummary> = BTreeMap::new();
+        for fill in &self.fills {
+            summaries.entry(key(fill)).or_default().add(fill);
+        }
+        summaries
+    }
+}
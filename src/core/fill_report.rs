@@ -0,0 +1,250 @@
+//! Follows one order to its final state and joins its executions and commissions, for
+//! EClient::buy_market() and sell_market()
+use std::fmt::{Display, Error, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::CommissionReport;
+use crate::core::contract::Contract;
+use crate::core::errors::{is_informational, IBKRApiLibError};
+use crate::core::execution::Execution;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{capped_price, Order, OrderStatusKind, WhyHeld};
+
+/// Errors about an order that do not reject it: a cancel that came too late, the confirmation
+/// of a cancel, which is followed by a Cancelled status, an order message warning and a pending
+/// short sale locate
+pub(crate) const ORDER_NOTICES: [i32; 4] = [161, 202, 399, 404];
+
+//==================================================================================================
+/// What happened to an order: its fills and the commissions charged for them
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FillReport {
+    pub order_id: i32,
+    pub perm_id: i64,
+    pub contract: Contract,
+    pub action: String,
+    /// Quantity ordered
+    pub quantity: f64,
+    /// Last status TWS reported
+    pub status: OrderStatusKind,
+    pub filled: f64,
+    pub avg_fill_price: f64,
+    /// Why TWS is holding the order back, as of the last status
+    pub why_held: WhyHeld,
+    /// Price TWS capped the order at outside regular trading hours, None if it is not capped
+    pub mkt_cap_price: Option<f64>,
+    pub executions: Vec<Execution>,
+    pub commissions: Vec<CommissionReport>,
+}
+
+impl FillReport {
+    /// Sum of the commissions reported so far
+    pub fn total_commission(&self) -> f64 {
+        self.commissions
+            .iter()
+            .map(|report| report.commission)
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Quantity of the executions received so far
+    pub fn executed(&self) -> f64 {
+        self.executions
+            .iter()
+            .map(|execution| execution.shares)
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order is working at a capped price before or after regular trading hours
+    pub fn is_capped(&self) -> bool {
+        self.mkt_cap_price.is_some()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_filled(&self) -> bool {
+        self.status == OrderStatusKind::Filled
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order is filled and every execution and commission report has arrived
+    pub fn is_complete(&self) -> bool {
+        self.is_filled()
+            && self.executed() >= self.filled
+            && self.executions.iter().all(|execution| {
+                self.commissions
+                    .iter()
+                    .any(|report| report.exec_id == execution.exec_id)
+            })
+    }
+}
+
+//==================================================================================================
+/// Why an order did not fill
+#[derive(Debug)]
+pub enum FillFailure {
+    /// TWS rejected the order
+    Rejected {
+        order_id: i32,
+        error_code: i32,
+        message: String,
+    },
+    /// The order was cancelled, possibly after filling in part
+    Cancelled(Box<FillReport>),
+    /// The order did not finish in time.  It has been cancelled, but may have filled in part.
+    Timeout(Box<FillReport>),
+    /// The order could not be placed or the connection failed
+    Request(IBKRApiLibError),
+}
+
+impl Display for FillFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            FillFailure::Rejected {
+                order_id,
+                error_code,
+                message,
+            } => write!(
+                f,
+                "order {} rejected with error {}: {}",
+                order_id, error_code, message
+            ),
+            FillFailure::Cancelled(report) => write!(
+                f,
+                "order {} cancelled after filling {} of {}",
+                report.order_id, report.filled, report.quantity
+            ),
+            FillFailure::Timeout(report) => write!(
+                f,
+                "order {} timed out after filling {} of {}",
+                report.order_id, report.filled, report.quantity
+            ),
+            FillFailure::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<IBKRApiLibError> for FillFailure {
+    fn from(err: IBKRApiLibError) -> Self {
+        FillFailure::Request(err)
+    }
+}
+
+//==================================================================================================
+/// Collects the status, executions and commissions of one order
+#[derive(Clone, Debug)]
+pub struct FillTracker {
+    report: FillReport,
+    rejection: Option<(i32, String)>,
+}
+
+impl FillTracker {
+    pub fn new(order_id: i32, contract: &Contract, order: &Order) -> Self {
+        FillTracker {
+            report: FillReport {
+                order_id,
+                contract: contract.clone(),
+                action: order.action.clone(),
+                quantity: order.total_quantity,
+                ..Default::default()
+            },
+            rejection: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event and returns whether it belonged to the order
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        let report = &mut self.report;
+        match msg {
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                filled,
+                avg_fill_price,
+                perm_id,
+                why_held,
+                mkt_cap_price,
+                ..
+            } if *order_id == report.order_id => {
+                report.status = status.clone();
+                report.filled = *filled;
+                report.avg_fill_price = *avg_fill_price;
+                report.perm_id = *perm_id;
+                report.why_held = why_held.clone();
+                report.mkt_cap_price = capped_price(*mkt_cap_price);
+                true
+            }
+            ServerRspMsg::OpenOrder { order_id, .. } => *order_id == report.order_id,
+            ServerRspMsg::ExecutionData { execution, .. }
+                if execution.order_id == report.order_id =>
+            {
+                // a correction repeats the exec id with a higher suffix and replaces the original
+                let base = |exec_id: &str| exec_id.rsplitn(2, '.').last().unwrap_or("").to_string();
+                let corrected = base(&execution.exec_id);
+                report
+                    .executions
+                    .retain(|previous| base(&previous.exec_id) != corrected);
+                report.executions.push(execution.clone());
+                true
+            }
+            ServerRspMsg::CommissionReport { commission_report }
+                if report
+                    .executions
+                    .iter()
+                    .any(|execution| execution.exec_id == commission_report.exec_id) =>
+            {
+                report.commissions.push(commission_report.clone());
+                true
+            }
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } if *req_id == report.order_id => {
+                if !is_informational(*error_code) && !ORDER_NOTICES.contains(error_code) {
+                    self.rejection = Some((*error_code, error_str.clone()));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order has reached a final state and nothing more is expected
+    pub fn is_finished(&self) -> bool {
+        self.report.is_complete()
+            || self.rejection.is_some()
+            || (self.report.status.is_terminal() && !self.report.is_filled())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn report(&self) -> &FillReport {
+        &self.report
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The result once the order is finished, or when waiting for it has timed out.  A filled
+    /// order is reported as filled even if some commission reports have not arrived.
+    pub fn finish(self) -> Result<FillReport, FillFailure> {
+        if self.report.is_filled() {
+            return Ok(self.report);
+        }
+        if let Some((error_code, message)) = self.rejection {
+            if self.report.filled == 0.0 {
+                return Err(FillFailure::Rejected {
+                    order_id: self.report.order_id,
+                    error_code,
+                    message,
+                });
+            }
+            return Err(FillFailure::Cancelled(Box::new(self.report)));
+        }
+        if self.report.status.is_terminal() {
+            return Err(FillFailure::Cancelled(Box::new(self.report)));
+        }
+        Err(FillFailure::Timeout(Box::new(self.report)))
+    }
+}
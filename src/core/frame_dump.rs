@@ -0,0 +1,186 @@
+//! Decodes hex or base64 dumps of TWS frames, for debugging packet captures
+use std::fmt;
+
+use num_traits::FromPrimitive;
+
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::{
+    decode_base64, FieldIterator, ServerReqMsgDiscriminants, ServerRspMsgDiscriminants,
+};
+
+const HANDSHAKE_PREFIX: &[u8] = b"API\0";
+
+//==================================================================================================
+/// Which side sent the frames, which decides how message ids are named
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameDirection {
+    /// Sent by TWS to the client
+    Incoming,
+    /// Sent by the client to TWS
+    Outgoing,
+}
+
+//==================================================================================================
+/// One decoded frame
+#[derive(Clone, Debug)]
+pub struct DecodedFrame {
+    pub msg_id: Option<i32>,
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} ({} fields)", self.name, self.fields.len())?;
+        for (i, field) in self.fields.iter().enumerate() {
+            writeln!(f, "  [{}] {:?}", i, field)?;
+        }
+        Ok(())
+    }
+}
+
+//==================================================================================================
+/// How a pasted dump is encoded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DumpEncoding {
+    /// Hex digits, with or without whitespace, commas, colons and 0x prefixes
+    Hex,
+    /// Standard base64
+    Base64,
+}
+
+//==================================================================================================
+/// Turns a pasted dump into bytes, guessing the encoding.  Text made only of hex digits is read
+/// as hex and anything else as base64, so a base64 dump that happens to be all hex digits is
+/// misread: use `parse_dump_as` or `decode_dump` when that matters.
+pub fn parse_dump(text: &str) -> Result<Vec<u8>, IBKRApiLibError> {
+    parse_dump_as(text, guess_encoding(text))
+}
+
+//==================================================================================================
+/// Turns a pasted dump in the given encoding into bytes
+pub fn parse_dump_as(text: &str, encoding: DumpEncoding) -> Result<Vec<u8>, IBKRApiLibError> {
+    match encoding {
+        DumpEncoding::Hex => {
+            let hex = strip_hex(text);
+            if hex.len() & 1 == 1 {
+                return Err(IBKRApiLibError::Parse(
+                    "hex dump has an odd number of digits".to_string(),
+                ));
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                        .ok_or_else(|| {
+                            IBKRApiLibError::Parse(format!("invalid hex byte at digit {}", i))
+                        })
+                })
+                .collect()
+        }
+        DumpEncoding::Base64 => decode_base64(text)
+            .map_err(|_| IBKRApiLibError::Parse("dump is not valid base64".to_string())),
+    }
+}
+
+//==================================================================================================
+/// Turns a pasted dump into frames.  With no encoding given it is guessed as in `parse_dump`,
+/// and if the guess does not split into whole frames the other encoding is tried before giving
+/// up with the error of the guess.
+pub fn decode_dump(
+    text: &str,
+    encoding: Option<DumpEncoding>,
+    direction: FrameDirection,
+) -> Result<Vec<DecodedFrame>, IBKRApiLibError> {
+    if let Some(encoding) = encoding {
+        return decode_frames(&parse_dump_as(text, encoding)?, direction);
+    }
+    let guess = guess_encoding(text);
+    let other = match guess {
+        DumpEncoding::Hex => DumpEncoding::Base64,
+        DumpEncoding::Base64 => DumpEncoding::Hex,
+    };
+    match parse_dump_as(text, guess).and_then(|bytes| decode_frames(&bytes, direction)) {
+        Ok(frames) => Ok(frames),
+        Err(e) => parse_dump_as(text, other)
+            .and_then(|bytes| decode_frames(&bytes, direction))
+            .map_err(|_| e),
+    }
+}
+
+//==================================================================================================
+fn strip_hex(text: &str) -> String {
+    text.replace("0x", "")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',' && *c != ':')
+        .collect()
+}
+
+//==================================================================================================
+fn guess_encoding(text: &str) -> DumpEncoding {
+    let hex = strip_hex(text);
+    if !hex.is_empty() && hex.len() & 1 == 0 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        DumpEncoding::Hex
+    } else {
+        DumpEncoding::Base64
+    }
+}
+
+//==================================================================================================
+/// Splits length-prefixed frames and decodes each one.  A leading handshake prefix is skipped.
+pub fn decode_frames(
+    bytes: &[u8],
+    direction: FrameDirection,
+) -> Result<Vec<DecodedFrame>, IBKRApiLibError> {
+    let mut remaining = bytes.strip_prefix(HANDSHAKE_PREFIX).unwrap_or(bytes);
+    let mut frames = Vec::new();
+
+    while !remaining.is_empty() {
+        if remaining.len() < 4 {
+            return Err(IBKRApiLibError::Parse(format!(
+                "{} trailing bytes are too short for a frame",
+                remaining.len()
+            )));
+        }
+        let size =
+            u32::from_be_bytes([remaining[0], remaining[1], remaining[2], remaining[3]]) as usize;
+        if remaining.len() - 4 < size {
+            return Err(IBKRApiLibError::Parse(format!(
+                "frame of {} bytes is truncated to {}",
+                size,
+                remaining.len() - 4
+            )));
+        }
+        let payload = String::from_utf8_lossy(&remaining[4..4 + size]);
+        frames.push(decode_frame(&payload, direction));
+        remaining = &remaining[4 + size..];
+    }
+    Ok(frames)
+}
+
+//==================================================================================================
+/// Decodes the payload of a single frame, without its length prefix
+pub fn decode_frame(payload: &str, direction: FrameDirection) -> DecodedFrame {
+    let fields: Vec<String> = FieldIterator::new(payload).map(String::from).collect();
+    let msg_id = fields.first().and_then(|id| id.parse::<i32>().ok());
+    let name = match msg_id {
+        Some(id) => {
+            let name = match direction {
+                FrameDirection::Incoming => {
+                    ServerRspMsgDiscriminants::from_i32(id).map(|name| format!("{:?}", name))
+                }
+                FrameDirection::Outgoing => {
+                    ServerReqMsgDiscriminants::from_i32(id).map(|name| format!("{:?}", name))
+                }
+            };
+            name.unwrap_or_else(|| format!("Unknown({})", id))
+        }
+        None => "Unknown".to_string(),
+    };
+    DecodedFrame {
+        msg_id,
+        name,
+        fields,
+    }
+}
@@ -0,0 +1,151 @@
+//! Splits the byte stream from TWS into length prefixed frames
+//!
+//! A FrameReader owns one buffer that socket reads append to and complete frames are taken from
+//! as slices.  Bytes of a frame split over several reads stay where they are until the frame is
+//! complete.  When the buffer is drained both cursors wrap back to its start, and otherwise only
+//! the unread tail, at most one partial frame, is moved to the front to make room, so a burst of
+//! many small frames costs no more copying than a single read.
+use std::convert::TryInto;
+use std::io::{self, ErrorKind, Read};
+
+use crate::core::common::MAX_MSG_LEN;
+use crate::core::errors::IBKRApiLibError;
+
+/// Bytes asked of the socket per read, and the initial size of the buffer
+pub const READ_CHUNK: usize = 4096;
+const SIZE_PREFIX: usize = 4;
+
+//==================================================================================================
+/// Incremental splitter of length prefixed frames
+#[derive(Clone, Debug)]
+pub struct FrameReader {
+    buf: Vec<u8>,
+    /// First unread byte
+    start: usize,
+    /// End of the bytes read
+    end: usize,
+    /// Frames announcing more bytes than this are rejected as a corrupt stream
+    max_frame: usize,
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        FrameReader::with_capacity(READ_CHUNK)
+    }
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        FrameReader::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_capacity(capacity: usize) -> Self {
+        FrameReader {
+            buf: vec![0; capacity.max(SIZE_PREFIX)],
+            start: 0,
+            end: 0,
+            max_frame: MAX_MSG_LEN as usize,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Rejects frames longer than `max_frame` bytes
+    pub fn max_frame(mut self, max_frame: usize) -> Self {
+        self.max_frame = max_frame;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Bytes read but not taken as frames yet
+    pub fn buffered(&self) -> usize {
+        self.end - self.start
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Size of the buffer.  It only grows for frames longer than what fits after the unread bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Makes room for at least `wanted` more bytes after `end`
+    fn reserve(&mut self, wanted: usize) {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = 0;
+        }
+        // move the unread tail to the front when the space after it runs short
+        if self.start > 0 && self.buf.len() - self.end < wanted.max(READ_CHUNK) {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        if self.buf.len() - self.end < wanted {
+            let len = (self.end + wanted).max(self.buf.len() * 2);
+            self.buf.resize(len, 0);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reads once from `source`.  Returns the bytes read, 0 when the source is closed.
+    pub fn read_from<R: Read + ?Sized>(&mut self, source: &mut R) -> io::Result<usize> {
+        // a partial frame needs at least its missing bytes, which may be more than a chunk
+        let missing = match self.pending_len() {
+            Some(len) if len <= self.max_frame => {
+                (SIZE_PREFIX + len).saturating_sub(self.buffered())
+            }
+            _ => 0,
+        };
+        self.reserve(missing.max(1));
+        let bytes_read = source.read(&mut self.buf[self.end..])?;
+        self.end += bytes_read;
+        Ok(bytes_read)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends bytes received some other way
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.reserve(bytes.len());
+        self.buf[self.end..self.end + bytes.len()].copy_from_slice(bytes);
+        self.end += bytes.len();
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Payload length of the next frame, once its size prefix is complete
+    fn pending_len(&self) -> Option<usize> {
+        if self.buffered() < SIZE_PREFIX {
+            return None;
+        }
+        let prefix = self.buf[self.start..self.start + SIZE_PREFIX]
+            .try_into()
+            .unwrap();
+        Some(i32::from_be_bytes(prefix) as u32 as usize)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The payload of the next complete frame, or None until more bytes are read.  Returns an
+    /// error if the size prefix is larger than the limit, after which the stream cannot be
+    /// split any further.
+    pub fn next_frame(&mut self) -> Result<Option<&[u8]>, IBKRApiLibError> {
+        let len = match self.pending_len() {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        if len > self.max_frame {
+            return Err(IBKRApiLibError::Io(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Frame of {} bytes is larger than the limit of {}",
+                    len, self.max_frame
+                ),
+            )));
+        }
+        if self.buffered() < SIZE_PREFIX + len {
+            return Ok(None);
+        }
+        let frame = self.start + SIZE_PREFIX;
+        self.start = frame + len;
+        Ok(Some(&self.buf[frame..frame + len]))
+    }
+}
@@ -0,0 +1,320 @@
+//! Polars DataFrame conversions of historical bars, historical ticks, positions and account
+//! summaries, plus the position and account summary collections they convert.
+//!
+//! Tick and bar times are UTC datetimes.
+use polars::prelude::{
+    Column, DataFrame, Int64Chunked, IntoColumn, IntoSeries, NamedFrom, NewChunkedArray, Series,
+    TimeUnit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::BarData;
+use crate::core::contract::Contract;
+#[cfg(feature = "marketdata")]
+use crate::core::historical::TickSeries;
+use crate::core::messages::ServerRspMsg;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+
+//----------------------------------------------------------------------------------------------
+/// Builds a frame from columns of equal length
+fn frame(columns: Vec<Column>) -> DataFrame {
+    DataFrame::new(columns).expect("frame columns have the same length")
+}
+
+//----------------------------------------------------------------------------------------------
+fn column<'a, T, V>(name: &str, rows: &'a [T], value: impl Fn(&'a T) -> V) -> Column
+where
+    Series: NamedFrom<Vec<V>, [V]>,
+{
+    Column::new(name.into(), rows.iter().map(value).collect::<Vec<_>>())
+}
+
+//----------------------------------------------------------------------------------------------
+/// Datetime column of microseconds since the epoch, in UTC when `utc` is set
+fn datetime(name: &str, micros: Vec<Option<i64>>, utc: bool) -> Column {
+    let zone = if utc { Some("UTC".into()) } else { None };
+    Int64Chunked::from_iter_options(name.into(), micros.into_iter())
+        .into_datetime(TimeUnit::Microseconds, zone)
+        .into_series()
+        .into_column()
+}
+
+//----------------------------------------------------------------------------------------------
+fn tick_times(times: impl Iterator<Item = i32>) -> Column {
+    datetime(
+        "time",
+        times
+            .map(|time| Some(time as i64 * MICROS_PER_SECOND))
+            .collect(),
+        true,
+    )
+}
+
+//==================================================================================================
+/// Historical bars of one request, which convert into a DataFrame
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BarSeries(pub Vec<BarData>);
+
+impl From<Vec<BarData>> for BarSeries {
+    fn from(bars: Vec<BarData>) -> Self {
+        BarSeries(bars)
+    }
+}
+
+/// Columns time, in UTC, open, high, low, close, volume, bar_count and average
+impl From<BarSeries> for DataFrame {
+    fn from(series: BarSeries) -> Self {
+        let bars = series.0;
+        let micros = bars
+            .iter()
+            .map(|bar| bar.date.map(|date| date.timestamp_micros()))
+            .collect();
+        frame(vec![
+            datetime("time", micros, true),
+            column("open", &bars, |bar| bar.open),
+            column("high", &bars, |bar| bar.high),
+            column("low", &bars, |bar| bar.low),
+            column("close", &bars, |bar| bar.close),
+            column("volume", &bars, |bar| bar.volume),
+            column("bar_count", &bars, |bar| bar.bar_count as i64),
+            column("average", &bars, |bar| bar.average),
+        ])
+    }
+}
+
+//==================================================================================================
+/// Columns by kind of tick:
+/// * Trades - time, price, size, exchange, special_conditions, past_limit, unreported
+/// * BidAsk - time, price_bid, price_ask, size_bid, size_ask, bid_past_low, ask_past_high
+/// * Midpoint - time, price
+#[cfg(feature = "marketdata")]
+impl From<TickSeries> for DataFrame {
+    fn from(series: TickSeries) -> Self {
+        match series {
+            TickSeries::Trades(ticks) => frame(vec![
+                tick_times(ticks.iter().map(|tick| tick.time)),
+                column("price", &ticks, |tick| tick.price),
+                column("size", &ticks, |tick| tick.size as i64),
+                column("exchange", &ticks, |tick| tick.exchange.as_str()),
+                column("special_conditions", &ticks, |tick| {
+                    tick.special_conditions.as_str()
+                }),
+                column("past_limit", &ticks, |tick| {
+                    tick.tick_attrib_last.past_limit
+                }),
+                column("unreported", &ticks, |tick| {
+                    tick.tick_attrib_last.unreported
+                }),
+            ]),
+            TickSeries::BidAsk(ticks) => frame(vec![
+                tick_times(ticks.iter().map(|tick| tick.time)),
+                column("price_bid", &ticks, |tick| tick.price_bid),
+                column("price_ask", &ticks, |tick| tick.price_ask),
+                column("size_bid", &ticks, |tick| tick.size_bid as i64),
+                column("size_ask", &ticks, |tick| tick.size_ask as i64),
+                column("bid_past_low", &ticks, |tick| {
+                    tick.tick_attrib_bid_ask.bid_past_low
+                }),
+                column("ask_past_high", &ticks, |tick| {
+                    tick.tick_attrib_bid_ask.ask_past_high
+                }),
+            ]),
+            TickSeries::Midpoint(ticks) => frame(vec![
+                tick_times(ticks.iter().map(|tick| tick.time)),
+                column("price", &ticks, |tick| tick.price),
+            ]),
+        }
+    }
+}
+
+//==================================================================================================
+/// One position, from PositionData or PositionMulti
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Position {
+    pub account: String,
+    /// Empty for PositionData
+    pub model_code: String,
+    pub contract: Contract,
+    pub position: f64,
+    pub avg_cost: f64,
+}
+
+//==================================================================================================
+/// Positions reported after req_positions() or req_positions_multi()
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PositionSnapshot {
+    pub positions: Vec<Position>,
+    complete: bool,
+}
+
+impl PositionSnapshot {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a position event and returns whether it was one.  A position replaces the one
+    /// held for the same account, model code and contract.
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        let update = match msg {
+            ServerRspMsg::PositionData {
+                account,
+                contract,
+                position,
+                avg_cost,
+            } => Position {
+                account: account.clone(),
+                model_code: String::new(),
+                contract: contract.clone(),
+                position: *position,
+                avg_cost: *avg_cost,
+            },
+            ServerRspMsg::PositionMulti {
+                account,
+                model_code,
+                contract,
+                position,
+                avg_cost,
+                ..
+            } => Position {
+                account: account.clone(),
+                model_code: model_code.clone(),
+                contract: contract.clone(),
+                position: *position,
+                avg_cost: *avg_cost,
+            },
+            ServerRspMsg::PositionEnd | ServerRspMsg::PositionMultiEnd { .. } => {
+                self.complete = true;
+                return true;
+            }
+            _ => return false,
+        };
+        match self.positions.iter_mut().find(|held| {
+            held.account == update.account
+                && held.model_code == update.model_code
+                && held.contract.con_id == update.contract.con_id
+        }) {
+            Some(held) => *held = update,
+            None => self.positions.push(update),
+        }
+        true
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the end of the initial positions has been received
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Columns account, model_code, con_id, symbol, sec_type, expiry, strike, right, currency,
+/// exchange, local_symbol, position and avg_cost
+impl From<PositionSnapshot> for DataFrame {
+    fn from(snapshot: PositionSnapshot) -> Self {
+        let positions = snapshot.positions;
+        frame(vec![
+            column("account", &positions, |held| held.account.as_str()),
+            column("model_code", &positions, |held| held.model_code.as_str()),
+            column("con_id", &positions, |held| held.contract.con_id as i64),
+            column("symbol", &positions, |held| held.contract.symbol.as_str()),
+            column("sec_type", &positions, |held| {
+                held.contract.sec_type.as_str()
+            }),
+            column("expiry", &positions, |held| {
+                held.contract.last_trade_date_or_contract_month.as_str()
+            }),
+            column("strike", &positions, |held| held.contract.strike),
+            column("right", &positions, |held| held.contract.right.as_str()),
+            column("currency", &positions, |held| {
+                held.contract.currency.as_str()
+            }),
+            column("exchange", &positions, |held| {
+                held.contract.exchange.as_str()
+            }),
+            column("local_symbol", &positions, |held| {
+                held.contract.local_symbol.as_str()
+            }),
+            column("position", &positions, |held| held.position),
+            column("avg_cost", &positions, |held| held.avg_cost),
+        ])
+    }
+}
+
+//==================================================================================================
+/// One value from AccountSummary
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountValue {
+    pub account: String,
+    pub tag: String,
+    pub value: String,
+    pub currency: String,
+}
+
+//==================================================================================================
+/// Values reported after req_account_summary()
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountSummary {
+    pub values: Vec<AccountValue>,
+    complete: bool,
+}
+
+impl AccountSummary {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an account summary event and returns whether it was one.  A value replaces the
+    /// one held for the same account, tag and currency.
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::AccountSummary {
+                account,
+                tag,
+                value,
+                currency,
+                ..
+            } => {
+                match self.values.iter_mut().find(|held| {
+                    held.account == *account && held.tag == *tag && held.currency == *currency
+                }) {
+                    Some(held) => held.value = value.clone(),
+                    None => self.values.push(AccountValue {
+                        account: account.clone(),
+                        tag: tag.clone(),
+                        value: value.clone(),
+                        currency: currency.clone(),
+                    }),
+                }
+                true
+            }
+            ServerRspMsg::AccountSummaryEnd { .. } => {
+                self.complete = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the end of the summary has been received
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Columns account, tag, currency, value and amount, which holds the value as a number or null
+/// when it is not numeric
+impl From<AccountSummary> for DataFrame {
+    fn from(summary: AccountSummary) -> Self {
+        let values = summary.values;
+        frame(vec![
+            column("account", &values, |held| held.account.as_str()),
+            column("tag", &values, |held| held.tag.as_str()),
+            column("currency", &values, |held| held.currency.as_str()),
+            column("value", &values, |held| held.value.as_str()),
+            column("amount", &values, |held| held.value.parse::<f64>().ok()),
+        ])
+    }
+}
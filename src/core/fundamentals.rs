@@ -0,0 +1,564 @@
+//! Typed reports parsed from the XML returned by req_fundamental_data()
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::core::errors::IBKRApiLibError;
+
+//==================================================================================================
+/// Report types accepted by req_fundamental_data()
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FundamentalReportType {
+    /// Company overview
+    ReportSnapshot,
+    /// Financial summary
+    ReportsFinSummary,
+    /// Financial ratios
+    ReportRatios,
+    /// Financial statements
+    ReportsFinStatements,
+    /// Analyst estimates
+    Resc,
+    /// Company calendar
+    CalendarReport,
+}
+
+impl FundamentalReportType {
+    /// The report type sent to TWS
+    pub fn code(&self) -> &'static str {
+        match self {
+            FundamentalReportType::ReportSnapshot => "ReportSnapshot",
+            FundamentalReportType::ReportsFinSummary => "ReportsFinSummary",
+            FundamentalReportType::ReportRatios => "ReportRatios",
+            FundamentalReportType::ReportsFinStatements => "ReportsFinStatements",
+            FundamentalReportType::Resc => "RESC",
+            FundamentalReportType::CalendarReport => "CalendarReport",
+        }
+    }
+}
+
+impl Display for FundamentalReportType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for FundamentalReportType {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ReportSnapshot" => Ok(FundamentalReportType::ReportSnapshot),
+            "ReportsFinSummary" => Ok(FundamentalReportType::ReportsFinSummary),
+            "ReportRatios" => Ok(FundamentalReportType::ReportRatios),
+            "ReportsFinStatements" => Ok(FundamentalReportType::ReportsFinStatements),
+            "RESC" => Ok(FundamentalReportType::Resc),
+            "CalendarReport" => Ok(FundamentalReportType::CalendarReport),
+            _ => Err(IBKRApiLibError::Xml(format!(
+                "unknown fundamental report type {}",
+                s
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// A named value from a Ratios or ForecastData block
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Ratio {
+    /// ID of the enclosing group, for example "Price and Volume".  Empty for forecast values.
+    pub group: String,
+    pub field_name: String,
+    /// N for numbers, D for dates and S for strings
+    pub value_type: String,
+    pub value: String,
+}
+
+impl Ratio {
+    pub fn number(&self) -> Option<f64> {
+        self.value.parse().ok()
+    }
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Industry {
+    /// Classification scheme, for example TRBC, NAICS or SIC
+    pub classification: String,
+    pub code: String,
+    pub description: String,
+}
+
+//==================================================================================================
+/// ReportSnapshot
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CompanySnapshot {
+    pub company_name: String,
+    pub ticker: String,
+    pub exchange: String,
+    pub reporting_currency: String,
+    pub employees: Option<i64>,
+    pub shares_outstanding: Option<f64>,
+    pub business_summary: String,
+    pub industries: Vec<Industry>,
+    pub ratios: Vec<Ratio>,
+    /// Consensus values from the ForecastData block
+    pub forecasts: Vec<Ratio>,
+}
+
+impl CompanySnapshot {
+    /// Looks up a ratio by field name, for example "PEEXCLXOR"
+    pub fn ratio(&self, field_name: &str) -> Option<&Ratio> {
+        self.ratios
+            .iter()
+            .find(|ratio| ratio.field_name == field_name)
+    }
+}
+
+//==================================================================================================
+/// One EPS, dividend per share or revenue figure from ReportsFinSummary
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SummaryValue {
+    pub as_of_date: String,
+    /// A (annual), R (restated), P (preliminary) or TTM
+    pub report_type: String,
+    /// For example 3M or 12M
+    pub period: String,
+    pub value: f64,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DividendEvent {
+    pub dividend_type: String,
+    pub ex_date: String,
+    pub record_date: String,
+    pub pay_date: String,
+    pub declaration_date: String,
+    pub value: f64,
+}
+
+//==================================================================================================
+/// ReportsFinSummary
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FinancialSummary {
+    pub currency: String,
+    pub eps: Vec<SummaryValue>,
+    pub dividends_per_share: Vec<SummaryValue>,
+    pub total_revenues: Vec<SummaryValue>,
+    pub dividends: Vec<DividendEvent>,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum StatementType {
+    Income,
+    Balance,
+    CashFlow,
+    Other(String),
+}
+
+impl StatementType {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "INC" => StatementType::Income,
+            "BAL" => StatementType::Balance,
+            "CAS" => StatementType::CashFlow,
+            _ => StatementType::Other(code.to_string()),
+        }
+    }
+}
+
+//==================================================================================================
+/// One line of a financial statement
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct StatementRow {
+    /// Chart of accounts code, for example SREV for total revenue
+    pub coa_code: String,
+    pub description: String,
+    pub value: f64,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub statement_type: StatementType,
+    pub statement_date: String,
+    pub period_length: Option<i32>,
+    /// M for months, W for weeks
+    pub period_unit: String,
+    pub rows: Vec<StatementRow>,
+}
+
+impl Statement {
+    pub fn value(&self, coa_code: &str) -> Option<f64> {
+        self.rows
+            .iter()
+            .find(|row| row.coa_code == coa_code)
+            .map(|row| row.value)
+    }
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FiscalPeriod {
+    /// Annual or Interim
+    pub period_type: String,
+    pub end_date: String,
+    pub fiscal_year: Option<i32>,
+    pub statements: Vec<Statement>,
+}
+
+impl FiscalPeriod {
+    pub fn statement(&self, statement_type: &StatementType) -> Option<&Statement> {
+        self.statements
+            .iter()
+            .find(|statement| &statement.statement_type == statement_type)
+    }
+}
+
+//==================================================================================================
+/// ReportsFinStatements
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FinancialStatements {
+    pub company_name: String,
+    /// Descriptions of the chart of accounts codes used in the statements
+    pub coa_map: HashMap<String, String>,
+    pub annual: Vec<FiscalPeriod>,
+    pub interim: Vec<FiscalPeriod>,
+}
+
+//==================================================================================================
+/// Consensus estimate for one measure and fiscal period
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Estimate {
+    /// For example EPS, REVENUE or DPS
+    pub measure: String,
+    pub unit: String,
+    /// A for annual, Q for quarterly
+    pub period_type: String,
+    pub fiscal_year: Option<i32>,
+    pub end_month: Option<i32>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub std_dev: Option<f64>,
+    pub num_of_est: Option<f64>,
+}
+
+//==================================================================================================
+/// RESC
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AnalystEstimates {
+    pub company_name: String,
+    pub estimates: Vec<Estimate>,
+}
+
+//==================================================================================================
+/// A parsed fundamental data report
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FundamentalReport {
+    Snapshot(CompanySnapshot),
+    FinancialSummary(FinancialSummary),
+    Ratios(Vec<Ratio>),
+    FinancialStatements(FinancialStatements),
+    Estimates(AnalystEstimates),
+    /// Reports without a typed form, currently CalendarReport
+    Raw(String),
+}
+
+//==================================================================================================
+/// Parses the data of a FundamentalData message.  TWS does not echo the report type, so pass the
+/// one given to req_fundamental_data().
+pub fn parse_report(
+    report_type: FundamentalReportType,
+    xml: &str,
+) -> Result<FundamentalReport, IBKRApiLibError> {
+    let document = roxmltree::Document::parse(xml)?;
+    let root = document.root_element();
+    Ok(match report_type {
+        FundamentalReportType::ReportSnapshot => FundamentalReport::Snapshot(parse_snapshot(root)),
+        FundamentalReportType::ReportsFinSummary => {
+            FundamentalReport::FinancialSummary(parse_fin_summary(root))
+        }
+        FundamentalReportType::ReportRatios => FundamentalReport::Ratios(
+            descendant(root, "Ratios")
+                .map(parse_ratios)
+                .unwrap_or_default(),
+        ),
+        FundamentalReportType::ReportsFinStatements => {
+            FundamentalReport::FinancialStatements(parse_fin_statements(root))
+        }
+        FundamentalReportType::Resc => FundamentalReport::Estimates(parse_estimates(root)),
+        FundamentalReportType::CalendarReport => FundamentalReport::Raw(xml.to_string()),
+    })
+}
+
+//==================================================================================================
+fn parse_snapshot(root: Node<'_, '_>) -> CompanySnapshot {
+    let issue = descendant(root, "Issue");
+    CompanySnapshot {
+        company_name: company_name(root),
+        ticker: issue
+            .and_then(|issue| typed_child(issue, "IssueID", "Ticker"))
+            .unwrap_or_default(),
+        exchange: issue
+            .and_then(|issue| descendant(issue, "Exchange"))
+            .map(|exchange| exchange.attribute("Code").unwrap_or("").to_string())
+            .unwrap_or_default(),
+        reporting_currency: descendant(root, "ReportingCurrency")
+            .and_then(|currency| currency.attribute("Code"))
+            .unwrap_or("")
+            .to_string(),
+        employees: descendant(root, "Employees").and_then(|node| text(node).parse().ok()),
+        shares_outstanding: descendant(root, "SharesOut").and_then(|node| text(node).parse().ok()),
+        business_summary: descendant(root, "TextInfo")
+            .and_then(|info| typed_child(info, "Text", "Business Summary"))
+            .unwrap_or_default(),
+        industries: root
+            .descendants()
+            .filter(|node| node.has_tag_name("Industry"))
+            .map(|industry| Industry {
+                classification: industry.attribute("type").unwrap_or("").to_string(),
+                code: industry.attribute("code").unwrap_or("").to_string(),
+                description: text(industry),
+            })
+            .collect(),
+        ratios: children(root, "Ratios")
+            .next()
+            .map(parse_ratios)
+            .unwrap_or_default(),
+        forecasts: children(root, "ForecastData")
+            .next()
+            .map(|forecast| {
+                children(forecast, "Ratio")
+                    .map(|ratio| Ratio {
+                        group: String::new(),
+                        field_name: ratio.attribute("FieldName").unwrap_or("").to_string(),
+                        value_type: ratio.attribute("Type").unwrap_or("").to_string(),
+                        value: children(ratio, "Value")
+                            .next()
+                            .map(text)
+                            .unwrap_or_default(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+//==================================================================================================
+fn parse_ratios(ratios: Node<'_, '_>) -> Vec<Ratio> {
+    children(ratios, "Group")
+        .flat_map(|group| {
+            let id = group.attribute("ID").unwrap_or("").to_string();
+            children(group, "Ratio").map(move |ratio| Ratio {
+                group: id.clone(),
+                field_name: ratio.attribute("FieldName").unwrap_or("").to_string(),
+                value_type: ratio.attribute("Type").unwrap_or("").to_string(),
+                value: text(ratio),
+            })
+        })
+        .collect()
+}
+
+//==================================================================================================
+fn parse_fin_summary(root: Node<'_, '_>) -> FinancialSummary {
+    let summary_values = |list: &str, item: &str| -> (String, Vec<SummaryValue>) {
+        match children(root, list).next() {
+            Some(list) => (
+                list.attribute("currency").unwrap_or("").to_string(),
+                children(list, item)
+                    .filter_map(|value| {
+                        Some(SummaryValue {
+                            as_of_date: value.attribute("asofDate").unwrap_or("").to_string(),
+                            report_type: value.attribute("reportType").unwrap_or("").to_string(),
+                            period: value.attribute("period").unwrap_or("").to_string(),
+                            value: text(value).parse().ok()?,
+                        })
+                    })
+                    .collect(),
+            ),
+            None => (String::new(), Vec::new()),
+        }
+    };
+
+    let (currency, eps) = summary_values("EPSs", "EPS");
+    let (_, dividends_per_share) = summary_values("DividendPerShares", "DividendPerShare");
+    let (_, total_revenues) = summary_values("TotalRevenues", "TotalRevenue");
+    let dividends = children(root, "Dividends")
+        .flat_map(|list| children(list, "Dividend"))
+        .filter_map(|dividend| {
+            let attribute = |name| dividend.attribute(name).unwrap_or("").to_string();
+            Some(DividendEvent {
+                dividend_type: attribute("type"),
+                ex_date: attribute("exDate"),
+                record_date: attribute("recordDate"),
+                pay_date: attribute("payDate"),
+                declaration_date: attribute("declarationDate"),
+                value: text(dividend).parse().ok()?,
+            })
+        })
+        .collect();
+
+    FinancialSummary {
+        currency,
+        eps,
+        dividends_per_share,
+        total_revenues,
+        dividends,
+    }
+}
+
+//==================================================================================================
+fn parse_fin_statements(root: Node<'_, '_>) -> FinancialStatements {
+    let coa_map: HashMap<String, String> = descendant(root, "COAMap")
+        .map(|map| {
+            children(map, "mapItem")
+                .map(|item| {
+                    (
+                        item.attribute("coaItem").unwrap_or("").to_string(),
+                        text(item),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let periods = |list: &str| -> Vec<FiscalPeriod> {
+        descendant(root, list)
+            .map(|list| {
+                children(list, "FiscalPeriod")
+                    .map(|period| parse_fiscal_period(period, &coa_map))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let annual = periods("AnnualPeriods");
+    let interim = periods("InterimPeriods");
+    FinancialStatements {
+        company_name: company_name(root),
+        coa_map,
+        annual,
+        interim,
+    }
+}
+
+//==================================================================================================
+fn parse_fiscal_period(period: Node<'_, '_>, coa_map: &HashMap<String, String>) -> FiscalPeriod {
+    FiscalPeriod {
+        period_type: period.attribute("Type").unwrap_or("").to_string(),
+        end_date: period.attribute("EndDate").unwrap_or("").to_string(),
+        fiscal_year: period.attribute("FiscalYear").and_then(|y| y.parse().ok()),
+        statements: children(period, "Statement")
+            .map(|statement| {
+                let header = children(statement, "FPHeader").next();
+                let header_text = |tag: &str| {
+                    header
+                        .and_then(|header| children(header, tag).next())
+                        .map(text)
+                        .unwrap_or_default()
+                };
+                Statement {
+                    statement_type: StatementType::from_code(
+                        statement.attribute("Type").unwrap_or(""),
+                    ),
+                    statement_date: header_text("StatementDate"),
+                    period_length: header_text("PeriodLength").parse().ok(),
+                    period_unit: header
+                        .and_then(|header| children(header, "periodType").next())
+                        .and_then(|unit| unit.attribute("Code"))
+                        .unwrap_or("")
+                        .to_string(),
+                    rows: children(statement, "lineItem")
+                        .filter_map(|item| {
+                            let coa_code = item.attribute("coaCode")?.to_string();
+                            Some(StatementRow {
+                                description: coa_map.get(&coa_code).cloned().unwrap_or_default(),
+                                value: text(item).parse().ok()?,
+                                coa_code,
+                            })
+                        })
+                        .collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+//==================================================================================================
+fn parse_estimates(root: Node<'_, '_>) -> AnalystEstimates {
+    let mut estimates = Vec::new();
+    for measure in root
+        .descendants()
+        .filter(|node| node.has_tag_name("FYEstimate") || node.has_tag_name("NPEstimate"))
+    {
+        for period in children(measure, "FYPeriod") {
+            let mut estimate = Estimate {
+                measure: measure.attribute("type").unwrap_or("").to_string(),
+                unit: measure.attribute("unit").unwrap_or("").to_string(),
+                period_type: period.attribute("periodType").unwrap_or("").to_string(),
+                fiscal_year: period.attribute("fYear").and_then(|y| y.parse().ok()),
+                end_month: period.attribute("endMonth").and_then(|m| m.parse().ok()),
+                ..Default::default()
+            };
+            for consensus in children(period, "ConsEstimate") {
+                let current = children(consensus, "ConsValue")
+                    .find(|value| value.attribute("dateType") == Some("CURR"))
+                    .and_then(|value| text(value).parse().ok());
+                match consensus.attribute("type").unwrap_or("") {
+                    "High" => estimate.high = current,
+                    "Low" => estimate.low = current,
+                    "Mean" => estimate.mean = current,
+                    "Median" => estimate.median = current,
+                    "StdDev" => estimate.std_dev = current,
+                    "NumOfEst" => estimate.num_of_est = current,
+                    _ => (),
+                }
+            }
+            estimates.push(estimate);
+        }
+    }
+    AnalystEstimates {
+        company_name: company_name(root),
+        estimates,
+    }
+}
+
+//==================================================================================================
+fn company_name(root: Node<'_, '_>) -> String {
+    descendant(root, "CoIDs")
+        .and_then(|ids| typed_child(ids, "CoID", "CompanyName"))
+        .unwrap_or_default()
+}
+
+//==================================================================================================
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+//==================================================================================================
+fn descendant<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.descendants().find(|child| child.has_tag_name(tag))
+}
+
+//==================================================================================================
+/// Text of the first `tag` child whose Type attribute is `type_name`
+fn typed_child(node: Node<'_, '_>, tag: &str, type_name: &str) -> Option<String> {
+    children(node, tag)
+        .find(|child| child.attribute("Type") == Some(type_name))
+        .map(text)
+}
+
+//==================================================================================================
+fn text(node: Node<'_, '_>) -> String {
+    node.text().unwrap_or("").trim().to_string()
+}
@@ -0,0 +1,150 @@
+//! Currency conversion orders on IDEALPRO
+//!
+//! IB quotes every currency pair one way round, such as EUR.USD or USD.JPY, so converting a
+//! balance is a sell of the base currency or a buy of it with a cash quantity in the quote
+//! currency.  convert() picks the pair and the side:
+//!
+//! ```no_run
+//! use ibtwsapi::core::fx;
+//!
+//! // sells 10,000 EUR on EUR.USD
+//! let to_usd = fx::convert(10_000.0, "EUR", "USD").unwrap();
+//! // buys EUR on EUR.USD for 10,000 USD, at 1.08 USD per EUR or better
+//! let to_eur = fx::convert(10_000.0, "USD", "EUR").unwrap().limit(1.08);
+//! ```
+//!
+//! Orders below the IDEALPRO minimum, about 25,000 USD, are routed as odd lots at a wider spread.
+use serde::{Deserialize, Serialize};
+
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::Order;
+
+pub const IDEALPRO: &str = "IDEALPRO";
+
+/// Currencies that are the base of their pairs, in order: each is the base against those after
+/// it and against every currency not listed
+const BASE_PRIORITY: [&str; 7] = ["EUR", "GBP", "AUD", "NZD", "USD", "CAD", "CHF"];
+/// Currencies that are the quote of their pairs against every other currency
+const QUOTE_ONLY: [&str; 1] = ["JPY"];
+
+//==================================================================================================
+/// A currency pair as IB quotes it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CurrencyPair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl CurrencyPair {
+    /// The pair IB quotes for two currencies, in either order
+    pub fn of(first: &str, second: &str) -> Result<Self, IBKRApiLibError> {
+        let first = first.trim().to_uppercase();
+        let second = second.trim().to_uppercase();
+        if first.len() != 3 || second.len() != 3 || first == second {
+            return Err(IBKRApiLibError::Config(format!(
+                "no currency pair for {:?} and {:?}",
+                first, second
+            )));
+        }
+
+        let rank = |currency: &str| {
+            if let Some(index) = BASE_PRIORITY.iter().position(|base| *base == currency) {
+                Some(index)
+            } else if QUOTE_ONLY.contains(&currency) {
+                Some(BASE_PRIORITY.len() + 1)
+            } else {
+                None
+            }
+        };
+        let first_is_base = match (rank(&first), rank(&second)) {
+            (Some(first), Some(second)) => first < second,
+            (Some(first), None) => first < BASE_PRIORITY.len(),
+            (None, Some(second)) => second >= BASE_PRIORITY.len(),
+            (None, None) => {
+                return Err(IBKRApiLibError::Config(format!(
+                    "the quoting of {}/{} is not known, build its CurrencyPair directly",
+                    first, second
+                )))
+            }
+        };
+        Ok(if first_is_base {
+            CurrencyPair {
+                base: first,
+                quote: second,
+            }
+        } else {
+            CurrencyPair {
+                base: second,
+                quote: first,
+            }
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The CASH contract of the pair on IDEALPRO
+    pub fn contract(&self) -> Contract {
+        Contract {
+            symbol: self.base.clone(),
+            sec_type: "CASH".to_string(),
+            exchange: IDEALPRO.to_string(),
+            currency: self.quote.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+//==================================================================================================
+/// The contract and order that convert an amount of one currency into another
+#[derive(Clone, Debug)]
+pub struct FxConversion {
+    pub pair: CurrencyPair,
+    pub contract: Contract,
+    /// A market order, or a limit order after limit()
+    pub order: Order,
+}
+
+impl FxConversion {
+    /// Makes the order a limit order at a price of the pair, in quote currency per unit of base
+    /// currency, whichever way the conversion goes
+    pub fn limit(mut self, price: f64) -> Self {
+        self.order.order_type = "LMT".to_string();
+        self.order.lmt_price = price;
+        self
+    }
+}
+
+//==================================================================================================
+/// Builds the order that converts `amount` of the currency `from` into the currency `to`
+///
+/// When `from` is the base of the pair the order sells `amount` of it.  Otherwise it buys the
+/// base currency with a cash quantity of `amount` in `from`.
+pub fn convert(amount: f64, from: &str, to: &str) -> Result<FxConversion, IBKRApiLibError> {
+    if !(amount > 0.0 && amount.is_finite()) {
+        return Err(IBKRApiLibError::Config(format!(
+            "invalid amount {} to convert",
+            amount
+        )));
+    }
+    let pair = CurrencyPair::of(from, to)?;
+    let order = if pair.base.eq_ignore_ascii_case(from.trim()) {
+        Order {
+            action: "SELL".to_string(),
+            order_type: "MKT".to_string(),
+            total_quantity: amount,
+            ..Default::default()
+        }
+    } else {
+        Order {
+            action: "BUY".to_string(),
+            order_type: "MKT".to_string(),
+            ..Default::default()
+        }
+        .with_cash_qty(amount)
+    };
+    Ok(FxConversion {
+        contract: pair.contract(),
+        pair,
+        order,
+    })
+}
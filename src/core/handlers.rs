@@ -0,0 +1,1205 @@
+//! Callback traits for decoded events, split by area so an application implements only the
+//! callbacks it needs.  Every callback has an empty default.
+//!
+//! A struct that implements several handler traits implements Handlers as well, returning
+//! itself for each of them.  Separate handler objects are combined with HandlerSet.  Either is
+//! passed to dispatch() or EClient::dispatch_events().
+//!
+//! ```no_run
+//! use ibtwsapi::core::common::{TickAttrib, TickType};
+//! use ibtwsapi::core::handlers::{dispatch, HandlerSet, MarketDataHandler};
+//! use ibtwsapi::core::messages::ServerRspMsg;
+//!
+//! struct Prices;
+//!
+//! impl MarketDataHandler for Prices {
+//!     fn tick_price(&mut self, req_id: i32, tick_type: TickType, price: f64, _: &TickAttrib) {
+//!         println!("{} {} {}", req_id, tick_type, price);
+//!     }
+//! }
+//!
+//! # fn handle(msg: &ServerRspMsg) {
+//! let mut prices = Prices;
+//! dispatch(msg, &mut HandlerSet::new().market_data(&mut prices));
+//! # }
+//! ```
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::core::account_values::{AccountKey, AccountValue};
+use crate::core::common::{
+    AccountId, BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
+    HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider,
+    PriceIncrement, RealTimeBar, RtVolumeTick, SmartComponent, TickAttrib, TickByTickData,
+    TickType,
+};
+use crate::core::connection::{ConnectivityNotice, FarmKind, FarmStatus};
+use crate::core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract};
+use crate::core::execution::Execution;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderState, OrderStatusKind, SoftDollarTier, WhyHeld};
+
+//==================================================================================================
+/// Errors, session and connection level events
+pub trait ConnectionHandler {
+    fn next_valid_id(&mut self, _order_id: i32) {}
+    /// Errors and notices, except the connectivity and data farm notices passed to the
+    /// callbacks below
+    fn error(&mut self, _req_id: i32, _error_code: i32, _error_str: &str) {}
+    /// TWS lost its connection to IB (error 1100)
+    fn connectivity_lost(&mut self) {}
+    /// TWS is connected to IB again (errors 1101 and 1102).  Market data subscriptions have to
+    /// be made again unless `data_maintained`.
+    fn connectivity_restored(&mut self, _data_maintained: bool) {}
+    /// A data farm connection changed state (errors 2103-2108, 2119, 2157 and 2158)
+    fn farm_status(&mut self, _kind: FarmKind, _name: &str, _status: FarmStatus) {}
+    fn current_time(&mut self, _time: i64) {}
+    fn managed_accounts(&mut self, _accounts: &[AccountId]) {}
+    fn receive_fa(&mut self, _fa_data: &FaDataType, _cxml: &str) {}
+    fn verify_message_api(&mut self, _api_data: &str) {}
+    fn verify_completed(&mut self, _is_successful: bool, _error_text: &str) {}
+    fn verify_and_auth_message_api(&mut self, _api_data: &str, _xyz_challenge: &str) {}
+    fn verify_and_auth_completed(&mut self, _is_successful: bool, _error_text: &str) {}
+    fn display_group_list(&mut self, _req_id: i32, _groups: &str) {}
+    fn display_group_updated(&mut self, _req_id: i32, _contract_info: &str) {}
+}
+
+//==================================================================================================
+/// Streaming and snapshot market data, market depth and real time bars
+pub trait MarketDataHandler {
+    fn tick_price(
+        &mut self,
+        _req_id: i32,
+        _tick_type: TickType,
+        _price: f64,
+        _attrib: &TickAttrib,
+    ) {
+    }
+    fn tick_size(&mut self, _req_id: i32, _tick_type: TickType, _size: i32) {}
+    #[allow(clippy::too_many_arguments)]
+    fn tick_option_computation(
+        &mut self,
+        _req_id: i32,
+        _tick_type: TickType,
+        _implied_vol: f64,
+        _delta: f64,
+        _opt_price: f64,
+        _pv_dividend: f64,
+        _gamma: f64,
+        _vega: f64,
+        _theta: f64,
+        _und_price: f64,
+    ) {
+    }
+    fn tick_generic(&mut self, _req_id: i32, _tick_type: TickType, _value: f64) {}
+    fn tick_string(&mut self, _req_id: i32, _tick_type: TickType, _value: &str) {}
+    /// An RTVolume or RTTradeVolume tick string, parsed.  tick_string() receives it as well.
+    fn rt_volume(&mut self, _req_id: i32, _tick_type: TickType, _tick: &RtVolumeTick) {}
+    #[allow(clippy::too_many_arguments)]
+    fn tick_efp(
+        &mut self,
+        _req_id: i32,
+        _tick_type: TickType,
+        _basis_points: f64,
+        _formatted_basis_points: &str,
+        _implied_futures_price: f64,
+        _hold_days: i32,
+        _future_last_trade_date: &str,
+        _dividend_impact: f64,
+        _dividends_to_last_trade_date: f64,
+    ) {
+    }
+    fn tick_snapshot_end(&mut self, _req_id: i32) {}
+    fn market_data_type(&mut self, _req_id: i32, _market_data_type: i32) {}
+    fn tick_req_params(
+        &mut self,
+        _req_id: i32,
+        _min_tick: f64,
+        _bbo_exchange: &str,
+        _snapshot_permissions: i32,
+    ) {
+    }
+    fn tick_by_tick(&mut self, _req_id: i32, _time: i64, _tick: &TickByTickData) {}
+    fn update_mkt_depth(
+        &mut self,
+        _req_id: i32,
+        _position: i32,
+        _operation: i32,
+        _side: i32,
+        _price: f64,
+        _size: i32,
+    ) {
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn update_mkt_depth_l2(
+        &mut self,
+        _req_id: i32,
+        _position: i32,
+        _market_maker: &str,
+        _operation: i32,
+        _side: i32,
+        _price: f64,
+        _size: i32,
+        _is_smart_depth: bool,
+    ) {
+    }
+    fn mkt_depth_exchanges(&mut self, _descriptions: &[DepthMktDataDescription]) {}
+    fn smart_components(&mut self, _req_id: i32, _smart_components: &[SmartComponent]) {}
+    fn reroute_mkt_data_req(&mut self, _req_id: i32, _con_id: i32, _exchange: &str) {}
+    fn reroute_mkt_depth_req(&mut self, _req_id: i32, _con_id: i32, _exchange: &str) {}
+    fn real_time_bar(&mut self, _req_id: i32, _bar: &RealTimeBar) {}
+}
+
+//==================================================================================================
+/// Order status, open and completed orders, executions and commissions
+pub trait OrderHandler {
+    #[allow(clippy::too_many_arguments)]
+    fn order_status(
+        &mut self,
+        _order_id: i32,
+        _status: &OrderStatusKind,
+        _filled: f64,
+        _remaining: f64,
+        _avg_fill_price: f64,
+        _perm_id: i64,
+        _parent_id: i32,
+        _last_fill_price: f64,
+        _client_id: i32,
+        _why_held: &WhyHeld,
+        _mkt_cap_price: f64,
+    ) {
+    }
+    fn open_order(
+        &mut self,
+        _order_id: i32,
+        _contract: &Contract,
+        _order: &Order,
+        _order_state: &OrderState,
+    ) {
+    }
+    fn open_order_end(&mut self) {}
+    fn order_bound(&mut self, _req_id: i32, _api_client_id: i32, _api_order_id: i32) {}
+    fn completed_order(&mut self, _contract: &Contract, _order: &Order, _order_state: &OrderState) {
+    }
+    fn completed_orders_end(&mut self) {}
+    fn exec_details(&mut self, _req_id: i32, _contract: &Contract, _execution: &Execution) {}
+    fn exec_details_end(&mut self, _req_id: i32) {}
+    fn commission_report(&mut self, _commission_report: &CommissionReport) {}
+    fn soft_dollar_tiers(&mut self, _req_id: i32, _tiers: &[SoftDollarTier]) {}
+}
+
+//==================================================================================================
+/// Account values, portfolio, positions and profit and loss
+pub trait AccountHandler {
+    fn update_account_value(&mut self, _key: &AccountKey, _value: &AccountValue, _account: &str) {}
+    #[allow(clippy::too_many_arguments)]
+    fn update_portfolio(
+        &mut self,
+        _contract: &Contract,
+        _position: f64,
+        _market_price: f64,
+        _market_value: f64,
+        _average_cost: f64,
+        _unrealized_pnl: f64,
+        _realized_pnl: f64,
+        _account_name: &str,
+    ) {
+    }
+    fn update_account_time(&mut self, _time_stamp: &str) {}
+    fn account_download_end(&mut self, _account_name: &str) {}
+    fn account_summary(
+        &mut self,
+        _req_id: i32,
+        _account: &str,
+        _tag: &str,
+        _value: &str,
+        _currency: &str,
+    ) {
+    }
+    fn account_summary_end(&mut self, _req_id: i32) {}
+    fn account_update_multi(
+        &mut self,
+        _req_id: i32,
+        _account: &str,
+        _model_code: &str,
+        _key: &AccountKey,
+        _value: &AccountValue,
+    ) {
+    }
+    fn account_update_multi_end(&mut self, _req_id: i32) {}
+    fn position(&mut self, _account: &str, _contract: &Contract, _position: f64, _avg_cost: f64) {}
+    fn position_end(&mut self) {}
+    fn position_multi(
+        &mut self,
+        _req_id: i32,
+        _account: &str,
+        _model_code: &str,
+        _contract: &Contract,
+        _position: f64,
+        _avg_cost: f64,
+    ) {
+    }
+    fn position_multi_end(&mut self, _req_id: i32) {}
+    fn pnl(&mut self, _req_id: i32, _daily_pnl: f64, _unrealized_pnl: f64, _realized_pnl: f64) {}
+    fn pnl_single(
+        &mut self,
+        _req_id: i32,
+        _pos: i32,
+        _daily_pnl: f64,
+        _unrealized_pnl: f64,
+        _realized_pnl: f64,
+        _value: f64,
+    ) {
+    }
+    fn family_codes(&mut self, _family_codes: &[FamilyCode]) {}
+}
+
+//==================================================================================================
+/// Contract details, symbol searches, option chains and fundamental data
+pub trait ContractHandler {
+    fn contract_details(&mut self, _req_id: i32, _contract_details: &ContractDetails) {}
+    fn bond_contract_details(&mut self, _req_id: i32, _contract_details: &ContractDetails) {}
+    fn contract_details_end(&mut self, _req_id: i32) {}
+    fn symbol_samples(&mut self, _req_id: i32, _descriptions: &[ContractDescription]) {}
+    #[allow(clippy::too_many_arguments)]
+    fn security_definition_option_parameter(
+        &mut self,
+        _req_id: i32,
+        _exchange: &str,
+        _underlying_con_id: i32,
+        _trading_class: &str,
+        _multiplier: &str,
+        _expirations: &HashSet<String>,
+        _strikes: &HashSet<Decimal>,
+    ) {
+    }
+    fn security_definition_option_parameter_end(&mut self, _req_id: i32) {}
+    fn market_rule(&mut self, _market_rule_id: i32, _price_increments: &[PriceIncrement]) {}
+    fn delta_neutral_validation(&mut self, _req_id: i32, _contract: &DeltaNeutralContract) {}
+    fn fundamental_data(&mut self, _req_id: i32, _data: &str) {}
+}
+
+//==================================================================================================
+/// Market scanner parameters and results
+pub trait ScannerHandler {
+    fn scanner_parameters(&mut self, _xml: &str) {}
+    #[allow(clippy::too_many_arguments)]
+    fn scanner_data(
+        &mut self,
+        _req_id: i32,
+        _rank: i32,
+        _contract_details: &ContractDetails,
+        _distance: &str,
+        _benchmark: &str,
+        _projection: &str,
+        _legs_str: &str,
+    ) {
+    }
+    fn scanner_data_end(&mut self, _req_id: i32) {}
+}
+
+//==================================================================================================
+/// News bulletins, headlines and articles
+pub trait NewsHandler {
+    fn update_news_bulletin(
+        &mut self,
+        _msg_id: i32,
+        _msg_type: i32,
+        _news_message: &str,
+        _origin_exch: &str,
+    ) {
+    }
+    fn tick_news(
+        &mut self,
+        _req_id: i32,
+        _time_stamp: i64,
+        _provider_code: &str,
+        _article_id: &str,
+        _headline: &str,
+        _extra_data: &str,
+    ) {
+    }
+    fn news_providers(&mut self, _news_providers: &[NewsProvider]) {}
+    fn news_article(&mut self, _req_id: i32, _article_type: i32, _article_text: &str) {}
+    fn historical_news(
+        &mut self,
+        _req_id: i32,
+        _time: &Option<DateTime<Utc>>,
+        _provider_code: &str,
+        _article_id: &str,
+        _headline: &str,
+    ) {
+    }
+    fn historical_news_end(&mut self, _req_id: i32, _has_more: bool) {}
+}
+
+//==================================================================================================
+/// Historical bars, ticks, histograms and head timestamps
+pub trait HistoricalHandler {
+    fn historical_data(&mut self, _req_id: i32, _bar: &BarData) {}
+    fn historical_data_end(&mut self, _req_id: i32, _start: &str, _end: &str) {}
+    fn historical_data_update(&mut self, _req_id: i32, _bar: &BarData) {}
+    fn head_timestamp(&mut self, _req_id: i32, _head_timestamp: &Option<DateTime<Utc>>) {}
+    fn histogram_data(&mut self, _req_id: i32, _items: &[HistogramData]) {}
+    fn historical_ticks(&mut self, _req_id: i32, _ticks: &[HistoricalTick], _done: bool) {}
+    fn historical_ticks_bid_ask(
+        &mut self,
+        _req_id: i32,
+        _ticks: &[HistoricalTickBidAsk],
+        _done: bool,
+    ) {
+    }
+    fn historical_ticks_last(&mut self, _req_id: i32, _ticks: &[HistoricalTickLast], _done: bool) {}
+}
+
+//==================================================================================================
+/// The handlers an application registered.  Areas without a handler give None and their events
+/// are not delivered.
+pub trait Handlers {
+    fn connection(&mut self) -> Option<&mut dyn ConnectionHandler> {
+        None
+    }
+    fn market_data(&mut self) -> Option<&mut dyn MarketDataHandler> {
+        None
+    }
+    fn orders(&mut self) -> Option<&mut dyn OrderHandler> {
+        None
+    }
+    fn account(&mut self) -> Option<&mut dyn AccountHandler> {
+        None
+    }
+    fn contracts(&mut self) -> Option<&mut dyn ContractHandler> {
+        None
+    }
+    fn scanner(&mut self) -> Option<&mut dyn ScannerHandler> {
+        None
+    }
+    fn news(&mut self) -> Option<&mut dyn NewsHandler> {
+        None
+    }
+    fn historical(&mut self) -> Option<&mut dyn HistoricalHandler> {
+        None
+    }
+}
+
+//==================================================================================================
+/// Handlers registered one by one, each a separate object
+#[derive(Default)]
+pub struct HandlerSet<'a> {
+    connection: Option<&'a mut dyn ConnectionHandler>,
+    market_data: Option<&'a mut dyn MarketDataHandler>,
+    orders: Option<&'a mut dyn OrderHandler>,
+    account: Option<&'a mut dyn AccountHandler>,
+    contracts: Option<&'a mut dyn ContractHandler>,
+    scanner: Option<&'a mut dyn ScannerHandler>,
+    news: Option<&'a mut dyn NewsHandler>,
+    historical: Option<&'a mut dyn HistoricalHandler>,
+}
+
+impl<'a> HandlerSet<'a> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn connection(mut self, handler: &'a mut dyn ConnectionHandler) -> Self {
+        self.connection = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn market_data(mut self, handler: &'a mut dyn MarketDataHandler) -> Self {
+        self.market_data = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn orders(mut self, handler: &'a mut dyn OrderHandler) -> Self {
+        self.orders = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn account(mut self, handler: &'a mut dyn AccountHandler) -> Self {
+        self.account = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn contracts(mut self, handler: &'a mut dyn ContractHandler) -> Self {
+        self.contracts = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn scanner(mut self, handler: &'a mut dyn ScannerHandler) -> Self {
+        self.scanner = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn news(mut self, handler: &'a mut dyn NewsHandler) -> Self {
+        self.news = Some(handler);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn historical(mut self, handler: &'a mut dyn HistoricalHandler) -> Self {
+        self.historical = Some(handler);
+        self
+    }
+}
+
+impl<'a> Handlers for HandlerSet<'a> {
+    fn connection(&mut self) -> Option<&mut dyn ConnectionHandler> {
+        match &mut self.connection {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn market_data(&mut self) -> Option<&mut dyn MarketDataHandler> {
+        match &mut self.market_data {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn orders(&mut self) -> Option<&mut dyn OrderHandler> {
+        match &mut self.orders {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn account(&mut self) -> Option<&mut dyn AccountHandler> {
+        match &mut self.account {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn contracts(&mut self) -> Option<&mut dyn ContractHandler> {
+        match &mut self.contracts {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn scanner(&mut self) -> Option<&mut dyn ScannerHandler> {
+        match &mut self.scanner {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn news(&mut self) -> Option<&mut dyn NewsHandler> {
+        match &mut self.news {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+    fn historical(&mut self) -> Option<&mut dyn HistoricalHandler> {
+        match &mut self.historical {
+            Some(handler) => Some(&mut **handler),
+            None => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// Calls the callback for an event.  Returns whether a handler for the event's area was
+/// registered.
+///
+/// # Arguments
+/// * msg - A decoded event
+/// * handlers - The registered handlers
+pub fn dispatch(msg: &ServerRspMsg, handlers: &mut dyn Handlers) -> bool {
+    match msg {
+        ServerRspMsg::NextValidId { order_id } => handlers
+            .connection()
+            .map(|handler| handler.next_valid_id(*order_id))
+            .is_some(),
+        ServerRspMsg::ErrMsg {
+            req_id,
+            error_code,
+            error_str,
+        } => handlers
+            .connection()
+            .map(
+                |handler| match ConnectivityNotice::from_error(*error_code, error_str) {
+                    Some(ConnectivityNotice::Lost) => handler.connectivity_lost(),
+                    Some(ConnectivityNotice::Restored { data_maintained }) => {
+                        handler.connectivity_restored(data_maintained)
+                    }
+                    Some(ConnectivityNotice::Farm { kind, name, status }) => {
+                        handler.farm_status(kind, &name, status)
+                    }
+                    None => handler.error(*req_id, *error_code, error_str),
+                },
+            )
+            .is_some(),
+        ServerRspMsg::CurrentTime { time } => handlers
+            .connection()
+            .map(|handler| handler.current_time(*time))
+            .is_some(),
+        ServerRspMsg::ManagedAccts { accounts } => handlers
+            .connection()
+            .map(|handler| handler.managed_accounts(accounts))
+            .is_some(),
+        ServerRspMsg::ReceiveFa { fa_data, cxml } => handlers
+            .connection()
+            .map(|handler| handler.receive_fa(fa_data, cxml))
+            .is_some(),
+        ServerRspMsg::VerifyMessageApi { api_data } => handlers
+            .connection()
+            .map(|handler| handler.verify_message_api(api_data))
+            .is_some(),
+        ServerRspMsg::VerifyCompleted {
+            is_successful,
+            error_text,
+        } => handlers
+            .connection()
+            .map(|handler| handler.verify_completed(*is_successful, error_text))
+            .is_some(),
+        ServerRspMsg::VerifyAndAuthMessageApi {
+            api_data,
+            xyz_challenge,
+        } => handlers
+            .connection()
+            .map(|handler| handler.verify_and_auth_message_api(api_data, xyz_challenge))
+            .is_some(),
+        ServerRspMsg::VerifyAndAuthCompleted {
+            is_successful,
+            error_text,
+        } => handlers
+            .connection()
+            .map(|handler| handler.verify_and_auth_completed(*is_successful, error_text))
+            .is_some(),
+        ServerRspMsg::DisplayGroupList { req_id, groups } => handlers
+            .connection()
+            .map(|handler| handler.display_group_list(*req_id, groups))
+            .is_some(),
+        ServerRspMsg::DisplayGroupUpdated {
+            req_id,
+            contract_info,
+        } => handlers
+            .connection()
+            .map(|handler| handler.display_group_updated(*req_id, contract_info))
+            .is_some(),
+
+        ServerRspMsg::TickPrice {
+            req_id,
+            tick_type,
+            price,
+            tick_attr,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.tick_price(*req_id, *tick_type, *price, tick_attr))
+            .is_some(),
+        ServerRspMsg::TickSize {
+            req_id,
+            tick_type,
+            size,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.tick_size(*req_id, *tick_type, *size))
+            .is_some(),
+        ServerRspMsg::TickOptionComputation {
+            ticker_id,
+            tick_type,
+            implied_vol,
+            delta,
+            opt_price,
+            pv_dividend,
+            gamma,
+            vega,
+            theta,
+            und_price,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.tick_option_computation(
+                    *ticker_id,
+                    *tick_type,
+                    *implied_vol,
+                    *delta,
+                    *opt_price,
+                    *pv_dividend,
+                    *gamma,
+                    *vega,
+                    *theta,
+                    *und_price,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::TickGeneric {
+            ticker_id,
+            tick_type,
+            value,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.tick_generic(*ticker_id, *tick_type, *value))
+            .is_some(),
+        ServerRspMsg::TickString {
+            req_id,
+            tick_type,
+            value,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.tick_string(*req_id, *tick_type, value);
+                if RtVolumeTick::is_rt_volume(*tick_type) {
+                    if let Ok(tick) = value.parse() {
+                        handler.rt_volume(*req_id, *tick_type, &tick);
+                    }
+                }
+            })
+            .is_some(),
+        ServerRspMsg::TickEfp {
+            ticker_id,
+            tick_type,
+            basis_points,
+            formatted_basis_points,
+            implied_futures_price,
+            hold_days,
+            future_last_trade_date,
+            dividend_impact,
+            dividends_to_last_trade_date,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.tick_efp(
+                    *ticker_id,
+                    *tick_type,
+                    *basis_points,
+                    formatted_basis_points,
+                    *implied_futures_price,
+                    *hold_days,
+                    future_last_trade_date,
+                    *dividend_impact,
+                    *dividends_to_last_trade_date,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::TickSnapshotEnd { req_id } => handlers
+            .market_data()
+            .map(|handler| handler.tick_snapshot_end(*req_id))
+            .is_some(),
+        ServerRspMsg::MarketDataType {
+            req_id,
+            market_data_type,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.market_data_type(*req_id, *market_data_type))
+            .is_some(),
+        ServerRspMsg::TickReqParams {
+            ticker_id,
+            min_tick,
+            bbo_exchange,
+            snapshot_permissions,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.tick_req_params(*ticker_id, *min_tick, bbo_exchange, *snapshot_permissions)
+            })
+            .is_some(),
+        ServerRspMsg::TickByTick { req_id, time, tick } => handlers
+            .market_data()
+            .map(|handler| handler.tick_by_tick(*req_id, *time, tick))
+            .is_some(),
+        ServerRspMsg::MarketDepth {
+            req_id,
+            position,
+            operation,
+            side,
+            price,
+            size,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.update_mkt_depth(*req_id, *position, *operation, *side, *price, *size)
+            })
+            .is_some(),
+        ServerRspMsg::MarketDepthL2 {
+            req_id,
+            position,
+            market_maker,
+            operation,
+            side,
+            price,
+            size,
+            is_smart_depth,
+        } => handlers
+            .market_data()
+            .map(|handler| {
+                handler.update_mkt_depth_l2(
+                    *req_id,
+                    *position,
+                    market_maker,
+                    *operation,
+                    *side,
+                    *price,
+                    *size,
+                    *is_smart_depth,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::MktDepthExchanges {
+            depth_mkt_data_descriptions,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.mkt_depth_exchanges(depth_mkt_data_descriptions))
+            .is_some(),
+        ServerRspMsg::SmartComponents {
+            req_id,
+            smart_components,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.smart_components(*req_id, smart_components))
+            .is_some(),
+        ServerRspMsg::RerouteMktDataReq {
+            req_id,
+            con_id,
+            exchange,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.reroute_mkt_data_req(*req_id, *con_id, exchange))
+            .is_some(),
+        ServerRspMsg::RerouteMktDepthReq {
+            req_id,
+            con_id,
+            exchange,
+        } => handlers
+            .market_data()
+            .map(|handler| handler.reroute_mkt_depth_req(*req_id, *con_id, exchange))
+            .is_some(),
+        ServerRspMsg::RealTimeBars { req_id, bar } => handlers
+            .market_data()
+            .map(|handler| handler.real_time_bar(*req_id, bar))
+            .is_some(),
+
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status,
+            filled,
+            remaining,
+            avg_fill_price,
+            perm_id,
+            parent_id,
+            last_fill_price,
+            client_id,
+            why_held,
+            mkt_cap_price,
+        } => handlers
+            .orders()
+            .map(|handler| {
+                handler.order_status(
+                    *order_id,
+                    status,
+                    *filled,
+                    *remaining,
+                    *avg_fill_price,
+                    *perm_id,
+                    *parent_id,
+                    *last_fill_price,
+                    *client_id,
+                    why_held,
+                    *mkt_cap_price,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::OpenOrder {
+            order_id,
+            contract,
+            order,
+            order_state,
+        } => handlers
+            .orders()
+            .map(|handler| handler.open_order(*order_id, contract, order, order_state))
+            .is_some(),
+        ServerRspMsg::OpenOrderEnd => handlers
+            .orders()
+            .map(|handler| handler.open_order_end())
+            .is_some(),
+        ServerRspMsg::OrderBound {
+            req_id,
+            api_client_id,
+            api_order_id,
+        } => handlers
+            .orders()
+            .map(|handler| handler.order_bound(*req_id, *api_client_id, *api_order_id))
+            .is_some(),
+        ServerRspMsg::CompletedOrder {
+            contract,
+            order,
+            order_state,
+        } => handlers
+            .orders()
+            .map(|handler| handler.completed_order(contract, order, order_state))
+            .is_some(),
+        ServerRspMsg::CompletedOrdersEnd => handlers
+            .orders()
+            .map(|handler| handler.completed_orders_end())
+            .is_some(),
+        ServerRspMsg::ExecutionData {
+            req_id,
+            contract,
+            execution,
+        } => handlers
+            .orders()
+            .map(|handler| handler.exec_details(*req_id, contract, execution))
+            .is_some(),
+        ServerRspMsg::ExecutionDataEnd { req_id } => handlers
+            .orders()
+            .map(|handler| handler.exec_details_end(*req_id))
+            .is_some(),
+        ServerRspMsg::CommissionReport { commission_report } => handlers
+            .orders()
+            .map(|handler| handler.commission_report(commission_report))
+            .is_some(),
+        ServerRspMsg::SoftDollarTiers { req_id, tiers } => handlers
+            .orders()
+            .map(|handler| handler.soft_dollar_tiers(*req_id, tiers))
+            .is_some(),
+
+        ServerRspMsg::AcctValue {
+            key,
+            value,
+            account_name,
+        } => handlers
+            .account()
+            .map(|handler| handler.update_account_value(key, value, account_name))
+            .is_some(),
+        ServerRspMsg::PortfolioValue {
+            contract,
+            position,
+            market_price,
+            market_value,
+            average_cost,
+            unrealized_pnl,
+            realized_pnl,
+            account_name,
+        } => handlers
+            .account()
+            .map(|handler| {
+                handler.update_portfolio(
+                    contract,
+                    *position,
+                    *market_price,
+                    *market_value,
+                    *average_cost,
+                    *unrealized_pnl,
+                    *realized_pnl,
+                    account_name,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::AcctUpdateTime { time_stamp } => handlers
+            .account()
+            .map(|handler| handler.update_account_time(time_stamp))
+            .is_some(),
+        ServerRspMsg::AcctDownloadEnd { account_name } => handlers
+            .account()
+            .map(|handler| handler.account_download_end(account_name))
+            .is_some(),
+        ServerRspMsg::AccountSummary {
+            req_id,
+            account,
+            tag,
+            value,
+            currency,
+        } => handlers
+            .account()
+            .map(|handler| handler.account_summary(*req_id, account, tag, value, currency))
+            .is_some(),
+        ServerRspMsg::AccountSummaryEnd { req_id } => handlers
+            .account()
+            .map(|handler| handler.account_summary_end(*req_id))
+            .is_some(),
+        ServerRspMsg::AccountUpdateMulti {
+            req_id,
+            account,
+            model_code,
+            key,
+            value,
+        } => handlers
+            .account()
+            .map(|handler| handler.account_update_multi(*req_id, account, model_code, key, value))
+            .is_some(),
+        ServerRspMsg::AccountUpdateMultiEnd { req_id } => handlers
+            .account()
+            .map(|handler| handler.account_update_multi_end(*req_id))
+            .is_some(),
+        ServerRspMsg::PositionData {
+            account,
+            contract,
+            position,
+            avg_cost,
+        } => handlers
+            .account()
+            .map(|handler| handler.position(account, contract, *position, *avg_cost))
+            .is_some(),
+        ServerRspMsg::PositionEnd => handlers
+            .account()
+            .map(|handler| handler.position_end())
+            .is_some(),
+        ServerRspMsg::PositionMulti {
+            req_id,
+            account,
+            model_code,
+            contract,
+            position,
+            avg_cost,
+        } => handlers
+            .account()
+            .map(|handler| {
+                handler.position_multi(*req_id, account, model_code, contract, *position, *avg_cost)
+            })
+            .is_some(),
+        ServerRspMsg::PositionMultiEnd { req_id } => handlers
+            .account()
+            .map(|handler| handler.position_multi_end(*req_id))
+            .is_some(),
+        ServerRspMsg::Pnl {
+            req_id,
+            daily_pnl,
+            unrealized_pnl,
+            realized_pnl,
+        } => handlers
+            .account()
+            .map(|handler| handler.pnl(*req_id, *daily_pnl, *unrealized_pnl, *realized_pnl))
+            .is_some(),
+        ServerRspMsg::PnlSingle {
+            req_id,
+            pos,
+            daily_pnl,
+            unrealized_pnl,
+            realized_pnl,
+            value,
+        } => handlers
+            .account()
+            .map(|handler| {
+                handler.pnl_single(
+                    *req_id,
+                    *pos,
+                    *daily_pnl,
+                    *unrealized_pnl,
+                    *realized_pnl,
+                    *value,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::FamilyCodes { family_codes } => handlers
+            .account()
+            .map(|handler| handler.family_codes(family_codes))
+            .is_some(),
+
+        ServerRspMsg::ContractData {
+            req_id,
+            contract_details,
+        } => handlers
+            .contracts()
+            .map(|handler| handler.contract_details(*req_id, contract_details))
+            .is_some(),
+        ServerRspMsg::BondContractData {
+            req_id,
+            contract_details,
+        } => handlers
+            .contracts()
+            .map(|handler| handler.bond_contract_details(*req_id, contract_details))
+            .is_some(),
+        ServerRspMsg::ContractDataEnd { req_id } => handlers
+            .contracts()
+            .map(|handler| handler.contract_details_end(*req_id))
+            .is_some(),
+        ServerRspMsg::SymbolSamples {
+            req_id,
+            contract_descriptions,
+        } => handlers
+            .contracts()
+            .map(|handler| handler.symbol_samples(*req_id, contract_descriptions))
+            .is_some(),
+        ServerRspMsg::SecurityDefinitionOptionParameter {
+            req_id,
+            exchange,
+            underlying_con_id,
+            trading_class,
+            multiplier,
+            expirations,
+            strikes,
+        } => handlers
+            .contracts()
+            .map(|handler| {
+                handler.security_definition_option_parameter(
+                    *req_id,
+                    exchange,
+                    *underlying_con_id,
+                    trading_class,
+                    multiplier,
+                    expirations,
+                    strikes,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::SecurityDefinitionOptionParameterEnd { req_id } => handlers
+            .contracts()
+            .map(|handler| handler.security_definition_option_parameter_end(*req_id))
+            .is_some(),
+        ServerRspMsg::MarketRule {
+            market_rule_id,
+            price_increments,
+        } => handlers
+            .contracts()
+            .map(|handler| handler.market_rule(*market_rule_id, price_increments))
+            .is_some(),
+        ServerRspMsg::DeltaNeutralValidation {
+            req_id,
+            delta_neutral_contract,
+        } => handlers
+            .contracts()
+            .map(|handler| handler.delta_neutral_validation(*req_id, delta_neutral_contract))
+            .is_some(),
+        ServerRspMsg::FundamentalData { req_id, data } => handlers
+            .contracts()
+            .map(|handler| handler.fundamental_data(*req_id, data))
+            .is_some(),
+
+        ServerRspMsg::ScannerParameters { xml } => handlers
+            .scanner()
+            .map(|handler| handler.scanner_parameters(xml))
+            .is_some(),
+        ServerRspMsg::ScannerData {
+            req_id,
+            rank,
+            contract_details,
+            distance,
+            benchmark,
+            projection,
+            legs_str,
+        } => handlers
+            .scanner()
+            .map(|handler| {
+                handler.scanner_data(
+                    *req_id,
+                    *rank,
+                    contract_details,
+                    distance,
+                    benchmark,
+                    projection,
+                    legs_str,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::ScannerDataEnd { req_id } => handlers
+            .scanner()
+            .map(|handler| handler.scanner_data_end(*req_id))
+            .is_some(),
+
+        ServerRspMsg::NewsBulletins {
+            msg_id,
+            msg_type,
+            news_message,
+            origin_exch,
+        } => handlers
+            .news()
+            .map(|handler| {
+                handler.update_news_bulletin(*msg_id, *msg_type, news_message, origin_exch)
+            })
+            .is_some(),
+        ServerRspMsg::TickNews {
+            ticker_id,
+            time_stamp,
+            provider_code,
+            article_id,
+            headline,
+            extra_data,
+        } => handlers
+            .news()
+            .map(|handler| {
+                handler.tick_news(
+                    *ticker_id,
+                    *time_stamp,
+                    provider_code,
+                    article_id,
+                    headline,
+                    extra_data,
+                )
+            })
+            .is_some(),
+        ServerRspMsg::NewsProviders { news_providers } => handlers
+            .news()
+            .map(|handler| handler.news_providers(news_providers))
+            .is_some(),
+        ServerRspMsg::NewsArticle {
+            req_id,
+            article_type,
+            article_text,
+        } => handlers
+            .news()
+            .map(|handler| handler.news_article(*req_id, *article_type, article_text))
+            .is_some(),
+        ServerRspMsg::HistoricalNews {
+            req_id,
+            time,
+            provider_code,
+            article_id,
+            headline,
+            ..
+        } => handlers
+            .news()
+            .map(|handler| {
+                handler.historical_news(*req_id, time, provider_code, article_id, headline)
+            })
+            .is_some(),
+        ServerRspMsg::HistoricalNewsEnd { req_id, has_more } => handlers
+            .news()
+            .map(|handler| handler.historical_news_end(*req_id, *has_more))
+            .is_some(),
+
+        ServerRspMsg::HistoricalData { req_id, bar } => handlers
+            .historical()
+            .map(|handler| handler.historical_data(*req_id, bar))
+            .is_some(),
+        ServerRspMsg::HistoricalDataEnd { req_id, start, end } => handlers
+            .historical()
+            .map(|handler| handler.historical_data_end(*req_id, start, end))
+            .is_some(),
+        ServerRspMsg::HistoricalDataUpdate { req_id, bar } => handlers
+            .historical()
+            .map(|handler| handler.historical_data_update(*req_id, bar))
+            .is_some(),
+        ServerRspMsg::HeadTimestamp {
+            req_id,
+            head_timestamp,
+            ..
+        } => handlers
+            .historical()
+            .map(|handler| handler.head_timestamp(*req_id, head_timestamp))
+            .is_some(),
+        ServerRspMsg::HistogramData { req_id, items } => handlers
+            .historical()
+            .map(|handler| handler.histogram_data(*req_id, items))
+            .is_some(),
+        ServerRspMsg::HistoricalTicks {
+            req_id,
+            ticks,
+            done,
+        } => handlers
+            .historical()
+            .map(|handler| handler.historical_ticks(*req_id, ticks, *done))
+            .is_some(),
+        ServerRspMsg::HistoricalTicksBidAsk {
+            req_id,
+            ticks,
+            done,
+        } => handlers
+            .historical()
+            .map(|handler| handler.historical_ticks_bid_ask(*req_id, ticks, *done))
+            .is_some(),
+        ServerRspMsg::HistoricalTicksLast {
+            req_id,
+            ticks,
+            done,
+        } => handlers
+            .historical()
+            .map(|handler| handler.historical_ticks_last(*req_id, ticks, *done))
+            .is_some(),
+    }
+}
@@ -0,0 +1,357 @@
+//! The connect-time handshake with TWS or IB Gateway
+//!
+//! The client opens with "API\0" followed by the range of versions it speaks.  The server answers
+//! with the version it chose and its connection time, and the client then sends StartApi.  The
+//! chosen ServerVersion decides which fields every later request may carry; supports() answers
+//! that for each Feature.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::{make_field, make_message, ServerReqMsgDiscriminants};
+use crate::core::server_versions::*;
+
+/// Sent before the version range to announce a v100+ client
+const API_PREFIX: &str = "API\0";
+
+//==================================================================================================
+/// A request field or message the server only understands from some version on.  Each feature
+/// is named after its MIN_SERVER_VER_ constant.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    PtaOrders,
+    FundamentalData,
+    DeltaNeutral,
+    ContractDataChain,
+    ScaleOrders2,
+    AlgoOrders,
+    ExecutionDataChain,
+    NotHeld,
+    SecIdType,
+    PlaceOrderConid,
+    ReqMktDataConid,
+    ReqCalcImpliedVolat,
+    ReqCalcOptionPrice,
+    SshortxOld,
+    Sshortx,
+    ReqGlobalCancel,
+    HedgeOrders,
+    ReqMarketDataType,
+    OptOutSmartRouting,
+    SmartComboRoutingParams,
+    DeltaNeutralConid,
+    ScaleOrders3,
+    OrderComboLegsPrice,
+    TrailingPercent,
+    DeltaNeutralOpenClose,
+    Positions,
+    AccountSummary,
+    TradingClass,
+    ScaleTable,
+    Linking,
+    AlgoId,
+    OptionalCapabilities,
+    OrderSolicited,
+    LinkingAuth,
+    Primaryexch,
+    RandomizeSizeAndPrice,
+    FractionalPositions,
+    PeggedToBenchmark,
+    ModelsSupport,
+    SecDefOptParamsReq,
+    ExtOperator,
+    SoftDollarTier,
+    ReqFamilyCodes,
+    ReqMatchingSymbols,
+    PastLimit,
+    MdSizeMultiplier,
+    CashQty,
+    ReqMktDepthExchanges,
+    TickNews,
+    ReqSmartComponents,
+    ReqNewsProviders,
+    ReqNewsArticle,
+    ReqHistoricalNews,
+    ReqHeadTimestamp,
+    ReqHistogram,
+    ServiceDataType,
+    AggGroup,
+    UnderlyingInfo,
+    CancelHeadtimestamp,
+    SyntRealtimeBars,
+    CfdReroute,
+    MarketRules,
+    Pnl,
+    NewsQueryOrigins,
+    UnrealizedPnl,
+    HistoricalTicks,
+    MarketCapPrice,
+    PreOpenBidAsk,
+    RealExpirationDate,
+    RealizedPnl,
+    LastLiquidity,
+    TickByTick,
+    DecisionMaker,
+    MifidExecution,
+    TickByTickIgnoreSize,
+    AutoPriceForHedge,
+    WhatIfExtFields,
+    ScannerGenericOpts,
+    ApiBindOrder,
+    OrderContainer,
+    SmartDepth,
+    RemoveNullAllCasting,
+    DPegOrders,
+    MktDepthPrimExchange,
+    CompletedOrders,
+    PriceMgmtAlgo,
+}
+
+impl Feature {
+    /// The first server version with the feature
+    pub fn min_version(&self) -> i32 {
+        match self {
+            Feature::PtaOrders => MIN_SERVER_VER_PTA_ORDERS,
+            Feature::FundamentalData => MIN_SERVER_VER_FUNDAMENTAL_DATA,
+            Feature::DeltaNeutral => MIN_SERVER_VER_DELTA_NEUTRAL,
+            Feature::ContractDataChain => MIN_SERVER_VER_CONTRACT_DATA_CHAIN,
+            Feature::ScaleOrders2 => MIN_SERVER_VER_SCALE_ORDERS2,
+            Feature::AlgoOrders => MIN_SERVER_VER_ALGO_ORDERS,
+            Feature::ExecutionDataChain => MIN_SERVER_VER_EXECUTION_DATA_CHAIN,
+            Feature::NotHeld => MIN_SERVER_VER_NOT_HELD,
+            Feature::SecIdType => MIN_SERVER_VER_SEC_ID_TYPE,
+            Feature::PlaceOrderConid => MIN_SERVER_VER_PLACE_ORDER_CONID,
+            Feature::ReqMktDataConid => MIN_SERVER_VER_REQ_MKT_DATA_CONID,
+            Feature::ReqCalcImpliedVolat => MIN_SERVER_VER_REQ_CALC_IMPLIED_VOLAT,
+            Feature::ReqCalcOptionPrice => MIN_SERVER_VER_REQ_CALC_OPTION_PRICE,
+            Feature::SshortxOld => MIN_SERVER_VER_SSHORTX_OLD,
+            Feature::Sshortx => MIN_SERVER_VER_SSHORTX,
+            Feature::ReqGlobalCancel => MIN_SERVER_VER_REQ_GLOBAL_CANCEL,
+            Feature::HedgeOrders => MIN_SERVER_VER_HEDGE_ORDERS,
+            Feature::ReqMarketDataType => MIN_SERVER_VER_REQ_MARKET_DATA_TYPE,
+            Feature::OptOutSmartRouting => MIN_SERVER_VER_OPT_OUT_SMART_ROUTING,
+            Feature::SmartComboRoutingParams => MIN_SERVER_VER_SMART_COMBO_ROUTING_PARAMS,
+            Feature::DeltaNeutralConid => MIN_SERVER_VER_DELTA_NEUTRAL_CONID,
+            Feature::ScaleOrders3 => MIN_SERVER_VER_SCALE_ORDERS3,
+            Feature::OrderComboLegsPrice => MIN_SERVER_VER_ORDER_COMBO_LEGS_PRICE,
+            Feature::TrailingPercent => MIN_SERVER_VER_TRAILING_PERCENT,
+            Feature::DeltaNeutralOpenClose => MIN_SERVER_VER_DELTA_NEUTRAL_OPEN_CLOSE,
+            Feature::Positions => MIN_SERVER_VER_POSITIONS,
+            Feature::AccountSummary => MIN_SERVER_VER_ACCOUNT_SUMMARY,
+            Feature::TradingClass => MIN_SERVER_VER_TRADING_CLASS,
+            Feature::ScaleTable => MIN_SERVER_VER_SCALE_TABLE,
+            Feature::Linking => MIN_SERVER_VER_LINKING,
+            Feature::AlgoId => MIN_SERVER_VER_ALGO_ID,
+            Feature::OptionalCapabilities => MIN_SERVER_VER_OPTIONAL_CAPABILITIES,
+            Feature::OrderSolicited => MIN_SERVER_VER_ORDER_SOLICITED,
+            Feature::LinkingAuth => MIN_SERVER_VER_LINKING_AUTH,
+            Feature::Primaryexch => MIN_SERVER_VER_PRIMARYEXCH,
+            Feature::RandomizeSizeAndPrice => MIN_SERVER_VER_RANDOMIZE_SIZE_AND_PRICE,
+            Feature::FractionalPositions => MIN_SERVER_VER_FRACTIONAL_POSITIONS,
+            Feature::PeggedToBenchmark => MIN_SERVER_VER_PEGGED_TO_BENCHMARK,
+            Feature::ModelsSupport => MIN_SERVER_VER_MODELS_SUPPORT,
+            Feature::SecDefOptParamsReq => MIN_SERVER_VER_SEC_DEF_OPT_PARAMS_REQ,
+            Feature::ExtOperator => MIN_SERVER_VER_EXT_OPERATOR,
+            Feature::SoftDollarTier => MIN_SERVER_VER_SOFT_DOLLAR_TIER,
+            Feature::ReqFamilyCodes => MIN_SERVER_VER_REQ_FAMILY_CODES,
+            Feature::ReqMatchingSymbols => MIN_SERVER_VER_REQ_MATCHING_SYMBOLS,
+            Feature::PastLimit => MIN_SERVER_VER_PAST_LIMIT,
+            Feature::MdSizeMultiplier => MIN_SERVER_VER_MD_SIZE_MULTIPLIER,
+            Feature::CashQty => MIN_SERVER_VER_CASH_QTY,
+            Feature::ReqMktDepthExchanges => MIN_SERVER_VER_REQ_MKT_DEPTH_EXCHANGES,
+            Feature::TickNews => MIN_SERVER_VER_TICK_NEWS,
+            Feature::ReqSmartComponents => MIN_SERVER_VER_REQ_SMART_COMPONENTS,
+            Feature::ReqNewsProviders => MIN_SERVER_VER_REQ_NEWS_PROVIDERS,
+            Feature::ReqNewsArticle => MIN_SERVER_VER_REQ_NEWS_ARTICLE,
+            Feature::ReqHistoricalNews => MIN_SERVER_VER_REQ_HISTORICAL_NEWS,
+            Feature::ReqHeadTimestamp => MIN_SERVER_VER_REQ_HEAD_TIMESTAMP,
+            Feature::ReqHistogram => MIN_SERVER_VER_REQ_HISTOGRAM,
+            Feature::ServiceDataType => MIN_SERVER_VER_SERVICE_DATA_TYPE,
+            Feature::AggGroup => MIN_SERVER_VER_AGG_GROUP,
+            Feature::UnderlyingInfo => MIN_SERVER_VER_UNDERLYING_INFO,
+            Feature::CancelHeadtimestamp => MIN_SERVER_VER_CANCEL_HEADTIMESTAMP,
+            Feature::SyntRealtimeBars => MIN_SERVER_VER_SYNT_REALTIME_BARS,
+            Feature::CfdReroute => MIN_SERVER_VER_CFD_REROUTE,
+            Feature::MarketRules => MIN_SERVER_VER_MARKET_RULES,
+            Feature::Pnl => MIN_SERVER_VER_PNL,
+            Feature::NewsQueryOrigins => MIN_SERVER_VER_NEWS_QUERY_ORIGINS,
+            Feature::UnrealizedPnl => MIN_SERVER_VER_UNREALIZED_PNL,
+            Feature::HistoricalTicks => MIN_SERVER_VER_HISTORICAL_TICKS,
+            Feature::MarketCapPrice => MIN_SERVER_VER_MARKET_CAP_PRICE,
+            Feature::PreOpenBidAsk => MIN_SERVER_VER_PRE_OPEN_BID_ASK,
+            Feature::RealExpirationDate => MIN_SERVER_VER_REAL_EXPIRATION_DATE,
+            Feature::RealizedPnl => MIN_SERVER_VER_REALIZED_PNL,
+            Feature::LastLiquidity => MIN_SERVER_VER_LAST_LIQUIDITY,
+            Feature::TickByTick => MIN_SERVER_VER_TICK_BY_TICK,
+            Feature::DecisionMaker => MIN_SERVER_VER_DECISION_MAKER,
+            Feature::MifidExecution => MIN_SERVER_VER_MIFID_EXECUTION,
+            Feature::TickByTickIgnoreSize => MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE,
+            Feature::AutoPriceForHedge => MIN_SERVER_VER_AUTO_PRICE_FOR_HEDGE,
+            Feature::WhatIfExtFields => MIN_SERVER_VER_WHAT_IF_EXT_FIELDS,
+            Feature::ScannerGenericOpts => MIN_SERVER_VER_SCANNER_GENERIC_OPTS,
+            Feature::ApiBindOrder => MIN_SERVER_VER_API_BIND_ORDER,
+            Feature::OrderContainer => MIN_SERVER_VER_ORDER_CONTAINER,
+            Feature::SmartDepth => MIN_SERVER_VER_SMART_DEPTH,
+            Feature::RemoveNullAllCasting => MIN_SERVER_VER_REMOVE_NULL_ALL_CASTING,
+            Feature::DPegOrders => MIN_SERVER_VER_D_PEG_ORDERS,
+            Feature::MktDepthPrimExchange => MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE,
+            Feature::CompletedOrders => MIN_SERVER_VER_COMPLETED_ORDERS,
+            Feature::PriceMgmtAlgo => MIN_SERVER_VER_PRICE_MGMT_ALGO,
+        }
+    }
+}
+
+//==================================================================================================
+/// The minimum server version of each request message, as the feature that introduced it.  None
+/// for messages every v100+ server accepts.  Fields added to older messages are checked where the
+/// message is encoded.
+pub fn min_server_version(msg: &ServerReqMsgDiscriminants) -> Option<Feature> {
+    use ServerReqMsgDiscriminants::*;
+    match msg {
+        ReqFundamentalData | CancelFundamentalData => Some(Feature::FundamentalData),
+        ReqCalcImpliedVolat | CancelCalcImpliedVolat => Some(Feature::ReqCalcImpliedVolat),
+        ReqCalcOptionPrice | CancelCalcOptionPrice => Some(Feature::ReqCalcOptionPrice),
+        ReqGlobalCancel => Some(Feature::ReqGlobalCancel),
+        ReqMarketDataType => Some(Feature::ReqMarketDataType),
+        ReqPositions | CancelPositions => Some(Feature::Positions),
+        ReqAccountSummary | CancelAccountSummary => Some(Feature::AccountSummary),
+        VerifyRequest
+        | VerifyMessage
+        | QueryDisplayGroups
+        | SubscribeToGroupEvents
+        | UpdateDisplayGroup
+        | UnsubscribeFromGroupEvents => Some(Feature::Linking),
+        VerifyAndAuthRequest | VerifyAndAuthMessage => Some(Feature::LinkingAuth),
+        ReqPositionsMulti
+        | CancelPositionsMulti
+        | ReqAccountUpdatesMulti
+        | CancelAccountUpdatesMulti => Some(Feature::ModelsSupport),
+        ReqSecDefOptParams => Some(Feature::SecDefOptParamsReq),
+        ReqSoftDollarTiers => Some(Feature::SoftDollarTier),
+        ReqFamilyCodes => Some(Feature::ReqFamilyCodes),
+        ReqMatchingSymbols => Some(Feature::ReqMatchingSymbols),
+        ReqMktDepthExchanges => Some(Feature::ReqMktDepthExchanges),
+        ReqSmartComponents => Some(Feature::ReqSmartComponents),
+        ReqNewsArticle => Some(Feature::ReqNewsArticle),
+        ReqNewsProviders => Some(Feature::ReqNewsProviders),
+        ReqHistoricalNews => Some(Feature::ReqHistoricalNews),
+        ReqHeadTimestamp => Some(Feature::ReqHeadTimestamp),
+        CancelHeadTimestamp => Some(Feature::CancelHeadtimestamp),
+        ReqHistogramData | CancelHistogramData => Some(Feature::ReqHistogram),
+        ReqMarketRule => Some(Feature::MarketRules),
+        ReqPnl | CancelPnl | ReqPnlSingle | CancelPnlSingle => Some(Feature::Pnl),
+        ReqHistoricalTicks => Some(Feature::HistoricalTicks),
+        ReqTickByTickData | CancelTickByTickData => Some(Feature::TickByTick),
+        ReqCompletedOrders => Some(Feature::CompletedOrders),
+        _ => None,
+    }
+}
+
+//==================================================================================================
+/// The version the server chose during the handshake
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct ServerVersion(pub i32);
+
+impl ServerVersion {
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.0 >= feature.min_version()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns IBKRApiLibError::UpdateTws if the server does not accept a request message
+    pub fn check(&self, msg: &ServerReqMsgDiscriminants) -> Result<(), IBKRApiLibError> {
+        match min_server_version(msg) {
+            Some(feature) if !self.supports(feature) => Err(IBKRApiLibError::UpdateTws {
+                required: feature.min_version(),
+                current: self.0,
+                feature,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+impl From<i32> for ServerVersion {
+    fn from(version: i32) -> Self {
+        ServerVersion(version)
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+//==================================================================================================
+/// The range of versions the client speaks, such as "v100..151"
+pub fn version_range() -> String {
+    format!("v{}..{}", MIN_CLIENT_VER, MAX_CLIENT_VER)
+}
+
+//==================================================================================================
+/// The bytes that open a connection: the API prefix followed by the version range
+pub fn hello() -> Result<Vec<u8>, IBKRApiLibError> {
+    let mut bytes = API_PREFIX.as_bytes().to_vec();
+    bytes.extend_from_slice(make_message(&version_range())?.as_slice());
+    Ok(bytes)
+}
+
+//==================================================================================================
+/// The server's answer to hello()
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerHello {
+    pub server_version: ServerVersion,
+    /// The server's time when the connection was accepted, such as "20231020 09:30:00 EST"
+    pub connection_time: String,
+}
+
+impl ServerHello {
+    /// Reads the fields of the answer.  None if they are not the two fields of an answer, as
+    /// when the server sends another message first.
+    pub fn parse(fields: &[String]) -> Option<Result<Self, IBKRApiLibError>> {
+        match fields {
+            [version, connection_time] => Some(
+                version
+                    .parse()
+                    .map(|version| ServerHello {
+                        server_version: ServerVersion(version),
+                        connection_time: connection_time.clone(),
+                    })
+                    .map_err(IBKRApiLibError::from),
+            ),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// The StartApi request that completes the handshake
+///
+/// # Arguments
+/// * client_id - The id of the client on the server
+/// * server_version - The version from the server's answer
+/// * optional_capabilities - Sent to servers supporting Feature::OptionalCapabilities
+pub fn start_api(
+    client_id: i32,
+    server_version: ServerVersion,
+    optional_capabilities: &str,
+) -> Result<String, IBKRApiLibError> {
+    let version = 2;
+    let mut msg = "".to_string();
+    msg.push_str(&make_field(&(ServerReqMsgDiscriminants::StartApi as i32))?);
+    msg.push_str(&make_field(&version)?);
+    msg.push_str(&make_field(&client_id)?);
+    if server_version.supports(Feature::OptionalCapabilities) {
+        msg.push_str(&make_field(&optional_capabilities.to_string())?);
+    }
+    Ok(msg)
+}
@@ -0,0 +1,735 @@
+//! Fetches historical ticks over a time range, and summarizes price histograms
+//!
+//! ReqHistoricalTicks returns at most about 1000 ticks per request, so a longer range is fetched
+//! page by page: each request starts at the time of the last tick received, and the ticks of
+//! that second which were already received are dropped from the next page.  Before the first
+//! page the range is moved past the head timestamp, the time of the earliest data TWS has, since
+//! asking for older data fails with error 162.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::client::{Collected, EClient};
+use crate::core::common::{
+    HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, SECONDS_PER_DAY,
+};
+use crate::core::contract::Contract;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError, TwsError};
+use crate::core::messages::ServerRspMsg;
+#[cfg(feature = "metrics")]
+use crate::core::metrics;
+use crate::core::req_ids::RequestKind;
+
+/// Most ticks TWS returns for one request
+pub const MAX_TICKS_PER_REQUEST: i32 = 1000;
+/// Format of a UTC time in historical data requests
+const UTC_FORMAT: &str = "%Y%m%d-%H:%M:%S";
+/// format_date of ReqHeadTimestamp asking for seconds since the epoch
+const EPOCH_SECONDS: i32 = 2;
+
+//==================================================================================================
+/// The kind of ticks to fetch
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickKind {
+    Trades,
+    BidAsk,
+    Midpoint,
+}
+
+impl TickKind {
+    /// The what_to_show value of the request
+    pub fn what_to_show(self) -> &'static str {
+        match self {
+            TickKind::Trades => "TRADES",
+            TickKind::BidAsk => "BID_ASK",
+            TickKind::Midpoint => "MIDPOINT",
+        }
+    }
+}
+
+//==================================================================================================
+/// Ticks of one kind, oldest first
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum TickSeries {
+    Trades(Vec<HistoricalTickLast>),
+    BidAsk(Vec<HistoricalTickBidAsk>),
+    Midpoint(Vec<HistoricalTick>),
+}
+
+impl TickSeries {
+    /// An empty series of the given kind
+    pub fn new(kind: TickKind) -> Self {
+        match kind {
+            TickKind::Trades => TickSeries::Trades(Vec::new()),
+            TickKind::BidAsk => TickSeries::BidAsk(Vec::new()),
+            TickKind::Midpoint => TickSeries::Midpoint(Vec::new()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn kind(&self) -> TickKind {
+        match self {
+            TickSeries::Trades(_) => TickKind::Trades,
+            TickSeries::BidAsk(_) => TickKind::BidAsk,
+            TickSeries::Midpoint(_) => TickKind::Midpoint,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn len(&self) -> usize {
+        match self {
+            TickSeries::Trades(ticks) => ticks.len(),
+            TickSeries::BidAsk(ticks) => ticks.len(),
+            TickSeries::Midpoint(ticks) => ticks.len(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Times of the ticks in seconds since the epoch
+    pub fn times(&self) -> Vec<i64> {
+        match self {
+            TickSeries::Trades(ticks) => times(ticks),
+            TickSeries::BidAsk(ticks) => times(ticks),
+            TickSeries::Midpoint(ticks) => times(ticks),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends the ticks of another series.  Ticks of another kind are dropped.
+    pub fn append(&mut self, other: TickSeries) {
+        match (self, other) {
+            (TickSeries::Trades(ticks), TickSeries::Trades(mut more)) => ticks.append(&mut more),
+            (TickSeries::BidAsk(ticks), TickSeries::BidAsk(mut more)) => ticks.append(&mut more),
+            (TickSeries::Midpoint(ticks), TickSeries::Midpoint(mut more)) => {
+                ticks.append(&mut more)
+            }
+            (series, other) => warn!(
+                "dropped {} {:?} ticks appended to {:?} ticks",
+                other.len(),
+                other.kind(),
+                series.kind()
+            ),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Keeps the ticks a page adds to the ticks already received, and moves the boundary to
+    /// its last tick
+    fn retain_new(&mut self, boundary: &mut Boundary, end: i64) {
+        match self {
+            TickSeries::Trades(ticks) => retain_new(ticks, boundary, end),
+            TickSeries::BidAsk(ticks) => retain_new(ticks, boundary, end),
+            TickSeries::Midpoint(ticks) => retain_new(ticks, boundary, end),
+        }
+    }
+}
+
+//==================================================================================================
+trait TickTime {
+    fn tick_time(&self) -> i64;
+}
+
+impl TickTime for HistoricalTick {
+    fn tick_time(&self) -> i64 {
+        self.time as i64
+    }
+}
+
+impl TickTime for HistoricalTickBidAsk {
+    fn tick_time(&self) -> i64 {
+        self.time as i64
+    }
+}
+
+impl TickTime for HistoricalTickLast {
+    fn tick_time(&self) -> i64 {
+        self.time as i64
+    }
+}
+
+//==================================================================================================
+fn times<T: TickTime>(ticks: &[T]) -> Vec<i64> {
+    ticks.iter().map(|tick| tick.tick_time()).collect()
+}
+
+//==================================================================================================
+/// The second the next request starts at, and how many ticks of that second were received
+#[derive(Clone, Copy, Debug)]
+struct Boundary {
+    time: i64,
+    received: usize,
+}
+
+//==================================================================================================
+fn retain_new<T: TickTime>(ticks: &mut Vec<T>, boundary: &mut Boundary, end: i64) {
+    let mut repeated = 0;
+    ticks.retain(|tick| {
+        let time = tick.tick_time();
+        if time < boundary.time || time >= end {
+            return false;
+        }
+        if time == boundary.time && repeated < boundary.received {
+            repeated += 1;
+            return false;
+        }
+        true
+    });
+
+    if let Some(last) = ticks.last().map(|tick| tick.tick_time()) {
+        let at_last = ticks
+            .iter()
+            .rev()
+            .take_while(|tick| tick.tick_time() == last)
+            .count();
+        *boundary = Boundary {
+            time: last,
+            received: if last == boundary.time {
+                boundary.received + at_last
+            } else {
+                at_last
+            },
+        };
+    }
+}
+
+//==================================================================================================
+/// Limits on how often historical data may be requested.  TWS rejects requests beyond them with
+/// a pacing violation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Pacing {
+    /// Least time between two requests
+    pub min_interval: Duration,
+    /// Most requests within `window`
+    pub max_requests: usize,
+    pub window: Duration,
+}
+
+impl Pacing {
+    /// No limits, for tests or when the caller paces requests itself
+    pub fn none() -> Self {
+        Pacing {
+            min_interval: Duration::from_secs(0),
+            max_requests: usize::MAX,
+            window: Duration::from_secs(0),
+        }
+    }
+}
+
+impl Default for Pacing {
+    /// The limits IB documents: at most 6 requests within 2 seconds and 60 within 10 minutes
+    fn default() -> Self {
+        Pacing {
+            min_interval: Duration::from_millis(334),
+            max_requests: 60,
+            window: Duration::from_secs(600),
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Debug)]
+struct Pacer {
+    pacing: Pacing,
+    /// Requests sent within the window
+    sent: VecDeque<Instant>,
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    fn new(pacing: Pacing) -> Self {
+        Pacer {
+            pacing,
+            sent: VecDeque::new(),
+            last: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sleeps until another request is allowed, and records it
+    fn wait(&mut self) {
+        let now = Instant::now();
+        while let Some(first) = self.sent.front() {
+            if now.duration_since(*first) < self.pacing.window {
+                break;
+            }
+            self.sent.pop_front();
+        }
+
+        let mut ready = now;
+        if let Some(last) = self.last {
+            ready = ready.max(last + self.pacing.min_interval);
+        }
+        if self.sent.len() >= self.pacing.max_requests {
+            if let Some(first) = self.sent.front() {
+                ready = ready.max(*first + self.pacing.window);
+            }
+        }
+        if ready > now {
+            debug!("pacing historical tick requests for {:?}", ready - now);
+            #[cfg(feature = "metrics")]
+            metrics::pacing_wait(ready - now);
+            thread::sleep(ready - now);
+        }
+        let sent = Instant::now();
+        self.sent.push_back(sent);
+        self.last = Some(sent);
+    }
+}
+
+//==================================================================================================
+/// The ticks to fetch
+#[derive(Clone, Debug)]
+pub struct TickRange {
+    pub contract: Contract,
+    pub kind: TickKind,
+    /// First second of the range
+    pub start: DateTime<Utc>,
+    /// End of the range, excluded
+    pub end: DateTime<Utc>,
+    /// Only ticks of regular trading hours
+    pub use_rth: bool,
+    /// Leave out bid/ask ticks that only change the size
+    pub ignore_size: bool,
+    /// How long to wait for each page
+    pub page_timeout: Duration,
+    pub pacing: Pacing,
+    /// Start no earlier than the head timestamp of the contract
+    pub check_head_timestamp: bool,
+}
+
+impl TickRange {
+    pub fn new(
+        contract: &Contract,
+        kind: TickKind,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        TickRange {
+            contract: contract.clone(),
+            kind,
+            start,
+            end,
+            use_rth: false,
+            ignore_size: false,
+            page_timeout: Duration::from_secs(60),
+            pacing: Pacing::default(),
+            check_head_timestamp: true,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn use_rth(mut self, use_rth: bool) -> Self {
+        self.use_rth = use_rth;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn ignore_size(mut self, ignore_size: bool) -> Self {
+        self.ignore_size = ignore_size;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn page_timeout(mut self, page_timeout: Duration) -> Self {
+        self.page_timeout = page_timeout;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn check_head_timestamp(mut self, check_head_timestamp: bool) -> Self {
+        self.check_head_timestamp = check_head_timestamp;
+        self
+    }
+}
+
+//==================================================================================================
+/// Iterates over the pages of a tick range.  Each page holds only ticks not returned before.
+/// Waiting for a page blocks, and an error ends the iteration.
+pub struct TickPages<'a> {
+    client: &'a mut EClient,
+    range: TickRange,
+    pacer: Pacer,
+    boundary: Boundary,
+    /// Whether the start has been checked against the head timestamp
+    head_checked: bool,
+    finished: bool,
+}
+
+impl<'a> TickPages<'a> {
+    pub fn new(client: &'a mut EClient, range: TickRange) -> Self {
+        TickPages {
+            client,
+            pacer: Pacer::new(range.pacing),
+            boundary: Boundary {
+                time: range.start.timestamp(),
+                received: 0,
+            },
+            head_checked: !range.check_head_timestamp,
+            finished: range.start >= range.end,
+            range,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Moves the boundary to the head timestamp if the range starts before it
+    fn skip_to_head(&mut self) -> Result<(), IBKRApiLibError> {
+        self.pacer.wait();
+        let head = head_timestamp(
+            self.client,
+            &self.range.contract,
+            self.range.kind.what_to_show(),
+            self.range.use_rth,
+            self.range.page_timeout,
+        )?;
+        if head.timestamp() > self.boundary.time {
+            debug!("no ticks before the head timestamp {}", head);
+            self.boundary = Boundary {
+                time: head.timestamp(),
+                received: 0,
+            };
+        }
+        if head >= self.range.end {
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the ticks from the boundary on and waits for them
+    fn request_page(&mut self) -> Result<TickSeries, IBKRApiLibError> {
+        let contract = &self.range.contract;
+        let req_id = self
+            .client
+            .next_req_id(RequestKind::HistoricalTicks, &contract.symbol);
+        let start = Utc
+            .timestamp_opt(self.boundary.time, 0)
+            .single()
+            .ok_or_else(|| {
+                IBKRApiLibError::Parse(format!("invalid tick time {}", self.boundary.time))
+            })?;
+
+        self.pacer.wait();
+        self.client.req_historical_ticks(
+            req_id,
+            contract,
+            &start.format(UTC_FORMAT).to_string(),
+            "",
+            MAX_TICKS_PER_REQUEST,
+            self.range.kind.what_to_show(),
+            self.range.use_rth as i32,
+            self.range.ignore_size,
+            vec![],
+        )?;
+
+        let mut page = TickSeries::new(self.range.kind);
+        let result = self.client.collect(self.range.page_timeout, |msg| {
+            let (ticks, done) = match msg {
+                ServerRspMsg::HistoricalTicks {
+                    req_id: id,
+                    ticks,
+                    done,
+                } if *id == req_id => (TickSeries::Midpoint(ticks.clone()), *done),
+                ServerRspMsg::HistoricalTicksBidAsk {
+                    req_id: id,
+                    ticks,
+                    done,
+                } if *id == req_id => (TickSeries::BidAsk(ticks.clone()), *done),
+                ServerRspMsg::HistoricalTicksLast {
+                    req_id: id,
+                    ticks,
+                    done,
+                } if *id == req_id => (TickSeries::Trades(ticks.clone()), *done),
+                ServerRspMsg::ErrMsg {
+                    req_id: id,
+                    error_code,
+                    error_str,
+                } if *id == req_id && !is_informational(*error_code) => {
+                    return Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                        req_id,
+                        error_code.to_string(),
+                        error_str.clone(),
+                    )))
+                }
+                _ => return Ok(Collected::Ignored),
+            };
+            page.append(ticks);
+            Ok(if done {
+                Collected::Finished
+            } else {
+                Collected::Consumed
+            })
+        });
+        self.client.correlations().complete(req_id);
+        result.map(|_| page)
+    }
+}
+
+impl<'a> Iterator for TickPages<'a> {
+    type Item = Result<TickSeries, IBKRApiLibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.head_checked && !self.finished {
+            self.head_checked = true;
+            if let Err(e) = self.skip_to_head() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+        let end = self.range.end.timestamp();
+        while !self.finished {
+            let mut page = match self.request_page() {
+                Ok(page) => page,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+            let times = page.times();
+            let full = times.len() >= MAX_TICKS_PER_REQUEST as usize;
+            let previous = self.boundary;
+            page.retain_new(&mut self.boundary, end);
+
+            match times.last() {
+                None => self.finished = true,
+                Some(last) if *last >= end => self.finished = true,
+                Some(_) if page.is_empty() && full => {
+                    // a whole page within one second: skip the rest of that second
+                    warn!(
+                        "more than {} ticks at {}, skipping the rest of that second",
+                        MAX_TICKS_PER_REQUEST, previous.time
+                    );
+                    self.boundary = Boundary {
+                        time: previous.time + 1,
+                        received: 0,
+                    };
+                }
+                Some(_) if page.is_empty() => self.finished = true,
+                Some(_) => (),
+            }
+            if !page.is_empty() {
+                return Some(Ok(page));
+            }
+        }
+        None
+    }
+}
+
+//==================================================================================================
+/// Fetches every tick of a range, requesting as many pages as needed and pacing the requests.
+/// Blocks until the last page has arrived.
+///
+/// # Arguments
+/// * client - A connected client
+/// * range - The contract, the kind of ticks and the time range
+pub fn fetch_ticks_range(
+    client: &mut EClient,
+    range: &TickRange,
+) -> Result<TickSeries, IBKRApiLibError> {
+    let mut series = TickSeries::new(range.kind);
+    for page in TickPages::new(client, range.clone()) {
+        series.append(page?);
+    }
+    Ok(series)
+}
+
+//==================================================================================================
+/// The time of the earliest data TWS has for a contract.  Requests for older data fail.
+///
+/// # Arguments
+/// * client - A connected client
+/// * contract - The contract
+/// * what_to_show - The kind of data, such as "TRADES", "MIDPOINT" or "BID_ASK"
+/// * timeout - How long to wait for the answer
+pub fn earliest_available(
+    client: &mut EClient,
+    contract: &Contract,
+    what_to_show: &str,
+    timeout: Duration,
+) -> Result<DateTime<Utc>, IBKRApiLibError> {
+    head_timestamp(client, contract, what_to_show, false, timeout)
+}
+
+//==================================================================================================
+fn head_timestamp(
+    client: &mut EClient,
+    contract: &Contract,
+    what_to_show: &str,
+    use_rth: bool,
+    timeout: Duration,
+) -> Result<DateTime<Utc>, IBKRApiLibError> {
+    let req_id = client.next_req_id(RequestKind::HeadTimestamp, &contract.symbol);
+    client.req_head_time_stamp(
+        req_id,
+        contract,
+        what_to_show,
+        use_rth as i32,
+        EPOCH_SECONDS,
+    )?;
+
+    let mut head = None;
+    let result = client.collect(timeout, |msg| match msg {
+        ServerRspMsg::HeadTimestamp {
+            req_id: id,
+            head_timestamp,
+            ..
+        } if *id == req_id => {
+            head = Some(*head_timestamp);
+            Ok(Collected::Finished)
+        }
+        ServerRspMsg::ErrMsg {
+            req_id: id,
+            error_code,
+            error_str,
+        } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+            TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+        )),
+        _ => Ok(Collected::Ignored),
+    });
+    client.correlations().complete(req_id);
+    result?;
+    match head {
+        Some(Some(head)) => Ok(head),
+        Some(None) => Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+            req_id,
+            TwsError::BadMessage.code().to_string(),
+            format!("{} Invalid head timestamp", TwsError::BadMessage.message()),
+        ))),
+        None => Err(IBKRApiLibError::Config(
+            "no head timestamp received".to_string(),
+        )),
+    }
+}
+
+//==================================================================================================
+/// The time_period of ReqHistogramData for a period: whole weeks are sent as weeks, anything
+/// else is rounded up to whole days
+pub fn histogram_period(period: Duration) -> String {
+    let days = period.as_secs().div_ceil(SECONDS_PER_DAY).max(1);
+    if period.as_secs().is_multiple_of(7 * SECONDS_PER_DAY) && days >= 7 {
+        format!("{} weeks", days / 7)
+    } else {
+        format!("{} days", days)
+    }
+}
+
+//==================================================================================================
+/// Future returned by EClient::histogram().  It resolves to the histogram sorted by price, or to
+/// the error TWS reported, and cancels the request if it is dropped before.  Events of other
+/// requests stay available to get_event().
+pub struct Histogram<'a> {
+    client: &'a mut EClient,
+    req_id: i32,
+    done: bool,
+}
+
+impl<'a> Histogram<'a> {
+    pub(crate) fn new(client: &'a mut EClient, req_id: i32) -> Self {
+        Histogram {
+            client,
+            req_id,
+            done: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+}
+
+impl Future for Histogram<'_> {
+    type Output = Result<Vec<HistogramData>, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let req_id = self.req_id;
+        let mut histogram = Vec::new();
+        let result = self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::HistogramData { req_id: id, items } if *id == req_id => {
+                histogram = items.clone();
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ => Ok(Collected::Ignored),
+        });
+        let output = match result {
+            Ok(()) => {
+                histogram.sort_by(|a, b| a.price.total_cmp(&b.price));
+                Ok(histogram)
+            }
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.client.wake_on_event(cx.waker());
+                return Poll::Pending;
+            }
+            Err(err) => Err(err),
+        };
+        self.done = true;
+        self.client.correlations().complete(req_id);
+        Poll::Ready(output)
+    }
+}
+
+impl Drop for Histogram<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            // nothing to cancel once the connection is gone
+            let _ = self.client.cancel_histogram_data(self.req_id);
+        }
+    }
+}
+
+//==================================================================================================
+/// The share of the counts at or below each price of a histogram sorted by price, from the lowest
+/// price to 1.0 at the highest
+pub fn cumulative_distribution(histogram: &[HistogramData]) -> Vec<(f64, f64)> {
+    let total: i64 = histogram.iter().map(|item| item.count as i64).sum();
+    let mut below = 0;
+    histogram
+        .iter()
+        .map(|item| {
+            below += item.count as i64;
+            let share = if total > 0 {
+                below as f64 / total as f64
+            } else {
+                0.0
+            };
+            (item.price, share)
+        })
+        .collect()
+}
+
+//==================================================================================================
+/// The lowest price of a histogram sorted by price at or below which at least `quantile` of the
+/// counts fall, such as 0.5 for the median.  None for an empty histogram.
+pub fn price_at_quantile(histogram: &[HistogramData], quantile: f64) -> Option<f64> {
+    cumulative_distribution(histogram)
+        .into_iter()
+        .find(|(_, share)| *share >= quantile)
+        .map(|(price, _)| price)
+        .or_else(|| histogram.last().map(|item| item.price))
+}
@@ -0,0 +1,49 @@
+//! Timestamps of the stages a message passes inside the client, for measuring where latency is
+//! spent.
+//!
+//! Nothing is captured until an observer is installed with EClient::set_latency_observer()
+//! before connecting.  Each decoded event is then stamped when its frame was read from the
+//! socket and when decoding finished, and the observer is called with the stamps when the event
+//! is handed to the application by get_event() or one of the blocking helpers.  Anything after
+//! that is the application's own time.
+use std::time::{Duration, Instant};
+
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// When one event passed each stage of the client
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatencyStamps {
+    /// The socket read that completed the frame of the event
+    pub read: Instant,
+    /// Decoding of the frame finished and the event was queued
+    pub decoded: Instant,
+    /// The event was taken from the queue by the application
+    pub dispatched: Instant,
+}
+
+impl LatencyStamps {
+    /// Time from the socket read to the end of decoding
+    pub fn decode_time(&self) -> Duration {
+        self.decoded.saturating_duration_since(self.read)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Time the event waited in the queue for the application
+    pub fn queue_time(&self) -> Duration {
+        self.dispatched.saturating_duration_since(self.decoded)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Time from the socket read until the application received the event
+    pub fn total(&self) -> Duration {
+        self.dispatched.saturating_duration_since(self.read)
+    }
+}
+
+//==================================================================================================
+/// Receives the stamps of every event the application takes from the client.  It is called on
+/// the thread taking the event, before the event is returned, so it should be quick.
+pub trait LatencyObserver: Send + Sync {
+    fn observe(&self, msg: &ServerRspMsg, stamps: &LatencyStamps);
+}
@@ -0,0 +1,240 @@
+//! Tracks the working orders reported by TWS and follows their cancellation, for
+//! EClient::cancel_all() and global_cancel()
+//!
+//! LiveOrders is fed OpenOrder and OrderStatus events and keeps each order under its perm id,
+//! since orders placed in TWS all have order id 0.  PendingCancels follows the orders a cancel was
+//! sent for until TWS reports each of them cancelled, completed or refused.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::contract::Contract;
+use crate::core::errors::is_informational;
+use crate::core::fill_report::ORDER_NOTICES;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderStatusKind};
+
+//==================================================================================================
+/// Selects orders by account, symbol and side.  A filter with no criteria selects every order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OrderFilter {
+    pub account: Option<String>,
+    pub symbol: Option<String>,
+    /// BUY, or SELL, which also selects short sales
+    pub side: Option<String>,
+}
+
+impl OrderFilter {
+    pub fn all() -> Self {
+        OrderFilter::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn account(mut self, account: &str) -> Self {
+        self.account = Some(account.to_string());
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = Some(symbol.to_string());
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only orders on the same side as action: BUY, or SELL, SSHORT and SLONG
+    pub fn side(mut self, action: &str) -> Self {
+        self.side = Some(action.to_string());
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn matches(&self, contract: &Contract, order: &Order) -> bool {
+        self.account
+            .as_ref()
+            .is_none_or(|account| *account == order.account)
+            && self
+                .symbol
+                .as_ref()
+                .is_none_or(|symbol| symbol.eq_ignore_ascii_case(&contract.symbol))
+            && self
+                .side
+                .as_ref()
+                .is_none_or(|side| is_buy(side) == is_buy(&order.action))
+    }
+}
+
+fn is_buy(action: &str) -> bool {
+    action.eq_ignore_ascii_case("BUY")
+}
+
+//==================================================================================================
+/// An order reported by TWS, with its last status
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TrackedOrder {
+    pub order_id: i32,
+    pub perm_id: i64,
+    pub contract: Contract,
+    pub order: Order,
+    pub status: OrderStatusKind,
+}
+
+//==================================================================================================
+/// The orders TWS reported in OpenOrder events, kept up to date by their OrderStatus events
+#[derive(Clone, Debug, Default)]
+pub struct LiveOrders {
+    orders: BTreeMap<i64, TrackedOrder>,
+}
+
+impl LiveOrders {
+    pub fn new() -> Self {
+        LiveOrders::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event and returns whether it was about an order
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::OpenOrder {
+                order_id,
+                contract,
+                order,
+                order_state,
+            } => {
+                self.orders.insert(
+                    order.perm_id,
+                    TrackedOrder {
+                        order_id: *order_id,
+                        perm_id: order.perm_id,
+                        contract: contract.clone(),
+                        order: order.clone(),
+                        status: order_state.order_status(),
+                    },
+                );
+                true
+            }
+            ServerRspMsg::OrderStatus {
+                perm_id, status, ..
+            } => match self.orders.get_mut(perm_id) {
+                Some(tracked) => {
+                    tracked.status = status.clone();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The orders that are still working or on their way to the exchange
+    pub fn live(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders
+            .values()
+            .filter(|tracked| tracked.status.is_live())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn get(&self, perm_id: i64) -> Option<&TrackedOrder> {
+        self.orders.get(&perm_id)
+    }
+}
+
+//==================================================================================================
+/// What became of the orders a cancel was sent for
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CancelReport {
+    /// Orders TWS reported as cancelled
+    pub cancelled: Vec<TrackedOrder>,
+    /// Orders that filled or became inactive before the cancel reached them
+    pub completed: Vec<TrackedOrder>,
+    /// Orders TWS refused to cancel, with the error code and message
+    pub failed: Vec<(TrackedOrder, i32, String)>,
+    /// Orders still working when waiting for them timed out
+    pub working: Vec<TrackedOrder>,
+}
+
+impl CancelReport {
+    /// Whether every order has stopped working and no cancel was refused
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.working.is_empty()
+    }
+}
+
+//==================================================================================================
+/// Follows the orders a cancel was sent for until none is working
+#[derive(Clone, Debug, Default)]
+pub struct PendingCancels {
+    pending: BTreeMap<i64, TrackedOrder>,
+    report: CancelReport,
+}
+
+impl PendingCancels {
+    pub fn new<'a>(orders: impl IntoIterator<Item = &'a TrackedOrder>) -> Self {
+        PendingCancels {
+            pending: orders
+                .into_iter()
+                .map(|tracked| (tracked.perm_id, tracked.clone()))
+                .collect(),
+            report: CancelReport::default(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event and returns whether it was about one of the orders
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::OrderStatus {
+                perm_id, status, ..
+            } if self.pending.contains_key(perm_id) => {
+                if status.is_terminal() {
+                    let mut tracked = self.pending.remove(perm_id).expect("checked above");
+                    tracked.status = status.clone();
+                    match status {
+                        OrderStatusKind::Cancelled | OrderStatusKind::ApiCancelled => {
+                            self.report.cancelled.push(tracked)
+                        }
+                        _ => self.report.completed.push(tracked),
+                    }
+                } else if let Some(tracked) = self.pending.get_mut(perm_id) {
+                    tracked.status = status.clone();
+                }
+                true
+            }
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } if *req_id > 0 => {
+                let perm_id = match self
+                    .pending
+                    .values()
+                    .find(|tracked| tracked.order_id == *req_id)
+                {
+                    Some(tracked) => tracked.perm_id,
+                    None => return false,
+                };
+                if !is_informational(*error_code) && !ORDER_NOTICES.contains(error_code) {
+                    let tracked = self.pending.remove(&perm_id).expect("found above");
+                    self.report
+                        .failed
+                        .push((tracked, *error_code, error_str.clone()));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The report, with the orders not heard from yet as still working
+    pub fn finish(mut self) -> CancelReport {
+        self.report.working = self.pending.into_values().collect();
+        self.report
+    }
+}
@@ -0,0 +1,863 @@
+//! Typed views over market data ticks
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::client::{Collected, EClient};
+use crate::core::common::{GenericTickType, SmartComponent, TickByTickData, TickType};
+use crate::core::contract::Contract;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// Quote assembled from the ticks of a market data snapshot.  Delayed ticks fill the same
+/// fields as their real time counterparts.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TickerSnapshot {
+    pub req_id: i32,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub last: Option<f64>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub bid_size: Option<i32>,
+    pub ask_size: Option<i32>,
+    pub last_size: Option<i32>,
+    pub volume: Option<i32>,
+    pub last_timestamp: Option<String>,
+}
+
+impl TickerSnapshot {
+    pub fn new(req_id: i32) -> Self {
+        TickerSnapshot {
+            req_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a tick to the snapshot.  Returns false if the message is not a tick for this
+    /// request.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::TickPrice {
+                req_id,
+                tick_type,
+                price,
+                ..
+            } if *req_id == self.req_id => {
+                let field = match tick_type {
+                    TickType::Bid | TickType::DelayedBid => &mut self.bid,
+                    TickType::Ask | TickType::DelayedAsk => &mut self.ask,
+                    TickType::Last | TickType::DelayedLast => &mut self.last,
+                    TickType::Open | TickType::DelayedOpen => &mut self.open,
+                    TickType::High | TickType::DelayedHigh => &mut self.high,
+                    TickType::Low | TickType::DelayedLow => &mut self.low,
+                    TickType::Close | TickType::DelayedClose => &mut self.close,
+                    _ => return true,
+                };
+                // TWS sends -1 when there is no price
+                if *price >= 0.0 {
+                    *field = Some(*price);
+                }
+                true
+            }
+            ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size,
+            } if *req_id == self.req_id => {
+                let field = match tick_type {
+                    TickType::BidSize | TickType::DelayedBidSize => &mut self.bid_size,
+                    TickType::AskSize | TickType::DelayedAskSize => &mut self.ask_size,
+                    TickType::LastSize | TickType::DelayedLastSize => &mut self.last_size,
+                    TickType::Volume | TickType::DelayedVolume => &mut self.volume,
+                    _ => return true,
+                };
+                *field = Some(*size);
+                true
+            }
+            ServerRspMsg::TickString {
+                req_id,
+                tick_type,
+                value,
+            } if *req_id == self.req_id => {
+                if let TickType::LastTimestamp | TickType::DelayedLastTimestamp = tick_type {
+                    self.last_timestamp = Some(value.clone());
+                }
+                true
+            }
+            ServerRspMsg::TickGeneric { ticker_id, .. } => *ticker_id == self.req_id,
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Midpoint of bid and ask, if both are known
+    pub fn midpoint(&self) -> Option<f64> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TickerSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "req_id: {}, bid: {:?}, ask: {:?}, last: {:?}, close: {:?}, volume: {:?}",
+            self.req_id, self.bid, self.ask, self.last, self.close, self.volume
+        )
+    }
+}
+
+//==================================================================================================
+/// Future returned by EClient::snapshot().  It resolves to the quote once TickSnapshotEnd
+/// arrives, or to the error TWS reported, and cancels the market data line if it is dropped
+/// before.  Events of other requests stay available to get_event().
+pub struct Snapshot<'a> {
+    client: &'a mut EClient,
+    snapshot: TickerSnapshot,
+    done: bool,
+}
+
+impl<'a> Snapshot<'a> {
+    pub(crate) fn new(client: &'a mut EClient, req_id: i32) -> Self {
+        Snapshot {
+            client,
+            snapshot: TickerSnapshot::new(req_id),
+            done: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.snapshot.req_id
+    }
+}
+
+impl Future for Snapshot<'_> {
+    type Output = Result<TickerSnapshot, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let req_id = self.snapshot.req_id;
+        let mut snapshot = std::mem::take(&mut self.snapshot);
+        let result = self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::TickSnapshotEnd { req_id: id } if *id == req_id => {
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == req_id && !is_informational(*error_code) => Err(IBKRApiLibError::ApiError(
+                TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+            )),
+            _ if snapshot.update(msg) => Ok(Collected::Consumed),
+            _ => Ok(Collected::Ignored),
+        });
+        let output = match result {
+            Ok(()) => Ok(snapshot),
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.snapshot = snapshot;
+                self.client.wake_on_event(cx.waker());
+                return Poll::Pending;
+            }
+            Err(err) => Err(err),
+        };
+        self.done = true;
+        self.client.correlations().complete(req_id);
+        Poll::Ready(output)
+    }
+}
+
+impl Drop for Snapshot<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            // nothing to cancel once the connection is gone
+            let _ = self.client.cancel_mkt_data(self.snapshot.req_id);
+        }
+    }
+}
+
+//==================================================================================================
+/// Shortable indicators above this value mean at least 1,000 shares are available to short
+pub const EASY_TO_BORROW_INDICATOR: f64 = 2.5;
+/// Shortable indicators above this value mean shares can be shorted once they are located
+pub const HARD_TO_BORROW_INDICATOR: f64 = 1.5;
+
+//==================================================================================================
+/// How easily a contract can be shorted, from the indicator of the Shortable tick
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortableStatus {
+    /// At least 1,000 shares are available
+    EasyToBorrow,
+    /// Shares have to be located before shorting
+    HardToBorrow,
+    /// No shares are available to short
+    NotAvailable,
+}
+
+impl ShortableStatus {
+    pub fn from_indicator(indicator: f64) -> Self {
+        if indicator > EASY_TO_BORROW_INDICATOR {
+            ShortableStatus::EasyToBorrow
+        } else if indicator > HARD_TO_BORROW_INDICATOR {
+            ShortableStatus::HardToBorrow
+        } else {
+            ShortableStatus::NotAvailable
+        }
+    }
+}
+
+//==================================================================================================
+/// What the Shortable generic tick (236) tells about shorting a contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Shortability {
+    pub status: ShortableStatus,
+    /// From the ShortableShares tick, None if TWS did not send it
+    pub available_shares: Option<Decimal>,
+    /// The borrow fee rate in percent per year.  TWS does not stream it as a tick, so it is
+    /// only known when set from another source, such as FEE_RATE historical data.
+    pub fee_rate: Option<f64>,
+}
+
+//==================================================================================================
+/// Collects the Shortable and ShortableShares ticks of a request
+#[derive(Clone, Debug, Default)]
+pub struct ShortabilityTicks {
+    pub req_id: i32,
+    pub indicator: Option<f64>,
+    pub available_shares: Option<Decimal>,
+}
+
+impl ShortabilityTicks {
+    pub fn new(req_id: i32) -> Self {
+        ShortabilityTicks {
+            req_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a tick.  Returns false if the message is not a tick for this request.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::TickGeneric {
+                ticker_id,
+                tick_type,
+                value,
+            } if *ticker_id == self.req_id => {
+                match tick_type {
+                    TickType::Shortable => self.indicator = Some(*value),
+                    TickType::ShortableShares => self.available_shares = shares(*value),
+                    _ => (),
+                }
+                true
+            }
+            // older TWS versions send the number of shares as a size
+            ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size,
+            } if *req_id == self.req_id => {
+                if let TickType::ShortableShares = tick_type {
+                    self.available_shares = Some(Decimal::from(*size));
+                }
+                true
+            }
+            ServerRspMsg::TickPrice { req_id, .. } | ServerRspMsg::TickString { req_id, .. } => {
+                *req_id == self.req_id
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True once both the indicator and the number of shares are known
+    pub fn is_complete(&self) -> bool {
+        self.indicator.is_some() && self.available_shares.is_some()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The shortability, None until the indicator is known
+    pub fn shortability(&self) -> Option<Shortability> {
+        Some(Shortability {
+            status: ShortableStatus::from_indicator(self.indicator?),
+            available_shares: self.available_shares,
+            fee_rate: None,
+        })
+    }
+}
+
+//==================================================================================================
+/// The number of shares of a ShortableShares tick.  TWS sends it as a double; going through its
+/// shortest text form keeps fractional shares exact.
+fn shares(value: f64) -> Option<Decimal> {
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Decimal::from_str(&value.to_string()).ok()
+}
+
+//==================================================================================================
+/// Tick types that TWS only sends when the generic tick is part of the request
+const GENERIC_TICK_TYPES: &[(GenericTickType, &[TickType])] = &[
+    (
+        GenericTickType::OptionVolume,
+        &[TickType::OptionCallVolume, TickType::OptionPutVolume],
+    ),
+    (
+        GenericTickType::OptionOpenInterest,
+        &[
+            TickType::OptionCallOpenInterest,
+            TickType::OptionPutOpenInterest,
+        ],
+    ),
+    (
+        GenericTickType::HistoricalVolatility,
+        &[TickType::OptionHistoricalVol],
+    ),
+    (
+        GenericTickType::AverageOptionVolume,
+        &[TickType::AvgOptVolume],
+    ),
+    (
+        GenericTickType::OptionImpliedVol,
+        &[TickType::OptionImpliedVol],
+    ),
+    (
+        GenericTickType::IndexFuturePremium,
+        &[TickType::IndexFuturePremium],
+    ),
+    (
+        GenericTickType::MiscellaneousStats,
+        &[
+            TickType::Low13Week,
+            TickType::High13Week,
+            TickType::Low26Week,
+            TickType::High26Week,
+            TickType::Low52Week,
+            TickType::High52Week,
+            TickType::AvgVolume,
+        ],
+    ),
+    (GenericTickType::MarkPrice, &[TickType::MarkPrice]),
+    (
+        GenericTickType::AuctionValues,
+        &[
+            TickType::AuctionVolume,
+            TickType::AuctionPrice,
+            TickType::AuctionImbalance,
+            TickType::RegulatoryImbalance,
+        ],
+    ),
+    (GenericTickType::RtVolume, &[TickType::RtVolume]),
+    (
+        GenericTickType::Shortable,
+        &[TickType::Shortable, TickType::ShortableShares],
+    ),
+    (
+        GenericTickType::FundamentalRatios,
+        &[TickType::FundamentalRatios],
+    ),
+    (GenericTickType::News, &[TickType::NewsTick]),
+    (GenericTickType::TradeCount, &[TickType::TradeCount]),
+    (GenericTickType::TradeRate, &[TickType::TradeRate]),
+    (GenericTickType::VolumeRate, &[TickType::VolumeRate]),
+    (GenericTickType::LastRthTrade, &[TickType::LastRthTrade]),
+    (GenericTickType::RtTradeVolume, &[TickType::RtTrdVolume]),
+    (
+        GenericTickType::RtHistoricalVolatility,
+        &[TickType::RtHistoricalVol],
+    ),
+    (GenericTickType::IbDividends, &[TickType::IbDividends]),
+    (
+        GenericTickType::BondFactorMultiplier,
+        &[TickType::BondFactorMultiplier],
+    ),
+    (
+        GenericTickType::FuturesOpenInterest,
+        &[TickType::FuturesOpenInterest],
+    ),
+    (
+        GenericTickType::ShortTermVolume,
+        &[
+            TickType::ShortTermVolume3Min,
+            TickType::ShortTermVolume5Min,
+            TickType::ShortTermVolume10Min,
+        ],
+    ),
+];
+
+//==================================================================================================
+/// The generic tick that has to be requested to receive a tick type, if any
+pub fn generic_tick_for(tick_type: TickType) -> Option<GenericTickType> {
+    GENERIC_TICK_TYPES
+        .iter()
+        .find(|(_, tick_types)| {
+            tick_types
+                .iter()
+                .any(|generic| *generic as i32 == tick_type as i32)
+        })
+        .map(|(generic_tick, _)| *generic_tick)
+}
+
+//==================================================================================================
+/// Parses a comma separated generic tick list.  Entries that are not numbers, such as "mdoff",
+/// are skipped.
+pub fn parse_generic_ticks(generic_tick_list: &str) -> BTreeSet<i32> {
+    generic_tick_list
+        .split(',')
+        .filter_map(|tick| tick.trim().parse().ok())
+        .collect()
+}
+
+//==================================================================================================
+/// The exchanges behind the single letter codes of a BBO exchange, from ReqSmartComponents
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SmartComponentMap {
+    pub bbo_exchange: String,
+    components: BTreeMap<char, SmartComponent>,
+}
+
+impl SmartComponentMap {
+    /// Indexes the components of a SmartComponents event by their letter
+    pub fn new(bbo_exchange: &str, components: &[SmartComponent]) -> Self {
+        SmartComponentMap {
+            bbo_exchange: bbo_exchange.to_string(),
+            components: components
+                .iter()
+                .filter_map(|component| {
+                    let letter = component.exchange_letter.chars().next()?;
+                    Some((letter, component.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The name of the exchange with a letter
+    pub fn exchange(&self, letter: char) -> Option<&str> {
+        self.components
+            .get(&letter)
+            .map(|component| component.exchange.as_str())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn component(&self, letter: char) -> Option<&SmartComponent> {
+        self.components.get(&letter)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The exchange an exchange field of a tick names.  A single letter is looked up, anything
+    /// else is already a name.
+    pub fn resolve<'a>(&'a self, exchange: &'a str) -> &'a str {
+        let mut letters = exchange.chars();
+        match (letters.next(), letters.next()) {
+            (Some(letter), None) => self.exchange(letter).unwrap_or(exchange),
+            _ => exchange,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The components ordered by letter
+    pub fn components(&self) -> impl Iterator<Item = &SmartComponent> {
+        self.components.values()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+//==================================================================================================
+/// Resolves the exchange letters in the ticks of market data requests.
+///
+/// Feed it every event with update(): TickReqParams tells which BBO exchange a request's letters
+/// belong to.  load_missing() then fetches the maps not known yet.
+#[derive(Clone, Debug, Default)]
+pub struct ExchangeResolver {
+    /// The BBO exchange of each request
+    bbo_exchanges: HashMap<i32, String>,
+    maps: HashMap<String, SmartComponentMap>,
+}
+
+impl ExchangeResolver {
+    pub fn new() -> Self {
+        ExchangeResolver::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the BBO exchange of a TickReqParams event.  Returns true if the event was one.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::TickReqParams {
+                ticker_id,
+                bbo_exchange,
+                ..
+            } if !bbo_exchange.is_empty() => {
+                self.bbo_exchanges.insert(*ticker_id, bbo_exchange.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a map, for example one saved from an earlier session
+    pub fn insert(&mut self, map: SmartComponentMap) {
+        self.maps.insert(map.bbo_exchange.clone(), map);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The BBO exchanges seen in TickReqParams events whose map is not known yet, sorted
+    pub fn missing(&self) -> Vec<String> {
+        let missing: BTreeSet<&String> = self
+            .bbo_exchanges
+            .values()
+            .filter(|bbo_exchange| !self.maps.contains_key(*bbo_exchange))
+            .collect();
+        missing.into_iter().cloned().collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the maps of the missing BBO exchanges
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * timeout - How long to wait for each map
+    pub fn load_missing(
+        &mut self,
+        client: &mut EClient,
+        timeout: Duration,
+    ) -> Result<(), IBKRApiLibError> {
+        for bbo_exchange in self.missing() {
+            let map = client.smart_components(&bbo_exchange, timeout)?;
+            self.insert(map);
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The map of the BBO exchange of a request
+    pub fn map(&self, req_id: i32) -> Option<&SmartComponentMap> {
+        self.maps.get(self.bbo_exchanges.get(&req_id)?)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The exchange named by the exchange field of a tick of a request.  Letters whose map is not
+    /// known are returned as they are.
+    pub fn resolve<'a>(&'a self, req_id: i32, exchange: &'a str) -> &'a str {
+        match self.map(req_id) {
+            Some(map) => map.resolve(exchange),
+            None => exchange,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The exchange of a tick by tick trade, resolved.  None for other ticks.
+    pub fn trade_exchange<'a>(&'a self, req_id: i32, tick: &'a TickByTickData) -> Option<&'a str> {
+        match tick {
+            TickByTickData::Last { exchange, .. } => Some(self.resolve(req_id, exchange)),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+fn contract_key(contract: &Contract) -> String {
+    if contract.con_id != 0 {
+        format!("{}@{}", contract.con_id, contract.exchange)
+    } else {
+        format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            contract.symbol,
+            contract.sec_type,
+            contract.last_trade_date_or_contract_month,
+            contract.strike,
+            contract.right,
+            contract.multiplier,
+            contract.exchange,
+            contract.currency,
+            contract.local_symbol
+        )
+    }
+}
+
+//==================================================================================================
+struct TickConsumer {
+    req_id: i32,
+    generic_ticks: BTreeSet<i32>,
+}
+
+impl TickConsumer {
+    fn wants(&self, msg: &ServerRspMsg) -> bool {
+        let tick_type = match msg {
+            ServerRspMsg::TickPrice { tick_type, .. }
+            | ServerRspMsg::TickSize { tick_type, .. }
+            | ServerRspMsg::TickString { tick_type, .. }
+            | ServerRspMsg::TickGeneric { tick_type, .. }
+            | ServerRspMsg::TickEfp { tick_type, .. }
+            | ServerRspMsg::TickOptionComputation { tick_type, .. } => *tick_type,
+            ServerRspMsg::TickNews { .. } => TickType::NewsTick,
+            _ => return true,
+        };
+        match generic_tick_for(tick_type) {
+            Some(generic_tick) => self.generic_ticks.contains(&(generic_tick as i32)),
+            None => true,
+        }
+    }
+}
+
+//==================================================================================================
+struct SharedTicker {
+    key: String,
+    contract: Contract,
+    upstream_id: i32,
+    generic_ticks: BTreeSet<i32>,
+    consumers: Vec<TickConsumer>,
+}
+
+impl SharedTicker {
+    fn generic_tick_list(&self) -> String {
+        join_generic_ticks(&self.generic_ticks)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+fn join_generic_ticks(generic_ticks: &BTreeSet<i32>) -> String {
+    generic_ticks
+        .iter()
+        .map(|tick| tick.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+//==================================================================================================
+/// Shares one market data line per contract between consumers that ask for different generic
+/// tick lists.
+///
+/// TWS treats a second request for the same contract as a competing subscription.  Instead, the
+/// consolidator keeps a single request with the union of all generic ticks, re-requesting it
+/// when a consumer needs ticks it does not include yet.  route() then hands each consumer only
+/// the ticks it asked for, under the consumer's own request id.
+pub struct TickerConsolidator {
+    tickers: Vec<SharedTicker>,
+    next_upstream_id: i32,
+}
+
+impl TickerConsolidator {
+    /// # Arguments
+    /// * first_req_id - Request ids sent to TWS are allocated upwards from this value.  Pick a
+    ///   range that the consumers' own request ids do not use.
+    pub fn new(first_req_id: i32) -> Self {
+        TickerConsolidator {
+            tickers: Vec::new(),
+            next_upstream_id: first_req_id,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes a consumer, sharing an existing line for the contract when there is one
+    ///
+    /// # Arguments
+    /// * req_id - The consumer's request id.  Routed ticks carry this id.
+    /// * contract - The contract to stream
+    /// * generic_tick_list - The generic ticks the consumer wants, as for req_mkt_data()
+    pub fn subscribe(
+        &mut self,
+        client: &mut EClient,
+        req_id: i32,
+        contract: &Contract,
+        generic_tick_list: &str,
+    ) -> Result<(), IBKRApiLibError> {
+        let consumer = TickConsumer {
+            req_id,
+            generic_ticks: parse_generic_ticks(generic_tick_list),
+        };
+        let key = contract_key(contract);
+
+        let index = match self.tickers.iter().position(|ticker| ticker.key == key) {
+            Some(index) => index,
+            None => {
+                let upstream_id = self.next_upstream_id;
+                self.next_upstream_id += 1;
+                let ticker = SharedTicker {
+                    key,
+                    contract: contract.clone(),
+                    upstream_id,
+                    generic_ticks: consumer.generic_ticks.clone(),
+                    consumers: vec![consumer],
+                };
+                client.req_mkt_data(
+                    upstream_id,
+                    contract,
+                    &ticker.generic_tick_list(),
+                    false,
+                    false,
+                    vec![],
+                )?;
+                self.tickers.push(ticker);
+                return Ok(());
+            }
+        };
+
+        let ticker = &mut self.tickers[index];
+        if consumer.generic_ticks.is_subset(&ticker.generic_ticks) {
+            ticker.consumers.push(consumer);
+            return Ok(());
+        }
+
+        // the line has to be widened, which TWS only allows by cancelling and re-requesting it.
+        // The ticker is only updated once both requests went through.
+        let generic_ticks: BTreeSet<i32> = ticker
+            .generic_ticks
+            .union(&consumer.generic_ticks)
+            .cloned()
+            .collect();
+        let generic_tick_list = join_generic_ticks(&generic_ticks);
+        let upstream_id = self.next_upstream_id;
+        client.cancel_mkt_data(ticker.upstream_id)?;
+        self.next_upstream_id += 1;
+        debug!(
+            "widening market data line for {} to generic ticks {} as {}",
+            ticker.contract.symbol, generic_tick_list, upstream_id
+        );
+        client.req_mkt_data(
+            upstream_id,
+            &ticker.contract,
+            &generic_tick_list,
+            false,
+            false,
+            vec![],
+        )?;
+        ticker.upstream_id = upstream_id;
+        ticker.generic_ticks = generic_ticks;
+        ticker.consumers.push(consumer);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes a consumer.  The line is cancelled once its last consumer is gone.
+    pub fn unsubscribe(
+        &mut self,
+        client: &mut EClient,
+        req_id: i32,
+    ) -> Result<(), IBKRApiLibError> {
+        let index = match self.tickers.iter().position(|ticker| {
+            ticker
+                .consumers
+                .iter()
+                .any(|consumer| consumer.req_id == req_id)
+        }) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        self.tickers[index]
+            .consumers
+            .retain(|consumer| consumer.req_id != req_id);
+        if self.tickers[index].consumers.is_empty() {
+            let ticker = self.tickers.remove(index);
+            client.cancel_mkt_data(ticker.upstream_id)?;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The request id used with TWS for a consumer's subscription
+    pub fn upstream_id(&self, req_id: i32) -> Option<i32> {
+        self.tickers
+            .iter()
+            .find(|ticker| {
+                ticker
+                    .consumers
+                    .iter()
+                    .any(|consumer| consumer.req_id == req_id)
+            })
+            .map(|ticker| ticker.upstream_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Fans out an event received on a shared line to the consumers that want it, rewriting the
+    /// request id.  Returns an empty Vec for events that do not belong to a shared line.
+    pub fn route(&self, msg: &ServerRspMsg) -> Vec<ServerRspMsg> {
+        let upstream_id = match ticker_id(msg) {
+            Some(id) => id,
+            None => return Vec::new(),
+        };
+        let ticker = match self
+            .tickers
+            .iter()
+            .find(|ticker| ticker.upstream_id == upstream_id)
+        {
+            Some(ticker) => ticker,
+            None => return Vec::new(),
+        };
+
+        ticker
+            .consumers
+            .iter()
+            .filter(|consumer| consumer.wants(msg))
+            .map(|consumer| with_ticker_id(msg, consumer.req_id))
+            .collect()
+    }
+}
+
+//==================================================================================================
+fn ticker_id(msg: &ServerRspMsg) -> Option<i32> {
+    match msg {
+        ServerRspMsg::TickPrice { req_id, .. }
+        | ServerRspMsg::TickSize { req_id, .. }
+        | ServerRspMsg::TickString { req_id, .. }
+        | ServerRspMsg::TickSnapshotEnd { req_id }
+        | ServerRspMsg::MarketDataType { req_id, .. }
+        | ServerRspMsg::ErrMsg { req_id, .. } => Some(*req_id),
+        ServerRspMsg::TickGeneric { ticker_id, .. }
+        | ServerRspMsg::TickEfp { ticker_id, .. }
+        | ServerRspMsg::TickOptionComputation { ticker_id, .. }
+        | ServerRspMsg::TickReqParams { ticker_id, .. }
+        | ServerRspMsg::TickNews { ticker_id, .. } => Some(*ticker_id),
+        _ => None,
+    }
+}
+
+//==================================================================================================
+fn with_ticker_id(msg: &ServerRspMsg, id: i32) -> ServerRspMsg {
+    let mut msg = msg.clone();
+    match &mut msg {
+        ServerRspMsg::TickPrice { req_id, .. }
+        | ServerRspMsg::TickSize { req_id, .. }
+        | ServerRspMsg::TickString { req_id, .. }
+        | ServerRspMsg::TickSnapshotEnd { req_id }
+        | ServerRspMsg::MarketDataType { req_id, .. }
+        | ServerRspMsg::ErrMsg { req_id, .. } => *req_id = id,
+        ServerRspMsg::TickGeneric { ticker_id, .. }
+        | ServerRspMsg::TickEfp { ticker_id, .. }
+        | ServerRspMsg::TickOptionComputation { ticker_id, .. }
+        | ServerRspMsg::TickReqParams { ticker_id, .. }
+        | ServerRspMsg::TickNews { ticker_id, .. } => *ticker_id = id,
+        _ => (),
+    }
+    msg
+}
@@ -2,31 +2,32 @@
 use std::any::Any;
 use std::collections::HashSet;
 use std::convert::TryInto;
-use std::io::Write;
 use std::string::String;
 use std::vec::Vec;
 
 use ascii;
 use ascii::AsAsciiStr;
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 
-use log::*;
 use num_derive::FromPrimitive;
+use tracing::debug;
 
+use crate::core::account_values::{AccountKey, AccountValue};
 use crate::core::common::{
-    BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode, HistogramData,
-    HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider, PriceIncrement,
-    RealTimeBar, SmartComponent, TickAttrib, TickAttribBidAsk, TickAttribLast, TickByTickType,
-    TickMsgType, TickType, UNSET_DOUBLE, UNSET_INTEGER,
+    AccountId, BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
+    HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider,
+    PriceIncrement, RealTimeBar, SmartComponent, TickAttrib, TickAttribBidAsk, TickAttribLast,
+    TickByTickData, TickByTickType, TickType, NO_VALID_ID, UNSET_DOUBLE, UNSET_INTEGER,
 };
 use crate::core::contract::{Contract, ContractDescription, ContractPreamble, ContractDetails, DeltaNeutralContract};
 use crate::core::errors::IBKRApiLibError;
 use crate::core::execution::{Execution,ExecutionFilter};
 use crate::core::scanner::ScannerSubscription;
-use crate::core::order::{Order, OrderState, SoftDollarTier};
+use crate::core::order::{Order, OrderState, OrderStatusKind, SoftDollarTier, WhyHeld};
 use serde::Deserialize;
 use serde::Serialize;
-use strum_macros::Display;
+use strum_macros::{Display, EnumDiscriminants};
 
 //==================================================================================================
 trait EClientMsgSink {
@@ -42,7 +43,7 @@ pub enum FAMessageDataTypes {
     Aliases = 3,
 }
 
-#[derive(FromPrimitive)]
+#[derive(FromPrimitive, Debug)]
 #[repr(i32)]
 pub enum ServerRspMsgDiscriminants {
     TickPrice = 1,
@@ -124,7 +125,10 @@ pub enum ServerRspMsgDiscriminants {
     CompletedOrdersEnd = 102,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, Display)]
+/// A decoded message from TWS.  ServerRspMsgKind names the variants without their fields, for
+/// use as a key.
+#[derive(Clone, Serialize, Deserialize, Debug, Display, EnumDiscriminants)]
+#[strum_discriminants(name(ServerRspMsgKind), derive(Hash))]
 pub enum ServerRspMsg {
     TickPrice {
         req_id: i32,
@@ -139,15 +143,15 @@ pub enum ServerRspMsg {
     },
     OrderStatus {
         order_id: i32,
-        status: String,
+        status: OrderStatusKind,
         filled: f64,
         remaining: f64,
         avg_fill_price: f64,
-        perm_id: i32,
+        perm_id: i64,
         parent_id: i32,
         last_fill_price: f64,
         client_id: i32,
-        why_held: String,
+        why_held: WhyHeld,
         mkt_cap_price: f64,
     },
     ErrMsg {
@@ -162,9 +166,8 @@ pub enum ServerRspMsg {
         order_state: OrderState,
     },
     AcctValue {
-        key: String,
-        val: String,
-        currency: String,
+        key: AccountKey,
+        value: AccountValue,
         account_name: String,
     },
     PortfolioValue {
@@ -217,7 +220,7 @@ pub enum ServerRspMsg {
         origin_exch: String,
     },
     ManagedAccts {
-        accounts_list: String,
+        accounts: Vec<AccountId>,
     },
     ReceiveFa {
         fa_data: FaDataType,
@@ -370,9 +373,8 @@ pub enum ServerRspMsg {
         req_id: i32,
         account: String,
         model_code: String,
-        key: String,
-        value: String,
-        currency: String,
+        key: AccountKey,
+        value: AccountValue,
     },
     AccountUpdateMultiEnd {
         req_id: i32,
@@ -420,7 +422,8 @@ pub enum ServerRspMsg {
     },
     TickNews {
         ticker_id: i32,
-        time_stamp: i32,
+        /// Milliseconds since the epoch
+        time_stamp: i64,
         provider_code: String,
         article_id: String,
         headline: String,
@@ -431,7 +434,10 @@ pub enum ServerRspMsg {
     },
     HistoricalNews {
         req_id: i32,
-        time: String,
+        /// Time of the article, which TWS sends in UTC.  None if it could not be read.
+        time: Option<DateTime<Utc>>,
+        #[cfg(feature = "raw-times")]
+        time_raw: String,
         provider_code: String,
         article_id: String,
         headline: String,
@@ -442,7 +448,10 @@ pub enum ServerRspMsg {
     },
     HeadTimestamp {
         req_id: i32,
-        head_timestamp: String,
+        /// None if TWS sent a time that could not be read
+        head_timestamp: Option<DateTime<Utc>>,
+        #[cfg(feature = "raw-times")]
+        head_timestamp_raw: String,
     },
     HistogramData {
         req_id: i32,
@@ -497,9 +506,8 @@ pub enum ServerRspMsg {
     },
     TickByTick {
         req_id: i32,
-        tick_type: i32,
         time: i64,
-        tick_msg: TickMsgType,
+        tick: TickByTickData,
     },
     OrderBound {
         req_id: i32,
@@ -519,7 +527,82 @@ pub enum ServerRspMsg {
     },
 }
 
-#[derive(FromPrimitive)]
+impl ServerRspMsg {
+    /// The request id a response belongs to.  Order ids and the -1 of errors that belong to no
+    /// request are not request ids and give None.
+    pub fn req_id(&self) -> Option<i32> {
+        match self {
+            ServerRspMsg::TickPrice { req_id, .. }
+            | ServerRspMsg::TickSize { req_id, .. }
+            | ServerRspMsg::ContractData { req_id, .. }
+            | ServerRspMsg::ExecutionData { req_id, .. }
+            | ServerRspMsg::MarketDepth { req_id, .. }
+            | ServerRspMsg::MarketDepthL2 { req_id, .. }
+            | ServerRspMsg::HistoricalData { req_id, .. }
+            | ServerRspMsg::BondContractData { req_id, .. }
+            | ServerRspMsg::ScannerData { req_id, .. }
+            | ServerRspMsg::TickString { req_id, .. }
+            | ServerRspMsg::RealTimeBars { req_id, .. }
+            | ServerRspMsg::FundamentalData { req_id, .. }
+            | ServerRspMsg::ContractDataEnd { req_id, .. }
+            | ServerRspMsg::ExecutionDataEnd { req_id, .. }
+            | ServerRspMsg::DeltaNeutralValidation { req_id, .. }
+            | ServerRspMsg::ScannerDataEnd { req_id, .. }
+            | ServerRspMsg::TickSnapshotEnd { req_id, .. }
+            | ServerRspMsg::MarketDataType { req_id, .. }
+            | ServerRspMsg::AccountSummary { req_id, .. }
+            | ServerRspMsg::AccountSummaryEnd { req_id, .. }
+            | ServerRspMsg::DisplayGroupList { req_id, .. }
+            | ServerRspMsg::DisplayGroupUpdated { req_id, .. }
+            | ServerRspMsg::PositionMulti { req_id, .. }
+            | ServerRspMsg::PositionMultiEnd { req_id, .. }
+            | ServerRspMsg::AccountUpdateMulti { req_id, .. }
+            | ServerRspMsg::AccountUpdateMultiEnd { req_id, .. }
+            | ServerRspMsg::SecurityDefinitionOptionParameter { req_id, .. }
+            | ServerRspMsg::SecurityDefinitionOptionParameterEnd { req_id, .. }
+            | ServerRspMsg::SoftDollarTiers { req_id, .. }
+            | ServerRspMsg::SymbolSamples { req_id, .. }
+            | ServerRspMsg::SmartComponents { req_id, .. }
+            | ServerRspMsg::NewsArticle { req_id, .. }
+            | ServerRspMsg::HistoricalNews { req_id, .. }
+            | ServerRspMsg::HistoricalNewsEnd { req_id, .. }
+            | ServerRspMsg::HeadTimestamp { req_id, .. }
+            | ServerRspMsg::HistogramData { req_id, .. }
+            | ServerRspMsg::HistoricalDataUpdate { req_id, .. }
+            | ServerRspMsg::RerouteMktDataReq { req_id, .. }
+            | ServerRspMsg::RerouteMktDepthReq { req_id, .. }
+            | ServerRspMsg::Pnl { req_id, .. }
+            | ServerRspMsg::PnlSingle { req_id, .. }
+            | ServerRspMsg::HistoricalTicks { req_id, .. }
+            | ServerRspMsg::HistoricalTicksBidAsk { req_id, .. }
+            | ServerRspMsg::HistoricalTicksLast { req_id, .. }
+            | ServerRspMsg::TickByTick { req_id, .. }
+            | ServerRspMsg::HistoricalDataEnd { req_id, .. } => Some(*req_id),
+            ServerRspMsg::TickOptionComputation { ticker_id, .. }
+            | ServerRspMsg::TickGeneric { ticker_id, .. }
+            | ServerRspMsg::TickEfp { ticker_id, .. }
+            | ServerRspMsg::TickReqParams { ticker_id, .. }
+            | ServerRspMsg::TickNews { ticker_id, .. } => Some(*ticker_id),
+            ServerRspMsg::ErrMsg { req_id, .. } if *req_id != NO_VALID_ID => Some(*req_id),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The order a message is about, for order status, open order, execution and order bound
+    /// messages
+    pub fn order_id(&self) -> Option<i32> {
+        match self {
+            ServerRspMsg::OrderStatus { order_id, .. }
+            | ServerRspMsg::OpenOrder { order_id, .. } => Some(*order_id),
+            ServerRspMsg::ExecutionData { execution, .. } => Some(execution.order_id),
+            ServerRspMsg::OrderBound { api_order_id, .. } => Some(*api_order_id),
+            _ => None,
+        }
+    }
+}
+
+#[derive(FromPrimitive, Debug)]
 #[repr(i32)]
 pub enum ServerReqMsgDiscriminants {
     ReqMktData = 1,
@@ -599,6 +682,37 @@ pub enum ServerReqMsgDiscriminants {
     ReqCompletedOrders = 99,
 }
 
+impl ServerReqMsgDiscriminants {
+    /// Whether the message stops something already requested, rather than asking for more
+    pub fn is_cancel(&self) -> bool {
+        use ServerReqMsgDiscriminants::*;
+        matches!(
+            self,
+            CancelMktData
+                | CancelOrder
+                | CancelMktDepth
+                | CancelNewsBulletins
+                | CancelScannerSubscription
+                | CancelHistoricalData
+                | CancelRealTimeBars
+                | CancelFundamentalData
+                | CancelCalcImpliedVolat
+                | CancelCalcOptionPrice
+                | ReqGlobalCancel
+                | CancelAccountSummary
+                | CancelPositions
+                | CancelPositionsMulti
+                | CancelAccountUpdatesMulti
+                | CancelHistogramData
+                | CancelHeadTimestamp
+                | CancelPnl
+                | CancelPnlSingle
+                | CancelTickByTickData
+                | UnsubscribeFromGroupEvents
+        )
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Display)]
 pub enum ServerReqMsg {
     ReqMktData {
@@ -989,22 +1103,24 @@ pub enum ServerReqMsg {
 
 //==================================================================================================
 pub fn make_message(msg: &str) -> Result<Vec<u8>, IBKRApiLibError> {
-    //let mut buffer = ByteBuffer::new();
-    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer = Vec::with_capacity(4 + msg.len());
+    write_message(msg, &mut buffer)?;
+    Ok(buffer)
+}
 
+//==================================================================================================
+/// Appends `msg` to `buffer` as a frame: the length as a big endian i32, then the text.  Lets
+/// callers reuse a buffer, e.g. one from a BufferPool, instead of allocating one per message.
+pub fn write_message(msg: &str, buffer: &mut Vec<u8>) -> Result<(), IBKRApiLibError> {
+    buffer.reserve(4 + msg.len());
     buffer.extend_from_slice(&i32::to_be_bytes(msg.len() as i32));
-
-    buffer.write(msg.as_ascii_str().unwrap().as_bytes())?;
-    let tmp = buffer.clone();
-    //debug!("Message after create: {:?}", buffer);
-
-    let (_size, _msg, _buf) = read_msg(tmp.as_slice())?;
-    //debug!("Message read: size:{}, msg:{}, bytes: {:?}", size, msg, buf);
-
-    Ok(tmp)
+    buffer.extend_from_slice(msg.as_ascii_str().unwrap().as_bytes());
+    Ok(())
 }
 
 //==================================================================================================
+/// Reads the first frame of `buf` and copies the rest.  Streams are split with a FrameReader
+/// instead, which does not copy.
 pub fn read_msg<'a>(buf: &[u8]) -> Result<(usize, String, Vec<u8>), IBKRApiLibError> {
     // first the size prefix and then the corresponding msg payload ""
 
@@ -1016,28 +1132,77 @@ pub fn read_msg<'a>(buf: &[u8]) -> Result<(usize, String, Vec<u8>), IBKRApiLibEr
     let size = i32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
     //debug!("read_msg: Message size: {:?}", size);
 
+    match split_msg(buf) {
+        Some((payload, rest)) => {
+            let text = String::from_utf8(payload.to_vec()).unwrap();
+            //debug!("read_msg: text in read message: {:?}", text);
+            Ok((size, text, rest.to_vec()))
+        }
+        None => Ok((size, String::new(), buf.to_vec())),
+    }
+}
+
+//==================================================================================================
+/// Splits the first frame off `buf` without copying.  Returns its payload and the bytes after
+/// it, or None if `buf` does not hold a complete frame yet.
+pub fn split_msg(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    if buf.len() < 4 {
+        return None;
+    }
+    let size = i32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
     if buf.len() - 4 >= size {
-        let text = String::from_utf8(buf[4..4 + size].to_vec()).unwrap();
-        //debug!("read_msg: text in read message: {:?}", text);
-        Ok((size, text, buf[4 + size..].to_vec()))
+        Some((&buf[4..4 + size], &buf[4 + size..]))
     } else {
-        Ok((size, String::new(), buf.to_vec()))
+        None
+    }
+}
+
+//==================================================================================================
+/// Iterates over the NULL terminated fields of a message payload without copying them.
+/// Callers only allocate for the fields they keep.
+#[derive(Clone, Debug)]
+pub struct FieldIterator<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> FieldIterator<'a> {
+    pub fn new(buf: &'a str) -> Self {
+        FieldIterator { remaining: buf }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The part of the payload that has not been read yet
+    pub fn remaining(&self) -> &'a str {
+        self.remaining
+    }
+}
+
+impl<'a> Iterator for FieldIterator<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        match self.remaining.find('\u{0}') {
+            Some(end) => {
+                let field = &self.remaining[..end];
+                self.remaining = &self.remaining[end + 1..];
+                Some(field)
+            }
+            None => {
+                let field = self.remaining;
+                self.remaining = "";
+                Some(field)
+            }
+        }
     }
 }
 
 //==================================================================================================
 pub fn read_fields(buf: &str) -> Vec<String> {
     //msg payload is made of fields terminated/separated by NULL chars
-    let a = '\u{0}';
-    let mut fields: Vec<&str> = buf.split(a).collect::<Vec<&str>>();
-    //debug!("fields.len() in read_fields: {}", fields.len());
-    //last one is empty
-    fields.remove(fields.len() - 1);
-
-    fields
-        .iter()
-        .map(|x| String::from(*x))
-        .collect::<Vec<String>>()
+    FieldIterator::new(buf).map(String::from).collect()
 }
 
 //==================================================================================================
@@ -1085,3 +1250,30 @@ pub fn make_field_handle_empty(val: &dyn Any) -> Result<String, IBKRApiLibError>
 
     make_field(val)
 }
+
+//==================================================================================================
+/// Decodes standard or URL-safe base64.  Whitespace and padding are ignored.
+#[cfg(any(feature = "codec", feature = "news"))]
+pub(crate) fn decode_base64(text: &str) -> Result<Vec<u8>, IBKRApiLibError> {
+    let mut bytes = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            _ => return Err(IBKRApiLibError::Parse(format!("not base64, found {:?}", c))),
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
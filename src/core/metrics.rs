@@ -0,0 +1,85 @@
+//! Counters, gauges and histograms of client internals, reported through the `metrics` facade.
+//!
+//! Nothing is collected until the application installs a recorder, for example the Prometheus
+//! exporter of the metrics-exporter-prometheus crate.  Call describe() once to register units
+//! and help texts.
+use std::time::Duration;
+
+use ::metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+use num_traits::FromPrimitive;
+
+use crate::core::messages::ServerRspMsgDiscriminants;
+
+/// Messages decoded, labelled with the message `type`
+pub const MESSAGES_DECODED: &str = "ibtwsapi_messages_decoded_total";
+pub const BYTES_RECEIVED: &str = "ibtwsapi_bytes_received_total";
+pub const BYTES_SENT: &str = "ibtwsapi_bytes_sent_total";
+/// Time taken to decode a message and queue the result
+pub const DECODE_SECONDS: &str = "ibtwsapi_decode_seconds";
+/// Decoded messages waiting for get_event()
+pub const EVENT_QUEUE_DEPTH: &str = "ibtwsapi_event_queue_depth";
+/// Time spent waiting for the pacing limits of historical data requests
+pub const PACING_WAIT_SECONDS: &str = "ibtwsapi_pacing_wait_seconds";
+/// Connections established by a client that had been connected before
+pub const RECONNECTS: &str = "ibtwsapi_reconnects_total";
+
+//==================================================================================================
+/// Registers the units and descriptions of the metrics with the installed recorder
+pub fn describe() {
+    describe_counter!(MESSAGES_DECODED, Unit::Count, "Messages decoded, by type");
+    describe_counter!(BYTES_RECEIVED, Unit::Bytes, "Bytes read from TWS");
+    describe_counter!(BYTES_SENT, Unit::Bytes, "Bytes written to TWS");
+    describe_histogram!(DECODE_SECONDS, Unit::Seconds, "Time to decode a message");
+    describe_gauge!(
+        EVENT_QUEUE_DEPTH,
+        Unit::Count,
+        "Decoded messages waiting to be read"
+    );
+    describe_histogram!(
+        PACING_WAIT_SECONDS,
+        Unit::Seconds,
+        "Time spent waiting for historical data pacing limits"
+    );
+    describe_counter!(RECONNECTS, Unit::Count, "Connections after the first");
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn message_decoded(msg_id: i32) {
+    let msg_type = match ServerRspMsgDiscriminants::from_i32(msg_id) {
+        Some(discriminant) => format!("{:?}", discriminant),
+        None => "Unknown".to_string(),
+    };
+    counter!(MESSAGES_DECODED, "type" => msg_type).increment(1);
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn decoded_in(elapsed: Duration) {
+    histogram!(DECODE_SECONDS).record(elapsed.as_secs_f64());
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn bytes_received(count: usize) {
+    counter!(BYTES_RECEIVED).increment(count as u64);
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn bytes_sent(count: usize) {
+    counter!(BYTES_SENT).increment(count as u64);
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn event_queue_depth(depth: usize) {
+    gauge!(EVENT_QUEUE_DEPTH).set(depth as f64);
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn pacing_wait(wait: Duration) {
+    histogram!(PACING_WAIT_SECONDS).record(wait.as_secs_f64());
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn reconnected() {
+    counter!(RECONNECTS).increment(1);
+}
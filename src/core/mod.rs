@@ -1,17 +1,108 @@
 //! Core structs, enums, and functions
+#[cfg(feature = "analytics")]
+pub mod account_diff;
 pub mod account_summary_tags;
+pub mod account_values;
 pub mod algo_params;
+#[cfg(feature = "backtest")]
+pub mod backtest;
+pub mod buffer_pool;
+pub mod callbacks;
+#[cfg(feature = "client-sync")]
 pub mod client;
 pub mod common;
+#[cfg(feature = "orders")]
+pub mod completed_orders;
+pub mod connection;
 pub mod contract;
+pub mod contracts;
+#[cfg(feature = "analytics")]
+pub mod corporate_actions;
+#[cfg(feature = "orders")]
+pub mod crypto;
 pub mod decoder;
+pub mod display_groups;
 pub mod errors;
+pub mod event_queue;
 pub mod execution;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod fa;
+#[cfg(feature = "analytics")]
+pub mod fill_ledger;
+#[cfg(feature = "orders")]
+pub mod fill_report;
+#[cfg(feature = "codec")]
+pub mod frame_dump;
+pub mod frame_reader;
+#[cfg(feature = "polars")]
+pub mod frames;
+#[cfg(feature = "xml")]
+pub mod fundamentals;
+#[cfg(feature = "orders")]
+pub mod fx;
+pub mod handlers;
+pub mod handshake;
+#[cfg(feature = "marketdata")]
+pub mod historical;
+pub mod latency;
+#[cfg(feature = "orders")]
+pub mod live_orders;
+#[cfg(feature = "marketdata")]
+pub mod market_data;
 pub mod messages;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod models;
+#[cfg(feature = "news")]
+pub mod news;
+pub mod options;
 pub mod order;
 pub mod order_condition;
 pub mod order_decoder;
+#[cfg(feature = "orders")]
+pub mod order_ids;
+#[cfg(feature = "client-sync")]
+pub mod pnl;
+#[cfg(feature = "marketdata")]
+pub mod price_ladder;
+pub mod pricing;
+#[cfg(feature = "client-sync")]
+pub mod proxy;
+#[cfg(feature = "client-sync")]
 pub mod reader;
+pub mod req_ids;
+#[cfg(feature = "orders")]
+pub mod risk_guard;
+pub mod router;
+#[cfg(feature = "client-sync")]
+pub mod runtime;
+#[cfg(feature = "orders")]
+pub mod safety_net;
 pub mod scanner;
 pub mod server_versions;
+#[cfg(feature = "client-sync")]
+pub mod session_manager;
+#[cfg(feature = "client-sync")]
+pub mod shutdown;
+#[cfg(feature = "orders")]
+pub mod slicer;
+#[cfg(feature = "client-sync")]
+pub mod socket_options;
+#[cfg(feature = "orders")]
+pub mod soft_dollar;
+#[cfg(feature = "client-sync")]
 pub mod streamer;
+#[cfg(feature = "marketdata")]
+pub mod subscriptions;
+#[cfg(all(feature = "orders", feature = "marketdata"))]
+pub mod synthetic_stop;
+#[cfg(feature = "codec")]
+pub mod test_vectors;
+pub mod time;
+pub mod trading_hours;
+pub mod units;
+#[cfg(feature = "bridge")]
+pub mod webhooks;
+#[cfg(feature = "orders")]
+pub mod what_if;
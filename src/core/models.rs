@@ -0,0 +1,339 @@
+//! Positions and account values of FA model portfolios
+//!
+//! ReqPositionsMulti and ReqAccountUpdatesMulti report positions and ledger values per account
+//! and model code.  ModelPortfolioTracker keeps the latest state of every (account, model) pair
+//! the subscriptions report, knows when each subscription has sent its initial snapshot, and
+//! consolidates positions and values across models.
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "client-sync")]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+use crate::core::contract::Contract;
+#[cfg(feature = "client-sync")]
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+#[cfg(feature = "client-sync")]
+use crate::core::req_ids::RequestKind;
+
+//==================================================================================================
+/// Which of the two multi subscriptions a req_id belongs to
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModelFeed {
+    /// PositionMulti, from req_positions_multi()
+    Positions,
+    /// AccountUpdateMulti, from req_account_updates_multi()
+    AccountValues,
+}
+
+//==================================================================================================
+/// A position held by a model in an account
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelPosition {
+    pub contract: Contract,
+    pub position: f64,
+    pub avg_cost: f64,
+}
+
+//==================================================================================================
+/// The latest positions and account values of one model in one account
+#[derive(Clone, Debug, Default)]
+pub struct ModelState {
+    pub account: String,
+    pub model_code: String,
+    /// Open positions by con_id.  Closed positions are removed.
+    pub positions: BTreeMap<i32, ModelPosition>,
+    /// Values by (key, currency)
+    pub values: BTreeMap<(String, String), String>,
+}
+
+impl ModelState {
+    /// Last value of a key in a currency.  Use "" for values without a currency.
+    pub fn value(&self, key: &str, currency: &str) -> Option<&str> {
+        self.values
+            .get(&(key.to_string(), currency.to_string()))
+            .map(String::as_str)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Last value of a key in a currency, if it is a number
+    pub fn amount(&self, key: &str, currency: &str) -> Option<f64> {
+        self.value(key, currency)
+            .and_then(|value| value.parse().ok())
+    }
+}
+
+//==================================================================================================
+/// A contract's position summed over several models
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConsolidatedPosition {
+    pub contract: Contract,
+    pub position: f64,
+    /// Average cost weighted by the size of each model's position
+    pub avg_cost: f64,
+    /// The models holding the contract
+    pub model_codes: Vec<String>,
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct Subscription {
+    feed: ModelFeed,
+    /// Set once the end marker of the initial snapshot arrived
+    loaded: bool,
+}
+
+//==================================================================================================
+/// Keeps the positions and account values reported by positions multi and account updates multi
+/// subscriptions.
+///
+/// Feed it every event with update(); events of other requests are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct ModelPortfolioTracker {
+    models: BTreeMap<(String, String), ModelState>,
+    subscriptions: HashMap<i32, Subscription>,
+}
+
+impl ModelPortfolioTracker {
+    pub fn new() -> Self {
+        ModelPortfolioTracker::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts accepting the events of a request made by the caller
+    ///
+    /// # Arguments
+    /// * req_id - The id the request was made with
+    /// * feed - Which request it was
+    pub fn track(&mut self, req_id: i32, feed: ModelFeed) {
+        self.subscriptions.insert(
+            req_id,
+            Subscription {
+                feed,
+                loaded: false,
+            },
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event.  Returns true if it belonged to a tracked request.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::PositionMulti {
+                req_id,
+                account,
+                model_code,
+                contract,
+                position,
+                avg_cost,
+            } if self.is_tracked(*req_id, ModelFeed::Positions) => {
+                let state = self.state_mut(account, model_code);
+                if *position == 0.0 {
+                    state.positions.remove(&contract.con_id);
+                } else {
+                    state.positions.insert(
+                        contract.con_id,
+                        ModelPosition {
+                            contract: contract.clone(),
+                            position: *position,
+                            avg_cost: *avg_cost,
+                        },
+                    );
+                }
+                true
+            }
+            ServerRspMsg::AccountUpdateMulti {
+                req_id,
+                account,
+                model_code,
+                key,
+                value,
+            } if self.is_tracked(*req_id, ModelFeed::AccountValues) => {
+                self.state_mut(account, model_code).values.insert(
+                    (key.to_string(), value.currency().to_string()),
+                    value.to_string(),
+                );
+                true
+            }
+            ServerRspMsg::PositionMultiEnd { req_id }
+            | ServerRspMsg::AccountUpdateMultiEnd { req_id } => {
+                match self.subscriptions.get_mut(req_id) {
+                    Some(subscription) => {
+                        subscription.loaded = true;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether every tracked request has sent its initial snapshot
+    pub fn is_loaded(&self) -> bool {
+        self.subscriptions
+            .values()
+            .all(|subscription| subscription.loaded)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn model(&self, account: &str, model_code: &str) -> Option<&ModelState> {
+        self.models
+            .get(&(account.to_string(), model_code.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Every (account, model) pair reported so far, ordered by account and model code
+    pub fn models(&self) -> impl Iterator<Item = &ModelState> {
+        self.models.values()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Positions summed over the models of an account, ordered by con_id
+    ///
+    /// # Arguments
+    /// * account - The account, or None for all accounts
+    pub fn consolidated_positions(&self, account: Option<&str>) -> Vec<ConsolidatedPosition> {
+        let mut consolidated: BTreeMap<i32, (ConsolidatedPosition, f64)> = BTreeMap::new();
+        for state in self.models_of(account) {
+            for (con_id, held) in &state.positions {
+                let (total, weight) = consolidated.entry(*con_id).or_insert_with(|| {
+                    (
+                        ConsolidatedPosition {
+                            contract: held.contract.clone(),
+                            position: 0.0,
+                            avg_cost: 0.0,
+                            model_codes: Vec::new(),
+                        },
+                        0.0,
+                    )
+                });
+                let size = held.position.abs();
+                total.avg_cost =
+                    (total.avg_cost * *weight + held.avg_cost * size) / (*weight + size);
+                *weight += size;
+                total.position += held.position;
+                if !total.model_codes.contains(&state.model_code) {
+                    total.model_codes.push(state.model_code.clone());
+                }
+            }
+        }
+        consolidated
+            .into_iter()
+            .map(|(_, (position, _))| position)
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A numeric account value summed over the models of an account.  None if no model reported
+    /// it as a number.
+    ///
+    /// # Arguments
+    /// * account - The account, or None for all accounts
+    /// * key - The value's key, such as "NetLiquidation"
+    /// * currency - The value's currency
+    pub fn consolidated_amount(
+        &self,
+        account: Option<&str>,
+        key: &str,
+        currency: &str,
+    ) -> Option<f64> {
+        self.models_of(account)
+            .filter_map(|state| state.amount(key, currency))
+            .fold(None, |total, amount| Some(total.unwrap_or(0.0) + amount))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the positions and account values of an account and model, and waits until
+    /// both have sent their initial snapshot.  Later changes arrive as events for update().
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * account - The account, or "" for all accounts
+    /// * model_code - The model, or "" for all models
+    /// * ledger_and_nlv - Only request the ledger and net liquidation values
+    /// * timeout - How long to wait for the snapshots
+    #[cfg(feature = "client-sync")]
+    pub fn subscribe(
+        &mut self,
+        client: &mut EClient,
+        account: &str,
+        model_code: &str,
+        ledger_and_nlv: bool,
+        timeout: Duration,
+    ) -> Result<(), IBKRApiLibError> {
+        let description = format!("{} {}", account, model_code);
+        let positions_id = client.next_req_id(RequestKind::PositionsMulti, &description);
+        client.req_positions_multi(positions_id, account, model_code)?;
+        self.track(positions_id, ModelFeed::Positions);
+        let values_id = client.next_req_id(RequestKind::AccountUpdatesMulti, &description);
+        client.req_account_updates_multi(values_id, account, model_code, ledger_and_nlv)?;
+        self.track(values_id, ModelFeed::AccountValues);
+
+        client.collect(timeout, |msg| match msg {
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } if (*req_id == positions_id || *req_id == values_id)
+                && !is_informational(*error_code) =>
+            {
+                Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                    *req_id,
+                    error_code.to_string(),
+                    error_str.clone(),
+                )))
+            }
+            _ if self.update(msg) => {
+                if self.is_loaded() {
+                    Ok(Collected::Finished)
+                } else {
+                    Ok(Collected::Consumed)
+                }
+            }
+            _ => Ok(Collected::Ignored),
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels every tracked request.  The last reported state is kept.
+    #[cfg(feature = "client-sync")]
+    pub fn unsubscribe_all(&mut self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        for (req_id, subscription) in self.subscriptions.drain() {
+            match subscription.feed {
+                ModelFeed::Positions => client.cancel_positions_multi(req_id)?,
+                ModelFeed::AccountValues => client.cancel_account_updates_multi(req_id)?,
+            }
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn is_tracked(&self, req_id: i32, feed: ModelFeed) -> bool {
+        self.subscriptions
+            .get(&req_id)
+            .is_some_and(|subscription| subscription.feed == feed)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn state_mut(&mut self, account: &str, model_code: &str) -> &mut ModelState {
+        self.models
+            .entry((account.to_string(), model_code.to_string()))
+            .or_insert_with(|| ModelState {
+                account: account.to_string(),
+                model_code: model_code.to_string(),
+                ..Default::default()
+            })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn models_of<'a>(&'a self, account: Option<&'a str>) -> impl Iterator<Item = &'a ModelState> {
+        self.models
+            .values()
+            .filter(move |state| account.is_none_or(|account| state.account == account))
+    }
+}
@@ -0,0 +1,483 @@
+//! News providers, headlines and articles
+//!
+//! Headlines arrive as TickNews events on a market data request with the news generic tick, or
+//! as HistoricalNews events for a past time range.  Both are turned into a Headline, whose
+//! article can then be fetched with fetch_article().
+#[cfg(feature = "client-sync")]
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+#[cfg(feature = "client-sync")]
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+#[cfg(feature = "client-sync")]
+use crate::core::common::NewsProvider;
+use crate::core::common::{GenericTickType, NO_VALID_ID};
+#[cfg(all(feature = "client-sync", feature = "marketdata"))]
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+#[cfg(feature = "client-sync")]
+use crate::core::errors::{is_informational, TwsApiReportableError};
+use crate::core::messages::{decode_base64, ServerRspMsg};
+#[cfg(feature = "client-sync")]
+use crate::core::req_ids::RequestKind;
+
+/// article_type of a plain text or HTML article
+pub const ARTICLE_TYPE_TEXT: i32 = 0;
+/// article_type of a binary article, usually a PDF, sent as base64
+pub const ARTICLE_TYPE_BINARY: i32 = 1;
+/// Most headlines TWS returns for one historical news request
+pub const MAX_HEADLINES_PER_REQUEST: i32 = 300;
+/// Format of the times of historical news requests
+const HISTORICAL_NEWS_FORMAT: &str = "%Y-%m-%d %H:%M:%S.0";
+
+//==================================================================================================
+/// The body of a news article
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ArticleBody {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl ArticleBody {
+    /// Decodes the body of a NewsArticle event
+    ///
+    /// # Arguments
+    /// * article_type - 0 for text, 1 for base64 encoded binary
+    /// * article_text - The article as sent by TWS
+    pub fn decode(article_type: i32, article_text: &str) -> Result<Self, IBKRApiLibError> {
+        match article_type {
+            ARTICLE_TYPE_TEXT => Ok(ArticleBody::Text(article_text.to_string())),
+            ARTICLE_TYPE_BINARY => Ok(ArticleBody::Binary(decode_base64(article_text)?)),
+            other => Err(IBKRApiLibError::bad_message(
+                NO_VALID_ID,
+                &format!("Unknown news article type {}", other),
+            )),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_pdf(&self) -> bool {
+        matches!(self, ArticleBody::Binary(bytes) if bytes.starts_with(b"%PDF"))
+    }
+}
+
+//==================================================================================================
+/// A news headline, live or historical
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Headline {
+    /// The ticker id of a live headline or the request id of a historical one
+    pub req_id: i32,
+    /// None if TWS sent a time that could not be read
+    pub time: Option<DateTime<Utc>>,
+    pub provider_code: String,
+    pub article_id: String,
+    pub headline: String,
+    /// Only sent with live headlines
+    pub extra_data: String,
+}
+
+impl Headline {
+    /// Builds a headline from a TickNews or HistoricalNews event
+    pub fn from_msg(msg: &ServerRspMsg) -> Option<Self> {
+        match msg {
+            ServerRspMsg::TickNews {
+                ticker_id,
+                time_stamp,
+                provider_code,
+                article_id,
+                headline,
+                extra_data,
+            } => Some(Headline {
+                req_id: *ticker_id,
+                time: Utc.timestamp_millis_opt(*time_stamp).single(),
+                provider_code: provider_code.clone(),
+                article_id: article_id.clone(),
+                headline: headline.clone(),
+                extra_data: extra_data.clone(),
+            }),
+            ServerRspMsg::HistoricalNews {
+                req_id,
+                time,
+                provider_code,
+                article_id,
+                headline,
+                ..
+            } => Some(Headline {
+                req_id: *req_id,
+                time: *time,
+                provider_code: provider_code.clone(),
+                article_id: article_id.clone(),
+                headline: headline.clone(),
+                extra_data: "".to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Headline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.time {
+            Some(time) => write!(f, "{} ", time.format("%Y-%m-%d %H:%M:%S"))?,
+            None => write!(f, "- ")?,
+        }
+        write!(
+            f,
+            "[{}:{}] {}",
+            self.provider_code, self.article_id, self.headline
+        )
+    }
+}
+
+//==================================================================================================
+/// The generic tick list that streams the headlines of the given providers with
+/// req_mkt_data().  Market data ticks are turned off, so only TickNews events are sent.
+///
+/// # Arguments
+/// * provider_codes - Provider codes such as BRFG or DJNL, as returned by news_providers()
+pub fn headline_ticks(provider_codes: &[&str]) -> String {
+    format!(
+        "mdoff,{}:{}",
+        GenericTickType::News,
+        provider_codes.join("+")
+    )
+}
+
+//==================================================================================================
+/// Formats a time for req_historical_news()
+pub fn historical_news_time(time: &DateTime<Utc>) -> String {
+    time.format(HISTORICAL_NEWS_FORMAT).to_string()
+}
+
+//==================================================================================================
+#[cfg(feature = "client-sync")]
+fn request_error(req_id: i32, msg: &ServerRspMsg) -> Option<IBKRApiLibError> {
+    match msg {
+        ServerRspMsg::ErrMsg {
+            req_id: id,
+            error_code,
+            error_str,
+        } if *id == req_id && !is_informational(*error_code) => Some(IBKRApiLibError::ApiError(
+            TwsApiReportableError::new(req_id, error_code.to_string(), error_str.clone()),
+        )),
+        _ => None,
+    }
+}
+
+//==================================================================================================
+/// Requests the news providers the account is subscribed to and waits for them
+///
+/// # Arguments
+/// * client - A connected client
+/// * timeout - How long to wait for the list
+#[cfg(feature = "client-sync")]
+pub fn news_providers(
+    client: &mut EClient,
+    timeout: Duration,
+) -> Result<Vec<NewsProvider>, IBKRApiLibError> {
+    client.req_news_providers()?;
+
+    let mut providers = Vec::new();
+    client.collect(timeout, |msg| match msg {
+        ServerRspMsg::NewsProviders { news_providers } => {
+            providers = news_providers.clone();
+            Ok(Collected::Finished)
+        }
+        _ => Ok(Collected::Ignored),
+    })?;
+    Ok(providers)
+}
+
+//==================================================================================================
+/// Requests an article and waits for its body, decoding binary articles
+///
+/// # Arguments
+/// * client - A connected client
+/// * provider_code - The provider of the headline
+/// * article_id - The article id of the headline
+/// * timeout - How long to wait for the article
+#[cfg(feature = "client-sync")]
+pub fn fetch_article(
+    client: &mut EClient,
+    provider_code: &str,
+    article_id: &str,
+    timeout: Duration,
+) -> Result<ArticleBody, IBKRApiLibError> {
+    let req_id = client.next_req_id(
+        RequestKind::News,
+        &format!("{}:{}", provider_code, article_id),
+    );
+    client.req_news_article(req_id, provider_code, article_id, vec![])?;
+
+    let mut body = None;
+    client.collect(timeout, |msg| match msg {
+        ServerRspMsg::NewsArticle {
+            req_id: id,
+            article_type,
+            article_text,
+        } if *id == req_id => {
+            body = Some(ArticleBody::decode(*article_type, article_text)?);
+            Ok(Collected::Finished)
+        }
+        _ => match request_error(req_id, msg) {
+            Some(err) => Err(err),
+            None => Ok(Collected::Ignored),
+        },
+    })?;
+    body.ok_or_else(|| IBKRApiLibError::Config(format!("no body for article {}", article_id)))
+}
+
+//==================================================================================================
+/// Requests the headlines of a contract over a time range and waits for all of them.  Returns
+/// the headlines, newest first as TWS sends them, and whether there are more than total_results.
+///
+/// # Arguments
+/// * client - A connected client
+/// * con_id - The contract id
+/// * provider_codes - The providers to get headlines from
+/// * start - The exclusive start of the range
+/// * end - The inclusive end of the range
+/// * total_results - The most headlines to return, 1 to 300
+/// * timeout - How long to wait for the headlines
+#[cfg(feature = "client-sync")]
+#[allow(clippy::too_many_arguments)]
+pub fn historical_headlines(
+    client: &mut EClient,
+    con_id: i32,
+    provider_codes: &[&str],
+    start: &DateTime<Utc>,
+    end: &DateTime<Utc>,
+    total_results: i32,
+    timeout: Duration,
+) -> Result<(Vec<Headline>, bool), IBKRApiLibError> {
+    let req_id = client.next_req_id(RequestKind::News, &con_id.to_string());
+    client.req_historical_news(
+        req_id,
+        con_id,
+        &provider_codes.join("+"),
+        &historical_news_time(start),
+        &historical_news_time(end),
+        total_results,
+        vec![],
+    )?;
+
+    let mut headlines = Vec::new();
+    let mut more = false;
+    client.collect(timeout, |msg| match msg {
+        ServerRspMsg::HistoricalNews { req_id: id, .. } if *id == req_id => {
+            headlines.extend(Headline::from_msg(msg));
+            Ok(Collected::Consumed)
+        }
+        ServerRspMsg::HistoricalNewsEnd {
+            req_id: id,
+            has_more,
+        } if *id == req_id => {
+            more = *has_more;
+            Ok(Collected::Finished)
+        }
+        _ => match request_error(req_id, msg) {
+            Some(err) => Err(err),
+            None => Ok(Collected::Ignored),
+        },
+    })?;
+    Ok((headlines, more))
+}
+
+//==================================================================================================
+/// Iterates over every headline of a contract in a time range, newest first.  Historical news
+/// requests are capped, so the range is walked backwards: while TWS reports more headlines, the
+/// next request ends at the oldest headline received so far.  Headlines returned by more than
+/// one request are only yielded once.  Waiting for a request blocks, and an error ends the
+/// iteration.
+#[cfg(feature = "client-sync")]
+pub struct HeadlineHistory<'a> {
+    client: &'a mut EClient,
+    con_id: i32,
+    provider_codes: Vec<String>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    page_timeout: Duration,
+    seen: HashSet<(String, String)>,
+    pending: VecDeque<Headline>,
+    finished: bool,
+}
+
+#[cfg(feature = "client-sync")]
+impl<'a> HeadlineHistory<'a> {
+    /// # Arguments
+    /// * client - A connected client
+    /// * con_id - The contract id
+    /// * provider_codes - The providers to get headlines from
+    /// * start - The exclusive start of the range
+    /// * end - The inclusive end of the range
+    /// * page_timeout - How long to wait for each request
+    pub fn new(
+        client: &'a mut EClient,
+        con_id: i32,
+        provider_codes: &[&str],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        page_timeout: Duration,
+    ) -> Self {
+        HeadlineHistory {
+            client,
+            con_id,
+            provider_codes: provider_codes.iter().map(|code| code.to_string()).collect(),
+            start,
+            end,
+            page_timeout,
+            seen: HashSet::new(),
+            pending: VecDeque::new(),
+            finished: start >= end,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the headlines up to the current end and moves the end back to the oldest one
+    fn request_page(&mut self) -> Result<(), IBKRApiLibError> {
+        let provider_codes: Vec<&str> = self.provider_codes.iter().map(String::as_str).collect();
+        let (headlines, more) = historical_headlines(
+            self.client,
+            self.con_id,
+            &provider_codes,
+            &self.start,
+            &self.end,
+            MAX_HEADLINES_PER_REQUEST,
+            self.page_timeout,
+        )?;
+
+        let oldest = headlines.iter().filter_map(|headline| headline.time).min();
+        let seen = &mut self.seen;
+        let before = self.pending.len();
+        self.pending
+            .extend(headlines.into_iter().filter(|headline| {
+                seen.insert((headline.provider_code.clone(), headline.article_id.clone()))
+            }));
+        // a page of only known headlines means the end can not move back any further
+        match oldest {
+            Some(oldest) if more && self.pending.len() > before && oldest > self.start => {
+                self.end = oldest
+            }
+            _ => self.finished = true,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client-sync")]
+impl Iterator for HeadlineHistory<'_> {
+    type Item = Result<Headline, IBKRApiLibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(headline) = self.pending.pop_front() {
+                return Some(Ok(headline));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(err) = self.request_page() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+//==================================================================================================
+/// Streams the live headlines of a contract.  Waiting for a headline blocks; the iteration ends
+/// when none arrives within the timeout, and an error is returned once before it ends.  The
+/// subscription is cancelled when the stream is dropped.
+#[cfg(all(feature = "client-sync", feature = "marketdata"))]
+pub struct HeadlineStream<'a> {
+    client: &'a mut EClient,
+    req_id: i32,
+    timeout: Duration,
+    finished: bool,
+}
+
+#[cfg(all(feature = "client-sync", feature = "marketdata"))]
+impl<'a> HeadlineStream<'a> {
+    /// Subscribes to the headlines of a contract
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * contract - The contract to stream headlines for
+    /// * provider_codes - The providers to get headlines from
+    /// * timeout - How long to wait for each headline
+    pub fn subscribe(
+        client: &'a mut EClient,
+        contract: &Contract,
+        provider_codes: &[&str],
+        timeout: Duration,
+    ) -> Result<Self, IBKRApiLibError> {
+        let req_id = client.next_req_id(RequestKind::News, &contract.symbol);
+        client.req_mkt_data(
+            req_id,
+            contract,
+            &headline_ticks(provider_codes),
+            false,
+            false,
+            vec![],
+        )?;
+        Ok(HeadlineStream {
+            client,
+            req_id,
+            timeout,
+            finished: false,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The ticker id of the subscription
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+}
+
+#[cfg(all(feature = "client-sync", feature = "marketdata"))]
+impl Iterator for HeadlineStream<'_> {
+    type Item = Result<Headline, IBKRApiLibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let req_id = self.req_id;
+        let mut headline = None;
+        let result = self.client.collect(self.timeout, |msg| match msg {
+            ServerRspMsg::TickNews { ticker_id, .. } if *ticker_id == req_id => {
+                headline = Headline::from_msg(msg);
+                Ok(Collected::Finished)
+            }
+            _ => match request_error(req_id, msg) {
+                Some(err) => Err(err),
+                None => Ok(Collected::Ignored),
+            },
+        });
+        match result {
+            Ok(()) => headline.map(Ok),
+            Err(IBKRApiLibError::RecvTimeoutError(_)) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "client-sync", feature = "marketdata"))]
+impl Drop for HeadlineStream<'_> {
+    fn drop(&mut self) {
+        // nothing to cancel once the connection is gone
+        let _ = self.client.cancel_mkt_data(self.req_id);
+    }
+}
@@ -0,0 +1,417 @@
+//! Option greeks reported by market data subscriptions
+//!
+//! An option's market data carries up to four TickOptionComputation ticks: greeks computed from
+//! the bid, the ask, the last trade and IB's model.  GreeksTracker keeps the latest of each per
+//! subscription, remembers when each was last updated, and reports what changed.
+//!
+//! EClient::implied_volatility() and EClient::option_price() ask TWS for a single computation
+//! and return an OptionCalculation future resolving to its greeks.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+#[cfg(feature = "marketdata")]
+use std::future::Future;
+#[cfg(feature = "marketdata")]
+use std::pin::Pin;
+#[cfg(feature = "marketdata")]
+use std::sync::mpsc::RecvTimeoutError;
+#[cfg(feature = "marketdata")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "marketdata")]
+use crate::core::client::{Collected, EClient};
+use crate::core::common::{TickType, UNSET_DOUBLE};
+use crate::core::contract::Contract;
+#[cfg(feature = "marketdata")]
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// The price a computation is based on.  Delayed ticks count as their real time counterparts.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GreekSource {
+    Bid,
+    Ask,
+    Last,
+    Model,
+}
+
+impl GreekSource {
+    /// The source of an option computation tick type
+    pub fn from_tick_type(tick_type: TickType) -> Option<Self> {
+        match tick_type {
+            TickType::BidOptionComputation | TickType::DelayedBidOption => Some(GreekSource::Bid),
+            TickType::AskOptionComputation | TickType::DelayedAskOption => Some(GreekSource::Ask),
+            TickType::LastOptionComputation | TickType::DelayedLastOption => {
+                Some(GreekSource::Last)
+            }
+            TickType::ModelOption | TickType::DelayedModelOption => Some(GreekSource::Model),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GreekSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+//==================================================================================================
+/// Greeks of one computation.  Values TWS has not computed yet are None.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OptionGreeks {
+    pub implied_vol: Option<f64>,
+    pub delta: Option<f64>,
+    pub opt_price: Option<f64>,
+    pub pv_dividend: Option<f64>,
+    pub gamma: Option<f64>,
+    pub vega: Option<f64>,
+    pub theta: Option<f64>,
+    pub und_price: Option<f64>,
+}
+
+impl OptionGreeks {
+    /// Overwrites the values a newer computation has, keeping the others
+    fn merge(&mut self, newer: &OptionGreeks) {
+        let fields = [
+            (&mut self.implied_vol, newer.implied_vol),
+            (&mut self.delta, newer.delta),
+            (&mut self.opt_price, newer.opt_price),
+            (&mut self.pv_dividend, newer.pv_dividend),
+            (&mut self.gamma, newer.gamma),
+            (&mut self.vega, newer.vega),
+            (&mut self.theta, newer.theta),
+            (&mut self.und_price, newer.und_price),
+        ];
+        for (field, value) in fields {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+    }
+}
+
+impl fmt::Display for OptionGreeks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "iv: {:?}, delta: {:?}, gamma: {:?}, vega: {:?}, theta: {:?}, price: {:?}, und_price: {:?}",
+            self.implied_vol,
+            self.delta,
+            self.gamma,
+            self.vega,
+            self.theta,
+            self.opt_price,
+            self.und_price
+        )
+    }
+}
+
+//==================================================================================================
+fn computed(value: f64) -> Option<f64> {
+    if value == UNSET_DOUBLE || value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+//==================================================================================================
+/// The request id, tick type and greeks of a TickOptionComputation event
+fn computation_of(msg: &ServerRspMsg) -> Option<(i32, TickType, OptionGreeks)> {
+    match msg {
+        ServerRspMsg::TickOptionComputation {
+            ticker_id,
+            tick_type,
+            implied_vol,
+            delta,
+            opt_price,
+            pv_dividend,
+            gamma,
+            vega,
+            theta,
+            und_price,
+        } => Some((
+            *ticker_id,
+            *tick_type,
+            OptionGreeks {
+                implied_vol: computed(*implied_vol),
+                delta: computed(*delta),
+                opt_price: computed(*opt_price),
+                pv_dividend: computed(*pv_dividend),
+                gamma: computed(*gamma),
+                vega: computed(*vega),
+                theta: computed(*theta),
+                und_price: computed(*und_price),
+            },
+        )),
+        _ => None,
+    }
+}
+
+//==================================================================================================
+/// The latest greeks of one subscription, by source
+#[derive(Clone, Debug)]
+pub struct TickerGreeks {
+    pub req_id: i32,
+    pub contract: Contract,
+    sources: BTreeMap<GreekSource, (OptionGreeks, Instant)>,
+}
+
+impl TickerGreeks {
+    /// The latest greeks computed from a source
+    pub fn get(&self, source: GreekSource) -> Option<&OptionGreeks> {
+        self.sources.get(&source).map(|(greeks, _)| greeks)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// When a source was last updated
+    pub fn updated(&self, source: GreekSource) -> Option<Instant> {
+        self.sources.get(&source).map(|(_, updated)| *updated)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether a source has not been updated for longer than max_age.  A source never reported
+    /// is stale.
+    pub fn is_stale(&self, source: GreekSource, max_age: Duration) -> bool {
+        self.updated(source)
+            .is_none_or(|updated| updated.elapsed() > max_age)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The model greeks, or those of the last trade, bid or ask, in that order of preference
+    pub fn best(&self) -> Option<(GreekSource, &OptionGreeks)> {
+        [
+            GreekSource::Model,
+            GreekSource::Last,
+            GreekSource::Bid,
+            GreekSource::Ask,
+        ]
+        .iter()
+        .find_map(|source| self.get(*source).map(|greeks| (*source, greeks)))
+    }
+}
+
+//==================================================================================================
+/// A change of the greeks of a subscription, returned by GreeksTracker::update()
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreeksChange {
+    pub req_id: i32,
+    pub source: GreekSource,
+    /// The greeks before the update, None for the first computation of the source
+    pub previous: Option<OptionGreeks>,
+    pub greeks: OptionGreeks,
+}
+
+//==================================================================================================
+/// Keeps the latest greeks of the market data subscriptions of options.
+///
+/// Feed it every event with update(); events of other requests are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct GreeksTracker {
+    tickers: HashMap<i32, TickerGreeks>,
+}
+
+impl GreeksTracker {
+    pub fn new() -> Self {
+        GreeksTracker::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts accepting the option computations of a market data request
+    ///
+    /// # Arguments
+    /// * req_id - The id the market data was requested with
+    /// * contract - The option
+    pub fn track(&mut self, req_id: i32, contract: &Contract) {
+        self.tickers.insert(
+            req_id,
+            TickerGreeks {
+                req_id,
+                contract: contract.clone(),
+                sources: BTreeMap::new(),
+            },
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops accepting the computations of a request and forgets its greeks
+    pub fn untrack(&mut self, req_id: i32) -> Option<TickerGreeks> {
+        self.tickers.remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event.  Returns the change if it is a computation of a tracked request that
+    /// changed its greeks.  Values the computation does not have keep their previous value.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> Option<GreeksChange> {
+        let (req_id, tick_type, update) = computation_of(msg)?;
+        let source = GreekSource::from_tick_type(tick_type)?;
+        let ticker = self.tickers.get_mut(&req_id)?;
+        let now = Instant::now();
+        match ticker.sources.get_mut(&source) {
+            Some((greeks, updated)) => {
+                *updated = now;
+                let previous = greeks.clone();
+                greeks.merge(&update);
+                if *greeks == previous {
+                    return None;
+                }
+                Some(GreeksChange {
+                    req_id,
+                    source,
+                    previous: Some(previous),
+                    greeks: greeks.clone(),
+                })
+            }
+            None => {
+                ticker.sources.insert(source, (update.clone(), now));
+                Some(GreeksChange {
+                    req_id,
+                    source,
+                    previous: None,
+                    greeks: update,
+                })
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn ticker(&self, req_id: i32) -> Option<&TickerGreeks> {
+        self.tickers.get(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The tracked requests, in no particular order
+    pub fn tickers(&self) -> impl Iterator<Item = &TickerGreeks> {
+        self.tickers.values()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The requests whose source has not been updated for longer than max_age, ordered by req_id
+    pub fn stale(&self, source: GreekSource, max_age: Duration) -> Vec<i32> {
+        let mut stale: Vec<i32> = self
+            .tickers
+            .values()
+            .filter(|ticker| ticker.is_stale(source, max_age))
+            .map(|ticker| ticker.req_id)
+            .collect();
+        stale.sort_unstable();
+        stale
+    }
+}
+
+//==================================================================================================
+/// What an OptionCalculation asked TWS to compute
+#[cfg(feature = "marketdata")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Calculation {
+    /// From the option price, by calculate_implied_volatility()
+    ImpliedVolatility,
+    /// From the volatility, by calculate_option_price()
+    OptionPrice,
+}
+
+//==================================================================================================
+/// Future returned by EClient::implied_volatility() and EClient::option_price().  It resolves to
+/// the greeks of the first TickOptionComputation of its request, or to the error TWS reported,
+/// and cancels the calculation once it is done or dropped.  Events of other requests stay
+/// available to get_event().
+#[cfg(feature = "marketdata")]
+pub struct OptionCalculation<'a> {
+    client: &'a mut EClient,
+    req_id: i32,
+    calculation: Calculation,
+    done: bool,
+}
+
+#[cfg(feature = "marketdata")]
+impl<'a> OptionCalculation<'a> {
+    pub(crate) fn new(client: &'a mut EClient, req_id: i32, calculation: Calculation) -> Self {
+        OptionCalculation {
+            client,
+            req_id,
+            calculation,
+            done: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn calculation(&self) -> Calculation {
+        self.calculation
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn cancel(&mut self) -> Result<(), IBKRApiLibError> {
+        match self.calculation {
+            Calculation::ImpliedVolatility => {
+                self.client.cancel_calculate_implied_volatility(self.req_id)
+            }
+            Calculation::OptionPrice => self.client.cancel_calculate_option_price(self.req_id),
+        }
+    }
+}
+
+#[cfg(feature = "marketdata")]
+impl Future for OptionCalculation<'_> {
+    type Output = Result<OptionGreeks, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let req_id = self.req_id;
+        let mut greeks = None;
+        let result = self.client.collect(Duration::ZERO, |msg| {
+            if let Some((id, _, computation)) = computation_of(msg) {
+                if id == req_id {
+                    greeks = Some(computation);
+                    return Ok(Collected::Finished);
+                }
+            }
+            match msg {
+                ServerRspMsg::ErrMsg {
+                    req_id: id,
+                    error_code,
+                    error_str,
+                } if *id == req_id && !is_informational(*error_code) => {
+                    Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                        req_id,
+                        error_code.to_string(),
+                        error_str.clone(),
+                    )))
+                }
+                _ => Ok(Collected::Ignored),
+            }
+        });
+        let output = match result {
+            Ok(()) => {
+                // the calculation keeps running until cancelled
+                self.cancel().map(|_| greeks.unwrap_or_default())
+            }
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.client.wake_on_event(cx.waker());
+                return Poll::Pending;
+            }
+            Err(err) => Err(err),
+        };
+        self.done = true;
+        self.client.correlations().complete(req_id);
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(feature = "marketdata")]
+impl Drop for OptionCalculation<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            // nothing to cancel once the connection is gone
+            let _ = self.cancel();
+        }
+    }
+}
@@ -1,15 +1,22 @@
 //! Types related to orders
 use std::fmt::{Display, Error, Formatter};
 
+use chrono::DateTime;
+use chrono_tz::Tz;
 use num_derive::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
 use serde::ser::{Serializer, SerializeStruct};
 use serde::de::{self, Deserializer, Visitor, SeqAccess};
 use crate::core::common::{TagValue, UNSET_DOUBLE, UNSET_INTEGER};
+use crate::core::fa::FaAllocationMethod;
 use crate::core::order::AuctionStrategy::AuctionUnset;
 use crate::core::order::Origin::Customer;
 use crate::core::order_condition::{Condition, OrderConditionEnum};
+use crate::core::time::{parse_zoned_time, TwsTime, TwsTimeZone};
+
+pub mod algo;
+pub mod trailing;
 
 //==================================================================================================
 #[repr(i32)]
@@ -43,6 +50,229 @@ impl Default for AuctionStrategy {
     }
 }
 
+//==================================================================================================
+/// How the remaining orders of a one-cancels-all group react when one of them fills
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq)]
+pub enum OcaType {
+    /// Cancel the remaining orders, with overfill protection
+    CancelWithBlock = 1,
+    /// Reduce the remaining orders by the filled quantity, with overfill protection
+    ReduceWithBlock = 2,
+    /// Reduce the remaining orders by the filled quantity, without overfill protection
+    ReduceNonBlock = 3,
+}
+
+//==================================================================================================
+/// What to do with an option position on exercise
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Default)]
+pub enum ExerciseAction {
+    #[default]
+    Exercise = 1,
+    Lapse = 2,
+}
+
+//==================================================================================================
+/// The parameters of an exercise request
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ExerciseOptions {
+    pub action: ExerciseAction,
+    /// Number of contracts to exercise or let lapse
+    pub quantity: i32,
+    /// Account holding the options
+    pub account: String,
+    /// Exercise even if the option is out of the money, or let it lapse even if it is in the
+    /// money, against the natural action
+    pub over_ride: bool,
+}
+
+impl ExerciseOptions {
+    pub fn new(action: ExerciseAction, quantity: i32, account: &str, over_ride: bool) -> Self {
+        ExerciseOptions {
+            action,
+            quantity,
+            account: account.to_string(),
+            over_ride,
+            ..Default::default()
+        }
+    }
+}
+
+//==================================================================================================
+/// The status TWS reports for an order in OrderStatusKind events and OrderState
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OrderStatusKind {
+    /// Not yet acknowledged by TWS
+    #[default]
+    ApiPending,
+    PendingSubmit,
+    PendingCancel,
+    /// Accepted by TWS but not yet sent to the exchange, for example a simulated order
+    PreSubmitted,
+    Submitted,
+    ApiCancelled,
+    Cancelled,
+    Filled,
+    /// Not working, for example because it was rejected or is outside trading hours
+    Inactive,
+    /// A status this version does not know
+    Other(String),
+}
+
+impl OrderStatusKind {
+    /// Whether the order can no longer fill
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatusKind::ApiCancelled
+                | OrderStatusKind::Cancelled
+                | OrderStatusKind::Filled
+                | OrderStatusKind::Inactive
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order is still working or on its way to the exchange.  An unknown status is
+    /// neither live nor terminal.
+    pub fn is_live(&self) -> bool {
+        matches!(
+            self,
+            OrderStatusKind::ApiPending
+                | OrderStatusKind::PendingSubmit
+                | OrderStatusKind::PendingCancel
+                | OrderStatusKind::PreSubmitted
+                | OrderStatusKind::Submitted
+        )
+    }
+}
+
+impl From<&str> for OrderStatusKind {
+    fn from(status: &str) -> Self {
+        match status {
+            "ApiPending" => OrderStatusKind::ApiPending,
+            "PendingSubmit" => OrderStatusKind::PendingSubmit,
+            "PendingCancel" => OrderStatusKind::PendingCancel,
+            "PreSubmitted" => OrderStatusKind::PreSubmitted,
+            "Submitted" => OrderStatusKind::Submitted,
+            "ApiCancelled" => OrderStatusKind::ApiCancelled,
+            "Cancelled" => OrderStatusKind::Cancelled,
+            "Filled" => OrderStatusKind::Filled,
+            "Inactive" => OrderStatusKind::Inactive,
+            other => OrderStatusKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl Display for OrderStatusKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            OrderStatusKind::Other(status) => write!(f, "{}", status),
+            status => write!(f, "{:?}", status),
+        }
+    }
+}
+
+//==================================================================================================
+/// A reason TWS gives in the why_held field of OrderStatus events for not working an order yet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HoldReason {
+    /// A short sale waiting for TWS to locate shares to borrow, sent as "locate"
+    Locate,
+    /// A child order waiting for its parent to fill, sent as "child"
+    Child,
+    /// An order waiting for its trigger or conditions to be met, sent as "trigger"
+    Trigger,
+    /// A reason this version does not know, as TWS sent it
+    Other(String),
+}
+
+impl From<&str> for HoldReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "locate" => HoldReason::Locate,
+            "child" => HoldReason::Child,
+            "trigger" => HoldReason::Trigger,
+            other => HoldReason::Other(other.to_string()),
+        }
+    }
+}
+
+impl Display for HoldReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            HoldReason::Locate => write!(f, "locate"),
+            HoldReason::Child => write!(f, "child"),
+            HoldReason::Trigger => write!(f, "trigger"),
+            HoldReason::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+//==================================================================================================
+/// The why_held field of an OrderStatus event.  TWS sends an empty field for an order it is not
+/// holding back, and a comma separated list of reasons, such as "child,trigger", otherwise.  The
+/// text is kept as sent.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WhyHeld(String);
+
+impl WhyHeld {
+    pub fn new(why_held: &str) -> Self {
+        WhyHeld(why_held.to_string())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether TWS is holding the order back for any reason
+    pub fn is_held(&self) -> bool {
+        !self.reasons().is_empty()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn reasons(&self) -> Vec<HoldReason> {
+        self.0
+            .split(',')
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+            .map(HoldReason::from)
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order is a short sale waiting for shares to be located
+    pub fn is_locating(&self) -> bool {
+        self.reasons().contains(&HoldReason::Locate)
+    }
+}
+
+impl From<&str> for WhyHeld {
+    fn from(why_held: &str) -> Self {
+        WhyHeld::new(why_held)
+    }
+}
+
+impl Display for WhyHeld {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+//==================================================================================================
+/// The price an order is capped at, from the mkt_cap_price field of an OrderStatus event.  TWS
+/// caps the price of some market orders sent outside regular trading hours and sends 0 for orders
+/// that are not capped.
+pub fn capped_price(mkt_cap_price: f64) -> Option<f64> {
+    if mkt_cap_price == 0.0 || mkt_cap_price == UNSET_DOUBLE || !mkt_cap_price.is_finite() {
+        None
+    } else {
+        Some(mkt_cap_price)
+    }
+}
+
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct SoftDollarTier {
@@ -133,6 +363,11 @@ impl OrderState {
             completed_status,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn order_status(&self) -> OrderStatusKind {
+        OrderStatusKind::from(self.status.as_str())
+    }
 }
 
 impl Display for OrderState {
@@ -481,11 +716,15 @@ pub struct Order {
     pub shareholder: String,
     pub imbalance_only: bool,
     pub route_marketable_to_bbo: bool,
-    pub parent_perm_id: i32,
+    /// Perm id of the parent order
+    pub parent_perm_id: i64,
 
     pub use_price_mgmt_algo: bool,
+
     pub client_id: i32,
-    pub perm_id: i32,
+    /// An i64, like the perm ids of OrderStatus and Execution, as recent perm ids no longer fit
+    /// in an i32
+    pub perm_id: i64,
 }
 
 impl Order {
@@ -493,7 +732,7 @@ impl Order {
         soft_dollar_tier: SoftDollarTier,
         order_id: i32,
         client_id: i32,
-        perm_id: i32,
+        perm_id: i64,
         action: String,
         total_quantity: f64,
         order_type: String,
@@ -618,7 +857,7 @@ impl Order {
         shareholder: String,
         imbalance_only: bool,
         route_marketable_to_bbo: bool,
-        parent_perm_id: i32,
+        parent_perm_id: i64,
         use_price_mgmt_algo: bool,
     ) -> Self {
         Order {
@@ -754,6 +993,93 @@ impl Order {
             use_price_mgmt_algo,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Allocates the order to the accounts of an FA group
+    ///
+    /// # Arguments
+    /// * group - Name of the group
+    /// * method - How the quantity is split between the accounts
+    /// * percentage - The percentage to change each position by, for FaAllocationMethod::PctChange
+    pub fn allocate_to_group(
+        mut self,
+        group: &str,
+        method: FaAllocationMethod,
+        percentage: Option<f64>,
+    ) -> Self {
+        self.fa_group = group.to_string();
+        self.fa_method = method.to_string();
+        self.fa_percentage = percentage.map(|pct| pct.to_string()).unwrap_or_default();
+        self.fa_profile.clear();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Allocates the order to the accounts of an FA profile
+    pub fn allocate_to_profile(mut self, profile: &str) -> Self {
+        self.fa_profile = profile.to_string();
+        self.fa_group.clear();
+        self.fa_method.clear();
+        self.fa_percentage.clear();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Bills the order's commissions to a soft dollar tier returned by ReqSoftDollarTiers.
+    /// place_order() refuses orders with a tier if TWS does not support soft dollar tiers.
+    pub fn with_soft_dollar_tier(mut self, tier: SoftDollarTier) -> Self {
+        self.soft_dollar_tier = tier;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sizes the order by an amount of cash instead of a quantity, in the currency of the
+    /// contract or, for forex, of the second currency of the pair.  Clears total_quantity.
+    /// place_order() refuses it if TWS does not support cash quantities.
+    pub fn with_cash_qty(mut self, cash_qty: f64) -> Self {
+        self.cash_qty = cash_qty;
+        self.total_quantity = 0.0;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Makes the order good till a date and time: sets good_till_date and a tif of GTD
+    pub fn with_good_till<Z: TwsTimeZone>(mut self, time: &DateTime<Z>) -> Self {
+        self.good_till_date = time.to_tws_time();
+        self.tif = "GTD".to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Keeps the order from working before a date and time, sent as good_after_time
+    pub fn with_good_after<Z: TwsTimeZone>(mut self, time: &DateTime<Z>) -> Self {
+        self.good_after_time = time.to_tws_time();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reads good_till_date.  None if it is empty or not in a format TWS writes with a zone.
+    pub fn good_till(&self) -> Option<DateTime<Tz>> {
+        parse_zoned_time(&self.good_till_date).ok()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reads good_after_time.  None if it is empty or not in a format TWS writes with a zone.
+    pub fn good_after(&self) -> Option<DateTime<Tz>> {
+        parse_zoned_time(&self.good_after_time).ok()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the order is sized by cash_qty
+    pub fn has_cash_qty(&self) -> bool {
+        self.cash_qty != UNSET_DOUBLE && self.cash_qty != 0.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The FA allocation method, if one is set and known
+    pub fn fa_allocation_method(&self) -> Option<FaAllocationMethod> {
+        self.fa_method.parse().ok()
+    }
 }
 
 impl Display for Order {
@@ -1006,3 +1332,20 @@ impl Default for Order {
         }
     }
 }
+
+//==================================================================================================
+/// Puts `orders` in the one-cancels-all group `group_name`.
+///
+/// Nothing else is changed, so each order is working as soon as it is placed and the group
+/// grows as the orders are placed.
+///
+/// # Arguments
+/// * orders - The orders of the group
+/// * group_name - Name of the group, unique among the account's working orders
+/// * oca_type - What happens to the other orders when one of them fills
+pub fn oca_group(orders: &mut [Order], group_name: &str, oca_type: OcaType) {
+    for order in orders.iter_mut() {
+        order.oca_group = group_name.to_string();
+        order.oca_type = oca_type as i32;
+    }
+}
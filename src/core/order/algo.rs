@@ -0,0 +1,523 @@
+//! Typed builders for IB algo orders
+//!
+//! Each builder knows the strategy name and parameter tags of one algo, so an order gets the
+//! parameters that algo accepts with values in the format TWS expects:
+//!
+//! ```no_run
+//! use ibtwsapi::core::order::algo::{AlgoParams, AlgoTime, Vwap};
+//! use ibtwsapi::core::order::Order;
+//! use ibtwsapi::core::units::Percent;
+//!
+//! let mut order = Order::default();
+//! Vwap::new(Percent::new(10.0))
+//!     .end_time(AlgoTime::parse("15:30:00", "US/Eastern").unwrap())
+//!     .no_take_liq(true)
+//!     .apply(&mut order);
+//! ```
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::TagValue;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::Order;
+use crate::core::units::{Notional, Percent};
+
+//==================================================================================================
+/// Strategy name and parameters of an IB algo
+pub trait AlgoParams {
+    /// Value of Order::algo_strategy
+    fn strategy(&self) -> &'static str;
+
+    /// Value of Order::algo_params
+    fn params(&self) -> Vec<TagValue>;
+
+    /// Sets the strategy and replaces any algo parameters already on the order
+    fn apply(&self, order: &mut Order) {
+        order.algo_strategy = self.strategy().to_string();
+        order.algo_params = self.params();
+    }
+}
+
+//==================================================================================================
+fn tag(tag: &str, value: String) -> TagValue {
+    TagValue::new(tag.to_string(), value)
+}
+
+//==================================================================================================
+fn flag(value: bool) -> String {
+    (value as i32).to_string()
+}
+
+//==================================================================================================
+/// A start or end time of an algo, as a time of day or a date and time in a time zone
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlgoTime(String);
+
+impl AlgoTime {
+    /// # Arguments
+    /// * time - Time of day
+    /// * time_zone - Time zone name TWS knows, such as US/Eastern
+    pub fn at(time: NaiveTime, time_zone: &str) -> Self {
+        AlgoTime(format!("{} {}", time.format("%H:%M:%S"), time_zone))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A date and time, for algos that run across days
+    pub fn on(date_time: NaiveDateTime, time_zone: &str) -> Self {
+        AlgoTime(format!(
+            "{} {}",
+            date_time.format("%Y%m%d-%H:%M:%S"),
+            time_zone
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses a time of day written as HH:MM:SS
+    pub fn parse(time: &str, time_zone: &str) -> Result<Self, IBKRApiLibError> {
+        NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .map(|time| AlgoTime::at(time, time_zone))
+            .map_err(|e| IBKRApiLibError::Parse(format!("invalid algo time {:?}: {}", time, e)))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+//==================================================================================================
+/// When an algo may trade.  Without a start time it starts right away, and without an end time
+/// it runs until the close.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TimeWindow {
+    pub start: Option<AlgoTime>,
+    pub end: Option<AlgoTime>,
+    /// Keep working the order after the end time if it is not complete
+    pub allow_past_end_time: bool,
+}
+
+impl TimeWindow {
+    fn push_params(&self, params: &mut Vec<TagValue>) {
+        if let Some(start) = &self.start {
+            params.push(tag("startTime", start.0.clone()));
+        }
+        if let Some(end) = &self.end {
+            params.push(tag("endTime", end.0.clone()));
+        }
+        params.push(tag("allowPastEndTime", flag(self.allow_past_end_time)));
+    }
+}
+
+//==================================================================================================
+fn push_monetary_value(params: &mut Vec<TagValue>, monetary_value: Option<Notional>) {
+    if let Some(value) = monetary_value {
+        params.push(tag("monetaryValue", value.value().to_string()));
+    }
+}
+
+//==================================================================================================
+/// How quickly the Adaptive algo should work the order
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Urgency {
+    Urgent,
+    Normal,
+    Patient,
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Urgent => "Urgent",
+            Urgency::Normal => "Normal",
+            Urgency::Patient => "Patient",
+        }
+    }
+}
+
+//==================================================================================================
+/// Adaptive algo: works the order between the bid and ask
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Adaptive {
+    pub urgency: Urgency,
+}
+
+impl Adaptive {
+    pub fn new(urgency: Urgency) -> Self {
+        Adaptive { urgency }
+    }
+}
+
+impl AlgoParams for Adaptive {
+    fn strategy(&self) -> &'static str {
+        "Adaptive"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        vec![tag("adaptivePriority", self.urgency.as_str().to_string())]
+    }
+}
+
+//==================================================================================================
+/// VWAP algo: aims for the volume weighted average price between the start and end time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Vwap {
+    pub max_pct_vol: Percent,
+    pub window: TimeWindow,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<Notional>,
+}
+
+impl Vwap {
+    /// # Arguments
+    /// * max_pct_vol - Largest share of the market volume the order may take, at most 50%
+    pub fn new(max_pct_vol: Percent) -> Self {
+        Vwap {
+            max_pct_vol,
+            window: TimeWindow::default(),
+            no_take_liq: false,
+            monetary_value: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn start_time(mut self, start: AlgoTime) -> Self {
+        self.window.start = Some(start);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn end_time(mut self, end: AlgoTime) -> Self {
+        self.window.end = Some(end);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn allow_past_end_time(mut self, allow: bool) -> Self {
+        self.window.allow_past_end_time = allow;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only post liquidity, never take it
+    pub fn no_take_liq(mut self, no_take_liq: bool) -> Self {
+        self.no_take_liq = no_take_liq;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cash amount to trade instead of the order quantity
+    pub fn monetary_value(mut self, value: Notional) -> Self {
+        self.monetary_value = Some(value);
+        self
+    }
+}
+
+impl AlgoParams for Vwap {
+    fn strategy(&self) -> &'static str {
+        "Vwap"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        let mut params = vec![tag("maxPctVol", self.max_pct_vol.fraction().to_string())];
+        self.window.push_params(&mut params);
+        params.push(tag("noTakeLiq", flag(self.no_take_liq)));
+        push_monetary_value(&mut params, self.monetary_value);
+        params
+    }
+}
+
+//==================================================================================================
+/// Which prices the TWAP algo trades at
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TwapStrategyType {
+    Marketable,
+    MatchingMidpoint,
+    MatchingSameSide,
+    MatchingLast,
+}
+
+impl TwapStrategyType {
+    fn as_str(self) -> &'static str {
+        match self {
+            TwapStrategyType::Marketable => "Marketable",
+            TwapStrategyType::MatchingMidpoint => "Matching Midpoint",
+            TwapStrategyType::MatchingSameSide => "Matching Same Side",
+            TwapStrategyType::MatchingLast => "Matching Last",
+        }
+    }
+}
+
+//==================================================================================================
+/// TWAP algo: trades evenly over time between the start and end time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Twap {
+    pub strategy_type: TwapStrategyType,
+    pub window: TimeWindow,
+    pub monetary_value: Option<Notional>,
+}
+
+impl Twap {
+    pub fn new(strategy_type: TwapStrategyType) -> Self {
+        Twap {
+            strategy_type,
+            window: TimeWindow::default(),
+            monetary_value: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn start_time(mut self, start: AlgoTime) -> Self {
+        self.window.start = Some(start);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn end_time(mut self, end: AlgoTime) -> Self {
+        self.window.end = Some(end);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn allow_past_end_time(mut self, allow: bool) -> Self {
+        self.window.allow_past_end_time = allow;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn monetary_value(mut self, value: Notional) -> Self {
+        self.monetary_value = Some(value);
+        self
+    }
+}
+
+impl AlgoParams for Twap {
+    fn strategy(&self) -> &'static str {
+        "Twap"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        let mut params = vec![tag("strategyType", self.strategy_type.as_str().to_string())];
+        self.window.push_params(&mut params);
+        push_monetary_value(&mut params, self.monetary_value);
+        params
+    }
+}
+
+//==================================================================================================
+/// How much market impact the Arrival Price algo accepts to finish sooner
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RiskAversion {
+    GetDone,
+    Aggressive,
+    Neutral,
+    Passive,
+}
+
+impl RiskAversion {
+    fn as_str(self) -> &'static str {
+        match self {
+            RiskAversion::GetDone => "Get Done",
+            RiskAversion::Aggressive => "Aggressive",
+            RiskAversion::Neutral => "Neutral",
+            RiskAversion::Passive => "Passive",
+        }
+    }
+}
+
+//==================================================================================================
+/// Arrival Price algo: aims for the bid/ask midpoint at the time the order is placed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArrivalPx {
+    pub max_pct_vol: Percent,
+    pub risk_aversion: RiskAversion,
+    pub window: TimeWindow,
+    /// Try to complete the order by the end of the day
+    pub force_completion: bool,
+    pub monetary_value: Option<Notional>,
+}
+
+impl ArrivalPx {
+    /// # Arguments
+    /// * max_pct_vol - Largest share of the market volume the order may take, at most 50%
+    /// * risk_aversion - Urgency of the order
+    pub fn new(max_pct_vol: Percent, risk_aversion: RiskAversion) -> Self {
+        ArrivalPx {
+            max_pct_vol,
+            risk_aversion,
+            window: TimeWindow::default(),
+            force_completion: false,
+            monetary_value: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn start_time(mut self, start: AlgoTime) -> Self {
+        self.window.start = Some(start);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn end_time(mut self, end: AlgoTime) -> Self {
+        self.window.end = Some(end);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn allow_past_end_time(mut self, allow: bool) -> Self {
+        self.window.allow_past_end_time = allow;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn force_completion(mut self, force: bool) -> Self {
+        self.force_completion = force;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn monetary_value(mut self, value: Notional) -> Self {
+        self.monetary_value = Some(value);
+        self
+    }
+}
+
+impl AlgoParams for ArrivalPx {
+    fn strategy(&self) -> &'static str {
+        "ArrivalPx"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        let mut params = vec![
+            tag("maxPctVol", self.max_pct_vol.fraction().to_string()),
+            tag("riskAversion", self.risk_aversion.as_str().to_string()),
+        ];
+        self.window.push_params(&mut params);
+        params.push(tag("forceCompletion", flag(self.force_completion)));
+        push_monetary_value(&mut params, self.monetary_value);
+        params
+    }
+}
+
+//==================================================================================================
+/// Dark Ice algo: shows only part of the order and randomizes the displayed size
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DarkIce {
+    pub display_size: i32,
+    pub window: TimeWindow,
+    pub monetary_value: Option<Notional>,
+}
+
+impl DarkIce {
+    /// # Arguments
+    /// * display_size - Quantity shown to the market
+    pub fn new(display_size: i32) -> Self {
+        DarkIce {
+            display_size,
+            window: TimeWindow::default(),
+            monetary_value: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn start_time(mut self, start: AlgoTime) -> Self {
+        self.window.start = Some(start);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn end_time(mut self, end: AlgoTime) -> Self {
+        self.window.end = Some(end);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn allow_past_end_time(mut self, allow: bool) -> Self {
+        self.window.allow_past_end_time = allow;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn monetary_value(mut self, value: Notional) -> Self {
+        self.monetary_value = Some(value);
+        self
+    }
+}
+
+impl AlgoParams for DarkIce {
+    fn strategy(&self) -> &'static str {
+        "DarkIce"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        let mut params = vec![tag("displaySize", self.display_size.to_string())];
+        self.window.push_params(&mut params);
+        push_monetary_value(&mut params, self.monetary_value);
+        params
+    }
+}
+
+//==================================================================================================
+/// Percentage of Volume algo: participates in a fixed share of the market volume
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PctVol {
+    pub pct_vol: Percent,
+    pub window: TimeWindow,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<Notional>,
+}
+
+impl PctVol {
+    /// # Arguments
+    /// * pct_vol - Target share of the market volume, between 1% and 50%
+    pub fn new(pct_vol: Percent) -> Self {
+        PctVol {
+            pct_vol,
+            window: TimeWindow::default(),
+            no_take_liq: false,
+            monetary_value: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn start_time(mut self, start: AlgoTime) -> Self {
+        self.window.start = Some(start);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn end_time(mut self, end: AlgoTime) -> Self {
+        self.window.end = Some(end);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn no_take_liq(mut self, no_take_liq: bool) -> Self {
+        self.no_take_liq = no_take_liq;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn monetary_value(mut self, value: Notional) -> Self {
+        self.monetary_value = Some(value);
+        self
+    }
+}
+
+impl AlgoParams for PctVol {
+    fn strategy(&self) -> &'static str {
+        "PctVol"
+    }
+
+    fn params(&self) -> Vec<TagValue> {
+        let mut params = vec![tag("pctVol", self.pct_vol.fraction().to_string())];
+        if let Some(start) = &self.window.start {
+            params.push(tag("startTime", start.0.clone()));
+        }
+        if let Some(end) = &self.window.end {
+            params.push(tag("endTime", end.0.clone()));
+        }
+        params.push(tag("noTakeLiq", flag(self.no_take_liq)));
+        push_monetary_value(&mut params, self.monetary_value);
+        params
+    }
+}
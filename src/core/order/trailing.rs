@@ -0,0 +1,179 @@
+//! Builders for trailing stop (TRAIL) and trailing stop limit (TRAIL LIMIT) orders
+//!
+//! TWS reads the trail from one of two fields: aux_price for a trailing amount or
+//! trailing_percent for a percentage.  The stop starts at trail_stop_price, or at the trail from
+//! the current price when it is not set.  A TRAIL LIMIT order then sends a limit either as
+//! lmt_price_offset, kept that far beyond the stop as it moves, or as lmt_price together with
+//! trail_stop_price.  Only one field of each pair may be set:
+//!
+//! ```no_run
+//! use ibtwsapi::core::order::trailing::TrailingStop;
+//! use ibtwsapi::core::units::{Percent, Price, TrailingOffset};
+//!
+//! let stop = TrailingStop::sell(100.0, TrailingOffset::Percent(Percent::new(2.5)))
+//!     .build()
+//!     .unwrap();
+//! let stop_limit = TrailingStop::sell(100.0, TrailingOffset::Amount(Price::new(1.5)))
+//!     .stop_price(98.5)
+//!     .limit_offset(0.25)
+//!     .build()
+//!     .unwrap();
+//! ```
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::Order;
+use crate::core::units::TrailingOffset;
+
+pub const TRAIL: &str = "TRAIL";
+pub const TRAIL_LIMIT: &str = "TRAIL LIMIT";
+
+//==================================================================================================
+/// Builds a TRAIL order, or a TRAIL LIMIT order when limit_offset() or limit_price() is called
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TrailingStop {
+    action: String,
+    quantity: f64,
+    trail: TrailingOffset,
+    stop_price: Option<f64>,
+    limit_offset: Option<f64>,
+    limit_price: Option<f64>,
+}
+
+impl TrailingStop {
+    /// # Arguments
+    /// * action - BUY, SELL or SSHORT
+    /// * quantity - The quantity to trade
+    /// * trail - How far the stop follows the price
+    pub fn new(action: &str, quantity: f64, trail: TrailingOffset) -> Self {
+        TrailingStop {
+            action: action.to_string(),
+            quantity,
+            trail,
+            stop_price: None,
+            limit_offset: None,
+            limit_price: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A stop above the price that follows it down, to close a short position
+    pub fn buy(quantity: f64, trail: TrailingOffset) -> Self {
+        TrailingStop::new("BUY", quantity, trail)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A stop below the price that follows it up, to close a long position
+    pub fn sell(quantity: f64, trail: TrailingOffset) -> Self {
+        TrailingStop::new("SELL", quantity, trail)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The initial stop price, sent as trail_stop_price
+    pub fn stop_price(mut self, price: f64) -> Self {
+        self.stop_price = Some(price);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Makes a TRAIL LIMIT order whose limit stays this far beyond the stop: above it for buys and
+    /// below it for sells
+    pub fn limit_offset(mut self, offset: f64) -> Self {
+        self.limit_offset = Some(offset);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Makes a TRAIL LIMIT order with an initial limit price.  TWS keeps the limit as far from the
+    /// stop as it is from stop_price(), which must be given as well.
+    pub fn limit_price(mut self, price: f64) -> Self {
+        self.limit_price = Some(price);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn build(&self) -> Result<Order, IBKRApiLibError> {
+        let limit = self.limit_offset.is_some() || self.limit_price.is_some();
+        let mut order = Order {
+            action: self.action.clone(),
+            order_type: if limit { TRAIL_LIMIT } else { TRAIL }.to_string(),
+            total_quantity: self.quantity,
+            trail_stop_price: self.stop_price.unwrap_or(UNSET_DOUBLE),
+            lmt_price_offset: self.limit_offset.unwrap_or(UNSET_DOUBLE),
+            lmt_price: self.limit_price.unwrap_or(UNSET_DOUBLE),
+            ..Default::default()
+        };
+        self.trail.apply(&mut order);
+        validate_trailing_order(&order)?;
+        Ok(order)
+    }
+}
+
+//==================================================================================================
+fn price(value: f64) -> Option<f64> {
+    if value == UNSET_DOUBLE || value == 0.0 || value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+//==================================================================================================
+fn invalid(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::Config(message)
+}
+
+//==================================================================================================
+/// Checks the trail and limit fields of a TRAIL or TRAIL LIMIT order.  Other orders pass.
+pub fn validate_trailing_order(order: &Order) -> Result<(), IBKRApiLibError> {
+    let limit = match order.order_type.as_str() {
+        TRAIL => false,
+        TRAIL_LIMIT => true,
+        _ => return Ok(()),
+    };
+    match (price(order.aux_price), price(order.trailing_percent)) {
+        (Some(_), Some(_)) => {
+            return Err(invalid(format!(
+                "A {} order takes a trailing amount or a trailing percent, not both",
+                order.order_type
+            )))
+        }
+        (None, None) => {
+            return Err(invalid(format!(
+                "A {} order needs a trailing amount in aux_price or a trailing_percent",
+                order.order_type
+            )))
+        }
+        (Some(amount), None) if amount < 0.0 || amount.is_infinite() => {
+            return Err(invalid(format!("Invalid trailing amount {}", amount)))
+        }
+        (None, Some(percent)) if percent <= 0.0 || percent >= 100.0 => {
+            return Err(invalid(format!("Invalid trailing percent {}", percent)))
+        }
+        _ => (),
+    }
+
+    let lmt_price_offset = price(order.lmt_price_offset);
+    let lmt_price = price(order.lmt_price);
+    if !limit {
+        if lmt_price_offset.is_some() || lmt_price.is_some() {
+            return Err(invalid(
+                "A TRAIL order has no limit, use TRAIL LIMIT instead".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+    match (lmt_price_offset, lmt_price) {
+        (Some(_), Some(_)) => Err(invalid(
+            "A TRAIL LIMIT order takes a limit offset or a limit price, not both".to_string(),
+        )),
+        (None, None) => Err(invalid(
+            "A TRAIL LIMIT order needs a lmt_price_offset or a lmt_price".to_string(),
+        )),
+        (None, Some(_)) if price(order.trail_stop_price).is_none() => Err(invalid(
+            "The limit price of a TRAIL LIMIT order needs a trail_stop_price".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
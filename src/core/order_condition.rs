@@ -1,15 +1,16 @@
 //! Types related to order and execution conditions
 use std::fmt::{Debug, Display, Error, Formatter};
-use std::slice::Iter;
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
 
+use crate::core::common::NO_VALID_ID;
 use crate::core::decoder::{decode_bool, decode_f64, decode_i32, decode_string};
 use crate::core::errors::IBKRApiLibError;
-use crate::core::messages::make_field;
+use crate::core::messages::{make_field, FieldIterator};
+use crate::core::order::Order;
 
 //==================================================================================================
 #[repr(i32)]
@@ -103,7 +104,7 @@ pub enum OrderConditionEnum {
 }
 
 impl Condition for OrderConditionEnum {
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         match self {
             OrderConditionEnum::Execution(s) => s.decode(fields_iter),
             OrderConditionEnum::Price(p) => p.decode(fields_iter),
@@ -163,6 +164,54 @@ impl Condition for OrderConditionEnum {
     }
 }
 
+impl OrderConditionEnum {
+    fn order_condition(&self) -> &OrderCondition {
+        match self {
+            OrderConditionEnum::Execution(s) => &s.order_condition,
+            OrderConditionEnum::Price(p) => {
+                &p.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::Margin(m) => &m.operator_condition.order_condition,
+            OrderConditionEnum::Time(t) => &t.operator_condition.order_condition,
+            OrderConditionEnum::Volume(v) => {
+                &v.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::PercentChange(pch) => {
+                &pch.contract_condition.operator_condition.order_condition
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn order_condition_mut(&mut self) -> &mut OrderCondition {
+        match self {
+            OrderConditionEnum::Execution(s) => &mut s.order_condition,
+            OrderConditionEnum::Price(p) => {
+                &mut p.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::Margin(m) => &mut m.operator_condition.order_condition,
+            OrderConditionEnum::Time(t) => &mut t.operator_condition.order_condition,
+            OrderConditionEnum::Volume(v) => {
+                &mut v.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::PercentChange(pch) => {
+                &mut pch.contract_condition.operator_condition.order_condition
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether this condition is joined to the next one with AND rather than OR
+    pub fn is_conjunction(&self) -> bool {
+        self.order_condition().is_conjunction_connection
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn set_conjunction(&mut self, is_conjunction: bool) {
+        self.order_condition_mut().is_conjunction_connection = is_conjunction;
+    }
+}
+
 impl Display for OrderConditionEnum {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(f, "{}", self.value_to_string())
@@ -188,7 +237,7 @@ impl Debug for OrderConditionEnum {
 
 //==================================================================================================
 pub trait Condition: Display + Debug + Serialize {
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError>;
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError>;
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError>;
     fn value_to_string(&self) -> String;
     fn set_value_from_string(&mut self, text: String);
@@ -228,7 +277,7 @@ impl OrderCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    pub fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         let connector = decode_string(fields_iter)?;
         self.is_conjunction_connection = connector == "a";
         Ok(())
@@ -247,7 +296,7 @@ impl OrderCondition {
     }
 }
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ExecutionCondition {
     pub sec_type: String,
     pub exchange: String,
@@ -266,9 +315,15 @@ impl ExecutionCondition {
     }
 }
 
+impl Default for ExecutionCondition {
+    fn default() -> Self {
+        ExecutionCondition::new(String::new(), String::new(), String::new())
+    }
+}
+
 impl Condition for ExecutionCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order_condition.decode(fields_iter)?;
         self.sec_type = decode_string(fields_iter)?;
         self.exchange = decode_string(fields_iter)?;
@@ -320,8 +375,18 @@ impl Debug for ExecutionCondition {
 
 impl From<OrderConditionEnum> for ExecutionCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        ExecutionCondition::default()
+    /// The condition held by the enum, or a default ExecutionCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Execution(condition) => condition,
+            _ => ExecutionCondition::default(),
+        }
+    }
+}
+
+impl From<ExecutionCondition> for OrderConditionEnum {
+    fn from(condition: ExecutionCondition) -> Self {
+        OrderConditionEnum::Execution(condition)
     }
 }
 
@@ -351,7 +416,7 @@ impl OperatorCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    pub fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order_condition.decode(fields_iter)?;
         self.is_more = decode_bool(fields_iter)?;
         Ok(())
@@ -366,7 +431,7 @@ impl OperatorCondition {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct MarginCondition {
     pub operator_condition: OperatorCondition,
     pub percent: f64,
@@ -382,11 +447,17 @@ impl MarginCondition {
     }
 }
 
+impl Default for MarginCondition {
+    fn default() -> Self {
+        MarginCondition::new(false, 0.0)
+    }
+}
+
 impl Condition for MarginCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.operator_condition.decode(fields_iter)?;
-        self.percent = decode_f64(fields_iter).unwrap();
+        self.percent = decode_f64(fields_iter)?;
         Ok(())
     }
 
@@ -426,8 +497,18 @@ impl Debug for MarginCondition {
 
 impl From<OrderConditionEnum> for MarginCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        MarginCondition::default()
+    /// The condition held by the enum, or a default MarginCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Margin(condition) => condition,
+            _ => MarginCondition::default(),
+        }
+    }
+}
+
+impl From<MarginCondition> for OrderConditionEnum {
+    fn from(condition: MarginCondition) -> Self {
+        OrderConditionEnum::Margin(condition)
     }
 }
 
@@ -451,7 +532,7 @@ impl ContractCondition {
 
 impl Condition for ContractCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.operator_condition.decode(fields_iter)?;
         self.con_id = decode_i32(fields_iter)?;
         self.exchange = decode_string(fields_iter)?;
@@ -503,7 +584,7 @@ impl From<OrderConditionEnum> for ContractCondition {
     }
 }
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TimeCondition {
     pub operator_condition: OperatorCondition,
     pub time: String,
@@ -519,11 +600,17 @@ impl TimeCondition {
     }
 }
 
+impl Default for TimeCondition {
+    fn default() -> Self {
+        TimeCondition::new(false, String::new())
+    }
+}
+
 impl Condition for TimeCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.operator_condition.decode(fields_iter)?;
-        self.time = decode_string(fields_iter).unwrap();
+        self.time = decode_string(fields_iter)?;
         Ok(())
     }
 
@@ -568,13 +655,23 @@ impl Debug for TimeCondition {
 //==================================================================================================
 impl From<OrderConditionEnum> for TimeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        TimeCondition::default()
+    /// The condition held by the enum, or a default TimeCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Time(condition) => condition,
+            _ => TimeCondition::default(),
+        }
+    }
+}
+
+impl From<TimeCondition> for OrderConditionEnum {
+    fn from(condition: TimeCondition) -> Self {
+        OrderConditionEnum::Time(condition)
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PriceCondition {
     pub contract_condition: ContractCondition,
     pub price: f64,
@@ -603,12 +700,28 @@ impl PriceCondition {
     }
 }
 
+impl Default for PriceCondition {
+    fn default() -> Self {
+        PriceCondition::new(TriggerMethod::Default, 0, "", false, 0.0)
+    }
+}
+
 impl Condition for PriceCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
         self.price = decode_f64(fields_iter)?;
-        self.contract_condition.decode(fields_iter)?;
-        self.trigger_method = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap();
+        self.contract_condition.con_id = decode_i32(fields_iter)?;
+        self.contract_condition.exchange = decode_string(fields_iter)?;
+        let trigger_method = decode_i32(fields_iter)?;
+        self.trigger_method = FromPrimitive::from_i32(trigger_method).ok_or_else(|| {
+            IBKRApiLibError::bad_message(
+                NO_VALID_ID,
+                &format!("Unknown trigger method {}", trigger_method),
+            )
+        })?;
         Ok(())
     }
 
@@ -663,13 +776,23 @@ impl Display for PriceCondition {
 
 impl From<OrderConditionEnum> for PriceCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        PriceCondition::default()
+    /// The condition held by the enum, or a default PriceCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Price(condition) => condition,
+            _ => PriceCondition::default(),
+        }
+    }
+}
+
+impl From<PriceCondition> for OrderConditionEnum {
+    fn from(condition: PriceCondition) -> Self {
+        OrderConditionEnum::Price(condition)
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PercentChangeCondition {
     pub contract_condition: ContractCondition,
     pub change_percent: f64,
@@ -690,11 +813,21 @@ impl PercentChangeCondition {
     }
 }
 
+impl Default for PercentChangeCondition {
+    fn default() -> Self {
+        PercentChangeCondition::new(0, String::new(), false, 0.0)
+    }
+}
+
 impl Condition for PercentChangeCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
         self.change_percent = decode_f64(fields_iter)?;
-        self.contract_condition.decode(fields_iter)?;
+        self.contract_condition.con_id = decode_i32(fields_iter)?;
+        self.contract_condition.exchange = decode_string(fields_iter)?;
         Ok(())
     }
 
@@ -749,13 +882,23 @@ impl Debug for PercentChangeCondition {
 
 impl From<OrderConditionEnum> for PercentChangeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        PercentChangeCondition::default()
+    /// The condition held by the enum, or a default PercentChangeCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::PercentChange(condition) => condition,
+            _ => PercentChangeCondition::default(),
+        }
+    }
+}
+
+impl From<PercentChangeCondition> for OrderConditionEnum {
+    fn from(condition: PercentChangeCondition) -> Self {
+        OrderConditionEnum::PercentChange(condition)
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct VolumeCondition {
     pub contract_condition: ContractCondition,
     pub volume: i32,
@@ -776,12 +919,21 @@ impl VolumeCondition {
     }
 }
 
+impl Default for VolumeCondition {
+    fn default() -> Self {
+        VolumeCondition::new(0, "", false, 0)
+    }
+}
+
 impl Condition for VolumeCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.contract_condition.decode(fields_iter)?;
+    fn decode(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
         self.volume = decode_i32(fields_iter)?;
-
+        self.contract_condition.con_id = decode_i32(fields_iter)?;
+        self.contract_condition.exchange = decode_string(fields_iter)?;
         Ok(())
     }
 
@@ -836,8 +988,18 @@ impl Debug for VolumeCondition {
 
 impl From<OrderConditionEnum> for VolumeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        VolumeCondition::default()
+    /// The condition held by the enum, or a default VolumeCondition if it holds another type
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Volume(condition) => condition,
+            _ => VolumeCondition::default(),
+        }
+    }
+}
+
+impl From<VolumeCondition> for OrderConditionEnum {
+    fn from(condition: VolumeCondition) -> Self {
+        OrderConditionEnum::Volume(condition)
     }
 }
 
@@ -854,3 +1016,188 @@ pub fn create_condition<'a>(cond_type: ConditionType) -> OrderConditionEnum {
         ConditionType::Volume => OrderConditionEnum::Volume(VolumeCondition::default()),
     }
 }
+
+//==================================================================================================
+/// Builds the conditions of a conditional order.
+///
+/// Conditions are joined with AND unless or() is called between them.  TWS evaluates the
+/// connectors from the first condition to the last, without precedence between AND and OR.
+///
+/// ```no_run
+/// use ibtwsapi::core::order::Order;
+/// use ibtwsapi::core::order_condition::{ConditionBuilder, TriggerMethod};
+///
+/// let mut order = Order::default();
+/// // activate when AAPL trades above 200, or after 15:00 New York time
+/// ConditionBuilder::new()
+///     .price(265598, "SMART", true, 200.0, TriggerMethod::Default)
+///     .or()
+///     .time(true, "20240105 15:00:00 US/Eastern")
+///     .apply(&mut order);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConditionBuilder {
+    conditions: Vec<OrderConditionEnum>,
+    ignore_rth: bool,
+    cancel_order: bool,
+}
+
+impl ConditionBuilder {
+    pub fn new() -> Self {
+        ConditionBuilder::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a condition, joined to the previous one with AND
+    pub fn condition<C: Into<OrderConditionEnum>>(mut self, condition: C) -> Self {
+        let mut condition = condition.into();
+        condition.set_conjunction(true);
+        self.conditions.push(condition);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Joins the last condition added and the next one with AND.  This is the default.
+    pub fn and(self) -> Self {
+        self.connect(true)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Joins the last condition added and the next one with OR
+    pub fn or(self) -> Self {
+        self.connect(false)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn connect(mut self, is_conjunction: bool) -> Self {
+        if let Some(last) = self.conditions.last_mut() {
+            last.set_conjunction(is_conjunction);
+        }
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True when the price of a contract crosses a level
+    ///
+    /// # Arguments
+    /// * con_id - Contract whose price is watched
+    /// * exchange - Exchange the price is taken from
+    /// * is_more - True to trigger above the price, false to trigger below it
+    /// * price - The price level
+    /// * trigger_method - How the price is determined, for example from the last trade or
+    ///   the bid and ask
+    pub fn price(
+        self,
+        con_id: i32,
+        exchange: &str,
+        is_more: bool,
+        price: f64,
+        trigger_method: TriggerMethod,
+    ) -> Self {
+        self.condition(PriceCondition::new(
+            trigger_method,
+            con_id,
+            exchange,
+            is_more,
+            price,
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True after or before a time
+    ///
+    /// # Arguments
+    /// * is_more - True for after the time, false for before it
+    /// * time - Time in TWS format, "yyyymmdd hh:mm:ss" followed by an optional time zone
+    pub fn time(self, is_more: bool, time: &str) -> Self {
+        self.condition(TimeCondition::new(is_more, time.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True when the margin cushion of the account crosses a percentage
+    ///
+    /// # Arguments
+    /// * is_more - True to trigger above the percentage, false to trigger below it
+    /// * percent - Margin cushion in percent
+    pub fn margin(self, is_more: bool, percent: f64) -> Self {
+        self.condition(MarginCondition::new(is_more, percent))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True once the account has an execution in a symbol
+    ///
+    /// # Arguments
+    /// * symbol - Symbol of the executed contract
+    /// * sec_type - Security type of the executed contract
+    /// * exchange - Exchange of the execution, or SMART for any
+    pub fn execution(self, symbol: &str, sec_type: &str, exchange: &str) -> Self {
+        self.condition(ExecutionCondition::new(
+            sec_type.to_string(),
+            exchange.to_string(),
+            symbol.to_string(),
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True when the traded volume of a contract for the day crosses a level
+    ///
+    /// # Arguments
+    /// * con_id - Contract whose volume is watched
+    /// * exchange - Exchange the volume is taken from
+    /// * is_more - True to trigger above the volume, false to trigger below it
+    /// * volume - The volume level
+    pub fn volume(self, con_id: i32, exchange: &str, is_more: bool, volume: i32) -> Self {
+        self.condition(VolumeCondition::new(con_id, exchange, is_more, volume))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True when the price change of a contract since the last close crosses a percentage
+    ///
+    /// # Arguments
+    /// * con_id - Contract whose price is watched
+    /// * exchange - Exchange the price is taken from
+    /// * is_more - True to trigger above the change, false to trigger below it
+    /// * change_percent - The change in percent
+    pub fn percent_change(
+        self,
+        con_id: i32,
+        exchange: &str,
+        is_more: bool,
+        change_percent: f64,
+    ) -> Self {
+        self.condition(PercentChangeCondition::new(
+            con_id,
+            exchange.to_string(),
+            is_more,
+            change_percent,
+        ))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Lets the conditions trigger outside regular trading hours
+    pub fn ignore_rth(mut self, ignore_rth: bool) -> Self {
+        self.ignore_rth = ignore_rth;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels the order when the conditions are met, instead of activating it.  Only limit
+    /// orders can be cancelled by conditions.
+    pub fn cancel_order(mut self, cancel_order: bool) -> Self {
+        self.cancel_order = cancel_order;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn conditions(&self) -> &[OrderConditionEnum] {
+        &self.conditions
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the conditions of an order, replacing any it had
+    pub fn apply(&self, order: &mut Order) {
+        order.conditions = self.conditions.clone();
+        order.conditions_ignore_rth = self.ignore_rth;
+        order.conditions_cancel_order = self.cancel_order;
+    }
+}
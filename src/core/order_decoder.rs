@@ -1,15 +1,15 @@
 //! Helper types and functions related to decoding order type messages
-use std::slice::Iter;
 
 use num_traits::FromPrimitive;
 
 use crate::core::common::{TagValue, UNSET_DOUBLE};
 use crate::core::contract::{ComboLeg, Contract, DeltaNeutralContract};
 use crate::core::decoder::{
-    decode_bool, decode_f64, decode_f64_show_unset, decode_i32, decode_i32_show_unset,
+    decode_bool, decode_f64, decode_f64_show_unset, decode_i32, decode_i32_show_unset, decode_i64,
     decode_string,
 };
 use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::FieldIterator;
 use crate::core::order::{Order, OrderComboLeg, OrderState, SoftDollarTier};
 use crate::core::order_condition::{create_condition, Condition};
 use crate::core::server_versions::{
@@ -49,7 +49,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     pub(crate) fn decode_completed(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         // read contract fields
         self.decode_contract_fields(fields_iter)?;
@@ -120,7 +120,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn decode_open(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    pub fn decode_open(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.decode_order_id(fields_iter)?;
 
         // read contract fields
@@ -195,7 +195,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_order_id(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_order_id(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.order_id = decode_i32(fields_iter)?;
         Ok(())
     }
@@ -203,7 +203,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_contract_fields(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.contract.con_id = decode_i32(fields_iter)?;
         self.contract.symbol = decode_string(fields_iter)?;
@@ -225,7 +225,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_action(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_action(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.action = decode_string(fields_iter)?;
         Ok(())
     }
@@ -233,7 +233,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_total_quantity(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_FRACTIONAL_POSITIONS {
             self.order.total_quantity = decode_f64(fields_iter)?;
@@ -244,13 +244,16 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_order_type(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_order_type(
+        &mut self,
+        fields_iter: &mut FieldIterator,
+    ) -> Result<(), IBKRApiLibError> {
         self.order.order_type = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_lmt_price(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_lmt_price(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         if self.version < 29 {
             self.order.lmt_price = decode_f64(fields_iter)?;
         } else {
@@ -260,7 +263,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_aux_price(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_aux_price(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         if self.version < 30 {
             self.order.aux_price = decode_f64(fields_iter)?;
         } else {
@@ -271,66 +274,69 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_tif(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_tif(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.tif = decode_string(fields_iter)?;
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_oca_group(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_oca_group(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.oca_group = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_account(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_account(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.account = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_open_close(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_open_close(
+        &mut self,
+        fields_iter: &mut FieldIterator,
+    ) -> Result<(), IBKRApiLibError> {
         self.order.open_close = decode_string(fields_iter)?;
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_origin(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_origin(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.origin = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap();
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_order_ref(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_order_ref(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.order_ref = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_client_id(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_client_id(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.client_id = decode_i32(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_perm_id(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.order.perm_id = decode_i32(fields_iter)?;
+    fn decode_perm_id(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
+        self.order.perm_id = decode_i64(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn decode_outside_rth(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.outside_rth = decode_bool(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_hidden(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_hidden(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.hidden = decode_bool(fields_iter)?;
         Ok(())
     }
@@ -338,7 +344,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_discretionary_amt(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.discretionary_amt = decode_f64(fields_iter)?;
         Ok(())
@@ -347,7 +353,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_good_after_time(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.good_after_time = decode_string(fields_iter)?;
         Ok(())
@@ -356,14 +362,14 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn skip_shares_allocation(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         decode_string(fields_iter)?; // deprecated
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_faparams(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_faparams(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.fa_group = decode_string(fields_iter)?;
         self.order.fa_method = decode_string(fields_iter)?;
         self.order.fa_percentage = decode_string(fields_iter)?;
@@ -372,7 +378,10 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_model_code(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_model_code(
+        &mut self,
+        fields_iter: &mut FieldIterator,
+    ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_MODELS_SUPPORT {
             self.order.model_code = decode_string(fields_iter)?;
         }
@@ -382,14 +391,14 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_good_till_date(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.good_till_date = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_rule80a(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_rule80a(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.rule80a = decode_string(fields_iter)?;
         Ok(())
     }
@@ -397,7 +406,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_percent_offset(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.percent_offset = decode_f64_show_unset(fields_iter)?;
         Ok(())
@@ -405,7 +414,7 @@ impl<'a> OrderDecoder<'a> {
 
     fn decode_settling_firm(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.settling_firm = decode_string(fields_iter)?;
         Ok(())
@@ -414,7 +423,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_short_sale_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.short_sale_slot = decode_i32(fields_iter)?;
         self.order.designated_location = decode_string(fields_iter)?;
@@ -429,7 +438,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_auction_strategy(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.auction_strategy = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap();
         Ok(())
@@ -438,7 +447,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_box_order_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.starting_price = decode_f64_show_unset(fields_iter)?;
         self.order.stock_ref_price = decode_f64_show_unset(fields_iter)?;
@@ -449,7 +458,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_peg_to_stk_or_vol_order_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.stock_range_lower = decode_f64_show_unset(fields_iter)?;
         self.order.stock_range_upper = decode_f64_show_unset(fields_iter)?;
@@ -459,7 +468,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_display_size(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.display_size = decode_i32(fields_iter)?;
         Ok(())
@@ -468,7 +477,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_block_order(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.block_order = decode_bool(fields_iter)?;
         Ok(())
@@ -477,7 +486,7 @@ impl<'a> OrderDecoder<'a> {
 
     fn decode_sweep_to_fill(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.sweep_to_fill = decode_bool(fields_iter)?;
         Ok(())
@@ -486,20 +495,20 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_all_or_none(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.all_or_none = decode_bool(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_min_qty(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_min_qty(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.min_qty = decode_i32_show_unset(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_oca_type(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_oca_type(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.oca_type = decode_i32(fields_iter)?;
         Ok(())
     }
@@ -507,7 +516,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_etrade_only(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.e_trade_only = decode_bool(fields_iter)?;
         Ok(())
@@ -516,7 +525,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_firm_quote_only(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.firm_quote_only = decode_bool(fields_iter)?;
         Ok(())
@@ -525,14 +534,14 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_nbbo_price_cap(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.nbbo_price_cap = decode_f64_show_unset(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_parent_id(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_parent_id(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         self.order.parent_id = decode_i32(fields_iter)?;
         Ok(())
     }
@@ -540,7 +549,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_trigger_method(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.trigger_method = decode_i32(fields_iter)?;
         Ok(())
@@ -548,7 +557,7 @@ impl<'a> OrderDecoder<'a> {
 
     fn decode_vol_order_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
         read_open_order_attribs: bool,
     ) -> Result<(), IBKRApiLibError> {
         self.order.volatility = decode_f64_show_unset(fields_iter)?;
@@ -582,7 +591,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_trail_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.trail_stop_price = decode_f64_show_unset(fields_iter)?;
         if self.version >= 30 {
@@ -594,7 +603,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_basis_points(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.basis_points = decode_f64_show_unset(fields_iter)?;
         self.order.basis_points_type = decode_i32_show_unset(fields_iter)?;
@@ -602,7 +611,10 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_combo_legs(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_combo_legs(
+        &mut self,
+        fields_iter: &mut FieldIterator,
+    ) -> Result<(), IBKRApiLibError> {
         self.contract.combo_legs_descrip = decode_string(fields_iter)?;
 
         if self.version >= 29 {
@@ -640,7 +652,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_smart_combo_routing_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 26 {
             let smart_combo_routing_params_count = decode_i32(fields_iter)?;
@@ -660,7 +672,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_scale_order_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 20 {
             self.order.scale_init_level_size = decode_i32_show_unset(fields_iter)?;
@@ -689,7 +701,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_hedge_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 24 {
             self.order.hedge_type = decode_string(fields_iter)?;
@@ -703,7 +715,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_opt_out_smart_routing(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 25 {
             self.order.opt_out_smart_routing = decode_bool(fields_iter)?;
@@ -714,7 +726,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_clearing_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.clearing_account = decode_string(fields_iter)?;
         self.order.clearing_intent = decode_string(fields_iter)?;
@@ -722,7 +734,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_not_held(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_not_held(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         if self.version >= 22 {
             self.order.not_held = decode_bool(fields_iter)?;
         }
@@ -732,7 +744,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_delta_neutral(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 20 {
             let delta_neutral_contract_present = decode_bool(fields_iter)?;
@@ -756,7 +768,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_algo_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 21 {
             self.order.algo_strategy = decode_string(fields_iter)?;
@@ -777,7 +789,7 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_solicited(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_solicited(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         if self.version >= 33 {
             self.order.solicited = decode_bool(fields_iter)?;
         }
@@ -787,7 +799,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_order_status(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order_state.status = decode_string(fields_iter)?;
         Ok(())
@@ -796,7 +808,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_what_if_info_and_commission(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.what_if = decode_bool(fields_iter)?;
         self.decode_order_status(fields_iter)?;
@@ -824,7 +836,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_vol_randomize_flags(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.version >= 34 {
             self.order.randomize_size = decode_bool(fields_iter)?;
@@ -836,7 +848,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_peg_to_bench_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_PEGGED_TO_BENCHMARK {
             if self.order.order_type == "PEG BENCH" {
@@ -851,14 +863,23 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_conditions(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_conditions(
+        &mut self,
+        fields_iter: &mut FieldIterator,
+    ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_PEGGED_TO_BENCHMARK {
             let conditions_size = decode_i32(fields_iter)?;
 
             if conditions_size > 0 {
                 self.order.conditions = vec![];
                 for _ in 0..conditions_size {
-                    let condition_type = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap();
+                    let type_code = decode_i32(fields_iter)?;
+                    let condition_type = FromPrimitive::from_i32(type_code).ok_or_else(|| {
+                        IBKRApiLibError::bad_message(
+                            self.order.order_id,
+                            &format!("Unknown condition type {}", type_code),
+                        )
+                    })?;
 
                     let mut condition = create_condition(condition_type);
                     condition.decode(fields_iter)?;
@@ -875,7 +896,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_adjusted_order_params(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_PEGGED_TO_BENCHMARK {
             self.order.adjusted_order_type = decode_string(fields_iter)?;
@@ -892,7 +913,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_stop_price_and_lmt_price_offset(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.trail_stop_price = decode_f64(fields_iter)?;
         self.order.lmt_price_offset = decode_f64(fields_iter)?;
@@ -902,7 +923,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_soft_dollar_tier(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_SOFT_DOLLAR_TIER {
             let name = decode_string(fields_iter)?;
@@ -914,9 +935,9 @@ impl<'a> OrderDecoder<'a> {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn decode_cash_qty(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
+    fn decode_cash_qty(&mut self, fields_iter: &mut FieldIterator) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_CASH_QTY {
-            self.order.cash_qty = decode_f64(fields_iter)?;
+            self.order.cash_qty = decode_f64_show_unset(fields_iter)?;
         }
         Ok(())
     }
@@ -924,7 +945,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_dont_use_auto_price_for_hedge(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_AUTO_PRICE_FOR_HEDGE {
             self.order.dont_use_auto_price_for_hedge = decode_bool(fields_iter)?;
@@ -935,7 +956,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_is_oms_containers(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_ORDER_CONTAINER {
             self.order.is_oms_container = decode_bool(fields_iter)?;
@@ -946,7 +967,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_discretionary_up_to_limit_price(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_D_PEG_ORDERS {
             self.order.discretionary_up_to_limit_price = decode_bool(fields_iter)?;
@@ -957,7 +978,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_auto_cancel_date(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.auto_cancel_date = decode_string(fields_iter)?;
         Ok(())
@@ -966,16 +987,27 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_filled_quantity(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
-        self.order.filled_quantity = decode_f64(fields_iter)?;
+        // an empty field means TWS did not report it, unlike a fill of 0
+        let filled_quantity = decode_string(fields_iter)?;
+        self.order.filled_quantity = if filled_quantity.is_empty() {
+            UNSET_DOUBLE
+        } else {
+            filled_quantity.parse().map_err(|_| {
+                IBKRApiLibError::bad_message(
+                    self.order.order_id,
+                    &format!("Invalid filled quantity {:?}", filled_quantity),
+                )
+            })?
+        };
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn decode_ref_futures_con_id(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.ref_futures_con_id = decode_i32(fields_iter)?;
         Ok(())
@@ -984,7 +1016,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_auto_cancel_parent(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.auto_cancel_parent = decode_bool(fields_iter)?;
         Ok(())
@@ -993,7 +1025,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_shareholder(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.shareholder = decode_string(fields_iter)?;
         Ok(())
@@ -1002,7 +1034,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_imbalance_only(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.imbalance_only = decode_bool(fields_iter)?;
         Ok(())
@@ -1011,7 +1043,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_route_marketable_to_bbo(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order.route_marketable_to_bbo = decode_bool(fields_iter)?;
         Ok(())
@@ -1020,16 +1052,16 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_parent_perm_id(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
-        self.order.parent_perm_id = decode_i32(fields_iter)?;
+        self.order.parent_perm_id = decode_i64(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn decode_completed_time(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order_state.completed_time = decode_string(fields_iter)?;
         Ok(())
@@ -1038,7 +1070,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_completed_status(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         self.order_state.completed_status = decode_string(fields_iter)?;
         Ok(())
@@ -1047,7 +1079,7 @@ impl<'a> OrderDecoder<'a> {
     //----------------------------------------------------------------------------------------------
     fn decode_use_price_mgmt_algo(
         &mut self,
-        fields_iter: &mut Iter<String>,
+        fields_iter: &mut FieldIterator,
     ) -> Result<(), IBKRApiLibError> {
         if self.server_version >= MIN_SERVER_VER_PRICE_MGMT_ALGO {
             self.order.use_price_mgmt_algo = decode_bool(fields_iter)?;
@@ -0,0 +1,219 @@
+//! Hands out order ids from NextValidId, and repairs the sequence after collisions
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::core::client::EClient;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+
+/// Error code TWS sends when an order id has already been used
+const DUPLICATE_ORDER_ID: i32 = 103;
+
+//==================================================================================================
+#[derive(Default)]
+struct SequencerState {
+    /// None until the first NextValidId arrives
+    next: Option<i32>,
+    collisions: Vec<i32>,
+    wakers: Vec<Waker>,
+}
+
+impl SequencerState {
+    fn advance_past(&mut self, order_id: i32) -> bool {
+        match self.next {
+            Some(next) if next > order_id => false,
+            _ => {
+                self.next = Some(order_id + 1);
+                true
+            }
+        }
+    }
+}
+
+//==================================================================================================
+/// Hands out increasing order ids to any number of threads or tasks.
+///
+/// Feed every event to observe().  Ids are only handed out once the first NextValidId has
+/// arrived; until then next_order_id() waits.  The sequence never moves backwards: a lower
+/// NextValidId after a reconnect is ignored, and order ids seen in OpenOrder or OrderStatus
+/// events, or reported as duplicates by TWS, move it past them.
+#[derive(Clone, Default)]
+pub struct OrderIdSequencer {
+    inner: Arc<(Mutex<SequencerState>, Condvar)>,
+}
+
+impl OrderIdSequencer {
+    pub fn new() -> Self {
+        OrderIdSequencer::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn state(&self) -> MutexGuard<'_, SequencerState> {
+        // every update leaves the state consistent, so a poisoned lock is still safe to use
+        self.inner
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn notify(&self, mut state: MutexGuard<'_, SequencerState>) {
+        let wakers: Vec<Waker> = state.wakers.drain(..).collect();
+        drop(state);
+        self.inner.1.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Updates the sequence from NextValidId, OpenOrder, OrderStatus and duplicate id errors
+    pub fn observe(&self, msg: &ServerRspMsg) {
+        let mut state = self.state();
+        match msg {
+            ServerRspMsg::NextValidId { order_id } => {
+                match state.next {
+                    Some(next) if next > *order_id => {
+                        debug!(
+                            "ignoring next valid id {}, ids up to {} are already used",
+                            order_id,
+                            next - 1
+                        );
+                        return;
+                    }
+                    _ => state.next = Some(*order_id),
+                }
+                self.notify(state);
+            }
+            ServerRspMsg::OpenOrder { order_id, .. }
+            | ServerRspMsg::OrderStatus { order_id, .. } => {
+                // ids of orders placed by this client before a restart, or by another tool
+                // using the same client id
+                let used_elsewhere = state.next.is_some() && state.advance_past(*order_id);
+                if used_elsewhere {
+                    warn!("order id {} was used elsewhere, skipping past it", order_id);
+                }
+            }
+            ServerRspMsg::ErrMsg {
+                req_id, error_code, ..
+            } if *error_code == DUPLICATE_ORDER_ID => {
+                warn!("order id {} is a duplicate, skipping past it", req_id);
+                state.collisions.push(*req_id);
+                if state.advance_past(*req_id) {
+                    self.notify(state);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Takes `count` consecutive ids if the sequence has been initialized
+    pub fn try_reserve(&self, count: i32) -> Option<Range<i32>> {
+        let mut state = self.state();
+        let start = state.next?;
+        state.next = Some(start + count.max(1));
+        Some(start..start + count.max(1))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Takes the next id if the sequence has been initialized
+    pub fn try_next_order_id(&self) -> Option<i32> {
+        self.try_reserve(1).map(|ids| ids.start)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits until the sequence has been initialized, then takes `count` consecutive ids
+    pub fn reserve(&self, count: i32) -> ReserveOrderIds {
+        ReserveOrderIds {
+            sequencer: self.clone(),
+            count,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits until the sequence has been initialized, then takes the next id
+    pub async fn next_order_id(&self) -> i32 {
+        self.reserve(1).await.start
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocking version of reserve() for threads without an executor
+    pub fn reserve_blocking(
+        &self,
+        count: i32,
+        timeout: Duration,
+    ) -> Result<Range<i32>, IBKRApiLibError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state();
+        while state.next.is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                return Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout));
+            }
+            state = self
+                .inner
+                .1
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        drop(state);
+        Ok(self
+            .try_reserve(count)
+            .expect("the sequence never becomes uninitialized"))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Asks TWS for the next valid id, for example to resynchronize after a collision.  The
+    /// answer arrives as a NextValidId event.
+    pub fn request_sync(&self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        client.req_ids(1)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id next_order_id() will hand out next, if known
+    pub fn peek(&self) -> Option<i32> {
+        self.state().next
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Order ids TWS rejected as duplicates.  Orders placed with them have to be placed again
+    /// with a new id.
+    pub fn collisions(&self) -> Vec<i32> {
+        self.state().collisions.clone()
+    }
+}
+
+//==================================================================================================
+/// Future returned by OrderIdSequencer::reserve()
+pub struct ReserveOrderIds {
+    sequencer: OrderIdSequencer,
+    count: i32,
+}
+
+impl Future for ReserveOrderIds {
+    type Output = Range<i32>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.sequencer.state();
+        match state.next {
+            Some(start) => {
+                let count = self.count.max(1);
+                state.next = Some(start + count);
+                Poll::Ready(start..start + count)
+            }
+            None => {
+                state.wakers.push(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
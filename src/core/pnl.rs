@@ -0,0 +1,329 @@
+//! Streams of daily profit and loss
+//!
+//! ReqPnl reports the PnL of an account or model, and ReqPnlSingle the PnL of one position.
+//! A PnlStream carries any number of these subscriptions on one client and yields their updates
+//! as PnlUpdate values.  PortfolioPnl sums the position updates into a portfolio view.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::{Collected, EClient};
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+use crate::core::req_ids::RequestKind;
+
+/// How long a stream waits for an update unless told otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+//==================================================================================================
+/// A PnL update.  Values TWS does not know yet are None.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PnlUpdate {
+    pub req_id: i32,
+    pub account: String,
+    pub model_code: String,
+    /// The position's contract, None for the PnL of the account or model
+    pub con_id: Option<i32>,
+    /// Size of the position, for position updates
+    pub position: Option<i32>,
+    pub daily_pnl: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    pub realized_pnl: Option<f64>,
+    /// Market value of the position, for position updates
+    pub value: Option<f64>,
+}
+
+impl fmt::Display for PnlUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "account: {}, con_id: {:?}, daily: {:?}, unrealized: {:?}, realized: {:?}",
+            self.account, self.con_id, self.daily_pnl, self.unrealized_pnl, self.realized_pnl
+        )
+    }
+}
+
+//==================================================================================================
+fn known(value: f64) -> Option<f64> {
+    if value == UNSET_DOUBLE || value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct PnlSubscription {
+    account: String,
+    model_code: String,
+    con_id: Option<i32>,
+}
+
+//==================================================================================================
+/// Yields the updates of the PnL subscriptions it holds.  Waiting for an update blocks; the
+/// iteration ends when none arrives within the timeout, and an error is returned once before it
+/// ends.  Every subscription is cancelled when the stream is dropped.
+pub struct PnlStream<'a> {
+    client: &'a mut EClient,
+    subscriptions: HashMap<i32, PnlSubscription>,
+    timeout: Duration,
+    finished: bool,
+}
+
+impl<'a> PnlStream<'a> {
+    /// A stream without subscriptions
+    pub fn new(client: &'a mut EClient) -> Self {
+        PnlStream {
+            client,
+            subscriptions: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+            finished: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// How long to wait for each update
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the PnL of an account or model and returns the request id
+    ///
+    /// # Arguments
+    /// * account - The account
+    /// * model_code - The model, or "" for the whole account
+    pub fn add_account(&mut self, account: &str, model_code: &str) -> Result<i32, IBKRApiLibError> {
+        let req_id = self.client.next_req_id(RequestKind::Pnl, account);
+        self.client.req_pnl(req_id, account, model_code)?;
+        self.subscriptions.insert(
+            req_id,
+            PnlSubscription {
+                account: account.to_string(),
+                model_code: model_code.to_string(),
+                con_id: None,
+            },
+        );
+        Ok(req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscribes to the PnL of one position and returns the request id
+    ///
+    /// # Arguments
+    /// * account - The account holding the position
+    /// * model_code - The model, or ""
+    /// * con_id - The position's contract
+    pub fn add_position(
+        &mut self,
+        account: &str,
+        model_code: &str,
+        con_id: i32,
+    ) -> Result<i32, IBKRApiLibError> {
+        let req_id = self
+            .client
+            .next_req_id(RequestKind::PnlSingle, &format!("{} {}", account, con_id));
+        self.client
+            .req_pnl_single(req_id, account, model_code, con_id)?;
+        self.subscriptions.insert(
+            req_id,
+            PnlSubscription {
+                account: account.to_string(),
+                model_code: model_code.to_string(),
+                con_id: Some(con_id),
+            },
+        );
+        Ok(req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels one subscription
+    pub fn remove(&mut self, req_id: i32) -> Result<(), IBKRApiLibError> {
+        match self.subscriptions.remove(&req_id) {
+            Some(PnlSubscription { con_id: None, .. }) => self.client.cancel_pnl(req_id),
+            Some(_) => self.client.cancel_pnl_single(req_id),
+            None => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The request ids of the subscriptions
+    pub fn req_ids(&self) -> Vec<i32> {
+        self.subscriptions.keys().copied().collect()
+    }
+}
+
+impl Iterator for PnlStream<'_> {
+    type Item = Result<PnlUpdate, IBKRApiLibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.subscriptions.is_empty() {
+            return None;
+        }
+        let mut update = None;
+        let subscriptions = &self.subscriptions;
+        let result = self.client.collect(self.timeout, |msg| {
+            if let Some(matched) = update_from(subscriptions, msg) {
+                update = Some(matched);
+                return Ok(Collected::Finished);
+            }
+            match msg {
+                ServerRspMsg::ErrMsg {
+                    req_id,
+                    error_code,
+                    error_str,
+                } if subscriptions.contains_key(req_id) && !is_informational(*error_code) => {
+                    Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                        *req_id,
+                        error_code.to_string(),
+                        error_str.clone(),
+                    )))
+                }
+                _ => Ok(Collected::Ignored),
+            }
+        });
+        match result {
+            Ok(()) => update.map(Ok),
+            Err(IBKRApiLibError::RecvTimeoutError(_)) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Drop for PnlStream<'_> {
+    fn drop(&mut self) {
+        for req_id in self.req_ids() {
+            // nothing to cancel once the connection is gone
+            let _ = self.remove(req_id);
+        }
+    }
+}
+
+//==================================================================================================
+/// Turns a Pnl or PnlSingle event of one of the subscriptions into an update
+fn update_from(
+    subscriptions: &HashMap<i32, PnlSubscription>,
+    msg: &ServerRspMsg,
+) -> Option<PnlUpdate> {
+    let (req_id, position, daily_pnl, unrealized_pnl, realized_pnl, value) = match msg {
+        ServerRspMsg::Pnl {
+            req_id,
+            daily_pnl,
+            unrealized_pnl,
+            realized_pnl,
+        } => (
+            *req_id,
+            None,
+            *daily_pnl,
+            *unrealized_pnl,
+            *realized_pnl,
+            None,
+        ),
+        ServerRspMsg::PnlSingle {
+            req_id,
+            pos,
+            daily_pnl,
+            unrealized_pnl,
+            realized_pnl,
+            value,
+        } => (
+            *req_id,
+            Some(*pos),
+            *daily_pnl,
+            *unrealized_pnl,
+            *realized_pnl,
+            known(*value),
+        ),
+        _ => return None,
+    };
+    let subscription = subscriptions.get(&req_id)?;
+    Some(PnlUpdate {
+        req_id,
+        account: subscription.account.clone(),
+        model_code: subscription.model_code.clone(),
+        con_id: subscription.con_id,
+        position,
+        daily_pnl: known(daily_pnl),
+        unrealized_pnl: known(unrealized_pnl),
+        realized_pnl: known(realized_pnl),
+        value,
+    })
+}
+
+//==================================================================================================
+/// The PnL of the positions of a portfolio, summed from position updates
+#[derive(Clone, Debug, Default)]
+pub struct PortfolioPnl {
+    /// The latest update of each position, by con_id
+    positions: BTreeMap<i32, PnlUpdate>,
+}
+
+impl PortfolioPnl {
+    pub fn new() -> Self {
+        PortfolioPnl::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a position update.  Returns false for account and model updates, which are not
+    /// part of the sums.
+    pub fn update(&mut self, update: &PnlUpdate) -> bool {
+        match update.con_id {
+            Some(con_id) => {
+                self.positions.insert(con_id, update.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The latest update of a position
+    pub fn position(&self, con_id: i32) -> Option<&PnlUpdate> {
+        self.positions.get(&con_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn positions(&self) -> impl Iterator<Item = &PnlUpdate> {
+        self.positions.values()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Daily PnL summed over the positions that reported one
+    pub fn daily_pnl(&self) -> f64 {
+        self.sum(|update| update.daily_pnl)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Unrealized PnL summed over the positions that reported one
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.sum(|update| update.unrealized_pnl)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Realized PnL summed over the positions that reported one
+    pub fn realized_pnl(&self) -> f64 {
+        self.sum(|update| update.realized_pnl)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Market value summed over the positions that reported one
+    pub fn value(&self) -> f64 {
+        self.sum(|update| update.value)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn sum<F: Fn(&PnlUpdate) -> Option<f64>>(&self, field: F) -> f64 {
+        self.positions.values().filter_map(field).sum()
+    }
+}
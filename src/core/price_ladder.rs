@@ -0,0 +1,301 @@
+//! Price ladder for DOM-style trading screens: book depth, traded volume and working orders per
+//! price level
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{TickByTickData, UNSET_DOUBLE};
+use crate::core::messages::ServerRspMsg;
+
+/// Market depth operations
+const DEPTH_INSERT: i32 = 0;
+const DEPTH_UPDATE: i32 = 1;
+const DEPTH_DELETE: i32 = 2;
+/// Market depth side of bids.  0 is the ask side.
+const DEPTH_BID: i32 = 1;
+
+//==================================================================================================
+/// One row of the ladder
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LadderLevel {
+    pub price: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    /// Volume traded at this price since the ladder was created or reset_volume() was called
+    pub traded_volume: f64,
+    /// Remaining quantity of the user's working buy orders at this price
+    pub working_buy: f64,
+    pub working_sell: f64,
+}
+
+impl LadderLevel {
+    pub fn is_empty(&self) -> bool {
+        self.bid_size == 0.0
+            && self.ask_size == 0.0
+            && self.traded_volume == 0.0
+            && self.working_buy == 0.0
+            && self.working_sell == 0.0
+    }
+}
+
+//==================================================================================================
+/// A change to the ladder.  A level whose fields are all zero has been cleared.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LadderUpdate {
+    Level(LadderLevel),
+    LastPrice(f64),
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct WorkingOrder {
+    is_buy: bool,
+    price: f64,
+    remaining: f64,
+}
+
+/// Combines market depth, tick-by-tick trades and the user's working orders of one contract
+/// into price levels, and reports which levels changed after each event.
+///
+/// Prices are bucketed by the contract's minimum tick, so levels line up even when prices
+/// carry floating point noise.
+#[derive(Clone, Debug)]
+pub struct PriceLadder {
+    con_id: i32,
+    depth_req_id: i32,
+    trades_req_id: i32,
+    tick_size: f64,
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+    traded: HashMap<i64, f64>,
+    orders: HashMap<i32, WorkingOrder>,
+    last_price: Option<f64>,
+    levels: BTreeMap<i64, LadderLevel>,
+}
+
+impl PriceLadder {
+    /// # Arguments
+    /// * con_id - Contract id.  OpenOrder events for other contracts are ignored.
+    /// * depth_req_id - Request id of the req_mkt_depth() subscription
+    /// * trades_req_id - Request id of the AllLast req_tick_by_tick_data() subscription
+    /// * tick_size - Minimum price increment of the contract
+    pub fn new(con_id: i32, depth_req_id: i32, trades_req_id: i32, tick_size: f64) -> Self {
+        PriceLadder {
+            con_id,
+            depth_req_id,
+            trades_req_id,
+            tick_size,
+            bids: Vec::new(),
+            asks: Vec::new(),
+            traded: HashMap::new(),
+            orders: HashMap::new(),
+            last_price: None,
+            levels: BTreeMap::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn key(&self, price: f64) -> i64 {
+        (price / self.tick_size).round() as i64
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn price(&self, key: i64) -> f64 {
+        key as f64 * self.tick_size
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies an event and returns the changes it caused
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> Vec<LadderUpdate> {
+        let mut touched = BTreeSet::new();
+        let mut updates = Vec::new();
+        match msg {
+            ServerRspMsg::MarketDepth {
+                req_id,
+                position,
+                operation,
+                side,
+                price,
+                size,
+            }
+            | ServerRspMsg::MarketDepthL2 {
+                req_id,
+                position,
+                operation,
+                side,
+                price,
+                size,
+                ..
+            } if *req_id == self.depth_req_id => {
+                let rows = if *side == DEPTH_BID {
+                    &mut self.bids
+                } else {
+                    &mut self.asks
+                };
+                let position = *position as usize;
+                let mut changed = vec![*price];
+                match *operation {
+                    DEPTH_INSERT => rows.insert(position.min(rows.len()), (*price, *size as f64)),
+                    DEPTH_UPDATE if position < rows.len() => {
+                        changed.push(rows[position].0);
+                        rows[position] = (*price, *size as f64);
+                    }
+                    DEPTH_DELETE if position < rows.len() => {
+                        changed.push(rows.remove(position).0);
+                    }
+                    _ => (),
+                }
+                touched.extend(changed.into_iter().map(|price| self.key(price)));
+            }
+            ServerRspMsg::TickByTick {
+                req_id,
+                tick: TickByTickData::Last { price, size, .. },
+                ..
+            } if *req_id == self.trades_req_id => {
+                let key = self.key(*price);
+                *self.traded.entry(key).or_insert(0.0) += *size as f64;
+                touched.insert(key);
+                if self.last_price != Some(*price) {
+                    self.last_price = Some(*price);
+                    updates.push(LadderUpdate::LastPrice(*price));
+                }
+            }
+            ServerRspMsg::OpenOrder {
+                order_id,
+                contract,
+                order,
+                ..
+            } if contract.con_id == self.con_id => {
+                let price = if order.lmt_price != UNSET_DOUBLE {
+                    order.lmt_price
+                } else {
+                    order.aux_price
+                };
+                if price != UNSET_DOUBLE {
+                    let remaining = self
+                        .orders
+                        .get(order_id)
+                        .map_or(order.total_quantity, |working| working.remaining);
+                    if let Some(previous) = self.orders.insert(
+                        *order_id,
+                        WorkingOrder {
+                            is_buy: order.action == "BUY",
+                            price,
+                            remaining,
+                        },
+                    ) {
+                        touched.insert(self.key(previous.price));
+                    }
+                    touched.insert(self.key(price));
+                }
+            }
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                remaining,
+                ..
+            } => {
+                if status.is_terminal() {
+                    if let Some(working) = self.orders.remove(order_id) {
+                        touched.insert(self.key(working.price));
+                    }
+                } else if let Some(working) = self.orders.get_mut(order_id) {
+                    working.remaining = *remaining;
+                    let key = (working.price / self.tick_size).round() as i64;
+                    touched.insert(key);
+                }
+            }
+            _ => (),
+        }
+        for key in touched {
+            if let Some(level) = self.refresh(key) {
+                updates.push(LadderUpdate::Level(level));
+            }
+        }
+        updates
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Recomputes a level and returns it if it changed
+    fn refresh(&mut self, key: i64) -> Option<LadderLevel> {
+        let sum = |rows: &[(f64, f64)]| -> f64 {
+            rows.iter()
+                .filter(|(price, _)| self.key(*price) == key)
+                .map(|(_, size)| size)
+                .sum()
+        };
+        let working = |is_buy: bool| -> f64 {
+            self.orders
+                .values()
+                .filter(|order| order.is_buy == is_buy && self.key(order.price) == key)
+                .map(|order| order.remaining)
+                .sum()
+        };
+        let level = LadderLevel {
+            price: self.price(key),
+            bid_size: sum(&self.bids),
+            ask_size: sum(&self.asks),
+            traded_volume: self.traded.get(&key).copied().unwrap_or(0.0),
+            working_buy: working(true),
+            working_sell: working(false),
+        };
+
+        let previous = self.levels.get(&key);
+        if previous == Some(&level) || (previous.is_none() && level.is_empty()) {
+            return None;
+        }
+        if level.is_empty() {
+            self.levels.remove(&key);
+        } else {
+            self.levels.insert(key, level.clone());
+        }
+        Some(level)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn last_price(&self) -> Option<f64> {
+        self.last_price
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|(price, _)| *price)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|(price, _)| *price)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Every non-empty level, highest price first
+    pub fn levels(&self) -> Vec<LadderLevel> {
+        self.levels.values().rev().cloned().collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// `rows` consecutive levels, empty ones included, centred on `price`, highest price first.
+    /// Use it to draw the visible part of the ladder.
+    pub fn window(&self, price: f64, rows: usize) -> Vec<LadderLevel> {
+        let top = self.key(price) + (rows / 2) as i64;
+        (0..rows as i64)
+            .map(|row| {
+                let key = top - row;
+                self.levels.get(&key).cloned().unwrap_or(LadderLevel {
+                    price: self.price(key),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Clears traded volume, for example at the start of a session
+    pub fn reset_volume(&mut self) -> Vec<LadderUpdate> {
+        let keys: Vec<i64> = self.traded.drain().map(|(key, _)| key).collect();
+        keys.into_iter()
+            .filter_map(|key| self.refresh(key))
+            .map(LadderUpdate::Level)
+            .collect()
+    }
+}
@@ -0,0 +1,224 @@
+//! Market rules: the price increments that apply to an instrument on an exchange, used to snap
+//! order prices to valid ticks before submission.
+//!
+//! ContractDetails lists one market rule id per valid exchange.  ReqMarketRule returns the price
+//! bands of a rule: from each band's low edge up to the next one, prices must be a multiple of
+//! the band's increment above the low edge.
+use std::collections::HashMap;
+#[cfg(feature = "client-sync")]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client-sync")]
+use crate::core::client::{Collected, EClient};
+use crate::core::common::PriceIncrement;
+use crate::core::contract::ContractDetails;
+#[cfg(feature = "client-sync")]
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+
+/// Rounding noise tolerated when checking whether a price is already on a tick
+const TICK_EPSILON: f64 = 1e-9;
+/// Decimals kept when removing floating point noise from a rounded price
+const PRICE_DECIMALS: i32 = 8;
+
+//==================================================================================================
+/// Which way a price is snapped to a valid tick
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceSide {
+    /// Rounds down, so a buy limit never pays more than asked for
+    Buy,
+    /// Rounds up, so a sell limit never receives less than asked for
+    Sell,
+}
+
+//==================================================================================================
+/// The price bands of one market rule
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PriceRule {
+    pub market_rule_id: i32,
+    /// Sorted by low edge
+    pub increments: Vec<PriceIncrement>,
+}
+
+impl PriceRule {
+    pub fn new(market_rule_id: i32, mut increments: Vec<PriceIncrement>) -> Self {
+        increments.sort_by(|a, b| a.low_edge.total_cmp(&b.low_edge));
+        PriceRule {
+            market_rule_id,
+            increments,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Index of the band a price falls in.  Prices below the first low edge use the first band.
+    fn band(&self, price: f64) -> Option<usize> {
+        if self.increments.is_empty() {
+            return None;
+        }
+        Some(
+            self.increments
+                .iter()
+                .rposition(|band| band.low_edge <= price + TICK_EPSILON)
+                .unwrap_or(0),
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The smallest price increment at a price, or None if the rule has no bands.  Negative
+    /// prices use the increment of their absolute value.
+    pub fn min_increment_at(&self, price: f64) -> Option<f64> {
+        self.band(price.abs())
+            .map(|index| self.increments[index].increment)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Snaps a price to a valid tick, rounding down for buys and up for sells.  Prices already
+    /// on a tick are returned unchanged, and a rule without bands leaves every price unchanged.
+    ///
+    /// # Arguments
+    /// * price - The limit or stop price
+    /// * side - Which way to round
+    pub fn round_to_valid_tick(&self, price: f64, side: PriceSide) -> f64 {
+        if price < 0.0 {
+            // rounding a negative price down is rounding its absolute value up
+            let mirrored = match side {
+                PriceSide::Buy => PriceSide::Sell,
+                PriceSide::Sell => PriceSide::Buy,
+            };
+            return -self.round_to_valid_tick(-price, mirrored);
+        }
+        let index = match self.band(price) {
+            Some(index) => index,
+            None => return price,
+        };
+        let band = &self.increments[index];
+        if band.increment <= 0.0 {
+            return price;
+        }
+
+        let steps = (price - band.low_edge) / band.increment;
+        let steps = match side {
+            PriceSide::Buy => (steps + TICK_EPSILON).floor(),
+            PriceSide::Sell => (steps - TICK_EPSILON).ceil(),
+        };
+        let mut rounded = band.low_edge + steps.max(0.0) * band.increment;
+        // rounding up may cross into the next band, whose low edge is always a valid tick
+        if let Some(next) = self.increments.get(index + 1) {
+            rounded = rounded.min(next.low_edge);
+        }
+        round_decimals(rounded)
+    }
+}
+
+//==================================================================================================
+fn round_decimals(price: f64) -> f64 {
+    let scale = 10f64.powi(PRICE_DECIMALS);
+    (price * scale).round() / scale
+}
+
+//==================================================================================================
+/// The market rule id of a contract on an exchange, taken from the rule ids ContractDetails
+/// lists in the order of its valid exchanges
+///
+/// # Arguments
+/// * details - The contract details
+/// * exchange - One of the valid exchanges, compared case-insensitively
+pub fn market_rule_id_for(details: &ContractDetails, exchange: &str) -> Option<i32> {
+    details
+        .valid_exchanges
+        .split(',')
+        .position(|valid| valid.trim().eq_ignore_ascii_case(exchange))
+        .and_then(|index| details.market_rule_ids.split(',').nth(index))
+        .and_then(|id| id.trim().parse().ok())
+}
+
+//==================================================================================================
+/// Market rules cached by id.  Rules rarely change, so each one only needs to be requested once
+/// per session.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PriceRules {
+    rules: HashMap<i32, PriceRule>,
+}
+
+impl PriceRules {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn insert(&mut self, rule: PriceRule) {
+        self.rules.insert(rule.market_rule_id, rule);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn get(&self, market_rule_id: i32) -> Option<&PriceRule> {
+        self.rules.get(&market_rule_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Caches the rule of a MarketRule event.  Returns false for other events.
+    pub fn update(&mut self, msg: &ServerRspMsg) -> bool {
+        match msg {
+            ServerRspMsg::MarketRule {
+                market_rule_id,
+                price_increments,
+            } => {
+                self.insert(PriceRule::new(*market_rule_id, price_increments.clone()));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The smallest increment at a price under a cached rule
+    pub fn min_increment_at(&self, market_rule_id: i32, price: f64) -> Option<f64> {
+        self.get(market_rule_id)
+            .and_then(|rule| rule.min_increment_at(price))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Snaps a price to a valid tick under a cached rule.  None if the rule is not cached.
+    pub fn round_to_valid_tick(
+        &self,
+        market_rule_id: i32,
+        price: f64,
+        side: PriceSide,
+    ) -> Option<f64> {
+        self.get(market_rule_id)
+            .map(|rule| rule.round_to_valid_tick(price, side))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a cached rule, requesting it and waiting for it if it is not cached yet
+    ///
+    /// # Arguments
+    /// * client - A connected client
+    /// * market_rule_id - The rule to get
+    /// * timeout - How long to wait for TWS to send the rule
+    #[cfg(feature = "client-sync")]
+    pub fn fetch(
+        &mut self,
+        client: &mut EClient,
+        market_rule_id: i32,
+        timeout: Duration,
+    ) -> Result<&PriceRule, IBKRApiLibError> {
+        if !self.rules.contains_key(&market_rule_id) {
+            client.req_market_rule(market_rule_id)?;
+            client.collect(timeout, |msg| match msg {
+                ServerRspMsg::MarketRule {
+                    market_rule_id: id, ..
+                } if *id == market_rule_id => {
+                    self.update(msg);
+                    Ok(Collected::Finished)
+                }
+                _ => Ok(Collected::Ignored),
+            })?;
+        }
+        self.get(market_rule_id).ok_or_else(|| {
+            IBKRApiLibError::Config(format!("market rule {} was not received", market_rule_id))
+        })
+    }
+}
@@ -0,0 +1,363 @@
+//! Connecting to TWS or IB Gateway through a SOCKS5 or HTTP CONNECT proxy
+//!
+//! Set EClientConfig::proxy() and EClient::connect() opens the tunnel before the API handshake.
+//! Host names are resolved by the proxy, so the Gateway only has to be reachable from it.
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::core::errors::IBKRApiLibError;
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_NO_AUTH: u8 = 0;
+const SOCKS_USERNAME_PASSWORD: u8 = 2;
+const SOCKS_CONNECT: u8 = 1;
+const SOCKS_IPV4: u8 = 1;
+const SOCKS_DOMAIN: u8 = 3;
+const SOCKS_IPV6: u8 = 4;
+/// Longest HTTP response header accepted from the proxy
+const MAX_HTTP_HEADER: usize = 8192;
+/// How long connect() waits for the proxy to accept the connection and for each step of the
+/// handshake
+pub const PROXY_TIMEOUT: Duration = Duration::from_secs(10);
+
+//==================================================================================================
+/// User name and password sent to the proxy
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+//==================================================================================================
+/// A proxy that EClient::connect() tunnels through
+#[derive(Clone, Debug, PartialEq)]
+pub enum Proxy {
+    /// A SOCKS5 proxy (RFC 1928), with optional username/password authentication (RFC 1929)
+    Socks5 {
+        /// host:port of the proxy
+        address: String,
+        credentials: Option<ProxyCredentials>,
+    },
+    /// An HTTP proxy that accepts CONNECT, with optional basic authentication
+    Http {
+        /// host:port of the proxy
+        address: String,
+        credentials: Option<ProxyCredentials>,
+    },
+}
+
+impl Proxy {
+    /// # Arguments
+    /// * address - host:port of the SOCKS5 proxy
+    pub fn socks5(address: &str) -> Self {
+        Proxy::Socks5 {
+            address: address.to_string(),
+            credentials: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// # Arguments
+    /// * address - host:port of the HTTP proxy
+    pub fn http(address: &str) -> Self {
+        Proxy::Http {
+            address: address.to_string(),
+            credentials: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Authenticates with the proxy
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        let value = Some(ProxyCredentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        match &mut self {
+            Proxy::Socks5 { credentials, .. } | Proxy::Http { credentials, .. } => {
+                *credentials = value
+            }
+        }
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn address(&self) -> &str {
+        match self {
+            Proxy::Socks5 { address, .. } | Proxy::Http { address, .. } => address,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Opens a connection to the proxy and asks it for a tunnel to host:port, giving up after
+    /// PROXY_TIMEOUT
+    pub fn connect(&self, host: &str, port: u32) -> Result<TcpStream, IBKRApiLibError> {
+        self.connect_timeout(host, port, PROXY_TIMEOUT)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Like connect(), but gives up if connecting to the proxy, or any read or write of the
+    /// handshake, takes longer than `timeout`.  The returned stream has no timeouts set.
+    pub fn connect_timeout(
+        &self,
+        host: &str,
+        port: u32,
+        timeout: Duration,
+    ) -> Result<TcpStream, IBKRApiLibError> {
+        debug!(
+            proxy = self.address(),
+            host, port, "connecting through proxy"
+        );
+        let port = u16::try_from(port)
+            .map_err(|_| IBKRApiLibError::Config(format!("Port {} is out of range", port)))?;
+        let mut stream = open_stream(self.address(), timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        match self {
+            Proxy::Socks5 { credentials, .. } => {
+                socks5_connect(&mut stream, host, port, credentials.as_ref())?
+            }
+            Proxy::Http { credentials, .. } => {
+                http_connect(&mut stream, host, port, credentials.as_ref())?
+            }
+        }
+        // the Reader blocks on the tunnel for as long as the connection is open
+        stream.set_read_timeout(None)?;
+        stream.set_write_timeout(None)?;
+        Ok(stream)
+    }
+}
+
+//==================================================================================================
+/// Connects to the first address of the proxy that answers within `timeout`
+fn open_stream(address: &str, timeout: Duration) -> Result<TcpStream, IBKRApiLibError> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(IBKRApiLibError::Io(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            format!("Proxy address {} did not resolve", address),
+        )
+    })))
+}
+
+//==================================================================================================
+fn proxy_error(kind: ErrorKind, msg: String) -> IBKRApiLibError {
+    IBKRApiLibError::Io(io::Error::new(kind, msg))
+}
+
+//==================================================================================================
+/// Performs the SOCKS5 handshake on an open connection to the proxy
+fn socks5_connect<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<(), IBKRApiLibError> {
+    let method = match credentials {
+        Some(_) => SOCKS_USERNAME_PASSWORD,
+        None => SOCKS_NO_AUTH,
+    };
+    stream.write_all(&[SOCKS_VERSION, 1, method])?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(proxy_error(
+            ErrorKind::InvalidData,
+            format!("Proxy answered with SOCKS version {}", reply[0]),
+        ));
+    }
+    if reply[1] != method {
+        return Err(proxy_error(
+            ErrorKind::PermissionDenied,
+            "SOCKS5 proxy refused the authentication method".to_string(),
+        ));
+    }
+
+    if let Some(credentials) = credentials {
+        let username = credentials.username.as_bytes();
+        let password = credentials.password.as_bytes();
+        if username.len() > 255 || password.len() > 255 {
+            return Err(IBKRApiLibError::Config(
+                "SOCKS5 username and password are limited to 255 bytes".to_string(),
+            ));
+        }
+        let mut auth = vec![1, username.len() as u8];
+        auth.extend_from_slice(username);
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password);
+        stream.write_all(&auth)?;
+        stream.read_exact(&mut reply)?;
+        if reply[1] != 0 {
+            return Err(proxy_error(
+                ErrorKind::PermissionDenied,
+                "SOCKS5 proxy rejected the credentials".to_string(),
+            ));
+        }
+    }
+
+    let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(SOCKS_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(SOCKS_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            if host.len() > 255 {
+                return Err(IBKRApiLibError::Config(format!(
+                    "Host name {} is too long for SOCKS5",
+                    host
+                )));
+            }
+            request.push(SOCKS_DOMAIN);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)?;
+    if header[1] != 0 {
+        return Err(proxy_error(
+            ErrorKind::ConnectionRefused,
+            format!(
+                "SOCKS5 proxy could not connect to {}:{}: {}",
+                host,
+                port,
+                socks5_reply(header[1])
+            ),
+        ));
+    }
+    // the address the proxy bound is not needed, but must be read past
+    let bound = match header[3] {
+        SOCKS_IPV4 => 4,
+        SOCKS_IPV6 => 16,
+        SOCKS_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        other => {
+            return Err(proxy_error(
+                ErrorKind::InvalidData,
+                format!("SOCKS5 proxy sent unknown address type {}", other),
+            ))
+        }
+    };
+    let mut rest = vec![0u8; bound + 2];
+    stream.read_exact(&mut rest)?;
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------
+fn socks5_reply(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+//==================================================================================================
+/// Asks an HTTP proxy for a tunnel with CONNECT on an open connection to the proxy
+fn http_connect<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<(), IBKRApiLibError> {
+    let authority = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => format!("[{}]:{}", host, port),
+        _ => format!("{}:{}", host, port),
+    };
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some(credentials) = credentials {
+        let token = base64(format!("{}:{}", credentials.username, credentials.password).as_bytes());
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+
+    // read byte by byte so nothing after the header is taken from the tunnel
+    let mut header = Vec::new();
+    let mut byte = [0u8; 1];
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_HTTP_HEADER {
+            return Err(proxy_error(
+                ErrorKind::InvalidData,
+                "HTTP proxy response header is too long".to_string(),
+            ));
+        }
+        stream.read_exact(&mut byte)?;
+        header.push(byte[0]);
+    }
+    let header = String::from_utf8_lossy(&header);
+    let status_line = header.lines().next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    let status = parts.next().unwrap_or_default();
+    if !version.starts_with("HTTP/") {
+        return Err(proxy_error(
+            ErrorKind::InvalidData,
+            format!("Not an HTTP response: {}", status_line),
+        ));
+    }
+    if !status.starts_with('2') {
+        let kind = match status {
+            "407" => ErrorKind::PermissionDenied,
+            _ => ErrorKind::ConnectionRefused,
+        };
+        return Err(proxy_error(
+            kind,
+            format!(
+                "HTTP proxy could not connect to {}: {}",
+                authority, status_line
+            ),
+        ));
+    }
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------
+/// Standard base64 with padding, for the Proxy-Authorization header
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
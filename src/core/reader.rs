@@ -1,15 +1,19 @@
 //! Reads and processes messages from the TCP socket
-use std::io::Read;
+use std::io::ErrorKind;
 use std::net::Shutdown;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Instant;
 
-use log::*;
+use tracing::{debug, error, info};
 
 use super::streamer::Streamer;
+use crate::core::buffer_pool::BufferPool;
 use crate::core::errors::IBKRApiLibError;
-use crate::core::messages::read_msg;
+use crate::core::frame_reader::FrameReader;
+#[cfg(feature = "metrics")]
+use crate::core::metrics;
 
 //==================================================================================================
 pub struct Reader {
@@ -17,6 +21,13 @@ pub struct Reader {
     messages: Sender<String>,
     disconnect_requested: Arc<AtomicBool>,
     is_connected: bool,
+    /// Bytes read but not yet sent to `messages`.  A frame split over several reads stays here
+    /// until it is complete.
+    frames: FrameReader,
+    /// Frames are copied into buffers from this pool, which the decoder gives back
+    pub(crate) buffers: BufferPool,
+    /// Receives the read time of every frame sent to `messages`, when latency is measured
+    pub(crate) read_times: Option<Sender<Instant>>,
 }
 
 impl Reader {
@@ -30,80 +41,96 @@ impl Reader {
             messages,
             disconnect_requested,
             is_connected: true,
+            frames: FrameReader::new(),
+            buffers: BufferPool::new(),
+            read_times: None,
         }
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn recv_packet(&mut self) -> Result<Vec<u8>, IBKRApiLibError> {
-        //debug!("_recv_all_msg");
-        let buf = self._recv_all_msg()?;
+    /// Reads once from the socket into the frame buffer.  Returns the number of bytes read.
+    fn fill(&mut self) -> Result<usize, IBKRApiLibError> {
+        let bytes_read = loop {
+            match self.frames.read_from(&mut *self.stream) {
+                Ok(bytes_read) => break bytes_read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    // nothing more can be read, as when the socket is closed
+                    self.is_connected = false;
+                    return Err(err.into());
+                }
+            }
+        };
+        #[cfg(feature = "metrics")]
+        metrics::bytes_received(bytes_read);
         // receiving 0 bytes outside a timeout means the connection is either
         // closed or broken
-        if buf.len() == 0 {
+        if bytes_read == 0 {
             if !self.disconnect_requested.load(Ordering::Acquire) {
                 info!("socket either closed or broken, disconnecting");
                 self.stream.shutdown(Shutdown::Both)?;
-                self.is_connected = false;
             }
+            self.is_connected = false;
         }
-        Ok(buf)
+        Ok(bytes_read)
     }
 
     //----------------------------------------------------------------------------------------------
-    fn _recv_all_msg(&mut self) -> Result<Vec<u8>, IBKRApiLibError> {
-        let mut cont = true;
-        let mut allbuf: Vec<u8> = Vec::new();
-        const NUM_BYTES: usize = 4096;
-
-        while cont {
-            let mut buf: [u8; NUM_BYTES] = [0; NUM_BYTES];
-
-            let bytes_read = self
-                .stream
-                .read(&mut buf)
-                .expect("Couldnt read from reader...");
-            allbuf.extend_from_slice(&buf[0..bytes_read]);
-            //logger.debug("len %d raw:%s|", len(buf), buf)
+    /// The next frame, or None if there is none left to split off
+    fn next_frame(&mut self) -> Result<Option<String>, IBKRApiLibError> {
+        match self.frames.next_frame() {
+            Ok(Some(frame)) => Ok(Some(self.buffers.take_string(frame))),
+            Ok(None) => Ok(None),
+            Err(err) => {
+                // the stream cannot be split after a corrupt size prefix
+                error!("{}, disconnecting", err);
+                self.stream.shutdown(Shutdown::Both)?;
+                self.is_connected = false;
+                Err(err)
+            }
+        }
+    }
 
-            if bytes_read < NUM_BYTES {
-                cont = false;
+    //----------------------------------------------------------------------------------------------
+    /// The next message, reading from the socket until a frame is complete.  None once the
+    /// connection is closed.  Used for the handshake, before run() takes over.
+    pub fn next_message(&mut self) -> Result<Option<String>, IBKRApiLibError> {
+        loop {
+            if let Some(msg) = self.next_frame()? {
+                return Ok(Some(msg));
+            }
+            if !self.is_connected || self.fill()? == 0 {
+                return Ok(None);
             }
         }
-        Ok(allbuf)
     }
 
     //----------------------------------------------------------------------------------------------
     fn process_reader_msgs(&mut self) -> Result<(), IBKRApiLibError> {
         // grab a packet of messages from the socket
-        let mut message_packet = self.recv_packet()?;
-        //debug!(" recvd size {}", message_packet.len());
-
-        // Read messages from the packet until there are no more.
-        // When this loop ends, break into the outer loop and grab another packet.
-        // Repeat until the connection is closed
-        //
-        let _msg = String::new();
-        while message_packet.len() > 0 {
-            // Read a message from the packet then add it to the message queue below.
-            let (_size, msg, remaining_messages) = read_msg(message_packet.as_slice())?;
-
-            // clear the Vec that holds the bytes from the packet
-            // and reload with the bytes that haven't been read.
-            // The variable remaining_messages only holds the unread messagesleft in the packet
-            message_packet.clear();
-            message_packet.extend_from_slice(remaining_messages.as_slice());
-
-            if msg.as_str() != "" {
-                self.messages.send(msg).expect("READER CANNOT SEND MESSAGE");
-            } else {
-                //Break to the outer loop in run and get another packet of messages.
+        let result = self.fill();
+        let read = Instant::now();
 
-                debug!("more incoming packet(s) are needed ");
-                break;
+        // Send every message the packet completed.  A partial one stays in the frame buffer
+        // until the next packet.
+        while let Some(msg) = self.next_frame()? {
+            if msg.is_empty() {
+                continue;
+            }
+            // stop sending read times once nothing receives them
+            if let Some(read_times) = &self.read_times {
+                if read_times.send(read).is_err() {
+                    self.read_times = None;
+                }
             }
+            self.messages.send(msg).expect("READER CANNOT SEND MESSAGE");
         }
-        Ok(())
+        if self.frames.buffered() > 0 {
+            debug!("more incoming packet(s) are needed ");
+        }
+        result.map(|_| ())
     }
+
     //----------------------------------------------------------------------------------------------
     pub fn run(&mut self) {
         debug!("starting reader loop");
@@ -0,0 +1,264 @@
+//! Request id allocation and the registry that ties responses back to their requests
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::MarketDataMode;
+use crate::core::errors::is_informational;
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// Hands out unique request ids.  Clones share the same counter, so ids can be taken from any
+/// thread.
+#[derive(Clone, Debug)]
+pub struct ReqIdAllocator {
+    next: Arc<AtomicI32>,
+}
+
+impl ReqIdAllocator {
+    /// # Arguments
+    /// * first - The first id handed out.  Keep request ids apart from order ids, which TWS
+    ///   also reports in ErrMsg::req_id.
+    pub fn new(first: i32) -> Self {
+        ReqIdAllocator {
+            next: Arc::new(AtomicI32::new(first)),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn next(&self) -> i32 {
+        self.next.fetch_add(1, Ordering::SeqCst)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reserves `count` consecutive ids and returns the first
+    pub fn next_block(&self, count: i32) -> i32 {
+        self.next.fetch_add(count.max(1), Ordering::SeqCst)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id the next call to next() will return
+    pub fn peek(&self) -> i32 {
+        self.next.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for ReqIdAllocator {
+    fn default() -> Self {
+        ReqIdAllocator::new(1)
+    }
+}
+
+//==================================================================================================
+/// The kind of request a req_id was used for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    MarketData,
+    TickByTick,
+    MarketDepth,
+    HistoricalData,
+    HistoricalTicks,
+    HeadTimestamp,
+    HistogramData,
+    RealTimeBars,
+    Scanner,
+    ContractDetails,
+    FundamentalData,
+    OptionParameters,
+    AccountSummary,
+    PositionsMulti,
+    AccountUpdatesMulti,
+    Pnl,
+    PnlSingle,
+    Executions,
+    ExerciseOptions,
+    News,
+    DisplayGroups,
+    SoftDollarTiers,
+    OptionCalculation,
+    SmartComponents,
+    Other(String),
+}
+
+impl Display for RequestKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            RequestKind::Other(name) => write!(f, "{}", name),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+//==================================================================================================
+/// What a req_id was used for
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    pub req_id: i32,
+    pub kind: RequestKind,
+    /// Free text supplied when the id was registered, for example the contract
+    pub description: String,
+    pub issued_at: Instant,
+    /// Set when the request was cancelled.  Responses that arrive afterwards are stale.
+    pub cancelled_at: Option<Instant>,
+}
+
+impl RequestContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled_at.is_some()
+    }
+}
+
+//==================================================================================================
+/// A response together with the request it answers, if that request was registered
+#[derive(Debug)]
+pub struct EnrichedEvent {
+    pub msg: ServerRspMsg,
+    pub context: Option<RequestContext>,
+    /// The mode of the market data subscription the event belongs to, as last reported by TWS
+    pub market_data_mode: Option<MarketDataMode>,
+}
+
+//==================================================================================================
+/// Maps req_ids to the requests that used them.  Clones share the same map.
+///
+/// Entries of one-shot requests are removed when their final message arrives, or when TWS
+/// reports an error for them.  Cancelled entries are kept so late responses can be recognised
+/// as stale; prune() drops them once no more can arrive.
+#[derive(Clone, Debug, Default)]
+pub struct CorrelationRegistry {
+    entries: Arc<Mutex<HashMap<i32, RequestContext>>>,
+}
+
+impl CorrelationRegistry {
+    pub fn new() -> Self {
+        CorrelationRegistry::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn lock(&self) -> MutexGuard<'_, HashMap<i32, RequestContext>> {
+        // the map holds no invariants a panicking thread could break
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn register(&self, req_id: i32, kind: RequestKind, description: &str) {
+        self.lock().insert(
+            req_id,
+            RequestContext {
+                req_id,
+                kind,
+                description: description.to_string(),
+                issued_at: Instant::now(),
+                cancelled_at: None,
+            },
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks a request as cancelled.  Unregistered ids are ignored.
+    pub fn cancel(&self, req_id: i32) {
+        if let Some(context) = self.lock().get_mut(&req_id) {
+            context.cancelled_at.get_or_insert_with(Instant::now);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes a request, for example once its response is complete
+    pub fn complete(&self, req_id: i32) -> Option<RequestContext> {
+        self.lock().remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn context(&self, req_id: i32) -> Option<RequestContext> {
+        self.lock().get(&req_id).cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_stale(&self, req_id: i32) -> bool {
+        matches!(self.lock().get(&req_id), Some(context) if context.is_cancelled())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Registered requests that have not been cancelled
+    pub fn active(&self) -> Vec<RequestContext> {
+        let mut active: Vec<RequestContext> = self
+            .lock()
+            .values()
+            .filter(|context| !context.is_cancelled())
+            .cloned()
+            .collect();
+        active.sort_by_key(|context| context.req_id);
+        active
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Drops requests cancelled more than `max_age` ago
+    pub fn prune(&self, max_age: Duration) {
+        let now = Instant::now();
+        self.lock().retain(|_, context| match context.cancelled_at {
+            Some(at) => now.duration_since(at) < max_age,
+            None => true,
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Attaches the request context to a response.  Returns None if the response is stale.
+    pub fn enrich(&self, msg: ServerRspMsg) -> Option<EnrichedEvent> {
+        let req_id = match msg.req_id() {
+            Some(req_id) => req_id,
+            None => {
+                return Some(EnrichedEvent {
+                    msg,
+                    context: None,
+                    market_data_mode: None,
+                })
+            }
+        };
+        let context = self.context(req_id);
+        if matches!(&context, Some(context) if context.is_cancelled()) {
+            return None;
+        }
+        let finished = match &msg {
+            ServerRspMsg::ErrMsg { error_code, .. } => !is_informational(*error_code),
+            msg => ends_request(msg),
+        };
+        if finished && context.is_some() {
+            self.complete(req_id);
+        }
+        Some(EnrichedEvent {
+            msg,
+            context,
+            market_data_mode: None,
+        })
+    }
+}
+
+//==================================================================================================
+/// True for the last message of a one-shot request
+fn ends_request(msg: &ServerRspMsg) -> bool {
+    match msg {
+        ServerRspMsg::ContractDataEnd { .. }
+        | ServerRspMsg::ExecutionDataEnd { .. }
+        | ServerRspMsg::TickSnapshotEnd { .. }
+        | ServerRspMsg::FundamentalData { .. }
+        | ServerRspMsg::SecurityDefinitionOptionParameterEnd { .. }
+        | ServerRspMsg::SymbolSamples { .. }
+        | ServerRspMsg::SoftDollarTiers { .. }
+        | ServerRspMsg::SmartComponents { .. }
+        | ServerRspMsg::NewsArticle { .. }
+        | ServerRspMsg::HistoricalNewsEnd { .. }
+        | ServerRspMsg::HeadTimestamp { .. }
+        | ServerRspMsg::HistogramData { .. }
+        | ServerRspMsg::HistoricalDataEnd { .. } => true,
+        ServerRspMsg::HistoricalTicks { done, .. }
+        | ServerRspMsg::HistoricalTicksBidAsk { done, .. }
+        | ServerRspMsg::HistoricalTicksLast { done, .. } => *done,
+        _ => false,
+    }
+}
@@ -0,0 +1,441 @@
+//! Pre-trade checks of portfolio exposure: gross and net limits, concentration caps and limits
+//! on groups of correlated symbols
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::core::account_values::AccountKey;
+use crate::core::client::EClient;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::Order;
+use crate::core::units::{Notional, Percent, Price, Size};
+
+//==================================================================================================
+/// Symbols whose exposure is capped together, for example highly correlated names
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExposureGroup {
+    pub name: String,
+    pub symbols: Vec<String>,
+    /// Cap on the absolute sum of the signed exposures of the symbols
+    pub max_exposure: Notional,
+}
+
+//==================================================================================================
+/// Portfolio limits in the base currency.  Limits left at None are not checked.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PortfolioLimits {
+    /// Sum of the absolute exposures of all positions
+    pub max_gross: Option<Notional>,
+    /// Absolute value of the sum of the signed exposures
+    pub max_net: Option<Notional>,
+    /// Absolute exposure of one symbol, as a percentage of net liquidation
+    pub max_symbol_concentration: Option<Percent>,
+    /// Absolute exposure of one sector, as a percentage of net liquidation
+    pub max_sector_concentration: Option<Percent>,
+    /// Sector of each symbol.  Symbols without one are not checked against the sector cap.
+    pub sectors: HashMap<String, String>,
+    pub groups: Vec<ExposureGroup>,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RiskViolation {
+    Gross {
+        exposure: Notional,
+        limit: Notional,
+    },
+    Net {
+        exposure: Notional,
+        limit: Notional,
+    },
+    SymbolConcentration {
+        symbol: String,
+        share: Percent,
+        limit: Percent,
+    },
+    SectorConcentration {
+        sector: String,
+        share: Percent,
+        limit: Percent,
+    },
+    Group {
+        group: String,
+        exposure: Notional,
+        limit: Notional,
+    },
+    /// Concentration caps need net liquidation, which has not been received or set
+    UnknownCapital,
+    /// No FX rate to the base currency is known for this currency
+    UnknownFxRate {
+        currency: String,
+    },
+    /// No price is known for the contract and none was given
+    UnknownPrice {
+        symbol: String,
+    },
+}
+
+impl Display for RiskViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            RiskViolation::Gross { exposure, limit } => {
+                write!(f, "gross exposure {} over limit {}", exposure, limit)
+            }
+            RiskViolation::Net { exposure, limit } => {
+                write!(f, "net exposure {} over limit {}", exposure, limit)
+            }
+            RiskViolation::SymbolConcentration {
+                symbol,
+                share,
+                limit,
+            } => write!(
+                f,
+                "{} is {} of capital, over limit {}",
+                symbol, share, limit
+            ),
+            RiskViolation::SectorConcentration {
+                sector,
+                share,
+                limit,
+            } => write!(
+                f,
+                "sector {} is {} of capital, over limit {}",
+                sector, share, limit
+            ),
+            RiskViolation::Group {
+                group,
+                exposure,
+                limit,
+            } => write!(
+                f,
+                "group {} exposure {} over limit {}",
+                group, exposure, limit
+            ),
+            RiskViolation::UnknownCapital => write!(f, "net liquidation is unknown"),
+            RiskViolation::UnknownFxRate { currency } => {
+                write!(f, "no FX rate for {}", currency)
+            }
+            RiskViolation::UnknownPrice { symbol } => write!(f, "no price for {}", symbol),
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+struct Holding {
+    symbol: String,
+    currency: String,
+    multiplier: f64,
+    quantity: f64,
+    price: Option<f64>,
+}
+
+impl Holding {
+    fn of(contract: &Contract) -> Self {
+        Holding {
+            symbol: contract.symbol.clone(),
+            currency: contract.currency.clone(),
+            multiplier: contract.multiplier.parse().unwrap_or(1.0),
+            ..Default::default()
+        }
+    }
+}
+
+/// Checks orders against portfolio limits before they are placed.
+///
+/// Positions, prices and net liquidation are kept up to date from PositionData,
+/// PortfolioValue, AcctValue and AccountSummary events passed to observe().  Exposures are
+/// converted to the base currency with rates set by set_fx_rate().  Any limit a proposed
+/// order would break, or any input missing to check one, is reported as a violation.
+///
+/// A guard covers one account.  Use one guard per account when managing several.
+#[derive(Clone, Debug)]
+pub struct RiskGuard {
+    limits: PortfolioLimits,
+    base_currency: String,
+    fx_rates: HashMap<String, f64>,
+    holdings: HashMap<i32, Holding>,
+    net_liquidation: Option<Notional>,
+}
+
+impl RiskGuard {
+    /// # Arguments
+    /// * limits - The limits to enforce
+    /// * base_currency - Currency of the limits and of net liquidation, for example USD
+    pub fn new(limits: PortfolioLimits, base_currency: &str) -> Self {
+        RiskGuard {
+            limits,
+            base_currency: base_currency.to_string(),
+            fx_rates: HashMap::new(),
+            holdings: HashMap::new(),
+            net_liquidation: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn limits(&self) -> &PortfolioLimits {
+        &self.limits
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn set_limits(&mut self, limits: PortfolioLimits) {
+        self.limits = limits;
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the value of one unit of `currency` in the base currency
+    pub fn set_fx_rate(&mut self, currency: &str, rate: f64) {
+        self.fx_rates.insert(currency.to_string(), rate);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn set_net_liquidation(&mut self, net_liquidation: Notional) {
+        self.net_liquidation = Some(net_liquidation);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the price used to value a contract, for example from market data
+    pub fn set_price(&mut self, contract: &Contract, price: Price) {
+        self.holding_mut(contract).price = Some(price.value());
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Updates positions, prices and net liquidation
+    pub fn observe(&mut self, msg: &ServerRspMsg) {
+        match msg {
+            ServerRspMsg::PositionData {
+                contract,
+                position,
+                avg_cost,
+                ..
+            } => {
+                let holding = self.holding_mut(contract);
+                holding.quantity = *position;
+                if holding.price.is_none() && *avg_cost > 0.0 {
+                    holding.price = Some(avg_cost / holding.multiplier);
+                }
+            }
+            ServerRspMsg::PortfolioValue {
+                contract,
+                position,
+                market_price,
+                ..
+            } => {
+                let holding = self.holding_mut(contract);
+                holding.quantity = *position;
+                holding.price = Some(*market_price);
+            }
+            ServerRspMsg::AcctValue {
+                key: AccountKey::NetLiquidation,
+                value,
+                ..
+            } if value.currency() == self.base_currency => {
+                if let Some(value) = value.amount().and_then(|amount| amount.to_f64()) {
+                    self.net_liquidation = Some(Notional::new(value));
+                }
+            }
+            ServerRspMsg::AccountSummary {
+                tag,
+                value,
+                currency,
+                ..
+            } if tag == "NetLiquidation" && *currency == self.base_currency => {
+                if let Ok(value) = value.parse::<f64>() {
+                    self.net_liquidation = Some(Notional::new(value));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn holding_mut(&mut self, contract: &Contract) -> &mut Holding {
+        self.holdings
+            .entry(contract.con_id)
+            .or_insert_with(|| Holding::of(contract))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Signed exposure of every symbol in the base currency
+    fn exposures(
+        &self,
+        holdings: &HashMap<i32, Holding>,
+    ) -> Result<HashMap<String, Notional>, RiskViolation> {
+        let mut exposures: HashMap<String, Notional> = HashMap::new();
+        for holding in holdings.values().filter(|holding| holding.quantity != 0.0) {
+            let price = holding.price.ok_or_else(|| RiskViolation::UnknownPrice {
+                symbol: holding.symbol.clone(),
+            })?;
+            let rate = if holding.currency == self.base_currency || holding.currency.is_empty() {
+                1.0
+            } else {
+                *self.fx_rates.get(&holding.currency).ok_or_else(|| {
+                    RiskViolation::UnknownFxRate {
+                        currency: holding.currency.clone(),
+                    }
+                })?
+            };
+            let exposure =
+                Price::new(price) * Size::new(holding.quantity) * (holding.multiplier * rate);
+            let total = exposures.entry(holding.symbol.clone()).or_default();
+            *total = *total + exposure;
+        }
+        Ok(exposures)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Every limit the portfolio would break if the order filled completely
+    ///
+    /// # Arguments
+    /// * contract - The contract of the order
+    /// * order - The order.  Its action and total_quantity are used.
+    /// * price - Expected fill price.  If None, the last known price of the contract is used.
+    pub fn violations(
+        &self,
+        contract: &Contract,
+        order: &Order,
+        price: Option<Price>,
+    ) -> Vec<RiskViolation> {
+        let mut holdings = self.holdings.clone();
+        let holding = holdings
+            .entry(contract.con_id)
+            .or_insert_with(|| Holding::of(contract));
+        let sign = if order.action == "BUY" { 1.0 } else { -1.0 };
+        holding.quantity += sign * order.total_quantity;
+        if let Some(price) = price {
+            holding.price = Some(price.value());
+        }
+
+        let exposures = match self.exposures(&holdings) {
+            Ok(exposures) => exposures,
+            Err(violation) => return vec![violation],
+        };
+        let mut violations = Vec::new();
+
+        let gross = exposures
+            .values()
+            .fold(Notional::default(), |sum, exposure| sum + exposure.abs());
+        if let Some(limit) = self.limits.max_gross {
+            if gross > limit {
+                violations.push(RiskViolation::Gross {
+                    exposure: gross,
+                    limit,
+                });
+            }
+        }
+        let net = exposures
+            .values()
+            .fold(Notional::default(), |sum, exposure| sum + *exposure)
+            .abs();
+        if let Some(limit) = self.limits.max_net {
+            if net > limit {
+                violations.push(RiskViolation::Net {
+                    exposure: net,
+                    limit,
+                });
+            }
+        }
+
+        for group in self.limits.groups.iter() {
+            let exposure = group
+                .symbols
+                .iter()
+                .filter_map(|symbol| exposures.get(symbol))
+                .fold(Notional::default(), |sum, exposure| sum + *exposure)
+                .abs();
+            if exposure > group.max_exposure {
+                violations.push(RiskViolation::Group {
+                    group: group.name.clone(),
+                    exposure,
+                    limit: group.max_exposure,
+                });
+            }
+        }
+
+        if self.limits.max_symbol_concentration.is_none()
+            && self.limits.max_sector_concentration.is_none()
+        {
+            return violations;
+        }
+        let capital = match self.net_liquidation {
+            Some(capital) if capital.value() > 0.0 => capital,
+            _ => {
+                violations.push(RiskViolation::UnknownCapital);
+                return violations;
+            }
+        };
+        let share = |exposure: Notional| Percent::from_fraction(exposure.value() / capital.value());
+
+        if let Some(limit) = self.limits.max_symbol_concentration {
+            let mut symbols: Vec<(&String, &Notional)> = exposures.iter().collect();
+            symbols.sort_by(|a, b| a.0.cmp(b.0));
+            for (symbol, exposure) in symbols {
+                let share = share(exposure.abs());
+                if share > limit {
+                    violations.push(RiskViolation::SymbolConcentration {
+                        symbol: symbol.clone(),
+                        share,
+                        limit,
+                    });
+                }
+            }
+        }
+        if let Some(limit) = self.limits.max_sector_concentration {
+            let mut sectors: HashMap<&String, Notional> = HashMap::new();
+            for (symbol, exposure) in exposures.iter() {
+                if let Some(sector) = self.limits.sectors.get(symbol) {
+                    let total = sectors.entry(sector).or_default();
+                    *total = *total + exposure.abs();
+                }
+            }
+            let mut sectors: Vec<(&String, Notional)> = sectors.into_iter().collect();
+            sectors.sort_by(|a, b| a.0.cmp(b.0));
+            for (sector, exposure) in sectors {
+                let share = share(exposure);
+                if share > limit {
+                    violations.push(RiskViolation::SectorConcentration {
+                        sector: sector.clone(),
+                        share,
+                        limit,
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns an error describing the first limit the order would break
+    pub fn check(
+        &self,
+        contract: &Contract,
+        order: &Order,
+        price: Option<Price>,
+    ) -> Result<(), IBKRApiLibError> {
+        match self.violations(contract, order, price).first() {
+            Some(violation) => Err(IBKRApiLibError::Config(format!(
+                "order refused by risk guard: {}",
+                violation
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks the order and places it if no limit would be broken
+    pub fn place_order(
+        &self,
+        client: &mut EClient,
+        order_id: i32,
+        contract: &Contract,
+        order: &Order,
+        price: Option<Price>,
+    ) -> Result<(), IBKRApiLibError> {
+        self.check(contract, order, price)?;
+        client.place_order(order_id, contract, order)
+    }
+}
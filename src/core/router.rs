@@ -0,0 +1,118 @@
+//! Routes decoded events to channels by message kind, message class and request id, so each
+//! consumer only receives the events it registered for.
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::core::event_queue::MessageClass;
+use crate::core::messages::{ServerRspMsg, ServerRspMsgKind};
+
+//==================================================================================================
+/// The events a channel receives.  Every criterion that is set must match; a route with none
+/// set matches every event.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Route {
+    pub kind: Option<ServerRspMsgKind>,
+    pub class: Option<MessageClass>,
+    /// Matches the request id of responses and of errors about a request
+    pub req_id: Option<i32>,
+}
+
+impl Route {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn kind(mut self, kind: ServerRspMsgKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn class(mut self, class: MessageClass) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn req_id(mut self, req_id: i32) -> Self {
+        self.req_id = Some(req_id);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn matches(&self, msg: &ServerRspMsg) -> bool {
+        self.kind
+            .is_none_or(|kind| ServerRspMsgKind::from(msg) == kind)
+            && self
+                .class
+                .is_none_or(|class| MessageClass::of(msg) == class)
+            && self
+                .req_id
+                .is_none_or(|req_id| msg.req_id() == Some(req_id))
+    }
+}
+
+//==================================================================================================
+/// Sends each event to the channels of the routes it matches.  Events that match no route go
+/// to the fallback channel if there is one, and are returned otherwise.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<(Route, Sender<ServerRspMsg>)>,
+    fallback: Option<Sender<ServerRspMsg>>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Registers a route and returns the channel receiving its events.  The route is removed
+    /// once the receiver is dropped.
+    pub fn subscribe(&mut self, route: Route) -> Receiver<ServerRspMsg> {
+        let (sender, receiver) = channel();
+        self.routes.push((route, sender));
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the channel receiving events that match no route, replacing any earlier one
+    pub fn fallback(&mut self) -> Receiver<ServerRspMsg> {
+        let (sender, receiver) = channel();
+        self.fallback = Some(sender);
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Number of routes whose receivers were still alive at the last event
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Delivers an event.  Returns it if it matched no route and there is no fallback channel.
+    pub fn route(&mut self, msg: ServerRspMsg) -> Option<ServerRspMsg> {
+        let mut delivered = false;
+        self.routes.retain(|(route, sender)| {
+            if !route.matches(&msg) {
+                return true;
+            }
+            let alive = sender.send(msg.clone()).is_ok();
+            delivered |= alive;
+            alive
+        });
+        if delivered {
+            return None;
+        }
+        match &self.fallback {
+            Some(fallback) => match fallback.send(msg) {
+                Ok(()) => None,
+                Err(err) => {
+                    self.fallback = None;
+                    Some(err.0)
+                }
+            },
+            None => Some(msg),
+        }
+    }
+}
@@ -0,0 +1,566 @@
+//! An event loop for small strategies, live or on a backtest
+//!
+//! A Strategy reacts to bars, ticks, order statuses, fills and timers.  A Runner reads the events
+//! of an EClient, or of a BacktestClient on its simulated clock, passes them to the strategy and
+//! fires its timers.  It stops when the strategy or a StopHandle asks it to, when the connection
+//! is lost, when a callback fails or, on a backtest, when the data runs out.  The strategy is
+//! then given on_stop() to cancel its orders, and a live client is shut down.
+//!
+#![cfg_attr(feature = "marketdata", doc = "```no_run")]
+#![cfg_attr(not(feature = "marketdata"), doc = "```ignore")]
+//! use std::time::Duration;
+//!
+//! use ibtwsapi::core::client::EClient;
+//! use ibtwsapi::core::common::BarData;
+//! use ibtwsapi::core::contract::Contract;
+//! use ibtwsapi::core::errors::IBKRApiLibError;
+//! use ibtwsapi::core::runtime::{Context, Runner, Strategy};
+//! use ibtwsapi::core::time::display_time;
+//!
+//! struct Logger;
+//!
+//! impl Strategy for Logger {
+//!     fn on_start(&mut self, ctx: &mut Context<'_>) -> Result<(), IBKRApiLibError> {
+//!         let mut contract = Contract::default();
+//!         contract.symbol = "AAPL".to_string();
+//!         contract.sec_type = "STK".to_string();
+//!         contract.exchange = "SMART".to_string();
+//!         contract.currency = "USD".to_string();
+//!         ctx.req_real_time_bars(1, &contract, 5, "TRADES", true, vec![])?;
+//!         ctx.set_timer(1, Duration::from_secs(60));
+//!         Ok(())
+//!     }
+//!
+//!     fn on_bar(&mut self, _: &mut Context<'_>, _: i32, bar: &BarData) -> Result<(), IBKRApiLibError> {
+//!         println!("{} {}", display_time(&bar.date), bar.close);
+//!         Ok(())
+//!     }
+//!
+//!     fn on_timer(&mut self, ctx: &mut Context<'_>, _: i32) -> Result<(), IBKRApiLibError> {
+//!         ctx.stop();
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), IBKRApiLibError> {
+//! let mut client = EClient::new();
+//! client.connect("127.0.0.1", 4002, 0)?;
+//! Runner::new().run_live(&mut client, &mut Logger)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use tracing::{info, warn};
+
+#[cfg(feature = "backtest")]
+use crate::core::backtest::BacktestClient;
+use crate::core::client::EClient;
+use crate::core::common::{BarData, TickByTickData, TickType};
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::execution::Execution;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::OrderStatusKind;
+use crate::core::shutdown::{ShutdownOptions, ShutdownReport};
+use crate::core::time::parse_tws_time;
+
+//==================================================================================================
+/// A market data update passed to Strategy::on_tick()
+#[derive(Clone, Debug)]
+pub enum Tick {
+    Price {
+        tick_type: TickType,
+        price: f64,
+    },
+    Size {
+        tick_type: TickType,
+        size: i32,
+    },
+    /// A tick by tick update, with its time in seconds since the epoch
+    ByTick {
+        time: i64,
+        data: TickByTickData,
+    },
+}
+
+//==================================================================================================
+/// The callbacks of a strategy.  Every callback has an empty default.  An Err stops the runner,
+/// which returns it after on_stop() and the shutdown.
+pub trait Strategy {
+    /// Called before the first event, to make the requests the strategy needs
+    fn on_start(&mut self, _ctx: &mut Context<'_>) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// A historical bar, a historical bar update or a real time bar
+    fn on_bar(
+        &mut self,
+        _ctx: &mut Context<'_>,
+        _req_id: i32,
+        _bar: &BarData,
+    ) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// A price or size of req_mkt_data(), or an update of req_tick_by_tick_data()
+    fn on_tick(
+        &mut self,
+        _ctx: &mut Context<'_>,
+        _req_id: i32,
+        _tick: &Tick,
+    ) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    fn on_order_status(
+        &mut self,
+        _ctx: &mut Context<'_>,
+        _order_id: i32,
+        _status: &OrderStatusKind,
+        _filled: f64,
+        _remaining: f64,
+        _avg_fill_price: f64,
+    ) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// An execution.  Its commission follows as a CommissionReport passed to on_event().
+    fn on_fill(
+        &mut self,
+        _ctx: &mut Context<'_>,
+        _contract: &Contract,
+        _execution: &Execution,
+    ) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// A timer set with Context::set_timer() or Context::set_interval() is due
+    fn on_timer(&mut self, _ctx: &mut Context<'_>, _timer_id: i32) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// Every event not passed to another callback, such as errors and commission reports
+    fn on_event(
+        &mut self,
+        _ctx: &mut Context<'_>,
+        _msg: &ServerRspMsg,
+    ) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    /// Called once when the runner stops, while requests can still be sent
+    fn on_stop(&mut self, _ctx: &mut Context<'_>) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+}
+
+//==================================================================================================
+/// Stops a Runner from another thread, such as a Ctrl-C handler.  Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug)]
+struct Timer {
+    id: i32,
+    due: DateTime<Utc>,
+    every: Option<chrono::Duration>,
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+struct Timers {
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    fn set(&mut self, id: i32, due: DateTime<Utc>, every: Option<chrono::Duration>) {
+        self.cancel(id);
+        self.timers.push(Timer { id, due, every });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn cancel(&mut self, id: i32) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.timers.iter().map(|timer| timer.due).min()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The ids of the timers due at `now`, earliest first.  Intervals are rescheduled, skipping
+    /// the times they missed, and other timers removed.
+    fn take_due(&mut self, now: DateTime<Utc>) -> Vec<i32> {
+        let mut due: Vec<(DateTime<Utc>, i32)> = Vec::new();
+        self.timers.retain_mut(|timer| {
+            if timer.due > now {
+                return true;
+            }
+            due.push((timer.due, timer.id));
+            match timer.every {
+                Some(every) => {
+                    while timer.due <= now {
+                        timer.due += every;
+                    }
+                    true
+                }
+                None => false,
+            }
+        });
+        due.sort_by_key(|(time, _)| *time);
+        due.into_iter().map(|(_, id)| id).collect()
+    }
+}
+
+//==================================================================================================
+/// What a strategy callback can use: the client, the clock, timers and stopping.  It derefs to
+/// the EClient, so requests are made on it directly.
+pub struct Context<'a> {
+    client: &'a mut EClient,
+    now: DateTime<Utc>,
+    timers: &'a mut Timers,
+    stop: &'a StopHandle,
+}
+
+impl<'a> Context<'a> {
+    /// The current time, simulated on a backtest
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Calls on_timer() with `timer_id` once, `after` from now.  Replaces a timer with the same id.
+    pub fn set_timer(&mut self, timer_id: i32, after: Duration) {
+        let after = chrono::Duration::from_std(after).unwrap_or(chrono::Duration::MAX);
+        self.timers.set(timer_id, self.now + after, None);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Calls on_timer() with `timer_id` every `every` from now.  Replaces a timer with the same
+    /// id.  Ticks missed while a callback was busy are skipped.
+    pub fn set_interval(&mut self, timer_id: i32, every: Duration) {
+        let every = chrono::Duration::from_std(every)
+            .unwrap_or(chrono::Duration::MAX)
+            .max(chrono::Duration::milliseconds(1));
+        self.timers.set(timer_id, self.now + every, Some(every));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn cancel_timer(&mut self, timer_id: i32) {
+        self.timers.cancel(timer_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops the runner after the current callback
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+}
+
+impl<'a> Deref for Context<'a> {
+    type Target = EClient;
+
+    fn deref(&self) -> &EClient {
+        self.client
+    }
+}
+
+impl<'a> DerefMut for Context<'a> {
+    fn deref_mut(&mut self) -> &mut EClient {
+        self.client
+    }
+}
+
+//==================================================================================================
+/// Runs a strategy on the events of a client
+#[derive(Clone, Debug)]
+pub struct Runner {
+    stop: StopHandle,
+    timers: Timers,
+    /// The longest a live runner waits for an event
+    poll: Duration,
+    shutdown: ShutdownOptions,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner {
+            stop: StopHandle::default(),
+            timers: Timers::default(),
+            poll: Duration::from_millis(100),
+            shutdown: ShutdownOptions::default(),
+        }
+    }
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Runner::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// How a live client is shut down when the runner stops
+    pub fn shutdown_options(mut self, shutdown: ShutdownOptions) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// How often a live runner checks its StopHandle when no events arrive
+    pub fn poll_interval(mut self, poll: Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn context<'a>(&'a mut self, client: &'a mut EClient, now: DateTime<Utc>) -> Context<'a> {
+        Context {
+            client,
+            now,
+            timers: &mut self.timers,
+            stop: &self.stop,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Passes an event to the callback for it
+    fn deliver<S: Strategy>(
+        &mut self,
+        client: &mut EClient,
+        now: DateTime<Utc>,
+        strategy: &mut S,
+        msg: &ServerRspMsg,
+    ) -> Result<(), IBKRApiLibError> {
+        let ctx = &mut self.context(client, now);
+        match msg {
+            ServerRspMsg::HistoricalData { req_id, bar }
+            | ServerRspMsg::HistoricalDataUpdate { req_id, bar } => {
+                strategy.on_bar(ctx, *req_id, bar)
+            }
+            ServerRspMsg::RealTimeBars { req_id, bar } => {
+                let bar = BarData {
+                    date: parse_tws_time(&bar.date_time, Tz::UTC),
+                    #[cfg(feature = "raw-times")]
+                    date_raw: bar.date_time.clone(),
+                    open: bar.open,
+                    high: bar.high,
+                    low: bar.low,
+                    close: bar.close,
+                    volume: bar.volume,
+                    bar_count: bar.count,
+                    average: bar.wap,
+                };
+                strategy.on_bar(ctx, *req_id, &bar)
+            }
+            ServerRspMsg::TickPrice {
+                req_id,
+                tick_type,
+                price,
+                ..
+            } => strategy.on_tick(
+                ctx,
+                *req_id,
+                &Tick::Price {
+                    tick_type: *tick_type,
+                    price: *price,
+                },
+            ),
+            ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size,
+            } => strategy.on_tick(
+                ctx,
+                *req_id,
+                &Tick::Size {
+                    tick_type: *tick_type,
+                    size: *size,
+                },
+            ),
+            ServerRspMsg::TickByTick { req_id, time, tick } => strategy.on_tick(
+                ctx,
+                *req_id,
+                &Tick::ByTick {
+                    time: *time,
+                    data: tick.clone(),
+                },
+            ),
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                filled,
+                remaining,
+                avg_fill_price,
+                ..
+            } => strategy.on_order_status(
+                ctx,
+                *order_id,
+                status,
+                *filled,
+                *remaining,
+                *avg_fill_price,
+            ),
+            ServerRspMsg::ExecutionData {
+                contract,
+                execution,
+                ..
+            } => strategy.on_fill(ctx, contract, execution),
+            msg => strategy.on_event(ctx, msg),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Calls on_timer() for every timer due at `now`
+    fn fire_timers<S: Strategy>(
+        &mut self,
+        client: &mut EClient,
+        now: DateTime<Utc>,
+        strategy: &mut S,
+    ) -> Result<(), IBKRApiLibError> {
+        for timer_id in self.timers.take_due(now) {
+            strategy.on_timer(&mut self.context(client, now), timer_id)?;
+        }
+        Ok(())
+    }
+
+    //==============================================================================================
+    /// Runs a strategy on a connected client until it stops, then shuts the client down
+    pub fn run_live<S: Strategy>(
+        &mut self,
+        client: &mut EClient,
+        strategy: &mut S,
+    ) -> Result<ShutdownReport, IBKRApiLibError> {
+        let result = self.live_loop(client, strategy);
+        if let Err(err) = &result {
+            warn!("strategy stopped by an error: {}", err);
+        }
+        let stopped = if client.is_connected() {
+            strategy.on_stop(&mut self.context(client, Utc::now()))
+        } else {
+            Ok(())
+        };
+        let report = client.shutdown_with(self.shutdown.clone())?;
+        result.and(stopped).map(|_| report)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn live_loop<S: Strategy>(
+        &mut self,
+        client: &mut EClient,
+        strategy: &mut S,
+    ) -> Result<(), IBKRApiLibError> {
+        strategy.on_start(&mut self.context(client, Utc::now()))?;
+        while !self.stop.is_stopped() {
+            if !client.is_connected() {
+                info!("connection lost, stopping the strategy");
+                break;
+            }
+            self.fire_timers(client, Utc::now(), strategy)?;
+            if self.stop.is_stopped() {
+                break;
+            }
+
+            let wait = match self.timers.next_due() {
+                Some(due) => (due - Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+                    .min(self.poll),
+                None => self.poll,
+            };
+            if let Some(msg) = client.wait_event(wait)? {
+                self.deliver(client, Utc::now(), strategy, &msg)?;
+            }
+        }
+        Ok(())
+    }
+
+    //==============================================================================================
+    /// Runs a strategy on a backtest until it stops or every loaded tick is played.  Timers fire
+    /// on the simulated clock, between the ticks.
+    #[cfg(feature = "backtest")]
+    pub fn run_backtest<S: Strategy>(
+        &mut self,
+        backtest: &mut BacktestClient,
+        strategy: &mut S,
+    ) -> Result<(), IBKRApiLibError> {
+        let result = self.backtest_loop(backtest, strategy);
+        let now = backtest.now();
+        let stopped = strategy.on_stop(&mut self.context(backtest, now));
+        // the responses to requests made on stop, such as cancels
+        let drained = self.drain(backtest, strategy);
+        result.and(stopped).and(drained)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    #[cfg(feature = "backtest")]
+    fn backtest_loop<S: Strategy>(
+        &mut self,
+        backtest: &mut BacktestClient,
+        strategy: &mut S,
+    ) -> Result<(), IBKRApiLibError> {
+        let now = backtest.now();
+        strategy.on_start(&mut self.context(backtest, now))?;
+        loop {
+            self.drain(backtest, strategy)?;
+            if self.stop.is_stopped() {
+                return Ok(());
+            }
+            let next_tick = match backtest.next_time() {
+                Some(time) => time,
+                None => return Ok(()),
+            };
+            match self.timers.next_due() {
+                Some(due) if due < next_tick => {
+                    backtest.run_until(due);
+                    self.drain(backtest, strategy)?;
+                    let now = backtest.now();
+                    self.fire_timers(backtest, now, strategy)?;
+                }
+                _ => {
+                    backtest.step();
+                }
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Delivers the events of a backtest until there are none or the strategy stops
+    #[cfg(feature = "backtest")]
+    fn drain<S: Strategy>(
+        &mut self,
+        backtest: &mut BacktestClient,
+        strategy: &mut S,
+    ) -> Result<(), IBKRApiLibError> {
+        while let Some(msg) = backtest.get_event()? {
+            let now = backtest.now();
+            self.deliver(backtest, now, strategy, &msg)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+//! Opt-in safety net that cancels working orders, and optionally flattens positions, when the
+//! strategy thread panics
+use std::collections::{HashMap, HashSet};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use tracing::{error, info, warn};
+
+use crate::core::client::EClient;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::Order;
+
+//==================================================================================================
+#[derive(Default)]
+struct SafetyNetState {
+    working_orders: HashSet<i32>,
+    positions: HashMap<(String, i32), (Contract, f64)>,
+    next_order_id: i32,
+}
+
+//==================================================================================================
+/// Cancels all working orders (and optionally flattens open positions) if the strategy panics.
+///
+/// Feed every event received from EClient::get_event() to observe() so the net knows which
+/// orders are working and which positions are open.  Then either keep a PanicGuard alive on the
+/// strategy thread or install the panic hook.
+///
+/// The net fires at most once until reset() is called.  Any other emergency mechanism, such as a
+/// dead man's switch, should call claim() before acting so the cancel is not issued twice.
+#[derive(Clone)]
+pub struct SafetyNet {
+    client: Arc<Mutex<EClient>>,
+    state: Arc<Mutex<SafetyNetState>>,
+    fired: Arc<AtomicBool>,
+    flatten: bool,
+}
+
+impl SafetyNet {
+    /// # Arguments
+    /// * client - A handle to the live connection, usually obtained from EClient::try_clone()
+    /// * flatten - If true, open positions are closed with market orders after the global cancel
+    pub fn new(client: EClient, flatten: bool) -> Self {
+        SafetyNet {
+            client: Arc::new(Mutex::new(client)),
+            state: Arc::new(Mutex::new(SafetyNetState::default())),
+            fired: Arc::new(AtomicBool::new(false)),
+            flatten,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Updates the working orders, positions and next order id from an event
+    pub fn observe(&self, msg: &ServerRspMsg) {
+        let mut state = lock_ignoring_poison(&self.state);
+        match msg {
+            ServerRspMsg::NextValidId { order_id } => {
+                state.next_order_id = state.next_order_id.max(*order_id);
+            }
+            ServerRspMsg::OpenOrder {
+                order_id,
+                order_state,
+                ..
+            } => {
+                state.next_order_id = state.next_order_id.max(order_id + 1);
+                if order_state.order_status().is_terminal() {
+                    state.working_orders.remove(order_id);
+                } else {
+                    state.working_orders.insert(*order_id);
+                }
+            }
+            ServerRspMsg::OrderStatus {
+                order_id, status, ..
+            } => {
+                state.next_order_id = state.next_order_id.max(order_id + 1);
+                if status.is_terminal() {
+                    state.working_orders.remove(order_id);
+                } else {
+                    state.working_orders.insert(*order_id);
+                }
+            }
+            ServerRspMsg::PositionData {
+                account,
+                contract,
+                position,
+                ..
+            } => {
+                let key = (account.clone(), contract.con_id);
+                if *position == 0.0 {
+                    state.positions.remove(&key);
+                } else {
+                    state.positions.insert(key, (contract.clone(), *position));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a guard that fires the net if it is dropped while its thread is panicking
+    pub fn guard(&self) -> PanicGuard {
+        PanicGuard {
+            net: self.clone(),
+            armed: true,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Installs a process-wide panic hook that fires the net when the calling thread panics.
+    /// The previously installed hook is still called afterwards.
+    pub fn install_panic_hook(&self) {
+        let net = self.clone();
+        let strategy_thread = thread::current().id();
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == strategy_thread {
+                if let Err(e) = net.trigger() {
+                    error!("safety net failed to fire: {}", e);
+                }
+            }
+            previous_hook(info);
+        }));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Claims the right to handle the emergency.  Returns false if the net has already fired
+    /// or another mechanism has already claimed it.
+    pub fn claim(&self) -> bool {
+        self.fired
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether the net has fired or been claimed
+    pub fn has_fired(&self) -> bool {
+        self.fired.load(Ordering::Acquire)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Re-arms the net after it has fired
+    pub fn reset(&self) {
+        self.fired.store(false, Ordering::Release);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Number of orders currently considered working
+    pub fn working_order_count(&self) -> usize {
+        lock_ignoring_poison(&self.state).working_orders.len()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Issues the global cancel, and the flattening orders if enabled.  Returns Ok(false) without
+    /// sending anything if the net was already claimed or there was nothing to do.
+    pub fn trigger(&self) -> Result<bool, IBKRApiLibError> {
+        let (has_working_orders, positions, mut next_order_id) = {
+            let state = lock_ignoring_poison(&self.state);
+            (
+                !state.working_orders.is_empty(),
+                state.positions.clone(),
+                state.next_order_id,
+            )
+        };
+
+        let flatten = self.flatten && !positions.is_empty();
+        if !has_working_orders && !flatten {
+            return Ok(false);
+        }
+        if !self.claim() {
+            info!("safety net already claimed, skipping cancel");
+            return Ok(false);
+        }
+
+        let mut client = lock_ignoring_poison(&self.client);
+        warn!("safety net fired: cancelling all working orders");
+        client.req_global_cancel()?;
+
+        if flatten {
+            for ((account, _), (contract, position)) in positions.iter() {
+                let mut contract = contract.clone();
+                if contract.exchange.is_empty() {
+                    contract.exchange = "SMART".to_string();
+                }
+                let order = Order {
+                    action: if *position > 0.0 { "SELL" } else { "BUY" }.to_string(),
+                    order_type: "MKT".to_string(),
+                    total_quantity: position.abs(),
+                    account: account.clone(),
+                    ..Default::default()
+                };
+                warn!(
+                    "safety net flattening {} {} {}",
+                    order.action, order.total_quantity, contract.symbol
+                );
+                client.place_order(next_order_id, &contract, &order)?;
+                next_order_id += 1;
+            }
+            lock_ignoring_poison(&self.state).next_order_id = next_order_id;
+        }
+        Ok(true)
+    }
+}
+
+//==================================================================================================
+/// Fires its SafetyNet when dropped during a panic
+pub struct PanicGuard {
+    net: SafetyNet,
+    armed: bool,
+}
+
+impl PanicGuard {
+    /// Prevents the guard from firing when it is dropped
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        if self.armed && thread::panicking() {
+            if let Err(e) = self.net.trigger() {
+                error!("safety net failed to fire: {}", e);
+            }
+        }
+    }
+}
+
+//==================================================================================================
+// The net must still work after the strategy panicked while holding one of its locks
+fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -4,6 +4,8 @@ use std::fmt::{Display, Error, Formatter};
 use serde::{Deserialize, Serialize};
 
 use crate::core::contract::ContractDetails;
+#[cfg(feature = "xml")]
+use crate::core::errors::IBKRApiLibError;
 
 //==================================================================================================
 
@@ -130,3 +132,663 @@ impl Display for ScannerSubscription {
         )
     }
 }
+
+//==================================================================================================
+/// Documented scan codes.  Custom holds any code not listed here, such as the ones returned by
+/// req_scanner_parameters().
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ScanCode {
+    TopPercGain,
+    TopPercLose,
+    MostActive,
+    MostActiveUsd,
+    AllSymbolsAsc,
+    AllSymbolsDesc,
+    HotByVolume,
+    HotByPrice,
+    HotByPriceRange,
+    TopTradeCount,
+    TopTradeRate,
+    TopPriceRange,
+    TopVolumeRate,
+    TopOpenPercGain,
+    TopOpenPercLose,
+    HighOpenGap,
+    LowOpenGap,
+    HighOptImpVolat,
+    LowOptImpVolat,
+    TopOptImpVolatGain,
+    TopOptImpVolatLose,
+    HighOptImpVolatOverHist,
+    LowOptImpVolatOverHist,
+    OptVolumeMostActive,
+    HotByOptVolume,
+    OptOpenInterestMostActive,
+    HighOptVolumePutCallRatio,
+    LowOptVolumePutCallRatio,
+    HighOptOpenInterestPutCallRatio,
+    LowOptOpenInterestPutCallRatio,
+    HighVs13WHl,
+    LowVs13WHl,
+    HighVs26WHl,
+    LowVs26WHl,
+    HighVs52WHl,
+    LowVs52WHl,
+    HighDividendYieldIb,
+    Halted,
+    ComboLatestTrade,
+    ComboQuotes,
+    ComboMostActive,
+    Custom(String),
+}
+
+impl ScanCode {
+    const KNOWN: [ScanCode; 41] = [
+        ScanCode::TopPercGain,
+        ScanCode::TopPercLose,
+        ScanCode::MostActive,
+        ScanCode::MostActiveUsd,
+        ScanCode::AllSymbolsAsc,
+        ScanCode::AllSymbolsDesc,
+        ScanCode::HotByVolume,
+        ScanCode::HotByPrice,
+        ScanCode::HotByPriceRange,
+        ScanCode::TopTradeCount,
+        ScanCode::TopTradeRate,
+        ScanCode::TopPriceRange,
+        ScanCode::TopVolumeRate,
+        ScanCode::TopOpenPercGain,
+        ScanCode::TopOpenPercLose,
+        ScanCode::HighOpenGap,
+        ScanCode::LowOpenGap,
+        ScanCode::HighOptImpVolat,
+        ScanCode::LowOptImpVolat,
+        ScanCode::TopOptImpVolatGain,
+        ScanCode::TopOptImpVolatLose,
+        ScanCode::HighOptImpVolatOverHist,
+        ScanCode::LowOptImpVolatOverHist,
+        ScanCode::OptVolumeMostActive,
+        ScanCode::HotByOptVolume,
+        ScanCode::OptOpenInterestMostActive,
+        ScanCode::HighOptVolumePutCallRatio,
+        ScanCode::LowOptVolumePutCallRatio,
+        ScanCode::HighOptOpenInterestPutCallRatio,
+        ScanCode::LowOptOpenInterestPutCallRatio,
+        ScanCode::HighVs13WHl,
+        ScanCode::LowVs13WHl,
+        ScanCode::HighVs26WHl,
+        ScanCode::LowVs26WHl,
+        ScanCode::HighVs52WHl,
+        ScanCode::LowVs52WHl,
+        ScanCode::HighDividendYieldIb,
+        ScanCode::Halted,
+        ScanCode::ComboLatestTrade,
+        ScanCode::ComboQuotes,
+        ScanCode::ComboMostActive,
+    ];
+
+    /// The code sent to TWS
+    pub fn code(&self) -> &str {
+        match self {
+            ScanCode::TopPercGain => "TOP_PERC_GAIN",
+            ScanCode::TopPercLose => "TOP_PERC_LOSE",
+            ScanCode::MostActive => "MOST_ACTIVE",
+            ScanCode::MostActiveUsd => "MOST_ACTIVE_USD",
+            ScanCode::AllSymbolsAsc => "ALL_SYMBOLS_ASC",
+            ScanCode::AllSymbolsDesc => "ALL_SYMBOLS_DESC",
+            ScanCode::HotByVolume => "HOT_BY_VOLUME",
+            ScanCode::HotByPrice => "HOT_BY_PRICE",
+            ScanCode::HotByPriceRange => "HOT_BY_PRICE_RANGE",
+            ScanCode::TopTradeCount => "TOP_TRADE_COUNT",
+            ScanCode::TopTradeRate => "TOP_TRADE_RATE",
+            ScanCode::TopPriceRange => "TOP_PRICE_RANGE",
+            ScanCode::TopVolumeRate => "TOP_VOLUME_RATE",
+            ScanCode::TopOpenPercGain => "TOP_OPEN_PERC_GAIN",
+            ScanCode::TopOpenPercLose => "TOP_OPEN_PERC_LOSE",
+            ScanCode::HighOpenGap => "HIGH_OPEN_GAP",
+            ScanCode::LowOpenGap => "LOW_OPEN_GAP",
+            ScanCode::HighOptImpVolat => "HIGH_OPT_IMP_VOLAT",
+            ScanCode::LowOptImpVolat => "LOW_OPT_IMP_VOLAT",
+            ScanCode::TopOptImpVolatGain => "TOP_OPT_IMP_VOLAT_GAIN",
+            ScanCode::TopOptImpVolatLose => "TOP_OPT_IMP_VOLAT_LOSE",
+            ScanCode::HighOptImpVolatOverHist => "HIGH_OPT_IMP_VOLAT_OVER_HIST",
+            ScanCode::LowOptImpVolatOverHist => "LOW_OPT_IMP_VOLAT_OVER_HIST",
+            ScanCode::OptVolumeMostActive => "OPT_VOLUME_MOST_ACTIVE",
+            ScanCode::HotByOptVolume => "HOT_BY_OPT_VOLUME",
+            ScanCode::OptOpenInterestMostActive => "OPT_OPEN_INTEREST_MOST_ACTIVE",
+            ScanCode::HighOptVolumePutCallRatio => "HIGH_OPT_VOLUME_PUT_CALL_RATIO",
+            ScanCode::LowOptVolumePutCallRatio => "LOW_OPT_VOLUME_PUT_CALL_RATIO",
+            ScanCode::HighOptOpenInterestPutCallRatio => "HIGH_OPT_OPEN_INTEREST_PUT_CALL_RATIO",
+            ScanCode::LowOptOpenInterestPutCallRatio => "LOW_OPT_OPEN_INTEREST_PUT_CALL_RATIO",
+            ScanCode::HighVs13WHl => "HIGH_VS_13W_HL",
+            ScanCode::LowVs13WHl => "LOW_VS_13W_HL",
+            ScanCode::HighVs26WHl => "HIGH_VS_26W_HL",
+            ScanCode::LowVs26WHl => "LOW_VS_26W_HL",
+            ScanCode::HighVs52WHl => "HIGH_VS_52W_HL",
+            ScanCode::LowVs52WHl => "LOW_VS_52W_HL",
+            ScanCode::HighDividendYieldIb => "HIGH_DIVIDEND_YIELD_IB",
+            ScanCode::Halted => "HALTED",
+            ScanCode::ComboLatestTrade => "COMBO_LATEST_TRADE",
+            ScanCode::ComboQuotes => "COMBO_QUOTES",
+            ScanCode::ComboMostActive => "COMBO_MOST_ACTIVE",
+            ScanCode::Custom(code) => code,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The documented scan code matching `code`, or Custom if there is none
+    pub fn from_code(code: &str) -> Self {
+        ScanCode::KNOWN
+            .iter()
+            .find(|known| known.code() == code)
+            .cloned()
+            .unwrap_or_else(|| ScanCode::Custom(code.to_string()))
+    }
+}
+
+impl Display for ScanCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+//==================================================================================================
+/// Instrument types a scan can run over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ScanInstrument {
+    UsStocks,
+    EuStocks,
+    AsiaStocks,
+    NaStocks,
+    UsFutures,
+    EuFutures,
+    AsiaFutures,
+    UsIndexes,
+    EuIndexes,
+    UsBonds,
+    UsEquityEtfs,
+    UsFixedIncomeEtfs,
+    EuWarrants,
+    NativeCombos,
+    Custom(String),
+}
+
+impl ScanInstrument {
+    const KNOWN: [ScanInstrument; 14] = [
+        ScanInstrument::UsStocks,
+        ScanInstrument::EuStocks,
+        ScanInstrument::AsiaStocks,
+        ScanInstrument::NaStocks,
+        ScanInstrument::UsFutures,
+        ScanInstrument::EuFutures,
+        ScanInstrument::AsiaFutures,
+        ScanInstrument::UsIndexes,
+        ScanInstrument::EuIndexes,
+        ScanInstrument::UsBonds,
+        ScanInstrument::UsEquityEtfs,
+        ScanInstrument::UsFixedIncomeEtfs,
+        ScanInstrument::EuWarrants,
+        ScanInstrument::NativeCombos,
+    ];
+
+    /// The instrument type sent to TWS
+    pub fn code(&self) -> &str {
+        match self {
+            ScanInstrument::UsStocks => "STK",
+            ScanInstrument::EuStocks => "STOCK.EU",
+            ScanInstrument::AsiaStocks => "STOCK.HK",
+            ScanInstrument::NaStocks => "STOCK.NA",
+            ScanInstrument::UsFutures => "FUT.US",
+            ScanInstrument::EuFutures => "FUT.EU",
+            ScanInstrument::AsiaFutures => "FUT.HK",
+            ScanInstrument::UsIndexes => "IND.US",
+            ScanInstrument::EuIndexes => "IND.EU",
+            ScanInstrument::UsBonds => "BOND",
+            ScanInstrument::UsEquityEtfs => "ETF.EQ.US",
+            ScanInstrument::UsFixedIncomeEtfs => "ETF.FI.US",
+            ScanInstrument::EuWarrants => "WAR.EU",
+            ScanInstrument::NativeCombos => "NATCOMB",
+            ScanInstrument::Custom(code) => code,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The documented instrument matching `code`, or Custom if there is none
+    pub fn from_code(code: &str) -> Self {
+        ScanInstrument::KNOWN
+            .iter()
+            .find(|known| known.code() == code)
+            .cloned()
+            .unwrap_or_else(|| ScanInstrument::Custom(code.to_string()))
+    }
+}
+
+impl Display for ScanInstrument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+//==================================================================================================
+/// Locations a scan can run over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ScanLocation {
+    StkUs,
+    StkUsMajor,
+    StkUsMinor,
+    StkNyse,
+    StkNasdaq,
+    StkAmex,
+    StkArca,
+    StkNaCanada,
+    StkEu,
+    StkEuIbis,
+    StkEuLse,
+    StkEuSbf,
+    StkHkSehk,
+    StkHkAsx,
+    StkHkTseJpn,
+    FutUs,
+    FutEuSoffex,
+    FutEuEurex,
+    IndUs,
+    BondUs,
+    NatcombOptUs,
+    Custom(String),
+}
+
+impl ScanLocation {
+    const KNOWN: [ScanLocation; 21] = [
+        ScanLocation::StkUs,
+        ScanLocation::StkUsMajor,
+        ScanLocation::StkUsMinor,
+        ScanLocation::StkNyse,
+        ScanLocation::StkNasdaq,
+        ScanLocation::StkAmex,
+        ScanLocation::StkArca,
+        ScanLocation::StkNaCanada,
+        ScanLocation::StkEu,
+        ScanLocation::StkEuIbis,
+        ScanLocation::StkEuLse,
+        ScanLocation::StkEuSbf,
+        ScanLocation::StkHkSehk,
+        ScanLocation::StkHkAsx,
+        ScanLocation::StkHkTseJpn,
+        ScanLocation::FutUs,
+        ScanLocation::FutEuSoffex,
+        ScanLocation::FutEuEurex,
+        ScanLocation::IndUs,
+        ScanLocation::BondUs,
+        ScanLocation::NatcombOptUs,
+    ];
+
+    /// The location code sent to TWS
+    pub fn code(&self) -> &str {
+        match self {
+            ScanLocation::StkUs => "STK.US",
+            ScanLocation::StkUsMajor => "STK.US.MAJOR",
+            ScanLocation::StkUsMinor => "STK.US.MINOR",
+            ScanLocation::StkNyse => "STK.NYSE",
+            ScanLocation::StkNasdaq => "STK.NASDAQ",
+            ScanLocation::StkAmex => "STK.AMEX",
+            ScanLocation::StkArca => "STK.ARCA",
+            ScanLocation::StkNaCanada => "STK.NA.CANADA",
+            ScanLocation::StkEu => "STK.EU",
+            ScanLocation::StkEuIbis => "STK.EU.IBIS",
+            ScanLocation::StkEuLse => "STK.EU.LSE",
+            ScanLocation::StkEuSbf => "STK.EU.SBF",
+            ScanLocation::StkHkSehk => "STK.HK.SEHK",
+            ScanLocation::StkHkAsx => "STK.HK.ASX",
+            ScanLocation::StkHkTseJpn => "STK.HK.TSE_JPN",
+            ScanLocation::FutUs => "FUT.US",
+            ScanLocation::FutEuSoffex => "FUT.EU.SOFFEX",
+            ScanLocation::FutEuEurex => "FUT.EU.EUREX",
+            ScanLocation::IndUs => "IND.US",
+            ScanLocation::BondUs => "BOND.US",
+            ScanLocation::NatcombOptUs => "NATCOMB.OPT.US",
+            ScanLocation::Custom(code) => code,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The documented location matching `code`, or Custom if there is none
+    pub fn from_code(code: &str) -> Self {
+        ScanLocation::KNOWN
+            .iter()
+            .find(|known| known.code() == code)
+            .cloned()
+            .unwrap_or_else(|| ScanLocation::Custom(code.to_string()))
+    }
+}
+
+impl Display for ScanLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+//==================================================================================================
+/// Builds a ScannerSubscription from typed values
+#[derive(Debug, Clone)]
+pub struct ScannerSubscriptionBuilder {
+    subscription: ScannerSubscription,
+}
+
+impl ScannerSubscriptionBuilder {
+    pub fn new(instrument: ScanInstrument, location: ScanLocation, scan_code: ScanCode) -> Self {
+        ScannerSubscriptionBuilder {
+            subscription: ScannerSubscription {
+                number_of_rows: -1,
+                instrument: instrument.code().to_string(),
+                location_code: location.code().to_string(),
+                scan_code: scan_code.code().to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Maximum number of results, at most 50
+    pub fn number_of_rows(mut self, number_of_rows: i32) -> Self {
+        self.subscription.number_of_rows = number_of_rows;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn above_price(mut self, price: f64) -> Self {
+        self.subscription.above_price = price;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn below_price(mut self, price: f64) -> Self {
+        self.subscription.below_price = price;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn above_volume(mut self, volume: i32) -> Self {
+        self.subscription.above_volume = volume;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn market_cap_above(mut self, market_cap: f64) -> Self {
+        self.subscription.market_cap_above = market_cap;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn market_cap_below(mut self, market_cap: f64) -> Self {
+        self.subscription.market_cap_below = market_cap;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn average_option_volume_above(mut self, volume: i32) -> Self {
+        self.subscription.average_option_volume_above = volume;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn exclude_convertible(mut self, exclude: bool) -> Self {
+        self.subscription.exclude_convertible = exclude;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// One of "ALL", "CORP" or "ADR"
+    pub fn stock_type_filter(mut self, filter: &str) -> Self {
+        self.subscription.stock_type_filter = filter.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn scanner_setting_pairs(mut self, pairs: &str) -> Self {
+        self.subscription.scanner_setting_pairs = pairs.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn build(self) -> ScannerSubscription {
+        self.subscription
+    }
+}
+
+//==================================================================================================
+/// An instrument type listed in the scanner parameters
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScanInstrumentInfo {
+    pub name: String,
+    pub instrument: ScanInstrument,
+    /// Ids of the filters that apply to this instrument
+    pub filters: Vec<String>,
+    pub group: String,
+    pub short_name: String,
+}
+
+//==================================================================================================
+/// A location in the scanner parameters' location tree
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScanLocationNode {
+    pub display_name: String,
+    pub location: ScanLocation,
+    pub instruments: Vec<ScanInstrument>,
+    pub route_exchange: String,
+    pub delayed_only: bool,
+    pub children: Vec<ScanLocationNode>,
+}
+
+//==================================================================================================
+/// A scan type listed in the scanner parameters
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScanTypeInfo {
+    pub display_name: String,
+    pub scan_code: ScanCode,
+    pub instruments: Vec<ScanInstrument>,
+    pub vendor: String,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ScanFilterKind {
+    /// A pair of fields bounding a value, such as priceAbove and priceBelow
+    Range,
+    Simple,
+}
+
+//==================================================================================================
+/// A field that can be set in ScannerSubscription::scanner_setting_pairs
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ScanFilterField {
+    pub code: String,
+    pub display_name: String,
+    /// The field's type attribute, for example DoubleField or ComboField
+    pub field_type: String,
+    pub tooltip: String,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScanFilter {
+    pub id: String,
+    pub category: String,
+    pub kind: ScanFilterKind,
+    pub fields: Vec<ScanFilterField>,
+}
+
+//==================================================================================================
+/// Structured form of the XML returned by req_scanner_parameters()
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ScannerParams {
+    pub instruments: Vec<ScanInstrumentInfo>,
+    pub locations: Vec<ScanLocationNode>,
+    pub scan_types: Vec<ScanTypeInfo>,
+    pub filters: Vec<ScanFilter>,
+}
+
+impl ScannerParams {
+    /// Searches the location tree for a location
+    pub fn find_location(&self, location: &ScanLocation) -> Option<&ScanLocationNode> {
+        fn search<'a>(
+            nodes: &'a [ScanLocationNode],
+            location: &ScanLocation,
+        ) -> Option<&'a ScanLocationNode> {
+            nodes.iter().find_map(|node| {
+                if &node.location == location {
+                    Some(node)
+                } else {
+                    search(&node.children, location)
+                }
+            })
+        }
+        search(&self.locations, location)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Scan types that can run over an instrument
+    pub fn scan_types_for(&self, instrument: &ScanInstrument) -> Vec<&ScanTypeInfo> {
+        self.scan_types
+            .iter()
+            .filter(|scan_type| scan_type.instruments.contains(instrument))
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Filters that apply to an instrument
+    pub fn filters_for(&self, instrument: &ScanInstrument) -> Vec<&ScanFilter> {
+        let ids = match self
+            .instruments
+            .iter()
+            .find(|info| &info.instrument == instrument)
+        {
+            Some(info) => &info.filters,
+            None => return Vec::new(),
+        };
+        self.filters
+            .iter()
+            .filter(|filter| ids.contains(&filter.id))
+            .collect()
+    }
+}
+
+#[cfg(feature = "xml")]
+impl ScannerParams {
+    /// Parses the XML returned by req_scanner_parameters()
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+        let mut params = ScannerParams::default();
+
+        for list in root.children().filter(|node| node.is_element()) {
+            match list.tag_name().name() {
+                "InstrumentList" => {
+                    params
+                        .instruments
+                        .extend(child_elements(list, "Instrument").map(|instrument| {
+                            ScanInstrumentInfo {
+                                name: child_text(instrument, "name"),
+                                instrument: ScanInstrument::from_code(&child_text(
+                                    instrument, "type",
+                                )),
+                                filters: split_list(&child_text(instrument, "filters")),
+                                group: child_text(instrument, "group"),
+                                short_name: child_text(instrument, "shortName"),
+                            }
+                        }))
+                }
+                "LocationTree" => params.locations.extend(parse_locations(list)),
+                "ScanTypeList" => params
+                    .scan_types
+                    .extend(child_elements(list, "ScanType").map(|scan_type| {
+                        ScanTypeInfo {
+                            display_name: child_text(scan_type, "displayName"),
+                            scan_code: ScanCode::from_code(&child_text(scan_type, "scanCode")),
+                            instruments: split_list(&child_text(scan_type, "instruments"))
+                                .iter()
+                                .map(|code| ScanInstrument::from_code(code))
+                                .collect(),
+                            vendor: child_text(scan_type, "vendor"),
+                        }
+                    })),
+                "FilterList" => {
+                    for filter in list.children().filter(|node| node.is_element()) {
+                        let kind = match filter.tag_name().name() {
+                            "RangeFilter" => ScanFilterKind::Range,
+                            "SimpleFilter" => ScanFilterKind::Simple,
+                            _ => continue,
+                        };
+                        params.filters.push(ScanFilter {
+                            id: child_text(filter, "id"),
+                            category: child_text(filter, "category"),
+                            kind,
+                            fields: filter
+                                .descendants()
+                                .filter(|node| node.has_tag_name("AbstractField"))
+                                .map(|field| ScanFilterField {
+                                    code: child_text(field, "code"),
+                                    display_name: child_text(field, "displayName"),
+                                    field_type: field.attribute("type").unwrap_or("").to_string(),
+                                    tooltip: child_text(field, "tooltip"),
+                                })
+                                .collect(),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(params)
+    }
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn parse_locations(tree: roxmltree::Node<'_, '_>) -> Vec<ScanLocationNode> {
+    child_elements(tree, "Location")
+        .map(|location| ScanLocationNode {
+            display_name: child_text(location, "displayName"),
+            location: ScanLocation::from_code(&child_text(location, "locationCode")),
+            instruments: split_list(&child_text(location, "instruments"))
+                .iter()
+                .map(|code| ScanInstrument::from_code(code))
+                .collect(),
+            route_exchange: child_text(location, "routeExchange"),
+            delayed_only: child_text(location, "delayedOnly") == "true",
+            children: child_elements(location, "LocationTree")
+                .flat_map(parse_locations)
+                .collect(),
+        })
+        .collect()
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn child_elements<'a, 'input: 'a>(
+    node: roxmltree::Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+//==================================================================================================
+/// Trimmed text of the first child element named `tag`, or an empty string
+#[cfg(feature = "xml")]
+fn child_text(node: roxmltree::Node<'_, '_>, tag: &str) -> String {
+    child_elements(node, tag)
+        .next()
+        .and_then(|child| child.text())
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+//==================================================================================================
+#[cfg(feature = "xml")]
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
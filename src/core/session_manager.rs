@@ -0,0 +1,251 @@
+//! Several EClient connections to the same TWS or IB Gateway, each with its own client id
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::EClient;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+/// The kind of traffic a session carries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SessionRole {
+    MarketData,
+    Orders,
+    Account,
+    /// Used for roles without a session of their own
+    General,
+    Custom(String),
+}
+
+impl Display for SessionRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            SessionRole::Custom(name) => write!(f, "{}", name),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+//==================================================================================================
+/// An event together with the session it arrived on
+#[derive(Debug)]
+pub struct TaggedEvent {
+    pub client_id: i32,
+    pub msg: ServerRspMsg,
+}
+
+//==================================================================================================
+struct Session {
+    client_id: i32,
+    roles: Vec<SessionRole>,
+    client: EClient,
+}
+
+/// Owns one EClient per client id and routes requests to them by role.
+///
+/// Each connection has its own market data lines and pacing limits, so spreading traffic over
+/// several raises both.  Roles without a session of their own fall back to the General session,
+/// or to the first session if there is none.
+///
+/// Order ids are per client id, and TWS only reports orders to the client that placed them,
+/// so all order traffic should go through one Orders session.
+#[derive(Default)]
+pub struct SessionManager {
+    sessions: Vec<Session>,
+    routes: HashMap<SessionRole, usize>,
+    next_poll: usize,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        SessionManager::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Connects a new session and assigns roles to it
+    ///
+    /// # Arguments
+    /// * host - Host of TWS or IB Gateway
+    /// * port - API port
+    /// * client_id - Must differ from the ids of the other sessions
+    /// * roles - Roles served by this session.  A role already assigned moves to this session.
+    pub fn connect(
+        &mut self,
+        host: &str,
+        port: u32,
+        client_id: i32,
+        roles: &[SessionRole],
+    ) -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        client.connect(host, port, client_id)?;
+        self.add(client_id, roles, client)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a client that is already connected
+    pub fn add(
+        &mut self,
+        client_id: i32,
+        roles: &[SessionRole],
+        client: EClient,
+    ) -> Result<(), IBKRApiLibError> {
+        if self
+            .sessions
+            .iter()
+            .any(|session| session.client_id == client_id)
+        {
+            return Err(IBKRApiLibError::Config(format!(
+                "a session with client id {} already exists",
+                client_id
+            )));
+        }
+        let index = self.sessions.len();
+        for role in roles {
+            if let Some(previous) = self.routes.insert(role.clone(), index) {
+                self.sessions[previous]
+                    .roles
+                    .retain(|assigned| assigned != role);
+            }
+        }
+        self.sessions.push(Session {
+            client_id,
+            roles: roles.to_vec(),
+            client,
+        });
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Disconnects a session and drops its roles, which fall back to the General session
+    pub fn remove(&mut self, client_id: i32) -> Result<(), IBKRApiLibError> {
+        let index = match self
+            .sessions
+            .iter()
+            .position(|session| session.client_id == client_id)
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let mut session = self.sessions.remove(index);
+        self.routes.retain(|_, routed| *routed != index);
+        for routed in self.routes.values_mut() {
+            if *routed > index {
+                *routed -= 1;
+            }
+        }
+        self.next_poll = 0;
+        if session.client.is_connected() {
+            session.client.disconnect()?;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn route(&self, role: &SessionRole) -> Option<usize> {
+        if let Some(&index) = self
+            .routes
+            .get(role)
+            .or_else(|| self.routes.get(&SessionRole::General))
+        {
+            return Some(index);
+        }
+        if self.sessions.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Client id of the session that serves a role
+    pub fn client_id_for(&self, role: &SessionRole) -> Option<i32> {
+        self.route(role).map(|index| self.sessions[index].client_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The client that serves a role, for sending requests
+    pub fn client(&mut self, role: &SessionRole) -> Result<&mut EClient, IBKRApiLibError> {
+        match self.route(role) {
+            Some(index) => Ok(&mut self.sessions[index].client),
+            None => Err(IBKRApiLibError::Config(format!(
+                "no session serves the {} role",
+                role
+            ))),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The client of a session, by client id
+    pub fn client_by_id(&mut self, client_id: i32) -> Option<&mut EClient> {
+        self.sessions
+            .iter_mut()
+            .find(|session| session.client_id == client_id)
+            .map(|session| &mut session.client)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Roles explicitly assigned to a session
+    pub fn roles(&self, client_id: i32) -> &[SessionRole] {
+        self.sessions
+            .iter()
+            .find(|session| session.client_id == client_id)
+            .map(|session| session.roles.as_slice())
+            .unwrap_or(&[])
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn client_ids(&self) -> Vec<i32> {
+        self.sessions
+            .iter()
+            .map(|session| session.client_id)
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the next event from any session.  Sessions are polled in turn so a busy one
+    /// cannot starve the others.
+    pub fn get_event(&mut self) -> Result<Option<TaggedEvent>, IBKRApiLibError> {
+        let count = self.sessions.len();
+        for offset in 0..count {
+            let index = (self.next_poll + offset) % count;
+            let session = &self.sessions[index];
+            if let Some(msg) = session.client.get_event()? {
+                self.next_poll = (index + 1) % count;
+                return Ok(Some(TaggedEvent {
+                    client_id: session.client_id,
+                    msg,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True if every session is connected
+    pub fn is_connected(&self) -> bool {
+        !self.sessions.is_empty()
+            && self
+                .sessions
+                .iter()
+                .all(|session| session.client.is_connected())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Disconnects every session.  The first error is returned after all have been tried.
+    pub fn disconnect_all(&mut self) -> Result<(), IBKRApiLibError> {
+        let mut result = Ok(());
+        for session in self.sessions.iter_mut() {
+            if session.client.is_connected() {
+                if let Err(e) = session.client.disconnect() {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,65 @@
+//! Options and results of EClient::shutdown()
+//!
+//! A shutdown stops new requests, waits for writes already in progress, optionally cancels the
+//! subscriptions still open, closes the socket and waits for the reader and decoder threads to
+//! exit.  The report says what could not be done before the timeout.
+use std::time::Duration;
+
+use crate::core::req_ids::RequestKind;
+
+//==================================================================================================
+/// How EClient::shutdown_with() closes the connection
+#[derive(Clone, Debug)]
+pub struct ShutdownOptions {
+    /// How long to wait for the reader and decoder threads to exit
+    pub timeout: Duration,
+    /// Send a cancel for every subscription still registered before closing
+    pub cancel_subscriptions: bool,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions {
+            timeout: Duration::from_secs(5),
+            cancel_subscriptions: true,
+        }
+    }
+}
+
+impl ShutdownOptions {
+    //----------------------------------------------------------------------------------------------
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Leave subscriptions open.  TWS drops them when the socket closes anyway.
+    pub fn keep_subscriptions(mut self) -> Self {
+        self.cancel_subscriptions = false;
+        self
+    }
+}
+
+//==================================================================================================
+/// What EClient::shutdown() managed to do
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownReport {
+    /// Subscriptions a cancel was sent for
+    pub cancelled: Vec<(i32, RequestKind)>,
+    /// Subscriptions whose cancel could not be sent, with the reason
+    pub failed_cancels: Vec<(i32, RequestKind, String)>,
+    /// Why the final flush of the socket failed, if it did
+    pub flush_error: Option<String>,
+    /// Reader and decoder threads still running when the timeout expired
+    pub threads_running: usize,
+    /// Events received but never taken with get_event()
+    pub undelivered_events: usize,
+}
+
+impl ShutdownReport {
+    /// Whether everything was flushed, cancelled and joined
+    pub fn is_clean(&self) -> bool {
+        self.failed_cancels.is_empty() && self.flush_error.is_none() && self.threads_running == 0
+    }
+}
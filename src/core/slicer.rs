@@ -0,0 +1,318 @@
+//! Splits a parent quantity into child orders sent over time
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::client::EClient;
+use crate::core::common::TickByTickData;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderStatusKind};
+
+//==================================================================================================
+/// When child orders are sent and how large they are
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SliceSchedule {
+    /// `slices` equal child orders, one every `interval`
+    FixedInterval { interval: Duration, slices: u32 },
+    /// Every `interval`, a child order that brings the quantity sent up to `rate` (0.1 for 10%)
+    /// of the volume traded since the start.  Volume is taken from the AllLast tick-by-tick
+    /// stream with request id `volume_req_id`, which the caller subscribes to.
+    Participation {
+        rate: f64,
+        interval: Duration,
+        volume_req_id: i32,
+    },
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlicerState {
+    Running,
+    Paused,
+    /// The whole quantity has been filled
+    Completed,
+    Cancelled,
+}
+
+//==================================================================================================
+/// A child order sent by the slicer
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ChildOrder {
+    pub order_id: i32,
+    pub quantity: f64,
+    pub filled: f64,
+    pub status: OrderStatusKind,
+}
+
+impl ChildOrder {
+    pub fn is_done(&self) -> bool {
+        self.status.is_terminal()
+    }
+}
+
+//==================================================================================================
+/// Sends a parent order as a series of child orders, for venues where IB algos are not
+/// available.
+///
+/// Call poll() regularly, for example after every event, and feed every event to observe() so
+/// the slicer sees fills, order ids and, for participation schedules, market volume.  Quantity
+/// sent but not filled by a child that ends without filling is sent again in later slices.
+#[derive(Clone, Debug)]
+pub struct OrderSlicer {
+    contract: Contract,
+    template: Order,
+    total_quantity: f64,
+    lot_size: f64,
+    schedule: SliceSchedule,
+    state: SlicerState,
+    next_order_id: i32,
+    next_slice_at: Option<Instant>,
+    slices_sent: u32,
+    market_volume: f64,
+    children: BTreeMap<i32, ChildOrder>,
+}
+
+impl OrderSlicer {
+    /// # Arguments
+    /// * contract - The contract to trade
+    /// * parent - Action, total quantity and order type of the parent.  Child orders are copies
+    ///   of it with their own quantity.
+    /// * schedule - When child orders are sent
+    /// * next_order_id - First order id to use.  Later ids follow NextValidId events.
+    pub fn new(
+        contract: Contract,
+        parent: Order,
+        schedule: SliceSchedule,
+        next_order_id: i32,
+    ) -> Self {
+        OrderSlicer {
+            contract,
+            total_quantity: parent.total_quantity,
+            template: parent,
+            lot_size: 1.0,
+            schedule,
+            state: SlicerState::Running,
+            next_order_id,
+            next_slice_at: None,
+            slices_sent: 0,
+            market_volume: 0.0,
+            children: BTreeMap::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Child quantities are rounded down to a multiple of this, 1 by default
+    pub fn with_lot_size(mut self, lot_size: f64) -> Self {
+        self.lot_size = lot_size;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records fills of child orders, order ids and market volume
+    pub fn observe(&mut self, msg: &ServerRspMsg) {
+        match msg {
+            ServerRspMsg::NextValidId { order_id } => {
+                self.next_order_id = self.next_order_id.max(*order_id);
+            }
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                filled,
+                ..
+            } => {
+                if let Some(child) = self.children.get_mut(order_id) {
+                    child.filled = *filled;
+                    child.status = status.clone();
+                    if self.filled() >= self.total_quantity {
+                        self.state = SlicerState::Completed;
+                    }
+                }
+            }
+            ServerRspMsg::TickByTick {
+                req_id,
+                tick: TickByTickData::Last { size, .. },
+                ..
+            } => {
+                if let SliceSchedule::Participation { volume_req_id, .. } = self.schedule {
+                    if *req_id == volume_req_id {
+                        self.market_volume += *size as f64;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the child order due at `now`, if any, and records it as sent.  poll() calls this
+    /// and places the order.
+    pub fn due_child(&mut self, now: Instant) -> Option<(i32, Order)> {
+        if self.state != SlicerState::Running {
+            return None;
+        }
+        if let Some(at) = self.next_slice_at {
+            if now < at {
+                return None;
+            }
+        }
+        let (interval, target) = match self.schedule {
+            SliceSchedule::FixedInterval { interval, slices } => {
+                let slice = (self.slices_sent + 1).min(slices.max(1));
+                (
+                    interval,
+                    self.total_quantity * slice as f64 / slices.max(1) as f64,
+                )
+            }
+            SliceSchedule::Participation { rate, interval, .. } => {
+                (interval, self.market_volume * rate)
+            }
+        };
+        self.next_slice_at = Some(now + interval);
+        self.slices_sent += 1;
+
+        let target = target.min(self.total_quantity);
+        let mut quantity = self.round_to_lot(target - self.committed());
+        // the last slice takes whatever rounding left over
+        if target >= self.total_quantity {
+            quantity = self.total_quantity - self.committed();
+        }
+        if quantity <= 0.0 {
+            return None;
+        }
+
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+        self.children.insert(
+            order_id,
+            ChildOrder {
+                order_id,
+                quantity,
+                status: OrderStatusKind::PendingSubmit,
+                ..Default::default()
+            },
+        );
+        let mut order = self.template.clone();
+        order.total_quantity = quantity;
+        Some((order_id, order))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Places the child order due at `now`, if any, and returns its order id
+    pub fn poll(
+        &mut self,
+        client: &mut EClient,
+        now: Instant,
+    ) -> Result<Option<i32>, IBKRApiLibError> {
+        match self.due_child(now) {
+            Some((order_id, order)) => {
+                debug!(
+                    "slicer placing child {} for {} of {}",
+                    order_id, order.total_quantity, self.total_quantity
+                );
+                client.place_order(order_id, &self.contract, &order)?;
+                Ok(Some(order_id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops sending child orders.  Working children are left alone.
+    pub fn pause(&mut self) {
+        if self.state == SlicerState::Running {
+            self.state = SlicerState::Paused;
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Resumes a paused slicer.  The next child is due immediately.
+    pub fn resume(&mut self) {
+        if self.state == SlicerState::Paused {
+            self.state = SlicerState::Running;
+            self.next_slice_at = None;
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops the slicer for good and cancels its working children
+    pub fn cancel(&mut self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        self.state = SlicerState::Cancelled;
+        for order_id in self.working_children() {
+            client.cancel_order(order_id)?;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn round_to_lot(&self, quantity: f64) -> f64 {
+        if self.lot_size <= 0.0 {
+            return quantity;
+        }
+        (quantity / self.lot_size + 1e-9).floor() * self.lot_size
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Quantity filled, plus quantity still working
+    fn committed(&self) -> f64 {
+        self.children
+            .values()
+            .map(|child| {
+                if child.is_done() {
+                    child.filled
+                } else {
+                    child.quantity.max(child.filled)
+                }
+            })
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn state(&self) -> SlicerState {
+        self.state
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_complete(&self) -> bool {
+        self.state == SlicerState::Completed
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn total_quantity(&self) -> f64 {
+        self.total_quantity
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn filled(&self) -> f64 {
+        self.children.values().map(|child| child.filled).sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn remaining(&self) -> f64 {
+        self.total_quantity - self.filled()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Market volume seen so far on the participation stream
+    pub fn market_volume(&self) -> f64 {
+        self.market_volume
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Child orders in the order they were sent
+    pub fn children(&self) -> Vec<&ChildOrder> {
+        self.children.values().collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn working_children(&self) -> Vec<i32> {
+        self.children
+            .values()
+            .filter(|child| !child.is_done())
+            .map(|child| child.order_id)
+            .collect()
+    }
+}
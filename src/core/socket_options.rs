@@ -0,0 +1,135 @@
+//! TCP options applied to the connection to TWS or IB Gateway
+//!
+//! EClient::connect() applies EClientConfig::socket to the socket once it is open, whether or not
+//! it goes through a proxy.  Options left as None keep the operating system's defaults.
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+
+//==================================================================================================
+/// TCP keepalive probing of an idle connection
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keepalive {
+    /// Idle time before the first probe
+    pub time: Duration,
+    /// Time between probes.  Not supported on every platform; ignored where it is not.
+    pub interval: Option<Duration>,
+    /// Probes without an answer before the connection is dropped.  Ignored on Windows.
+    pub retries: Option<u32>,
+}
+
+impl Keepalive {
+    /// # Arguments
+    /// * time - Idle time before the first probe
+    pub fn new(time: Duration) -> Self {
+        Keepalive {
+            time,
+            interval: None,
+            retries: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn to_socket2(self) -> TcpKeepalive {
+        let keepalive = TcpKeepalive::new().with_time(self.time);
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "windows",
+        ))]
+        let keepalive = match self.interval {
+            Some(interval) => keepalive.with_interval(interval),
+            None => keepalive,
+        };
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+        ))]
+        let keepalive = match self.retries {
+            Some(retries) => keepalive.with_retries(retries),
+            None => keepalive,
+        };
+        keepalive
+    }
+}
+
+//==================================================================================================
+/// Options for the socket to TWS
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SocketOptions {
+    /// TCP_NODELAY.  Some(true) disables Nagle's algorithm so requests are sent immediately.
+    pub nodelay: Option<bool>,
+    /// SO_KEEPALIVE and its timing
+    pub keepalive: Option<Keepalive>,
+    /// SO_SNDBUF in bytes
+    pub send_buffer_size: Option<usize>,
+    /// SO_RCVBUF in bytes
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    /// Disables Nagle's algorithm, for the lowest latency on small messages such as orders
+    pub fn nodelay(mut self) -> Self {
+        self.nodelay = Some(true);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn keepalive(mut self, keepalive: Keepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the options on an open socket
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = SockRef::from(stream);
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            socket.set_tcp_keepalive(&keepalive.to_socket2())?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Requests the soft dollar tiers of an account as futures
+//!
+//! Tiers are returned by ReqSoftDollarTiers as a SoftDollarTiers event.  Feed every event to
+//! SoftDollarTierRequests::observe(), from the thread reading events, and the futures returned by
+//! request() resolve when their answer arrives.  A tier is assigned to an order with
+//! Order::with_soft_dollar_tier().
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use crate::core::client::EClient;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::SoftDollarTier;
+use crate::core::req_ids::RequestKind;
+
+//==================================================================================================
+enum Pending {
+    Waiting(Option<Waker>),
+    Done(Result<Vec<SoftDollarTier>, IBKRApiLibError>),
+}
+
+//==================================================================================================
+/// Tracks the soft dollar tier requests still waiting for an answer.  Clones share the same
+/// requests.
+#[derive(Clone, Default)]
+pub struct SoftDollarTierRequests {
+    pending: Arc<Mutex<HashMap<i32, Pending>>>,
+}
+
+impl SoftDollarTierRequests {
+    pub fn new() -> Self {
+        SoftDollarTierRequests::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn pending(&self) -> MutexGuard<'_, HashMap<i32, Pending>> {
+        // every update leaves the map consistent, so a poisoned lock is still safe to use
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sends ReqSoftDollarTiers and returns a future resolving to the tiers
+    pub fn request(&self, client: &mut EClient) -> Result<SoftDollarTiersFuture, IBKRApiLibError> {
+        let req_id = client.next_req_id(RequestKind::SoftDollarTiers, "");
+        self.pending().insert(req_id, Pending::Waiting(None));
+        if let Err(err) = client.req_soft_dollar_tiers(req_id) {
+            self.pending().remove(&req_id);
+            return Err(err);
+        }
+        Ok(SoftDollarTiersFuture {
+            requests: self.clone(),
+            req_id,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Resolves the request a SoftDollarTiers event or a request error belongs to.  Returns true
+    /// if the event answered a pending request.
+    pub fn observe(&self, msg: &ServerRspMsg) -> bool {
+        let (req_id, result) = match msg {
+            ServerRspMsg::SoftDollarTiers { req_id, tiers } => (*req_id, Ok(tiers.clone())),
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } if !is_informational(*error_code) => (
+                *req_id,
+                Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                    *req_id,
+                    error_code.to_string(),
+                    error_str.clone(),
+                ))),
+            ),
+            _ => return false,
+        };
+        let mut pending = self.pending();
+        let waker = match pending.get_mut(&req_id) {
+            Some(entry @ Pending::Waiting(_)) => {
+                match std::mem::replace(entry, Pending::Done(result)) {
+                    Pending::Waiting(waker) => waker,
+                    Pending::Done(_) => None,
+                }
+            }
+            _ => return false,
+        };
+        drop(pending);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        true
+    }
+}
+
+//==================================================================================================
+/// Future returned by SoftDollarTierRequests::request().  Dropping it forgets the request.
+pub struct SoftDollarTiersFuture {
+    requests: SoftDollarTierRequests,
+    req_id: i32,
+}
+
+impl SoftDollarTiersFuture {
+    /// The id of the request
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+}
+
+impl Future for SoftDollarTiersFuture {
+    type Output = Result<Vec<SoftDollarTier>, IBKRApiLibError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut pending = self.requests.pending();
+        match pending.remove(&self.req_id) {
+            Some(Pending::Done(result)) => Poll::Ready(result),
+            Some(Pending::Waiting(_)) | None => {
+                pending.insert(self.req_id, Pending::Waiting(Some(cx.waker().clone())));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for SoftDollarTiersFuture {
+    fn drop(&mut self) {
+        self.requests.pending().remove(&self.req_id);
+    }
+}
@@ -1,5 +1,6 @@
 use bytebuffer::ByteBuffer;
 use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::{
     io::{self, Read, Write},
     net::Shutdown,
@@ -9,6 +10,8 @@ use std::{
 pub trait Streamer: Read + Write + Send + Sync {
     fn shutdown(&mut self, how: Shutdown) -> io::Result<()>;
     fn connect(&mut self, addr: &SocketAddr);
+    /// Creates a new handle to the same underlying connection
+    fn try_clone(&self) -> io::Result<Box<dyn Streamer>>;
 }
 //----------------------------------------------------------------------------------------------
 #[derive(Debug)]
@@ -30,6 +33,10 @@ impl Streamer for TcpStreamer {
     fn connect(&mut self, addr: &SocketAddr) {
         self.stream = TcpStream::connect(addr).expect("Cannot connect!!");
     }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Streamer>> {
+        Ok(Box::new(TcpStreamer::new(self.stream.try_clone()?)))
+    }
 }
 
 impl Clone for TcpStreamer {
@@ -54,16 +61,25 @@ impl Write for TcpStreamer {
 }
 
 //----------------------------------------------------------------------------------------------
+/// An in-memory connection.  Clones share the same buffer.
 pub struct TestStreamer {
-    stream: ByteBuffer,
+    stream: Arc<Mutex<ByteBuffer>>,
 }
 
 impl TestStreamer {
     pub fn new() -> Self {
         TestStreamer {
-            stream: ByteBuffer::new(),
+            stream: Arc::new(Mutex::new(ByteBuffer::new())),
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    fn buffer(&self) -> MutexGuard<'_, ByteBuffer> {
+        // the buffer holds no invariants a panicking thread could break
+        self.stream
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
 
 impl Streamer for TestStreamer {
@@ -72,11 +88,17 @@ impl Streamer for TestStreamer {
     }
 
     fn connect(&mut self, _addr: &SocketAddr) {}
+
+    fn try_clone(&self) -> io::Result<Box<dyn Streamer>> {
+        Ok(Box::new(TestStreamer {
+            stream: self.stream.clone(),
+        }))
+    }
 }
 
 impl Read for TestStreamer {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.read(buf)
+        self.buffer().read(buf)
     }
 
     fn read_to_end(&mut self, allbuf: &mut Vec<u8>) -> io::Result<usize> {
@@ -87,7 +109,7 @@ impl Read for TestStreamer {
             let mut buf: [u8; NUM_BYTES] = [0; NUM_BYTES];
 
             let bytes_read = self
-                .stream
+                .buffer()
                 .read(&mut buf)
                 .expect("Couldnt read from reader...");
             allbuf.extend_from_slice(&buf[0..bytes_read]);
@@ -102,9 +124,9 @@ impl Read for TestStreamer {
 
 impl Write for TestStreamer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.stream.write(buf)
+        self.buffer().write(buf)
     }
     fn flush(&mut self) -> io::Result<()> {
-        self.stream.flush()
+        self.buffer().flush()
     }
 }
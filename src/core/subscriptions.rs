@@ -0,0 +1,479 @@
+//! Declarative subscription manifest and a supervisor that keeps the live connection in line
+//! with it
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::client::EClient;
+use crate::core::common::{BarSize, HistoricalDuration, TickByTickType};
+use crate::core::contract::Contract;
+use crate::core::errors::{is_informational, IBKRApiLibError};
+use crate::core::messages::ServerRspMsg;
+
+//==================================================================================================
+fn default_sec_type() -> String {
+    "STK".to_string()
+}
+
+fn default_exchange() -> String {
+    "SMART".to_string()
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_what_to_show() -> String {
+    "TRADES".to_string()
+}
+
+//==================================================================================================
+/// The contract part of an instrument entry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractSpec {
+    pub symbol: String,
+    #[serde(default = "default_sec_type")]
+    pub sec_type: String,
+    #[serde(default = "default_exchange")]
+    pub exchange: String,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub primary_exchange: String,
+    #[serde(default)]
+    pub con_id: i32,
+}
+
+impl ContractSpec {
+    pub fn contract(&self) -> Contract {
+        Contract {
+            con_id: self.con_id,
+            symbol: self.symbol.clone(),
+            sec_type: self.sec_type.clone(),
+            exchange: self.exchange.clone(),
+            currency: self.currency.clone(),
+            primary_exchange: self.primary_exchange.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+//==================================================================================================
+/// Bar sizes are written in manifests as TWS writes them, such as "1 min"
+mod bar_size_setting {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::core::common::BarSize;
+
+    pub fn serialize<S: Serializer>(bar_size: &BarSize, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(bar_size)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BarSize, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+//==================================================================================================
+/// A stream of data wanted for an instrument
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DataSpec {
+    MarketData {
+        #[serde(default)]
+        generic_ticks: String,
+    },
+    RealTimeBars {
+        #[serde(default = "default_what_to_show")]
+        what_to_show: String,
+        #[serde(default)]
+        use_rth: bool,
+    },
+    /// Historical bars that keep updating after the initial download
+    HistoricalBars {
+        #[serde(with = "bar_size_setting")]
+        bar_size: BarSize,
+        duration: HistoricalDuration,
+        #[serde(default = "default_what_to_show")]
+        what_to_show: String,
+        #[serde(default)]
+        use_rth: bool,
+    },
+    TickByTick {
+        tick_type: TickByTickType,
+    },
+    Depth {
+        rows: i32,
+        #[serde(default)]
+        smart_depth: bool,
+    },
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InstrumentSpec {
+    #[serde(flatten)]
+    pub contract: ContractSpec,
+    #[serde(default)]
+    pub data: Vec<DataSpec>,
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountSpec {
+    pub account: String,
+    #[serde(default)]
+    pub model_code: String,
+    #[serde(default)]
+    pub ledger_and_nlv: bool,
+    /// Also subscribe to daily PnL for the account
+    #[serde(default)]
+    pub pnl: bool,
+}
+
+//==================================================================================================
+/// Desired subscriptions, usually loaded from a TOML or YAML file:
+///
+/// ```toml
+/// [[instruments]]
+/// symbol = "AAPL"
+/// data = [
+///     { type = "market_data", generic_ticks = "233" },
+///     { type = "historical_bars", bar_size = "1 min", duration = "1 D" },
+/// ]
+///
+/// [[accounts]]
+/// account = "DU123456"
+/// pnl = true
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SubscriptionManifest {
+    #[serde(default)]
+    pub instruments: Vec<InstrumentSpec>,
+    #[serde(default)]
+    pub accounts: Vec<AccountSpec>,
+}
+
+impl SubscriptionManifest {
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str) -> Result<Self, IBKRApiLibError> {
+        toml::from_str(text).map_err(|e| IBKRApiLibError::Parse(e.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(text: &str) -> Result<Self, IBKRApiLibError> {
+        serde_yaml::from_str(text).map_err(|e| IBKRApiLibError::Parse(e.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads a manifest, choosing the format from the file extension
+    pub fn load(path: &Path) -> Result<Self, IBKRApiLibError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => SubscriptionManifest::from_toml_str(&std::fs::read_to_string(path)?),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => {
+                SubscriptionManifest::from_yaml_str(&std::fs::read_to_string(path)?)
+            }
+            _ => Err(IBKRApiLibError::Config(format!(
+                "unsupported manifest format: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Flattens the manifest into one entry per request that has to be made
+    pub fn desired(&self) -> Vec<DesiredSubscription> {
+        let mut desired = Vec::new();
+        for instrument in self.instruments.iter() {
+            for data in instrument.data.iter() {
+                desired.push(DesiredSubscription::Data {
+                    contract: instrument.contract.clone(),
+                    data: data.clone(),
+                });
+            }
+        }
+        for account in self.accounts.iter() {
+            desired.push(DesiredSubscription::AccountUpdates(account.clone()));
+            if account.pnl {
+                desired.push(DesiredSubscription::Pnl(account.clone()));
+            }
+        }
+        desired
+    }
+}
+
+//==================================================================================================
+/// A single request the supervisor keeps alive
+#[derive(Clone, Debug, PartialEq)]
+pub enum DesiredSubscription {
+    Data {
+        contract: ContractSpec,
+        data: DataSpec,
+    },
+    AccountUpdates(AccountSpec),
+    Pnl(AccountSpec),
+}
+
+impl DesiredSubscription {
+    fn request(&self, client: &mut EClient, req_id: i32) -> Result<(), IBKRApiLibError> {
+        match self {
+            DesiredSubscription::Data { contract, data } => {
+                let contract = contract.contract();
+                match data {
+                    DataSpec::MarketData { generic_ticks } => {
+                        client.req_mkt_data(req_id, &contract, generic_ticks, false, false, vec![])
+                    }
+                    DataSpec::RealTimeBars {
+                        what_to_show,
+                        use_rth,
+                    } => client.req_real_time_bars(
+                        req_id,
+                        &contract,
+                        5,
+                        what_to_show,
+                        *use_rth,
+                        vec![],
+                    ),
+                    DataSpec::HistoricalBars {
+                        bar_size,
+                        duration,
+                        what_to_show,
+                        use_rth,
+                    } => client.req_historical_data(
+                        req_id,
+                        &contract,
+                        "",
+                        *duration,
+                        *bar_size,
+                        what_to_show,
+                        *use_rth as i32,
+                        1,
+                        true,
+                        vec![],
+                    ),
+                    DataSpec::TickByTick { tick_type } => {
+                        client.req_tick_by_tick_data(req_id, &contract, tick_type.clone(), 0, false)
+                    }
+                    DataSpec::Depth { rows, smart_depth } => {
+                        client.req_mkt_depth(req_id, &contract, *rows, *smart_depth, vec![])
+                    }
+                }
+            }
+            DesiredSubscription::AccountUpdates(account) => client.req_account_updates_multi(
+                req_id,
+                &account.account,
+                &account.model_code,
+                account.ledger_and_nlv,
+            ),
+            DesiredSubscription::Pnl(account) => {
+                client.req_pnl(req_id, &account.account, &account.model_code)
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn cancel(&self, client: &mut EClient, req_id: i32) -> Result<(), IBKRApiLibError> {
+        match self {
+            DesiredSubscription::Data { data, .. } => match data {
+                DataSpec::MarketData { .. } => client.cancel_mkt_data(req_id),
+                DataSpec::RealTimeBars { .. } => client.cancel_real_time_bars(req_id),
+                DataSpec::HistoricalBars { .. } => client.cancel_historical_data(req_id),
+                DataSpec::TickByTick { .. } => client.cancel_tick_by_tick_data(req_id),
+                DataSpec::Depth { smart_depth, .. } => {
+                    client.cancel_mkt_depth(req_id, *smart_depth)
+                }
+            },
+            DesiredSubscription::AccountUpdates(_) => client.cancel_account_updates_multi(req_id),
+            DesiredSubscription::Pnl(_) => client.cancel_pnl(req_id),
+        }
+    }
+}
+
+//==================================================================================================
+/// A subscription that TWS rejected.  It is not retried until the next reconnect or manifest
+/// change.
+#[derive(Clone, Debug)]
+pub struct FailedSubscription {
+    pub subscription: DesiredSubscription,
+    pub error_code: i32,
+    pub error_str: String,
+}
+
+//==================================================================================================
+/// Realizes a SubscriptionManifest against the live connection and keeps it realized.
+///
+/// Call realize() once connected, feed every event to observe() so rejected requests are noticed,
+/// and call on_reconnect() after a new connection has been established.
+pub struct SubscriptionSupervisor {
+    manifest: SubscriptionManifest,
+    next_req_id: i32,
+    active: HashMap<i32, DesiredSubscription>,
+    failed: Vec<FailedSubscription>,
+}
+
+impl SubscriptionSupervisor {
+    /// # Arguments
+    /// * manifest - The subscriptions that should be kept alive
+    /// * first_req_id - Request ids are allocated upwards from this value.  Pick a range that the
+    ///   rest of the application does not use.
+    pub fn new(manifest: SubscriptionManifest, first_req_id: i32) -> Self {
+        SubscriptionSupervisor {
+            manifest,
+            next_req_id: first_req_id,
+            active: HashMap::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn manifest(&self) -> &SubscriptionManifest {
+        &self.manifest
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Issues a request for every desired subscription that is neither active nor failed.
+    /// Returns the number of requests sent.
+    pub fn realize(&mut self, client: &mut EClient) -> Result<usize, IBKRApiLibError> {
+        let mut sent = 0;
+        for desired in self.manifest.desired() {
+            let known = self.active.values().any(|active| *active == desired)
+                || self
+                    .failed
+                    .iter()
+                    .any(|failed| failed.subscription == desired);
+            if known {
+                continue;
+            }
+            let req_id = self.next_req_id;
+            self.next_req_id += 1;
+            debug!(
+                "subscription supervisor requesting {:?} as {}",
+                desired, req_id
+            );
+            desired.request(client, req_id)?;
+            self.active.insert(req_id, desired);
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Replaces the manifest, cancelling subscriptions that are no longer wanted and requesting
+    /// new ones
+    pub fn set_manifest(
+        &mut self,
+        client: &mut EClient,
+        manifest: SubscriptionManifest,
+    ) -> Result<usize, IBKRApiLibError> {
+        let desired = manifest.desired();
+        let stale: Vec<i32> = self
+            .active
+            .iter()
+            .filter(|(_, active)| !desired.contains(active))
+            .map(|(req_id, _)| *req_id)
+            .collect();
+        for req_id in stale {
+            if let Some(subscription) = self.active.get(&req_id) {
+                subscription.cancel(client, req_id)?;
+                self.active.remove(&req_id);
+            }
+        }
+        self.manifest = manifest;
+        self.failed.clear();
+        self.realize(client)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Subscriptions do not survive a dropped connection, so request everything again
+    pub fn on_reconnect(&mut self, client: &mut EClient) -> Result<usize, IBKRApiLibError> {
+        self.active.clear();
+        self.failed.clear();
+        self.realize(client)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Call from ConnectionHandler::connectivity_restored().  When TWS lost the market data
+    /// (error 1101) everything is requested again, as after a reconnect.  Returns the number of
+    /// subscriptions made.
+    pub fn on_connectivity_restored(
+        &mut self,
+        client: &mut EClient,
+        data_maintained: bool,
+    ) -> Result<usize, IBKRApiLibError> {
+        if data_maintained {
+            return Ok(0);
+        }
+        self.on_reconnect(client)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Moves subscriptions rejected by TWS to the failed list
+    pub fn observe(&mut self, msg: &ServerRspMsg) {
+        if let ServerRspMsg::ErrMsg {
+            req_id,
+            error_code,
+            error_str,
+        } = msg
+        {
+            if is_informational(*error_code) {
+                return;
+            }
+            if let Some(subscription) = self.active.remove(req_id) {
+                warn!(
+                    "subscription {} failed with {}: {}",
+                    req_id, error_code, error_str
+                );
+                self.failed.push(FailedSubscription {
+                    subscription,
+                    error_code: *error_code,
+                    error_str: error_str.clone(),
+                });
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The subscription a request id belongs to, if it is one of ours
+    pub fn subscription(&self, req_id: i32) -> Option<&DesiredSubscription> {
+        self.active.get(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn active(&self) -> &HashMap<i32, DesiredSubscription> {
+        &self.active
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn failed(&self) -> &[FailedSubscription] {
+        &self.failed
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels every active subscription.  Subscriptions that could not be cancelled stay
+    /// active so the cancel can be retried, and the first error is returned.
+    pub fn cancel_all(&mut self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        let mut result = Ok(());
+        let req_ids: Vec<i32> = self.active.keys().copied().collect();
+        for req_id in req_ids {
+            match self.active[&req_id].cancel(client, req_id) {
+                Ok(()) => {
+                    self.active.remove(&req_id);
+                }
+                Err(err) => {
+                    warn!("subscription {} could not be cancelled: {}", req_id, err);
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
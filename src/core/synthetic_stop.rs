@@ -0,0 +1,417 @@
+//! Stop orders held on the client and sent as market or limit orders when the stop is touched
+#[cfg(feature = "toml")]
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::client::EClient;
+use crate::core::common::{TickByTickData, TickType, UNSET_DOUBLE};
+use crate::core::connection::{CONNECTIVITY_LOST, CONNECTIVITY_RESTORED_DATA_LOST};
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderStatusKind};
+
+//==================================================================================================
+/// The price that is compared with the stop price
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopTrigger {
+    Last,
+    /// Bid for sell stops, ask for buy stops
+    NearSide,
+    Midpoint,
+}
+
+//==================================================================================================
+/// The order sent when the stop is touched
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum StopExecution {
+    Market,
+    /// A limit order `offset` beyond the stop price: below it for sells, above it for buys
+    Limit {
+        offset: f64,
+    },
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopState {
+    /// Waiting for the stop price to be touched
+    Armed,
+    /// The order has been sent and is working
+    Triggered {
+        order_id: i32,
+    },
+    Filled {
+        order_id: i32,
+    },
+    Cancelled,
+    /// The order sent on trigger was cancelled or rejected by TWS
+    Failed {
+        order_id: i32,
+    },
+}
+
+//==================================================================================================
+/// A stop held on the client.  Market data for it is requested with `req_id`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SyntheticStop {
+    pub req_id: i32,
+    pub contract: Contract,
+    /// SELL to protect a long position, BUY to protect a short one
+    pub action: String,
+    pub quantity: f64,
+    pub stop_price: f64,
+    pub trigger: StopTrigger,
+    pub execution: StopExecution,
+    pub state: StopState,
+    #[serde(skip)]
+    quotes: Quotes,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Quotes {
+    bid: Option<f64>,
+    ask: Option<f64>,
+    last: Option<f64>,
+}
+
+impl SyntheticStop {
+    /// A stop triggered by the last price that sends a market order
+    ///
+    /// # Arguments
+    /// * req_id - Request id of the market data that drives the stop.  Must be unique per stop.
+    /// * contract - The contract to trade
+    /// * action - SELL or BUY
+    /// * quantity - Quantity of the order sent on trigger
+    /// * stop_price - The stop price
+    pub fn new(
+        req_id: i32,
+        contract: Contract,
+        action: &str,
+        quantity: f64,
+        stop_price: f64,
+    ) -> Self {
+        SyntheticStop {
+            req_id,
+            contract,
+            action: action.to_string(),
+            quantity,
+            stop_price,
+            trigger: StopTrigger::Last,
+            execution: StopExecution::Market,
+            state: StopState::Armed,
+            quotes: Quotes::default(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_trigger(mut self, trigger: StopTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_execution(mut self, execution: StopExecution) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn is_sell(&self) -> bool {
+        self.action == "SELL"
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn trigger_price(&self) -> Option<f64> {
+        match self.trigger {
+            StopTrigger::Last => self.quotes.last,
+            StopTrigger::NearSide if self.is_sell() => self.quotes.bid,
+            StopTrigger::NearSide => self.quotes.ask,
+            StopTrigger::Midpoint => match (self.quotes.bid, self.quotes.ask) {
+                (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+                _ => None,
+            },
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True if the stop is armed and the trigger price has reached the stop price
+    pub fn is_touched(&self) -> bool {
+        if self.state != StopState::Armed {
+            return false;
+        }
+        match self.trigger_price() {
+            Some(price) if price > 0.0 => {
+                if self.is_sell() {
+                    price <= self.stop_price
+                } else {
+                    price >= self.stop_price
+                }
+            }
+            _ => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The order sent when the stop is touched
+    pub fn order(&self) -> Order {
+        let (order_type, lmt_price) = match self.execution {
+            StopExecution::Market => ("MKT", UNSET_DOUBLE),
+            StopExecution::Limit { offset } if self.is_sell() => ("LMT", self.stop_price - offset),
+            StopExecution::Limit { offset } => ("LMT", self.stop_price + offset),
+        };
+        Order {
+            action: self.action.clone(),
+            total_quantity: self.quantity,
+            order_type: order_type.to_string(),
+            lmt_price,
+            ..Default::default()
+        }
+    }
+}
+
+//==================================================================================================
+/// Holds stops on the client for instruments or venues where native stops are unsupported or
+/// simulated poorly.
+///
+/// Feed every event to observe() and call poll() after each one.  Quotes are forgotten when
+/// TWS reports lost connectivity, so a stop never fires on a price from before the outage, and
+/// the state of every stop can be saved and loaded so a restart does not send an order twice.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SyntheticStopManager {
+    stops: Vec<SyntheticStop>,
+    next_order_id: i32,
+}
+
+impl SyntheticStopManager {
+    pub fn new() -> Self {
+        SyntheticStopManager::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a stop and arms it.  Request market data for it with subscribe() or req_mkt_data().
+    pub fn add(&mut self, mut stop: SyntheticStop) -> Result<(), IBKRApiLibError> {
+        if self.stop(stop.req_id).is_some() {
+            return Err(IBKRApiLibError::Config(format!(
+                "a synthetic stop with request id {} already exists",
+                stop.req_id
+            )));
+        }
+        if stop.action != "BUY" && stop.action != "SELL" {
+            return Err(IBKRApiLibError::Config(format!(
+                "synthetic stop action must be BUY or SELL, not {}",
+                stop.action
+            )));
+        }
+        stop.state = StopState::Armed;
+        stop.quotes = Quotes::default();
+        self.stops.push(stop);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn stop(&self, req_id: i32) -> Option<&SyntheticStop> {
+        self.stops.iter().find(|stop| stop.req_id == req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn stops(&self) -> &[SyntheticStop] {
+        &self.stops
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Moves the stop price of an armed stop, for example to trail it
+    pub fn set_stop_price(&mut self, req_id: i32, stop_price: f64) -> bool {
+        match self.armed_mut(req_id) {
+            Some(stop) => {
+                stop.stop_price = stop_price;
+                true
+            }
+            None => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Disarms a stop.  Its market data is not cancelled.
+    pub fn cancel(&mut self, req_id: i32) -> bool {
+        match self.armed_mut(req_id) {
+            Some(stop) => {
+                stop.state = StopState::Cancelled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Drops stops that are filled, cancelled or failed
+    pub fn remove_finished(&mut self) {
+        self.stops
+            .retain(|stop| matches!(stop.state, StopState::Armed | StopState::Triggered { .. }));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn armed_mut(&mut self, req_id: i32) -> Option<&mut SyntheticStop> {
+        self.stops
+            .iter_mut()
+            .find(|stop| stop.req_id == req_id && stop.state == StopState::Armed)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Updates quotes, order ids and the state of triggered orders
+    pub fn observe(&mut self, msg: &ServerRspMsg) {
+        match msg {
+            ServerRspMsg::NextValidId { order_id } => {
+                self.next_order_id = self.next_order_id.max(*order_id);
+            }
+            ServerRspMsg::TickPrice {
+                req_id,
+                tick_type,
+                price,
+                ..
+            } => {
+                if let Some(stop) = self.stops.iter_mut().find(|stop| stop.req_id == *req_id) {
+                    match tick_type {
+                        TickType::Bid | TickType::DelayedBid => stop.quotes.bid = Some(*price),
+                        TickType::Ask | TickType::DelayedAsk => stop.quotes.ask = Some(*price),
+                        TickType::Last | TickType::DelayedLast => stop.quotes.last = Some(*price),
+                        _ => (),
+                    }
+                }
+            }
+            ServerRspMsg::TickByTick { req_id, tick, .. } => {
+                if let Some(stop) = self.stops.iter_mut().find(|stop| stop.req_id == *req_id) {
+                    match tick {
+                        TickByTickData::Last { price, .. } => stop.quotes.last = Some(*price),
+                        TickByTickData::BidAsk {
+                            bid_price,
+                            ask_price,
+                            ..
+                        } => {
+                            stop.quotes.bid = Some(*bid_price);
+                            stop.quotes.ask = Some(*ask_price);
+                        }
+                        TickByTickData::MidPoint { .. } => (),
+                    }
+                }
+            }
+            ServerRspMsg::OrderStatus {
+                order_id, status, ..
+            } => {
+                self.next_order_id = self.next_order_id.max(order_id + 1);
+                for stop in self.stops.iter_mut() {
+                    if stop.state
+                        == (StopState::Triggered {
+                            order_id: *order_id,
+                        })
+                    {
+                        match status {
+                            OrderStatusKind::Filled => {
+                                stop.state = StopState::Filled {
+                                    order_id: *order_id,
+                                }
+                            }
+                            status if status.is_terminal() => {
+                                warn!(
+                                    "synthetic stop {} order {} ended as {}",
+                                    stop.req_id, order_id, status
+                                );
+                                stop.state = StopState::Failed {
+                                    order_id: *order_id,
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+            }
+            ServerRspMsg::ErrMsg { error_code, .. }
+                if *error_code == CONNECTIVITY_LOST
+                    || *error_code == CONNECTIVITY_RESTORED_DATA_LOST =>
+            {
+                self.clear_quotes();
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Forgets all quotes.  Call after reconnecting, before market data is requested again.
+    pub fn clear_quotes(&mut self) {
+        for stop in self.stops.iter_mut() {
+            stop.quotes = Quotes::default();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the orders of stops that have been touched and marks the stops as triggered.
+    /// poll() calls this and places the orders.
+    pub fn due_orders(&mut self) -> Vec<(i32, Contract, Order)> {
+        let mut due = Vec::new();
+        for stop in self.stops.iter_mut().filter(|stop| stop.is_touched()) {
+            let order_id = self.next_order_id;
+            self.next_order_id += 1;
+            stop.state = StopState::Triggered { order_id };
+            info!(
+                "synthetic stop {} touched at {}, sending order {}",
+                stop.req_id, stop.stop_price, order_id
+            );
+            due.push((order_id, stop.contract.clone(), stop.order()));
+        }
+        due
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Places the orders of all touched stops and returns their order ids
+    pub fn poll(&mut self, client: &mut EClient) -> Result<Vec<i32>, IBKRApiLibError> {
+        let mut placed = Vec::new();
+        for (order_id, contract, order) in self.due_orders() {
+            client.place_order(order_id, &contract, &order)?;
+            placed.push(order_id);
+        }
+        Ok(placed)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests market data for every armed stop, for example after a reconnect
+    pub fn subscribe(&self, client: &mut EClient) -> Result<(), IBKRApiLibError> {
+        for stop in self
+            .stops
+            .iter()
+            .filter(|stop| stop.state == StopState::Armed)
+        {
+            client.req_mkt_data(stop.req_id, &stop.contract, "", false, false, vec![])?;
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    #[cfg(feature = "toml")]
+    pub fn to_toml_string(&self) -> Result<String, IBKRApiLibError> {
+        toml::to_string(self).map_err(|e| IBKRApiLibError::Config(e.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str) -> Result<Self, IBKRApiLibError> {
+        toml::from_str(text).map_err(|e| IBKRApiLibError::Parse(e.to_string()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Saves the stops and their states.  Write after every poll() that placed an order so a
+    /// restart cannot send it again.
+    #[cfg(feature = "toml")]
+    pub fn save(&self, path: &Path) -> Result<(), IBKRApiLibError> {
+        std::fs::write(path, self.to_toml_string()?)?;
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Loads stops saved with save().  Quotes start empty.
+    #[cfg(feature = "toml")]
+    pub fn load(path: &Path) -> Result<Self, IBKRApiLibError> {
+        SyntheticStopManager::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,359 @@
+//! Replays captured TWS messages through the decoder and snapshots the decoded messages, so
+//! protocol compatibility across server versions is checked by tests.
+//!
+//! A capture is a text file with one section per server version:
+//!
+//! ```text
+//! # order status with and without the market cap price
+//! @server_version 130
+//! 3|6|7|Filled|100|0|150.25|1234|0|150.25|0||
+//! @server_version 151
+//! dump 00000007 3900 3100 3432 00
+//! ```
+//!
+//! Other lines are frames without their length prefix, with every field terminated by `|` in
+//! place of NUL.  Lines starting with `dump` hold a hex or base64 dump of length-prefixed
+//! frames, as accepted by frame_dump::decode_dump().  Blank lines and lines starting with `#`
+//! are ignored.  Recorded captures also have `@time 2024-01-02T14:30:00.125Z` lines, the time
+//! the frames after them were received, which the backtest replays them at.
+//!
+//! The ibtws_vectors binary writes the snapshots of every capture in testdata/captures to
+//! testdata/vectors, and the tests compare the decoder output against them.  New captures are
+//! recorded from a live session with a CaptureRecorder, as the ibtws_capture binary does.
+use std::fmt::{self, Debug, Display, Formatter, Write};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::core::common::ConnStatus;
+use crate::core::decoder::Decoder;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::event_queue::{event_queue, EventQueueConfig};
+use crate::core::frame_dump::{decode_dump, FrameDirection};
+use crate::core::messages::{FieldIterator, ServerRspMsg};
+
+const SECTION_PREFIX: &str = "@server_version";
+const DUMP_PREFIX: &str = "dump";
+const TIME_PREFIX: &str = "@time";
+
+/// Server versions every capture is expected to cover
+pub const VERSION_MATRIX: [i32; 3] = [130, 146, 151];
+
+//==================================================================================================
+/// The frames a server of one version sent
+#[derive(Clone, Debug, Default)]
+pub struct CaptureSection {
+    pub server_version: i32,
+    /// Frame payloads, fields terminated by NUL
+    pub frames: Vec<String>,
+    /// Receive times, as the index of the first frame received at a time and the time.  A time
+    /// applies to the frames up to the next one.
+    pub times: Vec<(usize, DateTime<Utc>)>,
+}
+
+impl CaptureSection {
+    /// The frames with the time they were received, None for frames before the first time
+    pub fn timed_frames(&self) -> Vec<(Option<DateTime<Utc>>, &str)> {
+        let mut times = self.times.iter().peekable();
+        let mut time = None;
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                while let Some((_, next)) = times.next_if(|(start, _)| *start <= index) {
+                    time = Some(*next);
+                }
+                (time, frame.as_str())
+            })
+            .collect()
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+pub struct Capture {
+    pub name: String,
+    pub sections: Vec<CaptureSection>,
+}
+
+impl Capture {
+    /// Parses the text of a capture file
+    ///
+    /// # Arguments
+    /// * name - Name of the capture, usually the file stem
+    /// * text - Contents of the capture file
+    pub fn parse(name: &str, text: &str) -> Result<Self, IBKRApiLibError> {
+        let mut sections: Vec<CaptureSection> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(version) = line.strip_prefix(SECTION_PREFIX) {
+                let server_version = version.trim().parse().map_err(|_| {
+                    IBKRApiLibError::Parse(format!(
+                        "{}:{}: invalid server version {:?}",
+                        name,
+                        index + 1,
+                        version.trim()
+                    ))
+                })?;
+                sections.push(CaptureSection {
+                    server_version,
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            let section = sections.last_mut().ok_or_else(|| {
+                IBKRApiLibError::Parse(format!(
+                    "{}:{}: frame before the first {} line",
+                    name,
+                    index + 1,
+                    SECTION_PREFIX
+                ))
+            })?;
+            if let Some(time) = line.strip_prefix(TIME_PREFIX) {
+                let time = DateTime::parse_from_rfc3339(time.trim()).map_err(|err| {
+                    IBKRApiLibError::Parse(format!(
+                        "{}:{}: invalid time {:?}: {}",
+                        name,
+                        index + 1,
+                        time.trim(),
+                        err
+                    ))
+                })?;
+                section
+                    .times
+                    .push((section.frames.len(), time.with_timezone(&Utc)));
+                continue;
+            }
+            match line.strip_prefix(DUMP_PREFIX) {
+                Some(dump) => {
+                    for frame in decode_dump(dump, None, FrameDirection::Incoming)? {
+                        section.frames.push(join_fields(&frame.fields));
+                    }
+                }
+                None => {
+                    let fields: Vec<&str> =
+                        line.strip_suffix('|').unwrap_or(line).split('|').collect();
+                    section.frames.push(join_fields(&fields));
+                }
+            }
+        }
+        Ok(Capture {
+            name: name.to_string(),
+            sections,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Server versions this capture has sections for
+    pub fn server_versions(&self) -> Vec<i32> {
+        self.sections
+            .iter()
+            .map(|section| section.server_version)
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Decodes every section and renders the decoded messages as text.  Equal captures always
+    /// give equal snapshots.
+    pub fn snapshot(&self) -> Result<String, IBKRApiLibError> {
+        let mut text = String::new();
+        for section in &self.sections {
+            writeln!(text, "{} {}", SECTION_PREFIX, section.server_version)
+                .expect("writing to a String cannot fail");
+            for msg in replay(section.server_version, &section.frames)? {
+                writeln!(text, "{:#?}", msg).expect("writing to a String cannot fail");
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Writes the capture in the format parse() reads.  Frames with a field that contains `|` or a
+/// line break are written as hex dumps.
+impl Display for Capture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for section in &self.sections {
+            writeln!(f, "{} {}", SECTION_PREFIX, section.server_version)?;
+            let mut times = section.times.iter().peekable();
+            for (index, frame) in section.frames.iter().enumerate() {
+                while let Some((_, time)) = times.next_if(|(start, _)| *start <= index) {
+                    writeln!(
+                        f,
+                        "{} {}",
+                        TIME_PREFIX,
+                        time.to_rfc3339_opts(SecondsFormat::Millis, true)
+                    )?;
+                }
+                let fields: Vec<&str> = FieldIterator::new(frame).collect();
+                if fields.iter().any(|field| field.contains(['|', '\n', '\r'])) {
+                    let prefix = (frame.len() as u32).to_be_bytes();
+                    let hex: String = prefix
+                        .iter()
+                        .chain(frame.as_bytes())
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect();
+                    writeln!(f, "{} {}", DUMP_PREFIX, hex)?;
+                } else {
+                    writeln!(f, "{}|", fields.join("|"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//==================================================================================================
+/// Records the frames a decoder receives into a Capture.  Clones share the capture, so the
+/// recorder given to EClientConfig::record_capture() can be read while the client runs.
+#[derive(Clone, Default)]
+pub struct CaptureRecorder {
+    capture: Arc<Mutex<Capture>>,
+}
+
+impl Debug for CaptureRecorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let capture = self.lock();
+        f.debug_struct("CaptureRecorder")
+            .field("name", &capture.name)
+            .field("server_versions", &capture.server_versions())
+            .finish()
+    }
+}
+
+impl CaptureRecorder {
+    /// # Arguments
+    /// * name - Name of the capture, usually the file stem it is saved under
+    pub fn new(name: &str) -> Self {
+        CaptureRecorder {
+            capture: Arc::new(Mutex::new(Capture {
+                name: name.to_string(),
+                sections: Vec::new(),
+            })),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn lock(&self) -> MutexGuard<'_, Capture> {
+        // frames are only appended, so a panicking thread cannot corrupt the capture
+        self.capture
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends a frame payload received now
+    pub fn record(&self, server_version: i32, frame: &str) {
+        self.record_at(server_version, frame, Utc::now())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends a frame payload, starting a new section when the server version changes, as it
+    /// does after a reconnect to another TWS.  Times are kept to the millisecond.
+    pub fn record_at(&self, server_version: i32, frame: &str, received: DateTime<Utc>) {
+        let received = DateTime::from_timestamp_millis(received.timestamp_millis())
+            .expect("a time truncated to milliseconds is in range");
+        let mut capture = self.lock();
+        let section = match capture.sections.last_mut() {
+            Some(section) if section.server_version == server_version => section,
+            _ => {
+                capture.sections.push(CaptureSection {
+                    server_version,
+                    ..Default::default()
+                });
+                capture.sections.last_mut().unwrap()
+            }
+        };
+        if section.times.last().map(|(_, time)| *time) != Some(received) {
+            section.times.push((section.frames.len(), received));
+        }
+        section.frames.push(frame.to_string());
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Frames recorded so far
+    pub fn len(&self) -> usize {
+        self.lock()
+            .sections
+            .iter()
+            .map(|section| section.frames.len())
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// A copy of the capture recorded so far
+    pub fn capture(&self) -> Capture {
+        self.lock().clone()
+    }
+}
+
+//==================================================================================================
+fn join_fields<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| format!("{}\0", field.as_ref()))
+        .collect()
+}
+
+//==================================================================================================
+/// Runs frames through a decoder for the given server version and returns the decoded messages
+/// in order.  A frame the decoder rejects fails the whole replay.
+pub fn replay(
+    server_version: i32,
+    frames: &[String],
+) -> Result<Vec<ServerRspMsg>, IBKRApiLibError> {
+    let mut msgs = Vec::new();
+    replay_with(server_version, frames.iter(), |_, msg| msgs.push(msg))?;
+    Ok(msgs)
+}
+
+//==================================================================================================
+/// A decoded message and the time its frame was received, if the capture has it
+pub type TimedMsg = (Option<DateTime<Utc>>, ServerRspMsg);
+
+//==================================================================================================
+/// Like replay(), with the time each message was received
+pub fn replay_timed(section: &CaptureSection) -> Result<Vec<TimedMsg>, IBKRApiLibError> {
+    let timed = section.timed_frames();
+    let mut msgs = Vec::new();
+    replay_with(
+        section.server_version,
+        timed.iter().map(|(_, frame)| *frame),
+        |index, msg| msgs.push((timed[index].0, msg)),
+    )?;
+    Ok(msgs)
+}
+
+//----------------------------------------------------------------------------------------------
+/// Calls `decoded` with the index of the frame and each message decoded from it
+fn replay_with<S: AsRef<str>>(
+    server_version: i32,
+    frames: impl IntoIterator<Item = S>,
+    mut decoded: impl FnMut(usize, ServerRspMsg),
+) -> Result<(), IBKRApiLibError> {
+    let (_, msg_queue) = channel::<String>();
+    let (sender, receiver) = event_queue(EventQueueConfig::default());
+    let mut decoder = Decoder::new(
+        msg_queue,
+        sender,
+        server_version,
+        Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+    );
+
+    for (index, frame) in frames.into_iter().enumerate() {
+        decoder.interpret(frame.as_ref())?;
+        while let Ok(msg) = receiver.try_recv() {
+            decoded(index, msg);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Dates and times in the formats TWS reads and writes
+//!
+//! Order times such as good_till_date and good_after_time, and the end time of historical data
+//! requests, are sent as "yyyymmdd hh:mm:ss zone", where zone is a time zone name such as
+//! US/Eastern or UTC.  TwsTime writes a chrono DateTime in that format, and parse_zoned_time()
+//! reads it back.
+//!
+//! Times in responses come in more formats: epoch seconds, "yyyymmdd" for daily bars,
+//! "yyyymmdd hh:mm:ss" in the time zone of the TWS session, with or without a zone name after
+//! it, and "yyyy-MM-dd HH:mm:ss.f" for historical news.  parse_tws_time() reads any of them as
+//! UTC, which is how the decoder fills the times of BarData, Execution, HeadTimestamp and
+//! HistoricalNews.  With the raw-times feature, the text TWS sent is kept next to each of them.
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::core::errors::IBKRApiLibError;
+use crate::core::trading_hours::parse_time_zone;
+
+/// Date and time part of a zoned time
+const DATE_TIME_FORMAT: &str = "%Y%m%d %H:%M:%S";
+/// A UTC time without a zone name, which TWS also accepts
+const UTC_FORMAT: &str = "%Y%m%d-%H:%M:%S";
+/// Date of daily and longer bars
+const DATE_FORMAT: &str = "%Y%m%d";
+/// Time of historical news
+const NEWS_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+//==================================================================================================
+/// A time zone TWS knows by name
+pub trait TwsTimeZone: TimeZone {
+    /// The name sent after the time, such as US/Eastern
+    fn tws_name(&self) -> &str;
+}
+
+impl TwsTimeZone for Utc {
+    fn tws_name(&self) -> &str {
+        "UTC"
+    }
+}
+
+impl TwsTimeZone for Tz {
+    fn tws_name(&self) -> &str {
+        self.name()
+    }
+}
+
+//==================================================================================================
+/// A value that can be sent as a time to TWS.  Strings are sent as they are, so requests that
+/// take a time accept both text in the TWS format and a chrono DateTime.
+pub trait TwsTime {
+    fn to_tws_time(&self) -> String;
+}
+
+impl TwsTime for str {
+    fn to_tws_time(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TwsTime for String {
+    fn to_tws_time(&self) -> String {
+        self.clone()
+    }
+}
+
+impl<Z: TwsTimeZone> TwsTime for DateTime<Z> {
+    /// Writes the time as "yyyymmdd hh:mm:ss zone", in the time zone of the DateTime
+    fn to_tws_time(&self) -> String {
+        format!(
+            "{} {}",
+            self.naive_local().format(DATE_TIME_FORMAT),
+            self.timezone().tws_name()
+        )
+    }
+}
+
+//==================================================================================================
+/// Reads a time written as "yyyymmdd hh:mm:ss zone", or as "yyyymmdd-hh:mm:ss" in UTC
+///
+/// # Arguments
+/// * time - The time as TWS sends it, such as "20240105 16:00:00 US/Eastern"
+pub fn parse_zoned_time(time: &str) -> Result<DateTime<Tz>, IBKRApiLibError> {
+    let invalid = |reason: &str| {
+        IBKRApiLibError::Parse(format!("{:?} is not a valid TWS time: {}", time, reason))
+    };
+    let time = time.trim();
+    if let Ok(utc) = NaiveDateTime::parse_from_str(time, UTC_FORMAT) {
+        return Ok(Tz::UTC.from_utc_datetime(&utc));
+    }
+    let mut parts = time.split_whitespace();
+    let (date, clock, zone) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(date), Some(clock), Some(zone), None) => (date, clock, zone),
+        (Some(_), Some(_), None, None) => return Err(invalid("it has no time zone")),
+        _ => return Err(invalid("expected yyyymmdd hh:mm:ss zone")),
+    };
+    let zone = Tz::from_str(zone).map_err(|_| invalid("unknown time zone"))?;
+    let local = NaiveDateTime::parse_from_str(&format!("{} {}", date, clock), DATE_TIME_FORMAT)
+        .map_err(|_| invalid("expected yyyymmdd hh:mm:ss zone"))?;
+    zone.from_local_datetime(&local)
+        .earliest()
+        .ok_or_else(|| invalid("the time does not exist in its time zone"))
+}
+
+//==================================================================================================
+/// Reads a time in any format TWS sends in responses, or returns None if it is in none of them
+///
+/// Epoch seconds and "yyyymmdd-hh:mm:ss" are UTC.  "yyyymmdd hh:mm:ss" is followed by a zone
+/// name or is in zone.  A date without a time is midnight UTC, so that date_naive() gives the
+/// date TWS sent back whatever the zone.
+///
+/// # Arguments
+/// * time - The time as TWS sent it
+/// * zone - The time zone of times sent without a zone name, usually that of the TWS session
+pub fn parse_tws_time(time: &str, zone: Tz) -> Option<DateTime<Utc>> {
+    parse_session_time(time, Some(zone))
+}
+
+//==================================================================================================
+/// Reads a time as parse_tws_time() does, in the zone of the TWS session if it is known.  Times
+/// sent without a zone name are None when it is not.
+pub(crate) fn parse_session_time(time: &str, zone: Option<Tz>) -> Option<DateTime<Utc>> {
+    let time = time.trim();
+    if time.is_empty() {
+        return None;
+    }
+    if time.bytes().all(|b| b.is_ascii_digit()) {
+        if time.len() == 8 {
+            let date = NaiveDate::parse_from_str(time, DATE_FORMAT).ok()?;
+            return Some(date.and_time(NaiveTime::MIN).and_utc());
+        }
+        return DateTime::from_timestamp(time.parse().ok()?, 0);
+    }
+    if let Ok(utc) = NaiveDateTime::parse_from_str(time, UTC_FORMAT) {
+        return Some(utc.and_utc());
+    }
+    if let Ok(local) = NaiveDateTime::parse_from_str(time, NEWS_FORMAT) {
+        return local_to_utc(zone?, &local);
+    }
+    let mut parts = time.split_whitespace();
+    let (date, clock) = (parts.next()?, parts.next()?);
+    let zone = match parts.next() {
+        Some(name) => parse_time_zone(name).ok()?,
+        None => zone?,
+    };
+    let local =
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, clock), DATE_TIME_FORMAT).ok()?;
+    local_to_utc(zone, &local)
+}
+
+//==================================================================================================
+/// The zone of a time TWS sends, such as the connection time of the handshake.  None if it has no
+/// zone name or the zone is unknown.
+pub fn zone_of(time: &str) -> Option<Tz> {
+    time.split_whitespace()
+        .nth(2)
+        .and_then(|name| parse_time_zone(name).ok())
+}
+
+//==================================================================================================
+/// Text of a decoded time for Display impls, "unknown" if it could not be read
+pub fn display_time(time: &Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => time.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+//==================================================================================================
+fn local_to_utc(zone: Tz, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
@@ -0,0 +1,194 @@
+//! Parses the trading_hours and liquid_hours of ContractDetails into sessions
+//!
+//! TWS sends the sessions of the coming days in the contract's time zone, in one of two
+//! formats.  Newer servers give the date on both ends of a session, so sessions can span
+//! midnight:
+//!
+//! `20240102:0930-20240102:1600;20240103:CLOSED`
+//!
+//! Older ones give the date once, followed by one or more time ranges:
+//!
+//! `20240102:0930-1200,1300-1600;20240103:CLOSED`
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::core::errors::IBKRApiLibError;
+
+/// IB time zone ids that chrono-tz does not know or reads as fixed offsets without daylight
+/// saving, and the zone IB means by them.  IST is India Standard Time and BST British Summer Time.
+const TIME_ZONE_ALIASES: [(&str, &str); 18] = [
+    ("EST", "America/New_York"),
+    ("EDT", "America/New_York"),
+    ("CST", "America/Chicago"),
+    ("CDT", "America/Chicago"),
+    ("MST", "America/Denver"),
+    ("MDT", "America/Denver"),
+    ("PST", "America/Los_Angeles"),
+    ("PDT", "America/Los_Angeles"),
+    ("BST", "Europe/London"),
+    ("CEST", "Europe/Brussels"),
+    ("IST", "Asia/Kolkata"),
+    ("SGT", "Asia/Singapore"),
+    ("KST", "Asia/Seoul"),
+    ("AEST", "Australia/Sydney"),
+    ("AEDT", "Australia/Sydney"),
+    ("JST", "Asia/Tokyo"),
+    ("HKT", "Asia/Hong_Kong"),
+    ("MET", "Europe/Brussels"),
+];
+
+//==================================================================================================
+/// Reads an IB time zone id such as "US/Eastern" or "EST (Eastern Standard Time)"
+pub fn parse_time_zone(time_zone_id: &str) -> Result<Tz, IBKRApiLibError> {
+    let id = time_zone_id.split_whitespace().next().unwrap_or("");
+    let id = TIME_ZONE_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(id))
+        .map_or(id, |(_, zone)| *zone);
+    id.parse()
+        .map_err(|_| IBKRApiLibError::Parse(format!("unknown time zone {:?}", time_zone_id)))
+}
+
+//==================================================================================================
+/// A period during which the contract trades
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.start, self.end)
+    }
+}
+
+//==================================================================================================
+/// The sessions of a contract over the days TWS reported, sorted by start
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradingHours {
+    pub time_zone: Tz,
+    pub sessions: Vec<Session>,
+    /// Days reported as closed, in the contract's time zone
+    pub closed_days: Vec<NaiveDate>,
+}
+
+impl TradingHours {
+    /// Parses a trading_hours or liquid_hours string
+    ///
+    /// # Arguments
+    /// * hours - The string from ContractDetails
+    /// * time_zone_id - The time_zone_id from ContractDetails, which the times are in
+    pub fn parse(hours: &str, time_zone_id: &str) -> Result<Self, IBKRApiLibError> {
+        let time_zone = parse_time_zone(time_zone_id)?;
+        let mut trading_hours = TradingHours {
+            time_zone,
+            sessions: Vec::new(),
+            closed_days: Vec::new(),
+        };
+
+        for day in hours
+            .split(';')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+        {
+            let invalid = || IBKRApiLibError::Parse(format!("invalid trading hours {:?}", day));
+            let (date, ranges) = day.split_once(':').ok_or_else(invalid)?;
+            let date = NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| invalid())?;
+            if ranges.eq_ignore_ascii_case("CLOSED") {
+                trading_hours.closed_days.push(date);
+                continue;
+            }
+            for range in ranges.split(',') {
+                let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+                let start = local_time(date, start).ok_or_else(invalid)?;
+                let end = match end.split_once(':') {
+                    Some((end_date, end_time)) => {
+                        let end_date =
+                            NaiveDate::parse_from_str(end_date, "%Y%m%d").map_err(|_| invalid())?;
+                        local_time(end_date, end_time).ok_or_else(invalid)?
+                    }
+                    None => {
+                        let end = local_time(date, end).ok_or_else(invalid)?;
+                        // an end before the start is on the next day
+                        if end <= start {
+                            end + chrono::Duration::days(1)
+                        } else {
+                            end
+                        }
+                    }
+                };
+                trading_hours.sessions.push(Session {
+                    start: to_utc(&time_zone, &start).ok_or_else(invalid)?,
+                    end: to_utc(&time_zone, &end).ok_or_else(invalid)?,
+                });
+            }
+        }
+        trading_hours.sessions.sort_by_key(|session| session.start);
+        Ok(trading_hours)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The session in progress at a time
+    pub fn session_at(&self, time: DateTime<Utc>) -> Option<&Session> {
+        self.sessions.iter().find(|session| session.contains(time))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_market_open(&self, now: DateTime<Utc>) -> bool {
+        self.session_at(now).is_some()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The start of the first session after a time.  None if TWS reported no later session.
+    pub fn next_open(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.sessions
+            .iter()
+            .map(|session| session.start)
+            .find(|start| *start > now)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The end of the session in progress, or of the next one if the market is closed
+    pub fn next_close(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.sessions
+            .iter()
+            .map(|session| session.end)
+            .find(|end| *end > now)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Whether TWS reported a day as closed, such as a holiday
+    pub fn is_closed_on(&self, date: NaiveDate) -> bool {
+        self.closed_days.contains(&date)
+    }
+}
+
+//==================================================================================================
+/// Reads an HHMM time on a date.  2400 is midnight at the end of the date.
+fn local_time(date: NaiveDate, time: &str) -> Option<NaiveDateTime> {
+    if time == "2400" {
+        return date.succ_opt()?.and_hms_opt(0, 0, 0);
+    }
+    NaiveTime::parse_from_str(time, "%H%M")
+        .ok()
+        .map(|time| date.and_time(time))
+}
+
+//==================================================================================================
+/// Converts a local time, taking the earlier time when a clock change makes it ambiguous and
+/// failing when it does not exist
+fn to_utc(time_zone: &Tz, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    time_zone
+        .from_local_datetime(local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
@@ -0,0 +1,230 @@
+//! Newtypes that keep prices, sizes, notional amounts and percentages apart
+use std::fmt::{Display, Error, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::order::Order;
+
+//==================================================================================================
+/// A price per unit of an instrument
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct Price(f64);
+
+impl Price {
+    pub fn new(value: f64) -> Self {
+        Price(value)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Rounds to the nearest multiple of `increment`, for example the minimum tick
+    pub fn round_to(self, increment: Price) -> Price {
+        if increment.0 <= 0.0 {
+            return self;
+        }
+        Price((self.0 / increment.0).round() * increment.0)
+    }
+}
+
+//==================================================================================================
+/// A quantity of shares, contracts or currency units
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct Size(f64);
+
+impl Size {
+    pub fn new(value: f64) -> Self {
+        Size(value)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn abs(self) -> Size {
+        Size(self.0.abs())
+    }
+}
+
+//==================================================================================================
+/// A price multiplied by a size, in the instrument's currency
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct Notional(f64);
+
+impl Notional {
+    pub fn new(value: f64) -> Self {
+        Notional(value)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn abs(self) -> Notional {
+        Notional(self.0.abs())
+    }
+}
+
+//==================================================================================================
+/// A percentage, stored in percentage points: 1.5 means 1.5%
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct Percent(f64);
+
+impl Percent {
+    pub fn new(points: f64) -> Self {
+        Percent(points)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// 0.015 becomes 1.5%
+    pub fn from_fraction(fraction: f64) -> Self {
+        Percent(fraction * 100.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn fraction(self) -> f64 {
+        self.0 / 100.0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// This percentage of a price, as an absolute price offset
+    pub fn of_price(self, price: Price) -> Price {
+        Price(price.0 * self.fraction())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn of_notional(self, notional: Notional) -> Notional {
+        Notional(notional.0 * self.fraction())
+    }
+}
+
+//==================================================================================================
+macro_rules! impl_unit_ops {
+    ($unit:ident, $suffix:expr) => {
+        impl Add for $unit {
+            type Output = $unit;
+            fn add(self, other: $unit) -> $unit {
+                $unit(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $unit {
+            type Output = $unit;
+            fn sub(self, other: $unit) -> $unit {
+                $unit(self.0 - other.0)
+            }
+        }
+
+        impl Neg for $unit {
+            type Output = $unit;
+            fn neg(self) -> $unit {
+                $unit(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $unit {
+            type Output = $unit;
+            fn mul(self, factor: f64) -> $unit {
+                $unit(self.0 * factor)
+            }
+        }
+
+        impl Div<f64> for $unit {
+            type Output = $unit;
+            fn div(self, divisor: f64) -> $unit {
+                $unit(self.0 / divisor)
+            }
+        }
+
+        impl Display for $unit {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+                write!(f, "{}{}", self.0, $suffix)
+            }
+        }
+    };
+}
+
+impl_unit_ops!(Price, "");
+impl_unit_ops!(Size, "");
+impl_unit_ops!(Notional, "");
+impl_unit_ops!(Percent, "%");
+
+impl Mul<Size> for Price {
+    type Output = Notional;
+    fn mul(self, size: Size) -> Notional {
+        Notional(self.0 * size.0)
+    }
+}
+
+impl Mul<Price> for Size {
+    type Output = Notional;
+    fn mul(self, price: Price) -> Notional {
+        Notional(self.0 * price.0)
+    }
+}
+
+impl Div<Price> for Notional {
+    type Output = Size;
+    fn div(self, price: Price) -> Size {
+        Size(self.0 / price.0)
+    }
+}
+
+impl Div<Size> for Notional {
+    type Output = Price;
+    fn div(self, size: Size) -> Price {
+        Price(self.0 / size.0)
+    }
+}
+
+//==================================================================================================
+/// Distance of a trailing stop from the market.  TWS takes an amount in aux_price and a
+/// percentage in trailing_percent, and mixing them up silently creates a very different order.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TrailingOffset {
+    Amount(Price),
+    Percent(Percent),
+}
+
+impl TrailingOffset {
+    /// Sets the matching field of a TRAIL or TRAIL LIMIT order and clears the other one
+    pub fn apply(self, order: &mut Order) {
+        match self {
+            TrailingOffset::Amount(amount) => {
+                order.aux_price = amount.value();
+                order.trailing_percent = UNSET_DOUBLE;
+            }
+            TrailingOffset::Percent(percent) => {
+                order.trailing_percent = percent.value();
+                order.aux_price = UNSET_DOUBLE;
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The offset as a price distance from `reference`
+    pub fn distance_from(self, reference: Price) -> Price {
+        match self {
+            TrailingOffset::Amount(amount) => amount,
+            TrailingOffset::Percent(percent) => percent.of_price(reference),
+        }
+    }
+}
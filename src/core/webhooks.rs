@@ -0,0 +1,434 @@
+//! Posts order lifecycle events and account alerts to webhook URLs as JSON
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::core::errors::IBKRApiLibError;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::OrderStatusKind;
+
+/// Error code TWS sends when an order is rejected
+const ORDER_REJECTED: i32 = 201;
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    OrderPlaced,
+    OrderPartiallyFilled,
+    OrderFilled,
+    OrderCancelled,
+    OrderRejected,
+    AccountAlert,
+}
+
+//==================================================================================================
+/// The JSON payload posted to webhooks.  `text` is a one-line summary, which is what Slack
+/// incoming webhooks display.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WebhookEvent {
+    pub kind: Option<WebhookEventKind>,
+    pub text: String,
+    pub order_id: Option<i32>,
+    pub perm_id: Option<i64>,
+    pub account: String,
+    pub symbol: String,
+    pub action: String,
+    pub quantity: Option<f64>,
+    pub status: String,
+    pub filled: Option<f64>,
+    pub remaining: Option<f64>,
+    pub avg_fill_price: Option<f64>,
+    pub error_code: Option<i32>,
+    pub message: String,
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+struct OrderInfo {
+    account: String,
+    symbol: String,
+    action: String,
+    quantity: f64,
+    status: Option<OrderStatusKind>,
+    filled: f64,
+    finished: bool,
+}
+
+/// Turns OpenOrder, OrderStatus and ErrMsg events into order lifecycle events, dropping the
+/// duplicate statuses TWS often sends
+#[derive(Clone, Debug, Default)]
+pub struct OrderEventTracker {
+    orders: HashMap<i32, OrderInfo>,
+    alert_codes: HashSet<i32>,
+}
+
+impl OrderEventTracker {
+    /// # Arguments
+    /// * alert_codes - Error codes reported as account alerts
+    pub fn new(alert_codes: &[i32]) -> Self {
+        OrderEventTracker {
+            orders: HashMap::new(),
+            alert_codes: alert_codes.iter().copied().collect(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> Option<WebhookEvent> {
+        match msg {
+            ServerRspMsg::OpenOrder {
+                order_id,
+                contract,
+                order,
+                ..
+            } => {
+                if self.orders.contains_key(order_id) {
+                    return None;
+                }
+                let info = OrderInfo {
+                    account: order.account.clone(),
+                    symbol: contract.symbol.clone(),
+                    action: order.action.clone(),
+                    quantity: order.total_quantity,
+                    ..Default::default()
+                };
+                let event = self.event(WebhookEventKind::OrderPlaced, *order_id, &info);
+                self.orders.insert(*order_id, info);
+                Some(event)
+            }
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                filled,
+                remaining,
+                avg_fill_price,
+                perm_id,
+                ..
+            } => {
+                let info = self.orders.entry(*order_id).or_default();
+                let duplicate = info.status.as_ref() == Some(status) && info.filled == *filled;
+                if info.finished || duplicate {
+                    return None;
+                }
+                let partially_filled = *filled > info.filled;
+                info.status = Some(status.clone());
+                info.filled = *filled;
+                let kind = match status {
+                    OrderStatusKind::Filled => WebhookEventKind::OrderFilled,
+                    OrderStatusKind::Cancelled | OrderStatusKind::ApiCancelled => {
+                        WebhookEventKind::OrderCancelled
+                    }
+                    OrderStatusKind::Inactive => WebhookEventKind::OrderRejected,
+                    _ if partially_filled => WebhookEventKind::OrderPartiallyFilled,
+                    _ => return None,
+                };
+                info.finished = kind != WebhookEventKind::OrderPartiallyFilled;
+
+                let info = info.clone();
+                let mut event = self.event(kind, *order_id, &info);
+                event.perm_id = Some(*perm_id);
+                event.filled = Some(*filled);
+                event.remaining = Some(*remaining);
+                event.avg_fill_price = Some(*avg_fill_price);
+                if kind != WebhookEventKind::OrderCancelled {
+                    event.text = format!("{} ({} @ {})", event.text, filled, avg_fill_price);
+                }
+                Some(event)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } => {
+                if *error_code == ORDER_REJECTED {
+                    let info = self.orders.entry(*req_id).or_default();
+                    if info.finished {
+                        return None;
+                    }
+                    info.finished = true;
+                    let info = info.clone();
+                    let mut event = self.event(WebhookEventKind::OrderRejected, *req_id, &info);
+                    event.error_code = Some(*error_code);
+                    event.message = error_str.clone();
+                    event.text = format!("{}: {}", event.text, error_str);
+                    Some(event)
+                } else if self.alert_codes.contains(error_code) {
+                    Some(WebhookEvent {
+                        kind: Some(WebhookEventKind::AccountAlert),
+                        text: format!("Alert {}: {}", error_code, error_str),
+                        error_code: Some(*error_code),
+                        message: error_str.clone(),
+                        ..Default::default()
+                    })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn event(&self, kind: WebhookEventKind, order_id: i32, info: &OrderInfo) -> WebhookEvent {
+        let label = match kind {
+            WebhookEventKind::OrderPlaced => "placed",
+            WebhookEventKind::OrderPartiallyFilled => "partially filled",
+            WebhookEventKind::OrderFilled => "filled",
+            WebhookEventKind::OrderCancelled => "cancelled",
+            WebhookEventKind::OrderRejected => "rejected",
+            WebhookEventKind::AccountAlert => "alert",
+        };
+        WebhookEvent {
+            kind: Some(kind),
+            text: format!(
+                "Order {} {}: {} {} {}",
+                order_id, label, info.action, info.quantity, info.symbol
+            ),
+            order_id: Some(order_id),
+            account: info.account.clone(),
+            symbol: info.symbol.clone(),
+            action: info.action.clone(),
+            quantity: Some(info.quantity),
+            status: info
+                .status
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Event kinds posted to this endpoint.  Empty means all.
+    #[serde(default)]
+    pub kinds: Vec<WebhookEventKind>,
+    /// Extra headers, for example an Authorization header
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+}
+
+impl WebhookEndpoint {
+    pub fn new(url: &str) -> Self {
+        WebhookEndpoint {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn accepts(&self, event: &WebhookEvent) -> bool {
+        self.kinds.is_empty() || matches!(event.kind, Some(kind) if self.kinds.contains(&kind))
+    }
+}
+
+//==================================================================================================
+/// Delivery attempts per event and endpoint, with exponential backoff between them
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default)]
+pub struct WebhookConfig {
+    pub endpoints: Vec<WebhookEndpoint>,
+    pub retry: RetryPolicy,
+    /// Error codes reported as account alerts, for example 2148 for margin cushion warnings
+    pub alert_codes: Vec<i32>,
+}
+
+//==================================================================================================
+/// Sends a JSON body to a URL.  Implemented over HTTP by HttpTransport.
+pub trait WebhookTransport: Send {
+    fn post(&self, url: &str, headers: &[(String, String)], body: &str) -> Result<(), String>;
+}
+
+/// Posts with ureq.  Any status of 400 or above is a failure.
+pub struct HttpTransport {
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    pub fn new(timeout: Duration) -> Self {
+        HttpTransport {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+}
+
+impl WebhookTransport for HttpTransport {
+    fn post(&self, url: &str, headers: &[(String, String)], body: &str) -> Result<(), String> {
+        let mut request = self.agent.post(url).set("Content-Type", "application/json");
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(body)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+//==================================================================================================
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeliveryStats {
+    pub delivered: u64,
+    pub retried: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    delivered: AtomicU64,
+    retried: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Feeds events through an OrderEventTracker and posts the results from a background thread,
+/// so a slow or unreachable webhook never blocks the event loop
+pub struct WebhookNotifier {
+    tracker: OrderEventTracker,
+    sender: Option<Sender<WebhookEvent>>,
+    worker: Option<JoinHandle<()>>,
+    stats: Arc<Counters>,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        WebhookNotifier::with_transport(
+            config,
+            Box::new(HttpTransport::new(Duration::from_secs(10))),
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn with_transport(config: WebhookConfig, transport: Box<dyn WebhookTransport>) -> Self {
+        let (sender, receiver) = channel::<WebhookEvent>();
+        let stats = Arc::new(Counters::default());
+        let worker_stats = stats.clone();
+        let tracker = OrderEventTracker::new(&config.alert_codes);
+
+        let worker = thread::spawn(move || {
+            for event in receiver.iter() {
+                let body = match serde_json::to_string(&event) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        error!("Could not serialize webhook event: {}", e);
+                        continue;
+                    }
+                };
+                for endpoint in config.endpoints.iter().filter(|e| e.accepts(&event)) {
+                    deliver(
+                        transport.as_ref(),
+                        endpoint,
+                        &body,
+                        &config.retry,
+                        &worker_stats,
+                    );
+                }
+            }
+        });
+
+        WebhookNotifier {
+            tracker,
+            sender: Some(sender),
+            worker: Some(worker),
+            stats,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Posts the lifecycle event carried by a message, if any
+    pub fn observe(&mut self, msg: &ServerRspMsg) -> Result<(), IBKRApiLibError> {
+        match self.tracker.observe(msg) {
+            Some(event) => self.notify(event),
+            None => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Posts an event built by the caller
+    pub fn notify(&self, event: WebhookEvent) -> Result<(), IBKRApiLibError> {
+        match self.sender.as_ref() {
+            Some(sender) => sender
+                .send(event)
+                .map_err(|_| IBKRApiLibError::Config("webhook worker has stopped".to_string())),
+            None => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn stats(&self) -> DeliveryStats {
+        DeliveryStats {
+            delivered: self.stats.delivered.load(Ordering::SeqCst),
+            retried: self.stats.retried.load(Ordering::SeqCst),
+            failed: self.stats.failed.load(Ordering::SeqCst),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits until every queued event has been delivered or has run out of retries
+    pub fn shutdown(mut self) -> DeliveryStats {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Webhook worker panicked");
+            }
+        }
+        self.stats()
+    }
+}
+
+//==================================================================================================
+fn deliver(
+    transport: &dyn WebhookTransport,
+    endpoint: &WebhookEndpoint,
+    body: &str,
+    retry: &RetryPolicy,
+    stats: &Counters,
+) {
+    let mut backoff = retry.initial_backoff;
+    for attempt in 1..=retry.max_attempts.max(1) {
+        match transport.post(&endpoint.url, &endpoint.headers, body) {
+            Ok(()) => {
+                stats.delivered.fetch_add(1, Ordering::SeqCst);
+                return;
+            }
+            Err(e) if attempt < retry.max_attempts => {
+                warn!(
+                    "Webhook {} failed (attempt {}): {}, retrying in {:?}",
+                    endpoint.url, attempt, e, backoff
+                );
+                stats.retried.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(retry.max_backoff);
+            }
+            Err(e) => {
+                error!("Webhook {} failed, giving up: {}", endpoint.url, e);
+            }
+        }
+    }
+    stats.failed.fetch_add(1, Ordering::SeqCst);
+}
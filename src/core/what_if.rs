@@ -0,0 +1,114 @@
+//! Previews an order as a future
+//!
+//! TWS checks a what-if order without transmitting it and answers with an OpenOrder whose
+//! OrderState carries the margin impact and the commission the order would have.
+//! EClient::what_if_order() asks for an order id, and the future it returns places the preview
+//! under that id once it arrives, then resolves to the OrderState.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::core::client::{Collected, EClient};
+use crate::core::contract::Contract;
+use crate::core::errors::{is_informational, IBKRApiLibError, TwsApiReportableError};
+use crate::core::fill_report::ORDER_NOTICES;
+use crate::core::messages::ServerRspMsg;
+use crate::core::order::{Order, OrderState};
+
+//==================================================================================================
+/// Future returned by EClient::what_if_order().  It resolves to the OrderState of the preview,
+/// or to the error TWS rejected it with, and leaves other events for get_event().
+pub struct WhatIfOrder<'a> {
+    client: &'a mut EClient,
+    contract: Contract,
+    preview: Order,
+    order_id: Option<i32>,
+}
+
+impl<'a> WhatIfOrder<'a> {
+    pub(crate) fn new(client: &'a mut EClient, contract: Contract, preview: Order) -> Self {
+        WhatIfOrder {
+            client,
+            contract,
+            preview,
+            order_id: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// The id the preview was placed under, None until TWS has sent it
+    pub fn order_id(&self) -> Option<i32> {
+        self.order_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Places the preview once NextValidId has arrived.  Returns its order id.
+    fn place_preview(&mut self) -> Result<i32, IBKRApiLibError> {
+        if let Some(order_id) = self.order_id {
+            return Ok(order_id);
+        }
+        let mut order_id = None;
+        self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::NextValidId { order_id: id } => {
+                order_id = Some(*id);
+                Ok(Collected::Finished)
+            }
+            _ => Ok(Collected::Ignored),
+        })?;
+        let order_id = order_id.expect("collect() only finishes on NextValidId");
+        self.client
+            .place_order(order_id, &self.contract, &self.preview)?;
+        self.order_id = Some(order_id);
+        Ok(order_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn read_order_state(&mut self, order_id: i32) -> Result<OrderState, IBKRApiLibError> {
+        let mut order_state = None;
+        self.client.collect(Duration::ZERO, |msg| match msg {
+            ServerRspMsg::OpenOrder {
+                order_id: id,
+                order_state: state,
+                ..
+            } if *id == order_id => {
+                order_state = Some(state.clone());
+                Ok(Collected::Finished)
+            }
+            ServerRspMsg::ErrMsg {
+                req_id: id,
+                error_code,
+                error_str,
+            } if *id == order_id
+                && !is_informational(*error_code)
+                && !ORDER_NOTICES.contains(error_code) =>
+            {
+                Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                    order_id,
+                    error_code.to_string(),
+                    error_str.clone(),
+                )))
+            }
+            _ => Ok(Collected::Ignored),
+        })?;
+        Ok(order_state.expect("collect() only finishes on OpenOrder"))
+    }
+}
+
+impl Future for WhatIfOrder<'_> {
+    type Output = Result<OrderState, IBKRApiLibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self
+            .place_preview()
+            .and_then(|order_id| self.read_order_state(order_id));
+        match result {
+            Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout)) => {
+                self.client.wake_on_event(cx.waker());
+                Poll::Pending
+            }
+            result => Poll::Ready(result),
+        }
+    }
+}
@@ -1,16 +1,17 @@
 use crate::{
     core::client::EClient,
     core::common::{
-        BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode, HistogramData,
-        HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, MarketDataTypeEnum, NewsProvider,
-        PriceIncrement, RealTimeBar, SmartComponent, TagValue, TickAttrib, TickAttribBidAsk,
-        TickAttribLast, TickByTickType, TickType,
+        BarData, BarSize, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
+        HistogramData, HistoricalDuration, HistoricalTick, HistoricalTickBidAsk,
+        HistoricalTickLast, MarketDataTypeEnum, NewsProvider, PriceIncrement, RealTimeBar,
+        SmartComponent, TagValue, TickAttrib, TickAttribBidAsk, TickAttribLast, TickByTickType,
+        TickType,
     },
     core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract},
     core::errors::IBKRApiLibError,
     core::execution::{Execution, ExecutionFilter},
     core::{
-        account_summary_tags::AccountSummaryTags,
+        account_summary_tags::AccountSummaryTag,
         algo_params::{
             fill_accumulate_distribute_params, fill_adaptive_params, fill_arrival_price_params,
             fill_balance_impact_risk_params, fill_close_price_params, fill_csfbinline_params,
@@ -20,7 +21,7 @@ use crate::{
             fill_twap_params, fill_vwap_params,
         },
         messages::{ServerRspMsg, ServerRspMsgDiscriminants},
-        order::{Order, OrderState, SoftDollarTier},
+        order::{OcaType, Order, OrderState, SoftDollarTier},
         order_condition::TriggerMethod,
     },
     examples::{
@@ -75,8 +76,8 @@ impl ExampleWrapper {
                 info!( "order_bound -- req_id: {}, api_client_id: {}, api_order_id: {}", req_id, api_client_id, api_order_id),
             Some(ServerRspMsg::MarketDataType {req_id, market_data_type}) =>
                 info!("market_data_type -- req_id: {}, market_data_type: {}", req_id, market_data_type),
-            Some(ServerRspMsg::ManagedAccts { accounts_list }) =>
-                info!("managed_accounts -- accounts_list: {}", accounts_list),
+            Some(ServerRspMsg::ManagedAccts { accounts }) =>
+                info!("managed_accounts -- accounts: {:?}", accounts),
             Some(ServerRspMsg::OpenOrderEnd) => info!("open_order_end. (no parameters passed)"),
             Some(ServerRspMsg::OpenOrder { order_id, contract, order, order_state }) =>
                 info!("open_order -- order_id: {}\n\n\t     contract: {}\n\t     order: {}\n\t    order_state: {}",
@@ -138,7 +139,7 @@ impl ExampleWrapper {
         self.client.req_family_codes()?;
 
         // Requesting accounts' summary
-        let all_tags = AccountSummaryTags::AllTags.to_string();
+        let all_tags = AccountSummaryTag::AllTags.to_string();
         self.client
             .req_account_summary(9001, "All", all_tags.as_str())?;
 
@@ -1012,10 +1013,10 @@ impl ExampleWrapper {
             order_samples::limit_order("BUY", 1.0, 12.0),
         ];
         let mut next_id = self.next_order_id();
-        order_samples::one_cancels_all(
+        let oca_orders = order_samples::one_cancels_all(
             format!("TestOCA_{}", next_id).as_ref(),
-            oca_orders.clone(),
-            2,
+            oca_orders,
+            OcaType::ReduceWithBlock,
         );
         for o in oca_orders {
             next_id = self.next_order_id();
@@ -1078,8 +1079,8 @@ impl ExampleWrapper {
             4102,
             contract_samples::simple_future().borrow(),
             query_time.as_str(),
-            "1 M",
-            "1 day",
+            HistoricalDuration::Months(1),
+            BarSize::_1Day,
             "MIDPOINT",
             1,
             1,
@@ -1090,8 +1091,8 @@ impl ExampleWrapper {
             4103,
             contract_samples::simple_future().borrow(),
             query_time.as_str(),
-            "10 D",
-            "1 min",
+            HistoricalDuration::Days(10),
+            BarSize::_1Min,
             "TRADES",
             1,
             1,
@@ -1102,8 +1103,8 @@ impl ExampleWrapper {
             4104,
             contract_samples::eur_gbp_fx().borrow(),
             "",
-            "1 M",
-            "1 day",
+            HistoricalDuration::Months(1),
+            BarSize::_1Day,
             "MIDPOINT",
             1,
             1,
@@ -1619,8 +1620,8 @@ impl ExampleWrapper {
             18002,
             contract_samples::cont_fut().borrow(),
             time_str.to_string().as_str(),
-            "1 Y",
-            "1 month",
+            HistoricalDuration::Years(1),
+            BarSize::_1Month,
             "TRADES",
             0,
             1,
@@ -2040,7 +2041,7 @@ impl ExampleWrapper {
             filled: f64,
             remaining: f64,
             avg_fill_price: f64,
-            perm_id: i32,
+            perm_id: i64,
             parent_id: i32,
             last_fill_price: f64,
             client_id: i32,
@@ -1,5 +1,11 @@
 //! Examples of populating structs for contacts, orders, scanner subscriptions and other requests
 pub mod contract_samples;
+#[cfg(all(
+    feature = "client-sync",
+    feature = "orders",
+    feature = "marketdata",
+    feature = "news"
+))]
 pub mod example_wrapper;
 pub mod fa_allocation_samples;
 pub mod order_samples;
@@ -3,7 +3,7 @@
 use num_traits::FromPrimitive;
 
 use crate::core::common::TagValue;
-use crate::core::order::{AuctionStrategy, Order, OrderComboLeg};
+use crate::core::order::{self, AuctionStrategy, OcaType, Order, OrderComboLeg};
 use crate::core::order_condition::{
     create_condition, ConditionType, ExecutionCondition, MarginCondition, PercentChangeCondition,
     PriceCondition, TimeCondition, VolumeCondition,
@@ -761,11 +761,13 @@ pub fn relative_market_combo(action: &str, quantity: f64, non_guaranteed: bool)
 /// of taking on a single position.
 /// Products: BOND, CASH, FUT, FOP, STK, OPT, WAR
 //==================================================================================================
-pub fn one_cancels_all(oca_group: &str, oca_orders: Vec<Order>, oca_type: i32) {
-    for mut order in oca_orders {
-        order.oca_group = oca_group.to_string();
-        order.oca_type = oca_type;
-    }
+pub fn one_cancels_all(
+    oca_group: &str,
+    mut oca_orders: Vec<Order>,
+    oca_type: OcaType,
+) -> Vec<Order> {
+    order::oca_group(&mut oca_orders, oca_group, oca_type);
+    oca_orders
 }
 
 /// Specific to US options, investors are able to create and enter volatility-type orders for options and combinations rather than price orders.
@@ -1023,7 +1025,7 @@ pub fn percentage_change_condition(
     is_conjunction: bool,
 ) -> PercentChangeCondition {
     let mut pct_change_condition: PercentChangeCondition =
-        create_condition(ConditionType::Execution).into();
+        create_condition(ConditionType::PercentChange).into();
     // If there is a price percent change measured against last close price above or below...
     pct_change_condition
         .contract_condition
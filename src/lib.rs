@@ -10,7 +10,14 @@
 //! ***next_valid_id*** to be called, which will start sending test requests to TWS (see the
 //! ***start_requests*** method in ***TestWrapper*** which is called by ***next_valid_id***).
 //!
-//! ```no_run        
+#![cfg_attr(
+    all(feature = "client-sync", feature = "orders", feature = "marketdata", feature = "news"),
+    doc = "```no_run"
+)]
+#![cfg_attr(
+    not(all(feature = "client-sync", feature = "orders", feature = "marketdata", feature = "news")),
+    doc = "```ignore"
+)]
 //! use log::*;
 //! use std::thread;
 //! use std::time::Duration;
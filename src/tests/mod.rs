@@ -1,2 +1,211 @@
+#[cfg(feature = "analytics")]
+pub(crate) mod test_account_diff;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_account_summary;
+pub(crate) mod test_account_values;
+pub(crate) mod test_algo;
+#[cfg(feature = "backtest")]
+pub(crate) mod test_backtest;
+pub(crate) mod test_buffer_pool;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_callbacks;
+pub(crate) mod test_combo;
+#[cfg(feature = "orders")]
+pub(crate) mod test_completed_orders;
+pub(crate) mod test_connection;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_contracts;
+#[cfg(feature = "analytics")]
+pub(crate) mod test_corporate_actions;
+#[cfg(feature = "orders")]
+pub(crate) mod test_crypto;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_display_groups;
+#[cfg(all(feature = "client-sync", feature = "orders"))]
 pub(crate) mod test_eclient;
+pub(crate) mod test_event_queue;
+#[cfg(feature = "orders")]
+pub(crate) mod test_executions;
+#[cfg(feature = "export")]
+pub(crate) mod test_export;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_fa;
+#[cfg(feature = "analytics")]
+pub(crate) mod test_fill_ledger;
+#[cfg(feature = "orders")]
+pub(crate) mod test_fill_report;
+#[cfg(feature = "codec")]
+pub(crate) mod test_frame_dump;
+pub(crate) mod test_frame_reader;
+#[cfg(feature = "polars")]
+pub(crate) mod test_frames;
+#[cfg(feature = "xml")]
+pub(crate) mod test_fundamentals;
+#[cfg(feature = "orders")]
+pub(crate) mod test_fx;
+pub(crate) mod test_handlers;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_handshake;
+#[cfg(feature = "marketdata")]
+pub(crate) mod test_historical;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_latency;
+#[cfg(feature = "orders")]
+pub(crate) mod test_live_orders;
+#[cfg(feature = "marketdata")]
+pub(crate) mod test_market_data;
 pub(crate) mod test_messages;
+#[cfg(all(feature = "metrics", feature = "client-sync"))]
+pub(crate) mod test_metrics;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_models;
+#[cfg(all(feature = "news", feature = "client-sync", feature = "marketdata"))]
+pub(crate) mod test_news;
+pub(crate) mod test_options;
+pub(crate) mod test_order;
+pub(crate) mod test_order_condition;
+#[cfg(feature = "orders")]
+pub(crate) mod test_order_ids;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_pnl;
+#[cfg(feature = "marketdata")]
+pub(crate) mod test_price_ladder;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_pricing;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_proxy;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_req_ids;
+#[cfg(feature = "orders")]
+pub(crate) mod test_risk_guard;
+#[cfg(feature = "codec")]
+pub(crate) mod test_round_trip;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_router;
+#[cfg(feature = "backtest")]
+pub(crate) mod test_runtime;
+#[cfg(feature = "orders")]
+pub(crate) mod test_safety_net;
+pub(crate) mod test_scanner;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_session_manager;
+#[cfg(feature = "marketdata")]
+pub(crate) mod test_shutdown;
+#[cfg(feature = "backtest")]
+pub(crate) mod test_sim;
+#[cfg(feature = "orders")]
+pub(crate) mod test_slicer;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_socket_options;
+#[cfg(feature = "orders")]
+pub(crate) mod test_soft_dollar;
+#[cfg(feature = "marketdata")]
+pub(crate) mod test_subscriptions;
+#[cfg(all(feature = "orders", feature = "marketdata"))]
+pub(crate) mod test_synthetic_stop;
+pub(crate) mod test_time;
+#[cfg(feature = "client-sync")]
+pub(crate) mod test_tracing;
+pub(crate) mod test_trading_hours;
+pub(crate) mod test_trailing;
+pub(crate) mod test_units;
+#[cfg(feature = "codec")]
+pub(crate) mod test_vectors;
+#[cfg(feature = "bridge")]
+pub(crate) mod test_webhooks;
+
+//==================================================================================================
+/// A waker that counts how often it was woken, to check that a future waits for events instead
+/// of waking itself
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct CountingWaker(std::sync::atomic::AtomicUsize);
+
+#[cfg(test)]
+impl CountingWaker {
+    pub(crate) fn count(&self) -> usize {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+impl std::task::Wake for CountingWaker {
+    fn wake(self: std::sync::Arc<Self>) {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+//==================================================================================================
+/// Makes a client look connected at server version 151, sending to a TestStreamer
+#[cfg(all(test, feature = "client-sync"))]
+pub(crate) fn connect_test(client: &mut crate::core::client::EClient) {
+    use crate::core::client::{ConnStatus, POISONED_MUTEX};
+    use crate::core::streamer::{Streamer, TestStreamer};
+
+    *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
+    client.set_streamer(Option::from(
+        Box::new(TestStreamer::new()) as Box<dyn Streamer>
+    ));
+    client.server_version = 151;
+}
+
+//--------------------------------------------------------------------------------------------------
+/// A new client, connected as by connect_test()
+#[cfg(all(test, feature = "client-sync"))]
+pub(crate) fn connected_client() -> crate::core::client::EClient {
+    let mut client = crate::core::client::EClient::new();
+    connect_test(&mut client);
+    client
+}
+
+//--------------------------------------------------------------------------------------------------
+/// The fields of every request a client connected by connect_test() has sent so far
+#[cfg(all(test, feature = "client-sync"))]
+pub(crate) fn sent_requests(client: &mut crate::core::client::EClient) -> Vec<Vec<String>> {
+    use std::io::Read;
+
+    use crate::core::messages::{read_fields, read_msg};
+
+    let mut buf = Vec::<u8>::new();
+    client
+        .stream
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut buf)
+        .unwrap();
+    let mut requests = Vec::new();
+    while !buf.is_empty() {
+        let (_, msg, rest) = read_msg(&buf).unwrap();
+        requests.push(read_fields(&msg));
+        buf = rest;
+    }
+    requests
+}
+
+//==================================================================================================
+#[cfg(all(test, feature = "client-sync"))]
+struct ThreadWaker(std::thread::Thread);
+
+#[cfg(all(test, feature = "client-sync"))]
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: std::sync::Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+/// Just enough of an executor to drive one future on the current thread
+#[cfg(all(test, feature = "client-sync"))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll};
+
+    let mut future = Box::pin(future);
+    let waker = std::sync::Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::account_diff::AccountDiffer;
+    use crate::core::messages::ServerRspMsg;
+
+    //------------------------------------------------------------------------------------------------
+    fn summary(tag: &str, value: &str) -> ServerRspMsg {
+        ServerRspMsg::AccountSummary {
+            req_id: 1,
+            account: "DU123456".to_string(),
+            tag: tag.to_string(),
+            value: value.to_string(),
+            currency: "USD".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_differ() {
+        let mut differ = AccountDiffer::new();
+
+        let first = differ
+            .observe(&summary("NetLiquidation", "1000.5"))
+            .unwrap();
+        assert_eq!(None, first.previous);
+        assert_eq!(None, first.delta);
+
+        assert!(differ
+            .observe(&summary("NetLiquidation", "1000.5"))
+            .is_none());
+
+        let change = differ.observe(&summary("NetLiquidation", "990.5")).unwrap();
+        assert_eq!(Some("1000.5".to_string()), change.previous);
+        assert_eq!(Some(-10.0), change.delta);
+
+        differ.observe(&summary("AccountType", "INDIVIDUAL"));
+        let change = differ.observe(&summary("AccountType", "IRA")).unwrap();
+        assert_eq!(None, change.delta);
+
+        assert!(differ
+            .observe(&ServerRspMsg::NextValidId { order_id: 1 })
+            .is_none());
+    }
+}
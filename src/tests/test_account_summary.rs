@@ -0,0 +1,300 @@
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::io::Read;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+    use std::time::Duration;
+
+    use crate::core::account_summary_tags::{AccountSummaryReport, AccountSummaryTag};
+    use crate::core::account_values::{AccountKey, AccountValue};
+    use crate::core::client::{EClient, POISONED_MUTEX};
+    use crate::core::common::{AccountId, Environment};
+    use crate::core::contract::Contract;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::tests::{block_on, connect_test, CountingWaker};
+
+    //------------------------------------------------------------------------------------------------
+    fn summary(req_id: i32, account: &str, tag: &str, value: &str, currency: &str) -> ServerRspMsg {
+        ServerRspMsg::AccountSummary {
+            req_id,
+            account: account.to_string(),
+            tag: tag.to_string(),
+            value: value.to_string(),
+            currency: currency.to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tag_list() {
+        assert_eq!(
+            "NetLiquidation,RegTEquity,$LEDGER:EUR,$LEDGER:ALL",
+            AccountSummaryTag::to_list(&[
+                AccountSummaryTag::NetLiquidation,
+                AccountSummaryTag::RegTEquity,
+                AccountSummaryTag::LedgerCurrency("EUR".to_string()),
+                AccountSummaryTag::LedgerAll,
+            ])
+        );
+        let all_tags = AccountSummaryTag::AllTags.to_string();
+        assert_eq!(29, all_tags.split(',').count());
+        assert!(all_tags.starts_with("AccountType,NetLiquidation,"));
+        assert!(!all_tags.contains(char::is_whitespace));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_report() {
+        let mut report = AccountSummaryReport::new();
+        assert!(report.update(&summary(1, "DU1", "NetLiquidation", "1000.5", "USD")));
+        assert!(report.update(&summary(1, "DU1", "AccountType", "INDIVIDUAL", "")));
+        assert!(report.update(&summary(1, "DU1", "CashBalance", "200", "EUR")));
+        assert!(report.update(&summary(1, "DU1", "NetLiquidation", "1001", "USD")));
+        assert!(report.update(&summary(1, "DU2", "NetLiquidation", "50", "USD")));
+        assert!(!report.update(&ServerRspMsg::AccountSummaryEnd { req_id: 1 }));
+
+        assert_eq!(2, report.accounts.len());
+        assert_eq!(3, report.accounts["DU1"].len());
+        assert_eq!(
+            Some(1001.0),
+            report.amount("DU1", &AccountSummaryTag::NetLiquidation)
+        );
+        assert_eq!(None, report.amount("DU1", &AccountSummaryTag::AccountType));
+        assert_eq!(
+            None,
+            report.amount("DU3", &AccountSummaryTag::NetLiquidation)
+        );
+        assert_eq!(
+            Some(200.0),
+            report
+                .value_in("DU1", "CashBalance", "EUR")
+                .and_then(|value| value.amount())
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_summary() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            summary(req_id, "DU1", "BuyingPower", "4000", "USD"),
+            summary(req_id + 1, "DU1", "BuyingPower", "1", "USD"),
+            ServerRspMsg::AccountSummaryEnd { req_id },
+        ] {
+            events.send(msg).unwrap();
+        }
+
+        let summary =
+            client.account_summary(&[AccountSummaryTag::BuyingPower, AccountSummaryTag::Ledger])?;
+        assert_eq!(req_id, summary.req_id());
+        let report = block_on(summary)?;
+        assert_eq!(
+            Some(4000.0),
+            report.amount("DU1", &AccountSummaryTag::BuyingPower)
+        );
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, rest) = read_msg(&buf)?;
+        assert_eq!("BuyingPower,$LEDGER", read_fields(&request)[4]);
+        // the subscription is cancelled once the summary is complete
+        let (_, cancel, _) = read_msg(&rest)?;
+        assert_eq!(req_id.to_string(), read_fields(&cancel)[2]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_summary_waits_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let req_id = client.req_id_allocator().peek();
+        let mut request = Box::pin(client.account_summary(&[AccountSummaryTag::NetLiquidation])?);
+        assert!(request.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(0, counter.count());
+        // a value arriving wakes it, and it waits for the end of the summary
+        events
+            .send(summary(req_id, "DU1", "NetLiquidation", "1000", "USD"))
+            .unwrap();
+        assert_eq!(1, counter.count());
+        assert!(request.as_mut().poll(&mut cx).is_pending());
+        events
+            .send(ServerRspMsg::AccountSummaryEnd { req_id })
+            .unwrap();
+        match request.as_mut().poll(&mut cx) {
+            Poll::Ready(report) => assert_eq!(
+                Some(1000.0),
+                report?.amount("DU1", &AccountSummaryTag::NetLiquidation)
+            ),
+            Poll::Pending => panic!("the summary is still pending"),
+        }
+        drop(request);
+        assert!(client.correlations().context(req_id).is_none());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_summary_error_and_drop() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        let req_id = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id,
+                error_code: 322,
+                error_str: "Maximum number of account summary requests exceeded".to_string(),
+            })
+            .unwrap();
+        assert!(block_on(client.account_summary(&[AccountSummaryTag::AllTags])?).is_err());
+
+        // a summary dropped before it completes cancels its subscription
+        let req_id = client.req_id_allocator().peek();
+        drop(client.account_summary(&[AccountSummaryTag::AllTags])?);
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let mut requests = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            requests.push(read_fields(&msg));
+            buf = rest;
+        }
+        assert_eq!(3, requests.len());
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::CancelAccountSummary as i32).to_string(),
+                "1".to_string(),
+                req_id.to_string(),
+            ],
+            requests[2]
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_ids() {
+        let accounts = AccountId::parse_list("DU111, U222,,DF333");
+        assert_eq!(
+            vec!["DU111", "U222", "DF333"],
+            accounts
+                .iter()
+                .map(AccountId::as_str)
+                .collect::<Vec<&str>>()
+        );
+        assert!(accounts[0].is_paper());
+        assert!(accounts[1].is_live());
+        assert!(accounts[2].is_paper());
+        assert_eq!(Environment::Live, Environment::of(&accounts));
+        assert_eq!(Environment::Paper, Environment::of(&accounts[2..]));
+        assert_eq!(Environment::Unknown, Environment::of(&[]));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn account_value(req_id: i32, account: &str, key: &str, value: &str) -> ServerRspMsg {
+        ServerRspMsg::AccountUpdateMulti {
+            req_id,
+            account: account.to_string(),
+            model_code: String::new(),
+            key: AccountKey::from(key),
+            value: AccountValue::parse(value, "USD"),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_for_each_account() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        assert!(client
+            .account_summaries(&[AccountSummaryTag::AllTags], Duration::from_millis(10))
+            .is_err());
+
+        *client.managed_accounts.lock().expect(POISONED_MUTEX) = AccountId::parse_list("DU1,DU2");
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            account_value(req_id, "DU1", "NetLiquidation", "1000"),
+            account_value(req_id, "DU1", "NetLiquidation-S", "900"),
+            ServerRspMsg::AccountUpdateMultiEnd { req_id },
+            account_value(req_id + 1, "DU2", "NetLiquidation", "50.25"),
+            ServerRspMsg::AccountUpdateMultiEnd { req_id: req_id + 1 },
+        ] {
+            events.send(msg).unwrap();
+        }
+        let report = client.account_summaries(
+            &[AccountSummaryTag::NetLiquidation],
+            Duration::from_millis(100),
+        )?;
+        assert_eq!(
+            Some(1000.0),
+            report.amount("DU1", &AccountSummaryTag::NetLiquidation)
+        );
+        assert_eq!(
+            Some(50.25),
+            report.amount("DU2", &AccountSummaryTag::NetLiquidation)
+        );
+        assert_eq!(1, report.accounts["DU1"].len());
+
+        let req_id = client.req_id_allocator().peek();
+        let position = |req_id: i32, account: &str, symbol: &str| ServerRspMsg::PositionMulti {
+            req_id,
+            account: account.to_string(),
+            model_code: String::new(),
+            contract: Contract {
+                symbol: symbol.to_string(),
+                ..Default::default()
+            },
+            position: 10.0,
+            avg_cost: 100.0,
+        };
+        for msg in [
+            position(req_id, "DU1", "AAPL"),
+            position(req_id, "DU1", "MSFT"),
+            ServerRspMsg::PositionMultiEnd { req_id },
+            ServerRspMsg::PositionMultiEnd { req_id: req_id + 1 },
+        ] {
+            events.send(msg).unwrap();
+        }
+        let positions = client.positions_by_account(Duration::from_millis(100))?;
+        assert_eq!(2, positions[&AccountId::new("DU1")].len());
+        assert!(positions[&AccountId::new("DU2")].is_empty());
+
+        // each account is requested and cancelled in turn
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let mut accounts = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            let fields = read_fields(&msg);
+            if fields.len() > 3 {
+                accounts.push(fields[3].clone());
+            }
+            buf = rest;
+        }
+        assert_eq!(vec!["DU1", "DU2", "DU1", "DU2"], accounts);
+        Ok(())
+    }
+}
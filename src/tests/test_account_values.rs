@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use rust_decimal_macros::dec;
+
+    use crate::core::account_values::{AccountKey, AccountValue};
+    use crate::core::common::ConnStatus;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+    use crate::core::messages::ServerRspMsg;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_keys() {
+        assert_eq!(
+            AccountKey::NetLiquidation,
+            AccountKey::from("NetLiquidation")
+        );
+        assert_eq!(
+            AccountKey::DayTradesRemainingT1,
+            AccountKey::from("DayTradesRemainingT+1")
+        );
+        assert_eq!(
+            "DayTradesRemainingT+1",
+            AccountKey::DayTradesRemainingT1.to_string()
+        );
+        assert_eq!("SMA", AccountKey::Sma.as_str());
+
+        // per segment keys are not documented and are kept as sent
+        let segment = AccountKey::from("NetLiquidation-S");
+        assert_eq!(AccountKey::Other("NetLiquidation-S".to_string()), segment);
+        assert!(segment.is_other());
+        assert_eq!("NetLiquidation-S", segment.to_string());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_value_parsing() {
+        let value = AccountValue::parse("100250.50", "EUR");
+        assert_eq!(Some(dec!(100250.50)), value.amount());
+        assert_eq!("EUR", value.currency());
+        assert_eq!("100250.50", value.to_string());
+
+        let value = AccountValue::parse("INDIVIDUAL", "");
+        assert_eq!(None, value.amount());
+        assert_eq!("INDIVIDUAL", value.to_string());
+        assert_eq!(Some(dec!(-1)), AccountValue::parse("-1", "").amount());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn decode(fields: &[&str]) -> Result<Vec<ServerRspMsg>, IBKRApiLibError> {
+        let (_, msg_queue) = channel::<String>();
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut decoder = Decoder::new(
+            msg_queue,
+            sender,
+            151,
+            Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+        );
+        decoder.interpret(&format!("{}\0", fields.join("\0")))?;
+        let mut msgs = Vec::new();
+        while let Ok(msg) = receiver.try_recv() {
+            msgs.push(msg);
+        }
+        Ok(msgs)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decode_account_values() -> Result<(), IBKRApiLibError> {
+        let msgs = decode(&["6", "2", "CashBalance", "1520.25", "USD", "DU123"])?;
+        match &msgs[..] {
+            [ServerRspMsg::AcctValue {
+                key,
+                value,
+                account_name,
+            }] => {
+                assert_eq!(AccountKey::CashBalance, *key);
+                assert_eq!(
+                    AccountValue::Amount {
+                        amount: dec!(1520.25),
+                        currency: "USD".to_string()
+                    },
+                    *value
+                );
+                assert_eq!("DU123", account_name);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+
+        let msgs = decode(&["73", "1", "9", "DU123", "Model1", "AccountType", "CORP", ""])?;
+        match &msgs[..] {
+            [ServerRspMsg::AccountUpdateMulti {
+                req_id, key, value, ..
+            }] => {
+                assert_eq!(9, *req_id);
+                assert_eq!(AccountKey::AccountType, *key);
+                assert_eq!(None, value.amount());
+                assert_eq!("CORP", value.to_string());
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::order::algo::{
+        Adaptive, AlgoParams, AlgoTime, ArrivalPx, DarkIce, PctVol, RiskAversion, Twap,
+        TwapStrategyType, Urgency, Vwap,
+    };
+    use crate::core::order::Order;
+    use crate::core::units::{Notional, Percent};
+
+    //------------------------------------------------------------------------------------------------
+    fn tags(algo: &dyn AlgoParams) -> Vec<(String, String)> {
+        algo.params()
+            .into_iter()
+            .map(|param| (param.tag, param.value))
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(tag, value)| (tag.to_string(), value.to_string()))
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_algo_params() -> Result<(), IBKRApiLibError> {
+        let start = AlgoTime::parse("09:45:00", "US/Eastern")?;
+        let end = AlgoTime::on(
+            NaiveDate::from_ymd_opt(2024, 1, 5)
+                .unwrap()
+                .and_hms_opt(15, 30, 0)
+                .unwrap(),
+            "US/Eastern",
+        );
+        assert_eq!("20240105-15:30:00 US/Eastern", end.as_str());
+        assert!(AlgoTime::parse("9.45", "US/Eastern").is_err());
+
+        let vwap = Vwap::new(Percent::new(10.0))
+            .start_time(start.clone())
+            .end_time(end.clone())
+            .no_take_liq(true)
+            .monetary_value(Notional::new(50000.0));
+        assert_eq!(
+            pairs(&[
+                ("maxPctVol", "0.1"),
+                ("startTime", "09:45:00 US/Eastern"),
+                ("endTime", "20240105-15:30:00 US/Eastern"),
+                ("allowPastEndTime", "0"),
+                ("noTakeLiq", "1"),
+                ("monetaryValue", "50000"),
+            ]),
+            tags(&vwap)
+        );
+
+        let twap = Twap::new(TwapStrategyType::MatchingMidpoint).allow_past_end_time(true);
+        assert_eq!(
+            pairs(&[
+                ("strategyType", "Matching Midpoint"),
+                ("allowPastEndTime", "1"),
+            ]),
+            tags(&twap)
+        );
+
+        let arrival = ArrivalPx::new(Percent::new(25.0), RiskAversion::GetDone)
+            .end_time(end)
+            .force_completion(true);
+        assert_eq!(
+            pairs(&[
+                ("maxPctVol", "0.25"),
+                ("riskAversion", "Get Done"),
+                ("endTime", "20240105-15:30:00 US/Eastern"),
+                ("allowPastEndTime", "0"),
+                ("forceCompletion", "1"),
+            ]),
+            tags(&arrival)
+        );
+
+        assert_eq!(
+            pairs(&[("displaySize", "100"), ("allowPastEndTime", "0")]),
+            tags(&DarkIce::new(100))
+        );
+        assert_eq!(
+            pairs(&[
+                ("pctVol", "0.05"),
+                ("startTime", "09:45:00 US/Eastern"),
+                ("noTakeLiq", "0"),
+            ]),
+            tags(&PctVol::new(Percent::new(5.0)).start_time(start))
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_algo_apply() {
+        let mut order = Order::default();
+        Vwap::new(Percent::new(10.0)).apply(&mut order);
+        Adaptive::new(Urgency::Patient).apply(&mut order);
+
+        // applying another algo replaces the parameters of the previous one
+        assert_eq!("Adaptive", order.algo_strategy);
+        assert_eq!(1, order.algo_params.len());
+        assert_eq!("adaptivePriority", order.algo_params[0].tag);
+        assert_eq!("Patient", order.algo_params[0].value);
+    }
+}
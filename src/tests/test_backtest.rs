@@ -0,0 +1,471 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::backtest::sim::CommissionModel;
+    use crate::core::backtest::{BacktestClient, BacktestConfig, BACKTEST_ACCOUNT};
+    use crate::core::common::{
+        AccountId, BarData, BarSize, HistoricalDuration, HistoricalTickBidAsk, TickByTickData,
+        TickByTickType, TickType,
+    };
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{Order, OrderStatusKind};
+    use crate::core::test_vectors::{Capture, CaptureRecorder};
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn stock(symbol: &str) -> Contract {
+        Contract {
+            symbol: symbol.to_string(),
+            sec_type: "STK".to_string(),
+            exchange: "SMART".to_string(),
+            currency: "USD".to_string(),
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: &str, open: f64, high: f64, low: f64, close: f64) -> BarData {
+        BarData {
+            date: parse_tws_time(date, Tz::UTC),
+            open,
+            high,
+            low,
+            close,
+            volume: 400,
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order(action: &str, order_type: &str, quantity: f64, lmt: f64, aux: f64) -> Order {
+        Order {
+            action: action.to_string(),
+            order_type: order_type.to_string(),
+            total_quantity: quantity,
+            lmt_price: lmt,
+            aux_price: aux,
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn events(client: &BacktestClient) -> Vec<ServerRspMsg> {
+        let mut events = Vec::new();
+        while let Some(msg) = client.get_event().unwrap() {
+            events.push(msg);
+        }
+        events
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn fills(events: &[ServerRspMsg]) -> Vec<(i32, f64, f64)> {
+        events
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::ExecutionData { execution, .. } => {
+                    Some((execution.order_id, execution.shares, execution.price))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn client() -> BacktestClient {
+        let mut client = BacktestClient::new();
+        client
+            .add_bars(
+                &stock("XYZ"),
+                &[
+                    bar("20240102  14:30:00", 100.0, 102.0, 99.0, 101.0),
+                    bar("20240102  14:31:00", 101.0, 101.5, 98.0, 98.5),
+                    bar("20240102  14:32:00", 98.5, 104.0, 98.5, 103.0),
+                ],
+            )
+            .unwrap();
+        let startup = events(&client);
+        let account = AccountId::new(BACKTEST_ACCOUNT);
+        assert!(startup.iter().any(|msg| matches!(msg,
+            ServerRspMsg::ManagedAccts { accounts } if *accounts == [account.clone()])));
+        assert_eq!(vec![account], client.managed_accounts());
+        assert!(startup
+            .iter()
+            .any(|msg| matches!(msg, ServerRspMsg::NextValidId { order_id: 1 })));
+        client
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_streams_bars() {
+        let mut client = client();
+        assert!(client.is_connected());
+        assert_eq!(12, client.remaining());
+
+        client
+            .req_mkt_data(7, &stock("xyz"), "", false, false, vec![])
+            .unwrap();
+        assert!(events(&client).is_empty());
+
+        assert!(client.step());
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap(),
+            client.now()
+        );
+        let lasts: Vec<f64> = events(&client)
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::TickPrice {
+                    req_id: 7,
+                    tick_type: TickType::Last,
+                    price,
+                    ..
+                } => Some(*price),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![100.0, 99.0, 102.0, 101.0], lasts);
+
+        client.cancel_mkt_data(7).unwrap();
+        assert!(client.step());
+        assert!(events(&client).is_empty());
+
+        // a new subscription starts with the last price
+        client
+            .req_mkt_data(8, &stock("XYZ"), "", true, false, vec![])
+            .unwrap();
+        let snapshot = events(&client);
+        assert!(snapshot.iter().any(|msg| matches!(
+            msg,
+            ServerRspMsg::TickSize {
+                req_id: 8,
+                tick_type: TickType::Volume,
+                size: 800
+            }
+        )));
+        assert!(matches!(
+            snapshot.last(),
+            Some(ServerRspMsg::TickSnapshotEnd { req_id: 8 })
+        ));
+
+        assert!(client.step());
+        assert!(!client.step());
+        assert_eq!(0, client.remaining());
+
+        client
+            .req_mkt_data(9, &stock("ABC"), "", false, false, vec![])
+            .unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::ErrMsg {
+                req_id: 9,
+                error_code: 200,
+                ..
+            }]
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_fills_orders() {
+        let mut client = BacktestClient::with_config(BacktestConfig::default().commission(
+            CommissionModel::PerShare {
+                rate: 0.01,
+                minimum: 1.0,
+            },
+        ));
+        client
+            .add_bars(
+                &stock("XYZ"),
+                &[
+                    bar("20240102  14:30:00", 100.0, 102.0, 99.0, 101.0),
+                    bar("20240102  14:31:00", 101.0, 101.5, 98.0, 98.5),
+                    bar("20240102  14:32:00", 98.5, 104.0, 98.5, 103.0),
+                ],
+            )
+            .unwrap();
+        client.step();
+        events(&client);
+
+        // marketable at the last price of 101
+        client
+            .place_order(
+                1,
+                &stock("XYZ"),
+                &order("BUY", "MKT", 100.0, f64::MAX, f64::MAX),
+            )
+            .unwrap();
+        let placed = events(&client);
+        assert_eq!(vec![(1, 100.0, 101.0)], fills(&placed));
+        assert!(placed.iter().any(|msg| matches!(msg,
+            ServerRspMsg::OrderStatus { order_id: 1, status, filled, .. }
+                if *status == OrderStatusKind::Filled && *filled == 100.0)));
+        assert!(placed.iter().any(|msg| matches!(msg,
+            ServerRspMsg::CommissionReport { commission_report }
+                if commission_report.commission == 1.0)));
+
+        client
+            .place_order(
+                2,
+                &stock("XYZ"),
+                &order("SELL", "LMT", 100.0, 103.5, f64::MAX),
+            )
+            .unwrap();
+        client
+            .place_order(
+                3,
+                &stock("XYZ"),
+                &order("SELL", "STP", 50.0, f64::MAX, 99.0),
+            )
+            .unwrap();
+        client
+            .place_order(4, &stock("XYZ"), &order("BUY", "LMT", 10.0, 90.0, f64::MAX))
+            .unwrap();
+        assert!(fills(&events(&client)).is_empty());
+
+        // the second bar trades down to 98, through the stop
+        client.step();
+        assert_eq!(vec![(3, 50.0, 98.0)], fills(&events(&client)));
+
+        // the limit is modified below the high of the third bar
+        client
+            .place_order(
+                2,
+                &stock("XYZ"),
+                &order("SELL", "LMT", 50.0, 103.5, f64::MAX),
+            )
+            .unwrap();
+        client.step();
+        let last = events(&client);
+        assert_eq!(vec![(2, 50.0, 104.0)], fills(&last));
+        let pnl: Vec<f64> = last
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::CommissionReport { commission_report } => {
+                    Some(commission_report.realized_pnl)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![50.0 * 3.0 - 1.0], pnl);
+
+        client.cancel_order(4).unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::OrderStatus {
+                order_id: 4,
+                status: OrderStatusKind::Cancelled,
+                ..
+            }]
+        ));
+        client.cancel_order(4).unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::ErrMsg {
+                req_id: 4,
+                error_code: 10147,
+                ..
+            }]
+        ));
+
+        client.req_positions().unwrap();
+        let positions = events(&client);
+        assert!(positions.iter().all(|msg| !matches!(msg,
+            ServerRspMsg::PositionData { position, .. } if *position != 0.0)));
+        assert!(matches!(positions.last(), Some(ServerRspMsg::PositionEnd)));
+
+        client
+            .place_order(
+                5,
+                &stock("XYZ"),
+                &order("BUY", "MOC", 1.0, f64::MAX, f64::MAX),
+            )
+            .unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::ErrMsg {
+                error_code: 387,
+                ..
+            }]
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_trailing_orders() {
+        let mut client = client();
+        client.step();
+
+        // the sell trails 2% below the high of 102, the buy starts at its trail stop price
+        let mut trail = order("SELL", "TRAIL", 10.0, f64::MAX, f64::MAX);
+        trail.trailing_percent = 2.0;
+        client.place_order(1, &stock("XYZ"), &trail).unwrap();
+        let mut trail_limit = order("BUY", "TRAIL LIMIT", 5.0, 102.5, 1.0);
+        trail_limit.trail_stop_price = 102.0;
+        client.place_order(2, &stock("XYZ"), &trail_limit).unwrap();
+        assert!(fills(&events(&client)).is_empty());
+
+        client.step();
+        // 101, 101.5, 98 then 98.5: the sell stop rises to 99.47, the buy stop falls to 99
+        assert_eq!(vec![(1, 10.0, 98.0)], fills(&events(&client)));
+
+        // 98.5, 104 then 103: the buy is stopped at 104, above its limit of 99.5
+        client.step();
+        assert!(fills(&events(&client)).is_empty());
+        client.cancel_order(2).unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::OrderStatus {
+                order_id: 2,
+                status: OrderStatusKind::Cancelled,
+                ..
+            }]
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_historical_data() {
+        let mut client = client();
+        client.step();
+        client.step();
+        client.req_current_time().unwrap();
+        let minute = Utc.with_ymd_and_hms(2024, 1, 2, 14, 31, 0).unwrap();
+        assert!(matches!(events(&client).as_slice(),
+            [ServerRspMsg::CurrentTime { time }] if *time == minute.timestamp()));
+
+        client
+            .req_historical_data(
+                3,
+                &stock("XYZ"),
+                "",
+                HistoricalDuration::Days(1),
+                BarSize::_1Min,
+                "TRADES",
+                1,
+                1,
+                false,
+                vec![],
+            )
+            .unwrap();
+        let history = events(&client);
+        assert_eq!(3, history.len());
+        assert!(matches!(&history[1],
+            ServerRspMsg::HistoricalData { req_id: 3, bar } if bar.close == 98.5));
+        assert!(matches!(
+            history[2],
+            ServerRspMsg::HistoricalDataEnd { req_id: 3, .. }
+        ));
+
+        client.req_account_updates(true, "").unwrap();
+        assert!(matches!(
+            events(&client).as_slice(),
+            [ServerRspMsg::ErrMsg {
+                error_code: 506,
+                ..
+            }]
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_quotes_and_start() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 1).unwrap();
+        let mut client = BacktestClient::with_config(BacktestConfig::default().start(start));
+        let quote = |time: i64, bid: f64, ask: f64| HistoricalTickBidAsk {
+            time: time as _,
+            price_bid: bid,
+            price_ask: ask,
+            size_bid: 1,
+            size_ask: 2,
+            ..Default::default()
+        };
+        let seconds = start.timestamp();
+        client.add_quotes(
+            &stock("XYZ"),
+            &[
+                quote(seconds - 1, 9.0, 11.0),
+                quote(seconds, 10.0, 10.5),
+                quote(seconds + 1, 10.25, 10.75),
+            ],
+        );
+        client
+            .req_tick_by_tick_data(4, &stock("XYZ"), TickByTickType::MidPoint, 0, false)
+            .unwrap();
+        events(&client);
+
+        client.run_until(start);
+        let mids: Vec<f64> = events(&client)
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::TickByTick {
+                    req_id: 4,
+                    tick: TickByTickData::MidPoint { mid_point },
+                    ..
+                } => Some(*mid_point),
+                _ => None,
+            })
+            .collect();
+        // the ticks before the start are not streamed, but the new bid is paired with the old ask
+        assert_eq!(vec![10.5, 10.25], mids);
+        assert_eq!(start, client.now());
+        assert_eq!(2, client.remaining());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_backtest_journal() {
+        let time =
+            |millis: i64| DateTime::from_timestamp_millis(1_704_205_800_000 + millis).unwrap();
+        let recorder = CaptureRecorder::new("session");
+        recorder.record_at(
+            151,
+            "1\u{0}6\u{0}5\u{0}1\u{0}185.5\u{0}100\u{0}0\u{0}",
+            time(0),
+        );
+        recorder.record_at(151, "2\u{0}6\u{0}5\u{0}0\u{0}100\u{0}", time(0));
+        recorder.record_at(
+            151,
+            "1\u{0}6\u{0}5\u{0}4\u{0}185.25\u{0}3\u{0}0\u{0}",
+            time(250),
+        );
+        recorder.record_at(151, "2\u{0}6\u{0}5\u{0}5\u{0}3\u{0}", time(250));
+        recorder.record_at(
+            151,
+            "1\u{0}6\u{0}6\u{0}4\u{0}1.5\u{0}3\u{0}0\u{0}",
+            time(500),
+        );
+        let capture = Capture::parse("session", &recorder.capture().to_string()).unwrap();
+
+        let mut client = BacktestClient::new();
+        assert_eq!(2, client.add_journal(&stock("AAPL"), &capture, 5).unwrap());
+        client
+            .req_mkt_data(1, &stock("AAPL"), "", false, false, vec![])
+            .unwrap();
+        events(&client);
+
+        client.step();
+        client.step();
+        assert!(!client.step());
+        assert_eq!(time(250), client.now());
+        let streamed = events(&client);
+        let size = |expected: TickType| {
+            streamed.iter().find_map(|msg| match msg {
+                ServerRspMsg::TickSize {
+                    req_id: 1,
+                    tick_type,
+                    size,
+                } if format!("{:?}", tick_type) == format!("{:?}", expected) => Some(*size),
+                _ => None,
+            })
+        };
+        assert_eq!(Some(100), size(TickType::BidSize));
+        assert_eq!(Some(3), size(TickType::LastSize));
+
+        let untimed =
+            Capture::parse("untimed", "@server_version 151\n1|6|5|4|185.25|3|0|\n").unwrap();
+        assert!(client.add_journal(&stock("AAPL"), &untimed, 5).is_err());
+    }
+}
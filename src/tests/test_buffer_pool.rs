@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "client-sync")]
+    use std::io::Write;
+    #[cfg(feature = "client-sync")]
+    use std::sync::atomic::AtomicBool;
+    #[cfg(feature = "client-sync")]
+    use std::sync::mpsc::channel;
+    #[cfg(feature = "client-sync")]
+    use std::sync::Arc;
+
+    use crate::core::buffer_pool::{BufferPool, PoolStats};
+    #[cfg(feature = "client-sync")]
+    use crate::core::errors::IBKRApiLibError;
+    #[cfg(feature = "client-sync")]
+    use crate::core::messages::make_message;
+    #[cfg(feature = "client-sync")]
+    use crate::core::reader::Reader;
+    #[cfg(feature = "client-sync")]
+    use crate::core::streamer::TestStreamer;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_reuse() {
+        let pool = BufferPool::with_limits(2, 16);
+        let first = pool.take_string(b"1\x002\x00");
+        assert_eq!("1\u{0}2\u{0}", first);
+        pool.give_string(first);
+        assert_eq!(1, pool.available());
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 4);
+        pool.give(buffer);
+
+        // too large to keep
+        pool.give(Vec::with_capacity(17));
+        // the pool holds at most two
+        pool.give(Vec::new());
+        pool.give(Vec::new());
+        assert_eq!(2, pool.available());
+        assert_eq!(
+            PoolStats {
+                allocated: 1,
+                reused: 1,
+                discarded: 2,
+            },
+            pool.stats()
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_invalid_utf8() {
+        let pool = BufferPool::new();
+        assert_eq!("a\u{fffd}b", pool.take_string(b"a\xffb"));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "client-sync")]
+    fn read_ticks(pool: &BufferPool, count: usize) -> Result<(), IBKRApiLibError> {
+        let mut streamer = TestStreamer::new();
+        for i in 0..count {
+            streamer.write_all(&make_message(&format!("1\u{0}6\u{0}{}\u{0}1\u{0}", i))?)?;
+        }
+        let (tx, rx) = channel();
+        let mut reader = Reader::new(Box::new(streamer), tx, Arc::new(AtomicBool::new(false)));
+        reader.buffers = pool.clone();
+        reader.run();
+        drop(reader);
+        // as the decoder does once a frame is decoded
+        let mut received = 0;
+        for msg in rx {
+            assert!(msg.starts_with("1\u{0}6\u{0}"));
+            pool.give_string(msg);
+            received += 1;
+        }
+        assert_eq!(count, received);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "client-sync")]
+    #[test]
+    fn test_reader_reuses_buffers() -> Result<(), IBKRApiLibError> {
+        let pool = BufferPool::new();
+        read_ticks(&pool, 10)?;
+        let warm = pool.stats();
+        assert_eq!(10, warm.allocated);
+
+        read_ticks(&pool, 10)?;
+        let stats = pool.stats();
+        assert_eq!(warm.allocated, stats.allocated);
+        assert_eq!(10, stats.reused);
+        Ok(())
+    }
+}
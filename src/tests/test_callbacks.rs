@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::core::callbacks::Callbacks;
+    use crate::core::client::EClient;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{ServerRspMsg, ServerRspMsgKind};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_callbacks_by_kind() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut callbacks = Callbacks::new();
+        let times = seen.clone();
+        let all = seen.clone();
+        callbacks
+            .on(ServerRspMsgKind::CurrentTime, move |msg| {
+                if let ServerRspMsg::CurrentTime { time } = msg {
+                    times.lock().unwrap().push(format!("time {}", time));
+                }
+            })
+            .on_any(move |msg| all.lock().unwrap().push(format!("any {}", msg)));
+
+        assert!(callbacks.dispatch(&ServerRspMsg::CurrentTime { time: 1704465000 }));
+        assert!(callbacks.dispatch(&ServerRspMsg::OpenOrderEnd));
+        callbacks.clear(ServerRspMsgKind::CurrentTime);
+        assert!(callbacks.dispatch(&ServerRspMsg::CurrentTime { time: 1704465001 }));
+
+        assert_eq!(
+            vec![
+                "time 1704465000",
+                "any CurrentTime",
+                "any OpenOrderEnd",
+                "any CurrentTime"
+            ],
+            *seen.lock().unwrap()
+        );
+        assert!(!Callbacks::new().dispatch(&ServerRspMsg::OpenOrderEnd));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_client_callbacks() -> Result<(), IBKRApiLibError> {
+        let ids = Arc::new(Mutex::new(Vec::new()));
+        let mut client = EClient::new();
+        let next_ids = ids.clone();
+        client.on(ServerRspMsgKind::NextValidId, move |msg| {
+            if let ServerRspMsg::NextValidId { order_id } = msg {
+                next_ids.lock().unwrap().push(*order_id);
+            }
+        });
+        for msg in [
+            ServerRspMsg::NextValidId { order_id: 4 },
+            ServerRspMsg::OpenOrderEnd,
+            ServerRspMsg::NextValidId { order_id: 5 },
+        ] {
+            client.evt_chan.0.send(msg).unwrap();
+        }
+
+        assert_eq!(2, client.run_callbacks()?);
+        assert_eq!(vec![4, 5], *ids.lock().unwrap());
+        assert!(client.get_event()?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::{Action, UNSET_DOUBLE};
+    use crate::core::contract::combo::{SpreadBuilder, SpreadLeg};
+    use crate::core::contract::{Contract, PositionType};
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::order::Order;
+
+    //------------------------------------------------------------------------------------------------
+    fn contract(con_id: i32, symbol: &str, sec_type: &str) -> Contract {
+        let mut contract = Contract::default();
+        contract.con_id = con_id;
+        contract.symbol = symbol.to_string();
+        contract.sec_type = sec_type.to_string();
+        contract.currency = "USD".to_string();
+        contract
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_combo_presets() -> Result<(), IBKRApiLibError> {
+        let lower = contract(1, "SPY", "OPT");
+        let middle = contract(2, "SPY", "OPT");
+        let upper = contract(3, "SPY", "OPT");
+
+        let butterfly = SpreadBuilder::butterfly(&lower, &middle, &upper).contract()?;
+        assert_eq!("BAG", butterfly.sec_type);
+        assert_eq!("SPY", butterfly.symbol);
+        assert_eq!("USD", butterfly.currency);
+        assert_eq!("SMART", butterfly.exchange);
+        let legs: Vec<(i32, f64, &str)> = butterfly
+            .combo_legs
+            .iter()
+            .map(|leg| (leg.con_id, leg.ratio, leg.action.as_str()))
+            .collect();
+        assert_eq!(
+            vec![(1, 1.0, "BUY"), (2, 2.0, "SELL"), (3, 1.0, "BUY")],
+            legs
+        );
+        assert!(butterfly
+            .combo_legs
+            .iter()
+            .all(|leg| leg.exchange == "SMART" && leg.exempt_code == -1));
+
+        let calendar = SpreadBuilder::calendar(&lower, &upper).contract()?;
+        assert_eq!("SELL", calendar.combo_legs[0].action);
+        assert_eq!("BUY", calendar.combo_legs[1].action);
+
+        let stock = contract(10, "IBM", "STK");
+        let call = contract(11, "IBM", "OPT");
+        let buy_write = SpreadBuilder::stock_vs_option(&stock, &call, 100).contract()?;
+        assert_eq!(100.0, buy_write.combo_legs[0].ratio);
+        assert_eq!(1.0, buy_write.combo_legs[1].ratio);
+
+        let pair = SpreadBuilder::new()
+            .exchange("ISLAND")
+            .leg(SpreadLeg::buy(&stock, 1).exchange("ARCA"))
+            .leg(SpreadLeg::new(
+                &contract(12, "MCD", "STK"),
+                Action::SSHORT,
+                1,
+            ))
+            .contract()?;
+        assert_eq!("IBM,MCD", pair.symbol);
+        assert_eq!("ARCA", pair.combo_legs[0].exchange);
+        assert_eq!("ISLAND", pair.combo_legs[1].exchange);
+        assert_eq!("SSHORT", pair.combo_legs[1].action);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_combo_validation() {
+        let option = contract(1, "SPY", "OPT");
+        assert!(SpreadBuilder::new()
+            .leg(SpreadLeg::buy(&option, 1))
+            .contract()
+            .is_err());
+        assert!(SpreadBuilder::vertical(&option, &contract(0, "SPY", "OPT"))
+            .contract()
+            .is_err());
+        assert!(SpreadBuilder::vertical(&option, &option)
+            .leg(SpreadLeg::buy(&option, 0))
+            .contract()
+            .is_err());
+        let mut euro = contract(2, "SPY", "OPT");
+        euro.currency = "EUR".to_string();
+        assert!(SpreadBuilder::vertical(&option, &euro).contract().is_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_combo_order_legs() {
+        let long = contract(1, "SPY", "OPT");
+        let short = contract(2, "SPY", "OPT");
+        let mut order = Order::default();
+
+        SpreadBuilder::vertical(&long, &short)
+            .non_guaranteed(true)
+            .apply(&mut order);
+        assert!(order.order_combo_legs.is_empty());
+        assert_eq!(1, order.smart_combo_routing_params.len());
+        assert_eq!("NonGuaranteed", order.smart_combo_routing_params[0].tag);
+        assert_eq!("1", order.smart_combo_routing_params[0].value);
+
+        SpreadBuilder::new()
+            .leg(SpreadLeg::buy(&long, 1).price(2.5))
+            .leg(SpreadLeg::sell(&short, 1).open_close(PositionType::ClosePos))
+            .non_guaranteed(true)
+            .non_guaranteed(false)
+            .apply(&mut order);
+        let prices: Vec<f64> = order.order_combo_legs.iter().map(|leg| leg.price).collect();
+        assert_eq!(vec![2.5, UNSET_DOUBLE], prices);
+        assert_eq!(1, order.smart_combo_routing_params.len());
+        assert_eq!("0", order.smart_combo_routing_params[0].value);
+    }
+}
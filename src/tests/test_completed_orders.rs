@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::client::ConnStatus;
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::completed_orders::CompletedOrder;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::{IBKRApiLibError, TwsError};
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::order::{Order, OrderState, OrderStatusKind};
+    use crate::examples::contract_samples;
+    use crate::tests::{block_on, connected_client, CountingWaker};
+
+    //------------------------------------------------------------------------------------------------
+    fn completed(status: &str, total_quantity: f64, filled_quantity: f64) -> ServerRspMsg {
+        let order = Order {
+            total_quantity,
+            filled_quantity,
+            ..Default::default()
+        };
+        let order_state = OrderState {
+            status: status.to_string(),
+            completed_time: "20231020 15:59:58 America/New_York".to_string(),
+            completed_status: format!("{} Size: {}", status, filled_quantity),
+            ..Default::default()
+        };
+        ServerRspMsg::CompletedOrder {
+            contract: contract_samples::usstock(),
+            order,
+            order_state,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_completed_orders() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        // the orders arrive while the future is waiting
+        let answer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            events.send(completed("Cancelled", 100.0, 40.0)).unwrap();
+            events
+                .send(ServerRspMsg::NextValidId { order_id: 9 })
+                .unwrap();
+            events.send(completed("Filled", 50.0, 50.0)).unwrap();
+            events.send(ServerRspMsg::CompletedOrdersEnd).unwrap();
+        });
+
+        let orders = block_on(client.completed_orders(true)?)?;
+        answer.join().unwrap();
+        assert_eq!(2, orders.len());
+        assert_eq!(OrderStatusKind::Cancelled, orders[0].status);
+        assert_eq!(Some(40.0), orders[0].filled_quantity);
+        assert_eq!(Some(60.0), orders[0].remaining());
+        assert!(orders[0].is_partially_filled());
+        assert_eq!("Cancelled Size: 40", orders[0].completed_status);
+        assert_eq!(OrderStatusKind::Filled, orders[1].status);
+        assert!(orders[1].status.is_terminal());
+        assert!(!orders[1].is_partially_filled());
+        // events of other requests stay queued
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::NextValidId { order_id: 9 })
+        ));
+
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let (_, msg, _) = read_msg(&buf)?;
+        let fields = read_fields(&msg);
+        assert_eq!(
+            (ServerReqMsgDiscriminants::ReqCompletedOrders as i32).to_string(),
+            fields[0]
+        );
+        assert_eq!("1", fields[1]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_completed_orders_wait_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = Box::pin(client.completed_orders(true)?);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        // nothing has arrived, so the future must not wake itself
+        assert_eq!(0, counter.count());
+        events.send(completed("Filled", 50.0, 50.0)).unwrap();
+        assert_eq!(1, counter.count());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(1, counter.count());
+        events.send(ServerRspMsg::CompletedOrdersEnd).unwrap();
+        assert_eq!(2, counter.count());
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(orders) => assert_eq!(1, orders?.len()),
+            Poll::Pending => panic!("CompletedOrdersEnd did not resolve the future"),
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_status() {
+        let unset = CompletedOrder::from_msg(&completed("Inactive", 10.0, UNSET_DOUBLE)).unwrap();
+        assert_eq!(None, unset.filled_quantity);
+        assert_eq!(None, unset.remaining());
+        assert_eq!(
+            OrderStatusKind::PreSubmitted,
+            OrderStatusKind::from("PreSubmitted")
+        );
+        assert!(!OrderStatusKind::from("Submitted").is_terminal());
+        assert!(OrderStatusKind::from("Submitted").is_live());
+        assert!(OrderStatusKind::PendingCancel.is_live());
+        assert!(!OrderStatusKind::Filled.is_live());
+        assert!(OrderStatusKind::Inactive.is_terminal());
+        let other = OrderStatusKind::from("Unknown");
+        assert_eq!(OrderStatusKind::Other("Unknown".to_string()), other);
+        assert!(!other.is_live() && !other.is_terminal());
+        assert_eq!("Unknown", other.to_string());
+        assert_eq!("ApiCancelled", OrderStatusKind::ApiCancelled.to_string());
+        assert!(CompletedOrder::from_msg(&ServerRspMsg::CompletedOrdersEnd).is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// A CompletedOrder frame at server version 151 for an order with nothing set but the fields
+    /// from the auto cancel date on
+    fn completed_frame(tail: &[&str]) -> String {
+        // the contract and order fields before the auto cancel date, all empty
+        let mut frame = format!("{}\0", 101);
+        frame.push_str(&"\0".repeat(81));
+        for field in tail {
+            frame.push_str(field);
+            frame.push('\0');
+        }
+        frame
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn decode(frame: &str) -> Result<CompletedOrder, IBKRApiLibError> {
+        let (_, msg_queue) = channel::<String>();
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut decoder = Decoder::new(
+            msg_queue,
+            sender,
+            151,
+            Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+        );
+        decoder.interpret(frame)?;
+        Ok(CompletedOrder::from_msg(&receiver.try_recv().unwrap()).unwrap())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decode_completed_order_trailing_fields() -> Result<(), IBKRApiLibError> {
+        let completed = decode(&completed_frame(&[
+            "20240105 16:00:00",
+            "",
+            "0",
+            "1",
+            "",
+            "1",
+            "0",
+            "3000000001",
+            "20240105 15:59:58 US/Eastern",
+            "Cancelled",
+        ]))?;
+        assert_eq!("20240105 16:00:00", completed.order.auto_cancel_date);
+        assert_eq!(None, completed.filled_quantity);
+        assert!(completed.order.auto_cancel_parent);
+        assert!(completed.order.imbalance_only);
+        assert!(!completed.order.route_marketable_to_bbo);
+        assert_eq!(3000000001, completed.order.parent_perm_id);
+        assert_eq!("20240105 15:59:58 US/Eastern", completed.completed_time);
+        assert_eq!("Cancelled", completed.completed_status);
+
+        // a reported fill of nothing is kept
+        let completed = decode(&completed_frame(&[
+            "",
+            "0",
+            "0",
+            "0",
+            "",
+            "0",
+            "0",
+            "0",
+            "",
+            "Cancelled",
+        ]))?;
+        assert_eq!(Some(0.0), completed.filled_quantity);
+
+        // a filled quantity that is not a number is a bad message, not an unreported fill
+        let result = decode(&completed_frame(&[
+            "",
+            "lots",
+            "0",
+            "0",
+            "",
+            "0",
+            "0",
+            "0",
+            "",
+            "Cancelled",
+        ]));
+        match result {
+            Err(IBKRApiLibError::ApiError(err)) => {
+                assert_eq!(TwsError::BadMessage.code().to_string(), err.code)
+            }
+            _ => panic!("expected a bad message error"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[cfg(feature = "client-sync")]
+    use crate::core::client::EClient;
+    use crate::core::common::ConnStatus;
+    use crate::core::connection::{
+        ConnectionEvent, ConnectionState, ConnectionWatch, ConnectivityNotice, FarmKind, FarmStatus,
+    };
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+
+    //------------------------------------------------------------------------------------------------
+    fn started() -> ConnectionWatch {
+        let watch = ConnectionWatch::new();
+        watch.apply(ConnectionEvent::Connect);
+        watch.apply(ConnectionEvent::HandshakeStarted);
+        watch.apply(ConnectionEvent::ApiStarted);
+        watch
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_transitions() {
+        let mut state = ConnectionState::default();
+        let mut visited = vec![state];
+        for event in [
+            ConnectionEvent::Connect,
+            ConnectionEvent::HandshakeStarted,
+            ConnectionEvent::ApiStarted,
+            ConnectionEvent::ConnectivityLost,
+            ConnectionEvent::ConnectivityRestored { data_lost: true },
+            ConnectionEvent::SocketClosed,
+            ConnectionEvent::Connect,
+            ConnectionEvent::ConnectFailed,
+        ] {
+            state = state.next(&event).unwrap();
+            visited.push(state);
+        }
+        assert_eq!(
+            vec![
+                ConnectionState::Disconnected,
+                ConnectionState::Connecting,
+                ConnectionState::Handshaking,
+                ConnectionState::ApiStarted,
+                ConnectionState::Degraded,
+                ConnectionState::ApiStarted,
+                ConnectionState::Closed,
+                ConnectionState::Connecting,
+                ConnectionState::Disconnected,
+            ],
+            visited
+        );
+        // events that make no sense in a state are refused
+        assert_eq!(
+            None,
+            ConnectionState::Disconnected.next(&ConnectionEvent::ConnectivityLost)
+        );
+        assert_eq!(
+            None,
+            ConnectionState::Closed.next(&ConnectionEvent::Disconnect)
+        );
+        assert_eq!(
+            None,
+            ConnectionState::Connecting.next(&ConnectionEvent::ApiStarted)
+        );
+        assert!(ConnectionState::Degraded.is_usable());
+        assert!(!ConnectionState::Handshaking.is_usable());
+        assert_eq!(
+            Some(ConnectionEvent::ConnectivityRestored { data_lost: false }),
+            ConnectionEvent::from_error_code(1102)
+        );
+        assert_eq!(None, ConnectionEvent::from_error_code(2104));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_watch() -> Result<(), IBKRApiLibError> {
+        let watch = ConnectionWatch::new();
+        let mut receiver = watch.subscribe();
+        assert!(!receiver.has_changed());
+        assert!(matches!(
+            receiver.changed(Duration::ZERO),
+            Err(IBKRApiLibError::RecvTimeoutError(_))
+        ));
+
+        let sender = watch.clone();
+        let connecting = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.apply(ConnectionEvent::Connect);
+        });
+        assert_eq!(
+            ConnectionState::Connecting,
+            receiver.changed(Duration::from_secs(5))?
+        );
+        connecting.join().unwrap();
+        assert!(!receiver.has_changed());
+
+        // an ignored event is not a change
+        assert_eq!(None, watch.apply(ConnectionEvent::ConnectivityLost));
+        assert!(!receiver.has_changed());
+
+        // changes between two reads are seen as one
+        watch.apply(ConnectionEvent::HandshakeStarted);
+        watch.apply(ConnectionEvent::ApiStarted);
+        assert!(receiver.has_changed());
+        assert_eq!(ConnectionState::ApiStarted, receiver.borrow());
+        assert_eq!(
+            ConnectionState::ApiStarted,
+            receiver.changed(Duration::ZERO)?
+        );
+        assert_eq!(Some(ConnectionEvent::ApiStarted), receiver.last_event());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decoder_events() -> Result<(), IBKRApiLibError> {
+        let (tx, rx) = channel::<String>();
+        let (events, _received) = event_queue(EventQueueConfig::default());
+        let conn_state = Arc::new(Mutex::new(ConnStatus::CONNECTED));
+        let mut decoder = Decoder::new(rx, events, 151, conn_state);
+        let watch = started();
+        decoder.connection = watch.clone();
+        let receiver = watch.subscribe();
+
+        decoder.interpret(
+            "4\x002\x00-1\x001100\x00Connectivity between IB and TWS has been lost.\x00",
+        )?;
+        assert_eq!(ConnectionState::Degraded, watch.state());
+        decoder.interpret("4\x002\x00-1\x001101\x00Connectivity restored - data lost.\x00")?;
+        assert_eq!(ConnectionState::ApiStarted, watch.state());
+        assert_eq!(
+            Some(ConnectionEvent::ConnectivityRestored { data_lost: true }),
+            receiver.last_event()
+        );
+        // data farm notices do not change the state
+        decoder.interpret("4\x002\x00-1\x002104\x00Market data farm connection is OK\x00")?;
+        assert_eq!(ConnectionState::ApiStarted, watch.state());
+
+        // the reader dropping its end means the socket closed
+        drop(tx);
+        decoder.run()?;
+        assert_eq!(ConnectionState::Closed, watch.state());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "client-sync")]
+    #[test]
+    fn test_connect_failed() {
+        // a port nothing listens on
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port() as u32
+        };
+        let mut client = EClient::new();
+        let receiver = client.watch_connection();
+        assert_eq!(ConnectionState::Disconnected, client.connection_state());
+        assert!(client.connect("127.0.0.1", port, 0).is_err());
+        assert_eq!(ConnectionState::Disconnected, client.connection_state());
+        assert!(receiver.has_changed());
+        assert_eq!(Some(ConnectionEvent::ConnectFailed), receiver.last_event());
+        assert!(!client.is_connected());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_connectivity_notices() {
+        assert_eq!(
+            Some(ConnectivityNotice::Lost),
+            ConnectivityNotice::from_error(1100, "Connectivity between IB and TWS has been lost.")
+        );
+        assert_eq!(
+            Some(ConnectivityNotice::Restored {
+                data_maintained: false
+            }),
+            ConnectivityNotice::from_error(1101, "")
+        );
+        assert_eq!(
+            Some(ConnectionEvent::ConnectivityRestored { data_lost: false }),
+            ConnectionEvent::from_error_code(1102)
+        );
+        assert_eq!(
+            Some(ConnectivityNotice::Farm {
+                kind: FarmKind::SecurityDefinition,
+                name: "secdefnj".to_string(),
+                status: FarmStatus::Ok,
+            }),
+            ConnectivityNotice::from_error(2158, "Sec-def data farm connection is OK:secdefnj")
+        );
+        assert_eq!(
+            Some(ConnectivityNotice::Farm {
+                kind: FarmKind::MarketData,
+                name: String::new(),
+                status: FarmStatus::Inactive,
+            }),
+            ConnectivityNotice::from_error(2108, "Market data farm connection is inactive")
+        );
+        assert_eq!(None, ConnectionEvent::from_error_code(2104));
+        assert_eq!(
+            None,
+            ConnectivityNotice::from_error(200, "No security definition")
+        );
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::contract::ContractDetails;
+    use crate::core::contracts::ContractCache;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::ServerRspMsg;
+    use crate::examples::contract_samples;
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn details(con_id: i32) -> ContractDetails {
+        let mut details = ContractDetails::default();
+        details.contract = contract_samples::usstock();
+        details.contract.con_id = con_id;
+        details.market_rule_ids = "26".to_string();
+        details
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fetch_uses_cache() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        events
+            .send(ServerRspMsg::ContractData {
+                req_id,
+                contract_details: details(265598),
+            })
+            .unwrap();
+        events
+            .send(ServerRspMsg::ContractDataEnd { req_id })
+            .unwrap();
+
+        let mut cache = ContractCache::new();
+        let mut contract = contract_samples::usstock();
+        let fetched = cache.fetch(&mut client, &contract, Duration::from_millis(100))?;
+        assert_eq!(265598, fetched[0].contract.con_id);
+
+        // keys ignore case, and a cached contract is not requested again
+        contract.exchange = contract.exchange.to_lowercase();
+        let cached = cache.fetch(&mut client, &contract, Duration::from_millis(0))?;
+        assert_eq!(265598, cached[0].contract.con_id);
+        assert_eq!(1, cache.len());
+
+        cache.invalidate(&contract);
+        assert!(cache.get(&contract).is_none());
+        assert!(cache
+            .fetch(&mut client, &contract, Duration::from_millis(10))
+            .is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_expired_entries() {
+        let contract = contract_samples::usstock();
+        let mut cache = ContractCache::new().ttl(Duration::from_secs(0));
+        cache.insert(&contract, vec![details(1)]);
+        assert!(cache.get(&contract).is_none());
+        assert_eq!(1, cache.len());
+        cache.prune();
+        assert!(cache.is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_persistence() -> Result<(), IBKRApiLibError> {
+        let path = std::env::temp_dir().join(format!("contract_cache_{}.json", std::process::id()));
+        let contract = contract_samples::usstock();
+        {
+            let mut cache = ContractCache::open(&path, None)?;
+            cache.insert(&contract, vec![details(265598)]);
+            cache.save()?;
+        }
+
+        let cache = ContractCache::open(&path, Some(Duration::from_secs(3600)))?;
+        assert_eq!(265598, cache.get(&contract).unwrap()[0].contract.con_id);
+        assert!(ContractCache::open(&path, Some(Duration::from_secs(0)))?.is_empty());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+
+    use crate::core::common::BarData;
+    use crate::core::corporate_actions::{
+        CorporateActionKind, CorporateActionTimeline, DEFAULT_TOLERANCE,
+    };
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: &str, close: f64, volume: i64) -> BarData {
+        BarData {
+            date: parse_tws_time(date, Tz::UTC),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_reconcile_split_and_dividend() {
+        // 4:1 split effective 20200831, then a 0.50 dividend going ex on 20200904
+        let trades = vec![
+            bar("20200827", 400.0, 100),
+            bar("20200828", 500.0, 100),
+            bar("20200831", 128.0, 400),
+            bar("20200903", 100.0, 400),
+            bar("20200904", 101.0, 400),
+        ];
+        let adjusted = vec![
+            bar("20200827", 99.5, 400),
+            bar("20200828", 124.375, 400),
+            bar("20200831", 127.36, 400),
+            bar("20200903", 99.5, 400),
+            bar("20200904", 101.0, 400),
+        ];
+
+        let timeline = CorporateActionTimeline::reconcile(&trades, &adjusted, DEFAULT_TOLERANCE);
+        assert_eq!(2, timeline.actions.len());
+        assert_eq!(
+            CorporateActionKind::Split {
+                numerator: 4,
+                denominator: 1
+            },
+            timeline.actions[0].kind
+        );
+        assert_eq!(
+            parse_tws_time("20200831", Tz::UTC),
+            Some(timeline.actions[0].effective_date)
+        );
+        assert_eq!("20200831: split 4:1", timeline.actions[0].to_string());
+        match timeline.actions[1].kind {
+            CorporateActionKind::Dividend { amount } => assert!((amount - 0.5).abs() < 1e-9),
+            ref other => panic!("unexpected action {:?}", other),
+        }
+        assert_eq!(1, timeline.splits().count());
+        assert_eq!(1, timeline.dividends().count());
+
+        let mut restated = trades.clone();
+        timeline.adjust(&mut restated);
+        for (restated, adjusted) in restated.iter().zip(adjusted.iter()) {
+            assert!((restated.close - adjusted.close).abs() < 1e-9);
+        }
+        assert_eq!(400, restated[0].volume);
+        assert_eq!(1.0, timeline.cumulative_factor(trades[4].date.unwrap()));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_reconcile_reverse_split() {
+        let trades = vec![bar("20200101", 1.0, 1000), bar("20200102", 10.0, 100)];
+        let adjusted = vec![bar("20200101", 10.0, 100), bar("20200102", 10.0, 100)];
+
+        let timeline = CorporateActionTimeline::reconcile(&trades, &adjusted, DEFAULT_TOLERANCE);
+        assert_eq!(
+            vec![CorporateActionKind::Split {
+                numerator: 1,
+                denominator: 10
+            }],
+            timeline
+                .actions
+                .iter()
+                .map(|action| action.kind.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::crypto::{
+        crypto_contract, is_crypto, validate_crypto_order, CryptoAsset, CryptoOrder, CryptoTif,
+    };
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::order::Order;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_crypto_contracts() {
+        let contract = CryptoAsset::Eth.contract();
+        assert_eq!(
+            ("ETH", "CRYPTO", "PAXOS", "USD"),
+            (
+                contract.symbol.as_str(),
+                contract.sec_type.as_str(),
+                contract.exchange.as_str(),
+                contract.currency.as_str()
+            )
+        );
+        assert!(is_crypto(&contract));
+        assert!(is_crypto(&crypto_contract("btc", "usd")));
+        assert_eq!("BTC", crypto_contract("btc", "usd").symbol);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_crypto_orders() -> Result<(), IBKRApiLibError> {
+        let order = CryptoOrder::buy_cash(dec!(250.129)).build()?;
+        assert_eq!(
+            ("BUY", "MKT", "IOC"),
+            (
+                order.action.as_str(),
+                order.order_type.as_str(),
+                order.tif.as_str()
+            )
+        );
+        assert_eq!(250.12, order.cash_qty);
+        assert_eq!(0.0, order.total_quantity);
+
+        let order = CryptoOrder::sell(dec!(0.123456789)).build()?;
+        assert_eq!(0.12345678, order.total_quantity);
+        assert_eq!(UNSET_DOUBLE, order.cash_qty);
+        assert_eq!(UNSET_DOUBLE, order.lmt_price);
+
+        // limits are rounded down for buys and up for sells
+        let buy = CryptoOrder::buy(dec!(0.5))
+            .limit(dec!(65000.37))
+            .min_tick(dec!(0.25))
+            .build()?;
+        assert_eq!(("LMT", "GTC"), (buy.order_type.as_str(), buy.tif.as_str()));
+        assert_eq!(65000.25, buy.lmt_price);
+        let sell = CryptoOrder::sell(dec!(0.5))
+            .stop_limit(dec!(64000.1), dec!(63990.1))
+            .min_tick(dec!(0.25))
+            .tif(CryptoTif::Ioc)
+            .build()?;
+        assert_eq!(
+            ("STP LMT", "IOC"),
+            (sell.order_type.as_str(), sell.tif.as_str())
+        );
+        assert_eq!((64000.25, 63990.25), (sell.aux_price, sell.lmt_price));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_crypto_order_validation() {
+        // a market buy is given in cash, and every market order is IOC
+        assert!(CryptoOrder::buy(dec!(1)).build().is_err());
+        assert!(CryptoOrder::buy_cash(dec!(100))
+            .tif(CryptoTif::Gtc)
+            .build()
+            .is_err());
+        assert!(CryptoOrder::buy_cash(dec!(0.001)).build().is_err());
+        assert!(CryptoOrder::buy_cash(dec!(100))
+            .limit(dec!(60000))
+            .build()
+            .is_err());
+        assert!(CryptoOrder::sell(dec!(0)).build().is_err());
+        assert!(CryptoOrder::sell(dec!(1)).limit(dec!(0)).build().is_err());
+
+        let order = |order_type: &str, tif: &str| Order {
+            action: "SELL".to_string(),
+            order_type: order_type.to_string(),
+            tif: tif.to_string(),
+            total_quantity: 1.0,
+            lmt_price: 60000.0,
+            aux_price: 60100.0,
+            ..Default::default()
+        };
+        assert!(validate_crypto_order(&order("LMT", "GTC")).is_ok());
+        assert!(validate_crypto_order(&order("stp lmt", "ioc")).is_ok());
+        assert!(validate_crypto_order(&order("LMT", "DAY")).is_err());
+        assert!(validate_crypto_order(&order("STP", "GTC")).is_err());
+        assert!(validate_crypto_order(&order("TRAIL", "GTC")).is_err());
+        assert!(validate_crypto_order(&order("MOC", "IOC")).is_err());
+    }
+}
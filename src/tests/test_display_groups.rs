@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::display_groups::{parse_groups, query_groups, DisplayGroup, GroupContract};
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerRspMsg};
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse() -> Result<(), IBKRApiLibError> {
+        assert_eq!(vec![1, 2, 7], parse_groups("1|2|7")?);
+        assert!(parse_groups("")?.is_empty());
+        assert!(parse_groups("1|x").is_err());
+
+        let contract: GroupContract = "8314@SMART".parse()?;
+        assert_eq!(
+            GroupContract::Contract {
+                con_id: 8314,
+                exchange: "SMART".to_string()
+            },
+            contract
+        );
+        assert_eq!("8314@SMART", contract.to_string());
+        assert_eq!(GroupContract::Empty, "none".parse()?);
+        assert_eq!(GroupContract::Combo, "combo".parse()?);
+        assert_eq!("none", GroupContract::Empty.to_string());
+        assert!("IBM@SMART".parse::<GroupContract>().is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_query_and_follow_group() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let query_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        events
+            .send(ServerRspMsg::DisplayGroupList {
+                req_id: query_id,
+                groups: "1|2|3".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            vec![1, 2, 3],
+            query_groups(&mut client, Duration::from_millis(100))?
+        );
+
+        let mut group = DisplayGroup::subscribe(&mut client, 2)?;
+        events
+            .send(ServerRspMsg::DisplayGroupUpdated {
+                req_id: group.req_id + 1,
+                contract_info: "1@SMART".to_string(),
+            })
+            .unwrap();
+        events
+            .send(ServerRspMsg::DisplayGroupUpdated {
+                req_id: group.req_id,
+                contract_info: "265598@SMART".to_string(),
+            })
+            .unwrap();
+        let changed = group.next_change(&mut client, Duration::from_millis(100))?;
+        assert_eq!(Some("265598@SMART".parse()?), changed);
+        assert_eq!(
+            None,
+            group.next_change(&mut client, Duration::from_millis(10))?
+        );
+        // the update of another subscription is left for the caller
+        assert!(client.get_event()?.is_some());
+
+        group.set_contract(&mut client, GroupContract::Empty)?;
+        let req_id = group.req_id;
+        group.unsubscribe(&mut client)?;
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, _query, rest) = read_msg(&buf)?;
+        let (_, subscribe, rest) = read_msg(&rest)?;
+        assert_eq!(
+            vec![req_id.to_string(), "2".to_string()],
+            read_fields(&subscribe)[2..4].to_vec()
+        );
+        let (_, update, _) = read_msg(&rest)?;
+        assert_eq!("none", read_fields(&update)[3]);
+        Ok(())
+    }
+}
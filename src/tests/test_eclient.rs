@@ -1,28 +1,38 @@
 #[cfg(test)]
 mod tests {
-    use crate::core::client::{ConnStatus, EClient, POISONED_MUTEX};
+    use crate::core::client::{EClient, EClientConfig};
 
     use crate::core::{
         common::{
-            BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
-            HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider,
-            PriceIncrement, RealTimeBar, SmartComponent, TickAttrib, TickAttribBidAsk,
-            TickAttribLast, TickByTickType, TickType,
+            BarData, CommissionReport, DepthMktDataDescription, Environment, FaDataType,
+            FamilyCode, HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast,
+            NewsProvider, PriceIncrement, RealTimeBar, SmartComponent, TickAttrib,
+            TickAttribBidAsk, TickAttribLast, TickByTickType, TickType,
         },
         contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract},
+        crypto::{CryptoAsset, CryptoOrder},
+        decoder::Decoder,
         execution::{Execution, ExecutionFilter},
-        order::{Order, SoftDollarTier},
-        streamer::{Streamer, TestStreamer},
+        fx,
+        order::{ExerciseAction, ExerciseOptions, Order, SoftDollarTier},
+        req_ids::RequestKind,
+        server_versions::MIN_SERVER_VER_CASH_QTY,
     };
+    use crate::tests::{block_on, CountingWaker};
     use crate::{
         core::{
             errors::IBKRApiLibError,
-            messages::{read_fields, read_msg, ServerReqMsgDiscriminants},
+            messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg},
             order::OrderState,
         },
-        examples::contract_samples::simple_future,
+        examples::contract_samples::{self, simple_future},
+        examples::order_samples,
     };
+    use rust_decimal_macros::dec;
+    use std::future::Future;
+    use std::sync::mpsc::channel;
     use std::sync::{Arc, Mutex};
+    use std::task::{Context, Waker};
 
     //------------------------------------------------------------------------------------------------
     trait ClientConnectForTest {
@@ -31,13 +41,51 @@ mod tests {
 
     impl ClientConnectForTest for EClient {
         fn connect_test(&mut self) {
-            *self.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
-            let streamer = TestStreamer::new();
-            self.set_streamer(Option::from(Box::new(streamer) as Box<dyn Streamer>));
-            self.server_version = 151;
+            crate::tests::connect_test(self);
         }
     }
 
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_exercise_option() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::new();
+        app.connect_test();
+        let mut contract = simple_future();
+        contract.local_symbol = "ESZ4 C5000".to_string();
+
+        let req_id = app.exercise_option(&contract, ExerciseAction::Lapse, 2, "DU111", true)?;
+        assert_eq!(
+            Some(RequestKind::ExerciseOptions),
+            app.correlations()
+                .context(req_id)
+                .map(|context| context.kind)
+        );
+        let fields = &sent_messages(&mut app)?[0];
+        assert_eq!(
+            ServerReqMsgDiscriminants::ExerciseOptions as i32,
+            fields[0].parse::<i32>().unwrap()
+        );
+        assert_eq!(req_id.to_string(), fields[2]);
+        // the trading class, then action, quantity, account and override end the message
+        assert_eq!(
+            vec!["", "2", "2", "DU111", "1"],
+            fields[fields.len() - 5..].to_vec()
+        );
+
+        let request = ExerciseOptions::new(ExerciseAction::Exercise, 1, "DU111", false);
+        app.req_exercise_options(7, &contract, &request)?;
+        let fields = &sent_messages(&mut app)?[0];
+        assert_eq!("7", fields[2]);
+        assert_eq!(
+            vec!["1", "1", "DU111", "0"],
+            fields[fields.len() - 4..].to_vec()
+        );
+        assert!(app
+            .exercise_options(7, &contract, 3, 1, &"DU111".to_string(), 0)
+            .is_err());
+        Ok(())
+    }
+
     //------------------------------------------------------------------------------------------------
     #[test]
     fn test_req_account_summary() -> Result<(), IBKRApiLibError> {
@@ -391,4 +439,240 @@ mod tests {
 
         Ok(())
     }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_require_paper() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::with_config(EClientConfig::default().require_paper());
+        app.connect_test();
+        let contract = simple_future();
+        let mut order = Order::default();
+        order.action = "BUY".to_string();
+        order.order_type = "MKT".to_string();
+        order.total_quantity = 1.0;
+
+        // the managed accounts list has not arrived yet
+        assert_eq!(Environment::Unknown, app.environment());
+        assert!(app.place_order(1, &contract, &order).is_err());
+
+        let (_, rx) = channel::<String>();
+        let mut decoder = Decoder::new(rx, app.evt_chan.0.clone(), 151, app.conn_state.clone());
+        decoder.environment = app.environment.clone();
+
+        decoder.interpret("15\x001\x00DU111,DU222\x00")?;
+        assert_eq!(Environment::Paper, app.environment());
+        app.place_order(1, &contract, &order)?;
+        let mut buf = Vec::<u8>::new();
+        app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        assert!(!buf.is_empty());
+
+        decoder.interpret("15\x001\x00DU111,U333\x00")?;
+        assert_eq!(Environment::Live, app.environment());
+        assert!(app.place_order(2, &contract, &order).is_err());
+
+        assert_eq!(Environment::Unknown, Environment::from_accounts(""));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_place_crypto_order() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::new();
+        app.connect_test();
+        let contract = CryptoAsset::Btc.contract();
+
+        let mut order = CryptoOrder::buy_cash(dec!(100)).build()?;
+        app.place_order(1, &contract, &order)?;
+        let msgs = sent_messages(&mut app)?;
+        assert_eq!(1, msgs.len());
+        for field in ["CRYPTO", "PAXOS", "IOC", "100"].iter() {
+            assert!(
+                msgs[0].iter().any(|sent| sent == field),
+                "{} not sent",
+                field
+            );
+        }
+
+        // refused before anything is sent
+        order.order_type = "STP".to_string();
+        assert!(matches!(
+            app.place_order(2, &contract, &order),
+            Err(IBKRApiLibError::Config(_))
+        ));
+        assert!(sent_messages(&mut app)?.is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_place_order_regulatory_fields() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::new();
+        app.connect_test();
+        let contract = simple_future();
+        let order = Order {
+            action: "BUY".to_string(),
+            order_type: "LMT".to_string(),
+            total_quantity: 1.0,
+            lmt_price: 4500.0,
+            ext_operator: "desk 4".to_string(),
+            ..Default::default()
+        };
+        app.place_order(1, &contract, &order)?;
+        let sent = sent_messages(&mut app)?;
+        assert_eq!(1, sent.len());
+        assert!(sent[0].contains(&"desk 4".to_string()));
+
+        // orders to IBKRATS and pegged orders without the newer attributes are not refused
+        let mut contract = contract_samples::usstock();
+        contract.exchange = "IBKRATS".to_string();
+        let peg_best = Order {
+            order_type: "PEG BEST".to_string(),
+            ..order_samples::limit_order("BUY", 100.0, 101.5)
+        };
+        for order in [order_samples::limit_order("BUY", 100.0, 101.5), peg_best].iter() {
+            app.place_order(2, &contract, order)?;
+        }
+        assert_eq!(2, sent_messages(&mut app)?.len());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn sent_messages(app: &mut EClient) -> Result<Vec<Vec<String>>, IBKRApiLibError> {
+        let mut buf = Vec::<u8>::new();
+        app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let mut msgs = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            msgs.push(read_fields(&msg));
+            buf = rest;
+        }
+        Ok(msgs)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_what_if_order() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::new();
+        app.connect_test();
+        let contract = simple_future();
+        let mut order = Order::default();
+        order.action = "BUY".to_string();
+        order.order_type = "LMT".to_string();
+        order.total_quantity = 2.0;
+        order.lmt_price = 4500.0;
+        order.parent_id = 11;
+        order.transmit = false;
+
+        let mut order_state = OrderState::default();
+        order_state.init_margin_change = "12500.0".to_string();
+        order_state.commission = 4.24;
+        let mut preview = order.clone();
+        preview.what_if = true;
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 12 })
+            .unwrap();
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::OpenOrder {
+                order_id: 12,
+                contract: contract.clone(),
+                order: preview,
+                order_state,
+            })
+            .unwrap();
+
+        let preview = app.what_if_order(&contract, &order)?;
+        assert_eq!(None, preview.order_id());
+        let state = block_on(preview)?;
+        assert_eq!("12500.0", state.init_margin_change);
+        assert_eq!(4.24, state.commission);
+        assert!(!order.what_if);
+
+        let sent = sent_messages(&mut app)?;
+        assert_eq!(2, sent.len());
+        assert_eq!(
+            ServerReqMsgDiscriminants::ReqIds as i32,
+            sent[0][0].parse::<i32>().unwrap()
+        );
+
+        // the preview is the order itself with what_if set, on its own and transmitted
+        let mut expected_order = order.clone();
+        expected_order.what_if = true;
+        expected_order.parent_id = 0;
+        expected_order.transmit = true;
+        let mut expected = EClient::new();
+        expected.connect_test();
+        expected.place_order(12, &contract, &expected_order)?;
+        assert_eq!(sent_messages(&mut expected)?[0], sent[1]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_cash_qty_order() -> Result<(), IBKRApiLibError> {
+        let conversion = fx::convert(1_500_000.0, "JPY", "USD")?;
+        let mut app = EClient::new();
+        app.connect_test();
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 3 })
+            .unwrap();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut preview = Box::pin(app.what_if_order(&conversion.contract, &conversion.order)?);
+        // the preview is placed as soon as its order id arrives, then waits for TWS
+        assert!(preview
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        assert_eq!(Some(3), preview.order_id());
+        assert_eq!(0, counter.count());
+        drop(preview);
+        let sent = sent_messages(&mut app)?;
+        assert_eq!(2, sent.len());
+        assert!(sent[1].iter().any(|field| field == "1500000"));
+
+        // older servers refuse cash quantities, but not orders without one
+        app.server_version = MIN_SERVER_VER_CASH_QTY - 1;
+        assert!(app
+            .place_order(4, &conversion.contract, &conversion.order)
+            .is_err());
+        let order = fx::convert(1_000.0, "EUR", "USD")?.order;
+        app.place_order(5, &conversion.contract, &order)?;
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_what_if_order_rejected() -> Result<(), IBKRApiLibError> {
+        let mut app = EClient::new();
+        app.connect_test();
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 5 })
+            .unwrap();
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id: 5,
+                error_code: 2104,
+                error_str: "Market data farm connection is OK".to_string(),
+            })
+            .unwrap();
+        app.evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id: 5,
+                error_code: 201,
+                error_str: "Order rejected - reason: insufficient margin".to_string(),
+            })
+            .unwrap();
+
+        match block_on(app.what_if_order(&simple_future(), &Order::default())?) {
+            Err(IBKRApiLibError::ApiError(err)) => assert_eq!("201", err.code),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::TryRecvError;
+    use std::sync::Arc;
+    use std::task::Waker;
+
+    use crate::core::common::TickType;
+    use crate::core::event_queue::{event_queue, EventQueueConfig, MessageClass, OverflowPolicy};
+    use crate::core::messages::ServerRspMsg;
+    use crate::tests::CountingWaker;
+
+    //------------------------------------------------------------------------------------------------
+    fn tick_size(size: i32) -> ServerRspMsg {
+        ServerRspMsg::TickSize {
+            req_id: 1,
+            tick_type: TickType::BidSize,
+            size,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bounded_queue_overflow_policies() {
+        let config = EventQueueConfig::bounded(2)
+            .with_policy(MessageClass::MarketData, OverflowPolicy::DropOldest)
+            .with_policy(MessageClass::Order, OverflowPolicy::DropNewest);
+        let (sender, receiver) = event_queue(config);
+
+        sender.send(tick_size(1)).unwrap();
+        sender
+            .send(ServerRspMsg::NextValidId { order_id: 7 })
+            .unwrap();
+        // market data replaces the oldest tick, order messages are discarded
+        sender.send(tick_size(2)).unwrap();
+        sender
+            .send(ServerRspMsg::NextValidId { order_id: 8 })
+            .unwrap();
+
+        let metrics = receiver.metrics();
+        assert_eq!(2, metrics.depth);
+        assert_eq!(2, metrics.high_water_mark);
+        assert_eq!(Some(&1), metrics.dropped.get(&MessageClass::MarketData));
+        assert_eq!(2, metrics.total_dropped());
+
+        match receiver.try_recv() {
+            Ok(ServerRspMsg::NextValidId { order_id }) => assert_eq!(7, order_id),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        match receiver.try_recv() {
+            Ok(ServerRspMsg::TickSize { size, .. }) => assert_eq!(2, size),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert_eq!(TryRecvError::Empty, receiver.try_recv().unwrap_err());
+
+        drop(sender);
+        assert_eq!(TryRecvError::Disconnected, receiver.try_recv().unwrap_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_register_waker() {
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+
+        // an empty queue wakes the task once, when the next message is queued
+        receiver.register_waker(&waker);
+        receiver.register_waker(&waker);
+        assert_eq!(0, counter.count());
+        sender.send(tick_size(1)).unwrap();
+        assert_eq!(1, counter.count());
+        sender.send(tick_size(2)).unwrap();
+        assert_eq!(1, counter.count());
+
+        // a waiting message wakes it at once
+        receiver.register_waker(&waker);
+        assert_eq!(2, counter.count());
+        receiver.try_recv().unwrap();
+        receiver.try_recv().unwrap();
+
+        // so does the last sender going away
+        receiver.register_waker(&waker);
+        drop(sender);
+        assert_eq!(3, counter.count());
+    }
+}
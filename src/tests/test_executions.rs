@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::core::common::Action;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::execution::{Execution, ExecutionFilter};
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::examples::contract_samples;
+    use crate::tests::{block_on, connected_client, CountingWaker};
+
+    //------------------------------------------------------------------------------------------------
+    fn execution_data(req_id: i32, exec_id: &str, shares: f64) -> ServerRspMsg {
+        ServerRspMsg::ExecutionData {
+            req_id,
+            contract: contract_samples::usstock(),
+            execution: Execution {
+                exec_id: exec_id.to_string(),
+                side: "BOT".to_string(),
+                shares,
+                ..Default::default()
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_execution_filter_builder() -> Result<(), IBKRApiLibError> {
+        let filter = ExecutionFilter::default()
+            .client_id(3)
+            .account("DU12345")
+            .time_since(Utc.with_ymd_and_hms(2023, 10, 20, 14, 30, 5).unwrap())
+            .symbol("IBM")
+            .sec_type("STK")
+            .exchange("SMART")
+            .side(Action::SELL);
+
+        let mut client = connected_client();
+        client.req_executions(7, &filter)?;
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let (_, msg, _) = read_msg(&buf)?;
+        assert_eq!(
+            vec![
+                "7",
+                "3",
+                "7",
+                "3",
+                "DU12345",
+                "20231020-14:30:05",
+                "IBM",
+                "STK",
+                "SMART",
+                "SELL"
+            ],
+            read_fields(&msg)[..10].to_vec()
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_executions() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        // the executions arrive while the future is waiting
+        let answer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            events.send(execution_data(req_id, "0001", 100.0)).unwrap();
+            // a fill of a live order, reported without a request
+            events.send(execution_data(-1, "0002", 5.0)).unwrap();
+            events.send(execution_data(req_id, "0003", 50.0)).unwrap();
+            events
+                .send(ServerRspMsg::ExecutionDataEnd { req_id })
+                .unwrap();
+        });
+
+        let filter = ExecutionFilter::default().symbol("AMZN");
+        let executions = block_on(client.executions(&filter)?)?;
+        answer.join().unwrap();
+        let ids: Vec<&str> = executions
+            .iter()
+            .map(|(_, execution)| execution.exec_id.as_str())
+            .collect();
+        assert_eq!(vec!["0001", "0003"], ids);
+        assert_eq!("AMZN", executions[0].0.symbol);
+        assert!(client.correlations().context(req_id).is_none());
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::ExecutionData { req_id: -1, .. })
+        ));
+
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let (_, msg, _) = read_msg(&buf)?;
+        assert_eq!(
+            (ServerReqMsgDiscriminants::ReqExecutions as i32).to_string(),
+            read_fields(&msg)[0]
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_executions_wait_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = Box::pin(client.executions(&ExecutionFilter::default())?);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        // nothing has arrived, so the future must not wake itself
+        assert_eq!(0, counter.count());
+        events
+            .send(ServerRspMsg::ExecutionDataEnd { req_id })
+            .unwrap();
+        assert_eq!(1, counter.count());
+        assert!(matches!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(_))));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_executions_error() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id,
+                error_code: 321,
+                error_str: "Error validating request".to_string(),
+            })
+            .unwrap();
+        let result = block_on(client.executions(&ExecutionFilter::default())?);
+        assert!(matches!(result, Err(IBKRApiLibError::ApiError(_))));
+        assert!(client.correlations().context(req_id).is_none());
+        Ok(())
+    }
+}
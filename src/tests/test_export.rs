@@ -0,0 +1,207 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use arrow_array::{Array, Float64Array, Int64Array, TimestampMicrosecondArray};
+    use arrow_schema::{DataType, TimeUnit};
+    use chrono::{DateTime, Utc};
+    use chrono_tz::Tz;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::core::common::{
+        BarData, HistoricalTickLast, TickAttrib, TickAttribBidAsk, TickAttribLast, TickByTickData,
+        TickType,
+    };
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::export::{to_csv, to_parquet, Record, StreamRecorder};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: &str, close: f64) -> BarData {
+        BarData::new(
+            parse_tws_time(date, Tz::UTC).unwrap(),
+            100.0,
+            101.5,
+            99.25,
+            close,
+            12000,
+            40,
+            100.4,
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn csv_text<R: Record>(rows: &[R]) -> Result<String, IBKRApiLibError> {
+        let mut out = Vec::new();
+        to_csv(rows, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bars_to_csv() -> Result<(), IBKRApiLibError> {
+        let bars = vec![
+            bar("20240105  14:30:00", 100.5),
+            bar("20240105 14:35:00 US/Eastern", 101.0),
+        ];
+        assert_eq!(
+            "time,open,high,low,close,volume,bar_count,average\n\
+             2024-01-05T14:30:00Z,100,101.5,99.25,100.5,12000,40,100.4\n\
+             2024-01-05T19:35:00Z,100,101.5,99.25,101,12000,40,100.4\n",
+            csv_text(&bars)?
+        );
+
+        let epoch = vec![bar("1704465000", 100.5)];
+        assert!(csv_text(&epoch)?.contains("\n2024-01-05T14:30:00Z,"));
+
+        let daily = vec![bar("20240105", 100.5)];
+        assert!(csv_text(&daily)?.contains("\n2024-01-05T00:00:00Z,"));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticks_to_parquet() -> Result<(), IBKRApiLibError> {
+        let ticks = vec![
+            HistoricalTickLast::new(
+                1704465000,
+                TickAttribLast::new(false, true),
+                100.25,
+                300,
+                "ISLAND".to_string(),
+                "".to_string(),
+            ),
+            HistoricalTickLast::new(
+                1704465001,
+                TickAttribLast::default(),
+                100.5,
+                100,
+                "ARCA".to_string(),
+                "I".to_string(),
+            ),
+        ];
+        let path =
+            std::env::temp_dir().join(format!("ibtwsapi-ticks-{}.parquet", std::process::id()));
+        to_parquet(&ticks, File::create(&path)?)?;
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path)?)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path)?;
+        let batch = &batches[0];
+        assert_eq!(2, batch.num_rows());
+        assert_eq!(
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            batch.schema().field(0).data_type()
+        );
+        let times = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(1_704_465_001_000_000, times.value(1));
+        let prices = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(100.25, prices.value(0));
+        let sizes = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(300, sizes.value(0));
+        assert_eq!(
+            vec![
+                "time",
+                "price",
+                "size",
+                "exchange",
+                "special_conditions",
+                "past_limit",
+                "unreported"
+            ],
+            batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| field.name().as_str())
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_stream_recorder() -> Result<(), IBKRApiLibError> {
+        let received: DateTime<Utc> = "2024-01-05T14:30:00.250Z".parse().unwrap();
+        let mut recorder = StreamRecorder::new();
+        let events = [
+            ServerRspMsg::MarketDepthL2 {
+                req_id: 7,
+                position: 0,
+                market_maker: "NSDQ".to_string(),
+                operation: 0,
+                side: 1,
+                price: 100.0,
+                size: 200,
+                is_smart_depth: true,
+            },
+            ServerRspMsg::TickPrice {
+                req_id: 8,
+                tick_type: TickType::Bid,
+                price: 99.5,
+                tick_attr: TickAttrib::default(),
+            },
+            ServerRspMsg::TickSize {
+                req_id: 8,
+                tick_type: TickType::BidSize,
+                size: 5,
+            },
+            ServerRspMsg::TickByTick {
+                req_id: 9,
+                time: 1704465000,
+                tick: TickByTickData::BidAsk {
+                    bid_price: 99.5,
+                    ask_price: 99.75,
+                    bid_size: 5,
+                    ask_size: 7,
+                    tick_attrib_bid_ask: TickAttribBidAsk::default(),
+                },
+            },
+            ServerRspMsg::NextValidId { order_id: 1 },
+        ];
+        let recorded: Vec<bool> = events
+            .iter()
+            .map(|event| recorder.observe_at(event, received))
+            .collect();
+        assert_eq!(vec![true, true, true, true, false], recorded);
+        assert_eq!(1, recorder.depth().len());
+
+        assert_eq!(
+            "received,req_id,position,market_maker,operation,side,price,size,is_smart_depth\n\
+             2024-01-05T14:30:00.250Z,7,0,NSDQ,0,1,100,200,true\n",
+            csv_text(recorder.depth())?
+        );
+        assert_eq!(
+            "received,time,req_id,field,price,size\n\
+             2024-01-05T14:30:00.250Z,,8,bidPrice,99.5,\n\
+             2024-01-05T14:30:00.250Z,,8,bidSize,,5\n\
+             2024-01-05T14:30:00.250Z,2024-01-05T14:30:00Z,9,Bid,99.5,5\n\
+             2024-01-05T14:30:00.250Z,2024-01-05T14:30:00Z,9,Ask,99.75,7\n",
+            csv_text(recorder.ticks())?
+        );
+
+        let mut parquet = Vec::new();
+        to_parquet(recorder.ticks(), &mut parquet)?;
+        assert_eq!(b"PAR1", &parquet[..4]);
+
+        recorder.clear();
+        assert!(recorder.ticks().is_empty());
+        Ok(())
+    }
+}
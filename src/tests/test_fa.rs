@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::client::EClient;
+    use crate::core::errors::IBKRApiLibError;
+    #[cfg(feature = "xml")]
+    use crate::core::fa::{FaAllocation, FaProfile, FaProfileType};
+    use crate::core::fa::{FaAllocationMethod, FaGroup};
+    use crate::core::messages::{read_fields, read_msg};
+    use crate::core::order::Order;
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    fn groups() -> Vec<FaGroup> {
+        vec![FaGroup {
+            name: "Growth & Income".to_string(),
+            accounts: vec!["DU1".to_string(), "DU2".to_string()],
+            default_method: FaAllocationMethod::NetLiq,
+        }]
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "xml")]
+    fn profiles() -> Vec<FaProfile> {
+        vec![FaProfile {
+            name: "Pct".to_string(),
+            profile_type: FaProfileType::Percentages,
+            allocations: vec![
+                FaAllocation {
+                    account: "DU1".to_string(),
+                    amount: 60.0,
+                },
+                FaAllocation {
+                    account: "DU2".to_string(),
+                    amount: 40.0,
+                },
+            ],
+        }]
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_allocation() {
+        let order =
+            Order::default().allocate_to_group("Growth", FaAllocationMethod::PctChange, Some(25.0));
+        assert_eq!("Growth", order.fa_group);
+        assert_eq!("PctChange", order.fa_method);
+        assert_eq!("25", order.fa_percentage);
+        assert_eq!(
+            Some(FaAllocationMethod::PctChange),
+            order.fa_allocation_method()
+        );
+
+        let order = order.allocate_to_profile("Pct");
+        assert_eq!("Pct", order.fa_profile);
+        assert!(order.fa_group.is_empty());
+        assert_eq!(None, order.fa_allocation_method());
+        assert!("Equal".parse::<FaAllocationMethod>().is_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_round_trip() -> Result<(), IBKRApiLibError> {
+        let xml = FaGroup::list_to_xml(&groups());
+        assert!(xml.contains("<name>Growth &amp; Income</name>"));
+        assert_eq!(groups(), FaGroup::parse_list(&xml)?);
+        assert_eq!(
+            profiles(),
+            FaProfile::parse_list(&FaProfile::list_to_xml(&profiles()))?
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_parse_tws_xml() -> Result<(), IBKRApiLibError> {
+        use crate::core::fa::FaAlias;
+
+        let groups = FaGroup::parse_list(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <ListOfGroups>
+              <Group>
+                <name>Equal</name>
+                <ListOfAccts varName="list">
+                  <String>DU1</String>
+                  <String>DU2</String>
+                </ListOfAccts>
+                <defaultMethod>EqualQuantity</defaultMethod>
+              </Group>
+            </ListOfGroups>"#,
+        )?;
+        assert_eq!(vec!["DU1", "DU2"], groups[0].accounts);
+        assert_eq!(FaAllocationMethod::EqualQuantity, groups[0].default_method);
+
+        let aliases = FaAlias::parse_list(
+            "<ListOfAccountAliases><AccountAlias><account>DU1</account>\
+             <alias>Main</alias></AccountAlias></ListOfAccountAliases>",
+        )?;
+        assert_eq!("Main", aliases[0].alias);
+        assert!(FaProfile::parse_list(
+            "<ListOfAllocationProfiles><AllocationProfile>\
+             <name>X</name><type>9</type></AllocationProfile></ListOfAllocationProfiles>"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_replace_fa_groups() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        client.replace_fa_groups(&groups())?;
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, _) = read_msg(&buf)?;
+        let fields = read_fields(&request);
+        assert_eq!("1", fields[2]);
+        assert_eq!(FaGroup::list_to_xml(&groups()), fields[3]);
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+
+    use crate::core::common::CommissionReport;
+    use crate::core::contract::Contract;
+    use crate::core::execution::Execution;
+    use crate::core::execution::{FillLedger, LotMatching};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn execution(exec_id: &str, time: &str, side: &str, shares: f64, price: f64) -> ServerRspMsg {
+        ServerRspMsg::ExecutionData {
+            req_id: -1,
+            contract: Contract {
+                con_id: 265598,
+                symbol: "AAPL".to_string(),
+                ..Default::default()
+            },
+            execution: Execution {
+                exec_id: exec_id.to_string(),
+                time: parse_tws_time(time, Tz::UTC),
+                acct_number: "DU123456".to_string(),
+                side: side.to_string(),
+                shares,
+                price,
+                ..Default::default()
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn commission(exec_id: &str, commission: f64) -> ServerRspMsg {
+        ServerRspMsg::CommissionReport {
+            commission_report: CommissionReport {
+                exec_id: exec_id.to_string(),
+                commission,
+                currency: "USD".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(matching: LotMatching) -> FillLedger {
+        let mut ledger = FillLedger::new(matching);
+        ledger.observe(&execution("e1", "20200406  09:31:00", "BOT", 100.0, 10.0));
+        ledger.observe(&commission("e1", 1.0));
+        ledger.observe(&execution("e2", "20200406  09:32:00", "BOT", 100.0, 11.0));
+        ledger.observe(&commission("e3", 1.5));
+        ledger.observe(&execution("e3", "20200407  10:00:00", "SLD", 150.0, 12.0));
+        ledger
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fill_ledger_fifo() {
+        let mut ledger = trade(LotMatching::Fifo);
+
+        let closed = ledger.closed_lots();
+        assert_eq!(2, closed.len());
+        assert_eq!(
+            ("e1", 100.0),
+            (closed[0].open_exec_id.as_str(), closed[0].quantity)
+        );
+        assert_eq!(
+            ("e2", 50.0),
+            (closed[1].open_exec_id.as_str(), closed[1].quantity)
+        );
+        assert_eq!(250.0, ledger.fill("e3").unwrap().realized_pnl);
+        assert_eq!(Some(1.5), ledger.fill("e3").unwrap().commission);
+        assert_eq!(50.0, ledger.position("DU123456", 265598));
+        assert_eq!(1, ledger.missing_commissions().len());
+
+        let days = ledger.per_day();
+        assert_eq!(2, days["20200406"].fills);
+        assert_eq!(200.0, days["20200406"].bought);
+        assert_eq!(250.0 - 1.5, days["20200407"].net_realized_pnl());
+        assert_eq!(2.5, ledger.per_symbol()["AAPL"].commissions);
+
+        // replayed executions are ignored, and a short is opened past flat
+        assert!(ledger
+            .observe(&execution("e3", "20200407  10:00:00", "SLD", 150.0, 12.0))
+            .is_empty());
+        let closed = ledger.observe(&execution("e4", "20200407  11:00:00", "SLD", 80.0, 9.0));
+        assert_eq!(-100.0, closed[0].realized_pnl);
+        assert_eq!(-30.0, ledger.position("DU123456", 265598));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fill_ledger_lifo() {
+        let ledger = trade(LotMatching::Lifo);
+
+        let closed = ledger.closed_lots();
+        assert_eq!(
+            ("e2", 100.0),
+            (closed[0].open_exec_id.as_str(), closed[0].quantity)
+        );
+        assert_eq!(
+            ("e1", 50.0),
+            (closed[1].open_exec_id.as_str(), closed[1].quantity)
+        );
+        assert_eq!(200.0, ledger.fill("e3").unwrap().realized_pnl);
+        assert_eq!("e1", ledger.open_lots("DU123456", 265598)[0].exec_id);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fill_ledger_fractional_quantities_close_flat() {
+        let mut ledger = FillLedger::new(LotMatching::Fifo);
+        ledger.observe(&execution("e1", "20200406  09:31:00", "BOT", 0.3, 10.0));
+        ledger.observe(&execution("e2", "20200406  09:32:00", "SLD", 0.1, 11.0));
+        let closed = ledger.observe(&execution("e3", "20200406  09:33:00", "SLD", 0.2, 12.0));
+
+        assert_eq!(1, closed.len());
+        assert!(ledger.open_lots("DU123456", 265598).is_empty());
+        assert!(ledger.position("DU123456", 265598).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::client::EClient;
+    use crate::core::common::{CommissionReport, TickAttrib, TickType, UNSET_DOUBLE};
+    use crate::core::contract::Contract;
+    use crate::core::execution::Execution;
+    use crate::core::fill_report::{FillFailure, FillTracker};
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::order::{capped_price, HoldReason, Order, OrderStatusKind, WhyHeld};
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str, filled: f64, avg_fill_price: f64) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled,
+            remaining: 100.0 - filled,
+            avg_fill_price,
+            perm_id: 99,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn execution(order_id: i32, exec_id: &str, shares: f64, price: f64) -> ServerRspMsg {
+        let mut execution = Execution::default();
+        execution.order_id = order_id;
+        execution.exec_id = exec_id.to_string();
+        execution.shares = shares;
+        execution.price = price;
+        ServerRspMsg::ExecutionData {
+            req_id: -1,
+            contract: Contract::default(),
+            execution,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn commission(exec_id: &str, commission: f64) -> ServerRspMsg {
+        let mut commission_report = CommissionReport::default();
+        commission_report.exec_id = exec_id.to_string();
+        commission_report.commission = commission;
+        ServerRspMsg::CommissionReport { commission_report }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn sent_message_ids(client: &mut EClient) -> Vec<i32> {
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let mut ids = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf).unwrap();
+            ids.push(read_fields(&msg)[0].parse().unwrap());
+            buf = rest;
+        }
+        ids
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_buy_market_filled() {
+        let mut client = connected_client();
+        let events = vec![
+            ServerRspMsg::NextValidId { order_id: 7 },
+            order_status(7, "Submitted", 0.0, 0.0),
+            ServerRspMsg::TickPrice {
+                req_id: 1,
+                tick_type: TickType::Last,
+                price: 10.0,
+                tick_attr: TickAttrib::default(),
+            },
+            execution(7, "e1.01", 40.0, 10.0),
+            commission("e1.01", 0.4),
+            order_status(7, "Submitted", 40.0, 10.0),
+            execution(7, "e2.01", 60.0, 10.5),
+            order_status(7, "Filled", 100.0, 10.3),
+            // a correction of the second execution
+            execution(7, "e2.02", 60.0, 10.25),
+            commission("e2.02", 0.6),
+        ];
+        for event in events {
+            client.evt_chan.0.send(event).unwrap();
+        }
+
+        let report = client
+            .buy_market(&Contract::default(), 100.0, Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(7, report.order_id);
+        assert_eq!(99, report.perm_id);
+        assert_eq!("BUY", report.action);
+        assert_eq!(100.0, report.filled);
+        assert_eq!(10.3, report.avg_fill_price);
+        assert_eq!(2, report.executions.len());
+        assert_eq!(10.25, report.executions[1].price);
+        assert!((report.total_commission() - 1.0).abs() < 1e-9);
+        assert!(report.is_complete());
+
+        // events of other requests stay available
+        assert!(matches!(
+            client.get_event().unwrap(),
+            Some(ServerRspMsg::TickPrice { .. })
+        ));
+        assert_eq!(
+            vec![
+                ServerReqMsgDiscriminants::ReqIds as i32,
+                ServerReqMsgDiscriminants::PlaceOrder as i32
+            ],
+            sent_message_ids(&mut client)
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sell_market_rejected() {
+        let mut client = connected_client();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 3 })
+            .unwrap();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id: 3,
+                error_code: 201,
+                error_str: "Order rejected - reason: no trading permissions".to_string(),
+            })
+            .unwrap();
+
+        match client.sell_market(&Contract::default(), 10.0, Duration::from_millis(200)) {
+            Err(FillFailure::Rejected {
+                order_id,
+                error_code,
+                ..
+            }) => {
+                assert_eq!(3, order_id);
+                assert_eq!(201, error_code);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_market_order_timeout() {
+        let mut client = connected_client();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 5 })
+            .unwrap();
+        client
+            .evt_chan
+            .0
+            .send(order_status(5, "Submitted", 20.0, 10.0))
+            .unwrap();
+
+        match client.buy_market(&Contract::default(), 100.0, Duration::from_millis(50)) {
+            Err(FillFailure::Timeout(report)) => assert_eq!(20.0, report.filled),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            Some(&(ServerReqMsgDiscriminants::CancelOrder as i32)),
+            sent_message_ids(&mut client).last()
+        );
+
+        // no order id at all
+        match client.buy_market(&Contract::default(), 100.0, Duration::from_millis(20)) {
+            Err(FillFailure::Request(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_held_and_capped_order() {
+        let mut tracker = FillTracker::new(7, &Contract::default(), &Order::default());
+        let mut held = order_status(7, "PreSubmitted", 0.0, 0.0);
+        if let ServerRspMsg::OrderStatus { why_held, .. } = &mut held {
+            *why_held = WhyHeld::from("child,locate");
+        }
+        assert!(tracker.observe(&held));
+        let report = tracker.report();
+        assert!(report.why_held.is_held());
+        assert!(report.why_held.is_locating());
+        assert_eq!(
+            vec![HoldReason::Child, HoldReason::Locate],
+            report.why_held.reasons()
+        );
+        assert!(!report.is_capped());
+
+        let mut capped = order_status(7, "Submitted", 0.0, 0.0);
+        if let ServerRspMsg::OrderStatus { mkt_cap_price, .. } = &mut capped {
+            *mkt_cap_price = 101.25;
+        }
+        tracker.observe(&capped);
+        let report = tracker.report();
+        assert!(!report.why_held.is_held());
+        assert!(report.why_held.reasons().is_empty());
+        assert_eq!(Some(101.25), report.mkt_cap_price);
+        assert!(report.is_capped());
+
+        assert_eq!(
+            HoldReason::Other("other".to_string()),
+            HoldReason::from("other")
+        );
+        assert_eq!("trigger", HoldReason::Trigger.to_string());
+        assert_eq!(None, capped_price(UNSET_DOUBLE));
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::frame_dump::{
+        decode_dump, decode_frames, parse_dump, parse_dump_as, DumpEncoding, FrameDirection,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decode_dump() -> Result<(), IBKRApiLibError> {
+        let hex = parse_dump("00 00 00 07 39 00 31 00 34 32 00")?;
+        let base64 = parse_dump("AAAABzkAMQA0MgA=")?;
+        assert_eq!(hex, base64);
+
+        let frames = decode_frames(&hex, FrameDirection::Incoming)?;
+        assert_eq!(1, frames.len());
+        assert_eq!(Some(9), frames[0].msg_id);
+        assert_eq!("NextValidId", frames[0].name);
+        assert_eq!(vec!["9", "1", "42"], frames[0].fields);
+
+        let frames = decode_frames(&hex, FrameDirection::Outgoing)?;
+        assert_eq!("ReqContractData", frames[0].name);
+
+        assert!(decode_frames(&hex[..8], FrameDirection::Incoming).is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decode_base64_dump_of_hex_digits() -> Result<(), IBKRApiLibError> {
+        // Base64 of one TickPrice frame that happens to be made only of hex digits
+        let dump = "AAAACDEAADE1AAAA";
+        let expected = vec!["1", "", "15", "", ""];
+
+        let frames = decode_dump(dump, None, FrameDirection::Incoming)?;
+        assert_eq!(1, frames.len());
+        assert_eq!(expected, frames[0].fields);
+
+        let frames = decode_dump(dump, Some(DumpEncoding::Base64), FrameDirection::Incoming)?;
+        assert_eq!(expected, frames[0].fields);
+
+        assert_eq!(parse_dump(dump)?, parse_dump_as(dump, DumpEncoding::Hex)?);
+        assert!(decode_dump(dump, Some(DumpEncoding::Hex), FrameDirection::Incoming).is_err());
+        assert!(parse_dump_as("AAAABzkAMQA0MgA=", DumpEncoding::Hex).is_err());
+        Ok(())
+    }
+}
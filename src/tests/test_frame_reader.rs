@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::frame_reader::{FrameReader, READ_CHUNK};
+    use crate::core::messages::make_message;
+
+    //------------------------------------------------------------------------------------------------
+    /// A source that returns at most `chunk` bytes per read, like a slow socket
+    struct Trickle {
+        bytes: Vec<u8>,
+        position: usize,
+        chunk: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self
+                .chunk
+                .min(buf.len())
+                .min(self.bytes.len() - self.position);
+            buf[..len].copy_from_slice(&self.bytes[self.position..self.position + len]);
+            self.position += len;
+            Ok(len)
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn ticks(count: usize) -> Result<Vec<u8>, IBKRApiLibError> {
+        let mut bytes = Vec::new();
+        for i in 0..count {
+            bytes.extend(make_message(&format!("1\u{0}6\u{0}{}\u{0}1\u{0}", i))?);
+        }
+        Ok(bytes)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_partial_frames() -> Result<(), IBKRApiLibError> {
+        let bytes = [
+            make_message("49\u{0}1\u{0}")?,
+            make_message("4\u{0}2\u{0}")?,
+        ]
+        .concat();
+        let mut frames = FrameReader::new();
+        let mut received = Vec::new();
+        for byte in bytes.chunks(1) {
+            frames.extend(byte);
+            while let Some(frame) = frames.next_frame()? {
+                received.push(frame.to_vec());
+            }
+        }
+        assert_eq!(
+            vec![b"49\x001\x00".to_vec(), b"4\x002\x00".to_vec()],
+            received
+        );
+        assert_eq!(0, frames.buffered());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bursts() -> Result<(), IBKRApiLibError> {
+        let count = 5000;
+        // frames end at every possible offset of a read
+        let mut source = Trickle {
+            bytes: ticks(count)?,
+            position: 0,
+            chunk: READ_CHUNK - 7,
+        };
+        let mut frames = FrameReader::new();
+        let mut received = 0;
+        while frames.read_from(&mut source)? > 0 {
+            while let Some(frame) = frames.next_frame()? {
+                assert!(frame.starts_with(b"1\x006\x00"));
+                received += 1;
+            }
+        }
+        assert_eq!(count, received);
+        assert_eq!(0, frames.buffered());
+        // only the partial frame is ever moved, so the buffer never had to grow
+        assert_eq!(READ_CHUNK, frames.capacity());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_large_frame() -> Result<(), IBKRApiLibError> {
+        let text = "x".repeat(3 * READ_CHUNK);
+        let mut source = Trickle {
+            bytes: [make_message(&text)?, make_message("1\u{0}")?].concat(),
+            position: 0,
+            chunk: 1000,
+        };
+        let mut frames = FrameReader::new();
+        let mut received = Vec::new();
+        while frames.read_from(&mut source)? > 0 {
+            while let Some(frame) = frames.next_frame()? {
+                received.push(frame.len());
+            }
+        }
+        assert_eq!(vec![text.len(), 2], received);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_frame_limit() -> Result<(), IBKRApiLibError> {
+        let mut frames = FrameReader::new().max_frame(16);
+        frames.extend(&make_message(&"x".repeat(17))?);
+        assert!(matches!(
+            frames.next_frame(),
+            Err(IBKRApiLibError::Io(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+
+        // a negative size is never a valid frame
+        let mut frames = FrameReader::new();
+        frames.extend(&(-1i32).to_be_bytes());
+        assert!(frames.next_frame().is_err());
+        Ok(())
+    }
+}
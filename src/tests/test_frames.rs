@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+    use polars::prelude::{DataFrame, DataType, PolarsError, TimeUnit};
+
+    use crate::core::common::BarData;
+    #[cfg(feature = "marketdata")]
+    use crate::core::common::{HistoricalTickBidAsk, TickAttribBidAsk};
+    use crate::core::contract::Contract;
+    use crate::core::frames::{AccountSummary, BarSeries, PositionSnapshot};
+    #[cfg(feature = "marketdata")]
+    use crate::core::historical::TickSeries;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: &str, close: f64) -> BarData {
+        BarData::new(
+            parse_tws_time(date, Tz::UTC).unwrap(),
+            100.0,
+            101.5,
+            99.25,
+            close,
+            12000,
+            40,
+            100.4,
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn names(frame: &DataFrame) -> Vec<String> {
+        frame
+            .get_column_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn micros(frame: &DataFrame, name: &str) -> Result<Vec<Option<i64>>, PolarsError> {
+        Ok(frame
+            .column(name)?
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .collect())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bar_frame() -> Result<(), PolarsError> {
+        let frame = DataFrame::from(BarSeries::from(vec![
+            bar("20240105  14:30:00", 100.5),
+            bar("20240105 14:35:00 US/Eastern", 101.0),
+            bar("1704465000", 101.0),
+        ]));
+        assert_eq!(
+            vec![
+                "time",
+                "open",
+                "high",
+                "low",
+                "close",
+                "volume",
+                "bar_count",
+                "average"
+            ],
+            names(&frame)
+        );
+        assert_eq!(
+            &DataType::Datetime(TimeUnit::Microseconds, Some("UTC".into())),
+            frame.column("time")?.dtype()
+        );
+        assert_eq!(
+            vec![
+                Some(1_704_465_000_000_000),
+                Some(1_704_483_300_000_000),
+                Some(1_704_465_000_000_000)
+            ],
+            micros(&frame, "time")?
+        );
+        assert_eq!(Some(101.0), frame.column("close")?.f64()?.get(1));
+        assert_eq!(Some(12000), frame.column("volume")?.i64()?.get(0));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "marketdata")]
+    #[test]
+    fn test_tick_frame() -> Result<(), PolarsError> {
+        let ticks = TickSeries::BidAsk(vec![HistoricalTickBidAsk::new(
+            1704465000,
+            TickAttribBidAsk::new(true, false),
+            99.5,
+            99.75,
+            5,
+            7,
+        )]);
+        let frame = DataFrame::from(ticks);
+        assert_eq!(
+            vec![
+                "time",
+                "price_bid",
+                "price_ask",
+                "size_bid",
+                "size_ask",
+                "bid_past_low",
+                "ask_past_high"
+            ],
+            names(&frame)
+        );
+        assert_eq!(vec![Some(1_704_465_000_000_000)], micros(&frame, "time")?);
+        assert_eq!(Some(99.75), frame.column("price_ask")?.f64()?.get(0));
+        assert_eq!(Some(7), frame.column("size_ask")?.i64()?.get(0));
+        assert_eq!(Some(true), frame.column("bid_past_low")?.bool()?.get(0));
+
+        let empty = DataFrame::from(TickSeries::Midpoint(vec![]));
+        assert_eq!((0, 2), empty.shape());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_position_frame() -> Result<(), PolarsError> {
+        let contract = |con_id: i32, symbol: &str| Contract {
+            con_id,
+            symbol: symbol.to_string(),
+            sec_type: "STK".to_string(),
+            currency: "USD".to_string(),
+            ..Default::default()
+        };
+        let position = |con_id: i32, symbol: &str, position: f64| ServerRspMsg::PositionData {
+            account: "DU123".to_string(),
+            contract: contract(con_id, symbol),
+            position,
+            avg_cost: 150.0,
+        };
+
+        let mut snapshot = PositionSnapshot::new();
+        assert!(snapshot.observe(&position(265598, "AAPL", 100.0)));
+        assert!(snapshot.observe(&position(272093, "MSFT", 20.0)));
+        assert!(snapshot.observe(&position(265598, "AAPL", 150.0)));
+        assert!(!snapshot.observe(&ServerRspMsg::NextValidId { order_id: 1 }));
+        assert!(!snapshot.is_complete());
+        assert!(snapshot.observe(&ServerRspMsg::PositionEnd));
+        assert!(snapshot.is_complete());
+
+        let frame = DataFrame::from(snapshot);
+        assert_eq!((2, 13), frame.shape());
+        assert_eq!(Some("AAPL"), frame.column("symbol")?.str()?.get(0));
+        assert_eq!(Some(150.0), frame.column("position")?.f64()?.get(0));
+        assert_eq!(Some(272093), frame.column("con_id")?.i64()?.get(1));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_summary_frame() -> Result<(), PolarsError> {
+        let value = |tag: &str, value: &str, currency: &str| ServerRspMsg::AccountSummary {
+            req_id: 9,
+            account: "DU123".to_string(),
+            tag: tag.to_string(),
+            value: value.to_string(),
+            currency: currency.to_string(),
+        };
+
+        let mut summary = AccountSummary::new();
+        summary.observe(&value("NetLiquidation", "100000.50", "USD"));
+        summary.observe(&value("AccountType", "INDIVIDUAL", ""));
+        summary.observe(&value("NetLiquidation", "100250.75", "USD"));
+        summary.observe(&ServerRspMsg::AccountSummaryEnd { req_id: 9 });
+        assert!(summary.is_complete());
+
+        let frame = DataFrame::from(summary);
+        assert_eq!(
+            vec!["account", "tag", "currency", "value", "amount"],
+            names(&frame)
+        );
+        let amounts: Vec<Option<f64>> = frame.column("amount")?.f64()?.into_iter().collect();
+        assert_eq!(vec![Some(100250.75), None], amounts);
+        assert_eq!(Some("INDIVIDUAL"), frame.column("value")?.str()?.get(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::fundamentals::{
+        parse_report, FundamentalReport, FundamentalReportType, StatementType,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_snapshot() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ReportSnapshot Major="1" Minor="0" Revision="1">
+  <CoIDs><CoID Type="RepNo">05680</CoID><CoID Type="CompanyName">Apple Inc.</CoID></CoIDs>
+  <Issues>
+    <Issue ID="1" Type="C" Desc="Common Stock" Order="1">
+      <IssueID Type="Ticker">AAPL</IssueID>
+      <Exchange Code="NASD" Country="USA">NASDAQ</Exchange>
+    </Issue>
+  </Issues>
+  <CoGeneralInfo>
+    <Employees LastUpdated="2019-09-28">137000</Employees>
+    <SharesOut Date="2020-01-17" TotalFloat="4400000000.0">4443265000.0</SharesOut>
+    <ReportingCurrency Code="USD">U.S. Dollars</ReportingCurrency>
+  </CoGeneralInfo>
+  <TextInfo><Text Type="Business Summary">Designs smartphones.</Text></TextInfo>
+  <peerInfo><IndustryInfo>
+    <Industry type="TRBC" order="1" reported="0" code="5710601012">Phones</Industry>
+  </IndustryInfo></peerInfo>
+  <Ratios PriceCurrency="USD" ReportingCurrency="USD">
+    <Group ID="Price and Volume"><Ratio FieldName="NPRICE" Type="N">317.7</Ratio></Group>
+    <Group ID="Income Statement"><Ratio FieldName="TTMREV" Type="N">267683.0</Ratio></Group>
+  </Ratios>
+  <ForecastData ConsensusType="Mean">
+    <Ratio FieldName="ConsRecom" Type="N"><Value PeriodType="CURR">2.0</Value></Ratio>
+  </ForecastData>
+</ReportSnapshot>"#;
+
+        let snapshot = match parse_report(FundamentalReportType::ReportSnapshot, xml).unwrap() {
+            FundamentalReport::Snapshot(snapshot) => snapshot,
+            other => panic!("unexpected report {:?}", other),
+        };
+        assert_eq!("Apple Inc.", snapshot.company_name);
+        assert_eq!("AAPL", snapshot.ticker);
+        assert_eq!("NASD", snapshot.exchange);
+        assert_eq!("USD", snapshot.reporting_currency);
+        assert_eq!(Some(137000), snapshot.employees);
+        assert_eq!("Designs smartphones.", snapshot.business_summary);
+        assert_eq!("TRBC", snapshot.industries[0].classification);
+        assert_eq!(2, snapshot.ratios.len());
+        assert_eq!(Some(317.7), snapshot.ratio("NPRICE").unwrap().number());
+        assert_eq!("Income Statement", snapshot.ratio("TTMREV").unwrap().group);
+        assert_eq!("2.0", snapshot.forecasts[0].value);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_fin_statements_and_summary() {
+        let xml = r#"<ReportFinancialStatements Major="1" Minor="0" Revision="1">
+  <CoIDs><CoID Type="CompanyName">Apple Inc.</CoID></CoIDs>
+  <FinancialStatements>
+    <COAMap>
+      <mapItem coaItem="SREV" statementType="INC" lineID="10" precision="1">Revenue</mapItem>
+      <mapItem coaItem="NINC" statementType="INC" lineID="20" precision="1">Net Income</mapItem>
+    </COAMap>
+    <AnnualPeriods>
+      <FiscalPeriod Type="Annual" EndDate="2019-09-28" FiscalYear="2019">
+        <Statement Type="INC">
+          <FPHeader>
+            <PeriodLength>12</PeriodLength>
+            <periodType Code="M">Months</periodType>
+            <StatementDate>2019-09-28</StatementDate>
+          </FPHeader>
+          <lineItem coaCode="SREV">260174.0</lineItem>
+          <lineItem coaCode="NINC">55256.0</lineItem>
+        </Statement>
+      </FiscalPeriod>
+    </AnnualPeriods>
+  </FinancialStatements>
+</ReportFinancialStatements>"#;
+
+        let statements =
+            match parse_report(FundamentalReportType::ReportsFinStatements, xml).unwrap() {
+                FundamentalReport::FinancialStatements(statements) => statements,
+                other => panic!("unexpected report {:?}", other),
+            };
+        assert!(statements.interim.is_empty());
+        let period = &statements.annual[0];
+        assert_eq!(Some(2019), period.fiscal_year);
+        let income = period.statement(&StatementType::Income).unwrap();
+        assert_eq!(Some(12), income.period_length);
+        assert_eq!("M", income.period_unit);
+        assert_eq!(Some(55256.0), income.value("NINC"));
+        assert_eq!("Revenue", income.rows[0].description);
+
+        let xml = r#"<FinancialSummary>
+  <EPSs currency="USD">
+    <EPS asofDate="2019-12-28" reportType="TTM" period="12M">12.59</EPS>
+  </EPSs>
+  <Dividends currency="USD">
+    <Dividend type="CD" exDate="2020-02-07" recordDate="2020-02-10" payDate="2020-02-13" declarationDate="2020-01-28">0.77</Dividend>
+  </Dividends>
+</FinancialSummary>"#;
+        let summary = match parse_report(FundamentalReportType::ReportsFinSummary, xml).unwrap() {
+            FundamentalReport::FinancialSummary(summary) => summary,
+            other => panic!("unexpected report {:?}", other),
+        };
+        assert_eq!("USD", summary.currency);
+        assert_eq!(12.59, summary.eps[0].value);
+        assert_eq!("2020-02-07", summary.dividends[0].ex_date);
+        assert!(summary.total_revenues.is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_estimates() {
+        let xml = r#"<REarnEstCons Version="1">
+  <ConsEstimates><FYEstimates>
+    <FYEstimate type="EPS" unit="U">
+      <FYPeriod periodType="A" fYear="2020" endMonth="9">
+        <ConsEstimate type="High"><ConsValue dateType="CURR">13.5</ConsValue></ConsEstimate>
+        <ConsEstimate type="Mean">
+          <ConsValue dateType="1WA">12.9</ConsValue>
+          <ConsValue dateType="CURR">13.0</ConsValue>
+        </ConsEstimate>
+        <ConsEstimate type="NumOfEst"><ConsValue dateType="CURR">38</ConsValue></ConsEstimate>
+      </FYPeriod>
+    </FYEstimate>
+  </FYEstimates></ConsEstimates>
+</REarnEstCons>"#;
+
+        let estimates = match parse_report("RESC".parse().unwrap(), xml).unwrap() {
+            FundamentalReport::Estimates(estimates) => estimates,
+            other => panic!("unexpected report {:?}", other),
+        };
+        let eps = &estimates.estimates[0];
+        assert_eq!("EPS", eps.measure);
+        assert_eq!(Some(2020), eps.fiscal_year);
+        assert_eq!(Some(13.5), eps.high);
+        assert_eq!(Some(13.0), eps.mean);
+        assert_eq!(None, eps.low);
+        assert_eq!(Some(38.0), eps.num_of_est);
+
+        assert!("Unknown".parse::<FundamentalReportType>().is_err());
+        assert!(parse_report(FundamentalReportType::ReportRatios, "<Ratios>").is_err());
+    }
+}
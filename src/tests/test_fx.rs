@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::fx::{convert, CurrencyPair};
+
+    //------------------------------------------------------------------------------------------------
+    fn pair(first: &str, second: &str) -> String {
+        let pair = CurrencyPair::of(first, second).unwrap();
+        format!("{}.{}", pair.base, pair.quote)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fx_pairs() {
+        assert_eq!("EUR.USD", pair("USD", "EUR"));
+        assert_eq!("EUR.GBP", pair("gbp", "eur"));
+        assert_eq!("GBP.USD", pair("GBP", "USD"));
+        assert_eq!("AUD.NZD", pair("NZD", "AUD"));
+        assert_eq!("USD.CAD", pair("CAD", "USD"));
+        assert_eq!("USD.JPY", pair("JPY", "USD"));
+        assert_eq!("CHF.JPY", pair("CHF", "JPY"));
+        assert_eq!("USD.SEK", pair("SEK", "USD"));
+        assert_eq!("SEK.JPY", pair("JPY", "SEK"));
+        assert!(CurrencyPair::of("SEK", "NOK").is_err());
+        assert!(CurrencyPair::of("USD", "usd").is_err());
+        assert!(CurrencyPair::of("US", "EUR").is_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fx_convert() {
+        let sell = convert(10_000.0, "EUR", "USD").unwrap();
+        assert_eq!(
+            ("EUR", "CASH", "IDEALPRO", "USD"),
+            (
+                sell.contract.symbol.as_str(),
+                sell.contract.sec_type.as_str(),
+                sell.contract.exchange.as_str(),
+                sell.contract.currency.as_str()
+            )
+        );
+        assert_eq!(
+            ("SELL", "MKT"),
+            (sell.order.action.as_str(), sell.order.order_type.as_str())
+        );
+        assert_eq!(10_000.0, sell.order.total_quantity);
+        assert!(!sell.order.has_cash_qty());
+
+        // buys the base with a cash quantity in the quote currency
+        let buy = convert(1_500_000.0, "JPY", "USD").unwrap().limit(151.25);
+        assert_eq!("USD.JPY", format!("{}.{}", buy.pair.base, buy.pair.quote));
+        assert_eq!(
+            ("BUY", "LMT"),
+            (buy.order.action.as_str(), buy.order.order_type.as_str())
+        );
+        assert_eq!(1_500_000.0, buy.order.cash_qty);
+        assert_eq!(0.0, buy.order.total_quantity);
+        assert_eq!(151.25, buy.order.lmt_price);
+        assert_eq!(UNSET_DOUBLE, sell.order.lmt_price);
+
+        assert!(convert(0.0, "EUR", "USD").is_err());
+        assert!(convert(f64::NAN, "EUR", "USD").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::{RtVolumeTick, TickAttrib, TickType};
+    use crate::core::connection::{FarmKind, FarmStatus};
+    use crate::core::handlers::{
+        dispatch, AccountHandler, ConnectionHandler, HandlerSet, Handlers, MarketDataHandler,
+        OrderHandler,
+    };
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{OrderStatusKind, WhyHeld};
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Trader {
+        next_order_id: i32,
+        statuses: Vec<(i32, String)>,
+    }
+
+    impl ConnectionHandler for Trader {
+        fn next_valid_id(&mut self, order_id: i32) {
+            self.next_order_id = order_id;
+        }
+    }
+
+    impl OrderHandler for Trader {
+        fn order_status(
+            &mut self,
+            order_id: i32,
+            status: &OrderStatusKind,
+            _: f64,
+            _: f64,
+            _: f64,
+            _: i64,
+            _: i32,
+            _: f64,
+            _: i32,
+            _: &WhyHeld,
+            _: f64,
+        ) {
+            self.statuses.push((order_id, status.to_string()));
+        }
+    }
+
+    impl Handlers for Trader {
+        fn connection(&mut self) -> Option<&mut dyn ConnectionHandler> {
+            Some(self)
+        }
+        fn orders(&mut self) -> Option<&mut dyn OrderHandler> {
+            Some(self)
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Prices(Vec<(i32, f64)>);
+
+    impl MarketDataHandler for Prices {
+        fn tick_price(&mut self, req_id: i32, _: TickType, price: f64, _: &TickAttrib) {
+            self.0.push((req_id, price));
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Volumes {
+        strings: usize,
+        ticks: Vec<(i32, i32, RtVolumeTick)>,
+    }
+
+    impl MarketDataHandler for Volumes {
+        fn tick_string(&mut self, _: i32, _: TickType, _: &str) {
+            self.strings += 1;
+        }
+        fn rt_volume(&mut self, req_id: i32, tick_type: TickType, tick: &RtVolumeTick) {
+            self.ticks.push((req_id, tick_type as i32, tick.clone()));
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Monitor {
+        errors: Vec<i32>,
+        restored: Vec<bool>,
+        farms: Vec<(FarmKind, String, FarmStatus)>,
+    }
+
+    impl ConnectionHandler for Monitor {
+        fn error(&mut self, _: i32, error_code: i32, _: &str) {
+            self.errors.push(error_code);
+        }
+        fn connectivity_restored(&mut self, data_maintained: bool) {
+            self.restored.push(data_maintained);
+        }
+        fn farm_status(&mut self, kind: FarmKind, name: &str, status: FarmStatus) {
+            self.farms.push((kind, name.to_string(), status));
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    struct Positions;
+
+    impl AccountHandler for Positions {}
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled: 0.0,
+            remaining: 100.0,
+            avg_fill_price: 0.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_dispatch_to_one_object() {
+        let mut trader = Trader::default();
+        assert!(dispatch(
+            &ServerRspMsg::NextValidId { order_id: 7 },
+            &mut trader
+        ));
+        assert!(dispatch(&order_status(7, "Submitted"), &mut trader));
+        assert!(!dispatch(&ServerRspMsg::PositionEnd, &mut trader));
+
+        assert_eq!(7, trader.next_order_id);
+        assert_eq!(vec![(7, "Submitted".to_string())], trader.statuses);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_dispatch_to_handler_set() {
+        let mut trader = Trader::default();
+        let mut prices = Prices::default();
+        let mut positions = Positions;
+        let tick = ServerRspMsg::TickPrice {
+            req_id: 3,
+            tick_type: TickType::Bid,
+            price: 101.5,
+            tick_attr: TickAttrib::default(),
+        };
+        {
+            let mut handlers = HandlerSet::new()
+                .orders(&mut trader)
+                .market_data(&mut prices)
+                .account(&mut positions);
+            assert!(dispatch(&tick, &mut handlers));
+            assert!(dispatch(&order_status(8, "Filled"), &mut handlers));
+            // the default callbacks count as delivered
+            assert!(dispatch(&ServerRspMsg::PositionEnd, &mut handlers));
+            // no connection handler was registered
+            assert!(!dispatch(
+                &ServerRspMsg::NextValidId { order_id: 9 },
+                &mut handlers
+            ));
+        }
+
+        assert_eq!(vec![(3, 101.5)], prices.0);
+        assert_eq!(vec![(8, "Filled".to_string())], trader.statuses);
+        assert_eq!(0, trader.next_order_id);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_connectivity_notices() {
+        let err_msg = |error_code, error_str: &str| ServerRspMsg::ErrMsg {
+            req_id: -1,
+            error_code,
+            error_str: error_str.to_string(),
+        };
+        let mut monitor = Monitor::default();
+        {
+            let mut handlers = HandlerSet::new().connection(&mut monitor);
+            for msg in &[
+                err_msg(
+                    1101,
+                    "Connectivity between IB and TWS has been restored - data lost.",
+                ),
+                err_msg(2104, "Market data farm connection is OK:usfarm.nj"),
+                err_msg(2105, "HMDS data farm connection is broken:ushmds"),
+                err_msg(502, "Couldn't connect to TWS."),
+            ] {
+                assert!(dispatch(msg, &mut handlers));
+            }
+        }
+
+        assert_eq!(vec![502], monitor.errors);
+        assert_eq!(vec![false], monitor.restored);
+        assert_eq!(
+            vec![
+                (
+                    FarmKind::MarketData,
+                    "usfarm.nj".to_string(),
+                    FarmStatus::Ok
+                ),
+                (
+                    FarmKind::Historical,
+                    "ushmds".to_string(),
+                    FarmStatus::Broken
+                ),
+            ],
+            monitor.farms
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_rt_volume_ticks() {
+        let tick_string = |tick_type, value: &str| ServerRspMsg::TickString {
+            req_id: 4,
+            tick_type,
+            value: value.to_string(),
+        };
+        let mut volumes = Volumes::default();
+        let mut handlers = HandlerSet::new().market_data(&mut volumes);
+        for msg in &[
+            tick_string(
+                TickType::RtVolume,
+                "101.25;300;1704465000123;48211;101.1342;true",
+            ),
+            tick_string(
+                TickType::RtTrdVolume,
+                ";;1704465001000;48211;101.1342;false",
+            ),
+            tick_string(TickType::RtVolume, "not a volume tick"),
+            tick_string(TickType::LastTimestamp, "1704465000"),
+        ] {
+            assert!(dispatch(msg, &mut handlers));
+        }
+
+        // every tick string still reaches tick_string()
+        assert_eq!(4, volumes.strings);
+        assert_eq!(
+            vec![
+                (
+                    4,
+                    TickType::RtVolume as i32,
+                    RtVolumeTick {
+                        price: Some(101.25),
+                        size: Some(300),
+                        time: 1704465000123,
+                        volume: 48211,
+                        vwap: 101.1342,
+                        single_trade: true,
+                    }
+                ),
+                (
+                    4,
+                    TickType::RtTrdVolume as i32,
+                    RtVolumeTick {
+                        price: None,
+                        size: None,
+                        time: 1704465001000,
+                        volume: 48211,
+                        vwap: 101.1342,
+                        single_trade: false,
+                    }
+                ),
+            ],
+            volumes.ticks
+        );
+        assert_eq!(
+            "2024-01-05 14:30:00.123 UTC",
+            volumes.ticks[0].2.datetime().unwrap().to_string()
+        );
+        assert!("101.25;300;1704465000123".parse::<RtVolumeTick>().is_err());
+    }
+}
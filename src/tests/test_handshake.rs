@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::client::EClient;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::handshake::{self, Feature, ServerHello, ServerVersion};
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants};
+    use crate::core::server_versions::{
+        MAX_CLIENT_VER, MIN_SERVER_VER_OPTIONAL_CAPABILITIES, MIN_SERVER_VER_PNL,
+        MIN_SERVER_VER_TICK_BY_TICK,
+    };
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_hello() -> Result<(), IBKRApiLibError> {
+        let hello = handshake::hello()?;
+        assert_eq!(b"API\0", &hello[..4]);
+        let (_, range, rest) = read_msg(&hello[4..])?;
+        assert_eq!(format!("v100..{}", MAX_CLIENT_VER), range);
+        assert!(rest.is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_server_hello() -> Result<(), IBKRApiLibError> {
+        let fields = vec!["151".to_string(), "20231020 09:30:00 EST".to_string()];
+        let hello = ServerHello::parse(&fields).unwrap()?;
+        assert_eq!(ServerVersion(151), hello.server_version);
+        assert_eq!("20231020 09:30:00 EST", hello.connection_time);
+
+        // other messages can arrive before the answer
+        assert!(
+            ServerHello::parse(&["4".to_string(), "2".to_string(), "-1".to_string()]).is_none()
+        );
+        assert!(ServerHello::parse(&[]).is_none());
+        let garbled = vec!["v151".to_string(), "".to_string()];
+        assert!(matches!(
+            ServerHello::parse(&garbled),
+            Some(Err(IBKRApiLibError::ParseInt(_)))
+        ));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_start_api() -> Result<(), IBKRApiLibError> {
+        let msg = handshake::start_api(7, ServerVersion(151), "+PACEAPI")?;
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::StartApi as i32).to_string(),
+                "2".to_string(),
+                "7".to_string(),
+                "+PACEAPI".to_string(),
+            ],
+            read_fields(&msg)
+        );
+        // servers before optional capabilities get no capabilities field
+        let old = ServerVersion(MIN_SERVER_VER_OPTIONAL_CAPABILITIES - 1);
+        assert_eq!(
+            3,
+            read_fields(&handshake::start_api(7, old, "+PACEAPI")?).len()
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_supports() {
+        assert_eq!(
+            MIN_SERVER_VER_TICK_BY_TICK,
+            Feature::TickByTick.min_version()
+        );
+        let version = ServerVersion::from(MIN_SERVER_VER_TICK_BY_TICK);
+        assert!(version.supports(Feature::TickByTick));
+        assert!(version.supports(Feature::Pnl));
+        assert!(!version.supports(Feature::TickByTickIgnoreSize));
+        assert!(!ServerVersion::default().supports(Feature::PtaOrders));
+        assert!(ServerVersion(150) < ServerVersion(151));
+        assert_eq!("137", version.to_string());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            Some(Feature::TickByTick),
+            handshake::min_server_version(&ServerReqMsgDiscriminants::CancelTickByTickData)
+        );
+        assert_eq!(
+            None,
+            handshake::min_server_version(&ServerReqMsgDiscriminants::ReqMktData)
+        );
+        let version = ServerVersion(MIN_SERVER_VER_TICK_BY_TICK - 1);
+        assert!(version
+            .check(&ServerReqMsgDiscriminants::ReqMktData)
+            .is_ok());
+        assert!(matches!(
+            version.check(&ServerReqMsgDiscriminants::ReqTickByTickData),
+            Err(IBKRApiLibError::UpdateTws {
+                required: MIN_SERVER_VER_TICK_BY_TICK,
+                current: 136,
+                feature: Feature::TickByTick,
+            })
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_unsupported_request() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        client.server_version = MIN_SERVER_VER_PNL - 1;
+
+        let result = client.req_pnl(5, "DU12345", "");
+        assert!(matches!(
+            result,
+            Err(IBKRApiLibError::UpdateTws {
+                required: MIN_SERVER_VER_PNL,
+                feature: Feature::Pnl,
+                ..
+            })
+        ));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("needs server version"));
+        // nothing reaches TWS
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        assert!(buf.is_empty());
+
+        client.server_version = MIN_SERVER_VER_PNL;
+        client.req_pnl(5, "DU12345", "")?;
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        assert!(!buf.is_empty());
+        Ok(())
+    }
+}
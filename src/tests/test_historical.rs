@@ -0,0 +1,513 @@
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+    use std::time::{Duration, Instant};
+
+    use chrono::{DateTime, Utc};
+    use num_traits::FromPrimitive;
+
+    use crate::core::client::EClient;
+    use crate::core::common::{
+        BarSize, HistogramData, HistoricalDuration, HistoricalTick, HistoricalTickLast,
+        TickAttribLast,
+    };
+    use crate::core::contract::Contract;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::historical::{
+        cumulative_distribution, earliest_available, fetch_ticks_range, histogram_period,
+        price_at_quantile, Pacing, TickKind, TickPages, TickRange, TickSeries,
+    };
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::tests::{block_on, connected_client, CountingWaker};
+
+    //------------------------------------------------------------------------------------------------
+    fn start() -> DateTime<Utc> {
+        "2024-01-05T14:30:00Z".parse().unwrap()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trades(req_id: i32, offsets: &[i64], done: bool) -> ServerRspMsg {
+        let ticks = offsets
+            .iter()
+            .map(|offset| {
+                HistoricalTickLast::new(
+                    (start().timestamp() + offset) as i32,
+                    TickAttribLast::default(),
+                    100.0 + *offset as f64,
+                    10,
+                    "ISLAND".to_string(),
+                    "".to_string(),
+                )
+            })
+            .collect();
+        ServerRspMsg::HistoricalTicksLast {
+            req_id,
+            ticks,
+            done,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Start times of the historical tick requests sent
+    fn sent_start_times(client: &mut EClient) -> Vec<String> {
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let mut starts = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf).unwrap();
+            starts.push(read_fields(&msg)[15].clone());
+            buf = rest;
+        }
+        starts
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fetch_ticks_range() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let first = client.req_id_allocator().peek();
+        let events = vec![
+            trades(first, &[0, 1, 1], false),
+            ServerRspMsg::NextValidId { order_id: 4 },
+            trades(first, &[2, 2], true),
+            // the ticks at second 2 are sent again
+            trades(first + 1, &[2, 2, 3, 5], true),
+            trades(first + 2, &[5, 12], true),
+        ];
+        for event in events {
+            client.evt_chan.0.send(event).unwrap();
+        }
+
+        let pacing = Pacing {
+            min_interval: Duration::from_millis(20),
+            ..Pacing::none()
+        };
+        let range = TickRange::new(
+            &Contract::default(),
+            TickKind::Trades,
+            start(),
+            start() + chrono::Duration::seconds(10),
+        )
+        .page_timeout(Duration::from_millis(200))
+        .pacing(pacing)
+        .check_head_timestamp(false);
+        let started = Instant::now();
+        let series = fetch_ticks_range(&mut client, &range)?;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        let offsets: Vec<i64> = series
+            .times()
+            .iter()
+            .map(|time| time - start().timestamp())
+            .collect();
+        assert_eq!(vec![0, 1, 1, 2, 2, 3, 5], offsets);
+        match &series {
+            TickSeries::Trades(ticks) => assert_eq!(103.0, ticks[5].price),
+            other => panic!("unexpected {:?}", other.kind()),
+        }
+        assert_eq!(
+            vec![
+                "20240105-14:30:00",
+                "20240105-14:30:02",
+                "20240105-14:30:05"
+            ],
+            sent_start_times(&mut client)
+        );
+        assert!(client.correlations().active().is_empty());
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::NextValidId { .. })
+        ));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tick_pages_full_second() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let first = client.req_id_allocator().peek();
+        let time = start().timestamp() as i32;
+        let crowded: Vec<HistoricalTick> = (0..1000)
+            .map(|i| HistoricalTick::new(time, 50.0 + i as f64 / 100.0, 0))
+            .collect();
+        let events = vec![
+            (first, crowded.clone()),
+            (first + 1, crowded),
+            (first + 2, vec![HistoricalTick::new(time + 3, 51.0, 0)]),
+            (first + 3, vec![HistoricalTick::new(time + 3, 51.0, 0)]),
+        ];
+        for (req_id, ticks) in events {
+            client
+                .evt_chan
+                .0
+                .send(ServerRspMsg::HistoricalTicks {
+                    req_id,
+                    ticks,
+                    done: true,
+                })
+                .unwrap();
+        }
+
+        let range = TickRange::new(
+            &Contract::default(),
+            TickKind::Midpoint,
+            start(),
+            start() + chrono::Duration::minutes(1),
+        )
+        .page_timeout(Duration::from_millis(200))
+        .pacing(Pacing::none())
+        .check_head_timestamp(false);
+        let pages: Vec<usize> = TickPages::new(&mut client, range)
+            .map(|page| page.map(|page| page.len()))
+            .collect::<Result<_, _>>()?;
+        // the second page repeats the first, so the rest of that second is skipped
+        assert_eq!(vec![1000, 1], pages);
+        assert_eq!(
+            vec![
+                "20240105-14:30:00",
+                "20240105-14:30:00",
+                "20240105-14:30:01",
+                "20240105-14:30:03"
+            ],
+            sent_start_times(&mut client)
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fetch_ticks_range_error() {
+        let mut client = connected_client();
+        let first = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id: first,
+                error_code: 162,
+                error_str:
+                    "Historical Market Data Service error message:No market data permissions"
+                        .to_string(),
+            })
+            .unwrap();
+
+        let range = TickRange::new(
+            &Contract::default(),
+            TickKind::BidAsk,
+            start(),
+            start() + chrono::Duration::hours(1),
+        )
+        .page_timeout(Duration::from_millis(200))
+        .pacing(Pacing::none());
+        match fetch_ticks_range(&mut client, &range) {
+            Err(IBKRApiLibError::ApiError(err)) => assert_eq!("162", err.code),
+            other => panic!("unexpected {:?}", other.map(|series| series.len())),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_range_starts_at_head_timestamp() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let first = client.req_id_allocator().peek();
+        let head = start() + chrono::Duration::seconds(3);
+        let events = vec![
+            ServerRspMsg::HeadTimestamp {
+                req_id: first,
+                head_timestamp: Some(head),
+                #[cfg(feature = "raw-times")]
+                head_timestamp_raw: head.timestamp().to_string(),
+            },
+            trades(first + 1, &[3, 4, 6], true),
+            ServerRspMsg::HeadTimestamp {
+                req_id: first + 2,
+                head_timestamp: Some(head),
+                #[cfg(feature = "raw-times")]
+                head_timestamp_raw: head.timestamp().to_string(),
+            },
+            ServerRspMsg::ErrMsg {
+                req_id: first + 3,
+                error_code: 354,
+                error_str: "Requested market data is not subscribed".to_string(),
+            },
+        ];
+        for event in events {
+            client.evt_chan.0.send(event).unwrap();
+        }
+
+        let range = TickRange::new(
+            &Contract::default(),
+            TickKind::Trades,
+            start(),
+            start() + chrono::Duration::seconds(5),
+        )
+        .page_timeout(Duration::from_millis(200))
+        .pacing(Pacing::none());
+        assert_eq!(2, fetch_ticks_range(&mut client, &range)?.len());
+        assert_eq!(
+            head,
+            earliest_available(
+                &mut client,
+                &Contract::default(),
+                "TRADES",
+                Duration::from_millis(200)
+            )?
+        );
+        assert!(earliest_available(
+            &mut client,
+            &Contract::default(),
+            "TRADES",
+            Duration::from_millis(200)
+        )
+        .is_err());
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, head_request, rest) = read_msg(&buf)?;
+        // seconds since the epoch are asked for
+        assert_eq!("2", read_fields(&head_request)[17]);
+        let (_, ticks_request, _) = read_msg(&rest)?;
+        assert_eq!("20240105-14:30:03", read_fields(&ticks_request)[15]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_histogram() -> Result<(), IBKRApiLibError> {
+        assert_eq!("1 days", histogram_period(Duration::from_secs(60)));
+        assert_eq!(
+            "3 days",
+            histogram_period(Duration::from_secs(2 * 86400 + 1))
+        );
+        assert_eq!("2 weeks", histogram_period(Duration::from_secs(14 * 86400)));
+
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::HistogramData {
+                req_id,
+                items: vec![
+                    HistogramData::new(101.0, 2),
+                    HistogramData::new(99.0, 1),
+                    HistogramData::new(100.0, 5),
+                ],
+            })
+            .unwrap();
+        let histogram =
+            client.histogram(&Contract::default(), true, Duration::from_secs(7 * 86400))?;
+        assert_eq!(req_id, histogram.req_id());
+        let histogram = block_on(histogram)?;
+        let prices: Vec<f64> = histogram.iter().map(|item| item.price).collect();
+        assert_eq!(vec![99.0, 100.0, 101.0], prices);
+        assert_eq!(
+            vec![(99.0, 0.125), (100.0, 0.75), (101.0, 1.0)],
+            cumulative_distribution(&histogram)
+        );
+        assert_eq!(Some(100.0), price_at_quantile(&histogram, 0.5));
+        assert_eq!(None, price_at_quantile(&[], 0.5));
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, _) = read_msg(&buf)?;
+        let fields = read_fields(&request);
+        assert_eq!("1 weeks", fields[fields.len() - 1]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_histogram_waits_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let req_id = client.req_id_allocator().peek();
+        let period = Duration::from_secs(86400);
+        let mut histogram = Box::pin(client.histogram(&Contract::default(), false, period)?);
+        assert!(histogram.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(0, counter.count());
+        // an event for someone else wakes it, and it waits again
+        events
+            .send(ServerRspMsg::HistogramData {
+                req_id: req_id + 1,
+                items: vec![HistogramData::new(1.0, 1)],
+            })
+            .unwrap();
+        assert_eq!(1, counter.count());
+        assert!(histogram.as_mut().poll(&mut cx).is_pending());
+        events
+            .send(ServerRspMsg::ErrMsg {
+                req_id,
+                error_code: 200,
+                error_str: "No security definition has been found".to_string(),
+            })
+            .unwrap();
+        assert!(matches!(
+            histogram.as_mut().poll(&mut cx),
+            Poll::Ready(Err(IBKRApiLibError::ApiError(_)))
+        ));
+        drop(histogram);
+
+        // a histogram dropped before it arrives is cancelled
+        let req_id = client.req_id_allocator().peek();
+        drop(client.histogram(&Contract::default(), false, period)?);
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let mut requests = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            requests.push(read_fields(&msg));
+            buf = rest;
+        }
+        assert_eq!(3, requests.len());
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::CancelHistogramData as i32).to_string(),
+                req_id.to_string(),
+            ],
+            requests[2]
+        );
+        assert!(client
+            .correlations()
+            .context(req_id)
+            .unwrap()
+            .cancelled_at
+            .is_some());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bar_size_and_duration_strings() -> Result<(), IBKRApiLibError> {
+        for bar_size in BarSize::ALL.iter() {
+            assert_eq!(*bar_size, bar_size.to_string().parse()?);
+        }
+        assert_eq!("1 secs", BarSize::_1Secs.to_string());
+        assert_eq!("8 hours", BarSize::_8Hours.to_string());
+        assert_eq!(BarSize::_2Hours, " 2  hours".parse()?);
+        assert!("7 mins".parse::<BarSize>().is_err());
+        // the sizes added later keep the earlier discriminants, and all compare by length
+        assert_eq!(Some(BarSize::_4Hours), BarSize::from_i32(14));
+        assert_eq!(Some(BarSize::_2Hours), BarSize::from_i32(18));
+        assert!(BarSize::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!("2 D", HistoricalDuration::Days(2).to_string());
+        assert_eq!(HistoricalDuration::Years(1), "1 Y".parse()?);
+        assert_eq!(HistoricalDuration::Seconds(60), "60".parse()?);
+        assert!("1 X".parse::<HistoricalDuration>().is_err());
+        assert_eq!(
+            HistoricalDuration::Seconds(3600),
+            HistoricalDuration::covering(chrono::Duration::hours(1))
+        );
+        assert_eq!(
+            HistoricalDuration::Days(2),
+            HistoricalDuration::covering(chrono::Duration::hours(25))
+        );
+        assert_eq!(
+            HistoricalDuration::Years(2),
+            HistoricalDuration::covering(chrono::Duration::days(400))
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bar_sizes_for_duration() {
+        assert_eq!(
+            Some(BarSize::_1Secs..=BarSize::_1Min),
+            HistoricalDuration::Seconds(60).bar_sizes()
+        );
+        assert_eq!(
+            Some(BarSize::_1Min..=BarSize::_1Day),
+            HistoricalDuration::Days(1).bar_sizes()
+        );
+        assert_eq!(None, HistoricalDuration::Days(0).bar_sizes());
+        assert_eq!(None, HistoricalDuration::Seconds(90000).bar_sizes());
+
+        assert!(HistoricalDuration::Days(1).check(BarSize::_1Min).is_ok());
+        assert!(HistoricalDuration::Years(1).check(BarSize::_1Day).is_ok());
+        assert!(HistoricalDuration::Years(1).check(BarSize::_1Hour).is_err());
+        match HistoricalDuration::Days(1).check(BarSize::_1Secs) {
+            Err(IBKRApiLibError::InvalidBarSize { duration, bar_size }) => {
+                assert_eq!(HistoricalDuration::Days(1), duration);
+                assert_eq!(BarSize::_1Secs, bar_size);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_req_historical_data_checks_bar_size() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let refused = client.req_historical_data(
+            1,
+            &Contract::default(),
+            "",
+            HistoricalDuration::Days(1),
+            BarSize::_1Secs,
+            "TRADES",
+            1,
+            1,
+            false,
+            vec![],
+        );
+        assert!(matches!(
+            refused,
+            Err(IBKRApiLibError::InvalidBarSize { .. })
+        ));
+        client.req_historical_data(
+            2,
+            &Contract::default(),
+            "",
+            HistoricalDuration::Days(2),
+            BarSize::_5Mins,
+            "TRADES",
+            1,
+            1,
+            false,
+            vec![],
+        )?;
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        // only the valid request was sent
+        let (_, request, rest) = read_msg(&buf)?;
+        assert!(rest.is_empty());
+        let fields = read_fields(&request);
+        assert!(fields.contains(&"5 mins".to_string()));
+        assert!(fields.contains(&"2 D".to_string()));
+        Ok(())
+    }
+}
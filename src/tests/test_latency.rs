@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use crate::core::client::{ConnStatus, EClient, POISONED_MUTEX};
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::latency::{LatencyObserver, LatencyStamps};
+    use crate::core::messages::{make_message, ServerRspMsg};
+    use crate::core::reader::Reader;
+    use crate::core::streamer::TestStreamer;
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct TestObserver(Mutex<Vec<(ServerRspMsg, LatencyStamps)>>);
+
+    impl LatencyObserver for TestObserver {
+        fn observe(&self, msg: &ServerRspMsg, stamps: &LatencyStamps) {
+            self.0.lock().unwrap().push((msg.clone(), *stamps));
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_latency_stamps() -> Result<(), IBKRApiLibError> {
+        let observer = Arc::new(TestObserver::default());
+        let mut client = EClient::new();
+        *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
+        client.set_latency_observer(Some(observer.clone()));
+
+        let (tx, rx) = channel::<String>();
+        let (read_times, read_times_rx) = channel::<Instant>();
+        let mut decoder = Decoder::new(
+            rx,
+            client.evt_chan.0.clone(),
+            151,
+            client.conn_state.clone(),
+        );
+        decoder.read_times = Some(read_times_rx);
+
+        let read = Instant::now();
+        read_times.send(read).unwrap();
+        tx.send("49\x001\x001704465000\x00".to_string()).unwrap();
+        drop(tx);
+        decoder.run()?;
+        // events sent without stamps are not observed
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::NextValidId { order_id: 4 })
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::CurrentTime { time: 1704465000 })
+        ));
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::NextValidId { .. })
+        ));
+
+        let observed = observer.0.lock().unwrap();
+        assert_eq!(1, observed.len());
+        let (msg, stamps) = &observed[0];
+        assert!(matches!(msg, ServerRspMsg::CurrentTime { .. }));
+        assert_eq!(read, stamps.read);
+        assert!(stamps.decoded >= stamps.read);
+        assert!(stamps.queue_time() >= Duration::from_millis(5));
+        assert_eq!(stamps.decode_time() + stamps.queue_time(), stamps.total());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_reader_without_latency_receiver() -> Result<(), IBKRApiLibError> {
+        let mut streamer = TestStreamer::new();
+        for time in 0..3 {
+            streamer.write_all(&make_message(&format!("49\u{0}1\u{0}{}\u{0}", time))?)?;
+        }
+        let (tx, rx) = channel();
+        let (read_times, read_times_rx) = channel::<Instant>();
+        drop(read_times_rx);
+        let mut reader = Reader::new(Box::new(streamer), tx, Arc::new(AtomicBool::new(false)));
+        reader.read_times = Some(read_times);
+        reader.run();
+        drop(reader);
+        assert_eq!(3, rx.iter().count());
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::contract::Contract;
+    use crate::core::live_orders::{OrderFilter, PendingCancels, TrackedOrder};
+    use crate::core::messages::{ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::order::{Order, OrderState, OrderStatusKind, WhyHeld};
+    use crate::tests::{connected_client, sent_requests};
+
+    //------------------------------------------------------------------------------------------------
+    fn open_order(order_id: i32, symbol: &str, action: &str, status: &str) -> ServerRspMsg {
+        let contract = Contract {
+            symbol: symbol.to_string(),
+            ..Default::default()
+        };
+        let order = Order {
+            action: action.to_string(),
+            account: "DU123".to_string(),
+            perm_id: 100 + i64::from(order_id),
+            ..Default::default()
+        };
+        let order_state = OrderState {
+            status: status.to_string(),
+            ..Default::default()
+        };
+        ServerRspMsg::OpenOrder {
+            order_id,
+            contract,
+            order,
+            order_state,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled: 0.0,
+            remaining: 100.0,
+            avg_fill_price: 0.0,
+            perm_id: 100 + i64::from(order_id),
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_filter() {
+        let contract = Contract {
+            symbol: "AAPL".to_string(),
+            ..Default::default()
+        };
+        let order = Order {
+            action: "SSHORT".to_string(),
+            account: "DU123".to_string(),
+            ..Default::default()
+        };
+        assert!(OrderFilter::all().matches(&contract, &order));
+        assert!(OrderFilter::all()
+            .account("DU123")
+            .symbol("aapl")
+            .side("SELL")
+            .matches(&contract, &order));
+        assert!(!OrderFilter::all().side("BUY").matches(&contract, &order));
+        assert!(!OrderFilter::all().symbol("MSFT").matches(&contract, &order));
+        assert!(!OrderFilter::all()
+            .account("DU456")
+            .matches(&contract, &order));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_cancel_all() {
+        let mut client = connected_client();
+        for msg in [
+            open_order(1, "AAPL", "BUY", "Submitted"),
+            open_order(2, "AAPL", "SELL", "Submitted"),
+            open_order(3, "MSFT", "BUY", "PreSubmitted"),
+            open_order(4, "AAPL", "BUY", "Filled"),
+            open_order(5, "AAPL", "BUY", "PreSubmitted"),
+            order_status(5, "Submitted"),
+            ServerRspMsg::OpenOrderEnd,
+            order_status(1, "PendingCancel"),
+            order_status(1, "Cancelled"),
+            order_status(5, "Filled"),
+        ] {
+            client.evt_chan.0.send(msg).unwrap();
+        }
+
+        let filter = OrderFilter::all().symbol("AAPL").side("BUY");
+        let report = client
+            .cancel_all(&filter, Duration::from_millis(100))
+            .unwrap();
+        assert!(report.is_complete());
+        assert_eq!(
+            vec![1],
+            report
+                .cancelled
+                .iter()
+                .map(|tracked| tracked.order_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, report.completed.len());
+        assert_eq!(OrderStatusKind::Filled, report.completed[0].status);
+
+        let sent = sent_requests(&mut client);
+        assert_eq!(
+            (ServerReqMsgDiscriminants::ReqOpenOrders as i32).to_string(),
+            sent[0][0]
+        );
+        let cancels: Vec<_> = sent[1..]
+            .iter()
+            .map(|fields| {
+                assert_eq!(
+                    (ServerReqMsgDiscriminants::CancelOrder as i32).to_string(),
+                    fields[0]
+                );
+                fields[2].clone()
+            })
+            .collect();
+        assert_eq!(vec!["1", "5"], cancels);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_global_cancel() {
+        let mut client = connected_client();
+        for msg in [
+            open_order(0, "ES", "SELL", "Submitted"),
+            open_order(7, "AAPL", "BUY", "Submitted"),
+            ServerRspMsg::OpenOrderEnd,
+            order_status(7, "ApiCancelled"),
+        ] {
+            client.evt_chan.0.send(msg).unwrap();
+        }
+
+        let report = client.global_cancel(Duration::from_millis(50)).unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(7, report.cancelled[0].order_id);
+        assert_eq!(100, report.working[0].perm_id);
+
+        let ids: Vec<_> = sent_requests(&mut client)
+            .iter()
+            .map(|fields| fields[0].clone())
+            .collect();
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::ReqAllOpenOrders as i32).to_string(),
+                (ServerReqMsgDiscriminants::ReqGlobalCancel as i32).to_string()
+            ],
+            ids
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_refused_cancel() {
+        let tracked = TrackedOrder {
+            order_id: 9,
+            perm_id: 109,
+            status: OrderStatusKind::Submitted,
+            ..Default::default()
+        };
+        let mut pending = PendingCancels::new([&tracked]);
+        let notice = ServerRspMsg::ErrMsg {
+            req_id: 9,
+            error_code: 202,
+            error_str: "Order Canceled - reason:".to_string(),
+        };
+        assert!(pending.observe(&notice));
+        assert!(!pending.is_done());
+        let refused = ServerRspMsg::ErrMsg {
+            req_id: 9,
+            error_code: 10147,
+            error_str: "OrderId 9 that needs to be cancelled is not found.".to_string(),
+        };
+        assert!(pending.observe(&refused));
+        assert!(pending.is_done());
+
+        let report = pending.finish();
+        assert!(!report.is_complete());
+        assert_eq!(10147, report.failed[0].1);
+        assert!(report.working.is_empty());
+    }
+}
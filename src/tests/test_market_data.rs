@@ -0,0 +1,818 @@
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    use rust_decimal_macros::dec;
+
+    use crate::core::client::{ConnStatus, EClient, EClientConfig, POISONED_MUTEX};
+    use crate::core::common::{
+        DepthMktDataDescription, DepthServiceType, GenericTickType, MarketDataMode, SmartComponent,
+        TickAttrib, TickAttribBidAsk, TickAttribLast, TickByTickData, TickByTickType, TickType,
+        UNSET_INTEGER,
+    };
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::market_data::{
+        generic_tick_for, ExchangeResolver, ShortableStatus, SmartComponentMap, TickerConsolidator,
+    };
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::options::OptionGreeks;
+    use crate::core::req_ids::RequestKind;
+    use crate::examples::contract_samples;
+    use crate::tests::{block_on, connect_test, connected_client, CountingWaker};
+
+    //------------------------------------------------------------------------------------------------
+    fn tick_price(req_id: i32, tick_type: TickType, price: f64) -> ServerRspMsg {
+        ServerRspMsg::TickPrice {
+            req_id,
+            tick_type,
+            price,
+            tick_attr: TickAttrib::default(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_snapshot() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        let req_id = client.req_id_allocator().peek();
+        events
+            .send(tick_price(req_id, TickType::Bid, 10.0))
+            .unwrap();
+        events
+            .send(ServerRspMsg::NextValidId { order_id: 5 })
+            .unwrap();
+        events
+            .send(tick_price(req_id, TickType::Ask, 10.5))
+            .unwrap();
+        events
+            .send(ServerRspMsg::TickSize {
+                req_id,
+                tick_type: TickType::Volume,
+                size: 1200,
+            })
+            .unwrap();
+        events
+            .send(ServerRspMsg::TickSnapshotEnd { req_id })
+            .unwrap();
+        events.send(tick_price(99, TickType::Last, 3.0)).unwrap();
+
+        let snapshot = client.snapshot(&contract_samples::usstock())?;
+        assert_eq!(req_id, snapshot.req_id());
+        let snapshot = block_on(snapshot)?;
+        assert_eq!(Some(10.0), snapshot.bid);
+        assert_eq!(Some(10.5), snapshot.ask);
+        assert_eq!(Some(10.25), snapshot.midpoint());
+        assert_eq!(Some(1200), snapshot.volume);
+        assert_eq!(None, snapshot.last);
+        assert!(client.correlations().context(req_id).is_none());
+
+        // events that were not part of the snapshot are still delivered in order
+        match client.get_event()? {
+            Some(ServerRspMsg::NextValidId { order_id }) => assert_eq!(5, order_id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match client.get_event()? {
+            Some(ServerRspMsg::TickPrice { req_id, .. }) => assert_eq!(99, req_id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_snapshot_waits_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let req_id = client.req_id_allocator().peek();
+        let mut snapshot = Box::pin(client.snapshot(&contract_samples::usstock())?);
+        assert!(snapshot.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(0, counter.count());
+        // a tick of the snapshot wakes it, and it waits for the rest
+        events
+            .send(tick_price(req_id, TickType::Bid, 10.0))
+            .unwrap();
+        assert_eq!(1, counter.count());
+        assert!(snapshot.as_mut().poll(&mut cx).is_pending());
+        events
+            .send(ServerRspMsg::TickSnapshotEnd { req_id })
+            .unwrap();
+        match snapshot.as_mut().poll(&mut cx) {
+            Poll::Ready(result) => assert_eq!(Some(10.0), result?.bid),
+            Poll::Pending => panic!("the snapshot is still pending"),
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_snapshot_error_and_drop() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id,
+                error_code: 200,
+                error_str: "No security definition has been found".to_string(),
+            })
+            .unwrap();
+        assert!(block_on(client.snapshot(&contract_samples::usstock())?).is_err());
+
+        // a snapshot dropped before it completes cancels its market data line
+        drop(client.snapshot(&contract_samples::usstock())?);
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::ReqMktData as i32).to_string(),
+                (ServerReqMsgDiscriminants::ReqMktData as i32).to_string(),
+                (ServerReqMsgDiscriminants::CancelMktData as i32).to_string(),
+            ],
+            sent_message_ids(&mut client)?
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn tick_generic(ticker_id: i32, tick_type: TickType, value: f64) -> ServerRspMsg {
+        ServerRspMsg::TickGeneric {
+            ticker_id,
+            tick_type,
+            value,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_shortability() -> Result<(), IBKRApiLibError> {
+        assert_eq!(
+            ShortableStatus::EasyToBorrow,
+            ShortableStatus::from_indicator(3.0)
+        );
+        assert_eq!(
+            ShortableStatus::HardToBorrow,
+            ShortableStatus::from_indicator(2.0)
+        );
+        assert_eq!(
+            ShortableStatus::NotAvailable,
+            ShortableStatus::from_indicator(1.5)
+        );
+
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        events
+            .send(tick_generic(req_id, TickType::Shortable, 3.0))
+            .unwrap();
+        events
+            .send(ServerRspMsg::NextValidId { order_id: 5 })
+            .unwrap();
+        events
+            .send(tick_generic(req_id, TickType::ShortableShares, 12345678.5))
+            .unwrap();
+
+        let shortability =
+            client.shortability(&contract_samples::usstock(), Duration::from_millis(100))?;
+        assert_eq!(ShortableStatus::EasyToBorrow, shortability.status);
+        assert_eq!(Some(dec!(12345678.5)), shortability.available_shares);
+        assert_eq!(None, shortability.fee_rate);
+        match client.get_event()? {
+            Some(ServerRspMsg::NextValidId { order_id }) => assert_eq!(5, order_id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // the market data request and its cancel
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let (_, request, rest) = read_msg(&buf)?;
+        assert!(read_fields(&request).contains(&"236".to_string()));
+        let (_, cancel, _) = read_msg(&rest)?;
+        assert_eq!(
+            (ServerReqMsgDiscriminants::CancelMktData as i32).to_string(),
+            read_fields(&cancel)[0]
+        );
+
+        // without the number of shares the indicator is enough
+        let req_id = client.req_id_allocator().peek();
+        events
+            .send(tick_generic(req_id, TickType::Shortable, 2.0))
+            .unwrap();
+        let shortability =
+            client.shortability(&contract_samples::usstock(), Duration::from_millis(20))?;
+        assert_eq!(ShortableStatus::HardToBorrow, shortability.status);
+        assert_eq!(None, shortability.available_shares);
+
+        assert!(client
+            .shortability(&contract_samples::usstock(), Duration::from_millis(10))
+            .is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_consolidator() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let mut consolidator = TickerConsolidator::new(5000);
+        let contract = contract_samples::usstock();
+
+        consolidator.subscribe(&mut client, 1, &contract, "")?;
+        assert_eq!(Some(5000), consolidator.upstream_id(1));
+        consolidator.subscribe(&mut client, 2, &contract, "233,165")?;
+        // widening the line moves it to a new request id
+        assert_eq!(Some(5001), consolidator.upstream_id(1));
+        assert_eq!(Some(5001), consolidator.upstream_id(2));
+
+        let routed = consolidator.route(&tick_price(5001, TickType::Bid, 10.0));
+        assert_eq!(2, routed.len());
+
+        let routed = consolidator.route(&tick_price(5001, TickType::High52Week, 20.0));
+        assert_eq!(1, routed.len());
+        match &routed[0] {
+            ServerRspMsg::TickPrice { req_id, .. } => assert_eq!(2, *req_id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(consolidator
+            .route(&tick_price(5000, TickType::Bid, 10.0))
+            .is_empty());
+
+        consolidator.unsubscribe(&mut client, 1)?;
+        consolidator.unsubscribe(&mut client, 2)?;
+        assert_eq!(None, consolidator.upstream_id(2));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_consolidator_keeps_line_when_widening_fails() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let mut consolidator = TickerConsolidator::new(5000);
+        let contract = contract_samples::usstock();
+
+        consolidator.subscribe(&mut client, 1, &contract, "")?;
+        *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::DISCONNECTED;
+        assert!(consolidator
+            .subscribe(&mut client, 2, &contract, "233,165")
+            .is_err());
+
+        assert_eq!(Some(5000), consolidator.upstream_id(1));
+        assert_eq!(None, consolidator.upstream_id(2));
+        // the line was not widened, so ticks only the failed consumer wanted are still filtered
+        assert!(consolidator
+            .route(&tick_price(5000, TickType::High52Week, 20.0))
+            .is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn venue(exchange: &str, sec_type: &str, service_data_type: &str) -> DepthMktDataDescription {
+        DepthMktDataDescription::new(
+            exchange.to_string(),
+            sec_type.to_string(),
+            "".to_string(),
+            service_data_type.to_string(),
+            UNSET_INTEGER,
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_depth_exchanges_for() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::MktDepthExchanges {
+                depth_mkt_data_descriptions: vec![
+                    venue("ISLAND", "STK", "Deep2"),
+                    venue("ARCA", "STK", "Deep"),
+                    venue("GLOBEX", "FUT", "Deep"),
+                ],
+            })
+            .unwrap();
+
+        let venues =
+            client.depth_exchanges_for(&contract_samples::usstock(), Duration::from_millis(200))?;
+        assert_eq!(1, venues.len());
+        assert_eq!("ISLAND", venues[0].exchange);
+        assert_eq!(DepthServiceType::Deep2, venues[0].service_type());
+        assert!(venues[0].has_market_makers());
+        assert_eq!(None, venues[0].aggregation_group());
+
+        let arca = venue("ARCA", "STK", "Deep");
+        assert!(arca.matches("stk", "SMART"));
+        assert!(!arca.matches("STK", "ISLAND"));
+        assert!(!arca.has_market_makers());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_smart_depth_request() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let mut contract = contract_samples::usstock();
+        client.req_mkt_depth(1, &contract, 5, false, vec![])?;
+        contract.exchange = "SMART".to_string();
+        client.req_mkt_depth(2, &contract, 5, false, vec![])?;
+        // cancelled as it was requested
+        client.cancel_mkt_depth(2, false)?;
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let mut smart_depth = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf).unwrap();
+            // is_smart_depth follows the number of rows, or the req_id of a cancel
+            let fields = read_fields(&msg);
+            smart_depth.push(fields[if fields.len() > 5 { 16 } else { 3 }].clone());
+            buf = rest;
+        }
+        assert_eq!(vec!["0", "1", "1"], smart_depth);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_generic_tick_list() {
+        assert_eq!(
+            "233,236",
+            GenericTickType::to_list(&[GenericTickType::RtVolume, GenericTickType::Shortable])
+        );
+        assert_eq!("", GenericTickType::to_list(&[]));
+        assert_eq!(
+            Some(GenericTickType::MiscellaneousStats),
+            generic_tick_for(TickType::High52Week)
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tick_by_tick_request() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let contract = contract_samples::usstock();
+        for (req_id, tick_type) in [
+            TickByTickType::Last,
+            TickByTickType::AllLast,
+            TickByTickType::BidAsk,
+            TickByTickType::MidPoint,
+        ]
+        .iter()
+        .enumerate()
+        {
+            client.req_tick_by_tick_data(req_id as i32, &contract, tick_type.clone(), 0, false)?;
+        }
+        assert!(client
+            .req_tick_by_tick_data(9, &contract, TickByTickType::NA, 0, false)
+            .is_err());
+
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let mut tick_types = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            // the type follows the contract fields
+            tick_types.push(read_fields(&msg)[14].clone());
+            buf = rest;
+        }
+        assert_eq!(vec!["Last", "AllLast", "BidAsk", "MidPoint"], tick_types);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tick_by_tick_payloads() -> Result<(), IBKRApiLibError> {
+        let client = connected_client();
+        let (_, rx) = channel::<String>();
+        let mut decoder = Decoder::new(
+            rx,
+            client.evt_chan.0.clone(),
+            151,
+            client.conn_state.clone(),
+        );
+        decoder.interpret("99\x001\x001\x001704465000\x00101.5\x00200\x002\x00ARCA\x00\x00")?;
+        decoder.interpret("99\x001\x002\x001704465001\x00101.25\x0050\x001\x00FINRA\x00I\x00")?;
+        decoder.interpret("99\x001\x003\x001704465002\x00101.0\x00101.5\x003\x004\x002\x00")?;
+        decoder.interpret("99\x001\x004\x001704465003\x00101.25\x00")?;
+
+        let mut ticks = Vec::new();
+        while let Some(msg) = client.get_event()? {
+            if let ServerRspMsg::TickByTick { req_id, time, tick } = msg {
+                assert_eq!(1, req_id);
+                ticks.push((time, tick));
+            }
+        }
+        assert_eq!(
+            vec![
+                (
+                    1704465000,
+                    TickByTickData::Last {
+                        all_last: false,
+                        price: 101.5,
+                        size: 200,
+                        tick_attrib_last: TickAttribLast::new(false, true),
+                        exchange: "ARCA".to_string(),
+                        special_conditions: "".to_string(),
+                    }
+                ),
+                (
+                    1704465001,
+                    TickByTickData::Last {
+                        all_last: true,
+                        price: 101.25,
+                        size: 50,
+                        tick_attrib_last: TickAttribLast::new(true, false),
+                        exchange: "FINRA".to_string(),
+                        special_conditions: "I".to_string(),
+                    }
+                ),
+                (
+                    1704465002,
+                    TickByTickData::BidAsk {
+                        bid_price: 101.0,
+                        ask_price: 101.5,
+                        bid_size: 3,
+                        ask_size: 4,
+                        tick_attrib_bid_ask: TickAttribBidAsk::new(false, true),
+                    }
+                ),
+                (1704465003, TickByTickData::MidPoint { mid_point: 101.25 }),
+            ],
+            ticks
+        );
+        let types: Vec<TickByTickType> = ticks.iter().map(|(_, tick)| tick.tick_type()).collect();
+        assert_eq!(
+            vec![
+                TickByTickType::Last,
+                TickByTickType::AllLast,
+                TickByTickType::BidAsk,
+                TickByTickType::MidPoint
+            ],
+            types
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn sent_message_ids(client: &mut EClient) -> Result<Vec<String>, IBKRApiLibError> {
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let mut ids = Vec::new();
+        while !buf.is_empty() {
+            let (_, msg, rest) = read_msg(&buf)?;
+            ids.push(read_fields(&msg)[0].clone());
+            buf = rest;
+        }
+        Ok(ids)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_implied_volatility() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        let option = contract_samples::option_at_ise();
+
+        let calculation = client.implied_volatility(&option, 2.5, 450.0)?;
+        assert_eq!(req_id, calculation.req_id());
+        // the answer arrives while the future is waiting
+        let answer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            events.send(tick_price(99, TickType::Bid, 1.0)).unwrap();
+            events
+                .send(ServerRspMsg::TickOptionComputation {
+                    ticker_id: req_id,
+                    tick_type: TickType::CustOptionComputation,
+                    implied_vol: 0.23,
+                    delta: 0.51,
+                    opt_price: 2.5,
+                    pv_dividend: 0.0,
+                    gamma: 0.02,
+                    vega: 0.4,
+                    theta: -0.1,
+                    und_price: 450.0,
+                })
+                .unwrap();
+        });
+        let greeks = block_on(calculation)?;
+        answer.join().unwrap();
+
+        assert_eq!(
+            OptionGreeks {
+                implied_vol: Some(0.23),
+                delta: Some(0.51),
+                opt_price: Some(2.5),
+                pv_dividend: Some(0.0),
+                gamma: Some(0.02),
+                vega: Some(0.4),
+                theta: Some(-0.1),
+                und_price: Some(450.0),
+            },
+            greeks
+        );
+        // the calculation was cancelled once answered
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::ReqCalcImpliedVolat as i32).to_string(),
+                (ServerReqMsgDiscriminants::CancelCalcImpliedVolat as i32).to_string(),
+            ],
+            sent_message_ids(&mut client)?
+        );
+        assert!(client.correlations().context(req_id).is_none());
+        // events of other requests are left for the application
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::TickPrice { req_id: 99, .. })
+        ));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_option_calculation_waits_for_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let events = client.evt_chan.0.clone();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let option = contract_samples::option_at_ise();
+        let mut calculation = Box::pin(client.option_price(&option, 0.2, 450.0)?);
+        assert!(calculation.as_mut().poll(&mut cx).is_pending());
+        // nothing has arrived, so the future must not wake itself
+        assert_eq!(0, counter.count());
+        // an event for someone else wakes it, and it waits again
+        events.send(tick_price(99, TickType::Bid, 1.0)).unwrap();
+        assert_eq!(1, counter.count());
+        assert!(calculation.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(1, counter.count());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_option_price_error_and_drop() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let option = contract_samples::option_at_ise();
+        let req_id = client.req_id_allocator().peek();
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::ErrMsg {
+                req_id,
+                error_code: 200,
+                error_str: "No security definition has been found".to_string(),
+            })
+            .unwrap();
+        assert!(block_on(client.option_price(&option, 0.2, 450.0)?).is_err());
+
+        // a calculation dropped before its answer is cancelled
+        drop(client.option_price(&option, 0.2, 450.0)?);
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::ReqCalcOptionPrice as i32).to_string(),
+                (ServerReqMsgDiscriminants::ReqCalcOptionPrice as i32).to_string(),
+                (ServerReqMsgDiscriminants::CancelCalcOptionPrice as i32).to_string(),
+            ],
+            sent_message_ids(&mut client)?
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(exchange: &str) -> TickByTickData {
+        TickByTickData::Last {
+            all_last: true,
+            price: 101.0,
+            size: 100,
+            tick_attrib_last: TickAttribLast::default(),
+            exchange: exchange.to_string(),
+            special_conditions: "".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_smart_components() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let components = vec![
+            SmartComponent::new(1, "ARCA".to_string(), "P".to_string()),
+            SmartComponent::new(2, "NASDAQ".to_string(), "Q".to_string()),
+            SmartComponent::new(3, "NYSE".to_string(), "N".to_string()),
+        ];
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::SmartComponents {
+                req_id,
+                smart_components: components.clone(),
+            })
+            .unwrap();
+
+        let map = client.smart_components("a6", Duration::from_millis(50))?;
+        assert_eq!(3, map.len());
+        assert_eq!(Some("NASDAQ"), map.exchange('Q'));
+        assert_eq!(None, map.exchange('Z'));
+        assert_eq!("ARCA", map.resolve("P"));
+        assert_eq!("ISLAND", map.resolve("ISLAND"));
+        assert!(client.correlations().context(req_id).is_none());
+
+        let mut resolver = ExchangeResolver::new();
+        assert!(resolver.update(&ServerRspMsg::TickReqParams {
+            ticker_id: 7,
+            min_tick: 0.01,
+            bbo_exchange: "a6".to_string(),
+            snapshot_permissions: 3,
+        }));
+        assert!(resolver.update(&ServerRspMsg::TickReqParams {
+            ticker_id: 8,
+            min_tick: 0.01,
+            bbo_exchange: "9c".to_string(),
+            snapshot_permissions: 3,
+        }));
+        assert_eq!(vec!["9c", "a6"], resolver.missing());
+        resolver.insert(map);
+        assert_eq!(vec!["9c"], resolver.missing());
+
+        assert_eq!(Some("NYSE"), resolver.trade_exchange(7, &trade("N")));
+        // letters of a BBO exchange without a map are left as they are
+        assert_eq!(Some("N"), resolver.trade_exchange(8, &trade("N")));
+        assert_eq!(
+            None,
+            resolver.trade_exchange(7, &TickByTickData::MidPoint { mid_point: 1.0 })
+        );
+        assert!(SmartComponentMap::new("a6", &[]).is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn no_permissions(req_id: i32) -> ServerRspMsg {
+        ServerRspMsg::ErrMsg {
+            req_id,
+            error_code: 10167,
+            error_str: "Requested market data is not subscribed".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_delayed_fallback() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::with_config(EClientConfig::default().delayed_fallback());
+        connect_test(&mut client);
+        let req_id = client.next_req_id(RequestKind::MarketData, "IBM");
+        client.req_mkt_data(
+            req_id,
+            &contract_samples::usstock(),
+            "",
+            false,
+            false,
+            vec![],
+        )?;
+
+        let events = client.evt_chan.0.clone();
+        events
+            .send(ServerRspMsg::MarketDataType {
+                req_id,
+                market_data_type: MarketDataMode::RealTime as i32,
+            })
+            .unwrap();
+        events.send(no_permissions(req_id)).unwrap();
+        events
+            .send(ServerRspMsg::MarketDataType {
+                req_id,
+                market_data_type: MarketDataMode::Delayed as i32,
+            })
+            .unwrap();
+        events
+            .send(tick_price(req_id, TickType::DelayedBid, 181.5))
+            .unwrap();
+
+        let first = client.get_enriched_event()?.unwrap();
+        assert_eq!(Some(MarketDataMode::RealTime), first.market_data_mode);
+        // the error is replaced by a delayed subscription with the same id
+        let second = client.get_enriched_event()?.unwrap();
+        assert!(matches!(second.msg, ServerRspMsg::MarketDataType { .. }));
+        let tick = client.get_enriched_event()?.unwrap();
+        assert!(matches!(tick.msg, ServerRspMsg::TickPrice { .. }));
+        assert_eq!(Some(MarketDataMode::Delayed), tick.market_data_mode);
+        assert!(tick.context.is_some_and(|context| !context.is_cancelled()));
+        assert_eq!(
+            vec![
+                (ServerReqMsgDiscriminants::ReqMktData as i32).to_string(),
+                (ServerReqMsgDiscriminants::CancelMktData as i32).to_string(),
+                (ServerReqMsgDiscriminants::ReqMarketDataType as i32).to_string(),
+                (ServerReqMsgDiscriminants::ReqMktData as i32).to_string(),
+            ],
+            sent_message_ids(&mut client)?
+        );
+
+        // a subscription only falls back once
+        events.send(no_permissions(req_id)).unwrap();
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::ErrMsg {
+                error_code: 10167,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_no_delayed_fallback() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        client.req_market_data_mode(MarketDataMode::Frozen)?;
+        client.req_mkt_data(7, &contract_samples::usstock(), "", false, false, vec![])?;
+        client.evt_chan.0.send(no_permissions(7)).unwrap();
+
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::ErrMsg {
+                error_code: 10167,
+                ..
+            })
+        ));
+        assert_eq!(None, client.market_data_mode(7));
+        assert_eq!(2, sent_message_ids(&mut client)?.len());
+        assert_eq!(
+            MarketDataMode::DelayedFrozen,
+            MarketDataMode::Frozen.delayed()
+        );
+        assert!(!MarketDataMode::Frozen.is_delayed());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn connectivity_restored(error_code: i32) -> ServerRspMsg {
+        ServerRspMsg::ErrMsg {
+            req_id: -1,
+            error_code,
+            error_str: "Connectivity between IB and TWS has been restored".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_resubscribe_after_data_loss() -> Result<(), IBKRApiLibError> {
+        let mut client =
+            EClient::with_config(EClientConfig::default().resubscribe_after_data_loss());
+        connect_test(&mut client);
+        client.req_mkt_data(7, &contract_samples::usstock(), "", false, false, vec![])?;
+        client.req_mkt_data(8, &contract_samples::usstock(), "", true, false, vec![])?;
+
+        // data was kept: nothing to do
+        client.evt_chan.0.send(connectivity_restored(1102)).unwrap();
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::ErrMsg {
+                error_code: 1102,
+                ..
+            })
+        ));
+        assert_eq!(2, sent_message_ids(&mut client)?.len());
+
+        // data was lost: the streaming request is made again, the snapshot is not
+        client.evt_chan.0.send(connectivity_restored(1101)).unwrap();
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::ErrMsg {
+                error_code: 1101,
+                ..
+            })
+        ));
+        assert_eq!(
+            vec![(ServerReqMsgDiscriminants::ReqMktData as i32).to_string()],
+            sent_message_ids(&mut client)?
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_no_resubscribe_after_data_loss() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        client.req_mkt_data(7, &contract_samples::usstock(), "", false, false, vec![])?;
+        client.evt_chan.0.send(connectivity_restored(1101)).unwrap();
+
+        assert!(client.get_event()?.is_some());
+        assert_eq!(1, sent_message_ids(&mut client)?.len());
+        Ok(())
+    }
+}
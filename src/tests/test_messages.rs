@@ -4,8 +4,8 @@ mod tests {
     use crate::core::common::{TickByTickType, UNSET_DOUBLE, UNSET_INTEGER};
     use crate::core::errors::IBKRApiLibError;
     use crate::core::messages::{
-        make_field, make_field_handle_empty, make_message, read_fields, read_msg,
-        ServerReqMsgDiscriminants,
+        make_field, make_field_handle_empty, make_message, read_fields, read_msg, split_msg,
+        write_message, FieldIterator, ServerReqMsgDiscriminants,
     };
     use crate::examples::contract_samples;
     #[test]
@@ -41,6 +41,17 @@ mod tests {
         assert_eq!(result_fields, read_fields(fields));
     }
 
+    #[test]
+    fn test_field_iterator() {
+        let fields = "here\u{0}\u{0}2.5\u{0}";
+        let mut fields_itr = FieldIterator::new(fields);
+        assert_eq!(Some("here"), fields_itr.next());
+        assert_eq!(Some(""), fields_itr.next());
+        assert_eq!("2.5\u{0}", fields_itr.remaining());
+        assert_eq!(Some("2.5"), fields_itr.next());
+        assert_eq!(None, fields_itr.next());
+    }
+
     #[test]
     fn test_make_msg() -> Result<(), IBKRApiLibError> {
         let mut msg = "".to_string();
@@ -92,4 +103,23 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_split_msg() -> Result<(), IBKRApiLibError> {
+        let mut buffer = Vec::new();
+        write_message("49\u{0}1\u{0}", &mut buffer)?;
+        write_message("4\u{0}2\u{0}", &mut buffer)?;
+        assert_eq!(make_message("49\u{0}1\u{0}")?, buffer[..9].to_vec());
+
+        let (first, rest) = split_msg(&buffer).unwrap();
+        assert_eq!(b"49\x001\x00", first);
+        let (second, rest) = split_msg(rest).unwrap();
+        assert_eq!(b"4\x002\x00", second);
+        assert!(rest.is_empty());
+        // incomplete frames are left for the next read
+        assert_eq!(None, split_msg(&buffer[10..15]));
+        assert_eq!(None, split_msg(&buffer[..3]));
+
+        Ok(())
+    }
 }
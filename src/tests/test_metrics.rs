@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{
+        Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString,
+        Unit,
+    };
+
+    use crate::core::client::EClient;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::metrics::{BYTES_SENT, DECODE_SECONDS, EVENT_QUEUE_DEPTH, MESSAGES_DECODED};
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Samples(Mutex<Vec<f64>>);
+
+    impl HistogramFn for Samples {
+        fn record(&self, value: f64) {
+            self.0.lock().unwrap().push(value);
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Keeps every metric in memory, keyed by name and labels
+    #[derive(Default)]
+    struct TestRecorder {
+        values: Mutex<HashMap<String, Arc<AtomicU64>>>,
+        samples: Mutex<HashMap<String, Arc<Samples>>>,
+    }
+
+    impl TestRecorder {
+        fn name(key: &Key) -> String {
+            let labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            if labels.is_empty() {
+                key.name().to_string()
+            } else {
+                format!("{}{{{}}}", key.name(), labels.join(","))
+            }
+        }
+
+        fn value(&self, name: &str) -> Arc<AtomicU64> {
+            let mut values = self.values.lock().unwrap();
+            values.entry(name.to_string()).or_default().clone()
+        }
+
+        fn counter(&self, name: &str) -> u64 {
+            self.value(name).load(Ordering::Relaxed)
+        }
+
+        fn gauge(&self, name: &str) -> f64 {
+            f64::from_bits(self.value(name).load(Ordering::Relaxed))
+        }
+
+        fn samples(&self, name: &str) -> usize {
+            let mut samples = self.samples.lock().unwrap();
+            let samples = samples.entry(name.to_string()).or_default();
+            let count = samples.0.lock().unwrap().len();
+            count
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.value(&TestRecorder::name(key)))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.value(&TestRecorder::name(key)))
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            let mut samples = self.samples.lock().unwrap();
+            Histogram::from_arc(samples.entry(TestRecorder::name(key)).or_default().clone())
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_client_metrics() -> Result<(), IBKRApiLibError> {
+        let recorder = TestRecorder::default();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+        crate::core::metrics::describe();
+
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        client.req_current_time()?;
+        // length prefix, message id and version
+        assert_eq!(9, recorder.counter(BYTES_SENT));
+
+        let (tx, rx) = channel::<String>();
+        let mut decoder = Decoder::new(
+            rx,
+            client.evt_chan.0.clone(),
+            151,
+            client.conn_state.clone(),
+        );
+        for msg in &[
+            "9\x001\x0042\x00",
+            "49\x001\x001704465000\x00",
+            "49\x001\x001704465001\x00",
+        ] {
+            tx.send(msg.to_string()).unwrap();
+        }
+        drop(tx);
+        decoder.run()?;
+
+        assert_eq!(
+            1,
+            recorder.counter(&format!("{}{{type=NextValidId}}", MESSAGES_DECODED))
+        );
+        assert_eq!(
+            2,
+            recorder.counter(&format!("{}{{type=CurrentTime}}", MESSAGES_DECODED))
+        );
+        assert_eq!(3, recorder.samples(DECODE_SECONDS));
+        assert_eq!(3.0, recorder.gauge(EVENT_QUEUE_DEPTH));
+
+        client.get_event()?;
+        assert_eq!(2.0, recorder.gauge(EVENT_QUEUE_DEPTH));
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::account_values::{AccountKey, AccountValue};
+    use crate::core::client::EClient;
+    use crate::core::contract::Contract;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerRspMsg};
+    use crate::core::models::{ModelFeed, ModelPortfolioTracker};
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    fn position(
+        req_id: i32,
+        model_code: &str,
+        con_id: i32,
+        position: f64,
+        avg_cost: f64,
+    ) -> ServerRspMsg {
+        let mut contract = Contract::default();
+        contract.con_id = con_id;
+        ServerRspMsg::PositionMulti {
+            req_id,
+            account: "DU1".to_string(),
+            model_code: model_code.to_string(),
+            contract,
+            position,
+            avg_cost,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn value(req_id: i32, model_code: &str, key: &str, value: &str) -> ServerRspMsg {
+        ServerRspMsg::AccountUpdateMulti {
+            req_id,
+            account: "DU1".to_string(),
+            model_code: model_code.to_string(),
+            key: AccountKey::from(key),
+            value: AccountValue::parse(value, "USD"),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_consolidation() {
+        let mut tracker = ModelPortfolioTracker::new();
+        tracker.track(1, ModelFeed::Positions);
+        tracker.track(2, ModelFeed::AccountValues);
+
+        assert!(tracker.update(&position(1, "Growth", 100, 10.0, 50.0)));
+        assert!(tracker.update(&position(1, "Income", 100, 30.0, 70.0)));
+        assert!(tracker.update(&position(1, "Income", 200, 5.0, 1.0)));
+        assert!(tracker.update(&position(1, "Income", 200, 0.0, 0.0)));
+        // untracked requests and the wrong feed are ignored
+        assert!(!tracker.update(&position(3, "Growth", 100, 1.0, 1.0)));
+        assert!(!tracker.update(&value(1, "Growth", "NetLiquidation", "1")));
+        assert!(tracker.update(&value(2, "Growth", "NetLiquidation", "1000")));
+        assert!(tracker.update(&value(2, "Income", "NetLiquidation", "2500.5")));
+        assert!(tracker.update(&ServerRspMsg::PositionMultiEnd { req_id: 1 }));
+        assert!(!tracker.is_loaded());
+        assert!(tracker.update(&ServerRspMsg::AccountUpdateMultiEnd { req_id: 2 }));
+        assert!(tracker.is_loaded());
+
+        assert_eq!(2, tracker.models().count());
+        assert!(tracker
+            .model("DU1", "Income")
+            .unwrap()
+            .positions
+            .get(&200)
+            .is_none());
+        let positions = tracker.consolidated_positions(Some("DU1"));
+        assert_eq!(1, positions.len());
+        assert_eq!(40.0, positions[0].position);
+        assert_eq!(65.0, positions[0].avg_cost);
+        assert_eq!(vec!["Growth", "Income"], positions[0].model_codes);
+        assert!(tracker.consolidated_positions(Some("DU2")).is_empty());
+        assert_eq!(
+            Some(3500.5),
+            tracker.consolidated_amount(None, "NetLiquidation", "USD")
+        );
+        assert_eq!(
+            None,
+            tracker.consolidated_amount(None, "NetLiquidation", "EUR")
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_subscribe() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        let positions_id = client.req_id_allocator().peek();
+        let values_id = positions_id + 1;
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            position(positions_id, "Growth", 100, 10.0, 50.0),
+            ServerRspMsg::PositionMultiEnd {
+                req_id: positions_id,
+            },
+            value(values_id, "Growth", "NetLiquidation", "1000"),
+            ServerRspMsg::AccountUpdateMultiEnd { req_id: values_id },
+            value(values_id, "Growth", "NetLiquidation", "1100"),
+        ] {
+            events.send(msg).unwrap();
+        }
+
+        let mut tracker = ModelPortfolioTracker::new();
+        tracker.subscribe(
+            &mut client,
+            "DU1",
+            "Growth",
+            false,
+            Duration::from_millis(100),
+        )?;
+        assert!(tracker.is_loaded());
+        assert_eq!(
+            Some(1000.0),
+            tracker
+                .model("DU1", "Growth")
+                .unwrap()
+                .amount("NetLiquidation", "USD")
+        );
+        // updates after the snapshot are left for the caller's event loop
+        let later = client.get_event()?.unwrap();
+        assert!(tracker.update(&later));
+        assert_eq!(
+            Some(1100.0),
+            tracker.consolidated_amount(Some("DU1"), "NetLiquidation", "USD")
+        );
+
+        tracker.unsubscribe_all(&mut client)?;
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, _) = read_msg(&buf)?;
+        let fields = read_fields(&request);
+        assert_eq!(
+            vec![
+                positions_id.to_string(),
+                "DU1".to_string(),
+                "Growth".to_string()
+            ],
+            fields[2..5].to_vec()
+        );
+        Ok(())
+    }
+}
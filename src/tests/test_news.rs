@@ -0,0 +1,258 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::common::NewsProvider;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::news::{
+        fetch_article, headline_ticks, historical_headlines, news_providers, ArticleBody, Headline,
+        HeadlineHistory, HeadlineStream,
+    };
+    use crate::core::time::parse_tws_time;
+    use crate::examples::contract_samples;
+    use crate::tests::{connected_client, sent_requests};
+
+    //------------------------------------------------------------------------------------------------
+    fn tick_news(ticker_id: i32, article_id: &str, headline: &str) -> ServerRspMsg {
+        ServerRspMsg::TickNews {
+            ticker_id,
+            time_stamp: 1704465000123,
+            provider_code: "BRFG".to_string(),
+            article_id: article_id.to_string(),
+            headline: headline.to_string(),
+            extra_data: "A:800015:L:en".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_article_body() -> Result<(), IBKRApiLibError> {
+        assert_eq!(
+            ArticleBody::Text("<p>Shares rose</p>".to_string()),
+            ArticleBody::decode(0, "<p>Shares rose</p>")?
+        );
+        let pdf = ArticleBody::decode(1, "JVBERi0x\nLjQ=")?;
+        assert_eq!(ArticleBody::Binary(b"%PDF-1.4".to_vec()), pdf);
+        assert!(pdf.is_pdf());
+        assert!(ArticleBody::decode(1, "not base64!").is_err());
+        assert!(ArticleBody::decode(2, "").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_headline_from_msg() {
+        let live = Headline::from_msg(&tick_news(3, "BRFG$1", "Shares rose")).unwrap();
+        assert_eq!(3, live.req_id);
+        assert_eq!(Utc.timestamp_millis_opt(1704465000123).single(), live.time);
+        assert_eq!("A:800015:L:en", live.extra_data);
+
+        let historical = Headline::from_msg(&ServerRspMsg::HistoricalNews {
+            req_id: 4,
+            time: Utc.with_ymd_and_hms(2024, 1, 5, 14, 30, 0).single(),
+            #[cfg(feature = "raw-times")]
+            time_raw: "2024-01-05 14:30:00.0".to_string(),
+            provider_code: "DJNL".to_string(),
+            article_id: "DJNL$2".to_string(),
+            headline: "Earnings beat".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            "2024-01-05 14:30:00 [DJNL:DJNL$2] Earnings beat",
+            historical.to_string()
+        );
+        assert!(Headline::from_msg(&ServerRspMsg::OpenOrderEnd).is_none());
+        assert_eq!("mdoff,292:BRFG+DJNL", headline_ticks(&["BRFG", "DJNL"]));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_news_providers_and_article() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        events
+            .send(ServerRspMsg::NewsProviders {
+                news_providers: vec![NewsProvider::new(
+                    "BRFG".to_string(),
+                    "Briefing.com".to_string(),
+                )],
+            })
+            .unwrap();
+        events
+            .send(ServerRspMsg::NewsArticle {
+                req_id,
+                article_type: 1,
+                article_text: "JVBERi0xLjQ=".to_string(),
+            })
+            .unwrap();
+
+        let providers = news_providers(&mut client, Duration::from_millis(100))?;
+        assert_eq!("Briefing.com", providers[0].name);
+        let body = fetch_article(&mut client, "BRFG", "BRFG$1", Duration::from_millis(100))?;
+        assert!(body.is_pdf());
+
+        events
+            .send(ServerRspMsg::ErrMsg {
+                req_id: req_id + 1,
+                error_code: 10172,
+                error_str: "Failed to request news article".to_string(),
+            })
+            .unwrap();
+        assert!(fetch_article(&mut client, "BRFG", "BRFG$2", Duration::from_millis(100)).is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_historical_headlines() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for (time, article_id) in &[
+            ("2024-01-05 14:30:00.0", "1"),
+            ("2024-01-05 09:00:00.0", "2"),
+        ] {
+            events
+                .send(ServerRspMsg::HistoricalNews {
+                    req_id,
+                    time: parse_tws_time(time, Tz::UTC),
+                    #[cfg(feature = "raw-times")]
+                    time_raw: time.to_string(),
+                    provider_code: "BRFG".to_string(),
+                    article_id: article_id.to_string(),
+                    headline: "Shares rose".to_string(),
+                })
+                .unwrap();
+        }
+        events
+            .send(ServerRspMsg::HistoricalNewsEnd {
+                req_id,
+                has_more: true,
+            })
+            .unwrap();
+
+        let (headlines, more) = historical_headlines(
+            &mut client,
+            8314,
+            &["BRFG", "DJNL"],
+            &Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap(),
+            &Utc.with_ymd_and_hms(2024, 1, 6, 0, 0, 0).unwrap(),
+            10,
+            Duration::from_millis(100),
+        )?;
+        assert_eq!(2, headlines.len());
+        assert_eq!("2", headlines[1].article_id);
+        assert!(more);
+
+        let request = &sent_requests(&mut client)[0];
+        assert_eq!("BRFG+DJNL", request[3]);
+        assert_eq!("2024-01-05 00:00:00.0", request[4]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn historical_news(req_id: i32, time: &str, article_id: &str) -> ServerRspMsg {
+        ServerRspMsg::HistoricalNews {
+            req_id,
+            time: parse_tws_time(time, Tz::UTC),
+            #[cfg(feature = "raw-times")]
+            time_raw: time.to_string(),
+            provider_code: "BRFG".to_string(),
+            article_id: article_id.to_string(),
+            headline: format!("Headline {}", article_id),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_headline_history() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            historical_news(req_id, "2024-01-05 14:30:00.0", "1"),
+            historical_news(req_id, "2024-01-05 12:00:00.0", "2"),
+            ServerRspMsg::HistoricalNewsEnd {
+                req_id,
+                has_more: true,
+            },
+            // the second request ends at the oldest headline, so it is sent again
+            historical_news(req_id + 1, "2024-01-05 12:00:00.0", "2"),
+            historical_news(req_id + 1, "2024-01-05 10:00:00.0", "3"),
+            ServerRspMsg::HistoricalNewsEnd {
+                req_id: req_id + 1,
+                has_more: false,
+            },
+        ] {
+            events.send(msg).unwrap();
+        }
+
+        let article_ids = HeadlineHistory::new(
+            &mut client,
+            8314,
+            &["BRFG"],
+            Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 6, 0, 0, 0).unwrap(),
+            Duration::from_millis(100),
+        )
+        .map(|headline| headline.map(|headline| headline.article_id))
+        .collect::<Result<Vec<String>, IBKRApiLibError>>()?;
+        assert_eq!(vec!["1", "2", "3"], article_ids);
+
+        let requests = sent_requests(&mut client);
+        assert_eq!(2, requests.len());
+        assert_eq!("2024-01-06 00:00:00.0", requests[0][5]);
+        assert_eq!("2024-01-05 12:00:00.0", requests[1][5]);
+
+        // an error ends the iteration after it is returned
+        let mut failing = HeadlineHistory::new(
+            &mut client,
+            8314,
+            &["BRFG"],
+            Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 6, 0, 0, 0).unwrap(),
+            Duration::from_millis(10),
+        );
+        assert!(failing.next().unwrap().is_err());
+        assert!(failing.next().is_none());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_headline_stream() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        events.send(tick_news(req_id, "BRFG$1", "First")).unwrap();
+        events
+            .send(tick_news(req_id + 1, "BRFG$2", "Other"))
+            .unwrap();
+        events.send(tick_news(req_id, "BRFG$3", "Second")).unwrap();
+
+        let headlines = HeadlineStream::subscribe(
+            &mut client,
+            &contract_samples::usstock(),
+            &["BRFG"],
+            Duration::from_millis(50),
+        )?
+        .map(|headline| headline.map(|headline| headline.headline))
+        .collect::<Result<Vec<String>, IBKRApiLibError>>()?;
+        assert_eq!(vec!["First", "Second"], headlines);
+
+        // headlines of other requests stay with the client
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::TickNews { article_id, .. }) if article_id == "BRFG$2"
+        ));
+        let requests = sent_requests(&mut client);
+        assert_eq!(2, requests.len());
+        assert!(requests[0].contains(&"mdoff,292:BRFG".to_string()));
+        Ok(())
+    }
+}
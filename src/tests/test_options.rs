@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::common::{TickType, UNSET_DOUBLE};
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::options::{GreekSource, GreeksTracker, OptionGreeks};
+
+    //------------------------------------------------------------------------------------------------
+    fn computation(req_id: i32, tick_type: TickType, implied_vol: f64, delta: f64) -> ServerRspMsg {
+        ServerRspMsg::TickOptionComputation {
+            ticker_id: req_id,
+            tick_type,
+            implied_vol,
+            delta,
+            opt_price: 2.5,
+            pv_dividend: 0.0,
+            gamma: 0.05,
+            vega: 0.12,
+            theta: -0.03,
+            und_price: UNSET_DOUBLE,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_greeks_by_source() {
+        let mut option = Contract::default();
+        option.symbol = "SPY".to_string();
+        option.sec_type = "OPT".to_string();
+        let mut tracker = GreeksTracker::new();
+        tracker.track(1, &option);
+
+        // untracked requests and other tick types are ignored
+        assert!(tracker
+            .update(&computation(2, TickType::ModelOption, 0.2, 0.5))
+            .is_none());
+        assert!(tracker
+            .update(&computation(1, TickType::Bid, 0.2, 0.5))
+            .is_none());
+
+        let change = tracker
+            .update(&computation(1, TickType::BidOptionComputation, 0.21, 0.48))
+            .unwrap();
+        assert_eq!(GreekSource::Bid, change.source);
+        assert!(change.previous.is_none());
+        assert_eq!(Some(0.21), change.greeks.implied_vol);
+        assert_eq!(None, change.greeks.und_price);
+
+        tracker
+            .update(&computation(1, TickType::DelayedModelOption, 0.2, 0.5))
+            .unwrap();
+        // a repeated computation is not a change
+        assert!(tracker
+            .update(&computation(1, TickType::ModelOption, 0.2, 0.5))
+            .is_none());
+        // values not computed yet keep the previous ones
+        let change = tracker
+            .update(&computation(1, TickType::ModelOption, UNSET_DOUBLE, 0.52))
+            .unwrap();
+        assert_eq!(Some(0.2), change.previous.unwrap().implied_vol);
+        assert_eq!(
+            OptionGreeks {
+                implied_vol: Some(0.2),
+                delta: Some(0.52),
+                opt_price: Some(2.5),
+                pv_dividend: Some(0.0),
+                gamma: Some(0.05),
+                vega: Some(0.12),
+                theta: Some(-0.03),
+                und_price: None,
+            },
+            change.greeks
+        );
+
+        let ticker = tracker.ticker(1).unwrap();
+        assert_eq!("SPY", ticker.contract.symbol);
+        assert_eq!(Some(0.48), ticker.get(GreekSource::Bid).unwrap().delta);
+        assert!(ticker.get(GreekSource::Ask).is_none());
+        let (source, best) = ticker.best().unwrap();
+        assert_eq!(GreekSource::Model, source);
+        assert_eq!(Some(0.52), best.delta);
+
+        assert!(tracker.untrack(1).is_some());
+        assert!(tracker.ticker(1).is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_stale_greeks() {
+        let mut tracker = GreeksTracker::new();
+        tracker.track(1, &Contract::default());
+        tracker.track(2, &Contract::default());
+        tracker.update(&computation(1, TickType::ModelOption, 0.2, 0.5));
+        thread::sleep(Duration::from_millis(20));
+        tracker.update(&computation(2, TickType::ModelOption, 0.3, 0.4));
+
+        let ticker = tracker.ticker(1).unwrap();
+        assert!(ticker.updated(GreekSource::Model).is_some());
+        assert!(!ticker.is_stale(GreekSource::Model, Duration::from_secs(60)));
+        // a source never reported is stale
+        assert!(ticker.is_stale(GreekSource::Bid, Duration::from_secs(60)));
+
+        assert_eq!(
+            vec![1],
+            tracker.stale(GreekSource::Model, Duration::from_millis(10))
+        );
+        assert_eq!(
+            vec![1, 2],
+            tracker.stale(GreekSource::Last, Duration::from_secs(60))
+        );
+    }
+}
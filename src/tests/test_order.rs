@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::order::{oca_group, OcaType, Order};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_oca_group() {
+        let mut orders = vec![Order::default(), Order::default(), Order::default()];
+        orders[1].transmit = false;
+        oca_group(&mut orders, "Bracket_7", OcaType::ReduceNonBlock);
+
+        for order in &orders {
+            assert_eq!("Bracket_7", order.oca_group);
+            assert_eq!(3, order.oca_type);
+        }
+        // transmit is left as it was
+        let transmit: Vec<bool> = orders.iter().map(|order| order.transmit).collect();
+        assert_eq!(vec![true, false, true], transmit);
+
+        let mut single = vec![Order::default()];
+        oca_group(&mut single, "Single", OcaType::CancelWithBlock);
+        assert_eq!(1, single[0].oca_type);
+        assert!(single[0].transmit);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_cash_qty() {
+        let order = Order {
+            total_quantity: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(UNSET_DOUBLE, order.cash_qty);
+        assert!(!order.has_cash_qty());
+
+        let order = order.with_cash_qty(2500.0);
+        assert!(order.has_cash_qty());
+        assert_eq!(0.0, order.total_quantity);
+        assert!(!order.with_cash_qty(0.0).has_cash_qty());
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests {
+    use num_traits::FromPrimitive;
+
+    use crate::core::errors::{IBKRApiLibError, TwsError};
+    use crate::core::messages::FieldIterator;
+    use crate::core::order::Order;
+    use crate::core::order_condition::{
+        create_condition, Condition, ConditionBuilder, ConditionType, OrderConditionEnum,
+        PriceCondition, TriggerMethod, VolumeCondition,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    /// Encodes conditions the way PlaceOrder does, type first
+    fn encode(conditions: &[OrderConditionEnum]) -> Result<String, IBKRApiLibError> {
+        let mut payload = String::new();
+        for condition in conditions {
+            payload.push_str(&format!("{}\0", condition.get_type() as i32));
+            payload.push_str(&condition.make_fields()?.concat());
+        }
+        Ok(payload)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Decodes conditions the way OpenOrder does
+    fn decode(payload: &str, count: usize) -> Result<Vec<OrderConditionEnum>, IBKRApiLibError> {
+        let mut fields = FieldIterator::new(payload);
+        let mut conditions = Vec::new();
+        for _ in 0..count {
+            let type_code: i32 = fields.next().unwrap().parse()?;
+            let mut condition = create_condition(FromPrimitive::from_i32(type_code).unwrap());
+            condition.decode(&mut fields)?;
+            conditions.push(condition);
+        }
+        assert_eq!(None, fields.next());
+        Ok(conditions)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_wire_format() -> Result<(), IBKRApiLibError> {
+        let conditions = ConditionBuilder::new()
+            .price(265598, "SMART", true, 200.5, TriggerMethod::Last)
+            .or()
+            .time(false, "20240105 15:00:00 US/Eastern")
+            .margin(true, 30.0)
+            .execution("EUR", "CASH", "IDEALPRO")
+            .volume(265598, "ISLAND", false, 100000)
+            .percent_change(265598, "SMART", true, 2.5)
+            .conditions()
+            .to_vec();
+
+        let payload = encode(&conditions)?;
+        let fields: Vec<&str> = FieldIterator::new(&payload).collect();
+        let expected: Vec<&str> = [
+            &["1", "o", "1", "200.5", "265598", "SMART", "2"][..],
+            &["3", "a", "0", "20240105 15:00:00 US/Eastern"],
+            &["4", "a", "1", "30"],
+            &["5", "a", "CASH", "IDEALPRO", "EUR"],
+            &["6", "a", "0", "100000", "265598", "ISLAND"],
+            &["7", "a", "1", "2.5", "265598", "SMART"],
+        ]
+        .concat();
+        assert_eq!(expected, fields);
+
+        let decoded = decode(&payload, conditions.len())?;
+        assert_eq!(payload, encode(&decoded)?);
+
+        let price: PriceCondition = decoded[0].clone().into();
+        assert_eq!(265598, price.contract_condition.con_id);
+        assert_eq!("SMART", price.contract_condition.exchange);
+        assert_eq!(200.5, price.price);
+        assert!(price.contract_condition.operator_condition.is_more);
+        assert_eq!(TriggerMethod::Last as i32, price.trigger_method as i32);
+        assert!(!decoded[0].is_conjunction());
+
+        let volume: VolumeCondition = decoded[4].clone().into();
+        assert_eq!(100000, volume.volume);
+        assert_eq!("ISLAND", volume.contract_condition.exchange);
+        assert!(!volume.contract_condition.operator_condition.is_more);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_unknown_trigger_method_is_a_bad_message() {
+        let payload = ["1", "o", "1", "200.5", "265598", "SMART", "99", ""].join("\0");
+        match decode(&payload, 1) {
+            Err(IBKRApiLibError::ApiError(err)) => {
+                assert_eq!(TwsError::BadMessage.code().to_string(), err.code)
+            }
+            _ => panic!("expected a bad message error"),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_conversions() {
+        let volume: VolumeCondition = create_condition(ConditionType::Volume).into();
+        assert_eq!(6, volume.get_type() as i32);
+
+        // converting from another type gives a default condition of the requested type
+        let price: PriceCondition = OrderConditionEnum::from(volume).into();
+        assert_eq!(1, price.get_type() as i32);
+        assert_eq!(0, price.contract_condition.con_id);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_builder_apply() {
+        let builder = ConditionBuilder::new()
+            .or()
+            .margin(false, 10.0)
+            .time(true, "20240105 09:30:00")
+            .or()
+            .and()
+            .volume(1, "SMART", true, 500)
+            .or()
+            .ignore_rth(true)
+            .cancel_order(true);
+        let connectors: Vec<bool> = builder
+            .conditions()
+            .iter()
+            .map(|condition| condition.is_conjunction())
+            .collect();
+        assert_eq!(vec![true, true, false], connectors);
+
+        let mut order = Order::default();
+        builder.apply(&mut order);
+        assert_eq!(3, order.conditions.len());
+        assert!(order.conditions_ignore_rth);
+        assert!(order.conditions_cancel_order);
+
+        ConditionBuilder::new().apply(&mut order);
+        assert!(order.conditions.is_empty());
+        assert!(!order.conditions_ignore_rth);
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{OrderStatusKind, WhyHeld};
+    use crate::core::order_ids::OrderIdSequencer;
+    use crate::tests::block_on;
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::Submitted,
+            filled: 0.0,
+            remaining: 0.0,
+            avg_fill_price: 0.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_next_order_id_waits_for_next_valid_id() {
+        let sequencer = OrderIdSequencer::new();
+        assert_eq!(None, sequencer.try_next_order_id());
+
+        let waiting = sequencer.clone();
+        let handle = thread::spawn(move || block_on(waiting.next_order_id()));
+        thread::sleep(Duration::from_millis(20));
+        sequencer.observe(&ServerRspMsg::NextValidId { order_id: 100 });
+
+        assert_eq!(100, handle.join().unwrap());
+        assert_eq!(101..104, block_on(sequencer.reserve(3)));
+        assert_eq!(
+            104..105,
+            sequencer
+                .reserve_blocking(1, Duration::from_millis(10))
+                .unwrap()
+        );
+        assert!(OrderIdSequencer::new()
+            .reserve_blocking(1, Duration::from_millis(10))
+            .is_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_id_collisions() {
+        let sequencer = OrderIdSequencer::new();
+        sequencer.observe(&ServerRspMsg::NextValidId { order_id: 10 });
+        assert_eq!(Some(10), sequencer.try_next_order_id());
+
+        // a reconnect must not hand out ids that are already used
+        sequencer.observe(&ServerRspMsg::NextValidId { order_id: 5 });
+        assert_eq!(Some(11), sequencer.peek());
+
+        sequencer.observe(&order_status(20));
+        assert_eq!(Some(21), sequencer.try_next_order_id());
+
+        sequencer.observe(&ServerRspMsg::ErrMsg {
+            req_id: 30,
+            error_code: 103,
+            error_str: "Duplicate order id".to_string(),
+        });
+        assert_eq!(vec![30], sequencer.collisions());
+        assert_eq!(Some(31), sequencer.peek());
+    }
+}
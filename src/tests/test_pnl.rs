@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerRspMsg};
+    use crate::core::pnl::{PnlUpdate, PortfolioPnl};
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn single(req_id: i32, pos: i32, daily_pnl: f64, value: f64) -> ServerRspMsg {
+        ServerRspMsg::PnlSingle {
+            req_id,
+            pos,
+            daily_pnl,
+            unrealized_pnl: 10.0,
+            realized_pnl: UNSET_DOUBLE,
+            value,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_stream() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let req_id = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            ServerRspMsg::Pnl {
+                req_id: req_id + 1,
+                daily_pnl: 1.0,
+                unrealized_pnl: 1.0,
+                realized_pnl: 1.0,
+            },
+            ServerRspMsg::Pnl {
+                req_id,
+                daily_pnl: 25.5,
+                unrealized_pnl: UNSET_DOUBLE,
+                realized_pnl: 3.0,
+            },
+        ] {
+            events.send(msg).unwrap();
+        }
+
+        let updates: Vec<PnlUpdate> = client
+            .subscribe_pnl("DU1", "")?
+            .with_timeout(Duration::from_millis(50))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(1, updates.len());
+        assert_eq!("DU1", updates[0].account);
+        assert_eq!(None, updates[0].con_id);
+        assert_eq!(Some(25.5), updates[0].daily_pnl);
+        assert_eq!(None, updates[0].unrealized_pnl);
+        // the update of another request is left for the caller
+        assert!(client.get_event()?.is_some());
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, rest) = read_msg(&buf)?;
+        assert_eq!(
+            vec![req_id.to_string(), "DU1".to_string()],
+            read_fields(&request)[1..3].to_vec()
+        );
+        // the subscription is cancelled when the stream is dropped
+        let (_, cancel, _) = read_msg(&rest)?;
+        assert_eq!("93", read_fields(&cancel)[0]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_portfolio_pnl() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let first = client.req_id_allocator().peek();
+        let events = client.evt_chan.0.clone();
+        for msg in [
+            single(first, 100, 5.0, 1000.0),
+            single(first + 1, -50, -2.0, UNSET_DOUBLE),
+            single(first, 100, 7.0, 1010.0),
+            ServerRspMsg::ErrMsg {
+                req_id: first + 1,
+                error_code: 200,
+                error_str: "No security definition".to_string(),
+            },
+        ] {
+            events.send(msg).unwrap();
+        }
+
+        let mut portfolio = PortfolioPnl::new();
+        let mut stream = client
+            .subscribe_pnl_single("DU1", "", 265598)?
+            .with_timeout(Duration::from_millis(50));
+        stream.add_position("DU1", "", 8314)?;
+        let mut errors = 0;
+        for update in &mut stream {
+            match update {
+                Ok(update) => assert!(portfolio.update(&update)),
+                Err(_) => errors += 1,
+            }
+        }
+        assert_eq!(1, errors);
+        assert!(stream.next().is_none());
+        drop(stream);
+
+        assert_eq!(2, portfolio.positions().count());
+        assert_eq!(Some(-50), portfolio.position(8314).unwrap().position);
+        assert_eq!(5.0, portfolio.daily_pnl());
+        assert_eq!(20.0, portfolio.unrealized_pnl());
+        assert_eq!(0.0, portfolio.realized_pnl());
+        assert_eq!(1010.0, portfolio.value());
+        assert!(!portfolio.update(&PnlUpdate::default()));
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::{TickAttribLast, TickByTickData};
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{Order, OrderState, OrderStatusKind, WhyHeld};
+    use crate::core::price_ladder::{LadderLevel, LadderUpdate, PriceLadder};
+
+    //------------------------------------------------------------------------------------------------
+    fn depth(position: i32, operation: i32, side: i32, price: f64, size: i32) -> ServerRspMsg {
+        ServerRspMsg::MarketDepth {
+            req_id: 1,
+            position,
+            operation,
+            side,
+            price,
+            size,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(price: f64, size: i32) -> ServerRspMsg {
+        ServerRspMsg::TickByTick {
+            req_id: 2,
+            time: 0,
+            tick: TickByTickData::Last {
+                all_last: true,
+                price,
+                size,
+                tick_attrib_last: TickAttribLast::default(),
+                exchange: "".to_string(),
+                special_conditions: "".to_string(),
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn open_order(order_id: i32, action: &str, price: f64, quantity: f64) -> ServerRspMsg {
+        let mut contract = Contract::default();
+        contract.con_id = 42;
+        let mut order = Order::default();
+        order.action = action.to_string();
+        order.order_type = "LMT".to_string();
+        order.lmt_price = price;
+        order.total_quantity = quantity;
+        ServerRspMsg::OpenOrder {
+            order_id,
+            contract,
+            order,
+            order_state: OrderState::default(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str, remaining: f64) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled: 0.0,
+            remaining,
+            avg_fill_price: 0.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn level(updates: &[LadderUpdate], index: usize) -> &LadderLevel {
+        match &updates[index] {
+            LadderUpdate::Level(level) => level,
+            other => panic!("expected a level, got {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_price_ladder_depth() {
+        let mut ladder = PriceLadder::new(42, 1, 2, 0.01);
+
+        let updates = ladder.observe(&depth(0, 0, 1, 99.99, 300));
+        assert_eq!(1, updates.len());
+        assert_eq!(300.0, level(&updates, 0).bid_size);
+        ladder.observe(&depth(0, 0, 0, 100.01, 200));
+        assert_eq!(Some(99.99), ladder.best_bid());
+        assert_eq!(Some(100.01), ladder.best_ask());
+
+        // the best bid moves up: the old level is cleared and the new one appears
+        let updates = ladder.observe(&depth(0, 1, 1, 100.0, 500));
+        assert_eq!(2, updates.len());
+        assert_eq!(0.0, level(&updates, 0).bid_size);
+        assert!(level(&updates, 0).is_empty());
+        assert_eq!(500.0, level(&updates, 1).bid_size);
+
+        // an unchanged level is not reported again
+        assert!(ladder.observe(&depth(0, 1, 1, 100.0, 500)).is_empty());
+        // other subscriptions are ignored
+        assert!(ladder
+            .observe(&ServerRspMsg::MarketDepth {
+                req_id: 9,
+                position: 0,
+                operation: 0,
+                side: 1,
+                price: 1.0,
+                size: 1,
+            })
+            .is_empty());
+
+        let updates = ladder.observe(&depth(0, 2, 0, 100.01, 0));
+        assert!(level(&updates, 0).is_empty());
+        assert_eq!(1, ladder.levels().len());
+        assert_eq!(None, ladder.best_ask());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_price_ladder_trades_and_orders() {
+        let mut ladder = PriceLadder::new(42, 1, 2, 0.01);
+        ladder.observe(&depth(0, 0, 1, 100.0, 500));
+
+        let updates = ladder.observe(&trade(100.0, 100));
+        assert_eq!(LadderUpdate::LastPrice(100.0), updates[0]);
+        assert_eq!(100.0, level(&updates, 1).traded_volume);
+        let updates = ladder.observe(&trade(100.0, 50));
+        assert_eq!(1, updates.len());
+        assert_eq!(150.0, level(&updates, 0).traded_volume);
+        assert_eq!(Some(100.0), ladder.last_price());
+
+        let updates = ladder.observe(&open_order(7, "BUY", 100.0, 20.0));
+        assert_eq!(20.0, level(&updates, 0).working_buy);
+        ladder.observe(&open_order(8, "SELL", 100.05, 10.0));
+        // orders for other contracts are ignored
+        let mut other = open_order(9, "SELL", 100.05, 10.0);
+        if let ServerRspMsg::OpenOrder { contract, .. } = &mut other {
+            contract.con_id = 43;
+        }
+        assert!(ladder.observe(&other).is_empty());
+
+        let updates = ladder.observe(&order_status(7, "Submitted", 5.0));
+        assert_eq!(5.0, level(&updates, 0).working_buy);
+        // a modified order moves to its new price
+        let updates = ladder.observe(&open_order(8, "SELL", 100.04, 10.0));
+        assert_eq!(2, updates.len());
+        assert_eq!(10.0, level(&updates, 0).working_sell);
+        assert!(level(&updates, 1).is_empty());
+
+        let updates = ladder.observe(&order_status(7, "Filled", 0.0));
+        assert_eq!(0.0, level(&updates, 0).working_buy);
+        assert_eq!(150.0, level(&updates, 0).traded_volume);
+
+        let window = ladder.window(100.02, 5);
+        assert_eq!(5, window.len());
+        assert!((window[0].price - 100.04).abs() < 1e-9);
+        assert_eq!(10.0, window[0].working_sell);
+        assert_eq!(500.0, window[4].bid_size);
+
+        let updates = ladder.reset_volume();
+        assert_eq!(1, updates.len());
+        assert_eq!(0.0, level(&updates, 0).traded_volume);
+        assert_eq!(2, ladder.levels().len());
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::client::EClient;
+    use crate::core::common::PriceIncrement;
+    use crate::core::contract::ContractDetails;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::pricing::{market_rule_id_for, PriceRule, PriceRules, PriceSide};
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    /// Cent ticks up to 1, then 5 cent ticks up to 10, then 10 cent ticks
+    fn banded_rule() -> PriceRule {
+        PriceRule::new(
+            26,
+            vec![
+                PriceIncrement::new(10.0, 0.1),
+                PriceIncrement::new(0.0, 0.01),
+                PriceIncrement::new(1.0, 0.05),
+            ],
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_min_increment_at() {
+        let rule = banded_rule();
+        assert_eq!(Some(0.01), rule.min_increment_at(0.5));
+        assert_eq!(Some(0.05), rule.min_increment_at(1.0));
+        assert_eq!(Some(0.1), rule.min_increment_at(250.0));
+        assert_eq!(Some(0.05), rule.min_increment_at(-3.0));
+        assert_eq!(None, PriceRule::new(1, vec![]).min_increment_at(3.0));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_round_to_valid_tick() {
+        let rule = banded_rule();
+        assert_eq!(0.57, rule.round_to_valid_tick(0.5789, PriceSide::Buy));
+        assert_eq!(0.58, rule.round_to_valid_tick(0.5711, PriceSide::Sell));
+        assert_eq!(1.35, rule.round_to_valid_tick(1.37, PriceSide::Buy));
+        assert_eq!(1.4, rule.round_to_valid_tick(1.37, PriceSide::Sell));
+        // prices already on a tick are kept despite floating point noise
+        assert_eq!(1.15, rule.round_to_valid_tick(0.1 * 11.5, PriceSide::Sell));
+        assert_eq!(12.3, rule.round_to_valid_tick(12.3, PriceSide::Buy));
+        // rounding up stops at the low edge of the next band
+        assert_eq!(10.0, rule.round_to_valid_tick(9.99, PriceSide::Sell));
+        assert_eq!(9.95, rule.round_to_valid_tick(9.99, PriceSide::Buy));
+        // a buy of a negative price still rounds down
+        assert_eq!(-1.4, rule.round_to_valid_tick(-1.37, PriceSide::Buy));
+        assert_eq!(
+            3.333,
+            PriceRule::default().round_to_valid_tick(3.333, PriceSide::Buy)
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_market_rule_id_for() {
+        let mut details = ContractDetails::default();
+        details.valid_exchanges = "SMART,AMEX,NYSE,ISLAND".to_string();
+        details.market_rule_ids = "26,26,239,26".to_string();
+        assert_eq!(Some(239), market_rule_id_for(&details, "nyse"));
+        assert_eq!(Some(26), market_rule_id_for(&details, "SMART"));
+        assert_eq!(None, market_rule_id_for(&details, "ARCA"));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fetch_caches_rules() -> Result<(), IBKRApiLibError> {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        let events = client.evt_chan.0.clone();
+        for market_rule_id in &[239, 26] {
+            events
+                .send(ServerRspMsg::MarketRule {
+                    market_rule_id: *market_rule_id,
+                    price_increments: vec![PriceIncrement::new(0.0, 0.01)],
+                })
+                .unwrap();
+        }
+
+        let mut rules = PriceRules::new();
+        let rule = rules.fetch(&mut client, 26, Duration::from_millis(100))?;
+        assert_eq!(26, rule.market_rule_id);
+        // cached, so no request and no wait
+        rules.fetch(&mut client, 26, Duration::from_millis(0))?;
+        assert_eq!(
+            Some(1.23),
+            rules.round_to_valid_tick(26, 1.234, PriceSide::Buy)
+        );
+        assert_eq!(None, rules.min_increment_at(239, 1.0));
+
+        // the other rule was left for the application
+        match client.get_event()? {
+            Some(msg) => assert!(rules.update(&msg)),
+            None => panic!("missing market rule"),
+        }
+        assert_eq!(Some(0.01), rules.min_increment_at(239, 1.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    use crate::core::client::EClientConfig;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::proxy::{Proxy, ProxyCredentials};
+
+    //------------------------------------------------------------------------------------------------
+    /// Accepts one connection, answers each expected request with the given reply, then sends
+    /// "hello" through the tunnel.  Returns everything the client sent.
+    fn fake_proxy(exchanges: Vec<(usize, Vec<u8>)>) -> (String, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            for (len, reply) in exchanges {
+                let mut buf = vec![0u8; len];
+                stream.read_exact(&mut buf).unwrap();
+                received.extend_from_slice(&buf);
+                stream.write_all(&reply).unwrap();
+            }
+            let _ = stream.write_all(b"hello");
+            received
+        });
+        (address, handle)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn read_hello(stream: &mut TcpStream) -> Vec<u8> {
+        let mut hello = [0u8; 5];
+        stream.read_exact(&mut hello).unwrap();
+        hello.to_vec()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_socks5() -> Result<(), IBKRApiLibError> {
+        let host = "gateway.internal";
+        let request_len = 4 + 1 + host.len() + 2;
+        let (address, proxy) = fake_proxy(vec![
+            (3, vec![5, 2]),
+            (13, vec![1, 0]),
+            (request_len, vec![5, 0, 0, 1, 10, 0, 0, 1, 0x0f, 0xa2]),
+        ]);
+        let mut stream = Proxy::socks5(&address)
+            .credentials("user", "secret")
+            .connect(host, 4002)?;
+        assert_eq!(b"hello".to_vec(), read_hello(&mut stream));
+        assert_eq!(None, stream.read_timeout()?);
+        assert_eq!(None, stream.write_timeout()?);
+
+        let mut expected = vec![5, 1, 2, 1, 4];
+        expected.extend_from_slice(b"user");
+        expected.push(6);
+        expected.extend_from_slice(b"secret");
+        expected.extend_from_slice(&[5, 1, 0, 3, host.len() as u8]);
+        expected.extend_from_slice(host.as_bytes());
+        expected.extend_from_slice(&4002u16.to_be_bytes());
+        assert_eq!(expected, proxy.join().unwrap());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_silent_proxy_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // accepts and never answers the greeting, keeping the connection open until joined
+        let proxy = thread::spawn(move || listener.accept().unwrap().0);
+
+        let start = Instant::now();
+        let result =
+            Proxy::socks5(&address).connect_timeout("10.0.0.7", 4002, Duration::from_millis(200));
+        match result {
+            Err(IBKRApiLibError::Io(e)) => assert!(
+                matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+                "{:?}",
+                e
+            ),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(proxy.join().unwrap());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_socks5_refused() {
+        let (address, proxy) = fake_proxy(vec![
+            (3, vec![5, 0]),
+            (10, vec![5, 5, 0, 1, 0, 0, 0, 0, 0, 0]),
+        ]);
+        let result = Proxy::socks5(&address).connect("10.0.0.7", 4002);
+        match result {
+            Err(IBKRApiLibError::Io(err)) => {
+                assert_eq!(ErrorKind::ConnectionRefused, err.kind());
+                assert!(err.to_string().contains("connection refused"));
+            }
+            other => panic!("expected a refused connection, got {:?}", other.map(|_| ())),
+        }
+        // an IPv4 target is sent as an address, not a name
+        assert_eq!(
+            vec![5, 1, 0, 5, 1, 0, 1, 10, 0, 0, 7, 0x0f, 0xa2],
+            proxy.join().unwrap()
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_http_connect() -> Result<(), IBKRApiLibError> {
+        let request = "CONNECT 10.0.0.7:4002 HTTP/1.1\r\nHost: 10.0.0.7:4002\r\n\
+                       Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n\r\n";
+        let (address, proxy) = fake_proxy(vec![(
+            request.len(),
+            b"HTTP/1.1 200 Connection established\r\n\r\n".to_vec(),
+        )]);
+        let mut stream = Proxy::http(&address)
+            .credentials("user", "secret")
+            .connect("10.0.0.7", 4002)?;
+        assert_eq!(b"hello".to_vec(), read_hello(&mut stream));
+        assert_eq!(request.as_bytes(), proxy.join().unwrap().as_slice());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_http_connect_denied() {
+        let request = "CONNECT 10.0.0.7:4002 HTTP/1.1\r\nHost: 10.0.0.7:4002\r\n\r\n";
+        let (address, proxy) = fake_proxy(vec![(
+            request.len(),
+            b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n".to_vec(),
+        )]);
+        match Proxy::http(&address).connect("10.0.0.7", 4002) {
+            Err(IBKRApiLibError::Io(err)) => {
+                assert_eq!(ErrorKind::PermissionDenied, err.kind());
+                assert!(err.to_string().contains("407"));
+            }
+            other => panic!("expected a denied connection, got {:?}", other.map(|_| ())),
+        }
+        proxy.join().unwrap();
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_config() {
+        let config =
+            EClientConfig::default().proxy(Proxy::socks5("bastion:1080").credentials("a", "b"));
+        assert_eq!(
+            Some(Proxy::Socks5 {
+                address: "bastion:1080".to_string(),
+                credentials: Some(ProxyCredentials {
+                    username: "a".to_string(),
+                    password: "b".to_string(),
+                }),
+            }),
+            config.proxy
+        );
+        assert!(matches!(
+            Proxy::http("bastion:3128").connect("gateway", 70000),
+            Err(IBKRApiLibError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::client::{EClient, EClientConfig};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::req_ids::{CorrelationRegistry, ReqIdAllocator, RequestKind};
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    fn summary(req_id: i32) -> ServerRspMsg {
+        ServerRspMsg::AccountSummary {
+            req_id,
+            account: "DU123".to_string(),
+            tag: "NetLiquidation".to_string(),
+            value: "1000".to_string(),
+            currency: "USD".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_req_id_allocator() {
+        let allocator = ReqIdAllocator::new(100);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let allocator = allocator.clone();
+                thread::spawn(move || (0..50).map(|_| allocator.next()).collect::<Vec<i32>>())
+            })
+            .collect();
+        let mut ids: Vec<i32> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(200, ids.len());
+        assert_eq!(100, ids[0]);
+
+        assert_eq!(300, allocator.next_block(10));
+        assert_eq!(310, allocator.peek());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_correlation_registry() {
+        let registry = CorrelationRegistry::new();
+        registry.register(1, RequestKind::ContractDetails, "AAPL");
+        registry.register(2, RequestKind::HistoricalData, "MSFT");
+
+        let event = registry
+            .enrich(ServerRspMsg::ContractDataEnd { req_id: 1 })
+            .unwrap();
+        assert_eq!(RequestKind::ContractDetails, event.context.unwrap().kind);
+        assert!(registry.context(1).is_none());
+
+        // errors that fail a request end it, notices do not
+        let notice = ServerRspMsg::ErrMsg {
+            req_id: 2,
+            error_code: 2104,
+            error_str: "".to_string(),
+        };
+        assert!(registry.enrich(notice).unwrap().context.is_some());
+        assert!(registry.context(2).is_some());
+
+        // positions multi keeps streaming after the end of its snapshot
+        registry.register(3, RequestKind::PositionsMulti, "DU123");
+        registry.enrich(ServerRspMsg::PositionMultiEnd { req_id: 3 });
+        assert!(registry.context(3).is_some());
+        registry.cancel(3);
+
+        registry.cancel(2);
+        assert!(registry.is_stale(2));
+        assert!(registry.active().is_empty());
+        assert!(registry.enrich(summary(2)).is_none());
+
+        registry.prune(Duration::from_secs(0));
+        assert!(registry.enrich(summary(2)).unwrap().context.is_none());
+
+        let unrelated = registry.enrich(ServerRspMsg::NextValidId { order_id: 2 });
+        assert!(unrelated.unwrap().context.is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_enriched_events() {
+        let mut client = EClient::with_config(EClientConfig::default().first_req_id(1000));
+        connect_test(&mut client);
+
+        let summary_id = client.next_req_id(RequestKind::AccountSummary, "All");
+        let other_id = client.next_req_id(RequestKind::Other("custom".to_string()), "");
+        assert_eq!(1000, summary_id);
+        assert_eq!(1002, client.req_id_allocator().peek());
+
+        client.evt_chan.0.send(summary(summary_id)).unwrap();
+        client.evt_chan.0.send(summary(other_id)).unwrap();
+        let event = client.get_enriched_event().unwrap().unwrap();
+        let context = event.context.unwrap();
+        assert_eq!(RequestKind::AccountSummary, context.kind);
+        assert_eq!("All", context.description);
+
+        client.cancel_account_summary(other_id).unwrap();
+        client.evt_chan.0.send(summary(summary_id)).unwrap();
+        let event = client.get_enriched_event().unwrap().unwrap();
+        assert_eq!(Some(summary_id), event.msg.req_id());
+        assert!(client.get_enriched_event().unwrap().is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_failed_cancel_keeps_request() {
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        // too old for PnL requests
+        client.server_version = 120;
+
+        let req_id = client.next_req_id(RequestKind::Pnl, "DU123");
+        assert!(client.cancel_pnl(req_id).is_err());
+        assert!(!client.correlations().is_stale(req_id));
+        assert_eq!(1, client.correlations().active().len());
+    }
+}
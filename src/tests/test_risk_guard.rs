@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::account_values::{AccountKey, AccountValue};
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::Order;
+    use crate::core::risk_guard::{ExposureGroup, PortfolioLimits, RiskGuard, RiskViolation};
+    use crate::core::units::{Notional, Percent, Price};
+
+    //------------------------------------------------------------------------------------------------
+    fn stock(con_id: i32, symbol: &str, currency: &str) -> Contract {
+        let mut contract = Contract::default();
+        contract.con_id = con_id;
+        contract.symbol = symbol.to_string();
+        contract.currency = currency.to_string();
+        contract
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order(action: &str, quantity: f64) -> Order {
+        let mut order = Order::default();
+        order.action = action.to_string();
+        order.total_quantity = quantity;
+        order
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn portfolio_value(contract: Contract, position: f64, market_price: f64) -> ServerRspMsg {
+        ServerRspMsg::PortfolioValue {
+            contract,
+            position,
+            market_price,
+            market_value: position * market_price,
+            average_cost: market_price,
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+            account_name: "DU123".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn guard(limits: PortfolioLimits) -> RiskGuard {
+        let mut guard = RiskGuard::new(limits, "USD");
+        guard.set_fx_rate("EUR", 1.1);
+        guard.observe(&portfolio_value(stock(1, "AAPL", "USD"), 100.0, 100.0));
+        guard.observe(&portfolio_value(stock(2, "SAP", "EUR"), -100.0, 100.0));
+        guard.observe(&ServerRspMsg::AcctValue {
+            key: AccountKey::NetLiquidation,
+            value: AccountValue::parse("100000", "USD"),
+            account_name: "DU123".to_string(),
+        });
+        guard
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_gross_and_net_limits() {
+        // gross 10000 + 11000, net 1000 short
+        let guard = guard(PortfolioLimits {
+            max_gross: Some(Notional::new(30000.0)),
+            max_net: Some(Notional::new(2000.0)),
+            ..Default::default()
+        });
+        let aapl = stock(1, "AAPL", "USD");
+
+        assert!(guard.check(&aapl, &order("BUY", 20.0), None).is_ok());
+        assert_eq!(
+            vec![RiskViolation::Net {
+                exposure: Notional::new(4000.0),
+                limit: Notional::new(2000.0),
+            }],
+            guard.violations(&aapl, &order("BUY", 50.0), None)
+        );
+        let violations = guard.violations(&aapl, &order("BUY", 50.0), Some(Price::new(200.0)));
+        assert_eq!(2, violations.len());
+        assert!(guard.check(&aapl, &order("BUY", 50.0), None).is_err());
+
+        // reducing the long lowers gross exposure and moves net towards the short
+        assert!(guard.check(&aapl, &order("SELL", 10.0), None).is_ok());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_concentration_and_group_limits() {
+        let mut sectors = HashMap::new();
+        sectors.insert("AAPL".to_string(), "Tech".to_string());
+        sectors.insert("MSFT".to_string(), "Tech".to_string());
+        let guard = guard(PortfolioLimits {
+            max_symbol_concentration: Some(Percent::new(15.0)),
+            max_sector_concentration: Some(Percent::new(20.0)),
+            sectors,
+            groups: vec![ExposureGroup {
+                name: "megacaps".to_string(),
+                symbols: vec!["AAPL".to_string(), "MSFT".to_string()],
+                max_exposure: Notional::new(18000.0),
+            }],
+            ..Default::default()
+        });
+        let msft = stock(3, "MSFT", "USD");
+
+        assert!(guard
+            .check(&msft, &order("BUY", 20.0), Some(Price::new(300.0)))
+            .is_ok());
+        let violations = guard.violations(&msft, &order("BUY", 40.0), Some(Price::new(300.0)));
+        assert_eq!(
+            vec![
+                RiskViolation::Group {
+                    group: "megacaps".to_string(),
+                    exposure: Notional::new(22000.0),
+                    limit: Notional::new(18000.0),
+                },
+                RiskViolation::SectorConcentration {
+                    sector: "Tech".to_string(),
+                    share: Percent::new(22.0),
+                    limit: Percent::new(20.0),
+                },
+            ],
+            violations
+        );
+        assert_eq!(
+            vec![RiskViolation::UnknownPrice {
+                symbol: "MSFT".to_string()
+            }],
+            guard.violations(&msft, &order("BUY", 1.0), None)
+        );
+
+        let mut unknown_fx = RiskGuard::new(PortfolioLimits::default(), "USD");
+        assert!(unknown_fx
+            .check(
+                &stock(4, "VOD", "GBP"),
+                &order("BUY", 1.0),
+                Some(Price::new(1.0))
+            )
+            .is_err());
+        unknown_fx.set_fx_rate("GBP", 1.25);
+        assert!(unknown_fx
+            .check(
+                &stock(4, "VOD", "GBP"),
+                &order("BUY", 1.0),
+                Some(Price::new(1.0))
+            )
+            .is_ok());
+    }
+}
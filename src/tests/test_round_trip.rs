@@ -0,0 +1,670 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use chrono::DateTime;
+    use proptest::prelude::*;
+
+    #[cfg(feature = "marketdata")]
+    use crate::core::client::EClient;
+    #[cfg(feature = "marketdata")]
+    use crate::core::common::TickByTickType;
+    use crate::core::common::{BarData, ConnStatus, TickAttrib, TickType};
+    use crate::core::contract::Contract;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+    #[cfg(feature = "marketdata")]
+    use crate::core::frame_reader::FrameReader;
+    use crate::core::messages::ServerRspMsg;
+    #[cfg(feature = "marketdata")]
+    use crate::core::messages::{read_fields, ServerReqMsgDiscriminants};
+    use crate::core::order::{OrderStatusKind, WhyHeld};
+    use crate::core::server_versions::{
+        MIN_SERVER_VER_MARKET_CAP_PRICE, MIN_SERVER_VER_PRE_OPEN_BID_ASK,
+        MIN_SERVER_VER_REALIZED_PNL, MIN_SERVER_VER_SMART_DEPTH,
+    };
+    #[cfg(feature = "marketdata")]
+    use crate::core::server_versions::{
+        MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE, MIN_SERVER_VER_TICK_BY_TICK,
+        MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE,
+    };
+    use crate::core::test_vectors::VERSION_MATRIX;
+    #[cfg(feature = "marketdata")]
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn text() -> impl Strategy<Value = String> {
+        "[A-Za-z0-9 .:_-]{0,16}"
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn number() -> impl Strategy<Value = f64> {
+        -1.0e9..1.0e9
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn id() -> impl Strategy<Value = i32> {
+        -1..1_000_000
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn contract() -> impl Strategy<Value = Contract> {
+        (
+            0..1_000_000_000,
+            "[A-Z]{1,5}",
+            prop::sample::select(vec!["STK", "OPT", "FUT", "CASH"]),
+            text(),
+            0.0..5000.0,
+            prop::sample::select(vec!["", "C", "P"]),
+            prop::sample::select(vec!["", "100", "50"]),
+            prop::sample::select(vec!["ISLAND", "ARCA", "CME", "IDEALPRO"]),
+            prop::sample::select(vec!["USD", "EUR", "JPY"]),
+            text(),
+        )
+            .prop_map(
+                |(
+                    con_id,
+                    symbol,
+                    sec_type,
+                    expiry,
+                    strike,
+                    right,
+                    multiplier,
+                    exchange,
+                    currency,
+                    trading_class,
+                )| {
+                    Contract {
+                        con_id,
+                        symbol: symbol.clone(),
+                        sec_type: sec_type.to_string(),
+                        last_trade_date_or_contract_month: expiry,
+                        strike,
+                        right: right.to_string(),
+                        multiplier: multiplier.to_string(),
+                        exchange: exchange.to_string(),
+                        currency: currency.to_string(),
+                        local_symbol: symbol,
+                        trading_class,
+                        ..Default::default()
+                    }
+                },
+            )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn response() -> impl Strategy<Value = ServerRspMsg> {
+        let price_types = vec![
+            TickType::Bid,
+            TickType::Ask,
+            TickType::Last,
+            TickType::High,
+            TickType::Low,
+            TickType::Close,
+        ];
+        let size_types = vec![
+            TickType::BidSize,
+            TickType::AskSize,
+            TickType::LastSize,
+            TickType::Volume,
+        ];
+        let string_types = vec![
+            TickType::LastTimestamp,
+            TickType::RtVolume,
+            TickType::RtTrdVolume,
+        ];
+        prop_oneof![
+            (
+                id(),
+                prop::sample::select(price_types),
+                number(),
+                any::<(bool, bool, bool)>()
+            )
+                .prop_map(|(req_id, tick_type, price, (auto, past, pre))| {
+                    ServerRspMsg::TickPrice {
+                        req_id,
+                        tick_type,
+                        price,
+                        tick_attr: TickAttrib::new(auto, past, pre),
+                    }
+                }),
+            (id(), prop::sample::select(size_types), 0..1_000_000).prop_map(
+                |(req_id, tick_type, size)| ServerRspMsg::TickSize {
+                    req_id,
+                    tick_type,
+                    size,
+                }
+            ),
+            (id(), prop::sample::select(string_types), text()).prop_map(
+                |(req_id, tick_type, value)| ServerRspMsg::TickString {
+                    req_id,
+                    tick_type,
+                    value,
+                }
+            ),
+            (id(), 100..3000, text()).prop_map(|(req_id, error_code, error_str)| {
+                ServerRspMsg::ErrMsg {
+                    req_id,
+                    error_code,
+                    error_str,
+                }
+            }),
+            (
+                id(),
+                prop::sample::select(vec!["Submitted", "Filled", "Cancelled"]),
+                (0.0..1e6, 0.0..1e6, number(), number(), number()),
+                // perm ids past i32::MAX, as recent TWS versions assign them
+                (0..5_000_000_000i64, id(), id()),
+                text()
+            )
+                .prop_map(
+                    |(
+                        order_id,
+                        status,
+                        (filled, remaining, avg, last, cap),
+                        (perm, parent, client),
+                        why,
+                    )| {
+                        ServerRspMsg::OrderStatus {
+                            order_id,
+                            status: OrderStatusKind::from(status),
+                            filled,
+                            remaining,
+                            avg_fill_price: avg,
+                            perm_id: perm,
+                            parent_id: parent,
+                            last_fill_price: last,
+                            client_id: client,
+                            why_held: WhyHeld::from(why.as_str()),
+                            mkt_cap_price: cap,
+                        }
+                    }
+                ),
+            (
+                id(),
+                0..20,
+                text(),
+                0..3,
+                0..2,
+                number(),
+                0..1_000_000,
+                any::<bool>()
+            )
+                .prop_map(
+                    |(req_id, position, market_maker, operation, side, price, size, smart)| {
+                        ServerRspMsg::MarketDepthL2 {
+                            req_id,
+                            position,
+                            market_maker,
+                            operation,
+                            side,
+                            price,
+                            size,
+                            is_smart_depth: smart,
+                        }
+                    }
+                ),
+            (text(), contract(), number(), number()).prop_map(
+                |(account, contract, position, avg_cost)| ServerRspMsg::PositionData {
+                    account,
+                    contract,
+                    position,
+                    avg_cost,
+                }
+            ),
+            (
+                id(),
+                -100_000..100_000,
+                (number(), number(), number(), number())
+            )
+                .prop_map(|(req_id, pos, (daily, unrealized, realized, value))| {
+                    ServerRspMsg::PnlSingle {
+                        req_id,
+                        pos,
+                        daily_pnl: daily,
+                        unrealized_pnl: unrealized,
+                        realized_pnl: realized,
+                        value,
+                    }
+                }),
+        ]
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Encodes a response the way TWS of `server_version` sends it
+    fn encode(msg: &ServerRspMsg, server_version: i32) -> String {
+        let fields: Vec<String> = match msg {
+            ServerRspMsg::TickPrice {
+                req_id,
+                tick_type,
+                price,
+                tick_attr,
+            } => {
+                let attr = tick_attr.can_auto_execute as i32
+                    | (tick_attr.past_limit as i32) << 1
+                    | (tick_attr.pre_open as i32) << 2;
+                vec![
+                    "1".into(),
+                    "6".into(),
+                    req_id.to_string(),
+                    (*tick_type as i32).to_string(),
+                    price.to_string(),
+                    "100".into(),
+                    attr.to_string(),
+                ]
+            }
+            ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size,
+            } => vec![
+                "2".into(),
+                "6".into(),
+                req_id.to_string(),
+                (*tick_type as i32).to_string(),
+                size.to_string(),
+            ],
+            ServerRspMsg::TickString {
+                req_id,
+                tick_type,
+                value,
+            } => vec![
+                "46".into(),
+                "6".into(),
+                req_id.to_string(),
+                (*tick_type as i32).to_string(),
+                value.clone(),
+            ],
+            ServerRspMsg::ErrMsg {
+                req_id,
+                error_code,
+                error_str,
+            } => vec![
+                "4".into(),
+                "2".into(),
+                req_id.to_string(),
+                error_code.to_string(),
+                error_str.clone(),
+            ],
+            ServerRspMsg::OrderStatus {
+                order_id,
+                status,
+                filled,
+                remaining,
+                avg_fill_price,
+                perm_id,
+                parent_id,
+                last_fill_price,
+                client_id,
+                why_held,
+                mkt_cap_price,
+            } => {
+                let mut fields = vec!["3".to_string()];
+                if server_version < MIN_SERVER_VER_MARKET_CAP_PRICE {
+                    fields.push("6".into());
+                }
+                fields.extend(vec![
+                    order_id.to_string(),
+                    status.to_string(),
+                    filled.to_string(),
+                    remaining.to_string(),
+                    avg_fill_price.to_string(),
+                    perm_id.to_string(),
+                    parent_id.to_string(),
+                    last_fill_price.to_string(),
+                    client_id.to_string(),
+                    why_held.to_string(),
+                ]);
+                if server_version >= MIN_SERVER_VER_MARKET_CAP_PRICE {
+                    fields.push(mkt_cap_price.to_string());
+                }
+                fields
+            }
+            ServerRspMsg::MarketDepthL2 {
+                req_id,
+                position,
+                market_maker,
+                operation,
+                side,
+                price,
+                size,
+                is_smart_depth,
+            } => {
+                let mut fields = vec![
+                    "13".to_string(),
+                    "1".into(),
+                    req_id.to_string(),
+                    position.to_string(),
+                    market_maker.clone(),
+                    operation.to_string(),
+                    side.to_string(),
+                    price.to_string(),
+                    size.to_string(),
+                ];
+                if server_version >= MIN_SERVER_VER_SMART_DEPTH {
+                    fields.push((*is_smart_depth as i32).to_string());
+                }
+                fields
+            }
+            ServerRspMsg::PositionData {
+                account,
+                contract,
+                position,
+                avg_cost,
+            } => vec![
+                "61".into(),
+                "3".into(),
+                account.clone(),
+                contract.con_id.to_string(),
+                contract.symbol.clone(),
+                contract.sec_type.clone(),
+                contract.last_trade_date_or_contract_month.clone(),
+                contract.strike.to_string(),
+                contract.right.clone(),
+                contract.multiplier.clone(),
+                contract.exchange.clone(),
+                contract.currency.clone(),
+                contract.local_symbol.clone(),
+                contract.trading_class.clone(),
+                position.to_string(),
+                avg_cost.to_string(),
+            ],
+            ServerRspMsg::PnlSingle {
+                req_id,
+                pos,
+                daily_pnl,
+                unrealized_pnl,
+                realized_pnl,
+                value,
+            } => {
+                let mut fields = vec![
+                    "95".to_string(),
+                    req_id.to_string(),
+                    pos.to_string(),
+                    daily_pnl.to_string(),
+                    unrealized_pnl.to_string(),
+                ];
+                if server_version >= MIN_SERVER_VER_REALIZED_PNL {
+                    fields.push(realized_pnl.to_string());
+                }
+                fields.push(value.to_string());
+                fields
+            }
+            other => panic!("no encoder for {:?}", other),
+        };
+        fields.iter().map(|field| format!("{}\0", field)).collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// The messages the decoder must produce from `msg` encoded for `server_version`: fields the
+    /// version does not send come back as their defaults
+    fn expected(msg: &ServerRspMsg, server_version: i32) -> Vec<ServerRspMsg> {
+        let mut msg = msg.clone();
+        match &mut msg {
+            ServerRspMsg::TickPrice { tick_attr, .. }
+                if server_version < MIN_SERVER_VER_PRE_OPEN_BID_ASK =>
+            {
+                tick_attr.pre_open = false
+            }
+            ServerRspMsg::OrderStatus { mkt_cap_price, .. }
+                if server_version < MIN_SERVER_VER_MARKET_CAP_PRICE =>
+            {
+                *mkt_cap_price = 0.0
+            }
+            ServerRspMsg::MarketDepthL2 { is_smart_depth, .. }
+                if server_version < MIN_SERVER_VER_SMART_DEPTH =>
+            {
+                *is_smart_depth = false
+            }
+            ServerRspMsg::PnlSingle { realized_pnl, .. }
+                if server_version < MIN_SERVER_VER_REALIZED_PNL =>
+            {
+                *realized_pnl = 0.0
+            }
+            _ => (),
+        }
+        // the size that comes with a bid, ask or last price is passed on as a size tick
+        let size = match &msg {
+            ServerRspMsg::TickPrice {
+                req_id, tick_type, ..
+            } => match tick_type {
+                TickType::Bid => Some((*req_id, TickType::BidSize)),
+                TickType::Ask => Some((*req_id, TickType::AskSize)),
+                TickType::Last => Some((*req_id, TickType::LastSize)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let mut msgs = vec![msg];
+        if let Some((req_id, tick_type)) = size {
+            msgs.push(ServerRspMsg::TickSize {
+                req_id,
+                tick_type,
+                size: 100,
+            });
+        }
+        msgs
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn decode(server_version: i32, frame: &str) -> Result<Vec<ServerRspMsg>, IBKRApiLibError> {
+        let (_, msg_queue) = channel::<String>();
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut decoder = Decoder::new(
+            msg_queue,
+            sender,
+            server_version,
+            Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+        );
+        decoder.interpret(frame)?;
+        let mut msgs = Vec::new();
+        while let Ok(msg) = receiver.try_recv() {
+            msgs.push(msg);
+        }
+        Ok(msgs)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn debug(msgs: &[ServerRspMsg]) -> Vec<String> {
+        msgs.iter().map(|msg| format!("{:?}", msg)).collect()
+    }
+
+    proptest! {
+        //--------------------------------------------------------------------------------------------
+        #[test]
+        fn test_response_round_trip(msg in response()) {
+            for server_version in VERSION_MATRIX {
+                let decoded = decode(server_version, &encode(&msg, server_version))?;
+                prop_assert_eq!(
+                    debug(&expected(&msg, server_version)),
+                    debug(&decoded),
+                    "server version {}",
+                    server_version
+                );
+            }
+        }
+
+        //--------------------------------------------------------------------------------------------
+        #[test]
+        fn test_historical_bars_round_trip(
+            req_id in id(),
+            (start, end) in (text(), text()),
+            bars in prop::collection::vec(
+                (
+                    1_000_000_000..4_000_000_000i64,
+                    (number(), number(), number(), number()),
+                    0..i64::MAX,
+                    number(),
+                    0..100_000,
+                ),
+                0..8,
+            ),
+        ) {
+            // dates in epoch seconds, as sent for format_date 2
+            let bars: Vec<(i64, BarData)> = bars
+                .into_iter()
+                .map(|(seconds, (open, high, low, close), volume, average, bar_count)| {
+                    let bar = BarData {
+                        date: DateTime::from_timestamp(seconds, 0),
+                        #[cfg(feature = "raw-times")]
+                        date_raw: seconds.to_string(),
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        bar_count,
+                        average,
+                    };
+                    (seconds, bar)
+                })
+                .collect();
+            let mut frame = format!("17\0{}\0{}\0{}\0{}\0", req_id, start, end, bars.len());
+            for (seconds, bar) in &bars {
+                frame.push_str(&format!(
+                    "{}\0{}\0{}\0{}\0{}\0{}\0{}\0{}\0",
+                    seconds, bar.open, bar.high, bar.low, bar.close, bar.volume, bar.average,
+                    bar.bar_count
+                ));
+            }
+
+            let mut expected: Vec<ServerRspMsg> = bars
+                .into_iter()
+                .map(|(_, bar)| ServerRspMsg::HistoricalData { req_id, bar })
+                .collect();
+            expected.push(ServerRspMsg::HistoricalDataEnd { req_id, start, end });
+            // every server version in the matrix sends bars without the message version
+            for server_version in VERSION_MATRIX {
+                prop_assert_eq!(debug(&expected), debug(&decode(server_version, &frame)?));
+            }
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// The fields of every request the client wrote
+    #[cfg(feature = "marketdata")]
+    fn sent(client: &mut EClient) -> Result<Vec<Vec<String>>, IBKRApiLibError> {
+        let mut frames = FrameReader::new();
+        while frames.read_from(client.stream.as_mut().unwrap())? > 0 {}
+        let mut requests = Vec::new();
+        while let Some(frame) = frames.next_frame()? {
+            requests.push(read_fields(std::str::from_utf8(frame).unwrap()));
+        }
+        Ok(requests)
+    }
+
+    #[cfg(feature = "marketdata")]
+    proptest! {
+        //--------------------------------------------------------------------------------------------
+        #[test]
+        fn test_mkt_depth_request_round_trip(
+            req_id in 1..1_000_000,
+            contract in contract(),
+            primary_exchange in prop::sample::select(vec!["", "NASDAQ"]),
+            num_rows in 1..50,
+            is_smart_depth in any::<bool>(),
+        ) {
+            let contract = Contract {
+                primary_exchange: primary_exchange.to_string(),
+                ..contract
+            };
+            for server_version in VERSION_MATRIX {
+                let mut client = connected_client();
+                client.server_version = server_version;
+                let result = client.req_mkt_depth(req_id, &contract, num_rows, is_smart_depth, vec![]);
+                let supported = (server_version >= MIN_SERVER_VER_SMART_DEPTH || !is_smart_depth)
+                    && (server_version >= MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE
+                        || primary_exchange.is_empty());
+                prop_assert_eq!(supported, result.is_ok(), "server version {}", server_version);
+                let requests = sent(&mut client)?;
+                if !supported {
+                    prop_assert!(requests.is_empty());
+                    continue;
+                }
+
+                // read back in the order TWS reads the fields
+                let fields = &requests[0];
+                let mut fields = fields.iter().map(String::as_str);
+                let mut next = || fields.next().unwrap();
+                prop_assert_eq!(
+                    (ServerReqMsgDiscriminants::ReqMktDepth as i32).to_string(),
+                    next()
+                );
+                prop_assert_eq!("5", next());
+                prop_assert_eq!(req_id.to_string(), next());
+                prop_assert_eq!(contract.con_id.to_string(), next());
+                prop_assert_eq!(contract.symbol.as_str(), next());
+                prop_assert_eq!(contract.sec_type.as_str(), next());
+                prop_assert_eq!(contract.last_trade_date_or_contract_month.as_str(), next());
+                prop_assert_eq!(contract.strike, next().parse::<f64>().unwrap());
+                prop_assert_eq!(contract.right.as_str(), next());
+                prop_assert_eq!(contract.multiplier.as_str(), next());
+                prop_assert_eq!(contract.exchange.as_str(), next());
+                if server_version >= MIN_SERVER_VER_MKT_DEPTH_PRIM_EXCHANGE {
+                    prop_assert_eq!(primary_exchange, next());
+                }
+                prop_assert_eq!(contract.currency.as_str(), next());
+                prop_assert_eq!(contract.local_symbol.as_str(), next());
+                prop_assert_eq!(contract.trading_class.as_str(), next());
+                prop_assert_eq!(num_rows.to_string(), next());
+                if server_version >= MIN_SERVER_VER_SMART_DEPTH {
+                    prop_assert_eq!((is_smart_depth as i32).to_string(), next());
+                }
+                // mkt_depth_options, always empty
+                prop_assert_eq!("", next());
+                prop_assert_eq!(None, fields.next());
+            }
+        }
+
+        //--------------------------------------------------------------------------------------------
+        #[test]
+        fn test_tick_by_tick_request_round_trip(
+            req_id in 1..1_000_000,
+            contract in contract(),
+            number_of_ticks in prop_oneof![Just(0), 1..1000],
+            ignore_size in any::<bool>(),
+        ) {
+            for server_version in VERSION_MATRIX {
+                let mut client = connected_client();
+                client.server_version = server_version;
+                let result = client.req_tick_by_tick_data(
+                    req_id,
+                    &contract,
+                    TickByTickType::BidAsk,
+                    number_of_ticks,
+                    ignore_size,
+                );
+                let supported = server_version >= MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE
+                    || (server_version >= MIN_SERVER_VER_TICK_BY_TICK
+                        && number_of_ticks == 0
+                        && !ignore_size);
+                prop_assert_eq!(supported, result.is_ok(), "server version {}", server_version);
+                let requests = sent(&mut client)?;
+                if !supported {
+                    prop_assert!(requests.is_empty());
+                    continue;
+                }
+
+                let fields = &requests[0];
+                prop_assert_eq!(
+                    (ServerReqMsgDiscriminants::ReqTickByTickData as i32).to_string(),
+                    fields[0].as_str()
+                );
+                prop_assert_eq!(req_id.to_string(), fields[1].as_str());
+                prop_assert_eq!(contract.con_id.to_string(), fields[2].as_str());
+                prop_assert_eq!(contract.symbol.as_str(), fields[3].as_str());
+                prop_assert_eq!(contract.trading_class.as_str(), fields[13].as_str());
+                prop_assert_eq!("BidAsk", fields[14].as_str());
+                if server_version >= MIN_SERVER_VER_TICK_BY_TICK_IGNORE_SIZE {
+                    prop_assert_eq!(number_of_ticks.to_string(), fields[15].as_str());
+                    prop_assert_eq!((ignore_size as i32).to_string(), fields[16].as_str());
+                    prop_assert_eq!(17, fields.len());
+                } else {
+                    prop_assert_eq!(15, fields.len());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::client::EClient;
+    use crate::core::common::{TickAttrib, TickType};
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::event_queue::MessageClass;
+    use crate::core::messages::{ServerRspMsg, ServerRspMsgKind};
+    use crate::core::router::{Route, Router};
+
+    //------------------------------------------------------------------------------------------------
+    fn tick(req_id: i32, price: f64) -> ServerRspMsg {
+        ServerRspMsg::TickPrice {
+            req_id,
+            tick_type: TickType::Last,
+            price,
+            tick_attr: TickAttrib::default(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn error(req_id: i32) -> ServerRspMsg {
+        ServerRspMsg::ErrMsg {
+            req_id,
+            error_code: 200,
+            error_str: "No security definition has been found for the request".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_router() {
+        let mut router = Router::new();
+        let prices = router.subscribe(Route::new().kind(ServerRspMsgKind::TickPrice).req_id(1));
+        let request = router.subscribe(Route::new().req_id(2));
+        let orders = router.subscribe(Route::new().class(MessageClass::Order));
+
+        assert!(router.route(tick(1, 10.0)).is_none());
+        assert!(router.route(tick(2, 20.0)).is_none());
+        assert!(router.route(error(2)).is_none());
+        assert!(router.route(ServerRspMsg::OpenOrderEnd).is_none());
+        // matches no route and there is no fallback
+        assert!(matches!(
+            router.route(tick(3, 30.0)),
+            Some(ServerRspMsg::TickPrice { req_id: 3, .. })
+        ));
+
+        assert!(matches!(
+            prices.try_recv(),
+            Ok(ServerRspMsg::TickPrice { req_id: 1, .. })
+        ));
+        assert!(prices.try_recv().is_err());
+        assert!(matches!(
+            request.try_recv(),
+            Ok(ServerRspMsg::TickPrice { req_id: 2, .. })
+        ));
+        assert!(matches!(
+            request.try_recv(),
+            Ok(ServerRspMsg::ErrMsg { req_id: 2, .. })
+        ));
+        assert!(matches!(orders.try_recv(), Ok(ServerRspMsg::OpenOrderEnd)));
+
+        let unrouted = router.fallback();
+        drop(prices);
+        assert!(router.route(tick(1, 11.0)).is_none());
+        assert_eq!(2, router.route_count());
+        assert!(matches!(
+            unrouted.try_recv(),
+            Ok(ServerRspMsg::TickPrice { req_id: 1, .. })
+        ));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_route_events() -> Result<(), IBKRApiLibError> {
+        let client = EClient::new();
+        let mut router = Router::new();
+        let errors = router.subscribe(Route::new().class(MessageClass::Error));
+        for msg in [tick(1, 10.0), error(1), tick(1, 10.5)] {
+            client.evt_chan.0.send(msg).unwrap();
+        }
+
+        assert_eq!(1, client.route_events(&mut router)?);
+        assert!(matches!(errors.try_recv(), Ok(ServerRspMsg::ErrMsg { .. })));
+        // unrouted events stay with the client, in order
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::TickPrice { price, .. }) if price == 10.0
+        ));
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::TickPrice { price, .. }) if price == 10.5
+        ));
+        assert!(client.get_event()?.is_none());
+        Ok(())
+    }
+}
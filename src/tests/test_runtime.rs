@@ -0,0 +1,250 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::backtest::BacktestClient;
+    use crate::core::common::{BarData, BarSize, HistoricalDuration, TickType};
+    use crate::core::contract::Contract;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::execution::Execution;
+    use crate::core::order::{Order, OrderStatusKind};
+    use crate::core::runtime::{Context, Runner, Strategy, Tick};
+    use crate::core::time::parse_tws_time;
+
+    //------------------------------------------------------------------------------------------------
+    fn stock() -> Contract {
+        Contract {
+            symbol: "XYZ".to_string(),
+            sec_type: "STK".to_string(),
+            exchange: "SMART".to_string(),
+            currency: "USD".to_string(),
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: &str, open: f64, high: f64, low: f64, close: f64) -> BarData {
+        BarData {
+            date: parse_tws_time(date, Tz::UTC),
+            open,
+            high,
+            low,
+            close,
+            volume: 400,
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn backtest() -> BacktestClient {
+        let mut client = BacktestClient::new();
+        client
+            .add_bars(
+                &stock(),
+                &[
+                    bar("20240102  14:30:00", 100.0, 102.0, 99.0, 101.0),
+                    bar("20240102  14:31:00", 101.0, 101.5, 98.0, 98.5),
+                    bar("20240102  14:32:00", 98.5, 104.0, 98.5, 103.0),
+                ],
+            )
+            .unwrap();
+        client
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn time(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 2, hour, min, sec).unwrap()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Buys on the first trade below 99, asks for the bars on stop and records what it is given
+    #[derive(Default)]
+    struct Recorder {
+        started: bool,
+        stopped: bool,
+        bars: Vec<(i32, f64)>,
+        lasts: Vec<f64>,
+        statuses: Vec<(i32, String)>,
+        fills: Vec<(i32, f64)>,
+        timers: Vec<(i32, DateTime<Utc>)>,
+        bought: bool,
+        stop_after_timers: usize,
+        fail_on_timer: bool,
+    }
+
+    impl Strategy for Recorder {
+        fn on_start(&mut self, ctx: &mut Context<'_>) -> Result<(), IBKRApiLibError> {
+            self.started = true;
+            ctx.req_mkt_data(1, &stock(), "", false, false, vec![])
+        }
+
+        fn on_bar(
+            &mut self,
+            _ctx: &mut Context<'_>,
+            req_id: i32,
+            bar: &BarData,
+        ) -> Result<(), IBKRApiLibError> {
+            self.bars.push((req_id, bar.close));
+            Ok(())
+        }
+
+        fn on_tick(
+            &mut self,
+            ctx: &mut Context<'_>,
+            _req_id: i32,
+            tick: &Tick,
+        ) -> Result<(), IBKRApiLibError> {
+            if let Tick::Price {
+                tick_type: TickType::Last,
+                price,
+            } = tick
+            {
+                self.lasts.push(*price);
+                if self.timers.is_empty() && self.lasts.len() == 1 {
+                    ctx.set_interval(7, Duration::from_secs(30));
+                }
+                if *price < 99.0 && !self.bought {
+                    self.bought = true;
+                    let order = Order {
+                        action: "BUY".to_string(),
+                        order_type: "MKT".to_string(),
+                        total_quantity: 10.0,
+                        ..Default::default()
+                    };
+                    ctx.place_order(1, &stock(), &order)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn on_order_status(
+            &mut self,
+            _ctx: &mut Context<'_>,
+            order_id: i32,
+            status: &OrderStatusKind,
+            _filled: f64,
+            _remaining: f64,
+            _avg_fill_price: f64,
+        ) -> Result<(), IBKRApiLibError> {
+            self.statuses.push((order_id, status.to_string()));
+            Ok(())
+        }
+
+        fn on_fill(
+            &mut self,
+            _ctx: &mut Context<'_>,
+            _contract: &Contract,
+            execution: &Execution,
+        ) -> Result<(), IBKRApiLibError> {
+            self.fills.push((execution.order_id, execution.price));
+            Ok(())
+        }
+
+        fn on_timer(
+            &mut self,
+            ctx: &mut Context<'_>,
+            timer_id: i32,
+        ) -> Result<(), IBKRApiLibError> {
+            self.timers.push((timer_id, ctx.now()));
+            if self.fail_on_timer {
+                return Err(IBKRApiLibError::Config("timer failed".to_string()));
+            }
+            if self.timers.len() == self.stop_after_timers {
+                ctx.stop();
+            }
+            Ok(())
+        }
+
+        fn on_stop(&mut self, ctx: &mut Context<'_>) -> Result<(), IBKRApiLibError> {
+            self.stopped = true;
+            ctx.cancel_mkt_data(1)?;
+            // the bars up to the simulated time, delivered after on_stop()
+            ctx.req_historical_data(
+                2,
+                &stock(),
+                "",
+                HistoricalDuration::Days(1),
+                BarSize::_1Min,
+                "TRADES",
+                1,
+                1,
+                false,
+                vec![],
+            )
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_runtime_backtest_callbacks() {
+        let mut client = backtest();
+        let mut strategy = Recorder::default();
+        Runner::new()
+            .run_backtest(&mut client, &mut strategy)
+            .unwrap();
+
+        assert!(strategy.started && strategy.stopped);
+        assert_eq!(0, client.remaining());
+        assert_eq!(vec![(2, 101.0), (2, 98.5), (2, 103.0)], strategy.bars);
+        assert_eq!(12, strategy.lasts.len());
+        // the trades of a bar share its time and are played together, so the order placed on the
+        // low fills at the close
+        assert_eq!(vec![(1, 98.5)], strategy.fills);
+        assert!(strategy
+            .statuses
+            .iter()
+            .any(|(order_id, status)| *order_id == 1 && status == "Filled"));
+
+        // the interval fires between the bars, not after the last tick
+        assert_eq!(
+            vec![
+                (7, time(14, 30, 30)),
+                (7, time(14, 31, 0)),
+                (7, time(14, 31, 30))
+            ],
+            strategy.timers
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_runtime_stops() {
+        // stopped by the strategy
+        let mut client = backtest();
+        let mut strategy = Recorder {
+            stop_after_timers: 1,
+            ..Default::default()
+        };
+        Runner::new()
+            .run_backtest(&mut client, &mut strategy)
+            .unwrap();
+        assert!(strategy.stopped);
+        assert_eq!(1, strategy.timers.len());
+        assert_eq!(time(14, 30, 30), client.now());
+        assert_eq!(8, client.remaining());
+
+        // stopped by a handle before it starts
+        let mut client = backtest();
+        let mut strategy = Recorder::default();
+        let mut runner = Runner::new();
+        runner.stop_handle().stop();
+        runner.run_backtest(&mut client, &mut strategy).unwrap();
+        assert!(strategy.started && strategy.stopped);
+        assert!(strategy.lasts.is_empty());
+        assert_eq!(12, client.remaining());
+
+        // stopped by an error, which is returned after on_stop()
+        let mut client = backtest();
+        let mut strategy = Recorder {
+            fail_on_timer: true,
+            ..Default::default()
+        };
+        let result = Runner::new().run_backtest(&mut client, &mut strategy);
+        assert!(matches!(result, Err(IBKRApiLibError::Config(err)) if err == "timer failed"));
+        assert!(strategy.stopped);
+        assert_eq!(1, strategy.timers.len());
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::order::{OrderStatusKind, WhyHeld};
+    use crate::core::safety_net::SafetyNet;
+    use crate::examples::contract_samples;
+    use crate::tests::{connected_client, sent_requests};
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled: 0.0,
+            remaining: 100.0,
+            avg_fill_price: 0.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_safety_net_fires_once() -> Result<(), IBKRApiLibError> {
+        let net = SafetyNet::new(connected_client(), false);
+
+        // nothing is working yet, so there is nothing to cancel
+        assert!(!net.trigger()?);
+
+        net.observe(&order_status(5, "Submitted"));
+        net.observe(&order_status(6, "PreSubmitted"));
+        net.observe(&order_status(6, "Filled"));
+        assert_eq!(1, net.working_order_count());
+
+        assert!(net.trigger()?);
+        assert!(net.has_fired());
+        assert!(!net.trigger()?);
+
+        net.reset();
+        assert!(net.claim());
+        assert!(!net.trigger()?);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_panic_guard_cancels_and_flattens() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let net = SafetyNet::new(client.try_clone()?, true);
+        net.observe(&ServerRspMsg::NextValidId { order_id: 10 });
+        net.observe(&order_status(5, "Submitted"));
+        net.observe(&ServerRspMsg::PositionData {
+            account: "DU123456".to_string(),
+            contract: contract_samples::usstock(),
+            position: 100.0,
+            avg_cost: 10.0,
+        });
+
+        let guard = net.guard();
+        let result = panic::catch_unwind(AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("strategy failed");
+        }));
+        assert!(result.is_err());
+        assert!(net.has_fired());
+
+        let requests = sent_requests(&mut client);
+        assert_eq!(2, requests.len());
+        assert_eq!(
+            (ServerReqMsgDiscriminants::ReqGlobalCancel as i32).to_string(),
+            requests[0][0]
+        );
+        assert_eq!(
+            (ServerReqMsgDiscriminants::PlaceOrder as i32).to_string(),
+            requests[1][0]
+        );
+        assert_eq!("10", requests[1][1]);
+        assert!(requests[1].contains(&"SELL".to_string()));
+        assert!(requests[1].contains(&"MKT".to_string()));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_disarmed_panic_guard_does_nothing() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let net = SafetyNet::new(client.try_clone()?, false);
+        net.observe(&order_status(5, "Submitted"));
+
+        let mut guard = net.guard();
+        guard.disarm();
+        let result = panic::catch_unwind(AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("strategy failed");
+        }));
+        assert!(result.is_err());
+        assert!(!net.has_fired());
+        assert!(sent_requests(&mut client).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::scanner::{
+        ScanCode, ScanInstrument, ScanLocation, ScannerSubscriptionBuilder,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_scanner_subscription_builder() {
+        let subscription = ScannerSubscriptionBuilder::new(
+            ScanInstrument::UsStocks,
+            ScanLocation::StkUsMajor,
+            ScanCode::HotByVolume,
+        )
+        .number_of_rows(10)
+        .above_price(5.0)
+        .build();
+
+        assert_eq!("STK", subscription.instrument);
+        assert_eq!("STK.US.MAJOR", subscription.location_code);
+        assert_eq!("HOT_BY_VOLUME", subscription.scan_code);
+        assert_eq!(10, subscription.number_of_rows);
+        assert_eq!(5.0, subscription.above_price);
+
+        assert_eq!(ScanCode::HighVs52WHl, ScanCode::from_code("HIGH_VS_52W_HL"));
+        assert_eq!(
+            ScanCode::Custom("SCAN_NEW".to_string()),
+            ScanCode::from_code("SCAN_NEW")
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_scanner_params_codes() {
+        use crate::core::scanner::ScannerParams;
+
+        let xml = "<ScanParameterResponse><InstrumentList><Instrument><name>US Stocks</name>\
+                   <type>STK</type></Instrument><Instrument><name>Combos</name>\
+                   <type>NATCOMB</type></Instrument></InstrumentList><LocationTree>\
+                   <Location><locationCode>STK.US.MAJOR</locationCode></Location>\
+                   </LocationTree><ScanTypeList><ScanType><scanCode>TOP_PERC_GAIN</scanCode>\
+                   </ScanType><ScanType><scanCode>SCAN_NEW</scanCode></ScanType>\
+                   </ScanTypeList></ScanParameterResponse>";
+
+        let params = ScannerParams::parse(xml).unwrap();
+        assert_eq!(
+            vec![ScanInstrument::UsStocks, ScanInstrument::NativeCombos],
+            params
+                .instruments
+                .iter()
+                .map(|info| info.instrument.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ScanLocation::StkUsMajor, params.locations[0].location);
+        assert_eq!(
+            vec![
+                ScanCode::TopPercGain,
+                ScanCode::Custom("SCAN_NEW".to_string())
+            ],
+            params
+                .scan_types
+                .iter()
+                .map(|scan_type| scan_type.scan_code.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_scanner_params_parse() {
+        use crate::core::scanner::{ScanFilterKind, ScannerParams};
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ScanParameterResponse>
+  <InstrumentList varName="fullInstrumentList">
+    <Instrument>
+      <name>US Stocks</name>
+      <type>STK</type>
+      <filters>PRICE,VOLUME</filters>
+      <group>STK.GLOBAL</group>
+      <shortName>US</shortName>
+    </Instrument>
+  </InstrumentList>
+  <LocationTree>
+    <Location>
+      <displayName>US Stocks</displayName>
+      <locationCode>STK.US</locationCode>
+      <instruments>STK</instruments>
+      <routeExchange>SMART</routeExchange>
+      <LocationTree>
+        <Location>
+          <displayName>Listed/NASDAQ</displayName>
+          <locationCode>STK.US.MAJOR</locationCode>
+          <instruments>STK</instruments>
+          <delayedOnly>true</delayedOnly>
+        </Location>
+      </LocationTree>
+    </Location>
+  </LocationTree>
+  <ScanTypeList>
+    <ScanType>
+      <displayName>Top % Gainers</displayName>
+      <scanCode>TOP_PERC_GAIN</scanCode>
+      <instruments>STK,STOCK.EU</instruments>
+    </ScanType>
+  </ScanTypeList>
+  <FilterList varName="filterList">
+    <RangeFilter>
+      <id>PRICE</id>
+      <category>Price</category>
+      <AbstractField type="DoubleField">
+        <code>priceAbove</code>
+        <displayName>Price above</displayName>
+      </AbstractField>
+      <AbstractField type="DoubleField">
+        <code>priceBelow</code>
+        <displayName>Price below</displayName>
+      </AbstractField>
+    </RangeFilter>
+    <SimpleFilter>
+      <id>HALTED</id>
+      <AbstractField type="ComboField">
+        <code>haltedIs</code>
+      </AbstractField>
+    </SimpleFilter>
+  </FilterList>
+</ScanParameterResponse>"#;
+
+        let params = ScannerParams::parse(xml).unwrap();
+        assert_eq!(1, params.instruments.len());
+        assert_eq!(ScanInstrument::UsStocks, params.instruments[0].instrument);
+        assert_eq!(vec!["PRICE", "VOLUME"], params.instruments[0].filters);
+
+        let major = params.find_location(&ScanLocation::StkUsMajor).unwrap();
+        assert_eq!("Listed/NASDAQ", major.display_name);
+        assert!(major.delayed_only);
+        assert_eq!("SMART", params.locations[0].route_exchange);
+
+        assert_eq!(1, params.scan_types_for(&ScanInstrument::EuStocks).len());
+        assert_eq!(ScanCode::TopPercGain, params.scan_types[0].scan_code);
+
+        let filters = params.filters_for(&ScanInstrument::UsStocks);
+        assert_eq!(1, filters.len());
+        assert_eq!(ScanFilterKind::Range, filters[0].kind);
+        assert_eq!("priceBelow", filters[0].fields[1].code);
+        assert_eq!("ComboField", params.filters[1].fields[0].field_type);
+
+        assert!(ScannerParams::parse("<unclosed>").is_err());
+    }
+}
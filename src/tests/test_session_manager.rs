@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::session_manager::{SessionManager, SessionRole};
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_session_routing() {
+        let mut manager = SessionManager::new();
+        assert!(manager.client(&SessionRole::Orders).is_err());
+
+        manager
+            .add(
+                1,
+                &[SessionRole::General, SessionRole::Orders],
+                connected_client(),
+            )
+            .unwrap();
+        manager
+            .add(2, &[SessionRole::MarketData], connected_client())
+            .unwrap();
+        assert!(manager.add(2, &[], connected_client()).is_err());
+
+        assert_eq!(Some(1), manager.client_id_for(&SessionRole::Orders));
+        assert_eq!(Some(2), manager.client_id_for(&SessionRole::MarketData));
+        assert_eq!(Some(1), manager.client_id_for(&SessionRole::Account));
+        assert!(manager.is_connected());
+
+        manager
+            .add(3, &[SessionRole::Orders], connected_client())
+            .unwrap();
+        assert_eq!(Some(3), manager.client_id_for(&SessionRole::Orders));
+        assert_eq!(&[SessionRole::General], manager.roles(1));
+
+        manager.remove(2).unwrap();
+        assert_eq!(Some(1), manager.client_id_for(&SessionRole::MarketData));
+        assert_eq!(Some(3), manager.client_id_for(&SessionRole::Orders));
+        assert_eq!(vec![1, 3], manager.client_ids());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_session_events_are_tagged() {
+        let mut manager = SessionManager::new();
+        let first = connected_client();
+        let second = connected_client();
+        for time in 0..2 {
+            first
+                .evt_chan
+                .0
+                .send(ServerRspMsg::CurrentTime { time })
+                .unwrap();
+        }
+        second
+            .evt_chan
+            .0
+            .send(ServerRspMsg::CurrentTime { time: 10 })
+            .unwrap();
+        manager.add(1, &[SessionRole::General], first).unwrap();
+        manager.add(2, &[SessionRole::MarketData], second).unwrap();
+
+        let mut received = Vec::new();
+        while let Some(event) = manager.get_event().unwrap() {
+            if let ServerRspMsg::CurrentTime { time } = event.msg {
+                received.push((event.client_id, time));
+            }
+        }
+        assert_eq!(vec![(1, 0), (2, 10), (1, 1)], received);
+    }
+}
@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::client::EClient;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerReqMsgDiscriminants, ServerRspMsg};
+    use crate::core::req_ids::RequestKind;
+    use crate::core::shutdown::ShutdownOptions;
+    use crate::examples::contract_samples;
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn sent_messages(client: &mut EClient) -> Result<Vec<i32>, IBKRApiLibError> {
+        let mut buf = Vec::<u8>::new();
+        client.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let mut ids = Vec::new();
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let (_, msg, remaining) = read_msg(&rest)?;
+            ids.push(read_fields(&msg)[0].parse()?);
+            rest = remaining;
+        }
+        Ok(ids)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_shutdown() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let pnl_id = client.next_req_id(RequestKind::Pnl, "DU12345");
+        client.req_pnl(pnl_id, "DU12345", "")?;
+        client.req_mkt_data(7, &contract_samples::usstock(), "", false, false, vec![])?;
+        // one-shot requests are left alone
+        let details_id = client.next_req_id(RequestKind::ContractDetails, "AMZN");
+        client
+            .evt_chan
+            .0
+            .send(ServerRspMsg::CurrentTime { time: 1697808000 })
+            .unwrap();
+
+        let report = client.shutdown(Duration::from_millis(50))?;
+        assert!(report.is_clean());
+        assert_eq!(
+            vec![(pnl_id, RequestKind::Pnl), (7, RequestKind::MarketData)],
+            report.cancelled
+        );
+        assert_eq!(1, report.undelivered_events);
+        assert!(!client.is_connected());
+        assert!(client.correlations().context(details_id).is_some());
+        assert_eq!(
+            vec![
+                ServerReqMsgDiscriminants::ReqPnl as i32,
+                ServerReqMsgDiscriminants::ReqMktData as i32,
+                ServerReqMsgDiscriminants::CancelPnl as i32,
+                ServerReqMsgDiscriminants::CancelMktData as i32,
+            ],
+            sent_messages(&mut client)?
+        );
+        // events received before the shutdown can still be read
+        assert!(matches!(
+            client.get_event()?,
+            Some(ServerRspMsg::CurrentTime { .. })
+        ));
+        // no new requests
+        assert!(matches!(
+            client.req_current_time(),
+            Err(IBKRApiLibError::ShuttingDown)
+        ));
+        // a second shutdown has nothing to do
+        assert!(client.shutdown(Duration::ZERO)?.cancelled.is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_shutdown_keep_subscriptions() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        client.req_mkt_data(7, &contract_samples::usstock(), "", false, false, vec![])?;
+        let report = client.shutdown_with(ShutdownOptions::default().keep_subscriptions())?;
+        assert!(report.is_clean());
+        assert!(report.cancelled.is_empty());
+        assert_eq!(
+            vec![ServerReqMsgDiscriminants::ReqMktData as i32],
+            sent_messages(&mut client)?
+        );
+        assert!(ServerReqMsgDiscriminants::CancelMktData.is_cancel());
+        assert!(!ServerReqMsgDiscriminants::ReqMktData.is_cancel());
+        Ok(())
+    }
+}
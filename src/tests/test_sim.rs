@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+
+    use crate::core::backtest::sim::{
+        CommissionModel, LatencyModel, MatchingEngine, SlippageModel, Tick,
+    };
+    use crate::core::contract::Contract;
+    use crate::core::event_queue::{event_queue, EventQueueConfig, EventReceiver};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{Order, OrderStatusKind};
+
+    //------------------------------------------------------------------------------------------------
+    fn engine() -> (MatchingEngine, EventReceiver) {
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        (MatchingEngine::new(sender).with_account("DU1"), receiver)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn time(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_704_205_800_000 + millis).unwrap()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn contract() -> Contract {
+        Contract {
+            symbol: "XYZ".to_string(),
+            sec_type: "STK".to_string(),
+            currency: "USD".to_string(),
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order(action: &str, order_type: &str, quantity: f64) -> Order {
+        Order {
+            action: action.to_string(),
+            order_type: order_type.to_string(),
+            total_quantity: quantity,
+            ..Default::default()
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn drain(receiver: &EventReceiver) -> Vec<ServerRspMsg> {
+        let mut events = Vec::new();
+        while let Ok(msg) = receiver.try_recv() {
+            events.push(msg);
+        }
+        events
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// (order id, fill price, execution time) of the executions
+    fn fills(events: &[ServerRspMsg]) -> Vec<(i32, f64, DateTime<Utc>)> {
+        events
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::ExecutionData { execution, .. } => {
+                    Some((execution.order_id, execution.price, execution.time.unwrap()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn error_codes(events: &[ServerRspMsg]) -> Vec<(i32, i32)> {
+        events
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::ErrMsg {
+                    req_id, error_code, ..
+                } => Some((*req_id, *error_code)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(price: f64) -> Tick {
+        Tick::Trade { price, size: 100 }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sim_stops_and_limits() {
+        let (mut engine, receiver) = engine();
+        engine.update(0, &trade(50.0));
+
+        let mut stop_limit = order("BUY", "STP LMT", 10.0);
+        stop_limit.aux_price = 51.0;
+        stop_limit.lmt_price = 51.25;
+        engine.place(1, 0, &contract(), &stop_limit, time(0));
+        let mut limit = order("SELL", "LMT", 5.0);
+        limit.lmt_price = 52.0;
+        engine.place(2, 0, &contract(), &limit, time(0));
+        assert!(engine.is_working(1) && engine.is_working(2));
+        assert!(fills(&drain(&receiver)).is_empty());
+
+        // the ask reaches the stop but is above the limit, the bid does not reach the sell limit
+        engine.on_tick(
+            0,
+            &Tick::Ask {
+                price: 51.5,
+                size: 1,
+            },
+            time(1000),
+        );
+        engine.on_tick(
+            0,
+            &Tick::Bid {
+                price: 51.0,
+                size: 1,
+            },
+            time(1000),
+        );
+        assert!(fills(&drain(&receiver)).is_empty());
+
+        engine.on_tick(
+            0,
+            &Tick::Ask {
+                price: 51.2,
+                size: 1,
+            },
+            time(2000),
+        );
+        engine.on_tick(0, &trade(52.5), time(3000));
+        let events = drain(&receiver);
+        assert_eq!(
+            vec![(1, 51.2, time(2000)), (2, 52.5, time(3000))],
+            fills(&events)
+        );
+        assert!(!engine.is_working(1) && !engine.is_working(2));
+        assert_eq!(5.0, engine.position(0));
+
+        engine.req_positions();
+        let positions = drain(&receiver);
+        assert!(matches!(positions.as_slice(), [
+            ServerRspMsg::PositionData { account, position, avg_cost, .. },
+            ServerRspMsg::PositionEnd,
+        ] if account == "DU1" && *position == 5.0 && *avg_cost == 51.2));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sim_trailing_stop() {
+        let (mut engine, receiver) = engine();
+        engine.update(0, &trade(100.0));
+
+        let mut trail = order("SELL", "TRAIL", 10.0);
+        trail.aux_price = 1.0;
+        engine.place(1, 0, &contract(), &trail, time(0));
+        let mut trail_limit = order("SELL", "TRAIL LIMIT", 10.0);
+        trail_limit.trailing_percent = 5.0;
+        trail_limit.lmt_price_offset = 0.5;
+        engine.place(2, 0, &contract(), &trail_limit, time(0));
+
+        // the stops follow the price up to 101 and 96.9, and stay when it falls back
+        for (second, price) in [(1, 100.5), (2, 101.0), (3, 102.0), (4, 101.5)] {
+            engine.on_tick(0, &trade(price), time(second * 1000));
+        }
+        assert!(fills(&drain(&receiver)).is_empty());
+        engine.on_tick(0, &trade(100.9), time(5000));
+        assert_eq!(1, fills(&drain(&receiver)).len());
+
+        // the limit is 0.5 below the stop of 96.9
+        engine.on_tick(0, &trade(95.0), time(6000));
+        assert!(fills(&drain(&receiver)).is_empty());
+        engine.on_tick(0, &trade(96.5), time(7000));
+        let events = drain(&receiver);
+        assert_eq!(vec![(2, 96.5, time(7000))], fills(&events));
+        assert_eq!(-20.0, engine.position(0));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sim_slippage_and_commission() {
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut engine = MatchingEngine::new(sender)
+            .with_slippage(SlippageModel::Fixed { amount: 0.05 })
+            .with_commission(CommissionModel::Proportional {
+                fraction: 0.001,
+                minimum: 1.0,
+            });
+        engine.update(
+            0,
+            &Tick::Bid {
+                price: 9.98,
+                size: 1,
+            },
+        );
+        engine.update(
+            0,
+            &Tick::Ask {
+                price: 10.0,
+                size: 1,
+            },
+        );
+
+        engine.place(1, 0, &contract(), &order("BUY", "MKT", 200.0), time(0));
+        let mut limit = order("SELL", "LMT", 200.0);
+        limit.lmt_price = 10.5;
+        engine.place(2, 0, &contract(), &limit, time(0));
+        engine.on_tick(
+            0,
+            &Tick::Bid {
+                price: 10.52,
+                size: 1,
+            },
+            time(1000),
+        );
+
+        let events = drain(&receiver);
+        let fills: Vec<f64> = fills(&events).iter().map(|(_, price, _)| *price).collect();
+        // the sell would slip to 10.47 but fills at its limit
+        assert_eq!(vec![10.05, 10.5], fills);
+        let reports: Vec<(f64, f64)> = events
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerRspMsg::CommissionReport { commission_report } => {
+                    Some((commission_report.commission, commission_report.realized_pnl))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(2, reports.len());
+        assert!((reports[0].0 - 2.01).abs() < 1e-9);
+        assert_eq!(f64::MAX, reports[0].1);
+        assert!((reports[1].0 - 2.1).abs() < 1e-9);
+        assert!((reports[1].1 - (0.45 * 200.0 - 2.1)).abs() < 1e-9);
+
+        let per_share = CommissionModel::PerShare {
+            rate: 0.005,
+            minimum: 1.0,
+        };
+        assert_eq!(1.0, per_share.commission(100.0, 10.0, 1.0));
+        assert_eq!(5.0, per_share.commission(-1000.0, 10.0, 1.0));
+        assert_eq!(
+            99.95,
+            SlippageModel::Proportional { fraction: 0.0005 }.apply(100.0, false)
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sim_latency() {
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut engine =
+            MatchingEngine::new(sender).with_latency(LatencyModel::Fixed { millis: 500 });
+        assert_eq!(
+            Duration::milliseconds(500),
+            LatencyModel::Fixed { millis: 500 }.delay()
+        );
+        engine.update(
+            0,
+            &Tick::Ask {
+                price: 10.0,
+                size: 1,
+            },
+        );
+
+        engine.place(1, 0, &contract(), &order("BUY", "MKT", 1.0), time(0));
+        assert!(drain(&receiver).is_empty());
+        engine.on_tick(
+            0,
+            &Tick::Ask {
+                price: 10.5,
+                size: 1,
+            },
+            time(200),
+        );
+        assert!(!engine.is_working(1));
+        assert!(drain(&receiver).is_empty());
+
+        // the order arrives at 500 and fills at the ask of then, before the tick at 600
+        engine.on_tick(
+            0,
+            &Tick::Ask {
+                price: 11.0,
+                size: 1,
+            },
+            time(600),
+        );
+        let events = drain(&receiver);
+        assert!(matches!(
+            &events[0],
+            ServerRspMsg::OrderStatus {
+                order_id: 1,
+                status: OrderStatusKind::Submitted,
+                ..
+            }
+        ));
+        assert_eq!(vec![(1, 10.5, time(500))], fills(&events));
+
+        // the order fills before the cancel arrives
+        let mut limit = order("BUY", "LMT", 1.0);
+        limit.lmt_price = 10.8;
+        engine.place(2, 0, &contract(), &limit, time(1000));
+        engine.advance(time(1500));
+        engine.cancel(2, time(1600));
+        engine.on_tick(
+            0,
+            &Tick::Ask {
+                price: 10.75,
+                size: 1,
+            },
+            time(1700),
+        );
+        engine.advance(time(2100));
+        let events = drain(&receiver);
+        assert_eq!(1, fills(&events).len());
+        assert_eq!(vec![(2, 10147)], error_codes(&events));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_sim_rejects_orders() {
+        let (mut engine, receiver) = engine();
+        engine.place(1, 0, &contract(), &order("BUY", "LMT", 1.0), time(0));
+        engine.place(2, 0, &contract(), &order("BUY", "MKT", 0.0), time(0));
+        engine.place(3, 0, &contract(), &order("SELL", "TRAIL", 1.0), time(0));
+        engine.place(4, 0, &contract(), &order("SELL", "MOC", 1.0), time(0));
+        engine.place(5, 0, &contract(), &order("HOLD", "MKT", 1.0), time(0));
+        engine.cancel(6, time(0));
+        assert_eq!(
+            vec![(1, 321), (2, 434), (3, 321), (4, 387), (5, 321), (6, 10147)],
+            error_codes(&drain(&receiver))
+        );
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::core::common::{TickAttribLast, TickByTickData};
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{Order, OrderStatusKind, WhyHeld};
+    use crate::core::slicer::{OrderSlicer, SliceSchedule, SlicerState};
+
+    //------------------------------------------------------------------------------------------------
+    fn parent(quantity: f64) -> Order {
+        let mut order = Order::default();
+        order.action = "BUY".to_string();
+        order.order_type = "MKT".to_string();
+        order.total_quantity = quantity;
+        order
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str, filled: f64) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled,
+            remaining: 0.0,
+            avg_fill_price: 10.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 10.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(req_id: i32, size: i32) -> ServerRspMsg {
+        ServerRspMsg::TickByTick {
+            req_id,
+            time: 0,
+            tick: TickByTickData::Last {
+                all_last: false,
+                price: 10.0,
+                size,
+                tick_attrib_last: TickAttribLast::default(),
+                exchange: "".to_string(),
+                special_conditions: "".to_string(),
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fixed_interval_slicer() {
+        let interval = Duration::from_secs(60);
+        let mut slicer = OrderSlicer::new(
+            Contract::default(),
+            parent(100.0),
+            SliceSchedule::FixedInterval {
+                interval,
+                slices: 3,
+            },
+            10,
+        );
+        let start = Instant::now();
+
+        let (order_id, order) = slicer.due_child(start).unwrap();
+        assert_eq!(10, order_id);
+        assert_eq!(33.0, order.total_quantity);
+        assert_eq!("BUY", order.action);
+        assert!(slicer.due_child(start + Duration::from_secs(30)).is_none());
+
+        // the first child is cancelled after a partial fill, so the shortfall moves on
+        slicer.observe(&order_status(10, "Cancelled", 20.0));
+        slicer.pause();
+        assert!(slicer.due_child(start + interval).is_none());
+        slicer.resume();
+        let (order_id, order) = slicer.due_child(start + interval).unwrap();
+        assert_eq!(11, order_id);
+        assert_eq!(46.0, order.total_quantity);
+        slicer.observe(&order_status(11, "Filled", 46.0));
+
+        let (_, order) = slicer.due_child(start + interval * 2).unwrap();
+        assert_eq!(34.0, order.total_quantity);
+        assert_eq!(vec![12], slicer.working_children());
+        slicer.observe(&order_status(12, "Filled", 34.0));
+
+        assert_eq!(SlicerState::Completed, slicer.state());
+        assert_eq!(100.0, slicer.filled());
+        assert!(slicer.due_child(start + interval * 3).is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_participation_slicer() {
+        let interval = Duration::from_secs(10);
+        let mut slicer = OrderSlicer::new(
+            Contract::default(),
+            parent(50.0),
+            SliceSchedule::Participation {
+                rate: 0.1,
+                interval,
+                volume_req_id: 7,
+            },
+            1,
+        );
+        let start = Instant::now();
+        assert!(slicer.due_child(start).is_none());
+
+        slicer.observe(&trade(7, 200));
+        slicer.observe(&trade(8, 1000));
+        slicer.observe(&trade(7, 155));
+        assert_eq!(355.0, slicer.market_volume());
+
+        let (_, order) = slicer.due_child(start + interval).unwrap();
+        assert_eq!(35.0, order.total_quantity);
+
+        slicer.observe(&trade(7, 1000));
+        let (_, order) = slicer.due_child(start + interval * 2).unwrap();
+        assert_eq!(15.0, order.total_quantity);
+        assert_eq!(2, slicer.children().len());
+        assert_eq!(50.0, slicer.remaining());
+    }
+}
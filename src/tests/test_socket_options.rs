@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    use socket2::SockRef;
+
+    use crate::core::client::EClientConfig;
+    use crate::core::socket_options::{Keepalive, SocketOptions};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_apply() -> std::io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        let options = SocketOptions::default()
+            .nodelay()
+            .keepalive(
+                Keepalive::new(Duration::from_secs(30))
+                    .interval(Duration::from_secs(5))
+                    .retries(3),
+            )
+            .send_buffer_size(64 * 1024)
+            .recv_buffer_size(256 * 1024);
+        options.apply(&stream)?;
+
+        let socket = SockRef::from(&stream);
+        assert!(socket.nodelay()?);
+        assert!(socket.keepalive()?);
+        // the kernel may round the buffer sizes up
+        assert!(socket.send_buffer_size()? >= 64 * 1024);
+        assert!(socket.recv_buffer_size()? >= 256 * 1024);
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(Duration::from_secs(30), socket.keepalive_time()?);
+            assert_eq!(Duration::from_secs(5), socket.keepalive_interval()?);
+            assert_eq!(3, socket.keepalive_retries()?);
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_defaults_untouched() -> std::io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        let socket = SockRef::from(&stream);
+        let send_buffer_size = socket.send_buffer_size()?;
+        SocketOptions::default().apply(&stream)?;
+        assert!(!socket.nodelay()?);
+        assert!(!socket.keepalive()?);
+        assert_eq!(send_buffer_size, socket.send_buffer_size()?);
+
+        let config = EClientConfig::default().socket(SocketOptions::default().nodelay());
+        assert_eq!(Some(true), config.socket.nodelay);
+        Ok(())
+    }
+}
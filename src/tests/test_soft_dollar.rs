@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{read_fields, read_msg, ServerRspMsg};
+    use crate::core::order::{Order, SoftDollarTier};
+    use crate::core::soft_dollar::SoftDollarTierRequests;
+    use crate::examples::contract_samples;
+    use crate::tests::{block_on, connected_client};
+
+    //------------------------------------------------------------------------------------------------
+    fn tier() -> SoftDollarTier {
+        SoftDollarTier::new(
+            "Research".to_string(),
+            "R1".to_string(),
+            "Research tier".to_string(),
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_request_resolves_from_events() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let requests = SoftDollarTierRequests::new();
+        let future = requests.request(&mut client)?;
+        let req_id = future.req_id();
+
+        let observer = requests.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            assert!(!observer.observe(&ServerRspMsg::SoftDollarTiers {
+                req_id: req_id + 1,
+                tiers: vec![],
+            }));
+            assert!(observer.observe(&ServerRspMsg::SoftDollarTiers {
+                req_id,
+                tiers: vec![tier()],
+            }));
+        });
+        let tiers = block_on(future)?;
+        handle.join().unwrap();
+        assert_eq!("R1", tiers[0].val);
+
+        let failing = requests.request(&mut client)?;
+        assert!(requests.observe(&ServerRspMsg::ErrMsg {
+            req_id: failing.req_id(),
+            error_code: 321,
+            error_str: "not configured".to_string(),
+        }));
+        assert!(block_on(failing).is_err());
+
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, _) = read_msg(&buf)?;
+        assert_eq!(req_id.to_string(), read_fields(&request)[1]);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_tier_encoding() -> Result<(), IBKRApiLibError> {
+        let order = Order::default().with_soft_dollar_tier(tier());
+        assert_eq!("Research", order.soft_dollar_tier.name);
+
+        let mut client = connected_client();
+        client.place_order(1, &contract_samples::usstock(), &order)?;
+        let mut buf = Vec::<u8>::new();
+        client
+            .stream
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let (_, request, _) = read_msg(&buf)?;
+        let fields = read_fields(&request);
+        let position = fields.iter().position(|field| field == "Research").unwrap();
+        assert_eq!("R1", fields[position + 1]);
+
+        // servers without soft dollar tiers refuse the order and the request
+        let mut old_client = connected_client();
+        old_client.server_version = 105;
+        assert!(old_client
+            .place_order(1, &contract_samples::usstock(), &order)
+            .is_err());
+        assert!(SoftDollarTierRequests::new()
+            .request(&mut old_client)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::client::{ConnStatus, POISONED_MUTEX};
+    use crate::core::common::TickByTickType;
+    #[cfg(feature = "yaml")]
+    use crate::core::common::{BarSize, HistoricalDuration};
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::subscriptions::{
+        AccountSpec, ContractSpec, DataSpec, InstrumentSpec, SubscriptionManifest,
+        SubscriptionSupervisor,
+    };
+    use crate::tests::connected_client;
+
+    //------------------------------------------------------------------------------------------------
+    fn manifest(with_account: bool) -> SubscriptionManifest {
+        SubscriptionManifest {
+            instruments: vec![InstrumentSpec {
+                contract: ContractSpec {
+                    symbol: "AAPL".to_string(),
+                    sec_type: "STK".to_string(),
+                    exchange: "SMART".to_string(),
+                    currency: "USD".to_string(),
+                    primary_exchange: "".to_string(),
+                    con_id: 0,
+                },
+                data: vec![
+                    DataSpec::MarketData {
+                        generic_ticks: "".to_string(),
+                    },
+                    DataSpec::TickByTick {
+                        tick_type: TickByTickType::Last,
+                    },
+                ],
+            }],
+            accounts: if with_account {
+                vec![AccountSpec {
+                    account: "DU123456".to_string(),
+                    model_code: "".to_string(),
+                    ledger_and_nlv: false,
+                    pnl: true,
+                }]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_supervisor_reconciles() -> Result<(), IBKRApiLibError> {
+        let mut client = connected_client();
+        let mut supervisor = SubscriptionSupervisor::new(manifest(true), 9000);
+
+        assert_eq!(4, supervisor.realize(&mut client)?);
+        assert_eq!(0, supervisor.realize(&mut client)?);
+
+        supervisor.observe(&ServerRspMsg::ErrMsg {
+            req_id: 9000,
+            error_code: 354,
+            error_str: "Requested market data is not subscribed".to_string(),
+        });
+        assert_eq!(3, supervisor.active().len());
+        assert_eq!(1, supervisor.failed().len());
+        assert_eq!(0, supervisor.realize(&mut client)?);
+
+        assert_eq!(4, supervisor.on_reconnect(&mut client)?);
+        assert_eq!(0, supervisor.on_connectivity_restored(&mut client, true)?);
+        assert_eq!(4, supervisor.on_connectivity_restored(&mut client, false)?);
+        assert_eq!(0, supervisor.set_manifest(&mut client, manifest(false))?);
+        assert_eq!(2, supervisor.active().len());
+
+        // subscriptions whose cancel fails are kept for another try
+        *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::DISCONNECTED;
+        assert!(supervisor.cancel_all(&mut client).is_err());
+        assert_eq!(2, supervisor.active().len());
+        *client.conn_state.lock().expect(POISONED_MUTEX) = ConnStatus::CONNECTED;
+        supervisor.cancel_all(&mut client)?;
+        assert!(supervisor.active().is_empty());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_manifest_from_toml() -> Result<(), IBKRApiLibError> {
+        let parsed = SubscriptionManifest::from_toml_str(
+            r#"
+            [[instruments]]
+            symbol = "AAPL"
+            data = [
+                { type = "market_data" },
+                { type = "tick_by_tick", tick_type = "Last" },
+            ]
+
+            [[accounts]]
+            account = "DU123456"
+            pnl = true
+            "#,
+        )?;
+        assert_eq!(manifest(true), parsed);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_historical_bars_from_yaml() -> Result<(), IBKRApiLibError> {
+        let parsed = SubscriptionManifest::from_yaml_str(
+            r#"
+            instruments:
+              - symbol: AAPL
+                data:
+                  - type: historical_bars
+                    bar_size: 5 mins
+                    duration: 2 D
+            "#,
+        )?;
+        match &parsed.instruments[0].data[0] {
+            DataSpec::HistoricalBars {
+                bar_size, duration, ..
+            } => {
+                assert_eq!(BarSize::_5Mins, *bar_size);
+                assert_eq!(HistoricalDuration::Days(2), *duration);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::{TickAttrib, TickType};
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{OrderStatusKind, WhyHeld};
+    use crate::core::synthetic_stop::{
+        StopExecution, StopState, StopTrigger, SyntheticStop, SyntheticStopManager,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    fn tick(req_id: i32, tick_type: TickType, price: f64) -> ServerRspMsg {
+        ServerRspMsg::TickPrice {
+            req_id,
+            tick_type,
+            price,
+            tick_attr: TickAttrib::default(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled: 0.0,
+            remaining: 0.0,
+            avg_fill_price: 0.0,
+            perm_id: 0,
+            parent_id: 0,
+            last_fill_price: 0.0,
+            client_id: 0,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn manager() -> SyntheticStopManager {
+        let mut contract = Contract::default();
+        contract.symbol = "XYZ".to_string();
+        let mut manager = SyntheticStopManager::new();
+        manager
+            .add(SyntheticStop::new(1, contract.clone(), "SELL", 100.0, 95.0))
+            .unwrap();
+        manager
+            .add(
+                SyntheticStop::new(2, contract.clone(), "BUY", 50.0, 105.0)
+                    .with_trigger(StopTrigger::NearSide)
+                    .with_execution(StopExecution::Limit { offset: 0.5 }),
+            )
+            .unwrap();
+        assert!(manager
+            .add(SyntheticStop::new(2, contract.clone(), "BUY", 1.0, 1.0))
+            .is_err());
+        assert!(manager
+            .add(SyntheticStop::new(3, contract, "SSHORT", 1.0, 1.0))
+            .is_err());
+        manager.observe(&ServerRspMsg::NextValidId { order_id: 10 });
+        manager
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_synthetic_stop_trigger() {
+        let mut manager = manager();
+
+        manager.observe(&tick(1, TickType::Last, 96.0));
+        manager.observe(&tick(2, TickType::Bid, 106.0));
+        assert!(manager.due_orders().is_empty());
+
+        manager.observe(&tick(1, TickType::Last, 94.9));
+        manager.observe(&tick(2, TickType::Ask, 105.0));
+        let due = manager.due_orders();
+        assert_eq!(2, due.len());
+        let (order_id, contract, order) = &due[0];
+        assert_eq!(10, *order_id);
+        assert_eq!("XYZ", contract.symbol);
+        assert_eq!("MKT", order.order_type);
+        assert_eq!(100.0, order.total_quantity);
+        let (order_id, _, order) = &due[1];
+        assert_eq!(11, *order_id);
+        assert_eq!("LMT", order.order_type);
+        assert_eq!(105.5, order.lmt_price);
+
+        // a triggered stop never fires twice
+        manager.observe(&tick(1, TickType::Last, 90.0));
+        assert!(manager.due_orders().is_empty());
+
+        manager.observe(&order_status(10, "Filled"));
+        manager.observe(&order_status(11, "Cancelled"));
+        assert_eq!(
+            StopState::Filled { order_id: 10 },
+            manager.stop(1).unwrap().state
+        );
+        assert_eq!(
+            StopState::Failed { order_id: 11 },
+            manager.stop(2).unwrap().state
+        );
+        manager.remove_finished();
+        assert!(manager.stops().is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_synthetic_stop_connectivity_loss() {
+        let mut manager = manager();
+        manager.observe(&tick(1, TickType::Last, 96.0));
+        manager.observe(&ServerRspMsg::ErrMsg {
+            req_id: -1,
+            error_code: 1100,
+            error_str: "Connectivity between IB and TWS has been lost.".to_string(),
+        });
+        assert!(manager.set_stop_price(1, 97.0));
+        assert!(manager.due_orders().is_empty());
+
+        manager.observe(&tick(1, TickType::Last, 96.5));
+        assert_eq!(1, manager.due_orders().len());
+        assert!(!manager.cancel(1));
+        assert!(manager.cancel(2));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_synthetic_stop_persistence() {
+        let mut manager = manager();
+        manager.observe(&tick(1, TickType::Last, 90.0));
+        assert_eq!(1, manager.due_orders().len());
+
+        let text = manager.to_toml_string().unwrap();
+        let mut restored = SyntheticStopManager::from_toml_str(&text).unwrap();
+        assert_eq!(
+            StopState::Triggered { order_id: 10 },
+            restored.stop(1).unwrap().state
+        );
+        assert_eq!(StopState::Armed, restored.stop(2).unwrap().state);
+
+        restored.observe(&tick(1, TickType::Last, 80.0));
+        restored.observe(&tick(2, TickType::Ask, 110.0));
+        let due = restored.due_orders();
+        assert_eq!(1, due.len());
+        assert_eq!(11, due[0].0);
+    }
+}
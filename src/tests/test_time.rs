@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::common::ConnStatus;
+    use crate::core::decoder::Decoder;
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::Order;
+    use crate::core::time::{parse_tws_time, parse_zoned_time, zone_of, TwsTime};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tws_time() {
+        let eastern = Tz::US__Eastern
+            .with_ymd_and_hms(2024, 1, 5, 16, 0, 0)
+            .unwrap();
+        assert_eq!("20240105 16:00:00 US/Eastern", eastern.to_tws_time());
+        let utc = Utc.with_ymd_and_hms(2024, 7, 1, 8, 30, 5).unwrap();
+        assert_eq!("20240701 08:30:05 UTC", utc.to_tws_time());
+        assert_eq!("20240701 08:30:05", "20240701 08:30:05".to_tws_time());
+        assert_eq!("", String::new().to_tws_time());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_zoned_time() {
+        let eastern = parse_zoned_time("20240105 16:00:00 US/Eastern").unwrap();
+        assert_eq!(Tz::US__Eastern, eastern.timezone());
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 1, 5, 21, 0, 0).unwrap(),
+            eastern.with_timezone(&Utc)
+        );
+        let utc = parse_zoned_time("20240701-08:30:05").unwrap();
+        assert_eq!("20240701 08:30:05 UTC", utc.to_tws_time());
+        assert_eq!(eastern, parse_zoned_time(&eastern.to_tws_time()).unwrap());
+
+        assert!(parse_zoned_time("").is_err());
+        assert!(parse_zoned_time("20240105 16:00:00").is_err());
+        assert!(parse_zoned_time("20240105 16:00:00 Mars/Olympus").is_err());
+        assert!(parse_zoned_time("2024-01-05 16:00:00 UTC").is_err());
+        // skipped by the change to daylight saving time
+        assert!(parse_zoned_time("20240310 02:30:00 America/New_York").is_err());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_good_till_and_good_after() {
+        let till = Tz::Europe__London
+            .with_ymd_and_hms(2024, 3, 1, 17, 30, 0)
+            .unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 2, 28, 14, 30, 0).unwrap();
+        let order = Order::default()
+            .with_good_till(&till)
+            .with_good_after(&after);
+        assert_eq!("GTD", order.tif);
+        assert_eq!("20240301 17:30:00 Europe/London", order.good_till_date);
+        assert_eq!("20240228 14:30:00 UTC", order.good_after_time);
+        assert_eq!(Some(till), order.good_till());
+        assert_eq!(
+            Some(after),
+            order.good_after().map(|time| time.with_timezone(&Utc))
+        );
+        assert_eq!(None, Order::default().good_till());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_tws_time() {
+        let utc = |h, m, s| Some(Utc.with_ymd_and_hms(2024, 1, 5, h, m, s).unwrap());
+        let eastern = Tz::US__Eastern;
+        assert_eq!(utc(14, 30, 0), parse_tws_time("1704465000", eastern));
+        assert_eq!(utc(0, 0, 0), parse_tws_time("20240105", eastern));
+        assert_eq!(
+            utc(14, 30, 0),
+            parse_tws_time("20240105  09:30:00", eastern)
+        );
+        assert_eq!(utc(9, 30, 0), parse_tws_time("20240105 09:30:00", Tz::UTC));
+        assert_eq!(
+            utc(8, 30, 0),
+            parse_tws_time("20240105 09:30:00 Europe/Paris", eastern)
+        );
+        assert_eq!(
+            utc(14, 30, 0),
+            parse_tws_time("20240105 09:30:00 EST", Tz::UTC)
+        );
+        assert_eq!(utc(9, 30, 0), parse_tws_time("20240105-09:30:00", eastern));
+        assert_eq!(
+            utc(14, 30, 0),
+            parse_tws_time("2024-01-05 14:30:00.0", Tz::UTC)
+        );
+
+        assert_eq!(None, parse_tws_time("", Tz::UTC));
+        assert_eq!(None, parse_tws_time("soon", Tz::UTC));
+        assert_eq!(None, parse_tws_time("20241305", Tz::UTC));
+        assert_eq!(
+            None,
+            parse_tws_time("20240105 09:30:00 Mars/Olympus", Tz::UTC)
+        );
+
+        assert_eq!(
+            Some(Tz::America__New_York),
+            zone_of("20240105 09:30:00 EST")
+        );
+        assert_eq!(Some(Tz::Asia__Singapore), zone_of("20240105 09:30:00 SGT"));
+        assert_eq!(None, zone_of("20240105 09:30:00 XYZT"));
+        assert_eq!(None, zone_of("20240105 09:30:00"));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decoded_times() {
+        let (_, msg_queue) = channel::<String>();
+        let (sender, receiver) = event_queue(EventQueueConfig::default());
+        let mut decoder = Decoder::new(
+            msg_queue,
+            sender,
+            151,
+            Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+        );
+        decoder.time_zone = zone_of("20240105 09:00:00 US/Eastern");
+        let frame = |fields: &[&str]| format!("{}\0", fields.join("\0"));
+        decoder
+            .interpret(&frame(&["88", "7", "20240105 09:30:00"]))
+            .unwrap();
+        decoder
+            .interpret(&frame(&[
+                "86",
+                "8",
+                "2024-01-05 14:30:00.0",
+                "BRFG",
+                "BRFG$1",
+                "Shares rose",
+            ]))
+            .unwrap();
+
+        let open = Utc.with_ymd_and_hms(2024, 1, 5, 14, 30, 0).unwrap();
+        match receiver.try_recv().unwrap() {
+            ServerRspMsg::HeadTimestamp {
+                req_id: 7,
+                head_timestamp,
+                ..
+            } => assert_eq!(Some(open), head_timestamp),
+            other => panic!("unexpected {:?}", other),
+        }
+        // historical news times are UTC whatever the zone of the session
+        match receiver.try_recv().unwrap() {
+            ServerRspMsg::HistoricalNews { time, .. } => assert_eq!(Some(open), time),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // text that is not a time decodes as None, and the rest of the message still does
+        decoder.interpret(&frame(&["88", "9", "soon"])).unwrap();
+        match receiver.try_recv().unwrap() {
+            ServerRspMsg::HeadTimestamp {
+                head_timestamp,
+                #[cfg(feature = "raw-times")]
+                head_timestamp_raw,
+                ..
+            } => {
+                assert_eq!(None, head_timestamp);
+                #[cfg(feature = "raw-times")]
+                assert_eq!("soon", head_timestamp_raw);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let bar = ["1", "2", "0.5", "1.5", "10", "1.2", "3"];
+        let mut historical = vec!["17", "10", "s", "e", "2", "soon"];
+        historical.extend_from_slice(&bar);
+        historical.push("20240105 09:30:00");
+        historical.extend_from_slice(&bar);
+        decoder.interpret(&frame(&historical)).unwrap();
+        let dates: Vec<_> = iter::from_fn(|| receiver.try_recv().ok())
+            .map(|msg| match msg {
+                ServerRspMsg::HistoricalData { bar, .. } => Some(bar.date),
+                ServerRspMsg::HistoricalDataEnd { .. } => None,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(vec![Some(None), Some(Some(open)), None], dates);
+
+        // times without a zone name are not read in a zone that is unknown
+        decoder.time_zone = zone_of("20240105 09:00:00 XYZT");
+        decoder
+            .interpret(&frame(&["88", "11", "20240105 09:30:00"]))
+            .unwrap();
+        decoder
+            .interpret(&frame(&["88", "12", "20240105 09:30:00 US/Eastern"]))
+            .unwrap();
+        let heads: Vec<_> = iter::from_fn(|| receiver.try_recv().ok())
+            .map(|msg| match msg {
+                ServerRspMsg::HeadTimestamp { head_timestamp, .. } => head_timestamp,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(vec![None, Some(open)], heads);
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::core::client::EClient;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::tests::connect_test;
+
+    //------------------------------------------------------------------------------------------------
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// An event with the name and fields of the span it was emitted in
+    #[derive(Clone, Debug)]
+    struct Captured {
+        message: String,
+        span: String,
+        fields: HashMap<String, String>,
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Keeps every event in memory
+    #[derive(Clone, Default)]
+    struct TestSubscriber {
+        next_id: Arc<AtomicU64>,
+        spans: Arc<Mutex<HashMap<u64, (String, Fields)>>>,
+        entered: Arc<Mutex<Vec<u64>>>,
+        events: Arc<Mutex<Vec<Captured>>>,
+    }
+
+    impl TestSubscriber {
+        fn events(&self) -> Vec<Captured> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    impl Subscriber for TestSubscriber {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            self.spans
+                .lock()
+                .unwrap()
+                .insert(id, (span.metadata().name().to_string(), fields));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some((_, fields)) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+                values.record(fields);
+            }
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let (span, span_fields) = match self.entered.lock().unwrap().last() {
+                Some(id) => {
+                    let spans = self.spans.lock().unwrap();
+                    let (name, fields) = &spans[id];
+                    (name.clone(), fields.0.clone())
+                }
+                None => (String::new(), HashMap::new()),
+            };
+            self.events.lock().unwrap().push(Captured {
+                message: fields.0.remove("message").unwrap_or_default(),
+                span,
+                fields: span_fields,
+            });
+        }
+
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_request_span() -> Result<(), IBKRApiLibError> {
+        let subscriber = TestSubscriber::default();
+        let mut client = EClient::new();
+        connect_test(&mut client);
+        tracing::subscriber::with_default(subscriber.clone(), || client.cancel_pnl(12))?;
+
+        let events = subscriber.events();
+        let sent = events
+            .iter()
+            .find(|event| event.message == "sending request")
+            .expect("request logged");
+        assert_eq!("cancel_pnl", sent.span);
+        assert_eq!(Some(&"12".to_string()), sent.fields.get("req_id"));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_event_spans() -> Result<(), IBKRApiLibError> {
+        let subscriber = TestSubscriber::default();
+        let (_, msg_queue) = channel::<String>();
+        let client = EClient::new();
+        let mut decoder = Decoder::new(
+            msg_queue,
+            client.evt_chan.0.clone(),
+            151,
+            client.conn_state.clone(),
+        );
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            decoder.interpret(
+                "11\x00-1\x008\x00495512552\x00ES\x00FUT\x0020240315\x000\x00\x0050\x00CME\x00\
+                 USD\x00ESH4\x00ES\x000000f711.6593b1c0.01.01\x0020240105  10:31:02\x00DU111\x00\
+                 CME\x00SLD\x002\x004750.25\x001235\x000\x000\x002\x004750.25\x00scalp\x00\x00\
+                 \x00core\x002\x00",
+            )?;
+            decoder
+                .interpret("59\x001\x000000f711.6593b1c0.01.01\x002.5\x00USD\x000\x000\x00\x00")?;
+            decoder.interpret("49\x001\x001704465000\x00")
+        })?;
+
+        let received: Vec<Captured> = subscriber
+            .events()
+            .into_iter()
+            .filter(|event| event.message == "received")
+            .collect();
+        assert_eq!(3, received.len());
+        assert!(received.iter().all(|event| event.span == "tws_event"));
+        assert_eq!(Some(&"-1".to_string()), received[0].fields.get("req_id"));
+        assert_eq!(Some(&"8".to_string()), received[0].fields.get("order_id"));
+        // the commission report is tagged with the order of its execution
+        assert_eq!(Some(&"8".to_string()), received[1].fields.get("order_id"));
+        assert_eq!(None, received[2].fields.get("order_id"));
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+    use crate::core::contract::ContractDetails;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::trading_hours::{parse_time_zone, TradingHours};
+
+    //------------------------------------------------------------------------------------------------
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_dated_sessions() -> Result<(), IBKRApiLibError> {
+        let mut details = ContractDetails::default();
+        details.time_zone_id = "US/Eastern".to_string();
+        details.liquid_hours =
+            "20240102:0930-20240102:1600;20240103:0930-20240103:1600;20240104:CLOSED".to_string();
+        let hours = details.liquid_schedule()?;
+
+        assert_eq!(2, hours.sessions.len());
+        // 09:30 in New York is 14:30 UTC in winter
+        assert_eq!(utc(2024, 1, 2, 14, 30), hours.sessions[0].start);
+        assert!(hours.is_market_open(utc(2024, 1, 2, 15, 0)));
+        assert!(!hours.is_market_open(utc(2024, 1, 2, 21, 0)));
+        assert_eq!(
+            Some(utc(2024, 1, 3, 14, 30)),
+            hours.next_open(utc(2024, 1, 2, 15, 0))
+        );
+        assert_eq!(
+            Some(utc(2024, 1, 2, 21, 0)),
+            hours.next_close(utc(2024, 1, 2, 15, 0))
+        );
+        assert_eq!(None, hours.next_open(utc(2024, 1, 3, 15, 0)));
+        assert!(hours.is_closed_on(NaiveDate::from_ymd_opt(2024, 1, 4).unwrap()));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_overnight_and_legacy_sessions() -> Result<(), IBKRApiLibError> {
+        // a futures session from Sunday evening to Monday afternoon in Chicago, in summer
+        let globex = TradingHours::parse("20240707:1700-20240708:1600", "US/Central")?;
+        assert_eq!(utc(2024, 7, 7, 22, 0), globex.sessions[0].start);
+        assert_eq!(utc(2024, 7, 8, 21, 0), globex.sessions[0].end);
+
+        let legacy = TradingHours::parse("20240102:0400-0930,0930-2000;20240103:1800-0200", "EST")?;
+        assert_eq!(3, legacy.sessions.len());
+        assert_eq!(utc(2024, 1, 2, 14, 30), legacy.sessions[1].start);
+        // an end before the start is on the next day
+        assert_eq!(utc(2024, 1, 4, 7, 0), legacy.sessions[2].end);
+
+        let full_day = TradingHours::parse("20240102:0000-2400", "UTC")?;
+        assert_eq!(utc(2024, 1, 3, 0, 0), full_day.sessions[0].end);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_invalid_hours() {
+        assert!(TradingHours::parse("20240102:0930-1600", "Mars/Olympus").is_err());
+        assert!(matches!(
+            TradingHours::parse("20240102", "UTC"),
+            Err(IBKRApiLibError::Parse(_))
+        ));
+        assert!(TradingHours::parse("20240102:9930-1600", "UTC").is_err());
+        assert!(TradingHours::parse("", "UTC").unwrap().sessions.is_empty());
+        assert_eq!(
+            "America/New_York",
+            parse_time_zone("EST (Eastern Standard Time)")
+                .unwrap()
+                .name()
+        );
+    }
+}
@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::order::trailing::{validate_trailing_order, TrailingStop};
+    use crate::core::order::Order;
+    use crate::core::units::{Percent, Price, TrailingOffset};
+
+    //------------------------------------------------------------------------------------------------
+    fn amount(amount: f64) -> TrailingOffset {
+        TrailingOffset::Amount(Price::new(amount))
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn percent(percent: f64) -> TrailingOffset {
+        TrailingOffset::Percent(Percent::new(percent))
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_trailing_stop() {
+        let order = TrailingStop::sell(100.0, percent(2.5)).build().unwrap();
+        assert_eq!("SELL", order.action);
+        assert_eq!("TRAIL", order.order_type);
+        assert_eq!(100.0, order.total_quantity);
+        assert_eq!(2.5, order.trailing_percent);
+        assert_eq!(UNSET_DOUBLE, order.aux_price);
+        assert_eq!(UNSET_DOUBLE, order.trail_stop_price);
+
+        let order = TrailingStop::buy(10.0, amount(1.5))
+            .stop_price(101.5)
+            .build()
+            .unwrap();
+        assert_eq!(1.5, order.aux_price);
+        assert_eq!(UNSET_DOUBLE, order.trailing_percent);
+        assert_eq!(101.5, order.trail_stop_price);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_trailing_stop_limit() {
+        let order = TrailingStop::sell(100.0, amount(1.5))
+            .limit_offset(0.25)
+            .build()
+            .unwrap();
+        assert_eq!("TRAIL LIMIT", order.order_type);
+        assert_eq!(0.25, order.lmt_price_offset);
+        assert_eq!(UNSET_DOUBLE, order.lmt_price);
+
+        let order = TrailingStop::sell(100.0, percent(1.0))
+            .stop_price(98.5)
+            .limit_price(98.25)
+            .build()
+            .unwrap();
+        assert_eq!(98.25, order.lmt_price);
+        assert_eq!(98.5, order.trail_stop_price);
+        assert_eq!(UNSET_DOUBLE, order.lmt_price_offset);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_invalid_trailing_orders() {
+        let refused = |stop: TrailingStop| matches!(stop.build(), Err(IBKRApiLibError::Config(_)));
+        assert!(refused(TrailingStop::sell(1.0, percent(100.0))));
+        assert!(refused(TrailingStop::sell(1.0, percent(-1.0))));
+        assert!(refused(TrailingStop::sell(1.0, amount(0.0))));
+        assert!(refused(
+            TrailingStop::sell(1.0, amount(1.0))
+                .stop_price(98.5)
+                .limit_offset(0.25)
+                .limit_price(98.25)
+        ));
+        assert!(refused(
+            TrailingStop::sell(1.0, amount(1.0)).limit_price(98.25)
+        ));
+
+        let mut order = TrailingStop::sell(1.0, amount(1.0)).build().unwrap();
+        order.trailing_percent = 2.0;
+        assert!(validate_trailing_order(&order).is_err());
+        order.trailing_percent = UNSET_DOUBLE;
+        order.lmt_price_offset = 0.1;
+        assert!(validate_trailing_order(&order).is_err());
+
+        let limit = Order {
+            order_type: "LMT".to_string(),
+            lmt_price: 10.0,
+            ..Default::default()
+        };
+        assert!(validate_trailing_order(&limit).is_ok());
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::order::Order;
+    use crate::core::units::{Notional, Percent, Price, Size, TrailingOffset};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_unit_arithmetic() {
+        let notional = Price::new(25.5) * Size::new(200.0);
+        assert_eq!(Notional::new(5100.0), notional);
+        assert_eq!(Size::new(200.0), notional / Price::new(25.5));
+        assert_eq!(Price::new(25.5), notional / Size::new(200.0));
+
+        assert_eq!(
+            Price::new(1.0),
+            Percent::new(2.0).of_price(Price::new(50.0))
+        );
+        assert_eq!(Percent::new(1.5), Percent::from_fraction(0.015));
+        assert_eq!("1.5%", Percent::new(1.5).to_string());
+        assert_eq!(
+            Price::new(10.25),
+            Price::new(10.26).round_to(Price::new(0.05))
+        );
+        assert_eq!(Size::new(5.0), (Size::new(-3.0) - Size::new(2.0)).abs());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_trailing_offset() {
+        let mut order = Order::default();
+        TrailingOffset::Percent(Percent::new(2.0)).apply(&mut order);
+        assert_eq!(2.0, order.trailing_percent);
+        assert_eq!(UNSET_DOUBLE, order.aux_price);
+
+        TrailingOffset::Amount(Price::new(0.5)).apply(&mut order);
+        assert_eq!(0.5, order.aux_price);
+        assert_eq!(UNSET_DOUBLE, order.trailing_percent);
+
+        assert_eq!(
+            Price::new(4.0),
+            TrailingOffset::Percent(Percent::new(2.0)).distance_from(Price::new(200.0))
+        );
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use chrono::DateTime;
+
+    use crate::core::common::ConnStatus;
+    use crate::core::decoder::Decoder;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::event_queue::{event_queue, EventQueueConfig};
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::test_vectors::{
+        replay, replay_timed, Capture, CaptureRecorder, VERSION_MATRIX,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    fn captures() -> Result<Vec<(Capture, PathBuf)>, IBKRApiLibError> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir.join("captures"))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.sort();
+
+        let mut captures = Vec::new();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let capture = Capture::parse(&name, &fs::read_to_string(&path)?)?;
+            let vector = dir.join("vectors").join(format!("{}.snap", name));
+            captures.push((capture, vector));
+        }
+        Ok(captures)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Snapshots are written with the default features, which keep the raw times
+    #[cfg(feature = "raw-times")]
+    #[test]
+    fn test_vectors_match_snapshots() -> Result<(), IBKRApiLibError> {
+        let captures = captures()?;
+        assert!(!captures.is_empty());
+        for (capture, vector) in captures {
+            let expected = fs::read_to_string(&vector).unwrap_or_default();
+            assert!(
+                capture.snapshot()? == expected,
+                "{} does not match the decoder output, run `cargo run --bin ibtws_vectors` and \
+                 review the diff",
+                vector.display()
+            );
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_vectors_cover_version_matrix() -> Result<(), IBKRApiLibError> {
+        for (capture, _) in captures()? {
+            let versions = capture.server_versions();
+            for version in VERSION_MATRIX.iter() {
+                assert!(
+                    versions.contains(version),
+                    "{} has no section for server version {}",
+                    capture.name,
+                    version
+                );
+            }
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_capture_parse() -> Result<(), IBKRApiLibError> {
+        let capture = Capture::parse(
+            "inline",
+            "# comment\n\
+             @server_version 151\n\
+             9|1|42|\n\
+             \n\
+             dump 00000007 3900 3100 3432 00\n",
+        )?;
+        assert_eq!(vec![151], capture.server_versions());
+        assert_eq!(capture.sections[0].frames[0], capture.sections[0].frames[1]);
+        assert_eq!("9\u{0}1\u{0}42\u{0}", capture.sections[0].frames[0]);
+
+        assert!(Capture::parse("inline", "9|1|42|\n").is_err());
+        assert!(Capture::parse("inline", "@server_version x\n").is_err());
+
+        // the same frame decodes differently on either side of the market cap price version
+        let frame = vec![
+            "3\u{0}7\u{0}Filled\u{0}1\u{0}0\u{0}2\u{0}0\u{0}0\u{0}2\u{0}0\u{0}\u{0}3\u{0}"
+                .to_string(),
+        ];
+        match &replay(151, &frame)?[0] {
+            ServerRspMsg::OrderStatus { mkt_cap_price, .. } => assert_eq!(3.0, *mkt_cap_price),
+            other => panic!("unexpected {:?}", other),
+        }
+        match &replay(130, &frame)?[0] {
+            ServerRspMsg::OrderStatus { status, .. } => assert_eq!("1", status.to_string()),
+            other => panic!("unexpected {:?}", other),
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_capture_recorder() -> Result<(), IBKRApiLibError> {
+        let frames = [
+            (146, "9\u{0}1\u{0}42\u{0}"),
+            (
+                146,
+                "4\u{0}2\u{0}-1\u{0}2104\u{0}Market data farm connection is OK:usfarm\u{0}",
+            ),
+            // fields that cannot be written between pipes
+            (146, "4\u{0}2\u{0}7\u{0}200\u{0}a|b\nc\u{0}"),
+            (
+                151,
+                "3\u{0}7\u{0}Filled\u{0}1\u{0}0\u{0}2\u{0}0\u{0}0\u{0}2\u{0}0\u{0}\u{0}3\u{0}",
+            ),
+            (151, "4\u{0}2\u{0}7\u{0}200\u{0}Zürich\u{0}"),
+        ];
+        let recorder = CaptureRecorder::new("recorded");
+        for (server_version, frame) in frames.iter() {
+            recorder.record(*server_version, frame);
+        }
+        assert_eq!(5, recorder.len());
+
+        let text = recorder.capture().to_string();
+        assert!(text.contains("\ndump 000000"));
+        assert!(text.contains("|Zürich|"));
+        let capture = Capture::parse("recorded", &text)?;
+        assert_eq!(vec![146, 151], capture.server_versions());
+        let parsed: Vec<(i32, &str)> = capture
+            .sections
+            .iter()
+            .flat_map(|section| {
+                section
+                    .frames
+                    .iter()
+                    .map(move |frame| (section.server_version, frame.as_str()))
+            })
+            .collect();
+        assert_eq!(frames.to_vec(), parsed);
+        assert_eq!(recorder.capture().snapshot()?, capture.snapshot()?);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_capture_times() -> Result<(), IBKRApiLibError> {
+        let time =
+            |millis: i64| DateTime::from_timestamp_millis(1_704_205_800_000 + millis).unwrap();
+        let recorder = CaptureRecorder::new("timed");
+        recorder.record_at(151, "9\u{0}1\u{0}42\u{0}", time(0));
+        recorder.record_at(151, "49\u{0}1\u{0}1704205800\u{0}", time(0));
+        recorder.record_at(151, "9\u{0}1\u{0}43\u{0}", time(125));
+        let text = recorder.capture().to_string();
+        assert_eq!(2, text.matches("@time ").count());
+        assert!(text.contains("@time 2024-01-02T14:30:00.125Z\n9|1|43|"));
+
+        let capture = Capture::parse("timed", &text)?;
+        let section = &capture.sections[0];
+        assert_eq!(vec![(0, time(0)), (2, time(125))], section.times);
+        let timed = replay_timed(section)?;
+        assert_eq!(3, timed.len());
+        assert_eq!(Some(time(0)), timed[1].0);
+        assert!(matches!(
+            timed[2],
+            (Some(_), ServerRspMsg::NextValidId { order_id: 43 })
+        ));
+
+        assert!(Capture::parse("bad", "@server_version 151\n@time yesterday\n").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_decoder_records_frames() {
+        let (tx, msg_queue) = channel::<String>();
+        let (sender, _receiver) = event_queue(EventQueueConfig::default());
+        let mut decoder = Decoder::new(
+            msg_queue,
+            sender,
+            151,
+            Arc::new(Mutex::new(ConnStatus::CONNECTED)),
+        );
+        let recorder = CaptureRecorder::new("decoder");
+        decoder.recorder = Some(recorder.clone());
+
+        tx.send("9\u{0}1\u{0}42\u{0}".to_string()).unwrap();
+        tx.send("49\u{0}1\u{0}1700000000\u{0}".to_string()).unwrap();
+        drop(tx);
+        decoder.run().unwrap();
+
+        let capture = recorder.capture();
+        assert_eq!(vec![151], capture.server_versions());
+        assert_eq!(
+            vec!["9\u{0}1\u{0}42\u{0}", "49\u{0}1\u{0}1700000000\u{0}"],
+            capture.sections[0].frames
+        );
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::core::contract::Contract;
+    use crate::core::messages::ServerRspMsg;
+    use crate::core::order::{Order, OrderState, OrderStatusKind, WhyHeld};
+    use crate::core::webhooks::{
+        OrderEventTracker, RetryPolicy, WebhookConfig, WebhookEndpoint, WebhookEvent,
+        WebhookEventKind, WebhookNotifier, WebhookTransport,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    fn open_order(order_id: i32) -> ServerRspMsg {
+        let mut contract = Contract::default();
+        contract.symbol = "AAPL".to_string();
+        let mut order = Order::default();
+        order.action = "BUY".to_string();
+        order.total_quantity = 100.0;
+        order.account = "DU123".to_string();
+        ServerRspMsg::OpenOrder {
+            order_id,
+            contract,
+            order,
+            order_state: OrderState::default(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn order_status(order_id: i32, status: &str, filled: f64) -> ServerRspMsg {
+        ServerRspMsg::OrderStatus {
+            order_id,
+            status: OrderStatusKind::from(status),
+            filled,
+            remaining: 100.0 - filled,
+            avg_fill_price: 150.0,
+            perm_id: 7,
+            parent_id: 0,
+            last_fill_price: 150.0,
+            client_id: 1,
+            why_held: WhyHeld::default(),
+            mkt_cap_price: 0.0,
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn error(req_id: i32, error_code: i32) -> ServerRspMsg {
+        ServerRspMsg::ErrMsg {
+            req_id,
+            error_code,
+            error_str: "message".to_string(),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_event_tracker() {
+        let mut tracker = OrderEventTracker::new(&[2148]);
+        let kinds: Vec<Option<WebhookEventKind>> = vec![
+            open_order(1),
+            open_order(1),
+            order_status(1, "Submitted", 0.0),
+            order_status(1, "Submitted", 40.0),
+            order_status(1, "Submitted", 40.0),
+            order_status(1, "Filled", 100.0),
+            order_status(1, "Filled", 100.0),
+            open_order(2),
+            order_status(2, "Cancelled", 0.0),
+            error(3, 201),
+            order_status(3, "Inactive", 0.0),
+            error(-1, 2148),
+            error(-1, 2104),
+        ]
+        .iter()
+        .filter_map(|msg| tracker.observe(msg))
+        .map(|event| event.kind)
+        .collect();
+
+        assert_eq!(
+            vec![
+                Some(WebhookEventKind::OrderPlaced),
+                Some(WebhookEventKind::OrderPartiallyFilled),
+                Some(WebhookEventKind::OrderFilled),
+                Some(WebhookEventKind::OrderPlaced),
+                Some(WebhookEventKind::OrderCancelled),
+                Some(WebhookEventKind::OrderRejected),
+                Some(WebhookEventKind::AccountAlert),
+            ],
+            kinds
+        );
+
+        let mut tracker = OrderEventTracker::new(&[]);
+        tracker.observe(&open_order(5));
+        let filled = tracker.observe(&order_status(5, "Filled", 100.0)).unwrap();
+        assert_eq!("AAPL", filled.symbol);
+        assert_eq!("DU123", filled.account);
+        assert_eq!(Some(7), filled.perm_id);
+        assert_eq!(Some(150.0), filled.avg_fill_price);
+
+        let json = serde_json::to_value(&filled).unwrap();
+        assert_eq!("order_filled", json["kind"]);
+        assert_eq!("Order 5 filled: BUY 100 AAPL (100 @ 150)", json["text"]);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Fails the first `failures` posts, then records the rest
+    struct FlakyTransport {
+        failures: Arc<Mutex<u32>>,
+        posted: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl WebhookTransport for FlakyTransport {
+        fn post(&self, url: &str, _headers: &[(String, String)], body: &str) -> Result<(), String> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("503 Service Unavailable".to_string());
+            }
+            self.posted
+                .lock()
+                .unwrap()
+                .push((url.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_webhook_delivery() {
+        let posted = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport {
+            failures: Arc::new(Mutex::new(2)),
+            posted: posted.clone(),
+        };
+        let mut fills_only = WebhookEndpoint::new("http://fills");
+        fills_only.kinds = vec![WebhookEventKind::OrderFilled];
+        let config = WebhookConfig {
+            endpoints: vec![WebhookEndpoint::new("http://all"), fills_only],
+            retry: RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(2),
+            },
+            alert_codes: vec![],
+        };
+
+        let mut notifier = WebhookNotifier::with_transport(config, Box::new(transport));
+        notifier.observe(&open_order(1)).unwrap();
+        notifier.observe(&order_status(1, "Filled", 100.0)).unwrap();
+        notifier.observe(&error(-1, 2104)).unwrap();
+        let stats = notifier.shutdown();
+
+        assert_eq!(3, stats.delivered);
+        assert_eq!(2, stats.retried);
+        assert_eq!(0, stats.failed);
+
+        let posted = posted.lock().unwrap();
+        let urls: Vec<&str> = posted.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(vec!["http://all", "http://all", "http://fills"], urls);
+        let event: WebhookEvent = serde_json::from_str(&posted[2].1).unwrap();
+        assert_eq!(Some(WebhookEventKind::OrderFilled), event.kind);
+    }
+}